@@ -0,0 +1,126 @@
+//! Abbreviations expanded when a word boundary is typed in insert mode,
+//! such as `teh` → `the`. Language abbreviations take precedence over global ones.
+
+use std::collections::HashMap;
+
+use crate::{
+    buffer::instance::{Cursor, Language, Selection},
+    lsp::client::LSPClientHandle,
+    state::{EditorState, Mode},
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Abbreviations {
+    pub global: HashMap<String, String>,
+    pub languages: HashMap<Language, HashMap<String, String>>,
+}
+
+impl Abbreviations {
+    /// Expansion of the word in a buffer of the language
+    pub fn get(&self, language: Language, word: &str) -> Option<&String> {
+        self.languages
+            .get(&language)
+            .and_then(|abbreviations| abbreviations.get(word))
+            .or_else(|| self.global.get(word))
+    }
+}
+
+pub fn default_abbreviations() -> Abbreviations {
+    let pairs = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(word, expansion)| (word.to_string(), expansion.to_string()))
+            .collect()
+    };
+    Abbreviations {
+        global: pairs(&[
+            ("teh", "the"),
+            ("adn", "and"),
+            ("recieve", "receive"),
+            ("seperate", "separate"),
+        ]),
+        languages: HashMap::from([
+            (Language::Rust, pairs(&[("fnmain", "fn main() {\n}")])),
+            (
+                Language::Python,
+                pairs(&[("ifmain", "if __name__ == \"__main__\":\n    main()")]),
+            ),
+        ]),
+    }
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Start column of the word ending at the column of the line
+pub fn word_before(line: &str, column: usize) -> Option<usize> {
+    let before = line.get(..column)?;
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map(|(idx, _)| idx)?;
+    Some(start)
+}
+
+/// Replace the abbreviation before the cursor of the current buffer with its expansion
+pub fn expand_before_cursor(state: &mut EditorState, lsp_handle: &Option<&mut LSPClientHandle>) {
+    if !state.abbreviations_enabled || !matches!(state.mode, Mode::Insert) {
+        return;
+    }
+    let Some(buffer_id) = state.buffer_idx else {
+        return;
+    };
+    let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+    let cursor = instance.cursor;
+    let Some(start) = word_before(&buffer.lines[cursor.row], cursor.column) else {
+        return;
+    };
+    let word = &buffer.lines[cursor.row][start..cursor.column];
+    let Some(expansion) = state
+        .preferences
+        .abbreviations
+        .get(buffer.language, word)
+        .cloned()
+    else {
+        return;
+    };
+
+    let start = Cursor {
+        row: cursor.row,
+        column: start,
+    };
+    let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+    buffer.remove_text(
+        &Selection {
+            mark: start,
+            cursor,
+        },
+        lsp_handle,
+        true,
+    );
+    let cursor = buffer.insert_text(&expansion, &start, lsp_handle, true);
+    instance.cursor = cursor;
+    instance.selection.cursor = cursor;
+    instance.selection.mark = cursor;
+    instance.column_level = cursor.column;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_abbreviations, word_before};
+    use crate::buffer::instance::Language;
+
+    #[test]
+    fn abbreviation_lookup() {
+        assert_eq!(word_before("let teh", 7), Some(4));
+        assert_eq!(word_before("let teh ", 8), None);
+        assert_eq!(word_before("", 0), None);
+
+        let abbreviations = default_abbreviations();
+        assert_eq!(abbreviations.get(Language::Rust, "teh").unwrap(), "the");
+        assert!(abbreviations.get(Language::Python, "fnmain").is_none());
+    }
+}
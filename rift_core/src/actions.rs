@@ -1,13 +1,62 @@
 use copypasta::ClipboardProvider;
 
 use crate::{
-    buffer::instance::{Cursor, Selection},
-    io::file_io,
-    lsp::client::LSPClientHandle,
+    abbreviations, block,
+    buffer::{
+        extmarks::{Sign, VirtualText},
+        folding::{self, Fold},
+        instance::{BufferInstance, Cursor, Language, Selection},
+        line_buffer::{LineBuffer, LineEnding, LineOrder},
+    },
+    call_hierarchy::{CallDirection, CallTree},
+    code_actions::{self, CodeActionMenu},
+    comments, concurrent, dap, diagnostics,
+    dialog::{ConfirmChoice, ConfirmDialog, ConfirmKind, InputDialog, InputKind},
+    diff::SideBySideDiff,
+    editorconfig::IndentStyle,
+    export, file_changes, filter,
+    git::{
+        self,
+        conflict::{find_conflicts, next_conflict, resolve, ConflictSide},
+        status::GitStatus,
+    },
+    indent,
+    io::{
+        encoding::{self, Encoding},
+        encryption, file_io,
+        location::{self, Target},
+    },
+    keys,
+    kill_ring::LastPaste,
+    layout::SplitDirection,
+    linter, logs,
+    lsp::{
+        client::LSPClientHandle,
+        servers,
+        types::{protocol, CallHierarchyItem, CodeAction, Diagnostic, SignatureHelp},
+    },
+    markdown,
+    notifications::NotificationLevel,
+    pairs::{self, TypedChar},
+    recovery,
+    replace::{
+        self, FileReplace, ReplaceChoice, ReplaceFlags, ReplaceMatch, ReplaceSession,
+        WorkspaceReplace,
+    },
+    scratch,
+    search::{self, SearchKind},
+    session, snippets,
+    spellcheck::{self, SpellMenu},
     state::{EditorState, Mode},
+    stats,
+    tasks::{self, ErrorParser, TaskRun},
+    terminal::{Terminal, TerminalKey},
+    text_objects::{self, TextObject},
 };
 
-#[derive(Debug)]
+/// Editor actions (serializable so that frontends outside the process,
+/// such as a webview, can send them)
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Action {
     InsertTextAtCursor(String),
     InsertText(String, Cursor),
@@ -15,6 +64,8 @@ pub enum Action {
     InsertNewLineAtCursor,
     EnterInsertMode,
     QuitInsertMode,
+    /// Enter the custom mode with the name
+    EnterMode(String),
     AddNewLineBelowAndEnterInsertMode,
     InsertAfterSelection,
     AddIndent,
@@ -22,7 +73,30 @@ pub enum Action {
     CycleNextBuffer,
     CyclePreviousBuffer,
     CloseCurrentBuffer,
+    Quit,
+    /// Quit without asking about unsaved changes, which are discarded
+    ForceQuit,
+    Confirm(ConfirmChoice),
+    SetActiveBuffer(u32),
+    MoveBuffer(u32, usize),
     SaveCurrentBuffer,
+    OpenSaveAsPrompt,
+    CloseSaveAsPrompt,
+    /// Use the text entered in the input dialog
+    SubmitInputDialog,
+    CloseInputDialog,
+    SaveBufferAs(String),
+    SaveBufferElevated(u32),
+    /// Encoding the current buffer is converted to when saved
+    SetBufferEncoding(Encoding),
+    /// Line ending the current buffer is converted to when saved
+    ConvertLineEndings(LineEnding),
+    /// Replace the buffer with the file on disk
+    ReloadBuffer(u32),
+    /// Keep the local changes of a buffer whose file changed on disk
+    KeepBufferChanges(u32),
+    /// Save the buffer even though its file changed on disk
+    OverwriteBuffer(u32),
     SelectCurrentLine,
     SelectAndExtentCurrentLine,
     SelectTillEndOfWord,
@@ -30,7 +104,52 @@ pub enum Action {
     SelectTillStartOfWord,
     ExtendSelectTillStartOfWord,
     OpenFile,
+    /// Open a file by path, relative paths are resolved against the workspace
+    OpenPath(String),
+    /// Reopen the files which were open when the editor last quit in the workspace
+    RestoreSession,
+    /// Open the scratch buffer with the name, creating it if required
+    OpenScratch(String),
+    /// Open the `path[:line[:column]]` or url under the cursor
+    GotoFileUnderCursor,
+    ListScratchBuffers,
+    /// Check or uncheck the markdown checkboxes of the selected lines
+    ToggleCheckbox,
+    PromoteHeading,
+    DemoteHeading,
+    /// Renumber the markdown ordered list under the cursor
+    RenumberList,
+    /// Insert a doc comment skeleton for the function under the cursor
+    GenerateDocComment,
     FormatCurrentBuffer,
+    LintCurrentBuffer,
+    /// Export the current buffer as highlighted html next to the file
+    ExportHtml,
+    /// Export the selection of the current buffer as highlighted html
+    ExportSelectionHtml,
+    /// Open the buffer tailing the log file
+    ShowLogs,
+    /// Filter the log buffer by level and target, such as `warn lsp`
+    FilterLogs(String),
+    /// Select the next match of the query in the current buffer
+    SearchBuffer(String),
+    /// Collect the lines containing the query in the workspace into the quickfix list
+    SearchWorkspace(String),
+    /// Repeat the last buffer search forwards or backwards
+    SearchNext,
+    SearchPrevious,
+    /// Replace matches of a regex pattern in the current buffer.
+    /// The replacement can refer to capture groups as `$1` or `${name}`,
+    /// flags are `i` to ignore case and `c` to confirm each match.
+    ReplaceInBuffer(String, String, String),
+    /// Same as `ReplaceInBuffer` limited to the selection
+    ReplaceInSelection(String, String, String),
+    /// Answer the confirmation of the selected match of a replacement
+    ReplaceStep(ReplaceChoice),
+    /// Preview the replacement of a regex pattern in all files of the workspace
+    ReplaceInWorkspace(String, String, String),
+    /// Apply the replacements shown in the workspace replace preview
+    ApplyWorkspaceReplace,
     MoveCursorDown,
     MoveCursorUp,
     MoveCursorLeft,
@@ -39,15 +158,83 @@ pub enum Action {
     ExtendCursorUp,
     ExtendCursorLeft,
     ExtendCursorRight,
+    /// Extend the selection as a block of screen columns, starting the block mode
+    ExtendBlockDown,
+    ExtendBlockUp,
+    ExtendBlockLeft,
+    ExtendBlockRight,
+    /// Switch the selection between a block and a range of text
+    ToggleBlockSelection,
     MoveCursorLineStart,
     MoveCursorLineEnd,
     ExtendCursorLineStart,
     ExtendCursorLineEnd,
     GoToBufferStart,
     GoToBufferEnd,
+    GoToLine(usize),
+    /// Remember the cursor position of the buffer under a name
+    SetMark(char),
+    GoToMark(char),
+    /// Collapse the innermost foldable range at the cursor or open the fold at the cursor
+    FoldToggle,
+    FoldAll,
+    UnfoldAll,
+    /// Folding ranges received from the language server
+    SetFoldingRanges(Vec<Fold>),
+    SelectFunction,
+    SelectBlock,
+    SelectArgument,
+    ExpandSelectionToParentNode,
+    /// Restore the selection from before the last expansion to a parent node
+    ShrinkSelection,
+    /// Add the pair of the character around the selection
+    SurroundSelection(char),
+    /// Replace the pair of the first character around the cursor with the pair of the second
+    ChangeSurround(char, char),
+    DeleteSurround(char),
+    /// Insert text in snippet syntax at the position
+    InsertSnippet(String, Cursor),
+    /// Insert the snippet with the prefix at the cursor
+    ExpandSnippet(String),
+    NextTabStop,
+    PreviousTabStop,
+    ScrollUp(usize),
+    ScrollDown(usize),
     Unselect,
     LSPHover,
     LSPCompletion,
+    /// Ask the language server for the signature of the call around the cursor
+    LSPSignatureHelp,
+    /// Signatures received from the language server, None closes the signature help
+    SetSignatureHelp(Option<SignatureHelp>),
+    CloseSignatureHelp,
+    /// Diagnostics received for a `textDocument/diagnostic` request, None if unchanged
+    SetPulledDiagnostics(usize, Option<Vec<Diagnostic>>),
+    /// Restart the language servers of the current buffer's language
+    LSPRestart,
+    /// Show the process, capabilities and latency of the language servers
+    LSPStatus,
+    /// Ask the language server for code actions for the selection and its diagnostics
+    LSPCodeActions,
+    /// Code actions received from the language server
+    ShowCodeActions(Vec<CodeAction>),
+    ApplyCodeAction(usize),
+    CloseCodeActionMenu,
+    /// Ask the language server for the call hierarchy of the symbol under the cursor
+    CallHierarchy,
+    /// Items received for the symbol, the first one becomes the root of the tree
+    SetCallHierarchy(Vec<CallHierarchyItem>),
+    /// Calls received for the expanded node
+    SetCallHierarchyCalls(Vec<CallHierarchyItem>),
+    CallHierarchyNext,
+    CallHierarchyPrevious,
+    CallHierarchyExpand,
+    CallHierarchyCollapse,
+    /// Switch between incoming and outgoing calls
+    CallHierarchyToggleDirection,
+    /// Go to the selected symbol
+    CallHierarchyOpen,
+    CloseCallHierarchy,
     DeletePreviousCharacter,
     DeleteNextCharacter,
     DeleteSelection,
@@ -60,6 +247,130 @@ pub enum Action {
     CutToClipboard,
     PasteFromRegister,
     PasteFromClipboard,
+    /// Paste the kill ring entry at the index, most recent first
+    PasteFromHistory(usize),
+    /// Replace the text of the last paste with the next older kill ring entry
+    CyclePaste,
+    /// Paste the lines of the most recent kill ring entry below each other
+    /// at the screen column of the cursor
+    PasteBlock,
+    OpenTerminal,
+    CloseTerminal,
+    QuitTerminalMode,
+    TerminalInput(String),
+    TerminalKey(TerminalKey),
+    TerminalScrollUp,
+    TerminalScrollDown,
+    ToggleFileTree,
+    ToggleMarkdownPreview,
+    DiffWithSaved,
+    /// Move to the next change of the buffer compared to the git HEAD
+    NextHunk,
+    PrevHunk,
+    /// Add the change under the cursor to the git index
+    StageHunk,
+    /// Replace the change under the cursor with the lines at the git HEAD
+    RevertHunk,
+    /// Compare the buffer with the file at the git HEAD side by side
+    DiffCurrentFile,
+    /// Show the commit which last changed the line under the cursor
+    GitBlameLine,
+    /// Show the commit of each line next to it
+    ToggleGitBlame,
+    /// Open the commit which last changed the line under the cursor in a new buffer
+    GitShowCommit,
+    /// Move the keyboard focus between the file tree and the editor
+    FocusFileTree,
+    FileTreeNext,
+    FileTreePrevious,
+    /// Expand or collapse the selected folder, or open the selected file
+    FileTreeOpen,
+    /// Collapse the selected folder or select the folder containing the selection
+    FileTreeCollapse,
+    FileTreeNewFile,
+    FileTreeNewFolder,
+    FileTreeRename,
+    FileTreeDelete,
+    /// Replace the merge conflict under the cursor with our side
+    ConflictKeepOurs,
+    /// Replace the merge conflict under the cursor with their side
+    ConflictKeepTheirs,
+    /// Replace the merge conflict under the cursor with our side followed by theirs
+    ConflictKeepBoth,
+    NextConflict,
+    /// List the staged, unstaged and untracked files of the repository
+    GitStatus,
+    GitStatusNext,
+    GitStatusPrevious,
+    /// Stage the selected file, or unstage it if it is staged
+    GitStatusToggleStage,
+    GitStatusOpen,
+    CloseGitStatus,
+    /// Open a buffer for the commit message, the staged changes are committed when it is saved
+    GitCommit,
+    ShowEditorStats,
+    /// Show line, word and character counts of the buffer and selection
+    BufferStats,
+    ToggleSpellcheck,
+    /// Show or hide placeholders for whitespace and control characters
+    ToggleWhitespace,
+    SpellSuggestions,
+    ApplySpellSuggestion(String),
+    AddWordToDictionary,
+    CloseSpellMenu,
+    RunTask(String),
+    StopTask,
+    /// Replace the selected lines with the output of the shell command given them as input
+    FilterSelectionThroughCommand(String),
+    SortSelectedLines,
+    /// Sort the selected lines by the number they start with
+    SortSelectedLinesNumeric,
+    SortSelectedLinesCaseInsensitive,
+    /// Remove the selected lines which repeat an earlier selected line
+    UniqueSelectedLines,
+    ReverseSelectedLines,
+    /// Place a sign of the namespace on a row of the current buffer
+    PlaceSign(String, usize, Sign),
+    /// Remove the signs of the namespace from the current buffer, only those on the row if given
+    RemoveSigns(String, Option<usize>),
+    /// Show virtual text of the namespace at a position of the current buffer
+    AddVirtualText(String, Cursor, VirtualText),
+    /// Remove the virtual text of the namespace from the current buffer
+    RemoveVirtualText(String),
+    NextError,
+    PrevError,
+    /// Move to the next diagnostic of the buffer
+    NextDiagnostic,
+    PreviousDiagnostic,
+    StartDebugging,
+    StopDebugging,
+    ToggleBreakpoint,
+    ToggleBreakpointAtRow(usize),
+    DebugContinue,
+    DebugStepOver,
+    DebugStepInto,
+    DebugStepOut,
+    ShowDebugPanel,
+    ShowNotificationHistory,
+    DismissNotifications,
+    CloseDiff,
+    /// Compare two files side by side
+    OpenDiff(String, String),
+    DiffNextHunk,
+    DiffPrevHunk,
+    /// Copy the selected hunk from the left side to the right side
+    DiffPushToRight,
+    DiffPushToLeft,
+    DiffSave,
+    SplitVertical,
+    SplitHorizontal,
+    FocusNextPane,
+    FocusPane(u32),
+    ClosePane,
+    ResizeSplit(u32, f32),
+    OpenCommandPalette,
+    RepeatLastChange,
+    ToggleAbbreviations,
 }
 
 pub fn perform_action(
@@ -67,13 +378,76 @@ pub fn perform_action(
     state: &mut EditorState,
     lsp_handle: &mut Option<&mut LSPClientHandle>,
 ) {
+    if let Some(actions) = state
+        .pending_keys
+        .take_count()
+        .and_then(|count| keys::with_count(&action, count))
+    {
+        for action in actions {
+            perform_action(action, state, lsp_handle);
+        }
+        return;
+    }
+    // A block selection collapsed to the cursor ends the block mode
+    if !matches!(
+        action,
+        Action::ExtendCursorDown
+            | Action::ExtendCursorUp
+            | Action::ExtendCursorLeft
+            | Action::ExtendCursorRight
+            | Action::ToggleBlockSelection
+    ) {
+        if let Some(instance) = state.buffer_idx.and_then(|id| state.instances.get_mut(&id)) {
+            if instance.selection.mark == instance.selection.cursor {
+                instance.block_selection = false;
+            }
+        }
+    }
+    record_edit(state, &action);
     match action {
         Action::InsertTextAtCursor(text) => {
+            if matches!(state.mode, Mode::Insert)
+                && edit_block(state, |buffer, selection| {
+                    block::insert_in_block(buffer, selection, &text, lsp_handle)
+                })
+            {
+                return;
+            }
+            if text.starts_with(|c: char| !abbreviations::is_word_char(c)) {
+                abbreviations::expand_before_cursor(state, lsp_handle);
+            }
+            snippets::remove_selected_placeholder(state, lsp_handle);
+            let mut chars = text.chars();
+            let typed = match (chars.next(), chars.next()) {
+                (Some(c), None) if matches!(state.mode, Mode::Insert) => {
+                    let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+                    pairs::typed_char(
+                        &state.preferences.auto_pairs,
+                        &buffer.lines[instance.cursor.row],
+                        instance.cursor.column,
+                        c,
+                    )
+                }
+                _ => TypedChar::Plain,
+            };
             let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
-            let cursor = buffer.insert_text(&text, &instance.cursor, lsp_handle, true);
+            let cursor = if typed == TypedChar::Skip {
+                let mut cursor = instance.cursor;
+                buffer.move_cursor_right(&mut cursor);
+                cursor
+            } else {
+                buffer.insert_text(&text, &instance.cursor, lsp_handle, true)
+            };
+            if let TypedChar::Pair(close) = typed {
+                buffer.insert_text(&close.to_string(), &cursor, lsp_handle, true);
+            }
             instance.cursor = cursor;
             instance.selection.cursor = instance.cursor;
             instance.selection.mark = instance.cursor;
+            // Opening a call or moving to the next argument updates the signature help
+            if text == "(" || text == "," || (text == ")" && state.signature_help.is_some()) {
+                request_signature_help(state, lsp_handle);
+            }
         }
         Action::InsertText(text, cursor) => {
             let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
@@ -93,17 +467,51 @@ pub fn perform_action(
         }
         Action::InsertNewLineAtCursor => {
             if matches!(state.mode, Mode::Insert) {
+                abbreviations::expand_before_cursor(state, lsp_handle);
+                if continue_markdown_list(state, lsp_handle) {
+                    return;
+                }
+                let language = state.get_buffer_by_id(state.buffer_idx.unwrap()).0.language;
+                let unit = state.indent_unit(state.buffer_idx.unwrap());
+                let comment_token = state
+                    .preferences
+                    .comment_token_for(language)
+                    .map(str::to_string);
                 let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
                 instance.cursor = instance.selection.cursor;
-                let indent_size = buffer.get_indentation_level(instance.cursor.row);
-                let cursor = buffer.insert_text("\n", &instance.cursor, lsp_handle, true);
+                if let Some(prefix) = comments::comment_continuation(
+                    buffer.language,
+                    comment_token.as_deref(),
+                    &buffer.lines[instance.cursor.row],
+                    instance.cursor.column,
+                ) {
+                    let cursor = buffer.insert_text(
+                        &format!("\n{}", prefix),
+                        &instance.cursor,
+                        lsp_handle,
+                        true,
+                    );
+                    instance.cursor = cursor;
+                    instance.selection.cursor = cursor;
+                    instance.selection.mark = cursor;
+                    instance.column_level = cursor.column;
+                    return;
+                }
+                let new_line =
+                    indent::new_line_indent(language, &buffer.lines, &instance.cursor, &unit);
+                let cursor = buffer.insert_text(
+                    &format!("\n{}", new_line.indent),
+                    &instance.cursor,
+                    lsp_handle,
+                    true,
+                );
+                if let Some(closing) = new_line.closing {
+                    buffer.insert_text(&format!("\n{}", closing), &cursor, lsp_handle, true);
+                }
                 instance.cursor = cursor;
-                instance.selection.cursor = instance.cursor;
-                instance.selection.mark = instance.cursor;
-                instance.selection =
-                    buffer.add_indentation(&instance.selection, indent_size, lsp_handle);
-                instance.cursor = instance.selection.cursor;
-                instance.column_level = instance.cursor.column;
+                instance.selection.cursor = cursor;
+                instance.selection.mark = cursor;
+                instance.column_level = cursor.column;
             }
         }
         Action::EnterInsertMode => {
@@ -113,41 +521,139 @@ pub fn perform_action(
         }
         Action::QuitInsertMode => {
             state.mode = Mode::Normal;
+            state.signature_help = None;
+        }
+        Action::EnterMode(name) => {
+            if state.preferences.modes.iter().any(|mode| mode.name == name) {
+                state.pending_keys.reset();
+                state.mode = Mode::Custom(name);
+            } else {
+                state.notify(NotificationLevel::Error, format!("Unknown mode: {}", name));
+            }
         }
         Action::AddNewLineBelowAndEnterInsertMode => {
             if matches!(state.mode, Mode::Normal) {
                 state.mode = Mode::Insert;
+                let unit = state.indent_unit(state.buffer_idx.unwrap());
                 let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
                 instance.cursor = instance.selection.cursor;
-                let indent_size = buffer.get_indentation_level(instance.cursor.row);
                 buffer.move_cursor_line_end(&mut instance.cursor);
-                let cursor = buffer.insert_text("\n", &instance.cursor, lsp_handle, true);
+                let new_line = indent::new_line_indent(
+                    buffer.language,
+                    &buffer.lines,
+                    &instance.cursor,
+                    &unit,
+                );
+                let cursor = buffer.insert_text(
+                    &format!("\n{}", new_line.indent),
+                    &instance.cursor,
+                    lsp_handle,
+                    true,
+                );
                 instance.cursor = cursor;
-                instance.selection.cursor = instance.cursor;
-                instance.selection.mark = instance.cursor;
-                instance.selection =
-                    buffer.add_indentation(&instance.selection, indent_size, lsp_handle);
-                instance.cursor = instance.selection.cursor;
-                instance.column_level = instance.cursor.column;
+                instance.selection.cursor = cursor;
+                instance.selection.mark = cursor;
+                instance.column_level = cursor.column;
             }
         }
         Action::InsertAfterSelection => {}
+        Action::ToggleCheckbox | Action::PromoteHeading | Action::DemoteHeading => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            if state.get_buffer_by_id(buffer_id).0.language != Language::Markdown {
+                state.notify(
+                    NotificationLevel::Info,
+                    "Only available in markdown buffers".to_string(),
+                );
+                return;
+            }
+            let transform = match action {
+                Action::ToggleCheckbox => markdown::toggle_checkbox,
+                Action::PromoteHeading => |line: &str| markdown::shift_heading(line, true),
+                _ => |line: &str| markdown::shift_heading(line, false),
+            };
+            let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            let (start, end) = instance.selection.in_order();
+            for row in start.row..=end.row {
+                let line = transform(&buffer.lines[row]);
+                buffer.replace_line(row, &line, lsp_handle);
+            }
+            for cursor in [&mut instance.selection.mark, &mut instance.selection.cursor] {
+                cursor.column = cursor.column.min(buffer.get_line_length(cursor.row));
+            }
+            instance.cursor = instance.selection.cursor;
+            instance.column_level = instance.cursor.column;
+        }
+        Action::GenerateDocComment => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            let language = buffer.language;
+            let Some(signature) = comments::function_signature(
+                language,
+                &buffer.get_content("\n".into()),
+                instance.cursor.row,
+            ) else {
+                state.notify(
+                    NotificationLevel::Info,
+                    "No function under the cursor".to_string(),
+                );
+                return;
+            };
+            let (doc, column) = comments::doc_skeleton(language, &signature);
+            buffer.insert_text(
+                &doc,
+                &Cursor {
+                    row: signature.row,
+                    column: 0,
+                },
+                lsp_handle,
+                true,
+            );
+            let cursor = Cursor {
+                row: signature.row,
+                column,
+            };
+            instance.cursor = cursor;
+            instance.selection.cursor = cursor;
+            instance.selection.mark = cursor;
+            instance.column_level = column;
+            state.mode = Mode::Insert;
+        }
+        Action::RenumberList => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            if buffer.language != Language::Markdown {
+                return;
+            }
+            for (row, line) in markdown::renumber_list(&buffer.lines, instance.cursor.row) {
+                buffer.replace_line(row, &line, lsp_handle);
+            }
+            for cursor in [&mut instance.selection.mark, &mut instance.selection.cursor] {
+                cursor.column = cursor.column.min(buffer.get_line_length(cursor.row));
+            }
+            instance.cursor = instance.selection.cursor;
+        }
         Action::AddIndent => {
             if matches!(state.mode, Mode::Normal) {
-                let tab_width = state.preferences.tab_width;
+                let indent = state.indent_unit(state.buffer_idx.unwrap());
                 let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
                 instance.selection =
-                    buffer.add_indentation(&instance.selection, tab_width, lsp_handle);
+                    buffer.add_indentation(&instance.selection, &indent, lsp_handle);
                 instance.cursor = instance.selection.cursor;
                 instance.column_level = instance.cursor.column;
             }
         }
         Action::RemoveIndent => {
             if matches!(state.mode, Mode::Normal) {
-                let tab_width = state.preferences.tab_width;
+                let indent = state.indent_unit(state.buffer_idx.unwrap());
                 let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
                 instance.selection =
-                    buffer.remove_indentation(&instance.selection, tab_width, lsp_handle);
+                    buffer.remove_indentation(&instance.selection, &indent, lsp_handle);
                 instance.cursor = instance.selection.cursor;
                 instance.column_level = instance.cursor.column;
             }
@@ -163,20 +669,243 @@ pub fn perform_action(
             }
         }
         Action::CloseCurrentBuffer => {
-            if state.buffer_idx.is_some() {
-                state.remove_buffer(state.buffer_idx.unwrap());
+            if let Some(id) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id(id);
+                if buffer.file_path.as_deref().is_some_and(scratch::is_scratch) {
+                    if !buffer.modified || state.save_buffer(id) {
+                        state.remove_buffer(id);
+                    }
+                } else if buffer.modified {
+                    state.confirm_dialog =
+                        Some(ConfirmDialog::close_buffer(id, &buffer.display_name()));
+                } else {
+                    state.remove_buffer(id);
+                }
+            }
+        }
+        Action::Quit => {
+            // Scratch buffers are saved first since they never ask
+            state.save_scratch_buffers();
+            let modified = state.modified_buffers();
+            if modified.is_empty() {
+                state.quit();
+            } else {
+                let names: Vec<String> = modified
+                    .iter()
+                    .map(|id| state.buffers[id].display_name())
+                    .collect();
+                state.confirm_dialog = Some(ConfirmDialog::quit(&names));
             }
         }
+        Action::ForceQuit => {
+            state.quit();
+        }
+        Action::Confirm(choice) => {
+            if let Some(dialog) = state.confirm_dialog.take() {
+                match (dialog.kind, choice) {
+                    (_, ConfirmChoice::Cancel) => {}
+                    (ConfirmKind::CloseBuffer(id), ConfirmChoice::Save) => {
+                        if state.save_buffer(id) {
+                            state.remove_buffer(id);
+                        }
+                    }
+                    (ConfirmKind::CloseBuffer(id), ConfirmChoice::Discard) => {
+                        state.remove_buffer(id);
+                    }
+                    (ConfirmKind::Quit, ConfirmChoice::Save) => {
+                        let mut saved = true;
+                        for id in state.modified_buffers() {
+                            saved &= state.save_buffer(id);
+                        }
+                        if saved {
+                            state.quit();
+                        }
+                    }
+                    (ConfirmKind::Quit, ConfirmChoice::Discard) => {
+                        state.quit();
+                    }
+                    (ConfirmKind::Recover, ConfirmChoice::Save) => {
+                        recovery::restore_journals(state);
+                    }
+                    (ConfirmKind::Recover, ConfirmChoice::Discard) => {
+                        recovery::discard_journals();
+                    }
+                }
+            }
+        }
+        Action::SetActiveBuffer(id) => {
+            if state.buffers.contains_key(&id) {
+                state.buffer_idx = Some(id);
+                state.update_view = true;
+            }
+        }
+        Action::MoveBuffer(id, position) => {
+            state.move_buffer(id, position);
+        }
         Action::SaveCurrentBuffer => {
             if matches!(state.mode, Mode::Normal) {
-                let line_ending = state.preferences.line_ending.clone();
-                let (buffer, _instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
-                buffer.modified = false;
-                file_io::override_file_content(
-                    &buffer.file_path.clone().unwrap(),
-                    buffer.get_content(line_ending.to_string()),
-                )
-                .unwrap();
+                let id = state.buffer_idx.unwrap();
+                let language = state.get_buffer_by_id(id).0.language;
+                if state.preferences.format_on_save_for(language) {
+                    state.format_buffer(id, lsp_handle);
+                }
+                let (buffer, instance) = state.get_buffer_by_id_mut(id);
+                if buffer.editorconfig.trim_trailing_whitespace == Some(true) {
+                    buffer.trim_trailing_whitespace(lsp_handle);
+                    for cursor in [&mut instance.selection.mark, &mut instance.selection.cursor] {
+                        cursor.column = cursor.column.min(buffer.get_line_length(cursor.row));
+                    }
+                    instance.cursor = instance.selection.cursor;
+                }
+                if state.save_buffer(id) {
+                    if state.preferences.lint_on_save {
+                        linter::lint_buffer(state, id);
+                    }
+                    // HEAD may have moved since the file was opened
+                    if let Some(path) = state.get_buffer_by_id(id).0.file_path.clone() {
+                        git::load_base(state, &path);
+                    }
+                    if state
+                        .git_commit
+                        .as_ref()
+                        .is_some_and(|(commit_id, _)| *commit_id == id)
+                    {
+                        commit_message(state, id);
+                    }
+                    if let Some(lsp_handle) = lsp_handle {
+                        let language = state.get_buffer_by_id(id).0.language;
+                        diagnostics::pull_diagnostics(state, language, lsp_handle, true);
+                    }
+                }
+            }
+        }
+        Action::OpenSaveAsPrompt => {
+            if let Some(id) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id(id);
+                state.save_as_input = Some(
+                    buffer
+                        .file_path
+                        .clone()
+                        .unwrap_or_else(|| format!("{}/", state.workspace_folder)),
+                );
+            }
+        }
+        Action::CloseSaveAsPrompt => {
+            state.save_as_input = None;
+        }
+        Action::SubmitInputDialog => {
+            if let Some(dialog) = state.input_dialog.take() {
+                match dialog.kind {
+                    InputKind::Decrypt(path, kind) => {
+                        state.open_encrypted(&path, kind, dialog.input.trim_end_matches('\n'))
+                    }
+                    kind => file_tree_operation(state, kind, dialog.input.trim()),
+                }
+            }
+        }
+        Action::CloseInputDialog => {
+            state.input_dialog = None;
+        }
+        Action::SaveBufferAs(path) => {
+            state.save_as_input = None;
+            if let Some(id) = state.buffer_idx {
+                let path = match std::path::absolute(&path) {
+                    Ok(path) => path.to_string_lossy().to_string(),
+                    Err(err) => {
+                        state.notify(
+                            NotificationLevel::Error,
+                            format!("Invalid path {}: {}", path, err),
+                        );
+                        return;
+                    }
+                };
+                let (buffer, _instance) = state.get_buffer_by_id_mut(id);
+                let previous = buffer.file_path.clone();
+                buffer.set_file_path(path.clone());
+                // Encrypted files saved under another encrypted name keep their secret
+                if let Some(file) = previous
+                    .as_ref()
+                    .and_then(|previous| state.encrypted_files.get(previous))
+                    .filter(|file| encryption::encryption_kind(&path) == Some(file.kind))
+                {
+                    state.encrypted_files.insert(path.clone(), file.clone());
+                }
+                if state.save_buffer(id) {
+                    state.notify(NotificationLevel::Info, format!("Saved {}", path));
+                } else {
+                    let (buffer, _instance) = state.get_buffer_by_id_mut(id);
+                    match previous {
+                        Some(previous) => buffer.set_file_path(previous),
+                        None => buffer.file_path = None,
+                    }
+                }
+            }
+        }
+        Action::SetBufferEncoding(encoding) => {
+            if let Some(buffer_id) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id_mut(buffer_id);
+                if buffer.encoding != encoding {
+                    buffer.encoding = encoding;
+                    buffer.modified = true;
+                }
+            }
+        }
+        Action::ConvertLineEndings(line_ending) => {
+            if let Some(buffer_id) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id_mut(buffer_id);
+                if buffer.line_ending != Some(line_ending) {
+                    buffer.line_ending = Some(line_ending);
+                    buffer.modified = true;
+                }
+            }
+        }
+        Action::ReloadBuffer(id) => {
+            if !state.buffers.contains_key(&id) {
+                return;
+            }
+            if let Err(err) = file_changes::reload_buffer(state, id, lsp_handle) {
+                state.notify(
+                    NotificationLevel::Error,
+                    format!("Failed to reload file: {}", err),
+                );
+            }
+        }
+        Action::KeepBufferChanges(id) => {
+            if state.buffers.contains_key(&id) {
+                file_changes::keep_buffer_changes(state, id);
+            }
+        }
+        Action::OverwriteBuffer(id) => {
+            if state.buffers.contains_key(&id) {
+                file_changes::keep_buffer_changes(state, id);
+                state.save_buffer(id);
+            }
+        }
+        Action::SaveBufferElevated(id) => {
+            if state.buffers.contains_key(&id) {
+                let content = state.save_content(id);
+                let (buffer, _instance) = state.get_buffer_by_id_mut(id);
+                if let Some(path) = buffer.file_path.clone() {
+                    if encryption::encryption_kind(&path).is_some() {
+                        state.notify(
+                            NotificationLevel::Error,
+                            format!("Encrypted files cannot be saved with sudo: {}", path),
+                        );
+                        return;
+                    }
+                    match encoding::encode(&content, buffer.encoding).and_then(|content| {
+                        file_io::override_file_content_elevated(&path, &content)
+                    }) {
+                        Ok(_) => {
+                            state.mark_saved(id, &path);
+                            state.notify(NotificationLevel::Info, format!("Saved {}", path));
+                        }
+                        Err(err) => state.notify(
+                            NotificationLevel::Error,
+                            format!("Failed to save {}: {:#}", path, err),
+                        ),
+                    }
+                }
             }
         }
         Action::SelectCurrentLine => {
@@ -225,31 +954,399 @@ pub fn perform_action(
                 state.modal_input = state.workspace_folder.clone();
             }
         }
+        Action::OpenPath(path) => {
+            let path = std::path::Path::new(&state.workspace_folder).join(path);
+            state.open_location(
+                &path.to_string_lossy(),
+                Cursor { row: 0, column: 0 },
+                lsp_handle,
+            );
+        }
+        Action::RestoreSession => {
+            if !session::restore_session(state) {
+                state.notify(
+                    NotificationLevel::Info,
+                    format!("No session saved for {}", state.workspace_folder),
+                );
+            }
+        }
+        Action::OpenScratch(name) => match scratch::create_scratch(&name) {
+            Ok(path) => state.open_location(&path, Cursor { row: 0, column: 0 }, lsp_handle),
+            Err(err) => state.notify(
+                NotificationLevel::Error,
+                format!("Failed to open scratch buffer: {:#}", err),
+            ),
+        },
+        Action::GotoFileUnderCursor => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+            let Some(token) =
+                location::token_at(&buffer.lines[instance.cursor.row], instance.cursor.column)
+            else {
+                return;
+            };
+            match location::parse_target(token) {
+                Target::Url(url) => {
+                    if let Err(err) = location::open_url(&url) {
+                        state.notify(NotificationLevel::Error, format!("{:#}", err));
+                    }
+                }
+                Target::File { path, line, column } => {
+                    let buffer_folder = buffer
+                        .file_path
+                        .as_ref()
+                        .and_then(|file_path| std::path::Path::new(file_path).parent());
+                    match location::resolve_path(&path, buffer_folder, &state.workspace_folder) {
+                        Some(path) => {
+                            let cursor = Cursor {
+                                row: line.unwrap_or(1).saturating_sub(1),
+                                column: column.unwrap_or(1).saturating_sub(1),
+                            };
+                            state.open_location(&path, cursor, lsp_handle);
+                        }
+                        None => state.notify(
+                            NotificationLevel::Warning,
+                            format!("File not found: {}", path),
+                        ),
+                    }
+                }
+            }
+        }
+        Action::ListScratchBuffers => {
+            let Some(dir) = scratch::scratch_dir() else {
+                return;
+            };
+            let dir = dir.to_string_lossy().to_string();
+            match file_io::get_directory_entries(&dir) {
+                Ok(entries) if !entries.is_empty() => {
+                    state.modal_open = true;
+                    state.modal_options = entries;
+                    state.modal_options_filtered = state.modal_options.clone();
+                    state.modal_selection_idx = None;
+                    state.modal_input = dir;
+                }
+                _ => state.notify(
+                    NotificationLevel::Info,
+                    "No scratch buffers, create one with Open Scratch".to_string(),
+                ),
+            }
+        }
         Action::FormatCurrentBuffer => {
             if matches!(state.mode, Mode::Normal) {
-                let (buffer, _instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
-                if let Some(lsp_handle) = lsp_handle {
-                    lsp_handle
-                        .send_request_sync(
-                            "textDocument/formatting".to_string(),
-                            Some(LSPClientHandle::formatting_request(
-                                buffer.file_path.clone().unwrap(),
-                                state.preferences.tab_width,
-                            )),
-                        )
-                        .unwrap();
+                let Some(buffer_idx) = state.buffer_idx else {
+                    return;
+                };
+                let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+                match (lsp_handle.as_mut(), buffer.file_path.clone()) {
+                    (Some(lsp_handle), Some(path)) => {
+                        lsp_handle
+                            .send_request_sync(
+                                "textDocument/formatting".to_string(),
+                                Some(LSPClientHandle::formatting_request(
+                                    path,
+                                    state.tab_width(buffer_idx),
+                                    buffer.editorconfig.indent_style != Some(IndentStyle::Tab),
+                                )),
+                            )
+                            .unwrap();
+                    }
+                    _ => state.format_buffer(buffer_idx, lsp_handle),
+                }
+            }
+        }
+        Action::LintCurrentBuffer => {
+            if let Some(id) = state.buffer_idx {
+                linter::lint_buffer(state, id);
+            }
+        }
+        Action::ExportHtml | Action::ExportSelectionHtml => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let selection_only = matches!(action, Action::ExportSelectionHtml);
+            let line_numbers = state.preferences.export_line_numbers;
+            let workspace_folder = state.workspace_folder.clone();
+            let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            let selection = selection_only.then_some(instance.selection);
+            let (title, path) = match &buffer.file_path {
+                Some(file_path) => (
+                    std::path::Path::new(file_path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    format!("{}.html", file_path),
+                ),
+                None => (
+                    "untitled".to_string(),
+                    std::path::Path::new(&workspace_folder)
+                        .join("export.html")
+                        .to_string_lossy()
+                        .to_string(),
+                ),
+            };
+            let spans = buffer.current_highlights().clone();
+            let (buffer, _) = state.get_buffer_by_id(buffer_id);
+            let html = export::to_html(
+                &title,
+                &buffer.lines,
+                &spans,
+                selection.as_ref(),
+                line_numbers,
+                &state.preferences.theme,
+            );
+            match std::fs::write(&path, html) {
+                Ok(()) => state.notify(NotificationLevel::Info, format!("Exported to {}", path)),
+                Err(err) => state.notify(
+                    NotificationLevel::Error,
+                    format!("Failed to export {}: {}", path, err),
+                ),
+            }
+        }
+        Action::ShowLogs => logs::show_logs(state),
+        Action::FilterLogs(filter) => {
+            if state.log_viewer.is_none() {
+                logs::show_logs(state);
+            }
+            logs::set_filter(state, logs::LogFilter::parse(&filter));
+        }
+        Action::SearchBuffer(query) => {
+            state.search_history.push(SearchKind::Buffer, &query);
+            search_buffer(state, &query, false);
+        }
+        Action::SearchNext | Action::SearchPrevious => {
+            let Some(query) = state
+                .search_history
+                .queries(SearchKind::Buffer)
+                .first()
+                .cloned()
+            else {
+                state.notify(NotificationLevel::Info, "No previous search".to_string());
+                return;
+            };
+            search_buffer(state, &query, matches!(action, Action::SearchPrevious));
+        }
+        Action::ReplaceInBuffer(pattern, replacement, flags) => {
+            start_replace(state, &pattern, replacement, &flags, false, lsp_handle);
+        }
+        Action::ReplaceInSelection(pattern, replacement, flags) => {
+            start_replace(state, &pattern, replacement, &flags, true, lsp_handle);
+        }
+        Action::ReplaceStep(choice) => {
+            let Some(session) = &state.replace_session else {
+                return;
+            };
+            let Some(found) = session.current.clone() else {
+                return;
+            };
+            let buffer_id = session.buffer_id;
+            match choice {
+                ReplaceChoice::Quit => finish_replace(state),
+                ReplaceChoice::Skip => {
+                    let content = state.buffers[&buffer_id].get_content("\n".to_string());
+                    let from = replace::next_start(&content, &found);
+                    select_replace_match(state, from);
+                }
+                ReplaceChoice::Replace | ReplaceChoice::All => {
+                    let content = state.buffers[&buffer_id].get_content("\n".to_string());
+                    replace_matches(
+                        state,
+                        buffer_id,
+                        &content,
+                        std::slice::from_ref(&found),
+                        lsp_handle,
+                    );
+                    let session = state.replace_session.as_mut().unwrap();
+                    session.replaced += 1;
+                    session.end = session.end + found.replacement.len() - (found.end - found.start);
+
+                    // Continue after the replacement
+                    let content = state.buffers[&buffer_id].get_content("\n".to_string());
+                    let replaced = ReplaceMatch {
+                        start: found.start + found.replacement.len(),
+                        end: found.start + found.replacement.len(),
+                        replacement: String::new(),
+                    };
+                    let from = if found.start == found.end {
+                        replace::next_start(&content, &replaced)
+                    } else {
+                        replaced.start
+                    };
+                    if choice == ReplaceChoice::All {
+                        let session = state.replace_session.as_mut().unwrap();
+                        let matches = replace::find_matches(
+                            &session.regex,
+                            &session.replacement,
+                            &content,
+                            from,
+                            session.end,
+                        );
+                        session.replaced += matches.len();
+                        replace_matches(state, buffer_id, &content, &matches, lsp_handle);
+                        finish_replace(state);
+                    } else {
+                        select_replace_match(state, from);
+                    }
+                }
+            }
+        }
+        Action::ReplaceInWorkspace(pattern, replacement, flags) => {
+            let regex = ReplaceFlags::parse(&flags)
+                .and_then(|flags| replace::build_regex(&pattern, &flags));
+            let regex = match regex {
+                Ok(regex) => regex,
+                Err(err) => {
+                    state.notify(NotificationLevel::Error, format!("{:#}", err));
+                    return;
+                }
+            };
+
+            let mut files = vec![];
+            for file in search::workspace_files(&state.workspace_folder) {
+                let path = file.to_string_lossy().to_string();
+                if encryption::encryption_kind(&path).is_some() {
+                    continue;
+                }
+                let Some(content) = workspace_file_content(state, &path) else {
+                    continue;
+                };
+                let matches =
+                    replace::find_matches(&regex, &replacement, &content, 0, content.len());
+                if !matches.is_empty() {
+                    files.push(FileReplace {
+                        path,
+                        content,
+                        matches,
+                    });
+                }
+            }
+            if files.is_empty() {
+                state.notify(
+                    NotificationLevel::Info,
+                    format!("No matches for {}", pattern),
+                );
+                return;
+            }
+
+            // The preview of a previous replacement is replaced
+            if let Some(previous) = state.workspace_replace.take() {
+                if state.buffers.contains_key(&previous.preview_buffer_id) {
+                    state.remove_buffer(previous.preview_buffer_id);
+                }
+            }
+            let mut buffer =
+                LineBuffer::new(replace::preview_text(&pattern, &replacement, &files), None);
+            buffer.modified = false;
+            let preview_buffer_id = state.add_buffer(buffer);
+            state.buffer_idx = Some(preview_buffer_id);
+            state.workspace_replace = Some(WorkspaceReplace {
+                preview_buffer_id,
+                files,
+            });
+            state.update_view = true;
+        }
+        Action::ApplyWorkspaceReplace => {
+            let Some(workspace_replace) = state.workspace_replace.take() else {
+                state.notify(
+                    NotificationLevel::Info,
+                    "No workspace replace to apply".to_string(),
+                );
+                return;
+            };
+            // Closing the preview cancels the replacement
+            if !state
+                .buffers
+                .contains_key(&workspace_replace.preview_buffer_id)
+            {
+                state.notify(
+                    NotificationLevel::Info,
+                    "Workspace replace was cancelled".to_string(),
+                );
+                return;
+            }
+
+            // Nothing is applied if any file changed since the preview
+            let changed = workspace_replace.files.iter().find(|file| {
+                workspace_file_content(state, &file.path).as_ref() != Some(&file.content)
+            });
+            if let Some(changed) = changed {
+                let message = format!(
+                    "{} changed since the preview, run the replace again",
+                    changed.path
+                );
+                state.notify(NotificationLevel::Error, message);
+                state.workspace_replace = Some(workspace_replace);
+                return;
+            }
+
+            let language = state
+                .buffer_idx
+                .map(|id| state.get_buffer_by_id(id).0.language);
+            let mut count = 0;
+            for file in &workspace_replace.files {
+                state.open_location(&file.path, Cursor { row: 0, column: 0 }, &*lsp_handle);
+                let Some(buffer_id) = state.buffer_idx else {
+                    continue;
+                };
+                let buffer = &state.buffers[&buffer_id];
+                if buffer.file_path.as_deref() != Some(file.path.as_str()) {
+                    continue;
                 }
+                let modified = buffer.modified;
+                // Language servers of other languages are not notified of the edits
+                let lsp_handle = if Some(buffer.language) == language {
+                    &*lsp_handle
+                } else {
+                    &None
+                };
+                replace_matches(state, buffer_id, &file.content, &file.matches, lsp_handle);
+                count += file.matches.len();
+                if !modified {
+                    state.save_buffer(buffer_id);
+                }
+            }
+            state.remove_buffer(workspace_replace.preview_buffer_id);
+            state.notify(
+                NotificationLevel::Info,
+                format!(
+                    "Replaced {} matches in {} files",
+                    count,
+                    workspace_replace.files.len()
+                ),
+            );
+        }
+        Action::SearchWorkspace(query) => {
+            if query.is_empty() {
+                return;
+            }
+            state.search_history.push(SearchKind::Workspace, &query);
+            state.quickfix.clear();
+            state.quickfix.entries = match search::search_workspace(&state.workspace_folder, &query)
+            {
+                Ok(entries) => entries,
+                Err(err) => {
+                    state.notify(NotificationLevel::Error, format!("{:#}", err));
+                    return;
+                }
+            };
+            if state.quickfix.entries.is_empty() {
+                state.notify(NotificationLevel::Info, format!("No matches for {}", query));
+                return;
             }
+            perform_action(Action::NextError, state, lsp_handle);
         }
         Action::MoveCursorDown => {
             let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
             buffer.move_cursor_down(&mut instance.cursor, instance.column_level);
+            skip_folds(buffer, instance, true);
             instance.selection.cursor = instance.cursor;
             instance.selection.mark = instance.cursor;
         }
         Action::MoveCursorUp => {
             let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
             buffer.move_cursor_up(&mut instance.cursor, instance.column_level);
+            skip_folds(buffer, instance, false);
             instance.selection.cursor = instance.cursor;
             instance.selection.mark = instance.cursor;
         }
@@ -270,11 +1367,13 @@ pub fn perform_action(
         Action::ExtendCursorDown => {
             let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
             buffer.move_cursor_down(&mut instance.cursor, instance.column_level);
+            skip_folds(buffer, instance, true);
             instance.selection.cursor = instance.cursor;
         }
         Action::ExtendCursorUp => {
             let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
             buffer.move_cursor_up(&mut instance.cursor, instance.column_level);
+            skip_folds(buffer, instance, false);
             instance.selection.cursor = instance.cursor;
         }
         Action::ExtendCursorLeft => {
@@ -333,6 +1432,168 @@ pub fn perform_action(
                 instance.column_level = instance.cursor.column;
             }
         }
+        Action::GoToLine(row) => {
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            instance.cursor = Cursor {
+                row: row.min(buffer.get_num_lines() - 1),
+                column: 0,
+            };
+            instance.selection.cursor = instance.cursor;
+            instance.selection.mark = instance.cursor;
+            instance.column_level = instance.cursor.column;
+        }
+        Action::SetMark(name) => {
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            buffer.marks.set(name, instance.cursor);
+        }
+        Action::GoToMark(name) => {
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            let Some(cursor) = buffer.marks.get(name) else {
+                state.notify(NotificationLevel::Info, format!("Mark not set: {}", name));
+                return;
+            };
+            instance.cursor = cursor;
+            instance.selection.cursor = instance.cursor;
+            instance.selection.mark = instance.cursor;
+            instance.column_level = instance.cursor.column;
+        }
+        Action::FoldToggle => {
+            let Some(ranges) = fold_ranges(state, &Action::FoldToggle, lsp_handle) else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            instance.folds.sync(&buffer.line_shifts);
+            if let Some(row) = instance.folds.toggle(&ranges, instance.cursor.row) {
+                move_to_fold(buffer, instance, row);
+            }
+        }
+        Action::FoldAll => {
+            let Some(ranges) = fold_ranges(state, &Action::FoldAll, lsp_handle) else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            instance.folds.sync(&buffer.line_shifts);
+            if let Some(row) = instance.folds.fold_all(&ranges, instance.cursor.row) {
+                move_to_fold(buffer, instance, row);
+            }
+        }
+        Action::UnfoldAll => {
+            let (_buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            instance.folds.collapsed.clear();
+        }
+        Action::SetFoldingRanges(ranges) => {
+            let (buffer, _instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            buffer.folding_ranges = Some((buffer.version, ranges));
+            if let Some(action) = state.pending_fold.take() {
+                perform_action(action, state, lsp_handle);
+            }
+        }
+        Action::SelectFunction => select_text_object(state, TextObject::Function),
+        Action::SelectBlock => select_text_object(state, TextObject::Block),
+        Action::SelectArgument => select_text_object(state, TextObject::Argument),
+        Action::ExpandSelectionToParentNode => {
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            let (start, end) = instance.selection.in_order();
+            let expanded = text_objects::expand_selection(
+                buffer.language,
+                &buffer.get_content("\n".to_string()),
+                buffer.byte_index_from_cursor(start, "\n"),
+                buffer.byte_index_from_cursor(end, "\n"),
+            );
+            let Some((start, end)) = expanded else {
+                state.notify(
+                    NotificationLevel::Info,
+                    "No syntax node to expand the selection to".to_string(),
+                );
+                return;
+            };
+            let selection = replace::selection_from_bytes(&buffer.lines, start, end);
+            instance.expanded_selections.push(instance.selection);
+            set_selection(instance, selection);
+        }
+        Action::ShrinkSelection => {
+            let (_buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            let (start, end) = instance.selection.in_order();
+            // The selection may have changed since it was expanded
+            let previous = instance.expanded_selections.pop().filter(|previous| {
+                let (previous_start, previous_end) = previous.in_order();
+                start <= previous_start && previous_end <= end
+            });
+            match previous {
+                Some(selection) => set_selection(instance, selection),
+                None => {
+                    instance.expanded_selections.clear();
+                    state.notify(
+                        NotificationLevel::Info,
+                        "No expanded selection to shrink".to_string(),
+                    );
+                }
+            }
+        }
+        Action::SurroundSelection(c) => {
+            let (open, close) = pairs::pair_of(&state.preferences.auto_pairs, c);
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            let (start, end) = instance.selection.in_order();
+            let (start, end) = (*start, *end);
+            buffer.insert_text(&close.to_string(), &end, lsp_handle, true);
+            buffer.insert_text(&open.to_string(), &start, lsp_handle, true);
+            let start = buffer.byte_index_from_cursor(&start, "\n");
+            let end =
+                buffer.byte_index_from_cursor(&end, "\n") + open.len_utf8() + close.len_utf8();
+            let selection = replace::selection_from_bytes(&buffer.lines, start, end);
+            set_selection(instance, selection);
+        }
+        Action::ChangeSurround(from, to) => {
+            replace_surround(state, from, Some(to), lsp_handle);
+        }
+        Action::DeleteSurround(c) => {
+            replace_surround(state, c, None, lsp_handle);
+        }
+        Action::InsertSnippet(body, position) => {
+            snippets::insert_snippet(state, &body, position, lsp_handle);
+        }
+        Action::ExpandSnippet(prefix) => {
+            let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+            let cursor = instance.cursor;
+            let Some(snippet) = state.snippets.get(buffer.language, &prefix) else {
+                state.notify(
+                    NotificationLevel::Info,
+                    format!("No snippet with the prefix {}", prefix),
+                );
+                return;
+            };
+            let body = snippet.body.clone();
+            snippets::insert_snippet(state, &body, cursor, lsp_handle);
+        }
+        Action::NextTabStop => {
+            snippets::jump_tab_stop(state, true);
+        }
+        Action::PreviousTabStop => {
+            snippets::jump_tab_stop(state, false);
+        }
+        Action::ScrollUp(lines) => {
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            let lines = lines.min(instance.scroll.row);
+            instance.scroll.row -= lines;
+            instance.scroll.column = 0;
+            instance.cursor.row = instance.cursor.row.saturating_sub(lines);
+            instance.cursor.column =
+                buffer.column_at_level(instance.cursor.row, instance.column_level);
+            instance.selection.cursor = instance.cursor;
+            instance.selection.mark = instance.cursor;
+        }
+        Action::ScrollDown(lines) => {
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            let last_line = buffer.get_num_lines() - 1;
+            let lines = lines.min(last_line.saturating_sub(instance.scroll.row));
+            instance.scroll.row += lines;
+            instance.scroll.column = 0;
+            instance.cursor.row = (instance.cursor.row + lines).min(last_line);
+            instance.cursor.column =
+                buffer.column_at_level(instance.cursor.row, instance.column_level);
+            instance.selection.cursor = instance.cursor;
+            instance.selection.mark = instance.cursor;
+        }
         Action::Unselect => {
             let (_buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
             instance.selection.cursor = instance.cursor;
@@ -355,6 +1616,15 @@ pub fn perform_action(
                 }
             }
         }
+        Action::LSPSignatureHelp => {
+            request_signature_help(state, lsp_handle);
+        }
+        Action::SetSignatureHelp(help) => {
+            state.signature_help = help;
+        }
+        Action::CloseSignatureHelp => {
+            state.signature_help = None;
+        }
         Action::LSPCompletion => {
             if matches!(state.mode, Mode::Normal) {
                 let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
@@ -371,12 +1641,228 @@ pub fn perform_action(
                 }
             }
         }
+        Action::SetPulledDiagnostics(id, diagnostics) => {
+            diagnostics::set_pulled_diagnostics(state, id, diagnostics);
+        }
+        Action::LSPRestart => {
+            if let Some(buffer_id) = state.buffer_idx {
+                state.lsp_restart = Some(state.get_buffer_by_id(buffer_id).0.language);
+            }
+        }
+        Action::LSPStatus => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let language = state.get_buffer_by_id(buffer_id).0.language;
+            match lsp_handle {
+                Some(lsp_handle) => {
+                    state.info_modal = Some(servers::status_report(language, lsp_handle));
+                }
+                None => state.notify(
+                    NotificationLevel::Info,
+                    format!("No language server running for {:?}", language),
+                ),
+            }
+        }
+        Action::LSPCodeActions => {
+            if matches!(state.mode, Mode::Normal) {
+                let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+                let Some(path) = buffer.file_path.clone() else {
+                    return;
+                };
+                let Some(lsp_handle) = lsp_handle else {
+                    state.notify(
+                        NotificationLevel::Info,
+                        "No language server for code actions".to_string(),
+                    );
+                    return;
+                };
+                let diagnostics = state
+                    .diagnostics
+                    .get(&path)
+                    .map(|diagnostics| {
+                        code_actions::diagnostics_in_range(
+                            &diagnostics.diagnostics,
+                            &instance.selection,
+                        )
+                        .into_iter()
+                        .map(protocol::diagnostic_to_lsp)
+                        .collect()
+                    })
+                    .unwrap_or_default();
+                lsp_handle
+                    .send_request_sync(
+                        "textDocument/codeAction".to_string(),
+                        Some(LSPClientHandle::code_action_request(
+                            path,
+                            instance.selection,
+                            diagnostics,
+                        )),
+                    )
+                    .unwrap();
+            }
+        }
+        Action::ShowCodeActions(actions) => {
+            if actions.is_empty() {
+                state.notify(
+                    NotificationLevel::Info,
+                    "No code actions available".to_string(),
+                );
+                return;
+            }
+            state.code_action_menu = Some(CodeActionMenu { actions, idx: 0 });
+        }
+        Action::ApplyCodeAction(idx) => {
+            let Some(action) = state
+                .code_action_menu
+                .take()
+                .and_then(|mut menu| (idx < menu.actions.len()).then(|| menu.actions.remove(idx)))
+            else {
+                return;
+            };
+            if let Err(err) = code_actions::apply_workspace_edit(state, &action.edit, &*lsp_handle)
+            {
+                state.notify(
+                    NotificationLevel::Error,
+                    format!("Failed to apply code action: {:#}", err),
+                );
+                return;
+            }
+            if let (Some(command), Some(lsp_handle)) = (&action.command, lsp_handle.as_mut()) {
+                lsp_handle
+                    .send_request_sync(
+                        "workspace/executeCommand".to_string(),
+                        Some(LSPClientHandle::execute_command_request(command)),
+                    )
+                    .unwrap();
+            }
+        }
+        Action::CloseCodeActionMenu => {
+            state.code_action_menu = None;
+        }
+        Action::CallHierarchy => {
+            let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+            let Some(path) = buffer.file_path.clone() else {
+                return;
+            };
+            let Some(lsp_handle) = lsp_handle else {
+                state.notify(
+                    NotificationLevel::Info,
+                    "No language server for call hierarchy".to_string(),
+                );
+                return;
+            };
+            lsp_handle
+                .send_request_sync(
+                    "textDocument/prepareCallHierarchy".to_string(),
+                    Some(LSPClientHandle::prepare_call_hierarchy_request(
+                        path,
+                        instance.cursor,
+                    )),
+                )
+                .unwrap();
+        }
+        Action::SetCallHierarchy(items) => {
+            let Some(root) = items.into_iter().next() else {
+                state.notify(
+                    NotificationLevel::Info,
+                    "No call hierarchy for the symbol".to_string(),
+                );
+                return;
+            };
+            let tree = CallTree::new(root.clone(), CallDirection::Incoming);
+            request_calls(tree.direction, &root, lsp_handle);
+            state.call_hierarchy = Some(tree);
+        }
+        Action::SetCallHierarchyCalls(calls) => {
+            if let Some(tree) = &mut state.call_hierarchy {
+                tree.set_calls(calls);
+            }
+        }
+        Action::CallHierarchyNext => {
+            if let Some(tree) = &mut state.call_hierarchy {
+                tree.select_next();
+            }
+        }
+        Action::CallHierarchyPrevious => {
+            if let Some(tree) = &mut state.call_hierarchy {
+                tree.select_previous();
+            }
+        }
+        Action::CallHierarchyExpand => {
+            if let Some(tree) = &mut state.call_hierarchy {
+                if let Some(item) = tree.expand() {
+                    request_calls(tree.direction, &item, lsp_handle);
+                }
+            }
+        }
+        Action::CallHierarchyCollapse => {
+            if let Some(tree) = &mut state.call_hierarchy {
+                tree.collapse();
+            }
+        }
+        Action::CallHierarchyToggleDirection => {
+            if let Some(tree) = &mut state.call_hierarchy {
+                if let Some(item) = tree.toggle_direction() {
+                    request_calls(tree.direction, &item, lsp_handle);
+                }
+            }
+        }
+        Action::CallHierarchyOpen => {
+            let Some(item) = state
+                .call_hierarchy
+                .take()
+                .and_then(|tree| tree.selected().map(|node| node.item.clone()))
+            else {
+                return;
+            };
+            state.open_location(&item.path, item.range.mark, &*lsp_handle);
+        }
+        Action::CloseCallHierarchy => {
+            state.call_hierarchy = None;
+        }
+        Action::ExtendBlockDown
+        | Action::ExtendBlockUp
+        | Action::ExtendBlockLeft
+        | Action::ExtendBlockRight => {
+            let extend = match action {
+                Action::ExtendBlockDown => Action::ExtendCursorDown,
+                Action::ExtendBlockUp => Action::ExtendCursorUp,
+                Action::ExtendBlockLeft => Action::ExtendCursorLeft,
+                _ => Action::ExtendCursorRight,
+            };
+            perform_action(extend, state, lsp_handle);
+            if let Some(instance) = state.buffer_idx.and_then(|id| state.instances.get_mut(&id)) {
+                instance.block_selection = true;
+            }
+        }
+        Action::ToggleBlockSelection => {
+            if let Some(instance) = state.buffer_idx.and_then(|id| state.instances.get_mut(&id)) {
+                instance.block_selection = !instance.block_selection;
+            }
+        }
         Action::DeletePreviousCharacter => {
+            if matches!(state.mode, Mode::Insert)
+                && edit_block(state, |buffer, selection| {
+                    block::delete_before_block(buffer, selection, lsp_handle)
+                })
+            {
+                return;
+            }
             if matches!(state.mode, Mode::Insert) {
+                let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+                let empty_pair = pairs::in_empty_pair(
+                    &state.preferences.auto_pairs,
+                    &buffer.lines[instance.cursor.row],
+                    instance.cursor.column,
+                );
                 let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
                 instance.selection.cursor = instance.cursor;
                 instance.selection.mark = instance.cursor;
                 buffer.move_cursor_left(&mut instance.selection.mark);
+                if empty_pair {
+                    buffer.move_cursor_right(&mut instance.selection.cursor);
+                }
 
                 let (_text, cursor) = buffer.remove_text(&instance.selection, lsp_handle, true);
                 instance.cursor = cursor;
@@ -400,13 +1886,25 @@ pub fn perform_action(
             }
         }
         Action::DeleteSelection => {
+            let mut deleted = String::new();
+            if matches!(state.mode, Mode::Normal)
+                && edit_block(state, |buffer, selection| {
+                    let (text, selection) = block::delete_block(buffer, selection, lsp_handle);
+                    deleted = text;
+                    selection
+                })
+            {
+                state.kill_ring.push(deleted);
+                return;
+            }
             if matches!(state.mode, Mode::Normal) {
                 let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
-                let (_text, cursor) = buffer.remove_text(&instance.selection, lsp_handle, true);
+                let (text, cursor) = buffer.remove_text(&instance.selection, lsp_handle, true);
                 instance.cursor = cursor;
                 instance.selection.cursor = instance.cursor;
                 instance.selection.mark = instance.cursor;
                 instance.column_level = instance.cursor.column;
+                state.kill_ring.push(text);
             }
         }
         Action::Undo => {
@@ -432,35 +1930,1657 @@ pub fn perform_action(
             }
         }
         Action::AddTab => {
+            if snippets::jump_tab_stop(state, true)
+                || snippets::expand_before_cursor(state, lsp_handle)
+            {
+                return;
+            }
             if matches!(state.mode, Mode::Insert) {
-                let tab_width = state.preferences.tab_width;
+                let indent = state.indent_unit(state.buffer_idx.unwrap());
                 let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
-                let cursor =
-                    buffer.insert_text(&" ".repeat(tab_width), &instance.cursor, lsp_handle, true);
+                let cursor = buffer.insert_text(&indent, &instance.cursor, lsp_handle, true);
                 instance.cursor = cursor;
                 instance.selection.cursor = instance.cursor;
                 instance.selection.mark = instance.cursor;
                 instance.column_level = instance.cursor.column;
             }
         }
-        Action::CopyToRegister => {}
+        // The register is the most recent entry of the kill ring
+        Action::CopyToRegister => {
+            let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+            let content = match instance.block() {
+                Some(selection) => block::block_text(buffer, &selection),
+                None => buffer.get_selection(&instance.selection),
+            };
+            state.kill_ring.push(content);
+        }
         Action::CopyToClipboard => {
             let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
-            state
-                .clipboard_ctx
-                .set_contents(buffer.get_selection(&instance.selection))
-                .unwrap();
+            let content = match instance.block() {
+                Some(selection) => block::block_text(buffer, &selection),
+                None => buffer.get_selection(&instance.selection),
+            };
+            state.kill_ring.push(content.clone());
+            if let Some(clipboard_ctx) = state.clipboard() {
+                clipboard_ctx.set_contents(content).unwrap();
+            }
+        }
+        Action::CutToRegister => {
+            perform_action(Action::DeleteSelection, state, lsp_handle);
+        }
+        Action::CutToClipboard => {
+            if matches!(state.mode, Mode::Normal) {
+                perform_action(Action::CopyToClipboard, state, lsp_handle);
+                perform_action(Action::DeleteSelection, state, lsp_handle);
+            }
+        }
+        Action::PasteFromRegister => {
+            perform_action(Action::PasteFromHistory(0), state, lsp_handle);
         }
-        Action::CutToRegister => {}
-        Action::CutToClipboard => {}
-        Action::PasteFromRegister => {}
         Action::PasteFromClipboard => {
-            let content = state.clipboard_ctx.get_contents().unwrap();
+            if let Some(clipboard_ctx) = state.clipboard() {
+                let content = clipboard_ctx.get_contents().unwrap();
+                // Text copied in other applications is added to the kill ring as well
+                state.kill_ring.push(content);
+                perform_action(Action::PasteFromHistory(0), state, lsp_handle);
+            }
+        }
+        Action::PasteFromHistory(idx) => {
+            let Some(content) = state.kill_ring.get(idx).cloned() else {
+                state.notify(NotificationLevel::Info, "Nothing to paste".to_string());
+                return;
+            };
+            paste(state, &content, idx, lsp_handle);
+        }
+        Action::PasteBlock => {
+            let Some(content) = state.kill_ring.get(0).cloned() else {
+                state.notify(NotificationLevel::Info, "Nothing to paste".to_string());
+                return;
+            };
             let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
-            let cursor = buffer.insert_text(&content, &instance.cursor, lsp_handle, true);
-            instance.cursor = cursor;
+            block::paste_block(buffer, &instance.cursor, &content, lsp_handle);
             instance.selection.cursor = instance.cursor;
             instance.selection.mark = instance.cursor;
         }
+        Action::CyclePaste => {
+            let buffer_id = state.buffer_idx.unwrap();
+            let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+            let Some(idx) = state.kill_ring.cycle_idx(buffer_id, buffer.version) else {
+                state.notify(
+                    NotificationLevel::Info,
+                    "The last edit was not a paste".to_string(),
+                );
+                return;
+            };
+            let selection = state.kill_ring.last_paste.take().unwrap().selection;
+            let content = state.kill_ring.get(idx).cloned().unwrap_or_default();
+            let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            let (_text, cursor) = buffer.remove_text(&selection, lsp_handle, true);
+            instance.cursor = cursor;
+            paste(state, &content, idx, lsp_handle);
+        }
+        Action::OpenTerminal => {
+            let spawn_terminal = match &state.terminal {
+                Some(terminal) => terminal.has_exited(),
+                None => true,
+            };
+            if spawn_terminal {
+                match Terminal::spawn(&state.workspace_folder, 24, 80, &state.rt) {
+                    Ok(terminal) => state.terminal = Some(terminal),
+                    Err(err) => state.notify(
+                        NotificationLevel::Error,
+                        format!("Failed to spawn terminal: {}", err),
+                    ),
+                }
+            }
+            if state.terminal.is_some() {
+                state.mode = Mode::Terminal;
+            }
+        }
+        Action::CloseTerminal => {
+            state.terminal = None;
+            if matches!(state.mode, Mode::Terminal) {
+                state.mode = Mode::Normal;
+            }
+        }
+        Action::QuitTerminalMode => {
+            if matches!(state.mode, Mode::Terminal) {
+                state.mode = Mode::Normal;
+            }
+        }
+        Action::TerminalInput(text) => {
+            if let Some(terminal) = &mut state.terminal {
+                if let Err(err) = terminal.write_input(text.as_bytes()) {
+                    tracing::error!("Failed to write to terminal: {}", err);
+                }
+            }
+        }
+        Action::TerminalKey(key) => {
+            if let Some(terminal) = &mut state.terminal {
+                if let Err(err) = terminal.write_key(key) {
+                    tracing::error!("Failed to write to terminal: {}", err);
+                }
+            }
+        }
+        Action::TerminalScrollUp => {
+            if let Some(terminal) = &mut state.terminal {
+                terminal.scroll(terminal.rows as isize / 2);
+            }
+        }
+        Action::TerminalScrollDown => {
+            if let Some(terminal) = &mut state.terminal {
+                terminal.scroll(-(terminal.rows as isize / 2));
+            }
+        }
+        Action::ToggleFileTree => {
+            state.file_tree_open = !state.file_tree_open;
+            state.file_tree_focused = false;
+        }
+        Action::FocusFileTree => {
+            state.file_tree_focused = !state.file_tree_focused;
+            state.file_tree_open |= state.file_tree_focused;
+            // Reveal the current file in the tree
+            if let Some(path) = state
+                .buffer_idx
+                .and_then(|id| state.buffers.get(&id))
+                .and_then(|buffer| buffer.file_path.clone())
+            {
+                let root = state.file_tree.root.clone();
+                for folder in std::path::Path::new(&path).ancestors().skip(1) {
+                    let folder = folder.to_string_lossy().to_string();
+                    if !folder.starts_with(&root) || folder == root {
+                        break;
+                    }
+                    state.file_tree.expanded.insert(folder);
+                }
+                state.file_tree.select_path(&path);
+            }
+        }
+        Action::FileTreeNext => state.file_tree.select_next(),
+        Action::FileTreePrevious => state.file_tree.select_previous(),
+        Action::FileTreeOpen => {
+            let Some(row) = state.file_tree.selected() else {
+                return;
+            };
+            if row.entry.is_dir {
+                state.file_tree.toggle(&row.entry.path);
+            } else {
+                state.file_tree_focused = false;
+                state.open_location(&row.entry.path, Cursor { row: 0, column: 0 }, &*lsp_handle);
+            }
+        }
+        Action::FileTreeCollapse => {
+            let Some(row) = state.file_tree.selected() else {
+                return;
+            };
+            if row.expanded {
+                state.file_tree.toggle(&row.entry.path);
+            } else if let Some(parent) = std::path::Path::new(&row.entry.path).parent() {
+                state.file_tree.select_path(&parent.to_string_lossy());
+            }
+        }
+        Action::FileTreeNewFile | Action::FileTreeNewFolder => {
+            // Created in the selected folder or the folder of the selected file
+            let folder = match state.file_tree.selected() {
+                Some(row) if row.entry.is_dir => row.entry.path,
+                Some(row) => std::path::Path::new(&row.entry.path)
+                    .parent()
+                    .map(|parent| parent.to_string_lossy().to_string())
+                    .unwrap_or(state.file_tree.root.clone()),
+                None => state.file_tree.root.clone(),
+            };
+            state.input_dialog = Some(if matches!(action, Action::FileTreeNewFile) {
+                InputDialog::new_file(&folder)
+            } else {
+                InputDialog::new_folder(&folder)
+            });
+        }
+        Action::FileTreeRename => {
+            if let Some(row) = state.file_tree.selected() {
+                state.input_dialog = Some(InputDialog::rename(&row.entry.path, &row.entry.name));
+            }
+        }
+        Action::FileTreeDelete => {
+            if let Some(row) = state.file_tree.selected() {
+                state.input_dialog = Some(InputDialog::delete(&row.entry.path, &row.entry.name));
+            }
+        }
+        Action::ToggleMarkdownPreview => {
+            state.markdown_preview_open = !state.markdown_preview_open;
+        }
+        Action::DiffWithSaved => {
+            if let Some(buffer_idx) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+                if let Some(path) = buffer.file_path.clone() {
+                    match file_io::read_file_content(&path) {
+                        Ok(saved) => {
+                            state.diff_view = Some(SideBySideDiff::new(
+                                format!("{} (saved)", path),
+                                &saved,
+                                format!("{} (buffer)", path),
+                                &buffer.get_content("\n".into()),
+                            ));
+                        }
+                        Err(err) => state.notify(
+                            NotificationLevel::Error,
+                            format!("Failed to read {}: {}", path, err),
+                        ),
+                    }
+                }
+            }
+        }
+        Action::NextHunk | Action::PrevHunk => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let hunks = git::current_hunks(state, buffer_id).unwrap_or_default();
+            let (_buffer, instance) = state.get_buffer_by_id(buffer_id);
+            let forward = matches!(action, Action::NextHunk);
+            let Some(row) = git::next_hunk(&hunks, instance.cursor.row, forward) else {
+                state.notify(NotificationLevel::Info, "No changes".to_string());
+                return;
+            };
+            let (_buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            instance.cursor = Cursor { row, column: 0 };
+            instance.selection.cursor = instance.cursor;
+            instance.selection.mark = instance.cursor;
+            instance.column_level = 0;
+        }
+        Action::StageHunk | Action::RevertHunk => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+            let row = instance.cursor.row;
+            let Some(hunk) = git::current_hunks(state, buffer_id)
+                .unwrap_or_default()
+                .into_iter()
+                .find(|hunk| hunk.contains(row))
+            else {
+                state.notify(
+                    NotificationLevel::Info,
+                    "No change under the cursor".to_string(),
+                );
+                return;
+            };
+            let path = buffer.file_path.clone().unwrap();
+            let base = state.git.base(&path).unwrap().to_vec();
+            if matches!(action, Action::StageHunk) {
+                match git::stage_hunk(&path, &base, &buffer.lines, &hunk) {
+                    Ok(()) => state.notify(NotificationLevel::Info, "Staged hunk".to_string()),
+                    Err(err) => state.notify(
+                        NotificationLevel::Error,
+                        format!("Failed to stage hunk: {:#}", err),
+                    ),
+                }
+                return;
+            }
+            let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            let start = Cursor {
+                row: hunk.start,
+                column: 0,
+            };
+            let selection = Selection {
+                cursor: Cursor {
+                    row: hunk.end,
+                    column: 0,
+                },
+                mark: start,
+            };
+            buffer.remove_text(&selection, lsp_handle, true);
+            let text: String = base[hunk.old_start..hunk.old_end]
+                .iter()
+                .map(|line| format!("{}\n", line))
+                .collect();
+            buffer.insert_text(&text, &start, lsp_handle, true);
+            instance.cursor = start;
+            instance.selection.cursor = start;
+            instance.selection.mark = start;
+            instance.column_level = 0;
+        }
+        Action::DiffCurrentFile => {
+            let Some(buffer_idx) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+            let Some(path) = buffer.file_path.clone() else {
+                return;
+            };
+            let Some(base) = state.git.base(&path) else {
+                state.notify(
+                    NotificationLevel::Info,
+                    format!("{} is not tracked by git", path),
+                );
+                return;
+            };
+            state.diff_view = Some(SideBySideDiff::new(
+                format!("{} (HEAD)", path),
+                &base.join("\n"),
+                format!("{} (buffer)", path),
+                &buffer.get_content("\n".into()),
+            ));
+        }
+        Action::GitBlameLine | Action::GitShowCommit => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+            let path = buffer.file_path.clone().unwrap_or_default();
+            let line = match git::blame::blame_line(state, buffer_id, instance.cursor.row) {
+                Ok(line) => line,
+                Err(err) => {
+                    state.notify(
+                        NotificationLevel::Error,
+                        format!("Failed to blame: {:#}", err),
+                    );
+                    return;
+                }
+            };
+            if matches!(action, Action::GitBlameLine) || !line.is_committed() {
+                state.info_modal = Some(line.details());
+                return;
+            }
+            let folder = std::path::Path::new(&path).parent().unwrap();
+            match git::run_git(folder, &["show", &line.hash], None) {
+                Ok(commit) => {
+                    let mut buffer = LineBuffer::new(commit, None);
+                    buffer.modified = false;
+                    state.buffer_idx = Some(state.add_buffer(buffer));
+                }
+                Err(err) => state.notify(
+                    NotificationLevel::Error,
+                    format!("Failed to show commit: {:#}", err),
+                ),
+            }
+        }
+        Action::NextConflict => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+            let conflicts = find_conflicts(&buffer.lines);
+            let Some(row) = next_conflict(&conflicts, instance.cursor.row, true) else {
+                state.notify(NotificationLevel::Info, "No merge conflicts".to_string());
+                return;
+            };
+            let (_buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            instance.cursor = Cursor { row, column: 0 };
+            instance.selection.cursor = instance.cursor;
+            instance.selection.mark = instance.cursor;
+            instance.column_level = 0;
+        }
+        Action::ConflictKeepOurs | Action::ConflictKeepTheirs | Action::ConflictKeepBoth => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+            let row = instance.cursor.row;
+            let Some(conflict) = find_conflicts(&buffer.lines)
+                .into_iter()
+                .find(|conflict| conflict.contains(row))
+            else {
+                state.notify(
+                    NotificationLevel::Info,
+                    "No merge conflict under the cursor".to_string(),
+                );
+                return;
+            };
+            let sides: &[ConflictSide] = match action {
+                Action::ConflictKeepOurs => &[ConflictSide::Ours],
+                Action::ConflictKeepTheirs => &[ConflictSide::Theirs],
+                _ => &[ConflictSide::Ours, ConflictSide::Theirs],
+            };
+            let kept = resolve(&buffer.lines, &conflict, sides);
+            // The last line of the buffer has no line ending to remove
+            let last = conflict.end + 1 >= buffer.get_num_lines();
+            let end = if last {
+                Cursor {
+                    row: conflict.end,
+                    column: buffer.get_line_length(conflict.end),
+                }
+            } else {
+                Cursor {
+                    row: conflict.end + 1,
+                    column: 0,
+                }
+            };
+            let mut text = kept.join("\n");
+            if !last && !kept.is_empty() {
+                text.push('\n');
+            }
+            let start = Cursor {
+                row: conflict.start,
+                column: 0,
+            };
+            let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            buffer.remove_text(
+                &Selection {
+                    cursor: end,
+                    mark: start,
+                },
+                lsp_handle,
+                true,
+            );
+            buffer.insert_text(&text, &start, lsp_handle, true);
+            instance.cursor = start;
+            instance.selection.cursor = start;
+            instance.selection.mark = start;
+            instance.column_level = 0;
+        }
+        Action::GitStatus => {
+            let folder = state
+                .buffer_idx
+                .and_then(|id| state.buffers.get(&id))
+                .and_then(|buffer| buffer.file_path.as_deref())
+                .and_then(|path| std::path::Path::new(path).parent())
+                .map(|folder| folder.to_path_buf())
+                .unwrap_or_else(|| state.workspace_folder.clone().into());
+            match GitStatus::load(&folder) {
+                Ok(status) => state.git_status = Some(status),
+                Err(err) => state.notify(
+                    NotificationLevel::Error,
+                    format!("Failed to get git status: {:#}", err),
+                ),
+            }
+        }
+        Action::GitStatusNext => {
+            if let Some(status) = &mut state.git_status {
+                status.select_next();
+            }
+        }
+        Action::GitStatusPrevious => {
+            if let Some(status) = &mut state.git_status {
+                status.select_previous();
+            }
+        }
+        Action::GitStatusToggleStage => {
+            if let Some(status) = &mut state.git_status {
+                if let Err(err) = status.toggle_stage() {
+                    state.notify(NotificationLevel::Error, format!("{:#}", err));
+                }
+            }
+        }
+        Action::GitStatusOpen => {
+            let Some(status) = state.git_status.take() else {
+                return;
+            };
+            if let Some(entry) = status.selected() {
+                let path = status.root.join(&entry.path);
+                state.open_location(
+                    &path.to_string_lossy(),
+                    Cursor { row: 0, column: 0 },
+                    &*lsp_handle,
+                );
+            }
+        }
+        Action::CloseGitStatus => {
+            state.git_status = None;
+        }
+        Action::GitCommit => {
+            if state.git_status.is_none() {
+                perform_action(Action::GitStatus, state, lsp_handle);
+            }
+            let Some(status) = state.git_status.take() else {
+                return;
+            };
+            let message_path =
+                match git::run_git(&status.root, &["rev-parse", "--absolute-git-dir"], None)
+                    .and_then(|git_dir| {
+                        let path = std::path::Path::new(git_dir.trim_end()).join("COMMIT_EDITMSG");
+                        std::fs::write(&path, status.commit_template())?;
+                        Ok(path.to_string_lossy().to_string())
+                    }) {
+                    Ok(path) => path,
+                    Err(err) => {
+                        state.notify(
+                            NotificationLevel::Error,
+                            format!("Failed to start commit: {:#}", err),
+                        );
+                        return;
+                    }
+                };
+            // A message buffer left open from an earlier commit is replaced
+            if let Some(id) = state
+                .buffers
+                .iter()
+                .find(|(_, buffer)| buffer.file_path.as_deref() == Some(message_path.as_str()))
+                .map(|(id, _)| *id)
+            {
+                state.remove_buffer(id);
+            }
+            state.open_location(&message_path, Cursor { row: 0, column: 0 }, &*lsp_handle);
+            state.git_commit = state.buffer_idx.map(|id| (id, status.root));
+        }
+        Action::ToggleGitBlame => {
+            state.git.blame_enabled = !state.git.blame_enabled;
+            state.update_view = true;
+        }
+        Action::ShowNotificationHistory => {
+            let history = state.notifications.history_text();
+            let mut buffer = LineBuffer::new(history, None);
+            buffer.modified = false;
+            state.buffer_idx = Some(state.add_buffer(buffer));
+        }
+        Action::DismissNotifications => {
+            state.notifications.dismiss_all();
+        }
+        Action::ToggleSpellcheck => {
+            state.spellcheck_enabled = !state.spellcheck_enabled;
+            state.update_view = true;
+        }
+        Action::ToggleWhitespace => {
+            state.preferences.render_whitespace = !state.preferences.render_whitespace;
+            state.update_view = true;
+        }
+        Action::SpellSuggestions => {
+            if let Some(buffer_id) = state.buffer_idx {
+                let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+                let cursor = instance.cursor;
+                let Some((column, word)) = buffer
+                    .lines
+                    .get(cursor.row)
+                    .and_then(|line| spellcheck::word_at(line, cursor.column))
+                    .map(|(column, word)| (column, word.to_string()))
+                else {
+                    return;
+                };
+                let Some(dictionary) = state.dictionary() else {
+                    state.notify(
+                        NotificationLevel::Warning,
+                        "Spellcheck unavailable: no dictionary found".into(),
+                    );
+                    return;
+                };
+                if dictionary.check(&word) {
+                    state.notify(
+                        NotificationLevel::Info,
+                        format!("\"{}\" is spelled correctly", word),
+                    );
+                    return;
+                }
+                let suggestions = dictionary.suggest(&word, 8);
+                state.spell_menu = Some(SpellMenu {
+                    range: Selection {
+                        cursor: Cursor {
+                            row: cursor.row,
+                            column: column + word.len(),
+                        },
+                        mark: Cursor {
+                            row: cursor.row,
+                            column,
+                        },
+                    },
+                    word,
+                    suggestions,
+                    idx: 0,
+                });
+            }
+        }
+        Action::ApplySpellSuggestion(text) => {
+            if let Some(menu) = state.spell_menu.take() {
+                perform_action(Action::DeleteText(menu.range), state, lsp_handle);
+                perform_action(Action::InsertText(text, menu.range.mark), state, lsp_handle);
+            }
+        }
+        Action::AddWordToDictionary => {
+            if let Some(menu) = state.spell_menu.take() {
+                if let Some(dictionary) = state.dictionary() {
+                    if let Err(err) = dictionary.add_word(&menu.word) {
+                        state.notify(
+                            NotificationLevel::Error,
+                            format!("Failed to update dictionary: {}", err),
+                        );
+                    }
+                }
+                state.spell_errors.clear();
+                state.update_view = true;
+            }
+        }
+        Action::CloseSpellMenu => {
+            state.spell_menu = None;
+        }
+        Action::ShowEditorStats => {
+            let stats = stats::editor_stats(state, lsp_handle);
+            let mut buffer = LineBuffer::new(stats, None);
+            buffer.modified = false;
+            state.buffer_idx = Some(state.add_buffer(buffer));
+        }
+        Action::BufferStats => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let stats = stats::buffer_stats(state, buffer_id);
+            let mut buffer = LineBuffer::new(stats, None);
+            buffer.modified = false;
+            state.buffer_idx = Some(state.add_buffer(buffer));
+        }
+        Action::RunTask(name) => {
+            if state.task.as_ref().is_some_and(|task| task.is_running()) {
+                state.notify(
+                    NotificationLevel::Warning,
+                    "A task is already running".to_string(),
+                );
+                return;
+            }
+            let task = match tasks::load_tasks(&state.workspace_folder, &state.preferences.tasks) {
+                Ok(tasks) => tasks.into_iter().find(|task| task.name == name),
+                Err(err) => {
+                    state.notify(
+                        NotificationLevel::Error,
+                        format!("Failed to read {}: {}", tasks::TASKS_FILE, err),
+                    );
+                    return;
+                }
+            };
+            let Some(task) = task else {
+                state.notify(NotificationLevel::Error, format!("No task named {}", name));
+                return;
+            };
+
+            let folder = task.folder(&state.workspace_folder);
+            let mut parser = ErrorParser::new(&task.error_formats, folder.clone());
+            if let Some(pattern) = &task.problem_matcher {
+                parser = match parser.with_matcher(pattern) {
+                    Ok(parser) => parser,
+                    Err(err) => {
+                        state.notify(
+                            NotificationLevel::Error,
+                            format!("Invalid problem matcher of task {}: {}", task.name, err),
+                        );
+                        return;
+                    }
+                };
+            }
+
+            // Output of the previous run is replaced
+            if let Some(previous) = state.task.take() {
+                if state.buffers.contains_key(&previous.buffer_id) {
+                    state.remove_buffer(previous.buffer_id);
+                }
+            }
+            state.quickfix.clear();
+            let mut buffer = LineBuffer::new(format!("$ {}", task.command_line()), None);
+            buffer.modified = false;
+            let buffer_id = state.add_buffer(buffer);
+            state.buffer_idx = Some(buffer_id);
+            state.update_view = true;
+
+            let kill = concurrent::cli::run_command(
+                task.program.clone(),
+                task.args.clone(),
+                folder,
+                tasks::task_output,
+                tasks::task_exit,
+                &state.rt,
+                state.async_handle.sender.clone(),
+            );
+            state.task = Some(TaskRun {
+                name: task.name,
+                buffer_id,
+                parser,
+                kill: Some(kill),
+            });
+        }
+        Action::FilterSelectionThroughCommand(command) => {
+            if matches!(state.mode, Mode::Normal) {
+                filter::filter_selection(state, command);
+            }
+        }
+        Action::SortSelectedLines
+        | Action::SortSelectedLinesNumeric
+        | Action::SortSelectedLinesCaseInsensitive
+        | Action::UniqueSelectedLines
+        | Action::ReverseSelectedLines => {
+            if !matches!(state.mode, Mode::Normal) {
+                return;
+            }
+            let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+            let selection = instance.selection;
+            instance.selection = match action {
+                Action::SortSelectedLines => {
+                    buffer.sort_lines(&selection, LineOrder::Lexical, lsp_handle)
+                }
+                Action::SortSelectedLinesNumeric => {
+                    buffer.sort_lines(&selection, LineOrder::Numeric, lsp_handle)
+                }
+                Action::SortSelectedLinesCaseInsensitive => {
+                    buffer.sort_lines(&selection, LineOrder::CaseInsensitive, lsp_handle)
+                }
+                Action::UniqueSelectedLines => buffer.unique_lines(&selection, lsp_handle),
+                _ => buffer.reverse_lines(&selection, lsp_handle),
+            };
+            instance.cursor = instance.selection.cursor;
+            instance.column_level = instance.cursor.column;
+        }
+        Action::PlaceSign(namespace, row, sign) => {
+            if let Some(buffer_id) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id_mut(buffer_id);
+                if row < buffer.get_num_lines() {
+                    buffer.extmarks.place_sign(&namespace, row, sign);
+                    state.update_view = true;
+                }
+            }
+        }
+        Action::RemoveSigns(namespace, row) => {
+            if let Some(buffer_id) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id_mut(buffer_id);
+                buffer.extmarks.remove_signs(&namespace, row);
+                state.update_view = true;
+            }
+        }
+        Action::AddVirtualText(namespace, position, text) => {
+            if let Some(buffer_id) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id_mut(buffer_id);
+                if position.row < buffer.get_num_lines()
+                    && position.column <= buffer.get_line_length(position.row)
+                {
+                    buffer.extmarks.add_virtual_text(&namespace, position, text);
+                    state.update_view = true;
+                }
+            }
+        }
+        Action::RemoveVirtualText(namespace) => {
+            if let Some(buffer_id) = state.buffer_idx {
+                let (buffer, _instance) = state.get_buffer_by_id_mut(buffer_id);
+                buffer.extmarks.remove_virtual_text(&namespace);
+                state.update_view = true;
+            }
+        }
+        Action::StopTask => {
+            if let Some(kill) = state.task.as_mut().and_then(|task| task.kill.take()) {
+                let _ = kill.send(());
+            }
+        }
+        Action::NextError | Action::PrevError => {
+            let entry = if matches!(action, Action::NextError) {
+                state.quickfix.next_entry()
+            } else {
+                state.quickfix.prev_entry()
+            };
+            let Some(entry) = entry.cloned() else {
+                state.notify(NotificationLevel::Info, "No errors".to_string());
+                return;
+            };
+            state.open_location(&entry.path, entry.cursor, lsp_handle);
+            let message = format!(
+                "[{}/{}] {}",
+                state.quickfix.idx.unwrap_or_default() + 1,
+                state.quickfix.entries.len(),
+                entry.message
+            );
+            state.notify(NotificationLevel::Info, message);
+        }
+        Action::NextDiagnostic | Action::PreviousDiagnostic => {
+            let Some(buffer_id) = state.buffer_idx else {
+                return;
+            };
+            let (_buffer, instance) = state.get_buffer_by_id(buffer_id);
+            let forward = matches!(action, Action::NextDiagnostic);
+            let diagnostics = diagnostics::buffer_diagnostics(state, buffer_id);
+            let Some(cursor) = diagnostics::next_diagnostic(diagnostics, instance.cursor, forward)
+            else {
+                state.notify(NotificationLevel::Info, "No diagnostics".to_string());
+                return;
+            };
+            let (_buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            instance.cursor = cursor;
+            instance.selection.cursor = cursor;
+            instance.selection.mark = cursor;
+            instance.column_level = cursor.column;
+        }
+        Action::StartDebugging => {
+            dap::start_debugging(state);
+        }
+        Action::StopDebugging => {
+            dap::stop_debugging(state);
+        }
+        Action::ToggleBreakpoint => {
+            if let Some(buffer_idx) = state.buffer_idx {
+                let (_buffer, instance) = state.get_buffer_by_id(buffer_idx);
+                let row = instance.cursor.row;
+                perform_action(Action::ToggleBreakpointAtRow(row), state, lsp_handle);
+            }
+        }
+        Action::ToggleBreakpointAtRow(row) => {
+            let Some(buffer_idx) = state.buffer_idx else {
+                return;
+            };
+            let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+            let Some(path) = buffer.file_path.clone() else {
+                state.notify(
+                    NotificationLevel::Warning,
+                    "Breakpoints can only be set in files".to_string(),
+                );
+                return;
+            };
+            let rows = state.breakpoints.entry(path.clone()).or_default();
+            if !rows.remove(&row) {
+                rows.insert(row);
+            }
+            if let Some(session) = &mut state.debug {
+                session.set_breakpoints(&path, &state.breakpoints[&path]);
+            }
+            state.update_view = true;
+        }
+        Action::DebugContinue => {
+            if state.debug.is_some() {
+                dap::resume(state, "continue");
+            } else {
+                dap::start_debugging(state);
+            }
+        }
+        Action::DebugStepOver => {
+            dap::resume(state, "next");
+        }
+        Action::DebugStepInto => {
+            dap::resume(state, "stepIn");
+        }
+        Action::DebugStepOut => {
+            dap::resume(state, "stepOut");
+        }
+        Action::ShowDebugPanel => {
+            dap::show_panel(state);
+        }
+        Action::CloseDiff => {
+            // Pushed hunks which were not saved are only discarded when closing again
+            if let Some(diff) = state.diff_view.as_mut() {
+                if diff.left_modified || diff.right_modified {
+                    diff.left_modified = false;
+                    diff.right_modified = false;
+                    state.notify(
+                        NotificationLevel::Warning,
+                        "Diff has unsaved changes, close again to discard them".to_string(),
+                    );
+                    return;
+                }
+            }
+            state.diff_view = None;
+        }
+        Action::OpenDiff(left, right) => match SideBySideDiff::from_files(&left, &right) {
+            Ok(diff) => state.diff_view = Some(diff),
+            Err(err) => state.notify(NotificationLevel::Error, format!("{:#}", err)),
+        },
+        Action::DiffNextHunk => {
+            if let Some(diff) = state.diff_view.as_mut() {
+                diff.next_hunk();
+            }
+        }
+        Action::DiffPrevHunk => {
+            if let Some(diff) = state.diff_view.as_mut() {
+                diff.prev_hunk();
+            }
+        }
+        Action::DiffPushToRight => {
+            if let Some(diff) = state.diff_view.as_mut() {
+                diff.push_hunk(true);
+            }
+        }
+        Action::DiffPushToLeft => {
+            if let Some(diff) = state.diff_view.as_mut() {
+                diff.push_hunk(false);
+            }
+        }
+        Action::DiffSave => {
+            if let Some(diff) = state.diff_view.as_mut() {
+                if let Err(err) = diff.save() {
+                    state.notify(
+                        NotificationLevel::Error,
+                        format!("Failed to save diff: {:#}", err),
+                    );
+                }
+            }
+        }
+        Action::SplitVertical => {
+            state.split_pane(SplitDirection::Vertical);
+        }
+        Action::SplitHorizontal => {
+            state.split_pane(SplitDirection::Horizontal);
+        }
+        Action::FocusNextPane => {
+            state.focus_pane(state.layout.next_pane());
+        }
+        Action::FocusPane(id) => {
+            state.focus_pane(id);
+        }
+        Action::ClosePane => {
+            state.close_pane();
+        }
+        Action::ResizeSplit(id, ratio) => {
+            state.layout.set_ratio(id, ratio);
+            state.update_view = true;
+        }
+        Action::OpenCommandPalette => {
+            if matches!(state.mode, Mode::Normal) {
+                state.command_palette_open = true;
+            }
+        }
+        Action::ToggleAbbreviations => {
+            state.abbreviations_enabled = !state.abbreviations_enabled;
+            state.notify(
+                NotificationLevel::Info,
+                format!(
+                    "Abbreviations {}",
+                    if state.abbreviations_enabled {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    }
+                ),
+            );
+        }
+        Action::RepeatLastChange => {
+            if matches!(state.mode, Mode::Normal) && state.buffer_idx.is_some() {
+                // Replaying records the same edit again
+                for action in state.last_edit.clone() {
+                    perform_action(action, state, lsp_handle);
+                }
+            }
+        }
+    }
+}
+
+fn request_calls(
+    direction: CallDirection,
+    item: &CallHierarchyItem,
+    lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    if let Some(lsp_handle) = lsp_handle {
+        lsp_handle
+            .send_request_sync(
+                direction.method().to_string(),
+                Some(LSPClientHandle::calls_request(&item.item)),
+            )
+            .unwrap();
+    }
+}
+
+/// Create, rename or delete a file from the file tree with the input of a dialog
+fn file_tree_operation(state: &mut EditorState, kind: InputKind, input: &str) {
+    if input.is_empty() {
+        return;
+    }
+    let join = |folder: &str| {
+        std::path::Path::new(folder)
+            .join(input)
+            .to_string_lossy()
+            .to_string()
+    };
+    let (result, path) = match &kind {
+        InputKind::NewFile(folder) => (file_io::create_file(&join(folder)), join(folder)),
+        InputKind::NewFolder(folder) => (file_io::create_directory(&join(folder)), join(folder)),
+        InputKind::Rename(path) => {
+            let renamed = std::path::Path::new(path)
+                .with_file_name(input)
+                .to_string_lossy()
+                .to_string();
+            (file_io::rename_file_or_directory(path, input), renamed)
+        }
+        InputKind::Delete(path) => {
+            if input != "y" {
+                return;
+            }
+            let result = if std::path::Path::new(path).is_dir() {
+                file_io::delete_directory_recursively(path)
+            } else {
+                file_io::delete_file(path)
+            };
+            (result, path.clone())
+        }
+        InputKind::Decrypt(..) => return,
+    };
+    if let Err(err) = result {
+        state.notify(
+            NotificationLevel::Error,
+            format!("File tree operation failed: {:#}", err),
+        );
+        return;
+    }
+    if let InputKind::Rename(previous) = &kind {
+        // Open buffers of a renamed file are saved to the new name
+        for buffer in state.buffers.values_mut() {
+            if buffer.file_path.as_ref() == Some(previous) {
+                buffer.set_file_path(path.clone());
+            }
+        }
+    }
+    if let InputKind::NewFile(folder) | InputKind::NewFolder(folder) = &kind {
+        if *folder != state.file_tree.root {
+            state.file_tree.expanded.insert(folder.clone());
+        }
+    }
+    state.file_tree.refresh();
+    state.file_tree.select_path(&path);
+}
+
+/// Commit the staged changes with the saved message buffer, closing it on success
+fn commit_message(state: &mut EditorState, buffer_id: u32) {
+    let Some((_, root)) = state.git_commit.take() else {
+        return;
+    };
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let path = buffer.file_path.clone().unwrap();
+    match git::status::commit(&root, &path) {
+        Ok(output) => {
+            state.remove_buffer(buffer_id);
+            state.notify(
+                NotificationLevel::Info,
+                output.lines().next().unwrap_or("Committed").to_string(),
+            );
+            git::reload_bases(state);
+        }
+        Err(err) => {
+            state.git_commit = Some((buffer_id, root));
+            state.notify(
+                NotificationLevel::Error,
+                format!("Failed to commit: {:#}", err),
+            );
+        }
+    }
+}
+
+/// Ask for the signature help at the cursor if the language server provides it
+fn request_signature_help(state: &EditorState, lsp_handle: &mut Option<&mut LSPClientHandle>) {
+    let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+    let (Some(path), Some(lsp_handle)) = (buffer.file_path.clone(), lsp_handle) else {
+        return;
+    };
+    if lsp_handle.supports("textDocument/signatureHelp") {
+        lsp_handle
+            .send_request_sync(
+                "textDocument/signatureHelp".to_string(),
+                Some(LSPClientHandle::signature_help_request(
+                    path,
+                    instance.cursor,
+                )),
+            )
+            .unwrap();
+    }
+}
+
+/// Insert the kill ring entry at the cursor and remember it for `CyclePaste`
+fn paste(
+    state: &mut EditorState,
+    content: &str,
+    idx: usize,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) {
+    let buffer_id = state.buffer_idx.unwrap();
+    let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+    let start = instance.cursor;
+    let cursor = buffer.insert_text(content, &start, lsp_handle, true);
+    instance.cursor = cursor;
+    instance.selection.cursor = instance.cursor;
+    instance.selection.mark = instance.cursor;
+    instance.column_level = instance.cursor.column;
+    state.kill_ring.last_paste = Some(LastPaste {
+        buffer_id,
+        selection: Selection {
+            mark: start,
+            cursor,
+        },
+        version: buffer.version,
+        idx,
+    });
+}
+
+/// Replace all matches in the current buffer (or selection),
+/// or start a session confirming each match
+fn start_replace(
+    state: &mut EditorState,
+    pattern: &str,
+    replacement: String,
+    flags: &str,
+    in_selection: bool,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) {
+    let Some(buffer_id) = state.buffer_idx else {
+        return;
+    };
+    let parsed = ReplaceFlags::parse(flags)
+        .and_then(|flags| Ok((flags, replace::build_regex(pattern, &flags)?)));
+    let (flags, regex) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            state.notify(NotificationLevel::Error, format!("{:#}", err));
+            return;
+        }
+    };
+
+    let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+    let content = buffer.get_content("\n".to_string());
+    let (from, to) = if in_selection {
+        let (start, end) = instance.selection.in_order();
+        (
+            buffer.byte_index_from_cursor(start, "\n"),
+            buffer.byte_index_from_cursor(end, "\n"),
+        )
+    } else {
+        (0, content.len())
+    };
+
+    if flags.confirm {
+        state.replace_session = Some(ReplaceSession {
+            buffer_id,
+            regex,
+            replacement,
+            current: None,
+            end: to,
+            replaced: 0,
+        });
+        select_replace_match(state, from);
+    } else {
+        let matches = replace::find_matches(&regex, &replacement, &content, from, to);
+        replace_matches(state, buffer_id, &content, &matches, lsp_handle);
+        let message = match matches.len() {
+            0 => format!("No matches for {}", pattern),
+            count => format!("Replaced {} matches", count),
+        };
+        state.notify(NotificationLevel::Info, message);
+    }
+}
+
+/// Replace the matches of the content of the buffer as a single edit
+fn replace_matches(
+    state: &mut EditorState,
+    buffer_id: u32,
+    content: &str,
+    matches: &[ReplaceMatch],
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) {
+    let (Some(first), Some(last)) = (matches.first(), matches.last()) else {
+        return;
+    };
+    let text = replace::apply_matches(content, matches);
+    let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+    let selection = replace::selection_from_bytes(&buffer.lines, first.start, last.end);
+    let (_text, start) = buffer.remove_text(&selection, lsp_handle, true);
+    let cursor = buffer.insert_text(&text, &start, lsp_handle, true);
+    instance.cursor = cursor;
+    instance.selection.cursor = cursor;
+    instance.selection.mark = cursor;
+    instance.column_level = cursor.column;
+    state.update_view = true;
+}
+
+/// Content of a file as it would be in a buffer, from its buffer if it is open
+fn workspace_file_content(state: &EditorState, path: &str) -> Option<String> {
+    match state
+        .buffers
+        .values()
+        .find(|buffer| buffer.file_path.as_deref() == Some(path))
+    {
+        Some(buffer) => Some(buffer.get_content("\n".to_string())),
+        None => {
+            let content = std::fs::read_to_string(path).ok()?;
+            Some(LineBuffer::new(content, None).get_content("\n".to_string()))
+        }
+    }
+}
+
+/// Select the next match of the replace session for confirmation, or finish it
+fn select_replace_match(state: &mut EditorState, from: usize) {
+    let Some(session) = &mut state.replace_session else {
+        return;
+    };
+    let (Some(buffer), Some(instance)) = (
+        state.buffers.get(&session.buffer_id),
+        state.instances.get_mut(&session.buffer_id),
+    ) else {
+        state.replace_session = None;
+        return;
+    };
+    let content = buffer.get_content("\n".to_string());
+    session.current = replace::next_match(
+        &session.regex,
+        &session.replacement,
+        &content,
+        from,
+        session.end,
+    );
+    let Some(found) = &session.current else {
+        finish_replace(state);
+        return;
+    };
+    instance.selection = replace::selection_from_bytes(&buffer.lines, found.start, found.end);
+    instance.cursor = instance.selection.cursor;
+    instance.column_level = instance.cursor.column;
+    state.update_view = true;
+}
+
+fn finish_replace(state: &mut EditorState) {
+    if let Some(session) = state.replace_session.take() {
+        state.notify(
+            NotificationLevel::Info,
+            format!("Replaced {} matches", session.replaced),
+        );
+    }
+}
+
+/// Select the next (or previous) match of the query in the current buffer
+fn search_buffer(state: &mut EditorState, query: &str, backwards: bool) {
+    let Some(buffer_idx) = state.buffer_idx else {
+        return;
+    };
+    state.search_query = Some(query.to_string());
+    let (buffer, instance) = state.get_buffer_by_id_mut(buffer_idx);
+    // Matches are searched from the start of the selection so that a selected
+    // match is skipped in both directions
+    let cursor = *instance.selection.in_order().0;
+    let Some(selection) = search::find_in_lines(&buffer.lines, query, &cursor, backwards) else {
+        state.notify(NotificationLevel::Info, format!("No matches for {}", query));
+        return;
+    };
+    instance.selection = selection;
+    instance.cursor = selection.cursor;
+    instance.column_level = selection.cursor.column;
+    state.update_view = true;
+}
+
+/// Continue the markdown list item under the cursor on a new line.
+/// A newline on an empty item removes its marker instead, ending the list.
+/// Returns false if the cursor is not after the marker of a list item.
+fn continue_markdown_list(
+    state: &mut EditorState,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) -> bool {
+    let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+    let cursor = instance.selection.cursor;
+    let line = &buffer.lines[cursor.row];
+    if buffer.language != Language::Markdown {
+        return false;
+    }
+    let Some(item) = markdown::parse_list_item(line) else {
+        return false;
+    };
+    if cursor.column < line.len() - item.text.len() {
+        return false;
+    }
+
+    let cursor = if item.text.is_empty() {
+        buffer.replace_line(cursor.row, "", lsp_handle);
+        Cursor {
+            row: cursor.row,
+            column: 0,
+        }
+    } else {
+        let prefix = markdown::continue_list(line).unwrap_or_default();
+        let cursor = buffer.insert_text(&format!("\n{}", prefix), &cursor, lsp_handle, true);
+        for (row, line) in markdown::renumber_list(&buffer.lines, cursor.row) {
+            buffer.replace_line(row, &line, lsp_handle);
+        }
+        Cursor {
+            row: cursor.row,
+            column: buffer.get_line_length(cursor.row)
+                - markdown::parse_list_item(&buffer.lines[cursor.row])
+                    .map(|item| item.text.len())
+                    .unwrap_or(0),
+        }
+    };
+    instance.cursor = cursor;
+    instance.selection.cursor = cursor;
+    instance.selection.mark = cursor;
+    instance.column_level = cursor.column;
+    true
+}
+
+/// Foldable ranges of the current buffer from its syntax tree or from the language server.
+/// Folding ranges are requested if they are not up to date, running the action again once they arrive.
+fn fold_ranges(
+    state: &mut EditorState,
+    action: &Action,
+    lsp_handle: &mut Option<&mut LSPClientHandle>,
+) -> Option<Vec<Fold>> {
+    let (buffer, _instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+    if let Some(ranges) =
+        folding::syntax_fold_ranges(buffer.language, &buffer.get_content("\n".to_string()))
+    {
+        return Some(ranges);
+    }
+    match &buffer.folding_ranges {
+        Some((version, ranges)) if *version == buffer.version => return Some(ranges.clone()),
+        _ => {}
+    }
+    match (lsp_handle, buffer.file_path.clone()) {
+        (Some(lsp_handle), Some(path)) => {
+            lsp_handle
+                .send_request_sync(
+                    "textDocument/foldingRange".to_string(),
+                    Some(LSPClientHandle::folding_range_request(path)),
+                )
+                .unwrap();
+            state.pending_fold = Some(action.clone());
+        }
+        _ => state.notify(
+            NotificationLevel::Info,
+            "Folding is not available for this buffer".to_string(),
+        ),
+    }
+    None
+}
+
+/// Apply the edit to the block selection of the current buffer and move the cursor
+/// to the edited block, false if there is no block selection
+fn edit_block(
+    state: &mut EditorState,
+    edit: impl FnOnce(&mut LineBuffer, &Selection) -> Selection,
+) -> bool {
+    let Some(buffer_id) = state.buffer_idx else {
+        return false;
+    };
+    let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+    let Some(selection) = instance.block() else {
+        return false;
+    };
+    instance.selection = edit(buffer, &selection);
+    instance.cursor = instance.selection.cursor;
+    instance.column_level = instance.cursor.column;
+    true
+}
+
+/// Move the cursor past the rows hidden by collapsed folds
+fn skip_folds(buffer: &LineBuffer, instance: &mut BufferInstance, down: bool) {
+    instance.folds.sync(&buffer.line_shifts);
+    let last_row = buffer.get_num_lines() - 1;
+    while instance.folds.is_folded(instance.cursor.row) && (!down || instance.cursor.row < last_row)
+    {
+        if down {
+            buffer.move_cursor_down(&mut instance.cursor, instance.column_level);
+        } else {
+            buffer.move_cursor_up(&mut instance.cursor, instance.column_level);
+        }
+    }
+}
+
+/// Select the innermost text object around the selection
+fn select_text_object(state: &mut EditorState, object: TextObject) {
+    let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+    let (start, end) = instance.selection.in_order();
+    let found = text_objects::select_text_object(
+        buffer.language,
+        &buffer.get_content("\n".to_string()),
+        buffer.byte_index_from_cursor(start, "\n"),
+        buffer.byte_index_from_cursor(end, "\n"),
+        object,
+    );
+    let Some((start, end)) = found else {
+        state.notify(
+            NotificationLevel::Info,
+            format!("No {:?} at the cursor", object).to_lowercase(),
+        );
+        return;
+    };
+    let selection = replace::selection_from_bytes(&buffer.lines, start, end);
+    set_selection(instance, selection);
+}
+
+/// Replace or remove the pair of the character around the cursor
+fn replace_surround(
+    state: &mut EditorState,
+    c: char,
+    replacement: Option<char>,
+    lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    let (open, close) = pairs::pair_of(&state.preferences.auto_pairs, c);
+    let replacement = replacement.map(|c| pairs::pair_of(&state.preferences.auto_pairs, c));
+    let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+    let byte = buffer.byte_index_from_cursor(&instance.cursor, "\n");
+    let content = buffer.get_content("\n".to_string());
+    let Some((start, end)) = pairs::find_surround(&content, byte, open, close) else {
+        state.notify(
+            NotificationLevel::Info,
+            format!("No {}{} around the cursor", open, close),
+        );
+        return;
+    };
+    // The closing character is replaced first so that the opening offset stays valid
+    let edits = [
+        (end, close, replacement.map(|(_, close)| close)),
+        (start, open, replacement.map(|(open, _)| open)),
+    ];
+    for (byte, old, new) in edits {
+        let selection = replace::selection_from_bytes(&buffer.lines, byte, byte + old.len_utf8());
+        let (_text, cursor) = buffer.remove_text(&selection, lsp_handle, true);
+        if let Some(new) = new {
+            buffer.insert_text(&new.to_string(), &cursor, lsp_handle, true);
+        }
+    }
+    let cursor = replace::cursor_from_byte(&buffer.lines, start);
+    set_selection(
+        instance,
+        Selection {
+            mark: cursor,
+            cursor,
+        },
+    );
+}
+
+fn set_selection(instance: &mut BufferInstance, selection: Selection) {
+    instance.selection = selection;
+    instance.cursor = selection.cursor;
+    instance.column_level = instance.cursor.column;
+}
+
+/// Move the cursor to the header of a fold which was collapsed around it
+fn move_to_fold(buffer: &LineBuffer, instance: &mut BufferInstance, row: usize) {
+    instance.cursor = Cursor {
+        row,
+        column: instance.cursor.column.min(buffer.get_line_length(row)),
+    };
+    instance.selection.cursor = instance.cursor;
+    instance.selection.mark = instance.cursor;
+}
+
+/// Track the last edit for `RepeatLastChange`.
+/// Everything from entering insert mode until leaving it is a single edit.
+fn record_edit(state: &mut EditorState, action: &Action) {
+    let is_edit = |action: &Action| {
+        matches!(
+            action,
+            Action::InsertTextAtCursor(_)
+                | Action::InsertNewLineAtCursor
+                | Action::AddNewLineBelowAndEnterInsertMode
+                | Action::AddIndent
+                | Action::RemoveIndent
+                | Action::DeletePreviousCharacter
+                | Action::DeleteNextCharacter
+                | Action::DeleteSelection
+                | Action::AddTab
+                | Action::PasteFromClipboard
+                | Action::PasteFromRegister
+                | Action::PasteFromHistory(_)
+                | Action::CyclePaste
+                | Action::CutToRegister
+                | Action::CutToClipboard
+                | Action::RevertHunk
+                | Action::ConflictKeepOurs
+                | Action::ConflictKeepTheirs
+                | Action::ConflictKeepBoth
+                | Action::ReplaceInBuffer(..)
+                | Action::ReplaceInSelection(..)
+                | Action::ReplaceStep(_)
+                | Action::ApplyWorkspaceReplace
+                | Action::ToggleCheckbox
+                | Action::PromoteHeading
+                | Action::DemoteHeading
+                | Action::RenumberList
+                | Action::GenerateDocComment
+                | Action::ExpandSnippet(_)
+                | Action::SurroundSelection(_)
+                | Action::ChangeSurround(..)
+                | Action::DeleteSurround(_)
+                | Action::FilterSelectionThroughCommand(_)
+                | Action::SortSelectedLines
+                | Action::SortSelectedLinesNumeric
+                | Action::SortSelectedLinesCaseInsensitive
+                | Action::UniqueSelectedLines
+                | Action::ReverseSelectedLines
+        )
+    };
+
+    match state.mode {
+        Mode::Insert => {
+            let Some(recording) = state.edit_recording.as_mut() else {
+                return;
+            };
+            match action {
+                Action::QuitInsertMode => {
+                    let mut recording = state.edit_recording.take().unwrap();
+                    if recording.iter().any(is_edit) {
+                        recording.push(Action::QuitInsertMode);
+                        state.last_edit = recording;
+                    }
+                }
+                // Typed characters are merged into a single insertion
+                Action::InsertTextAtCursor(text) => match recording.last_mut() {
+                    Some(Action::InsertTextAtCursor(previous)) => previous.push_str(text),
+                    _ => recording.push(action.clone()),
+                },
+                Action::MoveCursorDown
+                | Action::MoveCursorUp
+                | Action::MoveCursorLeft
+                | Action::MoveCursorRight
+                | Action::MoveCursorLineStart
+                | Action::MoveCursorLineEnd => recording.push(action.clone()),
+                action if is_edit(action) => recording.push(action.clone()),
+                _ => {}
+            }
+        }
+        Mode::Normal => {
+            state.edit_recording = None;
+            match action {
+                Action::EnterInsertMode
+                | Action::AddNewLineBelowAndEnterInsertMode
+                | Action::InsertAfterSelection => {
+                    state.edit_recording = Some(vec![action.clone()]);
+                }
+                action if is_edit(action) => state.last_edit = vec![action.clone()],
+                _ => {}
+            }
+        }
+        Mode::Terminal | Mode::Custom(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{perform_action, Action};
+    use crate::{
+        buffer::{instance::Cursor, line_buffer::LineBuffer},
+        session,
+        state::EditorState,
+    };
+
+    #[test]
+    fn repeat_insert_session() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        let id = state.add_buffer(LineBuffer::new("x\ny\n".into(), None));
+        state.buffer_idx = Some(id);
+
+        for action in [
+            Action::EnterInsertMode,
+            Action::InsertTextAtCursor("a".into()),
+            Action::InsertTextAtCursor("b".into()),
+            Action::QuitInsertMode,
+            Action::MoveCursorDown,
+            Action::MoveCursorLineStart,
+            Action::RepeatLastChange,
+        ] {
+            perform_action(action, &mut state, &mut None);
+        }
+        let (buffer, _instance) = state.get_buffer_by_id(id);
+        assert_eq!(buffer.get_content("\n".into()), "abx\naby\n");
+        assert_eq!(state.last_edit.len(), 3);
+    }
+
+    #[test]
+    fn continue_markdown_lists() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        let id = state.add_buffer(LineBuffer::new("- a\n".into(), Some("notes.md".into())));
+        state.buffer_idx = Some(id);
+        for action in [
+            Action::EnterInsertMode,
+            Action::MoveCursorLineEnd,
+            Action::InsertNewLineAtCursor,
+            Action::InsertTextAtCursor("b".into()),
+            Action::InsertNewLineAtCursor,
+            Action::InsertNewLineAtCursor,
+        ] {
+            perform_action(action, &mut state, &mut None);
+        }
+        let (buffer, _instance) = state.get_buffer_by_id(id);
+        assert_eq!(buffer.get_content("\n".into()), "- a\n- b\n\n");
+
+        let id = state.add_buffer(LineBuffer::new(
+            "1. a\n2. b\n".into(),
+            Some("list.md".into()),
+        ));
+        state.buffer_idx = Some(id);
+        for action in [
+            Action::MoveCursorLineEnd,
+            Action::InsertNewLineAtCursor,
+            Action::InsertTextAtCursor("c".into()),
+        ] {
+            perform_action(action, &mut state, &mut None);
+        }
+        let (buffer, _instance) = state.get_buffer_by_id(id);
+        assert_eq!(buffer.get_content("\n".into()), "1. a\n2. c\n3. b\n");
+    }
+
+    #[test]
+    fn scroll_onto_multibyte_line() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        let id = state.add_buffer(LineBuffer::new(
+            "abcd
+ééé
+abcd
+"
+            .into(),
+            None,
+        ));
+        state.buffer_idx = Some(id);
+        let (_buffer, instance) = state.get_buffer_by_id_mut(id);
+        instance.cursor.column = 3;
+        instance.column_level = 3;
+
+        perform_action(Action::ScrollDown(1), &mut state, &mut None);
+        let (_buffer, instance) = state.get_buffer_by_id(id);
+        // Byte 3 is inside the second `é`
+        assert_eq!(instance.cursor, Cursor { row: 1, column: 2 });
+
+        perform_action(Action::ScrollDown(1), &mut state, &mut None);
+        let (_buffer, instance) = state.get_buffer_by_id(id);
+        assert_eq!(instance.cursor, Cursor { row: 2, column: 3 });
+
+        perform_action(Action::ScrollUp(1), &mut state, &mut None);
+        let (_buffer, instance) = state.get_buffer_by_id(id);
+        assert_eq!(instance.cursor, Cursor { row: 1, column: 2 });
+    }
+
+    #[test]
+    fn force_quit_with_unsaved_changes() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        let mut buffer = LineBuffer::new("unsaved\n".into(), None);
+        buffer.modified = true;
+        let id = state.add_buffer(buffer);
+        state.buffer_idx = Some(id);
+
+        perform_action(Action::Quit, &mut state, &mut None);
+        assert!(!state.quit);
+        assert!(state.confirm_dialog.is_some());
+
+        state.confirm_dialog = None;
+        perform_action(Action::ForceQuit, &mut state, &mut None);
+        assert!(state.quit);
+        assert!(state.confirm_dialog.is_none());
+        let (buffer, _instance) = state.get_buffer_by_id(id);
+        assert!(buffer.modified);
+        // The session is saved under the config directory of the tests
+        assert!(session::session_path(&state.workspace_folder)
+            .unwrap()
+            .starts_with(std::env::temp_dir()));
     }
 }
@@ -0,0 +1,202 @@
+//! Rectangular (block) selections covering the same screen columns of consecutive lines,
+//! for editing aligned columns of text.
+
+use crate::{
+    buffer::{
+        graphemes,
+        instance::{Cursor, Selection},
+        line_buffer::LineBuffer,
+    },
+    lsp::client::LSPClientHandle,
+};
+
+/// Row and byte columns from start to end covered on each line of the block, which spans
+/// the screen columns between the mark and the cursor. Lines ending before the block are skipped.
+pub fn block_columns(buffer: &LineBuffer, selection: &Selection) -> Vec<(usize, usize, usize)> {
+    let (start, end) = selection.in_order();
+    let mark = buffer.visual_column(&selection.mark);
+    let cursor = buffer.visual_column(&selection.cursor);
+    let (left, right) = (mark.min(cursor), mark.max(cursor));
+    (start.row..=end.row)
+        .filter(|row| {
+            let line_end = Cursor {
+                row: *row,
+                column: buffer.get_line_length(*row),
+            };
+            buffer.visual_column(&line_end) >= left
+        })
+        .map(|row| {
+            (
+                row,
+                buffer.column_at_visual(row, left),
+                buffer.column_at_visual(row, right),
+            )
+        })
+        .collect()
+}
+
+/// Text of the block, a line for each of its lines
+pub fn block_text(buffer: &LineBuffer, selection: &Selection) -> String {
+    block_columns(buffer, selection)
+        .into_iter()
+        .map(|(row, start, end)| &buffer.lines[row][start..end])
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Selection with the mark and cursor moved to the columns computed for their rows
+fn move_ends(selection: &Selection, column: impl Fn(&Cursor) -> usize) -> Selection {
+    Selection {
+        mark: Cursor {
+            row: selection.mark.row,
+            column: column(&selection.mark),
+        },
+        cursor: Cursor {
+            row: selection.cursor.row,
+            column: column(&selection.cursor),
+        },
+    }
+}
+
+/// Remove the text of the block, returns it and the block narrowed to its left edge
+pub fn delete_block(
+    buffer: &mut LineBuffer,
+    selection: &Selection,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) -> (String, Selection) {
+    let text = block_text(buffer, selection);
+    let columns = block_columns(buffer, selection);
+    for (row, start, end) in &columns {
+        if end > start {
+            let range = Selection {
+                mark: Cursor {
+                    row: *row,
+                    column: *start,
+                },
+                cursor: Cursor {
+                    row: *row,
+                    column: *end,
+                },
+            };
+            buffer.remove_text(&range, lsp_handle, true);
+        }
+    }
+    let selection = move_ends(selection, |cursor| {
+        columns
+            .iter()
+            .find(|(row, _, _)| *row == cursor.row)
+            .map_or(cursor.column, |(_, start, _)| *start)
+    });
+    (text, selection)
+}
+
+/// Replace the block with the text on each of its lines, returns the block after the text
+pub fn insert_in_block(
+    buffer: &mut LineBuffer,
+    selection: &Selection,
+    text: &str,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) -> Selection {
+    let (_text, selection) = delete_block(buffer, selection, lsp_handle);
+    for (row, start, _) in block_columns(buffer, &selection) {
+        buffer.insert_text(text, &Cursor { row, column: start }, lsp_handle, true);
+    }
+    move_ends(&selection, |cursor| cursor.column + text.len())
+}
+
+/// Remove the character before the block on each of its lines,
+/// or the block itself if it is wider than a column
+pub fn delete_before_block(
+    buffer: &mut LineBuffer,
+    selection: &Selection,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) -> Selection {
+    if buffer.visual_column(&selection.mark) != buffer.visual_column(&selection.cursor) {
+        return delete_block(buffer, selection, lsp_handle).1;
+    }
+    let columns = block_columns(buffer, selection);
+    let moved = move_ends(selection, |cursor| {
+        graphemes::prev_boundary(&buffer.lines[cursor.row], cursor.column)
+    });
+    for (row, start, _) in columns {
+        let range = Selection {
+            mark: Cursor {
+                row,
+                column: graphemes::prev_boundary(&buffer.lines[row], start),
+            },
+            cursor: Cursor { row, column: start },
+        };
+        if range.mark != range.cursor {
+            buffer.remove_text(&range, lsp_handle, true);
+        }
+    }
+    moved
+}
+
+/// Insert each line of the content at the screen column of the cursor on consecutive lines,
+/// padding short lines with spaces and adding lines at the end of the buffer as needed
+pub fn paste_block(
+    buffer: &mut LineBuffer,
+    cursor: &Cursor,
+    content: &str,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) {
+    let visual = buffer.visual_column(cursor);
+    for (offset, text) in content.trim_end_matches('\n').split('\n').enumerate() {
+        let row = cursor.row + offset;
+        if row >= buffer.get_num_lines() {
+            let last = buffer.get_num_lines() - 1;
+            let end = Cursor {
+                row: last,
+                column: buffer.get_line_length(last),
+            };
+            buffer.insert_text("\n", &end, lsp_handle, true);
+        }
+        let end = Cursor {
+            row,
+            column: buffer.get_line_length(row),
+        };
+        let width = buffer.visual_column(&end);
+        if width < visual {
+            buffer.insert_text(&" ".repeat(visual - width), &end, lsp_handle, true);
+        }
+        let column = buffer.column_at_visual(row, visual);
+        buffer.insert_text(text, &Cursor { row, column }, lsp_handle, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block_text, delete_before_block, insert_in_block, paste_block};
+    use crate::buffer::{
+        instance::{Cursor, Selection},
+        line_buffer::LineBuffer,
+    };
+
+    #[test]
+    fn block_editing() {
+        let mut buffer = LineBuffer::new("abcd\nef\nghij\n".into(), None);
+        let block = Selection {
+            mark: Cursor { row: 0, column: 1 },
+            cursor: Cursor { row: 2, column: 3 },
+        };
+        assert_eq!(block_text(&buffer, &block), "bc\nf\nhi");
+
+        let block = insert_in_block(&mut buffer, &block, "X", &None);
+        assert_eq!(buffer.get_content("\n".into()), "aXd\neX\ngXj\n");
+        assert_eq!(block.mark, Cursor { row: 0, column: 2 });
+        assert_eq!(block.cursor, Cursor { row: 2, column: 2 });
+
+        let block = delete_before_block(&mut buffer, &block, &None);
+        assert_eq!(buffer.get_content("\n".into()), "ad\ne\ngj\n");
+        assert_eq!(block.cursor, Cursor { row: 2, column: 1 });
+
+        paste_block(
+            &mut buffer,
+            &Cursor { row: 1, column: 1 },
+            "12\n34\n56",
+            &None,
+        );
+        assert_eq!(buffer.get_content("\n".into()), "ad\ne12\ng34j\n 56");
+    }
+}
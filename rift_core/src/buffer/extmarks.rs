@@ -0,0 +1,482 @@
+use std::collections::{BTreeMap, HashSet};
+
+use super::{
+    graphemes,
+    instance::{Attribute, Cursor, GutterInfo, HighlightType},
+    line_buffer::HighlightedText,
+    marks::{shift_after_insert, shift_after_remove},
+};
+
+/// Where virtual text is shown relative to the position of its extmark
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum VirtualTextPosition {
+    /// After the end of the line
+    #[default]
+    EndOfLine,
+    /// Before the character at the position
+    Before,
+    /// After the character at the position
+    After,
+    /// Drawn over the characters starting at the position
+    Overlay,
+    /// On a line of its own above the line, such as code lenses
+    Above,
+}
+
+/// Text shown in the editor which is not part of the buffer
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct VirtualText {
+    /// Pieces of the text with their highlight
+    pub chunks: Vec<(String, HighlightType)>,
+    #[serde(default)]
+    pub position: VirtualTextPosition,
+}
+
+impl VirtualText {
+    pub fn new(text: &str, highlight: HighlightType, position: VirtualTextPosition) -> Self {
+        Self {
+            chunks: vec![(text.to_string(), highlight)],
+            position,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.chunks.iter().map(|(text, _)| text.as_str()).collect()
+    }
+
+    fn tokens(&self) -> Vec<(String, HashSet<Attribute>)> {
+        self.chunks
+            .iter()
+            .map(|(text, highlight)| {
+                (
+                    text.clone(),
+                    HashSet::from([Attribute::Highlight(*highlight)]),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Split the token containing the byte offset of the line so a token starts there,
+/// returning the index of that token
+fn split_tokens_at(line: &mut Vec<(String, HashSet<Attribute>)>, offset: usize) -> usize {
+    let mut start = 0;
+    for idx in 0..line.len() {
+        let len = line[idx].0.len();
+        if offset < start + len {
+            let mut at = offset - start;
+            while !line[idx].0.is_char_boundary(at) {
+                at += 1;
+            }
+            if at == 0 {
+                return idx;
+            }
+            let rest = line[idx].0.split_off(at);
+            let attributes = line[idx].1.clone();
+            line.insert(idx + 1, (rest, attributes));
+            return idx + 1;
+        }
+        start += len;
+    }
+    line.len()
+}
+
+/// Icon shown in the sign column of the gutter
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Sign {
+    pub text: char,
+    pub highlight: HighlightType,
+    /// Signs with a higher priority are shown instead of others on the same line
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// Position or range anchored in a buffer with optional decorations,
+/// which follows the text it points at as it is edited
+#[derive(Debug, Clone)]
+pub struct Extmark {
+    /// Owner of the extmark, such as `diagnostics` or the name of a plugin
+    pub namespace: String,
+    pub start: Cursor,
+    /// End of the range, the extmark is a single position when None
+    pub end: Option<Cursor>,
+    pub virtual_text: Option<VirtualText>,
+    /// Highlight of the range
+    pub highlight: Option<HighlightType>,
+    /// Shown in the sign column on the line of the start
+    pub sign: Option<Sign>,
+    /// Stay in place when text is inserted at the start instead of moving after it
+    pub left_gravity: bool,
+}
+
+impl Extmark {
+    pub fn new(namespace: &str, start: Cursor) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            start,
+            end: None,
+            virtual_text: None,
+            highlight: None,
+            sign: None,
+            left_gravity: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Extmarks {
+    marks: BTreeMap<u32, Extmark>,
+    next_id: u32,
+    /// Incremented when extmarks are added or removed
+    pub version: usize,
+}
+
+impl Extmarks {
+    /// Add an extmark, returning its id
+    pub fn add(&mut self, extmark: Extmark) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.version += 1;
+        self.marks.insert(id, extmark);
+        id
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Extmark> {
+        self.marks.get(&id)
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Extmark> {
+        self.version += 1;
+        self.marks.remove(&id)
+    }
+
+    pub fn clear_namespace(&mut self, namespace: &str) {
+        self.version += 1;
+        self.marks
+            .retain(|_, extmark| extmark.namespace != namespace);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Extmark)> {
+        self.marks.iter().map(|(id, extmark)| (*id, extmark))
+    }
+
+    /// Extmarks whose start is on the row
+    pub fn on_row(&self, row: usize) -> impl Iterator<Item = &Extmark> {
+        self.marks
+            .values()
+            .filter(move |extmark| extmark.start.row == row)
+    }
+
+    /// End of line virtual text of the extmarks on the row, joined in the order they were added
+    pub fn virtual_text(&self, row: usize) -> Option<String> {
+        let texts: Vec<String> = self
+            .on_row(row)
+            .filter_map(|extmark| extmark.virtual_text.as_ref())
+            .filter(|text| text.position == VirtualTextPosition::EndOfLine)
+            .map(VirtualText::text)
+            .collect();
+        (!texts.is_empty()).then(|| texts.join("  "))
+    }
+
+    /// Place a sign on the row, returning the id of its extmark
+    pub fn place_sign(&mut self, namespace: &str, row: usize, sign: Sign) -> u32 {
+        self.add(Extmark {
+            sign: Some(sign),
+            ..Extmark::new(namespace, Cursor { row, column: 0 })
+        })
+    }
+
+    /// Remove the signs of the namespace, only those on the row if one is given
+    pub fn remove_signs(&mut self, namespace: &str, row: Option<usize>) {
+        self.version += 1;
+        self.marks.retain(|_, extmark| {
+            extmark.namespace != namespace
+                || extmark.sign.is_none()
+                || row.is_some_and(|row| extmark.start.row != row)
+        });
+    }
+
+    /// Sign with the highest priority on the row, the most recently placed one on ties
+    pub fn sign(&self, row: usize) -> Option<Sign> {
+        self.on_row(row)
+            .filter_map(|extmark| extmark.sign)
+            .max_by_key(|sign| sign.priority)
+    }
+
+    /// Add an extmark with virtual text at the position, returning its id
+    pub fn add_virtual_text(
+        &mut self,
+        namespace: &str,
+        position: Cursor,
+        text: VirtualText,
+    ) -> u32 {
+        self.add(Extmark {
+            virtual_text: Some(text),
+            ..Extmark::new(namespace, position)
+        })
+    }
+
+    /// Remove the extmarks of the namespace which have virtual text
+    pub fn remove_virtual_text(&mut self, namespace: &str) {
+        self.version += 1;
+        self.marks
+            .retain(|_, extmark| extmark.namespace != namespace || extmark.virtual_text.is_none());
+    }
+
+    /// Merge the inline virtual text and the virtual lines into the visible lines from
+    /// `get_visible_lines`, keeping the relative cursor on the same character and
+    /// at most `visible_lines` lines with the cursor among them
+    pub fn decorate_lines(
+        &self,
+        lines: &mut HighlightedText,
+        gutter_info: &mut Vec<GutterInfo>,
+        relative_cursor: &mut Cursor,
+        visible_lines: usize,
+        tab_width: usize,
+    ) {
+        if !self
+            .marks
+            .values()
+            .any(|extmark| extmark.virtual_text.is_some())
+        {
+            return;
+        }
+        for idx in (0..gutter_info.len().min(lines.len())).rev() {
+            let info = gutter_info[idx];
+            let line_text: String = lines[idx].iter().map(|token| token.0.as_str()).collect();
+            let mut inline: Vec<(usize, &VirtualText)> = self
+                .on_row(info.start.row)
+                .filter(|extmark| {
+                    extmark.start.column >= info.start.column
+                        && (extmark.start.column < info.end
+                            || (info.wrap_end && extmark.start.column == info.end))
+                })
+                .filter_map(|extmark| {
+                    let text = extmark.virtual_text.as_ref()?;
+                    let offset = extmark.start.column - info.start.column;
+                    match text.position {
+                        VirtualTextPosition::Before | VirtualTextPosition::Overlay => {
+                            Some((offset, text))
+                        }
+                        VirtualTextPosition::After => {
+                            let next = line_text
+                                .get(offset..)
+                                .and_then(|rest| rest.chars().next())
+                                .map_or(0, char::len_utf8);
+                            Some((offset + next, text))
+                        }
+                        VirtualTextPosition::EndOfLine | VirtualTextPosition::Above => None,
+                    }
+                })
+                .collect();
+            // Later offsets first so the earlier ones stay valid
+            inline.sort_by_key(|(offset, _)| std::cmp::Reverse(*offset));
+            for (offset, text) in inline {
+                let line = &mut lines[idx];
+                let start = split_tokens_at(line, offset);
+                if text.position == VirtualTextPosition::Overlay {
+                    let end = split_tokens_at(line, offset + text.text().len());
+                    line.splice(start..end, text.tokens());
+                } else {
+                    line.splice(start..start, text.tokens());
+                    let column = line_text
+                        .get(..offset)
+                        .map_or(offset, |text| graphemes::visual_width(text, 0, tab_width));
+                    if idx == relative_cursor.row && column <= relative_cursor.column {
+                        relative_cursor.column += graphemes::width(&text.text());
+                    }
+                }
+            }
+
+            if info.wrapped {
+                continue;
+            }
+            let indent: String = line_text
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .collect();
+            let above: Vec<&VirtualText> = self
+                .on_row(info.start.row)
+                .filter_map(|extmark| extmark.virtual_text.as_ref())
+                .filter(|text| text.position == VirtualTextPosition::Above)
+                .collect();
+            for text in above.into_iter().rev() {
+                let mut tokens = text.tokens();
+                tokens.insert(0, (indent.clone(), HashSet::new()));
+                lines.insert(idx, tokens);
+                gutter_info.insert(
+                    idx,
+                    GutterInfo {
+                        end: info.start.column,
+                        wrapped: true,
+                        wrap_end: false,
+                        end_byte: info.start_byte,
+                        virtual_line: true,
+                        ..info
+                    },
+                );
+                if idx <= relative_cursor.row {
+                    relative_cursor.row += 1;
+                }
+            }
+        }
+
+        if lines.len() > visible_lines {
+            let excess = (relative_cursor.row + 1).saturating_sub(visible_lines);
+            lines.drain(..excess);
+            gutter_info.drain(..excess);
+            relative_cursor.row -= excess;
+            lines.truncate(visible_lines);
+            gutter_info.truncate(visible_lines);
+        }
+    }
+
+    /// Shift extmarks after text inserted from start to end.
+    /// Ends stay in place when text is inserted at them, so ranges do not grow.
+    pub fn adjust_insert(&mut self, start: &Cursor, end: &Cursor) {
+        for extmark in self.marks.values_mut() {
+            shift_after_insert(&mut extmark.start, start, end, !extmark.left_gravity);
+            if let Some(range_end) = &mut extmark.end {
+                shift_after_insert(range_end, start, end, false);
+                if *range_end < extmark.start {
+                    *range_end = extmark.start;
+                }
+            }
+        }
+    }
+
+    /// Shift extmarks after the text from start to end was removed
+    pub fn adjust_remove(&mut self, start: &Cursor, end: &Cursor) {
+        for extmark in self.marks.values_mut() {
+            shift_after_remove(&mut extmark.start, start, end);
+            if let Some(range_end) = &mut extmark.end {
+                shift_after_remove(range_end, start, end);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Extmark, Extmarks, Sign, VirtualText, VirtualTextPosition};
+    use crate::buffer::{
+        folding::Folds,
+        instance::{Cursor, HighlightType, Selection},
+        line_buffer::{LineBuffer, VisibleLinesView},
+    };
+
+    #[test]
+    fn extmarks_follow_edits() {
+        let cursor = |row, column| Cursor { row, column };
+        let sign = |text, priority| Sign {
+            text,
+            highlight: HighlightType::Red,
+            priority,
+        };
+        let mut buffer = LineBuffer::new("let a = 1;\nlet b = 2;\n".into(), None);
+        let range = buffer.extmarks.add(Extmark {
+            end: Some(cursor(1, 5)),
+            virtual_text: Some(VirtualText::new(
+                "b: i32",
+                HighlightType::Gray,
+                VirtualTextPosition::EndOfLine,
+            )),
+            sign: Some(sign('!', 0)),
+            ..Extmark::new("test", cursor(1, 4))
+        });
+        let fixed = buffer.extmarks.add(Extmark {
+            left_gravity: true,
+            ..Extmark::new("other", cursor(0, 4))
+        });
+
+        buffer.insert_text("x\n", &cursor(0, 4), &None, true);
+        let extmark = buffer.extmarks.get(range).unwrap();
+        assert_eq!(extmark.start, cursor(2, 4));
+        assert_eq!(extmark.end, Some(cursor(2, 5)));
+        assert_eq!(buffer.extmarks.get(fixed).unwrap().start, cursor(0, 4));
+        assert_eq!(buffer.extmarks.virtual_text(2).as_deref(), Some("b: i32"));
+        assert_eq!(buffer.extmarks.sign(2), Some(sign('!', 0)));
+
+        // Text inserted at the end does not extend the range
+        buffer.insert_text("c", &cursor(2, 5), &None, true);
+        assert_eq!(buffer.extmarks.get(range).unwrap().end, Some(cursor(2, 5)));
+
+        buffer.undo(&None);
+        buffer.undo(&None);
+        assert_eq!(buffer.extmarks.get(range).unwrap().start, cursor(1, 4));
+
+        // The sign with the highest priority is shown
+        buffer.extmarks.place_sign("git", 1, sign('+', 10));
+        buffer.extmarks.place_sign("lint", 1, sign('w', 5));
+        assert_eq!(buffer.extmarks.sign(1), Some(sign('+', 10)));
+        buffer.extmarks.remove_signs("git", Some(1));
+        assert_eq!(buffer.extmarks.sign(1), Some(sign('w', 5)));
+
+        buffer.extmarks.clear_namespace("test");
+        assert!(buffer.extmarks.get(range).is_none());
+        assert_eq!(Extmarks::default().sign(0), None);
+    }
+
+    #[test]
+    fn virtual_text_in_visible_lines() {
+        let cursor = |row, column| Cursor { row, column };
+        let mut buffer = LineBuffer::new("fn main() {\n    let a = 1;\n}\n".into(), None);
+        let text = |text: &str, position| VirtualText::new(text, HighlightType::Gray, position);
+        buffer.extmarks.add_virtual_text(
+            "lens",
+            cursor(1, 4),
+            text("2 references", VirtualTextPosition::Above),
+        );
+        buffer.extmarks.add_virtual_text(
+            "hints",
+            cursor(1, 9),
+            text(": i32", VirtualTextPosition::Before),
+        );
+        buffer.extmarks.add_virtual_text(
+            "hints",
+            cursor(1, 12),
+            text("0", VirtualTextPosition::Overlay),
+        );
+
+        let position = cursor(1, 12);
+        let mut view = |visible_lines| {
+            let (mut lines, mut relative_cursor, mut gutter_info) =
+                buffer.get_visible_lines(VisibleLinesView {
+                    scroll: &mut cursor(0, 0),
+                    cursor: &position,
+                    selection: &Selection {
+                        cursor: position,
+                        mark: position,
+                    },
+                    visible_lines,
+                    max_characters: 80,
+                    eol_sequence: "\n".into(),
+                    extra_segments: vec![],
+                    folds: &mut Folds::default(),
+                });
+            buffer.decorate_lines(
+                &mut lines,
+                &mut gutter_info,
+                &mut relative_cursor,
+                visible_lines,
+            );
+            (lines, relative_cursor, gutter_info)
+        };
+
+        let (lines, relative_cursor, gutter_info) = view(10);
+
+        let text_of = |line: &Vec<(String, _)>| -> String {
+            line.iter().map(|token| token.0.as_str()).collect()
+        };
+        assert_eq!(text_of(&lines[1]), "    2 references");
+        assert!(gutter_info[1].virtual_line);
+        assert_eq!(text_of(&lines[2]), "    let a: i32 = 0;");
+        assert_eq!(relative_cursor, cursor(2, 17));
+
+        // Lines past the visible ones are dropped, keeping the cursor visible
+        let (lines, relative_cursor, _gutter_info) = view(2);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(text_of(&lines[0]), "    2 references");
+        assert_eq!(relative_cursor.row, 1);
+    }
+}
@@ -0,0 +1,289 @@
+//! Code folding: foldable ranges from the syntax tree (or the language server)
+//! and the folds collapsed in a buffer instance, kept on their lines through edits.
+
+use std::collections::VecDeque;
+
+use tree_sitter::{Node, Parser};
+
+use super::instance::Language;
+
+/// Number of line shifts remembered for instances which have not caught up yet
+const LINE_SHIFTS_SIZE: usize = 1000;
+
+/// Range of rows which can be folded, the first row stays visible as the header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Fold {
+    pub start: usize,
+    /// Last row of the fold (inclusive)
+    pub end: usize,
+}
+
+impl Fold {
+    pub fn contains(&self, row: usize) -> bool {
+        self.start <= row && row <= self.end
+    }
+
+    /// Returns true if the row is hidden when the fold is collapsed
+    pub fn hides(&self, row: usize) -> bool {
+        self.start < row && row <= self.end
+    }
+}
+
+/// Lines added after a row (positive delta) or the lines after it merged into it (negative delta)
+#[derive(Debug, Clone, Copy)]
+struct LineShift {
+    row: usize,
+    delta: isize,
+}
+
+/// Recent changes in the number of lines of a buffer
+#[derive(Debug, Clone, Default)]
+pub struct LineShifts {
+    shifts: VecDeque<LineShift>,
+    /// Number of shifts recorded since the buffer was created
+    total: usize,
+}
+
+impl LineShifts {
+    pub fn push(&mut self, row: usize, delta: isize) {
+        if delta == 0 {
+            return;
+        }
+        self.shifts.push_back(LineShift { row, delta });
+        if self.shifts.len() > LINE_SHIFTS_SIZE {
+            self.shifts.pop_front();
+        }
+        self.total += 1;
+    }
+}
+
+/// Folds collapsed in a buffer instance
+#[derive(Debug, Clone, Default)]
+pub struct Folds {
+    pub collapsed: Vec<Fold>,
+    /// Number of line shifts of the buffer applied to the folds
+    synced: usize,
+}
+
+impl Folds {
+    /// Move the folds along with the lines added and removed since the last sync.
+    /// Folds are dropped if the instance fell too far behind.
+    pub fn sync(&mut self, line_shifts: &LineShifts) {
+        let pending = line_shifts.total - self.synced;
+        self.synced = line_shifts.total;
+        if pending == 0 {
+            return;
+        }
+        if pending > line_shifts.shifts.len() {
+            self.collapsed.clear();
+            return;
+        }
+        for shift in line_shifts
+            .shifts
+            .range(line_shifts.shifts.len() - pending..)
+        {
+            self.collapsed = self
+                .collapsed
+                .iter()
+                .filter_map(|fold| shift_fold(*fold, shift))
+                .collect();
+        }
+    }
+
+    /// Returns true if the row is hidden by a collapsed fold.
+    /// Folds containing the cursor row are shown open.
+    pub fn is_hidden(&self, row: usize, cursor_row: usize) -> bool {
+        self.collapsed
+            .iter()
+            .any(|fold| fold.hides(row) && !fold.hides(cursor_row))
+    }
+
+    /// Returns true if the row is inside a collapsed fold
+    pub fn is_folded(&self, row: usize) -> bool {
+        self.collapsed.iter().any(|fold| fold.hides(row))
+    }
+
+    /// Returns true if the row is the header of a collapsed fold
+    pub fn is_collapsed(&self, row: usize) -> bool {
+        self.collapsed.iter().any(|fold| fold.start == row)
+    }
+
+    /// Open the innermost collapsed fold containing the row, or collapse the innermost
+    /// foldable range containing it and return its header row
+    pub fn toggle(&mut self, ranges: &[Fold], row: usize) -> Option<usize> {
+        let innermost = |folds: &mut dyn Iterator<Item = (usize, &Fold)>| {
+            folds
+                .filter(|(_, fold)| fold.contains(row))
+                .max_by_key(|(_, fold)| (fold.start, usize::MAX - fold.end))
+                .map(|(idx, _)| idx)
+        };
+        if let Some(idx) = innermost(&mut self.collapsed.iter().enumerate()) {
+            self.collapsed.remove(idx);
+            return None;
+        }
+        let fold = ranges[innermost(&mut ranges.iter().enumerate())?];
+        self.collapsed.push(fold);
+        Some(fold.start)
+    }
+
+    /// Collapse all the ranges and return the header of the outermost fold hiding the row
+    pub fn fold_all(&mut self, ranges: &[Fold], row: usize) -> Option<usize> {
+        self.collapsed = ranges.to_vec();
+        self.collapsed
+            .iter()
+            .filter(|fold| fold.hides(row))
+            .map(|fold| fold.start)
+            .min()
+    }
+
+    /// Up to `count` rows which are not hidden starting from the row
+    pub fn visible_rows(
+        &self,
+        from: usize,
+        count: usize,
+        cursor_row: usize,
+        num_lines: usize,
+    ) -> Vec<usize> {
+        let rows: Vec<usize> = (from..num_lines)
+            .filter(|row| !self.is_hidden(*row, cursor_row))
+            .take(count)
+            .collect();
+        if rows.is_empty() {
+            return vec![from.min(num_lines.saturating_sub(1))];
+        }
+        rows
+    }
+
+    /// Row `count` visible rows above the row
+    pub fn row_above(&self, row: usize, count: usize, cursor_row: usize) -> usize {
+        (0..row)
+            .rev()
+            .filter(|row| !self.is_hidden(*row, cursor_row))
+            .take(count)
+            .last()
+            .unwrap_or(row)
+    }
+
+    /// Number of visible rows from `from` up to (excluding) `to`
+    pub fn visible_between(&self, from: usize, to: usize, cursor_row: usize) -> usize {
+        (from..to)
+            .filter(|row| !self.is_hidden(*row, cursor_row))
+            .count()
+    }
+}
+
+fn shift_fold(fold: Fold, shift: &LineShift) -> Option<Fold> {
+    let Fold { start, end } = fold;
+    let row = shift.row;
+    let count = shift.delta.unsigned_abs();
+    if shift.delta > 0 {
+        return Some(if row < start {
+            Fold {
+                start: start + count,
+                end: end + count,
+            }
+        } else if row < end {
+            Fold {
+                start,
+                end: end + count,
+            }
+        } else {
+            fold
+        });
+    }
+    // Rows row + 1 ..= row + count were merged into the row
+    if row + count < start {
+        Some(Fold {
+            start: start - count,
+            end: end - count,
+        })
+    } else if row < start {
+        None
+    } else if row < end {
+        let end = if row + count >= end { row } else { end - count };
+        (end > start).then_some(Fold { start, end })
+    } else {
+        Some(fold)
+    }
+}
+
+fn collect_ranges(node: Node, language: Language, ranges: &mut Vec<Fold>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let start = child.start_position().row;
+        let end = child.end_position();
+        // Nodes ending at the start of a line do not include that line
+        let end = if end.column == 0 {
+            end.row.saturating_sub(1)
+        } else {
+            end.row
+        };
+        // Python blocks start on the line after their header
+        let skipped = language == Language::Python && child.kind() == "block";
+        if end > start && !skipped {
+            ranges.push(Fold { start, end });
+        }
+        collect_ranges(child, language, ranges);
+    }
+}
+
+/// Foldable ranges of the source from its syntax tree, the largest range starting on each row.
+/// None for languages without a parser.
+pub fn syntax_fold_ranges(language: Language, source: &str) -> Option<Vec<Fold>> {
+    let ts_language = match language {
+        Language::Rust => tree_sitter_rust::LANGUAGE.into(),
+        Language::Python => tree_sitter_python::LANGUAGE.into(),
+        _ => return None,
+    };
+    let mut parser = Parser::new();
+    parser.set_language(&ts_language).ok()?;
+    let tree = parser.parse(source, None)?;
+
+    let mut ranges = vec![];
+    collect_ranges(tree.root_node(), language, &mut ranges);
+    ranges.sort_by_key(|fold| (fold.start, usize::MAX - fold.end));
+    ranges.dedup_by_key(|fold| fold.start);
+    Some(ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{syntax_fold_ranges, Fold, Folds, LineShifts};
+    use crate::buffer::instance::Language;
+
+    #[test]
+    fn folds() {
+        let source = "fn a() {\n    if b {\n        c();\n    }\n}\n\nfn d() {}\n";
+        let ranges = syntax_fold_ranges(Language::Rust, source).unwrap();
+        assert_eq!(
+            ranges,
+            vec![Fold { start: 0, end: 4 }, Fold { start: 1, end: 3 }]
+        );
+        assert!(syntax_fold_ranges(Language::PlainText, source).is_none());
+
+        let mut folds = Folds::default();
+        assert_eq!(folds.toggle(&ranges, 2), Some(1));
+        assert!(folds.is_collapsed(1));
+        assert!(folds.is_hidden(3, 1));
+        assert!(!folds.is_hidden(3, 2));
+        assert!(folds.is_folded(3));
+        assert_eq!(folds.visible_rows(0, 3, 1, 7), vec![0, 1, 4]);
+        assert_eq!(folds.row_above(4, 2, 1), 0);
+        assert_eq!(folds.visible_between(0, 5, 1), 3);
+        assert_eq!(folds.toggle(&ranges, 1), None);
+        assert!(folds.collapsed.is_empty());
+        assert_eq!(folds.fold_all(&ranges, 2), Some(0));
+
+        let mut line_shifts = LineShifts::default();
+        line_shifts.push(0, 2);
+        folds.sync(&line_shifts);
+        assert_eq!(
+            folds.collapsed,
+            vec![Fold { start: 0, end: 6 }, Fold { start: 3, end: 5 }]
+        );
+        // Removing the header of a fold drops it
+        line_shifts.push(2, -1);
+        folds.sync(&line_shifts);
+        assert_eq!(folds.collapsed, vec![Fold { start: 0, end: 5 }]);
+    }
+}
@@ -0,0 +1,171 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::{
+    instance::{placeholder, Attribute},
+    line_buffer::HighlightedText,
+};
+
+/// Columns the text takes up on screen, wide characters such as CJK and emoji take two
+pub fn width(text: &str) -> usize {
+    text.width()
+}
+
+/// Screen column after the grapheme cluster drawn at the column,
+/// tabs extend to the next multiple of the tab width
+fn advance(column: usize, grapheme: &str, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        column + tab_width - column % tab_width
+    } else {
+        column + grapheme.width()
+    }
+}
+
+/// Columns the text takes up on screen when drawn starting at the screen column
+pub fn visual_width(text: &str, column: usize, tab_width: usize) -> usize {
+    text.graphemes(true).fold(column, |column, grapheme| {
+        advance(column, grapheme, tab_width)
+    }) - column
+}
+
+/// Byte column of the grapheme cluster drawn over the screen column,
+/// the end of the line if it is shorter
+pub fn column_at_visual(line: &str, visual: usize, tab_width: usize) -> usize {
+    let mut column = 0;
+    for (start, grapheme) in line.grapheme_indices(true) {
+        column = advance(column, grapheme, tab_width);
+        if column > visual {
+            return start;
+        }
+    }
+    line.len()
+}
+
+/// Expand the tabs of the visible lines to the next tab stop and draw the
+/// invisible characters as their placeholders
+pub fn expand_tabs(lines: &mut HighlightedText, tab_width: usize) {
+    for line in lines {
+        let mut column = 0;
+        for (text, attributes) in line {
+            let invisible = attributes.contains(&Attribute::Invisible);
+            if !invisible && !text.contains('\t') {
+                column += width(text);
+                continue;
+            }
+            let mut shown = String::new();
+            for grapheme in text.graphemes(true) {
+                let next = advance(column, grapheme, tab_width);
+                if grapheme == "\t" {
+                    let pad = next - column - usize::from(invisible);
+                    if invisible {
+                        shown.push('→');
+                    }
+                    shown.extend(std::iter::repeat_n(' ', pad));
+                } else if invisible {
+                    shown.extend(grapheme.chars().map(|c| placeholder(c).unwrap_or(c)));
+                } else {
+                    shown.push_str(grapheme);
+                }
+                column = next;
+            }
+            *text = shown;
+        }
+    }
+}
+
+/// Byte column of the start of the grapheme cluster after the one at the column
+pub fn next_boundary(line: &str, column: usize) -> usize {
+    line.grapheme_indices(true)
+        .map(|(start, grapheme)| start + grapheme.len())
+        .find(|end| *end > column)
+        .unwrap_or(line.len())
+}
+
+/// Byte column of the start of the grapheme cluster before the column
+pub fn prev_boundary(line: &str, column: usize) -> usize {
+    line.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|start| *start < column)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Byte column of the start of the grapheme cluster containing the column
+pub fn floor_boundary(line: &str, column: usize) -> usize {
+    if column >= line.len() {
+        return line.len();
+    }
+    line.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .take_while(|start| *start <= column)
+        .last()
+        .unwrap_or(0)
+}
+
+/// Byte column where a line wrapped at the width continues, the end of the last grapheme
+/// cluster from the start which still fits and at least the end of the first one
+pub fn wrap_end(line: &str, start: usize, max_width: usize, tab_width: usize) -> usize {
+    let mut used = 0;
+    let mut end = start;
+    for grapheme in line[start..].graphemes(true) {
+        used = advance(used, grapheme, tab_width);
+        if used > max_width && end > start {
+            break;
+        }
+        end += grapheme.len();
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{
+        column_at_visual, expand_tabs, floor_boundary, next_boundary, prev_boundary, visual_width,
+        width, wrap_end,
+    };
+    use crate::buffer::instance::Attribute;
+
+    #[test]
+    fn graphemes_and_widths() {
+        // An accent combined with the e, a wide character and an emoji with a modifier
+        let line = "e\u{301}漢👍🏽a";
+        assert_eq!(width(line), 6);
+        assert_eq!(next_boundary(line, 0), 3);
+        assert_eq!(next_boundary(line, 3), 6);
+        assert_eq!(next_boundary(line, 6), 14);
+        assert_eq!(prev_boundary(line, 14), 6);
+        assert_eq!(prev_boundary(line, 3), 0);
+        assert_eq!(floor_boundary(line, 8), 6);
+        assert_eq!(floor_boundary(line, 20), line.len());
+
+        assert_eq!(wrap_end(line, 0, 2, 4), 3);
+        assert_eq!(wrap_end(line, 0, 3, 4), 6);
+        assert_eq!(wrap_end(line, 3, 1, 4), 6);
+        assert_eq!(wrap_end(line, 6, 4, 4), line.len());
+    }
+
+    #[test]
+    fn tabs() {
+        let line = "a\tb\t\tc";
+        assert_eq!(visual_width(line, 0, 4), 13);
+        assert_eq!(visual_width("\t", 2, 4), 2);
+        assert_eq!(column_at_visual(line, 0, 4), 0);
+        assert_eq!(column_at_visual(line, 2, 4), 1);
+        assert_eq!(column_at_visual(line, 4, 4), 2);
+        assert_eq!(column_at_visual(line, 12, 4), 5);
+        assert_eq!(column_at_visual(line, 20, 4), line.len());
+        assert_eq!(wrap_end(line, 0, 5, 4), 3);
+
+        let mut lines = vec![vec![
+            ("a\tb".to_string(), HashSet::new()),
+            ("\t".to_string(), HashSet::from([Attribute::Invisible])),
+            ("c".to_string(), HashSet::new()),
+        ]];
+        expand_tabs(&mut lines, 4);
+        let text: Vec<&str> = lines[0].iter().map(|token| token.0.as_str()).collect();
+        assert_eq!(text, ["a   b", "→  ", "c"]);
+    }
+}
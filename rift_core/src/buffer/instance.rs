@@ -1,6 +1,8 @@
 use std::collections::HashSet;
 
-use crate::lsp::types;
+use crate::{git::conflict::ConflictSide, lsp::types, preferences::LineNumbers};
+
+use super::folding::Folds;
 
 /// Struct representating a position in the buffer
 #[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
@@ -63,6 +65,14 @@ pub enum Edit {
         end: Cursor,
         text: String,
     },
+    /// Text from start to old end replaced by text ending at new end, undone in one step
+    Replace {
+        start: Cursor,
+        old_end: Cursor,
+        new_end: Cursor,
+        old_text: String,
+        text: String,
+    },
 }
 
 /// Gutter Information
@@ -74,6 +84,38 @@ pub struct GutterInfo {
     pub wrap_end: bool,
     pub start_byte: usize,
     pub end_byte: usize,
+    /// Line of virtual text shown above the row, which is not part of the buffer
+    #[serde(default)]
+    pub virtual_line: bool,
+    /// Line number shown in the gutter, set by `number_lines`
+    #[serde(default)]
+    pub number: Option<usize>,
+}
+
+impl GutterInfo {
+    /// Line number column of the gutter, padded to the width
+    pub fn label(&self, width: usize) -> String {
+        match self.number {
+            _ if width == 0 => String::new(),
+            Some(number) => format!("{:>width$}", number),
+            None if self.wrapped && !self.virtual_line => format!("{:>width$}", "."),
+            None => " ".repeat(width),
+        }
+    }
+}
+
+/// Set the line numbers of the gutter of a view with the cursor on the row
+pub fn number_lines(gutter_info: &mut [GutterInfo], cursor_row: usize, mode: LineNumbers) {
+    for info in gutter_info {
+        let row = info.start.row;
+        info.number = match mode {
+            _ if info.wrapped || info.virtual_line => None,
+            LineNumbers::Absolute => Some(row + 1),
+            LineNumbers::Hybrid if row == cursor_row => Some(row + 1),
+            LineNumbers::Relative | LineNumbers::Hybrid => Some(row.abs_diff(cursor_row)),
+            LineNumbers::Off => None,
+        };
+    }
 }
 
 /// File format / language
@@ -84,6 +126,8 @@ pub enum Language {
     Python,
     Markdown,
     TOML,
+    CSS,
+    HTML,
 }
 
 /// Types of highlighted tokens
@@ -101,7 +145,7 @@ pub enum HighlightType {
     Turquoise,
 }
 
-#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Eq, Hash, PartialEq)]
 pub enum Attribute {
     None,
     Visible,
@@ -110,6 +154,17 @@ pub enum Attribute {
     Select,
     Cursor,
     DiagnosticSeverity(types::DiagnosticSeverity),
+    Misspelled,
+    /// Line the debugger is stopped at
+    DebugStop,
+    /// Side of a merge conflict
+    Conflict(ConflictSide),
+    /// Space at the start of a level of indentation, drawn as a guide line
+    IndentGuide,
+    /// Bracket with its nesting depth, colored by the depth
+    Bracket(usize),
+    /// Whitespace or control characters, drawn as placeholders by `expand_tabs`
+    Invisible,
 }
 
 /// Struct representating a position in the buffer
@@ -120,17 +175,46 @@ pub struct Range {
     pub attributes: HashSet<Attribute>,
 }
 
+/// Glyph drawn in place of an invisible character when whitespace is rendered
+pub fn placeholder(c: char) -> Option<char> {
+    match c {
+        '\t' => Some('→'),
+        ' ' => Some('·'),
+        '\u{a0}' => Some('⍽'),
+        '\u{7f}' => Some('␡'),
+        // Control pictures of the C0 control characters
+        '\0'..='\u{1f}' => char::from_u32(0x2400 + c as u32),
+        '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{feff}' => Some('¦'),
+        c if c.is_control() => Some('�'),
+        _ => None,
+    }
+}
+
+/// Text shown for a token, indent guides are drawn in place of the space they are on
+pub fn display_text<'a>(text: &'a str, attributes: &HashSet<Attribute>) -> &'a str {
+    if attributes.contains(&Attribute::IndentGuide) {
+        "│"
+    } else {
+        text
+    }
+}
+
 /// An instance of a buffer (a single buffer can have multiple instances)
 /// Contains a cursor for insert mode,
 /// a selection for normal / visual mode,
 /// scroll position (the line and part of the line at the top of the view)
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BufferInstance {
     pub buffer_id: u32,
     pub cursor: Cursor,
     pub selection: Selection,
     pub scroll: Cursor,
     pub column_level: usize,
+    pub folds: Folds,
+    /// Selections before expanding to parent nodes, restored by shrinking the selection
+    pub expanded_selections: Vec<Selection>,
+    /// The selection is the block of screen columns between the mark and the cursor
+    pub block_selection: bool,
 }
 
 impl BufferInstance {
@@ -144,9 +228,18 @@ impl BufferInstance {
             },
             scroll: Cursor { row: 0, column: 0 },
             column_level: 0,
+            folds: Folds::default(),
+            expanded_selections: vec![],
+            block_selection: false,
         }
     }
 
+    /// Selection of the block mode, None if it is not active
+    pub fn block(&self) -> Option<Selection> {
+        (self.block_selection && self.selection.mark != self.selection.cursor)
+            .then_some(self.selection)
+    }
+
     pub fn set_cursor_position(&mut self, row: usize, column: usize) {
         self.cursor.row = row;
         self.cursor.column = column;
@@ -178,7 +271,12 @@ impl BufferInstance {
 
 #[cfg(test)]
 mod tests {
-    use crate::buffer::instance::{Cursor, Selection};
+    use crate::buffer::line_buffer::{LineBuffer, VisibleLinesView};
+    use crate::buffer::{
+        folding::Folds,
+        instance::{number_lines, Cursor, Selection},
+    };
+    use crate::preferences::LineNumbers;
 
     #[test]
     fn cursor_eq() {
@@ -206,4 +304,46 @@ mod tests {
         assert_eq!(end, *start_ord);
         assert_eq!(start, *end_ord);
     }
+
+    #[test]
+    fn gutter_line_numbers() {
+        let mut buffer = LineBuffer::new("a\nb\nlong line\nd\n".into(), None);
+        let cursor = Cursor { row: 1, column: 0 };
+        let selection = Selection {
+            cursor,
+            mark: cursor,
+        };
+        let (_, _, mut gutter_info) = buffer.get_visible_lines(VisibleLinesView {
+            scroll: &mut Cursor { row: 0, column: 0 },
+            cursor: &cursor,
+            selection: &selection,
+            visible_lines: 10,
+            max_characters: 5,
+            eol_sequence: "\n".into(),
+            extra_segments: vec![],
+            folds: &mut Folds::default(),
+        });
+        let labels = |gutter_info: &[_], mode| {
+            let mut gutter_info = Vec::from(gutter_info);
+            number_lines(&mut gutter_info, cursor.row, mode);
+            gutter_info
+                .iter()
+                .map(|info| info.label(2))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            labels(&gutter_info, LineNumbers::Absolute),
+            [" 1", " 2", " 3", " .", " 4", " 5"]
+        );
+        assert_eq!(
+            labels(&gutter_info, LineNumbers::Relative),
+            [" 1", " 0", " 1", " .", " 2", " 3"]
+        );
+        assert_eq!(
+            labels(&gutter_info, LineNumbers::Hybrid),
+            [" 1", " 2", " 1", " .", " 2", " 3"]
+        );
+        number_lines(&mut gutter_info, cursor.row, LineNumbers::Off);
+        assert_eq!(gutter_info[0].label(0), "");
+    }
 }
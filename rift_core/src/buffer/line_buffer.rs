@@ -1,38 +1,213 @@
 use std::{
+    cell::RefCell,
     cmp::{max, min},
     collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant, SystemTime},
 };
 
-use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent, Highlighter};
+use tokio::sync::mpsc::Sender;
+use tree_sitter::{InputEdit, Query};
 
-use crate::lsp::client::LSPClientHandle;
+use crate::{
+    concurrent::AsyncResult,
+    editorconfig::EditorConfig,
+    io::{encoding::Encoding, encryption, file_io},
+    lsp::{client::LSPClientHandle, types::protocol},
+    snippets::SnippetSession,
+    state::EditorState,
+};
 
-use super::instance::{
-    Attribute, Cursor, Edit, GutterInfo, HighlightType, Language, Range, Selection,
+use super::{
+    extmarks::Extmarks,
+    folding::{Fold, Folds, LineShifts},
+    graphemes,
+    instance::{
+        placeholder, Attribute, Cursor, Edit, GutterInfo, HighlightType, Language, Range, Selection,
+    },
+    line_index::LineIndex,
+    marks::Marks,
+    syntax::{self, SyntaxTree},
 };
 
+/// Line ending sequence of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// Line ending of the first line of the text, None if the text has a single line
+    pub fn detect(text: &str) -> Option<Self> {
+        let end = text.find('\n')?;
+        Some(if text[..end].ends_with('\r') {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        })
+    }
+
+    /// Line ending of the sequence used by the `line_ending` preference
+    pub fn from_sequence(sequence: &str) -> Self {
+        if sequence == "\r\n" {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::Crlf => "crlf",
+        }
+    }
+}
+
+/// Order of the lines sorted by `sort_lines`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineOrder {
+    Lexical,
+    /// By the number the line starts with, lines without one come first
+    Numeric,
+    CaseInsensitive,
+}
+
+/// Number at the start of the line, ignoring leading whitespace
+fn leading_number(line: &str) -> Option<f64> {
+    let line = line.trim_start();
+    let end = line
+        .char_indices()
+        .take_while(|(idx, c)| c.is_ascii_digit() || *c == '.' || (*idx == 0 && *c == '-'))
+        .last()
+        .map(|(idx, c)| idx + c.len_utf8())?;
+    line[..end].parse().ok()
+}
+
 /// Tree sitter syntax highlight params
 pub struct TreeSitterParams {
-    pub language_config: HighlightConfiguration,
-    pub highlight_map: HashMap<String, HighlightType>,
-    pub highlight_names: Vec<String>,
+    pub language: tree_sitter::Language,
+    pub query: Query,
+    /// Highlight of each capture of the query, None for captures which are not highlighted
+    pub capture_types: Vec<Option<HighlightType>>,
+    /// Query finding code of other languages embedded in the language
+    pub injections: Option<Query>,
 }
 
 /// Text buffer implementation as a list of lines
 pub struct LineBuffer {
     pub file_path: Option<String>,
+    /// Lines of the buffer (edit through insert_text / remove_text
+    /// so that the line index stays up to date)
     pub lines: Vec<String>,
+    line_index: RefCell<LineIndex>,
     pub modified: bool,
     pub changes: VecDeque<Edit>,
     pub change_idx: usize,
     pub version: usize,
     pub language: Language,
-    highlighter: Highlighter,
-    highlight_params: Option<TreeSitterParams>,
+    /// Syntax highlights of the whole buffer
+    highlights: HighlightSpans,
+    /// Buffer version the highlights were computed for
+    highlights_version: Option<usize>,
+    /// Syntax tree reused to highlight only what changed after edits
+    syntax: SyntaxTree,
+    /// Time taken to compute the latest highlights
+    pub highlight_duration: Option<Duration>,
+    /// Named positions kept in place through edits
+    pub marks: Marks,
+    /// Decorated positions and ranges kept in place through edits
+    pub extmarks: Extmarks,
+    /// Lines added and removed, for moving the folds of the instances
+    pub line_shifts: LineShifts,
+    /// Folding ranges from the language server and the version they are for
+    pub folding_ranges: Option<(usize, Vec<Fold>)>,
+    /// Snippet whose tab-stops are being filled in
+    pub snippet: Option<SnippetSession>,
+    /// Modification time of the file when it was last read or saved
+    pub disk_mtime: Option<SystemTime>,
+    /// Encoding the file was read in and is saved in
+    pub encoding: Encoding,
+    /// Line ending of the file, None follows the `line_ending` preference
+    pub line_ending: Option<LineEnding>,
+    /// EditorConfig properties of the file, which override the preferences
+    pub editorconfig: EditorConfig,
+    /// Columns between tab stops when the buffer is shown, set by the editor state
+    pub tab_width: usize,
 }
 
 pub type HighlightedText = Vec<Vec<(String, HashSet<Attribute>)>>;
 
+/// Start byte, end byte (inclusive) and type of highlighted tokens
+pub type HighlightSpans = Vec<(usize, usize, HighlightType)>;
+
+/// Part of a buffer shown in a view, from which `LineBuffer::get_visible_lines` computes its lines
+pub struct VisibleLinesView<'a> {
+    /// First line of the view, moved to keep the cursor in view
+    pub scroll: &'a mut Cursor,
+    pub cursor: &'a Cursor,
+    pub selection: &'a Selection,
+    pub visible_lines: usize,
+    /// Width of the view, longer lines are wrapped
+    pub max_characters: usize,
+    pub eol_sequence: String,
+    /// Segments from outside the buffer such as diagnostics and search matches
+    pub extra_segments: Vec<Range>,
+    pub folds: &'a mut Folds,
+}
+
+/// Highlights computed in the background for a version of a buffer
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HighlightResult {
+    buffer_id: u32,
+    version: usize,
+    spans: HighlightSpans,
+    duration: Duration,
+}
+
+/// Language named in a markdown code fence or an injection query
+pub fn language_from_name(name: &str) -> Option<Language> {
+    match name.trim().to_lowercase().as_str() {
+        "rust" | "rs" => Some(Language::Rust),
+        "python" | "py" => Some(Language::Python),
+        "markdown" | "md" => Some(Language::Markdown),
+        "toml" => Some(Language::TOML),
+        "css" | "scss" => Some(Language::CSS),
+        "html" | "htm" => Some(Language::HTML),
+        _ => None,
+    }
+}
+
+/// Language of a file based on its extension,
+/// encrypted files such as `notes.md.age` use the extension before the last one
+fn language_from_path(path: Option<&str>) -> Language {
+    let path = path.map(|path| match encryption::encryption_kind(path) {
+        Some(_) => std::path::Path::new(path).with_extension(""),
+        None => std::path::PathBuf::from(path),
+    });
+    match path
+        .as_deref()
+        .and_then(|path| path.extension())
+        .and_then(|extension| extension.to_str())
+    {
+        Some("rs") => Language::Rust,
+        Some("py") => Language::Python,
+        Some("md") => Language::Markdown,
+        Some("toml") => Language::TOML,
+        Some("css" | "scss") => Language::CSS,
+        Some("html" | "htm") => Language::HTML,
+        _ => Language::PlainText,
+    }
+}
+
 impl LineBuffer {
     /// Create a line buffer
     pub fn new(initial_text: String, file_path: Option<String>) -> Self {
@@ -52,97 +227,48 @@ impl LineBuffer {
             lines.push("".into());
         }
 
-        let language = match &file_path {
-            Some(path) => match std::path::Path::new(&path).extension() {
-                Some(extension) => match extension.to_str().unwrap() {
-                    "rs" => Language::Rust,
-                    "py" => Language::Python,
-                    "md" => Language::Markdown,
-                    "toml" => Language::TOML,
-                    _ => Language::PlainText,
-                },
-                None => Language::PlainText,
-            },
-            None => Language::PlainText,
-        };
-
-        // Syntax highlighter
-        let highlighter = Highlighter::new();
-        let highlight_map: HashMap<String, HighlightType> = HashMap::from([
-            ("attribute".into(), HighlightType::Red),
-            ("constant".into(), HighlightType::Red),
-            ("function.builtin".into(), HighlightType::Purple),
-            ("function".into(), HighlightType::Blue),
-            ("keyword".into(), HighlightType::Purple),
-            ("operator".into(), HighlightType::Purple),
-            ("property".into(), HighlightType::Yellow),
-            ("punctuation".into(), HighlightType::White),
-            ("punctuation.bracket".into(), HighlightType::Orange),
-            ("punctuation.delimiter".into(), HighlightType::Orange),
-            ("string".into(), HighlightType::Green),
-            ("string.special".into(), HighlightType::Orange),
-            ("comment".into(), HighlightType::Gray),
-            ("comment.documentation".into(), HighlightType::Gray),
-            ("tag".into(), HighlightType::Turquoise),
-            ("type".into(), HighlightType::Yellow),
-            ("type.builtin".into(), HighlightType::Yellow),
-            ("variable".into(), HighlightType::Red),
-            ("variable.builtin".into(), HighlightType::Orange),
-            ("variable.parameter".into(), HighlightType::Red),
-        ]);
-        let highlight_names: Vec<String> =
-            highlight_map.keys().map(|key| key.to_string()).collect();
-
-        let language_config = match language {
-            Language::Rust => Some(
-                HighlightConfiguration::new(
-                    tree_sitter_rust::LANGUAGE.into(),
-                    "rust",
-                    tree_sitter_rust::HIGHLIGHTS_QUERY,
-                    tree_sitter_rust::INJECTIONS_QUERY,
-                    "",
-                )
-                .unwrap(),
-            ),
-            Language::Python => Some(
-                HighlightConfiguration::new(
-                    tree_sitter_python::LANGUAGE.into(),
-                    "python",
-                    tree_sitter_python::HIGHLIGHTS_QUERY,
-                    "",
-                    "",
-                )
-                .unwrap(),
-            ),
-            _ => None,
-        };
-
-        let highlight_params = if let Some(mut language_config) = language_config {
-            language_config.configure(&highlight_names);
-            tracing::info!("Highlight Names: {:#?}", language_config.names());
-
-            Some(TreeSitterParams {
-                language_config,
-                highlight_map,
-                highlight_names,
-            })
-        } else {
-            None
-        };
+        let language = language_from_path(file_path.as_deref());
+        let editorconfig = file_path
+            .as_deref()
+            .map(EditorConfig::for_file)
+            .unwrap_or_default();
+        let line_ending = editorconfig
+            .end_of_line
+            .or(LineEnding::detect(&initial_text));
+        let disk_mtime = file_path.as_deref().and_then(file_io::modified_time);
 
         Self {
             file_path,
             lines,
-            highlighter,
-            highlight_params,
+            line_index: RefCell::new(LineIndex::new()),
+            highlights: vec![],
+            highlights_version: None,
+            syntax: SyntaxTree::default(),
+            highlight_duration: None,
             modified: false,
             changes: VecDeque::new(),
             change_idx: 0,
             version: 1,
             language,
+            marks: Marks::default(),
+            extmarks: Extmarks::default(),
+            line_shifts: LineShifts::default(),
+            folding_ranges: None,
+            snippet: None,
+            disk_mtime,
+            encoding: Encoding::default(),
+            line_ending,
+            editorconfig,
+            tab_width: 4,
         }
     }
 
+    /// Line ending the buffer is saved with given the preferred sequence
+    pub fn line_ending_or(&self, preference: &str) -> LineEnding {
+        self.line_ending
+            .unwrap_or_else(|| LineEnding::from_sequence(preference))
+    }
+
     /// Get text buffer content as a string
     /// with the desired EOL sequence
     pub fn get_content(&self, eol_sequence: String) -> String {
@@ -202,68 +328,81 @@ impl LineBuffer {
         result
     }
 
-    pub fn byte_index_from_cursor(&self, cursor: &Cursor, eol_sequence: &str) -> usize {
-        let mut byte_index = 0;
+    /// Byte offset of the start of a row
+    pub fn byte_index_from_row(&self, row: usize, eol_sequence: &str) -> usize {
+        let row = row.min(self.lines.len());
+        self.line_index.borrow_mut().line_start(&self.lines, row) + row * eol_sequence.len()
+    }
 
-        for (idx, line) in self.lines.iter().enumerate() {
-            match idx.cmp(&cursor.row) {
-                std::cmp::Ordering::Less => {
-                    byte_index += line.len() + eol_sequence.len();
-                }
-                std::cmp::Ordering::Equal => {
-                    byte_index += cursor.column;
-                }
-                std::cmp::Ordering::Greater => {}
-            }
+    /// Position of the cursor with its column in UTF-16 code units, as counted by language servers
+    pub fn lsp_position(&self, cursor: &Cursor) -> Cursor {
+        Cursor {
+            row: cursor.row,
+            column: self
+                .lines
+                .get(cursor.row)
+                .map(|line| protocol::byte_to_utf16_column(line, cursor.column))
+                .unwrap_or(cursor.column),
         }
+    }
 
-        byte_index
+    pub fn byte_index_from_cursor(&self, cursor: &Cursor, eol_sequence: &str) -> usize {
+        if cursor.row >= self.lines.len() {
+            return self.byte_index_from_row(self.lines.len(), eol_sequence);
+        }
+        self.byte_index_from_row(cursor.row, eol_sequence) + cursor.column
     }
 
     pub fn get_visible_lines(
         &mut self,
-        scroll: &mut Cursor,
-        cursor: &Cursor,
-        selection: &Selection,
-        visible_lines: usize,
-        max_characters: usize,
-        eol_sequence: String,
-        mut extra_segments: Vec<Range>,
+        view: VisibleLinesView,
     ) -> (HighlightedText, Cursor, Vec<GutterInfo>) {
+        let VisibleLinesView {
+            scroll,
+            cursor,
+            selection,
+            visible_lines,
+            max_characters,
+            eol_sequence,
+            mut extra_segments,
+            folds,
+        } = view;
         let mut segments = vec![];
         segments.append(&mut extra_segments);
+        folds.sync(&self.line_shifts);
 
         // Calculate range of lines which need to be rendered
-        // before taking line wrap into account
+        // before taking line wrap into account (skipping folded lines)
+        let scrolled_up = cursor < scroll;
+        let scrolled_down = !scrolled_up
+            && folds.visible_between(scroll.row, cursor.row, cursor.row) >= visible_lines;
         let mut range_start = scroll.row;
-        let mut range_end = range_start + visible_lines + 3;
-
-        if cursor < scroll {
-            range_start = cursor.row.saturating_sub(3);
-            range_end = range_start + visible_lines;
-        } else if cursor.row >= scroll.row + visible_lines {
-            range_end = cursor.row + 3;
-            range_start = range_end.saturating_sub(visible_lines);
-        }
-
-        // Calculate start byte
-        let mut start_byte = 0;
-        for line in self.lines.get(..range_start).unwrap() {
-            start_byte += line.len() + eol_sequence.len();
+        let mut row_count = visible_lines + 3;
+
+        if scrolled_up {
+            range_start = folds.row_above(cursor.row, 3, cursor.row);
+            row_count = visible_lines;
+        } else if scrolled_down {
+            range_start = folds.row_above(cursor.row, visible_lines.saturating_sub(3), cursor.row);
+            row_count = visible_lines;
         }
+        let rows = folds.visible_rows(range_start, row_count, cursor.row, self.lines.len());
 
         // Calculate gutter info
         let mut gutter_info = vec![];
         let mut start = 0;
-        for (line_idx, line) in self
-            .lines
-            .get(range_start..range_end)
-            .unwrap_or(&self.lines[range_start..])
-            .iter()
-            .enumerate()
-        {
+        let mut start_byte = 0;
+        let mut next_row = None;
+        for row in rows {
+            let line = &self.lines[row];
+            // Calculate start byte after folded lines
+            if next_row != Some(row) {
+                start_byte = self.byte_index_from_row(row, &eol_sequence);
+            }
+            next_row = Some(row + 1);
+
             while start < line.len() {
-                let end = (start + max_characters).min(line.len());
+                let end = graphemes::wrap_end(line, start, max_characters, self.tab_width);
                 let eol_len = if end == line.len() {
                     eol_sequence.len()
                 } else {
@@ -271,15 +410,14 @@ impl LineBuffer {
                 };
                 let end_byte = start_byte + end - start + eol_len;
                 gutter_info.push(GutterInfo {
-                    start: Cursor {
-                        row: range_start + line_idx,
-                        column: start,
-                    },
+                    start: Cursor { row, column: start },
                     end,
                     wrapped: start != 0,
                     wrap_end: end == line.len(),
                     start_byte,
                     end_byte,
+                    virtual_line: false,
+                    number: None,
                 });
 
                 start_byte = end_byte;
@@ -289,15 +427,14 @@ impl LineBuffer {
             if line.is_empty() {
                 let end_byte = start_byte + eol_sequence.len();
                 gutter_info.push(GutterInfo {
-                    start: Cursor {
-                        row: range_start + line_idx,
-                        column: 0,
-                    },
+                    start: Cursor { row, column: 0 },
                     end: 0,
                     wrapped: false,
                     wrap_end: true,
                     start_byte,
                     end_byte,
+                    virtual_line: false,
+                    number: None,
                 });
                 start_byte = end_byte;
             }
@@ -326,7 +463,12 @@ impl LineBuffer {
                 && (cursor.column < line_info.end
                     || (cursor.column == line_info.end && line_info.wrap_end))
             {
-                relative_cursor.column -= line_info.start.column;
+                // Screen column, wide characters take up more than one
+                relative_cursor.column = self.lines[cursor.row]
+                    .get(line_info.start.column..cursor.column)
+                    .map_or(cursor.column - line_info.start.column, |text| {
+                        graphemes::visual_width(text, 0, self.tab_width)
+                    });
                 break;
             }
             cursor_idx += 1;
@@ -334,10 +476,11 @@ impl LineBuffer {
 
         // Update range of lines that need to be rendered
         // taking line wrap into account
-        if cursor < scroll {
+        let mut range_end;
+        if scrolled_up {
             range_start = cursor_idx.saturating_sub(3);
             range_end = range_start + visible_lines;
-        } else if cursor.row >= scroll.row + visible_lines {
+        } else if scrolled_down {
             range_end = cursor_idx + 3;
             range_start = range_end.saturating_sub(visible_lines);
         } else {
@@ -371,40 +514,17 @@ impl LineBuffer {
             attributes: HashSet::from([Attribute::Cursor]),
         });
 
-        // Highlight
-        if let Some(highlight_params) = &self.highlight_params {
-            let mut highlight_type = HighlightType::None;
-            let content = self.get_content("\n".into());
-            let highlights = self
-                .highlighter
-                .highlight(
-                    &highlight_params.language_config,
-                    content.as_bytes(),
-                    None,
-                    |_| None,
-                )
-                .unwrap();
-
-            for event in highlights {
-                match event.unwrap() {
-                    HighlightEvent::Source { start, end } => {
-                        if end >= gutter_info.first().unwrap().start_byte
-                            && start <= gutter_info.last().unwrap().end_byte
-                        {
-                            segments.push(Range {
-                                start,
-                                end: end.saturating_sub(1),
-                                attributes: HashSet::from([Attribute::Highlight(highlight_type)]),
-                            });
-                        }
-                    }
-                    HighlightEvent::HighlightStart(s) => {
-                        highlight_type =
-                            highlight_params.highlight_map[&highlight_params.highlight_names[s.0]];
-                    }
-                    HighlightEvent::HighlightEnd => {
-                        highlight_type = HighlightType::None;
-                    }
+        // Highlight (only if the highlights are up to date with the buffer)
+        if self.highlights_version == Some(self.version) {
+            let visible_start = gutter_info.first().unwrap().start_byte;
+            let visible_end = gutter_info.last().unwrap().end_byte;
+            for (start, end, highlight_type) in &self.highlights {
+                if *end + 1 >= visible_start && *start <= visible_end {
+                    segments.push(Range {
+                        start: *start,
+                        end: *end,
+                        attributes: HashSet::from([Attribute::Highlight(*highlight_type)]),
+                    });
                 }
             }
         }
@@ -421,13 +541,17 @@ impl LineBuffer {
         {}
 
         for line_info in &gutter_info {
+            // Skip segments of folded lines
+            while split_segments_iter
+                .next_if(|s| s.start < line_info.start_byte)
+                .is_some()
+            {}
             while let Some(segment) = split_segments_iter.next_if(|s| s.end < line_info.end_byte) {
                 highlighted_line.push((
                     self.lines[line_info.start.row][segment.start - line_info.start_byte
                         + line_info.start.column
-                        ..(segment.end - line_info.start_byte + 1 + line_info.start.column).min(
-                            line_info.end_byte - line_info.start_byte - 1 + line_info.start.column,
-                        )]
+                        ..(segment.end - line_info.start_byte + 1 + line_info.start.column)
+                            .min(line_info.end)]
                         .to_string(),
                     segment.attributes.clone(),
                 ));
@@ -451,6 +575,228 @@ impl LineBuffer {
         )
     }
 
+    /// Add the virtual text of the extmarks to the lines from `get_visible_lines`,
+    /// then expand their tabs
+    pub fn decorate_lines(
+        &self,
+        lines: &mut HighlightedText,
+        gutter_info: &mut Vec<GutterInfo>,
+        relative_cursor: &mut Cursor,
+        visible_lines: usize,
+    ) {
+        self.extmarks.decorate_lines(
+            lines,
+            gutter_info,
+            relative_cursor,
+            visible_lines,
+            self.tab_width,
+        );
+        graphemes::expand_tabs(lines, self.tab_width);
+    }
+
+    /// Change the file the buffer is saved to
+    pub fn set_file_path(&mut self, path: String) {
+        self.language = language_from_path(Some(&path));
+        self.disk_mtime = file_io::modified_time(&path);
+        self.editorconfig = EditorConfig::for_file(&path);
+        if let Some(line_ending) = self.editorconfig.end_of_line {
+            self.line_ending = Some(line_ending);
+        }
+        self.file_path = Some(path);
+        self.highlights.clear();
+        self.highlights_version = None;
+        self.syntax = SyntaxTree::default();
+    }
+
+    /// Name shown for the buffer
+    pub fn display_name(&self) -> String {
+        self.file_path
+            .clone()
+            .unwrap_or_else(|| "[No Name]".to_string())
+    }
+
+    /// Approximate heap memory used by the buffer in bytes
+    pub fn memory_usage(&self) -> usize {
+        let lines: usize = self
+            .lines
+            .iter()
+            .map(|line| line.capacity() + std::mem::size_of::<String>())
+            .sum();
+        let changes: usize = self
+            .changes
+            .iter()
+            .map(|edit| match edit {
+                Edit::Insert { text, .. } | Edit::Delete { text, .. } => {
+                    text.capacity() + std::mem::size_of::<Edit>()
+                }
+                Edit::Replace { old_text, text, .. } => {
+                    old_text.capacity() + text.capacity() + std::mem::size_of::<Edit>()
+                }
+            })
+            .sum();
+        let highlights =
+            self.highlights.capacity() * std::mem::size_of::<(usize, usize, HighlightType)>();
+        lines + changes + highlights
+    }
+
+    /// Number of highlighted tokens
+    pub fn highlight_count(&self) -> usize {
+        self.highlights.len()
+    }
+
+    /// Returns true if the buffer has a syntax highlighter
+    pub fn has_highlighter(&self) -> bool {
+        matches!(
+            self.language,
+            Language::Rust | Language::Python | Language::Markdown
+        )
+    }
+
+    /// Byte ranges (with "\n" line endings) of prose to spellcheck: the whole
+    /// buffer for text files, comments and strings for code.
+    /// None while highlights for the current version are not available.
+    pub fn prose_ranges(&self) -> Option<Vec<(usize, usize)>> {
+        match self.language {
+            Language::PlainText | Language::Markdown => {
+                Some(vec![(0, self.byte_index_from_row(self.lines.len(), "\n"))])
+            }
+            _ if !self.has_highlighter() => Some(vec![]),
+            _ if self.highlights_version != Some(self.version) => None,
+            // Comments and strings are the only captures highlighted gray and green
+            _ => Some(
+                self.highlights
+                    .iter()
+                    .filter(|(_, _, highlight_type)| {
+                        matches!(highlight_type, HighlightType::Gray | HighlightType::Green)
+                    })
+                    .map(|(start, end, _)| (*start, *end))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Buffer version the highlights were computed for
+    pub fn highlights_version(&self) -> Option<usize> {
+        self.highlights_version
+    }
+
+    /// Returns true while highlights for the current version are being computed
+    pub fn highlights_pending(&self) -> bool {
+        self.syntax.is_parsing() && self.highlights_version != Some(self.version)
+    }
+
+    /// Compute the syntax highlights of the buffer on the current thread,
+    /// only the ranges changed since the last highlights if there is a syntax tree
+    pub fn update_highlights(&mut self) {
+        if self.language == Language::Markdown {
+            let start = Instant::now();
+            self.highlights = syntax::highlight_fenced_code(self);
+            self.highlights_version = Some(self.version);
+            self.highlight_duration = Some(start.elapsed());
+        } else if let Some(highlight_params) = highlight_params(self.language) {
+            let start = Instant::now();
+            let content = self.get_content("\n".into());
+            self.syntax
+                .update(&highlight_params, &content, &mut self.highlights);
+            self.highlights_version = Some(self.version);
+            self.highlight_duration = Some(start.elapsed());
+        }
+    }
+
+    /// Keep the syntax tree and highlights in place through an edit
+    fn record_syntax_edit(&mut self, edit: InputEdit) {
+        syntax::shift_spans(&mut self.highlights, &edit);
+        self.syntax.edit(&edit);
+    }
+
+    /// Syntax highlights of the current version, computed on the current thread if outdated
+    pub fn current_highlights(&mut self) -> &HighlightSpans {
+        if self.highlights_version != Some(self.version) {
+            self.update_highlights();
+        }
+        &self.highlights
+    }
+
+    /// Compute the syntax highlights of the buffer on a worker thread the first time.
+    /// The result is sent through the async handle and applied by `apply_highlights`,
+    /// afterwards the syntax tree is updated incrementally on the current thread.
+    pub fn request_highlights(
+        &mut self,
+        buffer_id: u32,
+        rt: &tokio::runtime::Runtime,
+        sender: Sender<AsyncResult>,
+    ) {
+        let Some(highlight_params) = highlight_params(self.language) else {
+            return;
+        };
+        if self.highlights_version == Some(self.version) || self.syntax.is_parsing() {
+            return;
+        }
+        // Only code blocks of markdown are highlighted, which is quick enough to do in place
+        if self.syntax.has_tree() || self.language == Language::Markdown {
+            self.update_highlights();
+            return;
+        }
+
+        let content = self.get_content("\n".into());
+        let version = self.version;
+        let tree_slot = self.syntax.start_background();
+        rt.spawn_blocking(move || {
+            let start = Instant::now();
+            let spans = match syntax::parse_and_highlight(&highlight_params, &content) {
+                Some((tree, spans)) => {
+                    if let Ok(mut slot) = tree_slot.lock() {
+                        *slot = Some(tree);
+                    }
+                    spans
+                }
+                None => vec![],
+            };
+            let result = HighlightResult {
+                buffer_id,
+                version,
+                spans,
+                duration: start.elapsed(),
+            };
+            if let Err(err) = sender.blocking_send(AsyncResult {
+                result: serde_json::to_string(&result).unwrap(),
+                callback: apply_highlights,
+            }) {
+                tracing::error!("Failed to send highlights: {}", err);
+            }
+        });
+    }
+
+    /// Byte offsets of the brackets from the start to the end byte with their nesting depth,
+    /// from the syntax tree of the last highlights
+    pub fn bracket_depths(&self, start: usize, end: usize) -> Vec<(usize, usize)> {
+        self.syntax.bracket_depths(start, end)
+    }
+
+    /// Columns of the indent guides of the row, one at the start of each level
+    /// of the spaces it is indented with
+    pub fn indent_guides(&self, row: usize, tab_width: usize) -> Vec<usize> {
+        let indent = self.lines[row].chars().take_while(|c| *c == ' ').count();
+        (0..indent).step_by(tab_width.max(1)).collect()
+    }
+
+    /// Byte columns of the characters of the row drawn as placeholders when whitespace
+    /// is rendered: tabs, trailing and non-breaking spaces and control characters
+    pub fn invisible_characters(&self, row: usize) -> Vec<(usize, usize)> {
+        let line = &self.lines[row];
+        let trailing = line.trim_end_matches(' ').len();
+        line.char_indices()
+            .filter(|(column, c)| {
+                if *c == ' ' {
+                    *column >= trailing
+                } else {
+                    placeholder(*c).is_some()
+                }
+            })
+            .map(|(column, c)| (column, column + c.len_utf8()))
+            .collect()
+    }
+
     /// Get line length
     pub fn get_line_length(&self, row: usize) -> usize {
         self.lines[row].len()
@@ -470,7 +816,7 @@ impl LineBuffer {
                 cursor.row += 1;
             }
         } else {
-            cursor.column += 1;
+            cursor.column = graphemes::next_boundary(&self.lines[cursor.row], cursor.column);
         }
     }
 
@@ -482,25 +828,49 @@ impl LineBuffer {
                 cursor.column = self.get_line_length(cursor.row);
             }
         } else {
-            cursor.column -= 1;
+            cursor.column = graphemes::prev_boundary(&self.lines[cursor.row], cursor.column);
         }
     }
 
+    /// Screen column of the cursor within its line
+    pub fn visual_column(&self, cursor: &Cursor) -> usize {
+        let line = &self.lines[cursor.row];
+        line.get(..cursor.column).map_or(cursor.column, |text| {
+            graphemes::visual_width(text, 0, self.tab_width)
+        })
+    }
+
+    /// Byte column of the character of the row drawn over the screen column
+    pub fn column_at_visual(&self, row: usize, visual: usize) -> usize {
+        graphemes::column_at_visual(&self.lines[row], visual, self.tab_width)
+    }
+
+    /// Column of the cursor moved to the row from the screen column, on a grapheme boundary
+    fn vertical_column(&self, row: usize, visual: usize, column_level: usize) -> usize {
+        let column = max(
+            min(column_level, self.get_line_length(row)),
+            self.column_at_visual(row, visual),
+        );
+        graphemes::floor_boundary(&self.lines[row], column)
+    }
+
+    /// Column of the row closest to the column level, on a grapheme boundary
+    pub fn column_at_level(&self, row: usize, column_level: usize) -> usize {
+        graphemes::floor_boundary(
+            &self.lines[row],
+            min(column_level, self.get_line_length(row)),
+        )
+    }
+
     /// Move cursor up in insert mode
     pub fn move_cursor_up(&self, cursor: &mut Cursor, column_level: usize) -> usize {
         if cursor.row == 0 {
             cursor.column = 0;
             cursor.column
         } else {
+            let visual = self.visual_column(cursor);
             cursor.row -= 1;
-            if cursor.column > self.get_line_length(cursor.row) {
-                cursor.column = self.get_line_length(cursor.row);
-            } else {
-                cursor.column = max(
-                    min(column_level, self.get_line_length(cursor.row)),
-                    cursor.column,
-                )
-            }
+            cursor.column = self.vertical_column(cursor.row, visual, column_level);
             column_level
         }
     }
@@ -511,15 +881,9 @@ impl LineBuffer {
             cursor.column = self.get_line_length(cursor.row);
             cursor.column
         } else {
+            let visual = self.visual_column(cursor);
             cursor.row += 1;
-            if cursor.column > self.get_line_length(cursor.row) {
-                cursor.column = self.get_line_length(cursor.row);
-            } else {
-                cursor.column = max(
-                    min(column_level, self.get_line_length(cursor.row)),
-                    cursor.column,
-                )
-            }
+            cursor.column = self.vertical_column(cursor.row, visual, column_level);
             column_level
         }
     }
@@ -549,6 +913,10 @@ impl LineBuffer {
     /// Insert text at cursor position and return update cursor position
     pub fn insert_text_no_log(&mut self, text: &str, cursor: &Cursor) -> Cursor {
         self.modified = true;
+        let start_byte = self
+            .has_highlighter()
+            .then(|| self.byte_index_from_cursor(cursor, "\n"));
+        self.line_index.get_mut().invalidate(cursor.row);
 
         let mut updated_cursor = *cursor;
         let current_line = self.lines[cursor.row].clone();
@@ -567,6 +935,23 @@ impl LineBuffer {
         let mut current_line = self.lines[updated_cursor.row].clone();
         current_line.push_str(s2);
         self.lines[updated_cursor.row] = current_line;
+        self.marks.adjust_insert(cursor, &updated_cursor);
+        self.extmarks.adjust_insert(cursor, &updated_cursor);
+        if let Some(snippet) = self.snippet.as_mut() {
+            snippet.adjust_insert(cursor, &updated_cursor);
+        }
+        self.line_shifts
+            .push(cursor.row, (updated_cursor.row - cursor.row) as isize);
+        if let Some(start_byte) = start_byte {
+            self.record_syntax_edit(InputEdit {
+                start_byte,
+                old_end_byte: start_byte,
+                new_end_byte: start_byte + text.len(),
+                start_position: syntax::point(cursor),
+                old_end_position: syntax::point(cursor),
+                new_end_position: syntax::point(&updated_cursor),
+            });
+        }
 
         updated_cursor
     }
@@ -592,21 +977,18 @@ impl LineBuffer {
         self.version += 1;
 
         if let Some(lsp_handle) = lsp_handle {
-            lsp_handle
-                .send_notification_sync(
-                    "textDocument/didChange".to_string(),
-                    Some(LSPClientHandle::did_change_text_document(
-                        self.file_path.clone().unwrap(),
-                        self.version,
-                        // Selection {
-                        //     cursor: *cursor,
-                        //     mark: *cursor,
-                        // },
-                        // text.to_string(),
-                        self.get_content("\n".to_owned()),
-                    )),
-                )
-                .unwrap();
+            // The text before the cursor is unchanged by the insert
+            let position = self.lsp_position(cursor);
+            lsp_handle.did_change(
+                self.file_path.clone().unwrap(),
+                self.version,
+                Selection {
+                    cursor: position,
+                    mark: position,
+                },
+                text,
+                || self.get_content("\n".to_owned()),
+            );
         }
 
         updated_cursor
@@ -627,6 +1009,25 @@ impl LineBuffer {
         } else {
             selection.mark
         };
+        if self.has_highlighter() {
+            let start_byte = self.byte_index_from_cursor(&start, "\n");
+            self.record_syntax_edit(InputEdit {
+                start_byte,
+                old_end_byte: self.byte_index_from_cursor(&end, "\n"),
+                new_end_byte: start_byte,
+                start_position: syntax::point(&start),
+                old_end_position: syntax::point(&end),
+                new_end_position: syntax::point(&start),
+            });
+        }
+        self.line_index.get_mut().invalidate(start.row);
+        self.marks.adjust_remove(&start, &end);
+        self.extmarks.adjust_remove(&start, &end);
+        if let Some(snippet) = self.snippet.as_mut() {
+            snippet.adjust_remove(&start, &end);
+        }
+        self.line_shifts
+            .push(start.row, -((end.row - start.row) as isize));
 
         if start.row == end.row {
             let current_line = self.lines[start.row].clone();
@@ -665,6 +1066,11 @@ impl LineBuffer {
         lsp_handle: &Option<&mut LSPClientHandle>,
         log: bool,
     ) -> (String, Cursor) {
+        // Positions for the language servers are computed before the text is gone
+        let range = lsp_handle.is_some().then(|| Selection {
+            cursor: self.lsp_position(&selection.cursor),
+            mark: self.lsp_position(&selection.mark),
+        });
         let (text, cursor) = self.remove_text_no_log(selection);
 
         let (start, end) = selection.in_order();
@@ -679,19 +1085,14 @@ impl LineBuffer {
         }
         self.version += 1;
 
-        if let Some(lsp_handle) = lsp_handle {
-            lsp_handle
-                .send_notification_sync(
-                    "textDocument/didChange".to_string(),
-                    Some(LSPClientHandle::did_change_text_document(
-                        self.file_path.clone().unwrap(),
-                        self.version,
-                        // *selection,
-                        // "".to_string(),
-                        self.get_content("\n".to_owned()),
-                    )),
-                )
-                .unwrap();
+        if let (Some(lsp_handle), Some(range)) = (lsp_handle, range) {
+            lsp_handle.did_change(
+                self.file_path.clone().unwrap(),
+                self.version,
+                range,
+                "",
+                || self.get_content("\n".to_owned()),
+            );
         }
 
         (text, cursor)
@@ -727,6 +1128,20 @@ impl LineBuffer {
                     let cursor = self.insert_text(&text.clone(), &start.clone(), lsp_handle, false);
                     return Some(cursor);
                 }
+                Edit::Replace {
+                    start,
+                    new_end,
+                    old_text,
+                    ..
+                } => {
+                    let (start, old_text) = (*start, old_text.clone());
+                    let selection = Selection {
+                        cursor: *new_end,
+                        mark: start,
+                    };
+                    self.remove_text(&selection, lsp_handle, false);
+                    return Some(self.insert_text(&old_text, &start, lsp_handle, false));
+                }
             }
         }
         None
@@ -762,33 +1177,218 @@ impl LineBuffer {
                     );
                     return Some(cursor);
                 }
+                Edit::Replace {
+                    start,
+                    old_end,
+                    text,
+                    ..
+                } => {
+                    let (start, text) = (*start, text.clone());
+                    let selection = Selection {
+                        cursor: *old_end,
+                        mark: start,
+                    };
+                    self.remove_text(&selection, lsp_handle, false);
+                    return Some(self.insert_text(&text, &start, lsp_handle, false));
+                }
             }
         }
         None
     }
 
-    /// Get indentation level (number of spaces) of given row
-    pub fn get_indentation_level(&self, row: usize) -> usize {
+    /// Replace the selected text as a single undoable edit and return the end of the new text
+    pub fn replace_text(
+        &mut self,
+        selection: &Selection,
+        text: &str,
+        lsp_handle: &Option<&mut LSPClientHandle>,
+    ) -> Cursor {
+        let (start, old_end) = selection.in_order();
+        let (start, old_end) = (*start, *old_end);
+        let (old_text, _cursor) = self.remove_text(selection, lsp_handle, false);
+        let new_end = self.insert_text(text, &start, lsp_handle, false);
+
+        self.changes.truncate(self.change_idx);
+        self.changes.push_back(Edit::Replace {
+            start,
+            old_end,
+            new_end,
+            old_text,
+            text: text.to_owned(),
+        });
+        self.change_idx = self.changes.len();
+        new_end
+    }
+
+    /// Replace the lines of the selection with the transformed lines as a single undoable edit,
+    /// returns the selection of the new lines. A selection ending at the start of a line
+    /// does not include it.
+    fn transform_lines(
+        &mut self,
+        selection: &Selection,
+        transform: impl FnOnce(Vec<String>) -> Vec<String>,
+        lsp_handle: &Option<&mut LSPClientHandle>,
+    ) -> Selection {
+        let (start, end) = selection.in_order();
+        let end_row = if end.column == 0 && end.row > start.row {
+            end.row - 1
+        } else {
+            end.row
+        };
+        let lines = transform(self.lines[start.row..=end_row].to_vec());
+        let range = Selection {
+            mark: Cursor {
+                row: start.row,
+                column: 0,
+            },
+            cursor: Cursor {
+                row: end_row,
+                column: self.get_line_length(end_row),
+            },
+        };
+        let end = self.replace_text(&range, &lines.join("\n"), lsp_handle);
+        Selection {
+            mark: range.mark,
+            cursor: end,
+        }
+    }
+
+    /// Sort the selected lines, lines which compare equal keep their order
+    pub fn sort_lines(
+        &mut self,
+        selection: &Selection,
+        order: LineOrder,
+        lsp_handle: &Option<&mut LSPClientHandle>,
+    ) -> Selection {
+        self.transform_lines(
+            selection,
+            |mut lines| {
+                match order {
+                    LineOrder::Lexical => lines.sort(),
+                    LineOrder::Numeric => {
+                        lines.sort_by(|a, b| match (leading_number(a), leading_number(b)) {
+                            (Some(a), Some(b)) => a.total_cmp(&b),
+                            (a, b) => a.is_some().cmp(&b.is_some()),
+                        })
+                    }
+                    LineOrder::CaseInsensitive => {
+                        lines.sort_by_cached_key(|line| line.to_lowercase())
+                    }
+                }
+                lines
+            },
+            lsp_handle,
+        )
+    }
+
+    /// Remove the selected lines which repeat an earlier selected line
+    pub fn unique_lines(
+        &mut self,
+        selection: &Selection,
+        lsp_handle: &Option<&mut LSPClientHandle>,
+    ) -> Selection {
+        self.transform_lines(
+            selection,
+            |lines| {
+                let mut seen = HashSet::new();
+                lines
+                    .into_iter()
+                    .filter(|line| seen.insert(line.clone()))
+                    .collect()
+            },
+            lsp_handle,
+        )
+    }
+
+    /// Reverse the order of the selected lines
+    pub fn reverse_lines(
+        &mut self,
+        selection: &Selection,
+        lsp_handle: &Option<&mut LSPClientHandle>,
+    ) -> Selection {
+        self.transform_lines(
+            selection,
+            |mut lines| {
+                lines.reverse();
+                lines
+            },
+            lsp_handle,
+        )
+    }
+
+    /// Replace the text of a row (without its line ending) as an undoable edit
+    pub fn replace_line(
+        &mut self,
+        row: usize,
+        text: &str,
+        lsp_handle: &Option<&mut LSPClientHandle>,
+    ) {
+        if self.lines[row] == text {
+            return;
+        }
+        let start = Cursor { row, column: 0 };
+        if !self.lines[row].is_empty() {
+            self.remove_text(
+                &Selection {
+                    mark: start,
+                    cursor: Cursor {
+                        row,
+                        column: self.lines[row].len(),
+                    },
+                },
+                lsp_handle,
+                true,
+            );
+        }
+        if !text.is_empty() {
+            self.insert_text(text, &start, lsp_handle, true);
+        }
+    }
+
+    /// Remove spaces and tabs at the end of each line
+    pub fn trim_trailing_whitespace(&mut self, lsp_handle: &Option<&mut LSPClientHandle>) {
+        for row in 0..self.lines.len() {
+            let line = &self.lines[row];
+            let trimmed = line.trim_end_matches([' ', '\t']).len();
+            if trimmed < line.len() {
+                let selection = Selection {
+                    cursor: Cursor {
+                        row,
+                        column: line.len(),
+                    },
+                    mark: Cursor {
+                        row,
+                        column: trimmed,
+                    },
+                };
+                self.remove_text(&selection, lsp_handle, true);
+            }
+        }
+    }
+
+    /// Leading whitespace of the line
+    pub fn get_indentation(&self, row: usize) -> String {
         let line = &self.lines[row];
-        line.chars().take_while(|c| *c == ' ').count()
+        line.chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect()
     }
 
     /// Add indentation to the selected lines and returns the updated cursor position
     pub fn add_indentation(
         &mut self,
         selection: &Selection,
-        tab_size: usize,
+        indent: &str,
         lsp_handle: &Option<&mut LSPClientHandle>,
     ) -> Selection {
         self.modified = true;
 
         let mut updated_selection = *selection;
-        let tab = " ".repeat(tab_size);
-        updated_selection.mark.column += tab_size;
-        updated_selection.cursor.column += tab_size;
+        updated_selection.mark.column += indent.len();
+        updated_selection.cursor.column += indent.len();
         let (start, end) = selection.in_order();
         for i in start.row..=end.row {
-            self.insert_text(&tab, &Cursor { row: i, column: 0 }, lsp_handle, true);
+            self.insert_text(indent, &Cursor { row: i, column: 0 }, lsp_handle, true);
         }
         updated_selection
     }
@@ -797,18 +1397,18 @@ impl LineBuffer {
     pub fn remove_indentation(
         &mut self,
         selection: &Selection,
-        tab_size: usize,
+        indent: &str,
         lsp_handle: &Option<&mut LSPClientHandle>,
     ) -> Selection {
         self.modified = true;
 
         let mut updated_selection = *selection;
-        let tab = " ".repeat(tab_size);
+        let tab_size = indent.len();
         let (start, end) = selection.in_order();
         let (start_new, end_new) = updated_selection.in_order_mut();
         for i in start.row..=end.row {
             let current_line = &self.lines[i];
-            if current_line.starts_with(&tab) {
+            if current_line.starts_with(indent) {
                 self.remove_text(
                     &Selection {
                         cursor: Cursor { row: i, column: 0 },
@@ -854,8 +1454,11 @@ impl LineBuffer {
         // while start > 0 && line.chars().nth(start - 1).unwrap().is_alphanumeric() {
         //     start -= 1;
         // }
-        while end < line.len() && line.chars().nth(end).unwrap().is_alphanumeric() {
-            end += 1;
+        while let Some(c) = line.get(end..).and_then(|rest| rest.chars().next()) {
+            if !c.is_alphanumeric() {
+                break;
+            }
+            end += c.len_utf8();
         }
         // updated_selection.mark.column = start;
         updated_selection.cursor.column = end;
@@ -863,11 +1466,154 @@ impl LineBuffer {
     }
 }
 
+/// Tree sitter highlight params of a language.
+/// Built on first use and shared between all buffers of the language.
+pub fn highlight_params(language: Language) -> Option<Arc<TreeSitterParams>> {
+    static RUST: OnceLock<Arc<TreeSitterParams>> = OnceLock::new();
+    static PYTHON: OnceLock<Arc<TreeSitterParams>> = OnceLock::new();
+
+    let params = match language {
+        Language::Rust => &RUST,
+        Language::Python => &PYTHON,
+        _ => return None,
+    };
+    Some(
+        params
+            .get_or_init(|| {
+                let start = Instant::now();
+                let params = build_highlight_params(language);
+                tracing::info!(
+                    "Built {:?} highlight configuration in {:?}",
+                    language,
+                    start.elapsed()
+                );
+                Arc::new(params)
+            })
+            .clone(),
+    )
+}
+
+fn build_highlight_params(language: Language) -> TreeSitterParams {
+    let highlight_map: HashMap<String, HighlightType> = HashMap::from([
+        ("attribute".into(), HighlightType::Red),
+        ("constant".into(), HighlightType::Red),
+        ("function.builtin".into(), HighlightType::Purple),
+        ("function".into(), HighlightType::Blue),
+        ("keyword".into(), HighlightType::Purple),
+        ("operator".into(), HighlightType::Purple),
+        ("property".into(), HighlightType::Yellow),
+        ("punctuation".into(), HighlightType::White),
+        ("punctuation.bracket".into(), HighlightType::Orange),
+        ("punctuation.delimiter".into(), HighlightType::Orange),
+        ("string".into(), HighlightType::Green),
+        ("string.special".into(), HighlightType::Orange),
+        ("comment".into(), HighlightType::Gray),
+        ("comment.documentation".into(), HighlightType::Gray),
+        ("tag".into(), HighlightType::Turquoise),
+        ("type".into(), HighlightType::Yellow),
+        ("type.builtin".into(), HighlightType::Yellow),
+        ("variable".into(), HighlightType::Red),
+        ("variable.builtin".into(), HighlightType::Orange),
+        ("variable.parameter".into(), HighlightType::Red),
+    ]);
+    let (language, highlights_query, injections_query): (tree_sitter::Language, &str, &str) =
+        match language {
+            Language::Rust => (
+                tree_sitter_rust::LANGUAGE.into(),
+                tree_sitter_rust::HIGHLIGHTS_QUERY,
+                tree_sitter_rust::INJECTIONS_QUERY,
+            ),
+            Language::Python => (
+                tree_sitter_python::LANGUAGE.into(),
+                tree_sitter_python::HIGHLIGHTS_QUERY,
+                "",
+            ),
+            _ => unreachable!("no grammar for {:?}", language),
+        };
+    let query = Query::new(&language, highlights_query).unwrap();
+    let injections =
+        (!injections_query.is_empty()).then(|| Query::new(&language, injections_query).unwrap());
+
+    // A capture such as `function.method` uses the most specific
+    // highlighted name it starts with, `function` in this case
+    let capture_types = query
+        .capture_names()
+        .iter()
+        .map(|capture| {
+            let parts: Vec<&str> = capture.split('.').collect();
+            (1..=parts.len())
+                .rev()
+                .find_map(|len| highlight_map.get(&parts[..len].join(".")).copied())
+        })
+        .collect();
+
+    TreeSitterParams {
+        language,
+        query,
+        capture_types,
+        injections,
+    }
+}
+
+/// Store highlights computed in the background along with their syntax tree
+fn apply_highlights(
+    result: String,
+    state: &mut EditorState,
+    _lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    let Ok(result) = serde_json::from_str::<HighlightResult>(&result) else {
+        return;
+    };
+    if let Some(buffer) = state.buffers.get_mut(&result.buffer_id) {
+        if !buffer.syntax.is_parsing() {
+            return;
+        }
+        // Edits made in the meantime are highlighted incrementally on the next request
+        let mut spans = result.spans;
+        buffer.syntax.finish_background(&mut spans);
+        buffer.highlights = spans;
+        if buffer.version == result.version {
+            buffer.highlights_version = Some(result.version);
+        }
+        buffer.highlight_duration = Some(result.duration);
+        state.update_view = true;
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::buffer::instance::{Cursor, Selection};
+    use crate::buffer::{
+        folding::Folds,
+        instance::{Cursor, Selection},
+    };
+
+    use super::{LineBuffer, LineEnding, LineOrder, VisibleLinesView};
+
+    #[test]
+    fn indent_guides() {
+        let buf = LineBuffer::new("a\n    b\n          c\n\td\n".into(), None);
+        assert!(buf.indent_guides(0, 4).is_empty());
+        assert_eq!(buf.indent_guides(1, 4), [0]);
+        assert_eq!(buf.indent_guides(2, 4), [0, 4, 8]);
+        assert!(buf.indent_guides(3, 4).is_empty());
+    }
+
+    #[test]
+    fn lsp_positions() {
+        let buf = LineBuffer::new("a😀é\n".into(), None);
+        assert_eq!(buf.lsp_position(&Cursor { row: 0, column: 5 }).column, 3);
+        assert_eq!(buf.lsp_position(&Cursor { row: 0, column: 7 }).column, 4);
+        assert_eq!(buf.lsp_position(&Cursor { row: 1, column: 0 }).column, 0);
+    }
 
-    use super::LineBuffer;
+    #[test]
+    fn invisible_characters() {
+        let buf = LineBuffer::new("\tlet a = 1;\u{a0}x  \n".into(), None);
+        assert_eq!(
+            buf.invisible_characters(0),
+            [(0, 1), (11, 13), (14, 15), (15, 16)]
+        );
+    }
 
     #[test]
     fn line_buffer_empty() {
@@ -883,6 +1629,21 @@ mod tests {
         assert_eq!(buf.lines, vec![""])
     }
 
+    #[test]
+    fn line_buffer_detects_line_ending() {
+        let buf = LineBuffer::new("Hello\r\nWorld\r\n".into(), None);
+        assert_eq!(buf.lines, vec!["Hello", "World", ""]);
+        assert_eq!(buf.line_ending, Some(LineEnding::Crlf));
+        assert_eq!(buf.get_content("\r\n".into()), "Hello\r\nWorld\r\n");
+        assert_eq!(
+            LineBuffer::new("Hello\nWorld".into(), None).line_ending,
+            Some(LineEnding::Lf)
+        );
+        let buf = LineBuffer::new("Hello".into(), None);
+        assert_eq!(buf.line_ending, None);
+        assert_eq!(buf.line_ending_or("\r\n"), LineEnding::Crlf);
+    }
+
     #[test]
     fn line_buffer_with_no_extra_line() {
         let buf = LineBuffer::new("Hello\nWorld".into(), None);
@@ -906,12 +1667,36 @@ mod tests {
             mark: Cursor { row: 0, column: 0 },
             cursor: Cursor { row: 0, column: 0 },
         };
-        let (_lines, visible_cursor, _gutter_info) =
-            buf.get_visible_lines(&mut scroll, &cursor, &selection, 10, 5, "\n".into(), vec![]);
-        // assert_eq!(vec!["Hello", "World", ""], lines);
+        let (lines, visible_cursor, _gutter_info) = buf.get_visible_lines(VisibleLinesView {
+            scroll: &mut scroll,
+            cursor: &cursor,
+            selection: &selection,
+            visible_lines: 10,
+            max_characters: 5,
+            eol_sequence: "\n".into(),
+            extra_segments: vec![],
+            folds: &mut Folds::default(),
+        });
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|line| line.iter().map(|token| token.0.as_str()).collect())
+            .collect();
+        assert_eq!(vec!["Hello", "World", ""], lines);
         assert_eq!(visible_cursor, Cursor { row: 0, column: 0 });
     }
 
+    #[test]
+    fn line_buffer_highlights_current_version() {
+        let mut buf = LineBuffer::new("fn main() {}\n".into(), Some("main.rs".into()));
+        assert!(buf.has_highlighter());
+        buf.update_highlights();
+        assert!(!buf.highlights.is_empty());
+
+        // Highlights are not used once the buffer changes
+        buf.insert_text("x", &Cursor { row: 0, column: 0 }, &None, false);
+        assert_ne!(buf.highlights_version, Some(buf.version));
+    }
+
     #[test]
     fn move_cursor_right_same_line() {
         let buf = LineBuffer::new("Hello\nWorld\n".into(), None);
@@ -936,6 +1721,72 @@ mod tests {
         assert_eq!(cursor, Cursor { row: 2, column: 0 })
     }
 
+    #[test]
+    fn wide_characters() {
+        let mut buf = LineBuffer::new("e\u{301}漢字ab\nx\n".into(), None);
+        let mut cursor = Cursor { row: 0, column: 0 };
+        buf.move_cursor_right(&mut cursor);
+        assert_eq!(cursor, Cursor { row: 0, column: 3 });
+        buf.move_cursor_right(&mut cursor);
+        assert_eq!(cursor, Cursor { row: 0, column: 6 });
+        buf.move_cursor_left(&mut cursor);
+        assert_eq!(cursor, Cursor { row: 0, column: 3 });
+
+        // Moving down and back up never lands inside a character
+        let mut cursor = Cursor { row: 1, column: 1 };
+        buf.move_cursor_up(&mut cursor, 5);
+        assert_eq!(cursor, Cursor { row: 0, column: 3 });
+
+        // Wrapped by screen columns, the cursor column is a screen column
+        let cursor = Cursor { row: 0, column: 9 };
+        let (lines, relative_cursor, gutter_info) = buf.get_visible_lines(VisibleLinesView {
+            scroll: &mut Cursor { row: 0, column: 0 },
+            cursor: &cursor,
+            selection: &Selection {
+                cursor,
+                mark: cursor,
+            },
+            visible_lines: 10,
+            max_characters: 4,
+            eol_sequence: "\n".into(),
+            extra_segments: vec![],
+            folds: &mut Folds::default(),
+        });
+        let text: Vec<String> = lines
+            .iter()
+            .map(|line| line.iter().map(|token| token.0.as_str()).collect())
+            .collect();
+        assert_eq!(text[..2], ["e\u{301}漢", "字ab"]);
+        assert_eq!(gutter_info[1].start.column, 6);
+        assert_eq!(relative_cursor, Cursor { row: 1, column: 2 });
+    }
+
+    #[test]
+    fn sort_unique_and_reverse_lines() {
+        let mut buffer = LineBuffer::new("b\n10 x\nB\n9 y\na\nb\nend\n".into(), None);
+        let selection = Selection {
+            mark: Cursor { row: 0, column: 0 },
+            cursor: Cursor { row: 6, column: 0 },
+        };
+        let selection = buffer.sort_lines(&selection, LineOrder::Lexical, &None);
+        assert_eq!(buffer.lines[..6], ["10 x", "9 y", "B", "a", "b", "b"]);
+        assert_eq!(selection.cursor, Cursor { row: 5, column: 1 });
+
+        buffer.sort_lines(&selection, LineOrder::Numeric, &None);
+        assert_eq!(buffer.lines[..6], ["B", "a", "b", "b", "9 y", "10 x"]);
+        buffer.sort_lines(&selection, LineOrder::CaseInsensitive, &None);
+        assert_eq!(buffer.lines[..6], ["10 x", "9 y", "a", "B", "b", "b"]);
+
+        let selection = buffer.unique_lines(&selection, &None);
+        assert_eq!(buffer.lines[..6], ["10 x", "9 y", "a", "B", "b", "end"]);
+        buffer.reverse_lines(&selection, &None);
+        assert_eq!(buffer.lines[..6], ["b", "B", "a", "9 y", "10 x", "end"]);
+
+        // Each command is a single undoable edit
+        buffer.undo(&None);
+        assert_eq!(buffer.lines[..6], ["10 x", "9 y", "a", "B", "b", "end"]);
+    }
+
     #[test]
     fn byte_index_from_cursor() {
         let buf = LineBuffer::new("Hello\nWorld\n".into(), None);
@@ -0,0 +1,52 @@
+/// Byte offsets of the start of each line (excluding line endings).
+/// Offsets are computed lazily up to the requested row and are invalidated
+/// from the first edited row, so lookups near recent edits stay cheap.
+#[derive(Debug, Default)]
+pub struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new() -> Self {
+        Self { starts: vec![0] }
+    }
+
+    /// Forget offsets of lines after the given row (the row was edited)
+    pub fn invalidate(&mut self, row: usize) {
+        self.starts.truncate(row + 1);
+        if self.starts.is_empty() {
+            self.starts.push(0);
+        }
+    }
+
+    /// Sum of the lengths of the lines before the row
+    pub fn line_start(&mut self, lines: &[String], row: usize) -> usize {
+        let row = row.min(lines.len());
+        if self.starts.is_empty() {
+            self.starts.push(0);
+        }
+        while self.starts.len() <= row {
+            let previous = self.starts.len() - 1;
+            self.starts
+                .push(self.starts[previous] + lines[previous].len());
+        }
+        self.starts[row]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LineIndex;
+
+    #[test]
+    fn line_index_invalidate() {
+        let mut lines: Vec<String> = vec!["ab".into(), "cde".into(), "f".into()];
+        let mut index = LineIndex::new();
+        assert_eq!(index.line_start(&lines, 2), 5);
+
+        lines[0].push('x');
+        index.invalidate(0);
+        assert_eq!(index.line_start(&lines, 1), 3);
+        assert_eq!(index.line_start(&lines, 3), 7);
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+use super::instance::Cursor;
+
+/// Named positions in a buffer which follow the text they point at as it is edited
+#[derive(Debug, Clone, Default)]
+pub struct Marks {
+    pub positions: BTreeMap<char, Cursor>,
+}
+
+impl Marks {
+    pub fn set(&mut self, name: char, cursor: Cursor) {
+        self.positions.insert(name, cursor);
+    }
+
+    pub fn get(&self, name: char) -> Option<Cursor> {
+        self.positions.get(&name).copied()
+    }
+
+    /// Shift marks after text inserted from start to end
+    pub fn adjust_insert(&mut self, start: &Cursor, end: &Cursor) {
+        for mark in self.positions.values_mut() {
+            shift_after_insert(mark, start, end, true);
+        }
+    }
+
+    /// Shift marks after the text from start to end was removed
+    pub fn adjust_remove(&mut self, start: &Cursor, end: &Cursor) {
+        for mark in self.positions.values_mut() {
+            shift_after_remove(mark, start, end);
+        }
+    }
+}
+
+/// Shift a position after text was inserted from start to end.
+/// A position at the start of the insertion moves along if `move_at_start` is set.
+pub fn shift_after_insert(
+    position: &mut Cursor,
+    start: &Cursor,
+    end: &Cursor,
+    move_at_start: bool,
+) {
+    if *position < *start || (*position == *start && !move_at_start) {
+        return;
+    }
+    if position.row == start.row {
+        position.column = end.column + position.column - start.column;
+    }
+    position.row += end.row - start.row;
+}
+
+/// Shift a position after the text from start to end was removed.
+/// Positions inside the removed text move to its start.
+pub fn shift_after_remove(position: &mut Cursor, start: &Cursor, end: &Cursor) {
+    if *position <= *start {
+        return;
+    }
+    if *position < *end {
+        *position = *start;
+        return;
+    }
+    if position.row == end.row {
+        position.column = start.column + position.column - end.column;
+    }
+    position.row -= end.row - start.row;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Marks;
+    use crate::buffer::instance::Cursor;
+
+    #[test]
+    fn marks_follow_edits() {
+        let cursor = |row, column| Cursor { row, column };
+        let mut marks = Marks::default();
+        marks.set('a', cursor(0, 4));
+        marks.set('b', cursor(2, 1));
+        marks.set('c', cursor(0, 1));
+
+        // "xy\nz" inserted at 0:2
+        marks.adjust_insert(&cursor(0, 2), &cursor(1, 1));
+        assert_eq!(marks.get('a'), Some(cursor(1, 3)));
+        assert_eq!(marks.get('b'), Some(cursor(3, 1)));
+        assert_eq!(marks.get('c'), Some(cursor(0, 1)));
+
+        marks.adjust_remove(&cursor(0, 2), &cursor(1, 1));
+        assert_eq!(marks.get('a'), Some(cursor(0, 4)));
+        assert_eq!(marks.get('b'), Some(cursor(2, 1)));
+
+        marks.adjust_remove(&cursor(0, 0), &cursor(2, 0));
+        assert_eq!(marks.get('a'), Some(cursor(0, 0)));
+        assert_eq!(marks.get('b'), Some(cursor(0, 1)));
+        assert_eq!(marks.get('d'), None);
+    }
+}
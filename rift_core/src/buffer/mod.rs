@@ -1,2 +1,8 @@
+pub mod extmarks;
+pub mod folding;
+pub mod graphemes;
 pub mod instance;
 pub mod line_buffer;
+pub mod line_index;
+pub mod marks;
+pub mod syntax;
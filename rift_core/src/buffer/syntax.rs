@@ -0,0 +1,480 @@
+//! Syntax tree of a buffer kept in sync with its edits, so that after an edit
+//! only the ranges which changed are parsed and highlighted again.
+
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Mutex},
+};
+
+use tree_sitter::{InputEdit, Node, Parser, Point, QueryCursor, Tree};
+
+use crate::markdown::{parse_markdown, MarkdownBlock};
+
+use super::{
+    instance::{Cursor, HighlightType, Language},
+    line_buffer::{
+        highlight_params, language_from_name, HighlightSpans, LineBuffer, TreeSitterParams,
+    },
+};
+
+/// Tree of a full parse running in the background and the edits made since it started
+type BackgroundParse = (Arc<Mutex<Option<Tree>>>, Vec<InputEdit>);
+
+#[derive(Debug, Default)]
+pub struct SyntaxTree {
+    tree: Option<Tree>,
+    /// Byte ranges edited since the tree was last highlighted
+    edited: Vec<(usize, usize)>,
+    background: Option<BackgroundParse>,
+}
+
+/// Position of the cursor in the syntax tree
+pub fn point(cursor: &Cursor) -> Point {
+    Point {
+        row: cursor.row,
+        column: cursor.column,
+    }
+}
+
+impl SyntaxTree {
+    pub fn has_tree(&self) -> bool {
+        self.tree.is_some()
+    }
+
+    /// Returns true while a full parse runs in the background
+    pub fn is_parsing(&self) -> bool {
+        self.background.is_some()
+    }
+
+    /// Keep the tree in sync with an edit of the buffer
+    pub fn edit(&mut self, edit: &InputEdit) {
+        if let Some(tree) = &mut self.tree {
+            tree.edit(edit);
+        }
+        if let Some((_, edits)) = &mut self.background {
+            edits.push(*edit);
+        }
+        for (start, end) in &mut self.edited {
+            *start = shift_offset(*start, edit);
+            *end = shift_offset(*end, edit);
+        }
+        if self.tree.is_some() || self.background.is_some() {
+            self.edited.push((edit.start_byte, edit.new_end_byte));
+        }
+    }
+
+    /// Start a full parse in the background, its tree is stored in the returned slot
+    /// and picked up by `finish_background`
+    pub fn start_background(&mut self) -> Arc<Mutex<Option<Tree>>> {
+        let slot = Arc::new(Mutex::new(None));
+        self.tree = None;
+        self.edited.clear();
+        self.background = Some((slot.clone(), vec![]));
+        slot
+    }
+
+    /// Adopt the tree of the background parse along with the highlights computed
+    /// from it, catching both up with the edits made since the parse started
+    pub fn finish_background(&mut self, spans: &mut HighlightSpans) {
+        let Some((slot, edits)) = self.background.take() else {
+            return;
+        };
+        let Some(mut tree) = slot.lock().ok().and_then(|mut tree| tree.take()) else {
+            return;
+        };
+        for edit in &edits {
+            tree.edit(edit);
+            shift_spans(spans, edit);
+        }
+        self.tree = Some(tree);
+    }
+
+    /// Parse the content reusing the previous tree and highlight the ranges which
+    /// changed since, or everything if there is no tree yet
+    pub fn update(&mut self, params: &TreeSitterParams, content: &str, spans: &mut HighlightSpans) {
+        let Some(old_tree) = self.tree.take() else {
+            // A full parse supersedes one running in the background
+            self.background = None;
+            self.edited.clear();
+            if let Some((tree, full)) = parse_and_highlight(params, content) {
+                self.tree = Some(tree);
+                *spans = full;
+            }
+            return;
+        };
+        let Some(tree) = parse(params, content, Some(&old_tree)) else {
+            return;
+        };
+
+        let mut ranges: Vec<(usize, usize)> = old_tree
+            .changed_ranges(&tree)
+            .map(|range| (range.start_byte, range.end_byte))
+            .collect();
+        ranges.append(&mut self.edited);
+        for (start, end) in line_ranges(content, ranges) {
+            remove_range(spans, start, end);
+            spans.extend(highlight_range(params, &tree, content, start, end));
+        }
+        spans.sort_by_key(|(start, _, _)| *start);
+        self.tree = Some(tree);
+    }
+
+    /// Byte offsets of the brackets from the start to the end byte with their nesting depth
+    pub fn bracket_depths(&self, start: usize, end: usize) -> Vec<(usize, usize)> {
+        let mut brackets = vec![];
+        if let Some(tree) = &self.tree {
+            collect_brackets(tree.root_node(), 0, start, end, &mut brackets);
+        }
+        brackets
+    }
+}
+
+fn is_bracket(node: &Node) -> bool {
+    node.child_count() == 0 && matches!(node.kind(), "(" | ")" | "[" | "]" | "{" | "}")
+}
+
+/// Brackets of the node and its descendants, the children of a node with brackets
+/// are nested one level deeper than its brackets
+fn collect_brackets(
+    node: Node,
+    depth: usize,
+    start: usize,
+    end: usize,
+    brackets: &mut Vec<(usize, usize)>,
+) {
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node
+        .children(&mut cursor)
+        .filter(|child| child.end_byte() > start && child.start_byte() < end)
+        .collect();
+    let inner = if node.children(&mut cursor).any(|child| is_bracket(&child)) {
+        depth + 1
+    } else {
+        depth
+    };
+    for child in children {
+        if is_bracket(&child) {
+            brackets.push((child.start_byte(), depth));
+        } else {
+            collect_brackets(child, inner, start, end, brackets);
+        }
+    }
+}
+
+fn parse(params: &TreeSitterParams, content: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+    let mut parser = Parser::new();
+    parser.set_language(&params.language).ok()?;
+    parser.parse(content, old_tree)
+}
+
+/// Parse the whole content and highlight it
+pub fn parse_and_highlight(
+    params: &TreeSitterParams,
+    content: &str,
+) -> Option<(Tree, HighlightSpans)> {
+    let tree = parse(params, content, None)?;
+    let spans = highlight_range(params, &tree, content, 0, content.len());
+    Some((tree, spans))
+}
+
+/// Highlights of the bytes from start to end. Nested captures take precedence
+/// over the captures containing them and the first pattern wins for the same node.
+fn highlight_range(
+    params: &TreeSitterParams,
+    tree: &Tree,
+    content: &str,
+    start: usize,
+    end: usize,
+) -> HighlightSpans {
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(start..end);
+    let mut captures = vec![];
+    for (query_match, idx) in cursor.captures(&params.query, tree.root_node(), content.as_bytes()) {
+        let capture = query_match.captures[idx];
+        if let Some(highlight_type) = params.capture_types[capture.index as usize] {
+            captures.push((
+                capture.node.start_byte(),
+                capture.node.end_byte(),
+                query_match.pattern_index,
+                highlight_type,
+            ));
+        }
+    }
+    captures.sort_by_key(|(start, end, pattern, _)| (*start, Reverse(*end), *pattern));
+    captures.dedup_by_key(|(start, end, _, _)| (*start, *end));
+
+    let mut painted: Vec<Option<HighlightType>> = vec![None; end - start];
+    for (capture_start, capture_end, _, highlight_type) in captures {
+        for byte in capture_start.max(start)..capture_end.min(end) {
+            painted[byte - start] = Some(highlight_type);
+        }
+    }
+
+    // Injected code is highlighted with its own grammar on top of the host highlights
+    for (node_start, node_end, language) in injections(params, tree, content, start, end) {
+        let Some(injected_params) = highlight_params(language) else {
+            continue;
+        };
+        let Some((_, spans)) =
+            parse_and_highlight(&injected_params, &content[node_start..node_end])
+        else {
+            continue;
+        };
+        for (span_start, span_end, highlight_type) in spans {
+            for byte in (node_start + span_start).max(start)..(node_start + span_end + 1).min(end) {
+                painted[byte - start] = Some(highlight_type);
+            }
+        }
+    }
+
+    let mut spans = vec![];
+    let mut run_start = 0;
+    for idx in 1..=painted.len() {
+        if idx == painted.len() || painted[idx] != painted[run_start] {
+            if let Some(highlight_type) = painted[run_start] {
+                spans.push((start + run_start, start + idx - 1, highlight_type));
+            }
+            run_start = idx;
+        }
+    }
+    spans
+}
+
+/// Byte ranges and languages of the code injected into the host language between
+/// start and end, code nested in an injection is left to the injected language
+fn injections(
+    params: &TreeSitterParams,
+    tree: &Tree,
+    content: &str,
+    start: usize,
+    end: usize,
+) -> Vec<(usize, usize, Language)> {
+    let Some(query) = &params.injections else {
+        return vec![];
+    };
+    let content_index = query.capture_index_for_name("injection.content");
+    let language_index = query.capture_index_for_name("injection.language");
+    let mut cursor = QueryCursor::new();
+    cursor.set_byte_range(start..end);
+
+    let mut injections = vec![];
+    for query_match in cursor.matches(query, tree.root_node(), content.as_bytes()) {
+        let mut name = query
+            .property_settings(query_match.pattern_index)
+            .iter()
+            .find(|property| &*property.key == "injection.language")
+            .and_then(|property| property.value.as_deref().map(str::to_string));
+        let mut node = None;
+        for capture in query_match.captures {
+            if Some(capture.index) == content_index {
+                node = Some(capture.node);
+            } else if Some(capture.index) == language_index {
+                name = content
+                    .get(capture.node.start_byte()..capture.node.end_byte())
+                    .map(str::to_string);
+            }
+        }
+        if let (Some(node), Some(language)) = (node, name.as_deref().and_then(language_from_name)) {
+            injections.push((node.start_byte(), node.end_byte(), language));
+        }
+    }
+    injections.sort_by_key(|(start, end, _)| (*start, Reverse(*end)));
+    let mut outermost: Vec<(usize, usize, Language)> = vec![];
+    for injection in injections {
+        if outermost.last().is_none_or(|last| injection.0 >= last.1) {
+            outermost.push(injection);
+        }
+    }
+    outermost
+}
+
+/// Highlights of the fenced code blocks of a markdown document in the language
+/// named after the opening fence
+pub fn highlight_fenced_code(buffer: &LineBuffer) -> HighlightSpans {
+    let mut spans = vec![];
+    for block in parse_markdown(&buffer.lines) {
+        let MarkdownBlock::CodeBlock {
+            row,
+            language,
+            lines,
+        } = block
+        else {
+            continue;
+        };
+        let Some(params) = language_from_name(&language).and_then(highlight_params) else {
+            continue;
+        };
+        let Some((_, code_spans)) = parse_and_highlight(&params, &lines.join("\n")) else {
+            continue;
+        };
+        let offset = buffer.byte_index_from_row(row + 1, "\n");
+        spans.extend(
+            code_spans
+                .into_iter()
+                .map(|(start, end, highlight_type)| (start + offset, end + offset, highlight_type)),
+        );
+    }
+    spans
+}
+
+/// Ranges expanded to whole lines, sorted and merged where they overlap
+fn line_ranges(content: &str, mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let bytes = content.as_bytes();
+    for (start, end) in &mut ranges {
+        *start = (*start).min(bytes.len());
+        *end = (*end).clamp(*start, bytes.len());
+        *start = bytes[..*start]
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |idx| idx + 1);
+        *end = bytes[*end..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(bytes.len(), |idx| *end + idx + 1);
+    }
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = vec![];
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Offset after the edit of a byte offset before it
+fn shift_offset(offset: usize, edit: &InputEdit) -> usize {
+    if offset <= edit.start_byte {
+        offset
+    } else if offset >= edit.old_end_byte {
+        offset - edit.old_end_byte + edit.new_end_byte
+    } else {
+        edit.new_end_byte
+    }
+}
+
+/// Move highlights after the edit and cut the edited bytes out of the ones overlapping it
+pub fn shift_spans(spans: &mut HighlightSpans, edit: &InputEdit) {
+    let mut shifted = Vec::with_capacity(spans.len() + 1);
+    for (start, end, highlight_type) in spans.drain(..) {
+        if end < edit.start_byte {
+            shifted.push((start, end, highlight_type));
+        } else if start >= edit.old_end_byte {
+            shifted.push((
+                shift_offset(start, edit),
+                shift_offset(end, edit),
+                highlight_type,
+            ));
+        } else {
+            if start < edit.start_byte {
+                shifted.push((start, edit.start_byte - 1, highlight_type));
+            }
+            if end >= edit.old_end_byte {
+                shifted.push((edit.new_end_byte, shift_offset(end, edit), highlight_type));
+            }
+        }
+    }
+    *spans = shifted;
+}
+
+/// Remove the bytes from start to end from the highlights
+fn remove_range(spans: &mut HighlightSpans, start: usize, end: usize) {
+    let mut kept = Vec::with_capacity(spans.len() + 1);
+    for (span_start, span_end, highlight_type) in spans.drain(..) {
+        if span_end < start || span_start >= end {
+            kept.push((span_start, span_end, highlight_type));
+            continue;
+        }
+        if span_start < start {
+            kept.push((span_start, start - 1, highlight_type));
+        }
+        if span_end >= end {
+            kept.push((end, span_end, highlight_type));
+        }
+    }
+    *spans = kept;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::{
+        instance::{Cursor, HighlightType},
+        line_buffer::LineBuffer,
+    };
+
+    #[test]
+    fn incremental_highlights_match_full_parse() {
+        let mut buf = LineBuffer::new(
+            "fn main() {\n    let x = 1;\n}\n".into(),
+            Some("main.rs".into()),
+        );
+        buf.update_highlights();
+
+        buf.insert_text("\"", &Cursor { row: 1, column: 12 }, &None, false);
+        buf.insert_text("// note\n", &Cursor { row: 0, column: 0 }, &None, false);
+        buf.remove_text(
+            &crate::buffer::instance::Selection {
+                cursor: Cursor { row: 2, column: 12 },
+                mark: Cursor { row: 2, column: 13 },
+            },
+            &None,
+            false,
+        );
+        buf.insert_text("fn f() {}\n", &Cursor { row: 3, column: 1 }, &None, false);
+        buf.update_highlights();
+        let incremental = buf.current_highlights().clone();
+
+        let mut full = LineBuffer::new(buf.get_content("\n".into()), Some("main.rs".into()));
+        assert_eq!(&incremental, full.current_highlights());
+    }
+
+    #[test]
+    fn bracket_depths() {
+        let mut buf = LineBuffer::new(
+            "fn main() {\n    f(a[0], \"(\");\n}\n".into(),
+            Some("main.rs".into()),
+        );
+        buf.update_highlights();
+        let depths = |start, end| -> Vec<(char, usize)> {
+            let content = buf.get_content("\n".into());
+            buf.bracket_depths(start, end)
+                .into_iter()
+                .map(|(byte, depth)| (content.as_bytes()[byte] as char, depth))
+                .collect()
+        };
+        // The bracket inside the string is not part of a pair
+        assert_eq!(
+            depths(0, 100),
+            [
+                ('(', 0),
+                (')', 0),
+                ('{', 0),
+                ('(', 1),
+                ('[', 2),
+                (']', 2),
+                (')', 1),
+                ('}', 0)
+            ]
+        );
+        assert_eq!(depths(12, 30), [('(', 1), ('[', 2), (']', 2), (')', 1)]);
+    }
+
+    #[test]
+    fn injected_highlights() {
+        // The string is only recognized by parsing the macro arguments as rust
+        let mut buf = LineBuffer::new("println!(\"hi\");\n".into(), Some("main.rs".into()));
+        assert!(buf
+            .current_highlights()
+            .contains(&(9, 12, HighlightType::Green)));
+
+        let mut buf = LineBuffer::new(
+            "# Title\n```python\n# note\n```\n".into(),
+            Some("notes.md".into()),
+        );
+        assert_eq!(
+            buf.current_highlights(),
+            &vec![(18, 23, HighlightType::Gray)]
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Tree of the callers or callees of a symbol, whose calls are requested
+//! from the language server as its nodes are expanded.
+
+use crate::lsp::types::CallHierarchyItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallDirection {
+    /// Callers of the symbol
+    Incoming,
+    /// Functions called by the symbol
+    Outgoing,
+}
+
+impl CallDirection {
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Incoming => "callHierarchy/incomingCalls",
+            Self::Outgoing => "callHierarchy/outgoingCalls",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Incoming => "Incoming Calls",
+            Self::Outgoing => "Outgoing Calls",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CallNode {
+    pub item: CallHierarchyItem,
+    pub depth: usize,
+    pub expanded: bool,
+    /// The calls of the node were received
+    loaded: bool,
+}
+
+impl CallNode {
+    fn new(item: CallHierarchyItem, depth: usize) -> Self {
+        Self {
+            item,
+            depth,
+            expanded: false,
+            loaded: false,
+        }
+    }
+}
+
+/// Call hierarchy of a symbol, stored as nodes in depth first order
+#[derive(Debug, Clone)]
+pub struct CallTree {
+    pub nodes: Vec<CallNode>,
+    pub direction: CallDirection,
+    /// Selected row of the visible nodes
+    pub idx: usize,
+    /// Node waiting for its calls from the language server
+    pending: Option<usize>,
+}
+
+impl CallTree {
+    /// Tree of the symbol, the calls of the root are requested right away
+    pub fn new(root: CallHierarchyItem, direction: CallDirection) -> Self {
+        let mut tree = Self {
+            nodes: vec![CallNode::new(root, 0)],
+            direction,
+            idx: 0,
+            pending: None,
+        };
+        tree.expand();
+        tree
+    }
+
+    /// Indices of the nodes which are not inside collapsed nodes
+    pub fn visible(&self) -> Vec<usize> {
+        let mut visible = vec![];
+        let mut collapsed_depth = None;
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if collapsed_depth.is_some_and(|depth| node.depth > depth) {
+                continue;
+            }
+            collapsed_depth = (!node.expanded).then_some(node.depth);
+            visible.push(idx);
+        }
+        visible
+    }
+
+    pub fn selected(&self) -> Option<&CallNode> {
+        self.visible().get(self.idx).map(|idx| &self.nodes[*idx])
+    }
+
+    /// Indented name of the node with a marker for whether it is expanded
+    pub fn label(&self, idx: usize) -> String {
+        let node = &self.nodes[idx];
+        let has_children = self
+            .nodes
+            .get(idx + 1)
+            .is_some_and(|next| next.depth > node.depth);
+        let marker = if node.loaded && !has_children {
+            " "
+        } else if node.expanded {
+            "▾"
+        } else {
+            "▸"
+        };
+        let mut label = format!("{}{} {}", "  ".repeat(node.depth), marker, node.item.name);
+        if !node.item.detail.is_empty() {
+            label.push_str("  ");
+            label.push_str(&node.item.detail);
+        }
+        label
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.visible().len();
+        self.idx = (self.idx + 1) % count;
+    }
+
+    pub fn select_previous(&mut self) {
+        let count = self.visible().len();
+        self.idx = (self.idx + count - 1) % count;
+    }
+
+    /// Expand the selected node, returning its item if its calls have to be requested
+    pub fn expand(&mut self) -> Option<CallHierarchyItem> {
+        let idx = *self.visible().get(self.idx)?;
+        let node = &mut self.nodes[idx];
+        node.expanded = true;
+        if node.loaded || self.pending.is_some() {
+            return None;
+        }
+        self.pending = Some(idx);
+        Some(node.item.clone())
+    }
+
+    /// Collapse the selected node, or select its parent if it is collapsed
+    pub fn collapse(&mut self) {
+        let visible = self.visible();
+        let Some(idx) = visible.get(self.idx).copied() else {
+            return;
+        };
+        if self.nodes[idx].expanded && self.pending != Some(idx) {
+            self.nodes[idx].expanded = false;
+            return;
+        }
+        let depth = self.nodes[idx].depth;
+        if let Some(row) = visible[..self.idx]
+            .iter()
+            .rposition(|parent| self.nodes[*parent].depth + 1 == depth)
+        {
+            self.idx = row;
+        }
+    }
+
+    /// Add the calls received for the pending node as its children
+    pub fn set_calls(&mut self, calls: Vec<CallHierarchyItem>) {
+        let Some(idx) = self.pending.take() else {
+            return;
+        };
+        let depth = self.nodes[idx].depth + 1;
+        self.nodes[idx].loaded = true;
+        self.nodes.splice(
+            idx + 1..idx + 1,
+            calls.into_iter().map(|item| CallNode::new(item, depth)),
+        );
+    }
+
+    /// Start over from the root in the other direction, returning the root to request calls for
+    pub fn toggle_direction(&mut self) -> Option<CallHierarchyItem> {
+        let direction = match self.direction {
+            CallDirection::Incoming => CallDirection::Outgoing,
+            CallDirection::Outgoing => CallDirection::Incoming,
+        };
+        *self = Self::new(self.nodes[0].item.clone(), direction);
+        self.pending.map(|idx| self.nodes[idx].item.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::{CallDirection, CallTree};
+    use crate::{
+        buffer::instance::{Cursor, Selection},
+        lsp::types::CallHierarchyItem,
+    };
+
+    fn item(name: &str) -> CallHierarchyItem {
+        let cursor = Cursor { row: 0, column: 0 };
+        CallHierarchyItem {
+            name: name.to_string(),
+            detail: String::new(),
+            path: String::new(),
+            range: Selection {
+                cursor,
+                mark: cursor,
+            },
+            item: Value::Null,
+        }
+    }
+
+    #[test]
+    fn call_tree() {
+        let mut tree = CallTree::new(item("main"), CallDirection::Incoming);
+        // The root is pending so expanding does not request it again
+        assert!(tree.expand().is_none());
+        tree.set_calls(vec![item("a"), item("b")]);
+        assert_eq!(tree.visible(), vec![0, 1, 2]);
+
+        tree.select_next();
+        assert_eq!(tree.expand().unwrap().name, "a");
+        tree.set_calls(vec![item("c")]);
+        let names: Vec<&str> = tree
+            .visible()
+            .iter()
+            .map(|idx| tree.nodes[*idx].item.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["main", "a", "c", "b"]);
+        assert_eq!(tree.label(1), "  ▾ a");
+        assert_eq!(tree.label(3), "  ▸ b");
+
+        tree.collapse();
+        assert_eq!(tree.visible(), vec![0, 1, 3]);
+        tree.collapse();
+        assert_eq!(tree.selected().unwrap().item.name, "main");
+        tree.select_previous();
+        assert_eq!(tree.selected().unwrap().item.name, "b");
+
+        assert_eq!(tree.toggle_direction().unwrap().name, "main");
+        assert_eq!(tree.direction, CallDirection::Outgoing);
+        assert_eq!(tree.nodes.len(), 1);
+    }
+}
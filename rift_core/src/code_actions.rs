@@ -0,0 +1,409 @@
+//! Code actions offered by the language server for the cursor range and applying
+//! their workspace edits, or the ones requested by the server, through the buffers.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+use crate::{
+    buffer::instance::{Cursor, Selection},
+    lsp::{
+        client::LSPClientHandle,
+        types::{
+            protocol, CodeAction, Diagnostic, DocumentChange, RequestMessage, ResponseError,
+            TextEdit, WorkspaceEdit,
+        },
+    },
+    notifications::NotificationLevel,
+    state::EditorState,
+};
+
+/// JSON-RPC error code for requests the client does not handle
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// Code actions being shown to the user
+#[derive(Debug, Clone)]
+pub struct CodeActionMenu {
+    pub actions: Vec<CodeAction>,
+    pub idx: usize,
+}
+
+/// Diagnostics overlapping the range, a diagnostic touching an empty range counts
+pub fn diagnostics_in_range<'a>(
+    diagnostics: &'a [Diagnostic],
+    range: &Selection,
+) -> Vec<&'a Diagnostic> {
+    let (start, end) = range.in_order();
+    diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            let (diagnostic_start, diagnostic_end) = diagnostic.range.in_order();
+            diagnostic_start <= end && start <= diagnostic_end
+        })
+        .collect()
+}
+
+/// Text edits in the order they can be applied one after another without
+/// shifting the positions of the remaining edits: last edit first, and edits at the
+/// same position in reverse so that their text ends up in the original order
+pub fn edits_in_apply_order(edits: &[TextEdit]) -> Vec<&TextEdit> {
+    let mut edits: Vec<(usize, &TextEdit)> = edits.iter().enumerate().collect();
+    edits.sort_by(|(a_idx, a), (b_idx, b)| {
+        let a_start = a.range.in_order().0;
+        let b_start = b.range.in_order().0;
+        b_start.partial_cmp(a_start).unwrap().then(b_idx.cmp(a_idx))
+    });
+    edits.into_iter().map(|(_, edit)| edit).collect()
+}
+
+/// Apply the changes in order, opening the edited files which are not open yet.
+/// Stops at the first change which fails, keeping the changes applied before it,
+/// the edits of a file are applied only if all of them are in the file.
+/// The cursor of each edited buffer moves to its first edit and the current buffer stays current.
+pub fn apply_workspace_edit(
+    state: &mut EditorState,
+    edit: &WorkspaceEdit,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) -> Result<()> {
+    let current = state.buffer_idx;
+    let result = edit
+        .changes
+        .iter()
+        .try_for_each(|change| apply_document_change(state, change, current, lsp_handle));
+    if current.is_some_and(|id| state.buffers.contains_key(&id)) {
+        state.buffer_idx = current;
+    }
+    state.update_view = true;
+    result
+}
+
+/// Byte position of a position with a UTF-16 column in the lines, the position
+/// just past the last line is the end of the last line
+fn byte_cursor(lines: &[String], position: &Cursor) -> Result<Cursor> {
+    if position.row == lines.len() && position.column == 0 {
+        let row = position.row.saturating_sub(1);
+        return Ok(Cursor {
+            row,
+            column: lines.get(row).map(String::len).unwrap_or(0),
+        });
+    }
+    let line = lines
+        .get(position.row)
+        .with_context(|| format!("Line {} is out of range", position.row + 1))?;
+    let column = protocol::utf16_to_byte_column(line, position.column).with_context(|| {
+        format!(
+            "Column {} is not a character boundary of line {}",
+            position.column,
+            position.row + 1
+        )
+    })?;
+    Ok(Cursor {
+        row: position.row,
+        column,
+    })
+}
+
+/// Text edits with byte columns of edits with UTF-16 columns, failing if any
+/// of their positions is not in the lines
+fn byte_edits(lines: &[String], edits: &[TextEdit]) -> Result<Vec<TextEdit>> {
+    edits
+        .iter()
+        .map(|edit| {
+            Ok(TextEdit {
+                text: edit.text.clone(),
+                range: Selection {
+                    mark: byte_cursor(lines, &edit.range.mark)?,
+                    cursor: byte_cursor(lines, &edit.range.cursor)?,
+                },
+            })
+        })
+        .collect()
+}
+
+fn open_buffer(state: &EditorState, path: &str) -> Option<u32> {
+    state
+        .buffers
+        .iter()
+        .find(|(_, buffer)| buffer.file_path.as_deref() == Some(path))
+        .map(|(id, _)| *id)
+}
+
+fn apply_document_change(
+    state: &mut EditorState,
+    change: &DocumentChange,
+    current: Option<u32>,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) -> Result<()> {
+    match change {
+        DocumentChange::Edit { path, edits } => {
+            let buffer_id = match open_buffer(state, path) {
+                Some(id) => id,
+                None => {
+                    state.open_location(path, Cursor { row: 0, column: 0 }, lsp_handle);
+                    match open_buffer(state, path) {
+                        Some(id) => id,
+                        None => bail!("Failed to open {}", path),
+                    }
+                }
+            };
+            // Language servers of other languages are not notified of the edits
+            let language = current.map(|id| state.get_buffer_by_id(id).0.language);
+            let lsp_handle = if Some(state.buffers[&buffer_id].language) == language {
+                lsp_handle
+            } else {
+                &None
+            };
+            let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+            // Every edit is checked before any is applied
+            let edits = byte_edits(&buffer.lines, edits).with_context(|| path.clone())?;
+            let mut first = None;
+            for text_edit in edits_in_apply_order(&edits) {
+                let (_text, start) = buffer.remove_text(&text_edit.range, lsp_handle, true);
+                buffer.insert_text(&text_edit.text, &start, lsp_handle, true);
+                first = Some(start);
+            }
+            if let Some(cursor) = first {
+                instance.cursor = cursor;
+                instance.selection.cursor = cursor;
+                instance.selection.mark = cursor;
+                instance.column_level = cursor.column;
+            }
+        }
+        DocumentChange::Create {
+            path,
+            overwrite,
+            ignore_if_exists,
+        } => {
+            if Path::new(path).exists() && !overwrite {
+                if *ignore_if_exists {
+                    return Ok(());
+                }
+                bail!("{} already exists", path);
+            }
+            if let Some(parent) = Path::new(path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, "")?;
+        }
+        DocumentChange::Rename {
+            old_path,
+            new_path,
+            overwrite,
+            ignore_if_exists,
+        } => {
+            if Path::new(new_path).exists() && !overwrite {
+                if *ignore_if_exists {
+                    return Ok(());
+                }
+                bail!("{} already exists", new_path);
+            }
+            if let Some(parent) = Path::new(new_path).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::rename(old_path, new_path)?;
+            if let Some(id) = open_buffer(state, old_path) {
+                state
+                    .get_buffer_by_id_mut(id)
+                    .0
+                    .set_file_path(new_path.clone());
+            }
+        }
+        DocumentChange::Delete {
+            path,
+            recursive,
+            ignore_if_not_exists,
+        } => {
+            let path_ref = Path::new(path);
+            if !path_ref.exists() {
+                if *ignore_if_not_exists {
+                    return Ok(());
+                }
+                bail!("{} does not exist", path);
+            }
+            let deleted: Vec<u32> = state
+                .buffers
+                .iter()
+                .filter(|(_, buffer)| {
+                    buffer
+                        .file_path
+                        .as_deref()
+                        .is_some_and(|file_path| Path::new(file_path).starts_with(path_ref))
+                })
+                .map(|(id, _)| *id)
+                .collect();
+            // Unsaved changes of the deleted files are not thrown away
+            if let Some(id) = deleted.iter().find(|id| state.buffers[id].modified) {
+                bail!(
+                    "{} has unsaved changes",
+                    state.buffers[id].file_path.clone().unwrap_or_default()
+                );
+            }
+            if path_ref.is_dir() {
+                if *recursive {
+                    std::fs::remove_dir_all(path)?;
+                } else {
+                    std::fs::remove_dir(path)?;
+                }
+            } else {
+                std::fs::remove_file(path)?;
+            }
+            for id in deleted {
+                state.remove_buffer(id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Respond to a request from the language server, applying the edits of `workspace/applyEdit`
+pub fn handle_server_request(
+    state: &mut EditorState,
+    request: RequestMessage,
+    lsp_handle: &mut LSPClientHandle,
+) {
+    let (result, error) = match request.method.as_str() {
+        "workspace/applyEdit" => {
+            let applied = protocol::parse_apply_edit(request.params.unwrap_or_default())
+                .and_then(|edit| apply_workspace_edit(state, &edit, &Some(&mut *lsp_handle)));
+            let result = match applied {
+                Ok(()) => json!({ "applied": true }),
+                Err(err) => {
+                    let reason = format!("{:#}", err);
+                    state.notify(
+                        NotificationLevel::Error,
+                        format!("Failed to apply workspace edit: {}", reason),
+                    );
+                    json!({ "applied": false, "failureReason": reason })
+                }
+            };
+            (Some(result), None)
+        }
+        "window/workDoneProgress/create" | "client/registerCapability" => (Some(Value::Null), None),
+        // Configuration is not provided, servers fall back to their defaults
+        "workspace/configuration" => {
+            let items = request
+                .params
+                .as_ref()
+                .and_then(|params| params.get("items"))
+                .and_then(Value::as_array)
+                .map(Vec::len)
+                .unwrap_or(0);
+            (Some(Value::Array(vec![Value::Null; items])), None)
+        }
+        method => (
+            None,
+            Some(ResponseError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unhandled method: {}", method),
+                data: None,
+            }),
+        ),
+    };
+    if let Err(err) = lsp_handle.send_response_sync(request.id, result, error) {
+        tracing::error!("Failed to respond to language server: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_workspace_edit, byte_edits, diagnostics_in_range, edits_in_apply_order};
+    use crate::{
+        buffer::{
+            instance::{Cursor, Selection},
+            line_buffer::LineBuffer,
+        },
+        lsp::types::{Diagnostic, DiagnosticSeverity, DocumentChange, TextEdit, WorkspaceEdit},
+        state::EditorState,
+    };
+
+    fn selection(start: (usize, usize), end: (usize, usize)) -> Selection {
+        Selection {
+            mark: Cursor {
+                row: start.0,
+                column: start.1,
+            },
+            cursor: Cursor {
+                row: end.0,
+                column: end.1,
+            },
+        }
+    }
+
+    #[test]
+    fn code_action_ranges() {
+        let diagnostic = |start, end| Diagnostic {
+            range: selection(start, end),
+            severity: DiagnosticSeverity::Error,
+            code: String::new(),
+            source: String::new(),
+            message: String::new(),
+        };
+        let diagnostics = vec![diagnostic((0, 2), (0, 5)), diagnostic((2, 0), (3, 1))];
+        assert_eq!(
+            diagnostics_in_range(&diagnostics, &selection((0, 5), (0, 5))).len(),
+            1
+        );
+        assert_eq!(
+            diagnostics_in_range(&diagnostics, &selection((0, 0), (2, 0))).len(),
+            2
+        );
+        assert!(diagnostics_in_range(&diagnostics, &selection((1, 0), (1, 4))).is_empty());
+
+        let edit = |text: &str, start, end| TextEdit {
+            text: text.to_string(),
+            range: selection(start, end),
+        };
+        let edits = vec![
+            edit("a", (0, 0), (0, 0)),
+            edit("b", (0, 0), (0, 0)),
+            edit("c", (1, 2), (1, 4)),
+        ];
+        let texts: Vec<&str> = edits_in_apply_order(&edits)
+            .iter()
+            .map(|edit| edit.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn validate_edit_positions() {
+        let lines = vec!["a😀b".to_string(), "é".to_string()];
+        let edit = |start, end| TextEdit {
+            text: String::new(),
+            range: selection(start, end),
+        };
+        let edits = byte_edits(&lines, &[edit((0, 3), (0, 4)), edit((1, 1), (2, 0))]).unwrap();
+        assert_eq!(edits[0].range.mark, Cursor { row: 0, column: 5 });
+        assert_eq!(edits[0].range.cursor, Cursor { row: 0, column: 6 });
+        assert_eq!(edits[1].range.cursor, Cursor { row: 1, column: 2 });
+        // Inside the surrogate pair, past the end of the line and past the last line
+        assert!(byte_edits(&lines, &[edit((0, 2), (0, 2))]).is_err());
+        assert!(byte_edits(&lines, &[edit((1, 0), (1, 2))]).is_err());
+        assert!(byte_edits(&lines, &[edit((3, 0), (3, 0))]).is_err());
+    }
+
+    #[test]
+    fn keep_unsaved_deleted_files() {
+        let file =
+            std::env::temp_dir().join(format!("rift_code_actions_{}.rs", std::process::id()));
+        std::fs::write(&file, "a").unwrap();
+        let path = file.to_str().unwrap().to_string();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        let id = state.add_buffer(LineBuffer::new("b".into(), Some(path.clone())));
+        state.buffers.get_mut(&id).unwrap().modified = true;
+
+        let edit = WorkspaceEdit {
+            changes: vec![DocumentChange::Delete {
+                path,
+                recursive: false,
+                ignore_if_not_exists: false,
+            }],
+        };
+        assert!(apply_workspace_edit(&mut state, &edit, &None).is_err());
+        assert!(file.exists());
+        assert!(state.buffers.contains_key(&id));
+        std::fs::remove_file(file).unwrap();
+    }
+}
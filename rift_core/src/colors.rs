@@ -0,0 +1,237 @@
+//! Color literals shown as swatches in stylesheet and markup buffers.
+//! Colors are found by scanning the text since no language server providing
+//! `textDocument/documentColor` is started for these languages.
+
+use crate::buffer::instance::Language;
+
+/// Notation a color literal is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorFormat {
+    Hex,
+    Rgb,
+    Hsl,
+}
+
+/// Color literal found in a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorLiteral {
+    /// Start and end column of the literal
+    pub start: usize,
+    pub end: usize,
+    pub rgba: [u8; 4],
+    pub format: ColorFormat,
+}
+
+/// Returns true if color swatches are shown for the language
+pub fn has_color_literals(language: Language) -> bool {
+    matches!(language, Language::CSS | Language::HTML)
+}
+
+/// Hex, rgb(a) and hsl(a) color literals in a line
+pub fn find_colors(line: &str) -> Vec<ColorLiteral> {
+    let mut colors = vec![];
+    let mut idx = 0;
+    while idx < line.len() {
+        let rest = &line[idx..];
+        let found = if rest.starts_with('#') {
+            parse_hex(rest)
+        } else if rest.starts_with("rgb") {
+            parse_function(rest, ColorFormat::Rgb)
+        } else if rest.starts_with("hsl") {
+            parse_function(rest, ColorFormat::Hsl)
+        } else {
+            None
+        };
+        // Literals must not be part of a longer identifier
+        let boundary = line[..idx]
+            .chars()
+            .last()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '-' || c == '_'));
+        match found {
+            Some((length, rgba, format)) if boundary => {
+                colors.push(ColorLiteral {
+                    start: idx,
+                    end: idx + length,
+                    rgba,
+                    format,
+                });
+                idx += length;
+            }
+            _ => idx += rest.chars().next().map(char::len_utf8).unwrap_or(1),
+        }
+    }
+    colors
+}
+
+fn parse_hex(text: &str) -> Option<(usize, [u8; 4], ColorFormat)> {
+    let digits: String = text[1..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = |hex: &str| u8::from_str_radix(hex, 16).ok();
+    let rgba = match digits.len() {
+        3 | 4 => {
+            let expanded: String = digits.chars().flat_map(|c| [c, c]).collect();
+            let alpha = if digits.len() == 4 {
+                &expanded[6..8]
+            } else {
+                "ff"
+            };
+            [
+                value(&expanded[0..2])?,
+                value(&expanded[2..4])?,
+                value(&expanded[4..6])?,
+                value(alpha)?,
+            ]
+        }
+        6 | 8 => {
+            let alpha = if digits.len() == 8 {
+                &digits[6..8]
+            } else {
+                "ff"
+            };
+            [
+                value(&digits[0..2])?,
+                value(&digits[2..4])?,
+                value(&digits[4..6])?,
+                value(alpha)?,
+            ]
+        }
+        _ => return None,
+    };
+    Some((digits.len() + 1, rgba, ColorFormat::Hex))
+}
+
+fn parse_function(text: &str, format: ColorFormat) -> Option<(usize, [u8; 4], ColorFormat)> {
+    let open = text.find('(')?;
+    if !matches!(&text[3..open], "" | "a") {
+        return None;
+    }
+    let close = open + text[open..].find(')')?;
+    let args: Vec<&str> = text[open + 1..close]
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|arg| !arg.is_empty())
+        .collect();
+    if !(args.len() == 3 || args.len() == 4) {
+        return None;
+    }
+    let number = |arg: &str| -> Option<f32> {
+        arg.trim_end_matches(['%', 'd', 'e', 'g'])
+            .parse::<f32>()
+            .ok()
+    };
+    let alpha = match args.get(3) {
+        Some(arg) if arg.ends_with('%') => number(arg)? / 100.0,
+        Some(arg) => number(arg)?,
+        None => 1.0,
+    };
+    let alpha = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let [r, g, b] = match format {
+        ColorFormat::Rgb => {
+            let channel = |arg: &str| -> Option<u8> {
+                let value = number(arg)?;
+                let value = if arg.ends_with('%') {
+                    value * 2.55
+                } else {
+                    value
+                };
+                Some(value.clamp(0.0, 255.0).round() as u8)
+            };
+            [channel(args[0])?, channel(args[1])?, channel(args[2])?]
+        }
+        _ => hsl_to_rgb(
+            number(args[0])?,
+            number(args[1])? / 100.0,
+            number(args[2])? / 100.0,
+        ),
+    };
+    Some((close + 1, [r, g, b, alpha], format))
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [u8; 3] {
+    let saturation = saturation.clamp(0.0, 1.0);
+    let lightness = lightness.clamp(0.0, 1.0);
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r, g, b].map(|channel| ((channel + m) * 255.0).round() as u8)
+}
+
+fn rgb_to_hsl([r, g, b]: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = [r, g, b].map(|channel| channel as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (hue, saturation, lightness)
+}
+
+/// Write a color in the given notation
+pub fn format_color(rgba: [u8; 4], format: ColorFormat) -> String {
+    let [r, g, b, a] = rgba;
+    let alpha = (a as f32 / 255.0 * 100.0).round() / 100.0;
+    match format {
+        ColorFormat::Hex if a == 255 => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        ColorFormat::Hex => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+        ColorFormat::Rgb if a == 255 => format!("rgb({}, {}, {})", r, g, b),
+        ColorFormat::Rgb => format!("rgba({}, {}, {}, {})", r, g, b, alpha),
+        ColorFormat::Hsl => {
+            let (hue, saturation, lightness) = rgb_to_hsl([r, g, b]);
+            let (hue, saturation, lightness) = (
+                hue.round(),
+                (saturation * 100.0).round(),
+                (lightness * 100.0).round(),
+            );
+            if a == 255 {
+                format!("hsl({}, {}%, {}%)", hue, saturation, lightness)
+            } else {
+                format!("hsla({}, {}%, {}%, {})", hue, saturation, lightness, alpha)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_colors, format_color, ColorFormat};
+
+    #[test]
+    fn color_literals() {
+        let colors = find_colors("a { color: #f00; background: rgba(0, 128, 255, 0.5) }");
+        assert_eq!(colors.len(), 2);
+        assert_eq!((colors[0].start, colors[0].end), (11, 15));
+        assert_eq!(colors[0].rgba, [255, 0, 0, 255]);
+        assert_eq!(colors[1].rgba, [0, 128, 255, 128]);
+
+        let hsl = find_colors("hsl(120, 100%, 50%)");
+        assert_eq!(hsl[0].rgba, [0, 255, 0, 255]);
+        assert_eq!(
+            format_color(hsl[0].rgba, ColorFormat::Hsl),
+            "hsl(120, 100%, 50%)"
+        );
+        assert!(find_colors("issue#123 and #zzz").is_empty());
+    }
+}
@@ -0,0 +1,959 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    actions::Action,
+    buffer::line_buffer::LineEnding,
+    io::{encoding::Encoding, file_io},
+    kill_ring::KillRing,
+    replace,
+    search::{PastSearch, SearchKind},
+    snippets::Snippets,
+    state::EditorState,
+};
+
+/// Number of recently executed commands which are remembered
+pub const COMMAND_HISTORY_SIZE: usize = 50;
+
+/// Command listed in the command palette
+pub struct PaletteCommand {
+    pub name: &'static str,
+    pub keybinding: &'static str,
+    pub action: fn() -> Action,
+}
+
+/// Command run with an argument typed in the command palette
+pub struct PromptCommand {
+    pub name: &'static str,
+    pub prompt: &'static str,
+    pub action: fn(&str) -> Result<Action>,
+    /// Past arguments (most recent first) cycled through with up and down
+    pub history: Option<fn(&EditorState) -> Vec<String>>,
+}
+
+/// Commands prompting for an argument once selected in the command palette
+pub fn prompt_commands() -> Vec<PromptCommand> {
+    vec![
+        PromptCommand {
+            name: "Go To Line…",
+            prompt: "Line",
+            action: |input| {
+                let line: usize = input.trim().parse().context("Invalid line number")?;
+                Ok(Action::GoToLine(line.saturating_sub(1)))
+            },
+            history: None,
+        },
+        PromptCommand {
+            name: "Search Buffer…",
+            prompt: "Search",
+            action: |input| Ok(Action::SearchBuffer(input.to_string())),
+            history: Some(|state| state.search_history.queries(SearchKind::Buffer)),
+        },
+        PromptCommand {
+            name: "Search Workspace…",
+            prompt: "Search",
+            action: |input| Ok(Action::SearchWorkspace(input.to_string())),
+            history: Some(|state| state.search_history.queries(SearchKind::Workspace)),
+        },
+        PromptCommand {
+            name: "Open Recent File…",
+            prompt: "Path",
+            action: |input| Ok(Action::OpenPath(input.to_string())),
+            history: Some(|state| state.recent_files.entries.iter().cloned().collect()),
+        },
+        PromptCommand {
+            name: "Set Encoding…",
+            prompt: "Encoding",
+            action: |input| Ok(Action::SetBufferEncoding(Encoding::parse(input)?)),
+            history: Some(|_state| {
+                Encoding::ALL
+                    .iter()
+                    .map(|encoding| encoding.label().to_string())
+                    .collect()
+            }),
+        },
+        PromptCommand {
+            name: "Search History…",
+            prompt: "Past search",
+            action: |input| Ok(PastSearch::parse(input)?.action()),
+            history: Some(|state| state.search_history.labels()),
+        },
+        PromptCommand {
+            name: "Replace In Buffer…",
+            prompt: "pattern/replacement/flags",
+            action: |input| {
+                let (pattern, replacement, flags) = replace::parse_replace_command(input)?;
+                Ok(Action::ReplaceInBuffer(pattern, replacement, flags))
+            },
+            history: None,
+        },
+        PromptCommand {
+            name: "Replace In Selection…",
+            prompt: "pattern/replacement/flags",
+            action: |input| {
+                let (pattern, replacement, flags) = replace::parse_replace_command(input)?;
+                Ok(Action::ReplaceInSelection(pattern, replacement, flags))
+            },
+            history: None,
+        },
+        PromptCommand {
+            name: "Replace In Workspace…",
+            prompt: "pattern/replacement/flags",
+            action: |input| {
+                let (pattern, replacement, flags) = replace::parse_replace_command(input)?;
+                Ok(Action::ReplaceInWorkspace(pattern, replacement, flags))
+            },
+            history: None,
+        },
+        PromptCommand {
+            name: "Filter Selection Through Command…",
+            prompt: "Command",
+            action: |input| Ok(Action::FilterSelectionThroughCommand(input.to_string())),
+            history: None,
+        },
+        PromptCommand {
+            name: "Paste From History…",
+            prompt: "Entry",
+            action: |input| {
+                let idx = KillRing::parse_preview(input).context("Invalid kill ring entry")?;
+                Ok(Action::PasteFromHistory(idx))
+            },
+            history: Some(|state| state.kill_ring.previews()),
+        },
+        PromptCommand {
+            name: "Set Mark…",
+            prompt: "Mark",
+            action: |input| Ok(Action::SetMark(parse_char(input)?)),
+            history: None,
+        },
+        PromptCommand {
+            name: "Go To Mark…",
+            prompt: "Mark",
+            action: |input| Ok(Action::GoToMark(parse_char(input)?)),
+            history: None,
+        },
+        PromptCommand {
+            name: "Surround Selection…",
+            prompt: "Character",
+            action: |input| Ok(Action::SurroundSelection(parse_char(input)?)),
+            history: None,
+        },
+        PromptCommand {
+            name: "Change Surround…",
+            prompt: "Current and new characters",
+            action: |input| {
+                let mut chars = input.chars().filter(|c| !c.is_whitespace());
+                match (chars.next(), chars.next(), chars.next()) {
+                    (Some(from), Some(to), None) => Ok(Action::ChangeSurround(from, to)),
+                    _ => bail!("Expected the current and the new character, such as ( ["),
+                }
+            },
+            history: None,
+        },
+        PromptCommand {
+            name: "Delete Surround…",
+            prompt: "Character",
+            action: |input| Ok(Action::DeleteSurround(parse_char(input)?)),
+            history: None,
+        },
+        PromptCommand {
+            name: "Insert Snippet…",
+            prompt: "Snippet",
+            action: |input| {
+                Ok(Action::ExpandSnippet(
+                    Snippets::parse_label(input).to_string(),
+                ))
+            },
+            history: Some(|state| {
+                let (buffer, _instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+                state.snippets.labels(buffer.language)
+            }),
+        },
+        PromptCommand {
+            name: "Open Path…",
+            prompt: "Path",
+            action: |input| Ok(Action::OpenPath(input.trim().to_string())),
+            history: None,
+        },
+        PromptCommand {
+            name: "Open Scratch…",
+            prompt: "Name",
+            action: |input| Ok(Action::OpenScratch(input.to_string())),
+            history: None,
+        },
+        PromptCommand {
+            name: "Task: Run…",
+            prompt: "Task",
+            action: |input| Ok(Action::RunTask(input.trim().to_string())),
+            history: None,
+        },
+        PromptCommand {
+            name: "Filter Logs…",
+            prompt: "Level and target",
+            action: |input| Ok(Action::FilterLogs(input.to_string())),
+            history: None,
+        },
+        PromptCommand {
+            name: "Enter Mode…",
+            prompt: "Mode",
+            action: |input| Ok(Action::EnterMode(input.trim().to_string())),
+            history: Some(|state| {
+                state
+                    .preferences
+                    .modes
+                    .iter()
+                    .map(|mode| mode.name.clone())
+                    .collect()
+            }),
+        },
+        PromptCommand {
+            name: "Run Action…",
+            prompt: "Action",
+            action: parse_action,
+            history: None,
+        },
+    ]
+}
+
+/// A single character, such as the name of a mark
+fn parse_char(input: &str) -> Result<char> {
+    let mut chars = input.trim().chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => bail!("Expected a single character"),
+    }
+}
+
+/// Parse an action name optionally followed by its arguments as json,
+/// such as `Undo`, `GoToLine 10` or `RunTask "build"`
+pub fn parse_action(input: &str) -> Result<Action> {
+    let input = input.trim();
+    let value = match input.split_once(char::is_whitespace) {
+        Some((name, arguments)) => {
+            let arguments: serde_json::Value = serde_json::from_str(arguments.trim())
+                .with_context(|| format!("Invalid arguments: {}", arguments.trim()))?;
+            serde_json::json!({ name: arguments })
+        }
+        None => serde_json::Value::String(input.to_string()),
+    };
+    serde_json::from_value(value).with_context(|| format!("Unknown action: {}", input))
+}
+
+/// Commands available in the command palette
+pub fn commands() -> Vec<PaletteCommand> {
+    vec![
+        PaletteCommand {
+            name: "Open File",
+            keybinding: "f",
+            action: || Action::OpenFile,
+        },
+        PaletteCommand {
+            name: "Go To File Under Cursor",
+            keybinding: "ctrl+g",
+            action: || Action::GotoFileUnderCursor,
+        },
+        PaletteCommand {
+            name: "Apply Workspace Replace",
+            keybinding: "",
+            action: || Action::ApplyWorkspaceReplace,
+        },
+        PaletteCommand {
+            name: "Cycle Paste",
+            keybinding: "alt+p",
+            action: || Action::CyclePaste,
+        },
+        PaletteCommand {
+            name: "Fold Toggle",
+            keybinding: "alt+z",
+            action: || Action::FoldToggle,
+        },
+        PaletteCommand {
+            name: "Fold All",
+            keybinding: "",
+            action: || Action::FoldAll,
+        },
+        PaletteCommand {
+            name: "Unfold All",
+            keybinding: "",
+            action: || Action::UnfoldAll,
+        },
+        PaletteCommand {
+            name: "Next Tab Stop",
+            keybinding: "tab",
+            action: || Action::NextTabStop,
+        },
+        PaletteCommand {
+            name: "Previous Tab Stop",
+            keybinding: "shift+tab",
+            action: || Action::PreviousTabStop,
+        },
+        PaletteCommand {
+            name: "Select Function",
+            keybinding: "",
+            action: || Action::SelectFunction,
+        },
+        PaletteCommand {
+            name: "Select Block",
+            keybinding: "",
+            action: || Action::SelectBlock,
+        },
+        PaletteCommand {
+            name: "Select Argument",
+            keybinding: "",
+            action: || Action::SelectArgument,
+        },
+        PaletteCommand {
+            name: "Expand Selection To Parent Node",
+            keybinding: "alt+o",
+            action: || Action::ExpandSelectionToParentNode,
+        },
+        PaletteCommand {
+            name: "Shrink Selection",
+            keybinding: "alt+i",
+            action: || Action::ShrinkSelection,
+        },
+        PaletteCommand {
+            name: "Search Next",
+            keybinding: "",
+            action: || Action::SearchNext,
+        },
+        PaletteCommand {
+            name: "Search Previous",
+            keybinding: "",
+            action: || Action::SearchPrevious,
+        },
+        PaletteCommand {
+            name: "Export Html",
+            keybinding: "",
+            action: || Action::ExportHtml,
+        },
+        PaletteCommand {
+            name: "Export Selection Html",
+            keybinding: "",
+            action: || Action::ExportSelectionHtml,
+        },
+        PaletteCommand {
+            name: "Show Logs",
+            keybinding: "",
+            action: || Action::ShowLogs,
+        },
+        PaletteCommand {
+            name: "Generate Doc Comment",
+            keybinding: "",
+            action: || Action::GenerateDocComment,
+        },
+        PaletteCommand {
+            name: "Markdown: Toggle Checkbox",
+            keybinding: "",
+            action: || Action::ToggleCheckbox,
+        },
+        PaletteCommand {
+            name: "Markdown: Promote Heading",
+            keybinding: "",
+            action: || Action::PromoteHeading,
+        },
+        PaletteCommand {
+            name: "Markdown: Demote Heading",
+            keybinding: "",
+            action: || Action::DemoteHeading,
+        },
+        PaletteCommand {
+            name: "Markdown: Renumber List",
+            keybinding: "",
+            action: || Action::RenumberList,
+        },
+        PaletteCommand {
+            name: "Scratch Buffers",
+            keybinding: "",
+            action: || Action::ListScratchBuffers,
+        },
+        PaletteCommand {
+            name: "Save Buffer",
+            keybinding: "shift+s",
+            action: || Action::SaveCurrentBuffer,
+        },
+        PaletteCommand {
+            name: "Save Buffer As",
+            keybinding: "ctrl+shift+s",
+            action: || Action::OpenSaveAsPrompt,
+        },
+        PaletteCommand {
+            name: "Close Buffer",
+            keybinding: "ctrl+/",
+            action: || Action::CloseCurrentBuffer,
+        },
+        PaletteCommand {
+            name: "Next Buffer",
+            keybinding: "alt+.",
+            action: || Action::CycleNextBuffer,
+        },
+        PaletteCommand {
+            name: "Previous Buffer",
+            keybinding: "alt+,",
+            action: || Action::CyclePreviousBuffer,
+        },
+        PaletteCommand {
+            name: "Task: Build",
+            keybinding: "",
+            action: || Action::RunTask("build".into()),
+        },
+        PaletteCommand {
+            name: "Task: Test",
+            keybinding: "",
+            action: || Action::RunTask("test".into()),
+        },
+        PaletteCommand {
+            name: "Task: Run",
+            keybinding: "",
+            action: || Action::RunTask("run".into()),
+        },
+        PaletteCommand {
+            name: "Task: Stop",
+            keybinding: "",
+            action: || Action::StopTask,
+        },
+        PaletteCommand {
+            name: "Next Error",
+            keybinding: "]",
+            action: || Action::NextError,
+        },
+        PaletteCommand {
+            name: "Previous Error",
+            keybinding: "[",
+            action: || Action::PrevError,
+        },
+        PaletteCommand {
+            name: "Next Diagnostic",
+            keybinding: "alt+]",
+            action: || Action::NextDiagnostic,
+        },
+        PaletteCommand {
+            name: "Previous Diagnostic",
+            keybinding: "alt+[",
+            action: || Action::PreviousDiagnostic,
+        },
+        PaletteCommand {
+            name: "Debug: Start / Continue",
+            keybinding: "f5",
+            action: || Action::DebugContinue,
+        },
+        PaletteCommand {
+            name: "Debug: Stop",
+            keybinding: "shift+f5",
+            action: || Action::StopDebugging,
+        },
+        PaletteCommand {
+            name: "Debug: Toggle Breakpoint",
+            keybinding: "f9",
+            action: || Action::ToggleBreakpoint,
+        },
+        PaletteCommand {
+            name: "Debug: Step Over",
+            keybinding: "f10",
+            action: || Action::DebugStepOver,
+        },
+        PaletteCommand {
+            name: "Debug: Step Into",
+            keybinding: "f11",
+            action: || Action::DebugStepInto,
+        },
+        PaletteCommand {
+            name: "Debug: Step Out",
+            keybinding: "shift+f11",
+            action: || Action::DebugStepOut,
+        },
+        PaletteCommand {
+            name: "Debug: Show Panel",
+            keybinding: "",
+            action: || Action::ShowDebugPanel,
+        },
+        PaletteCommand {
+            name: "Format Buffer",
+            keybinding: "s",
+            action: || Action::FormatCurrentBuffer,
+        },
+        PaletteCommand {
+            name: "Repeat Last Change",
+            keybinding: ".",
+            action: || Action::RepeatLastChange,
+        },
+        PaletteCommand {
+            name: "Toggle Abbreviations",
+            keybinding: "",
+            action: || Action::ToggleAbbreviations,
+        },
+        PaletteCommand {
+            name: "Lint Buffer",
+            keybinding: "",
+            action: || Action::LintCurrentBuffer,
+        },
+        PaletteCommand {
+            name: "Go To Buffer Start",
+            keybinding: "g",
+            action: || Action::GoToBufferStart,
+        },
+        PaletteCommand {
+            name: "Go To Buffer End",
+            keybinding: "shift+g",
+            action: || Action::GoToBufferEnd,
+        },
+        PaletteCommand {
+            name: "Undo",
+            keybinding: "u",
+            action: || Action::Undo,
+        },
+        PaletteCommand {
+            name: "Redo",
+            keybinding: "shift+u",
+            action: || Action::Redo,
+        },
+        PaletteCommand {
+            name: "Copy To Clipboard",
+            keybinding: "shift+y",
+            action: || Action::CopyToClipboard,
+        },
+        PaletteCommand {
+            name: "Paste From Clipboard",
+            keybinding: "shift+p",
+            action: || Action::PasteFromClipboard,
+        },
+        PaletteCommand {
+            name: "Hover",
+            keybinding: "",
+            action: || Action::LSPHover,
+        },
+        PaletteCommand {
+            name: "Completion",
+            keybinding: "",
+            action: || Action::LSPCompletion,
+        },
+        PaletteCommand {
+            name: "Signature Help",
+            keybinding: "",
+            action: || Action::LSPSignatureHelp,
+        },
+        PaletteCommand {
+            name: "Code Actions",
+            keybinding: "alt+a",
+            action: || Action::LSPCodeActions,
+        },
+        PaletteCommand {
+            name: "Restart Language Server",
+            keybinding: "",
+            action: || Action::LSPRestart,
+        },
+        PaletteCommand {
+            name: "Language Server Status",
+            keybinding: "",
+            action: || Action::LSPStatus,
+        },
+        PaletteCommand {
+            name: "Call Hierarchy",
+            keybinding: "alt+c",
+            action: || Action::CallHierarchy,
+        },
+        PaletteCommand {
+            name: "Open Terminal",
+            keybinding: "t",
+            action: || Action::OpenTerminal,
+        },
+        PaletteCommand {
+            name: "Close Terminal",
+            keybinding: "shift+t",
+            action: || Action::CloseTerminal,
+        },
+        PaletteCommand {
+            name: "Toggle File Tree",
+            keybinding: "e",
+            action: || Action::ToggleFileTree,
+        },
+        PaletteCommand {
+            name: "Convert Line Endings to LF",
+            keybinding: "",
+            action: || Action::ConvertLineEndings(LineEnding::Lf),
+        },
+        PaletteCommand {
+            name: "Convert Line Endings to CRLF",
+            keybinding: "",
+            action: || Action::ConvertLineEndings(LineEnding::Crlf),
+        },
+        PaletteCommand {
+            name: "Restore Session",
+            keybinding: "",
+            action: || Action::RestoreSession,
+        },
+        PaletteCommand {
+            name: "Focus File Tree",
+            keybinding: "alt+e",
+            action: || Action::FocusFileTree,
+        },
+        PaletteCommand {
+            name: "Toggle Markdown Preview",
+            keybinding: "shift+e",
+            action: || Action::ToggleMarkdownPreview,
+        },
+        PaletteCommand {
+            name: "Diff With Saved",
+            keybinding: "",
+            action: || Action::DiffWithSaved,
+        },
+        PaletteCommand {
+            name: "Diff With Git HEAD",
+            keybinding: "",
+            action: || Action::DiffCurrentFile,
+        },
+        PaletteCommand {
+            name: "Next Git Hunk",
+            keybinding: "",
+            action: || Action::NextHunk,
+        },
+        PaletteCommand {
+            name: "Previous Git Hunk",
+            keybinding: "",
+            action: || Action::PrevHunk,
+        },
+        PaletteCommand {
+            name: "Stage Git Hunk",
+            keybinding: "",
+            action: || Action::StageHunk,
+        },
+        PaletteCommand {
+            name: "Revert Git Hunk",
+            keybinding: "",
+            action: || Action::RevertHunk,
+        },
+        PaletteCommand {
+            name: "Git Blame Line",
+            keybinding: "alt+b",
+            action: || Action::GitBlameLine,
+        },
+        PaletteCommand {
+            name: "Toggle Git Blame",
+            keybinding: "",
+            action: || Action::ToggleGitBlame,
+        },
+        PaletteCommand {
+            name: "Show Commit of Line",
+            keybinding: "alt+shift+b",
+            action: || Action::GitShowCommit,
+        },
+        PaletteCommand {
+            name: "Next Merge Conflict",
+            keybinding: "",
+            action: || Action::NextConflict,
+        },
+        PaletteCommand {
+            name: "Keep Our Side Of Conflict",
+            keybinding: "",
+            action: || Action::ConflictKeepOurs,
+        },
+        PaletteCommand {
+            name: "Keep Their Side Of Conflict",
+            keybinding: "",
+            action: || Action::ConflictKeepTheirs,
+        },
+        PaletteCommand {
+            name: "Keep Both Sides Of Conflict",
+            keybinding: "",
+            action: || Action::ConflictKeepBoth,
+        },
+        PaletteCommand {
+            name: "Git Status",
+            keybinding: "",
+            action: || Action::GitStatus,
+        },
+        PaletteCommand {
+            name: "Git Commit",
+            keybinding: "",
+            action: || Action::GitCommit,
+        },
+        PaletteCommand {
+            name: "Quit",
+            keybinding: "",
+            action: || Action::Quit,
+        },
+        PaletteCommand {
+            name: "Force Quit",
+            keybinding: "",
+            action: || Action::ForceQuit,
+        },
+        PaletteCommand {
+            name: "Toggle Spellcheck",
+            keybinding: "",
+            action: || Action::ToggleSpellcheck,
+        },
+        PaletteCommand {
+            name: "Toggle Whitespace",
+            keybinding: "",
+            action: || Action::ToggleWhitespace,
+        },
+        PaletteCommand {
+            name: "Sort Lines",
+            keybinding: "",
+            action: || Action::SortSelectedLines,
+        },
+        PaletteCommand {
+            name: "Sort Lines Numerically",
+            keybinding: "",
+            action: || Action::SortSelectedLinesNumeric,
+        },
+        PaletteCommand {
+            name: "Sort Lines Case Insensitive",
+            keybinding: "",
+            action: || Action::SortSelectedLinesCaseInsensitive,
+        },
+        PaletteCommand {
+            name: "Unique Lines",
+            keybinding: "",
+            action: || Action::UniqueSelectedLines,
+        },
+        PaletteCommand {
+            name: "Reverse Lines",
+            keybinding: "",
+            action: || Action::ReverseSelectedLines,
+        },
+        PaletteCommand {
+            name: "Toggle Block Selection",
+            keybinding: "",
+            action: || Action::ToggleBlockSelection,
+        },
+        PaletteCommand {
+            name: "Paste Block",
+            keybinding: "",
+            action: || Action::PasteBlock,
+        },
+        PaletteCommand {
+            name: "Spelling Suggestions",
+            keybinding: "ctrl+z",
+            action: || Action::SpellSuggestions,
+        },
+        PaletteCommand {
+            name: "Show Editor Stats",
+            keybinding: "",
+            action: || Action::ShowEditorStats,
+        },
+        PaletteCommand {
+            name: "Buffer Stats",
+            keybinding: "",
+            action: || Action::BufferStats,
+        },
+        PaletteCommand {
+            name: "Show Notification History",
+            keybinding: "",
+            action: || Action::ShowNotificationHistory,
+        },
+        PaletteCommand {
+            name: "Dismiss Notifications",
+            keybinding: "",
+            action: || Action::DismissNotifications,
+        },
+        PaletteCommand {
+            name: "Split Vertical",
+            keybinding: "v",
+            action: || Action::SplitVertical,
+        },
+        PaletteCommand {
+            name: "Split Horizontal",
+            keybinding: "shift+v",
+            action: || Action::SplitHorizontal,
+        },
+        PaletteCommand {
+            name: "Focus Next Pane",
+            keybinding: "n",
+            action: || Action::FocusNextPane,
+        },
+        PaletteCommand {
+            name: "Close Pane",
+            keybinding: "shift+n",
+            action: || Action::ClosePane,
+        },
+    ]
+}
+
+/// Recently executed commands (most recent first) and how often they were run
+#[derive(Debug, Default)]
+pub struct CommandHistory {
+    pub entries: VecDeque<String>,
+    pub counts: HashMap<String, usize>,
+    /// File the history is persisted to across sessions
+    path: Option<PathBuf>,
+}
+
+impl CommandHistory {
+    /// Load the history saved in the config directory.
+    /// Each line is the number of runs and the name of a command separated by a tab.
+    pub fn load() -> Self {
+        let mut history = Self {
+            path: file_io::config_dir().map(|dir| dir.join("command_history")),
+            ..Default::default()
+        };
+        let Some(content) = history
+            .path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+        else {
+            return history;
+        };
+        for line in content.lines() {
+            if let Some((count, name)) = line.split_once('\t') {
+                history.entries.push_back(name.to_string());
+                history
+                    .counts
+                    .insert(name.to_string(), count.parse().unwrap_or(1));
+            }
+        }
+        history.entries.truncate(COMMAND_HISTORY_SIZE);
+        history
+    }
+
+    /// Record an executed command
+    pub fn push(&mut self, name: &str) {
+        self.entries.retain(|entry| entry != name);
+        self.entries.push_front(name.to_string());
+        if let Some(removed) = self.entries.get(COMMAND_HISTORY_SIZE).cloned() {
+            self.entries.truncate(COMMAND_HISTORY_SIZE);
+            self.counts.remove(&removed);
+        }
+        *self.counts.entry(name.to_string()).or_default() += 1;
+        if let Err(err) = self.save() {
+            tracing::warn!("Failed to save command history: {}", err);
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content: String = self
+            .entries
+            .iter()
+            .map(|name| format!("{}\t{}\n", self.counts.get(name).unwrap_or(&1), name))
+            .collect();
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Position of the command in the history
+    pub fn rank(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry == name)
+    }
+
+    /// Bonus for recently and frequently run commands
+    pub fn score(&self, name: &str) -> i64 {
+        let Some(rank) = self.rank(name) else {
+            return 0;
+        };
+        let count = self.counts.get(name).copied().unwrap_or(1);
+        (COMMAND_HISTORY_SIZE - rank) as i64 + count.min(COMMAND_HISTORY_SIZE) as i64
+    }
+}
+
+/// Match the characters of the pattern in order against the candidate (ignoring case).
+/// Returns a score (higher is better) and the char indices of matched characters.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let mut indices = vec![];
+    let mut score = 0;
+    let mut pattern_chars = pattern.chars().filter(|c| !c.is_whitespace()).peekable();
+    let mut previous: Option<(usize, char)> = None;
+
+    for (idx, c) in candidate.chars().enumerate() {
+        let Some(p) = pattern_chars.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(p.to_lowercase()) {
+            score += 1;
+            // Consecutive matches and matches at the start of words rank higher
+            match previous {
+                Some((previous_idx, _)) if indices.last() == Some(&previous_idx) => score += 5,
+                Some((_, previous_char)) if !previous_char.is_alphanumeric() => score += 3,
+                None => score += 3,
+                _ => {}
+            }
+            indices.push(idx);
+            pattern_chars.next();
+        }
+        previous = Some((idx, c));
+    }
+
+    if pattern_chars.peek().is_some() {
+        return None;
+    }
+    // Prefer shorter candidates among equal matches
+    score -= candidate.chars().count() as i64 / 10;
+    Some((score, indices))
+}
+
+/// Commands matching the query ordered by recent use and match score.
+/// Returns the index of the command and the matched char indices of its name.
+pub fn filter_commands(
+    names: &[&str],
+    query: &str,
+    history: &CommandHistory,
+) -> Vec<(usize, Vec<usize>)> {
+    let mut matches: Vec<(usize, i64, Vec<usize>)> = names
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, name)| {
+            let (score, indices) = fuzzy_match(query, name)?;
+            Some((idx, score + history.score(name), indices))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    matches
+        .into_iter()
+        .map(|(idx, _score, indices)| (idx, indices))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{commands, filter_commands, fuzzy_match, parse_action, CommandHistory};
+    use crate::actions::Action;
+
+    #[test]
+    fn fuzzy_match_indices() {
+        assert_eq!(fuzzy_match("sb", "Save Buffer").unwrap().1, vec![0, 5]);
+        assert!(fuzzy_match("bs", "Save Buffer").is_none());
+        assert!(fuzzy_match("", "Undo").is_some());
+    }
+
+    #[test]
+    fn recent_commands_first() {
+        let names: Vec<&str> = commands().iter().map(|command| command.name).collect();
+        let mut history = CommandHistory::default();
+        let unranked = filter_commands(&names, "", &history);
+        assert_eq!(names[unranked[0].0], "Open File");
+
+        history.push("Close Pane");
+        let ranked = filter_commands(&names, "", &history);
+        assert_eq!(names[ranked[0].0], "Close Pane");
+
+        // Frequently run commands outrank a single recent run
+        let mut history = CommandHistory::default();
+        for name in ["Undo", "Undo", "Undo", "Close Pane"] {
+            history.push(name);
+        }
+        assert!(history.score("Undo") > history.score("Close Pane"));
+    }
+
+    #[test]
+    fn parse_actions_with_arguments() {
+        assert!(matches!(parse_action("Undo"), Ok(Action::Undo)));
+        assert!(matches!(
+            parse_action("GoToLine 10"),
+            Ok(Action::GoToLine(10))
+        ));
+        assert!(matches!(
+            parse_action("RunTask \"build\""),
+            Ok(Action::RunTask(name)) if name == "build"
+        ));
+        assert!(parse_action("NoSuchAction").is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Comment continuation on newline and doc comment skeletons generated
+//! from the tree-sitter signature of the function under the cursor.
+
+use tree_sitter::{Node, Parser};
+
+use crate::buffer::instance::Language;
+
+/// Line comment leaders of a language, longest first
+fn line_comment_leaders(language: Language) -> &'static [&'static str] {
+    match language {
+        Language::Rust => &["///", "//!", "//"],
+        Language::Python | Language::TOML => &["#"],
+        _ => &[],
+    }
+}
+
+fn has_block_comments(language: Language) -> bool {
+    matches!(language, Language::Rust | Language::CSS)
+}
+
+/// Prefix of the line inserted when a newline is typed at the column of a comment line:
+/// the indentation, comment leader and spacing of the current line.
+/// The configured comment token of the language is tried before the built in leaders.
+pub fn comment_continuation(
+    language: Language,
+    comment_token: Option<&str>,
+    line: &str,
+    column: usize,
+) -> Option<String> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    let block_comment = has_block_comments(language) && !trimmed.contains("*/");
+    // Following lines of block comments are aligned with the first star
+    if block_comment && trimmed.starts_with("/*") {
+        return (column >= indent.len() + 2).then(|| format!("{} * ", indent));
+    }
+    let leader = comment_token
+        .into_iter()
+        .chain(line_comment_leaders(language).iter().copied())
+        .find(|leader| !leader.is_empty() && trimmed.starts_with(*leader))
+        .filter(|_| !trimmed.starts_with("#!"))
+        .or((block_comment && (trimmed == "*" || trimmed.starts_with("* "))).then_some("*"))?;
+    if column < indent.len() + leader.len() {
+        return None;
+    }
+
+    let after = &trimmed[leader.len()..];
+    let spacing = match after.len() - after.trim_start().len() {
+        0 if after.is_empty() => 1,
+        spacing => spacing,
+    };
+    Some(format!("{}{}{}", indent, leader, " ".repeat(spacing)))
+}
+
+/// Signature of a function as needed for its doc comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Row the doc comment is inserted at
+    pub row: usize,
+    pub indent: String,
+    pub params: Vec<String>,
+    pub returns: bool,
+}
+
+fn node_text<'a>(node: Node, source: &'a str) -> &'a str {
+    &source[node.byte_range()]
+}
+
+/// Innermost node of the kind containing the row
+fn enclosing_node<'a>(node: Node<'a>, kind: &str, row: usize) -> Option<Node<'a>> {
+    if node.start_position().row > row || node.end_position().row < row {
+        return None;
+    }
+    let mut cursor = node.walk();
+    let inner = node
+        .children(&mut cursor)
+        .find_map(|child| enclosing_node(child, kind, row));
+    inner.or((node.kind() == kind).then_some(node))
+}
+
+fn leading_whitespace(line: &str) -> String {
+    line.chars().take_while(|c| c.is_whitespace()).collect()
+}
+
+/// Signature of the function containing the row
+pub fn function_signature(language: Language, source: &str, row: usize) -> Option<Signature> {
+    let (ts_language, kind) = match language {
+        Language::Rust => (tree_sitter_rust::LANGUAGE.into(), "function_item"),
+        Language::Python => (tree_sitter_python::LANGUAGE.into(), "function_definition"),
+        _ => return None,
+    };
+    let mut parser = Parser::new();
+    parser.set_language(&ts_language).ok()?;
+    let tree = parser.parse(source, None)?;
+    let function = enclosing_node(tree.root_node(), kind, row)?;
+    let lines: Vec<&str> = source.split('\n').collect();
+
+    let parameters = function.child_by_field_name("parameters")?;
+    let mut cursor = parameters.walk();
+    let params = parameters
+        .named_children(&mut cursor)
+        .filter_map(|param| {
+            let name = match param.kind() {
+                "parameter" => param.child_by_field_name("pattern")?,
+                "identifier" => param,
+                "default_parameter" | "typed_default_parameter" => {
+                    param.child_by_field_name("name")?
+                }
+                "typed_parameter" | "list_splat_pattern" | "dictionary_splat_pattern" => {
+                    param.named_child(0)?
+                }
+                _ => return None,
+            };
+            let name = node_text(name, source);
+            (!matches!(name, "self" | "cls")).then(|| name.to_string())
+        })
+        .collect();
+    let returns = function.child_by_field_name("return_type").is_some();
+
+    let (row, indent) = match language {
+        // Docstrings are the first statement of the body
+        Language::Python => {
+            let body = function.child_by_field_name("body")?;
+            let body_row = body.start_position().row;
+            if body_row == function.start_position().row {
+                return None;
+            }
+            (body_row, leading_whitespace(lines[body_row]))
+        }
+        _ => {
+            let row = function.start_position().row;
+            (row, leading_whitespace(lines[row]))
+        }
+    };
+    Some(Signature {
+        row,
+        indent,
+        params,
+        returns,
+    })
+}
+
+/// Doc comment inserted at the start of the signature's row and the
+/// length of its first line, where the cursor is placed for the summary
+pub fn doc_skeleton(language: Language, signature: &Signature) -> (String, usize) {
+    let indent = &signature.indent;
+    let mut lines = vec![];
+    match language {
+        Language::Python => {
+            lines.push(format!("{}\"\"\"", indent));
+            if !signature.params.is_empty() || signature.returns {
+                lines.push(String::new());
+            }
+            if !signature.params.is_empty() {
+                lines.push(format!("{}Args:", indent));
+                for param in &signature.params {
+                    lines.push(format!("{}    {}: ", indent, param));
+                }
+            }
+            if signature.returns {
+                lines.push(format!("{}Returns:", indent));
+                lines.push(format!("{}    ", indent));
+            }
+            lines.push(format!("{}\"\"\"", indent));
+        }
+        _ => {
+            lines.push(format!("{}/// ", indent));
+            if !signature.params.is_empty() {
+                lines.push(format!("{}///", indent));
+                lines.push(format!("{}/// # Arguments", indent));
+                lines.push(format!("{}///", indent));
+                for param in &signature.params {
+                    lines.push(format!("{}/// * `{}` - ", indent, param));
+                }
+            }
+            if signature.returns {
+                lines.push(format!("{}///", indent));
+                lines.push(format!("{}/// # Returns", indent));
+                lines.push(format!("{}///", indent));
+                lines.push(format!("{}/// ", indent));
+            }
+        }
+    }
+    let first_line = lines[0].len();
+    (
+        lines.iter().map(|line| format!("{}\n", line)).collect(),
+        first_line,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{comment_continuation, doc_skeleton, function_signature, Signature};
+    use crate::buffer::instance::Language;
+
+    #[test]
+    fn continue_comments() {
+        let continuation =
+            |language, line: &str| comment_continuation(language, None, line, line.len());
+        assert_eq!(
+            continuation(Language::Rust, "    /// Docs"),
+            Some("    /// ".into())
+        );
+        assert_eq!(continuation(Language::Rust, "//"), Some("// ".into()));
+        assert_eq!(
+            continuation(Language::Rust, "  /** Block"),
+            Some("   * ".into())
+        );
+        assert_eq!(
+            continuation(Language::Rust, "   * more"),
+            Some("   * ".into())
+        );
+        assert_eq!(continuation(Language::Rust, "   */"), None);
+        assert_eq!(
+            continuation(Language::Python, "#  note"),
+            Some("#  ".into())
+        );
+        assert_eq!(
+            continuation(Language::Python, "#!/usr/bin/env python"),
+            None
+        );
+        assert_eq!(continuation(Language::Markdown, "# Heading"), None);
+        assert_eq!(
+            comment_continuation(Language::Rust, None, "  // text", 2),
+            None
+        );
+        assert_eq!(
+            comment_continuation(Language::Markdown, Some("%%"), "%% note", 7),
+            Some("%% ".into())
+        );
+    }
+
+    #[test]
+    fn doc_comment_skeletons() {
+        let source = "impl A {\n    fn f(&self, a: u32, (b, c): (u8, u8)) -> bool {\n        true\n    }\n}\n";
+        let signature = function_signature(Language::Rust, source, 2).unwrap();
+        assert_eq!(
+            signature,
+            Signature {
+                row: 1,
+                indent: "    ".into(),
+                params: vec!["a".into(), "(b, c)".into()],
+                returns: true,
+            }
+        );
+        let (doc, cursor) = doc_skeleton(Language::Rust, &signature);
+        assert_eq!(cursor, 8);
+        assert!(doc.starts_with("    /// \n    ///\n    /// # Arguments\n"));
+
+        let source = "def f(self, a, b: int = 1, *args) -> int:\n    return a\n";
+        let signature = function_signature(Language::Python, source, 0).unwrap();
+        assert_eq!(signature.row, 1);
+        assert_eq!(signature.params, vec!["a", "b", "args"]);
+        let (doc, _) = doc_skeleton(Language::Python, &signature);
+        assert_eq!(
+            doc,
+            "    \"\"\"\n\n    Args:\n        a: \n        b: \n        args: \n    Returns:\n        \n    \"\"\"\n"
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::process::Stdio;
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    process::Command,
+    sync::{mpsc::Sender, oneshot},
+};
+
+use crate::{lsp::client::LSPClientHandle, state::EditorState};
+
+use super::AsyncResult;
+
+type Callback = fn(String, state: &mut EditorState, lsp_handle: &mut Option<&mut LSPClientHandle>);
+
+/// Send each line of output of a program to the output callback as it is
+/// produced and the exit status to the exit callback once it finishes.
+/// The program is killed when the returned sender is used or dropped.
+pub fn run_command(
+    program: String,
+    args: Vec<String>,
+    cwd: String,
+    on_output: Callback,
+    on_exit: Callback,
+    rt: &tokio::runtime::Runtime,
+    sender: Sender<AsyncResult>,
+) -> oneshot::Sender<()> {
+    let (kill_tx, kill_rx) = oneshot::channel::<()>();
+    rt.spawn(async move {
+        let mut command = Command::new(&program);
+
+        #[cfg(target_os = "windows")]
+        {
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let child = command
+            .args(&args)
+            .current_dir(&cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) => {
+                let result = AsyncResult {
+                    result: format!("Failed to run {}: {}", program, err),
+                    callback: on_exit,
+                };
+                if let Err(err) = sender.send(result).await {
+                    tracing::error!("Failed to send command result: {}", err);
+                }
+                return;
+            }
+        };
+
+        let stdout = forward_lines(child.stdout.take().unwrap(), on_output, sender.clone());
+        let stderr = forward_lines(child.stderr.take().unwrap(), on_output, sender.clone());
+        let status = tokio::select! {
+            status = child.wait() => match status {
+                Ok(status) => status.to_string(),
+                Err(err) => format!("failed: {}", err),
+            },
+            _ = kill_rx => {
+                if let Err(err) = child.kill().await {
+                    tracing::error!("Failed to kill {}: {}", program, err);
+                }
+                "killed".to_string()
+            }
+        };
+        // Output still being read is sent before the exit status
+        let _ = tokio::join!(stdout, stderr);
+
+        let result = AsyncResult {
+            result: status,
+            callback: on_exit,
+        };
+        if let Err(err) = sender.send(result).await {
+            tracing::error!("Failed to send command result: {}", err);
+        }
+    });
+    kill_tx
+}
+
+/// Run a command line in the shell with the input written to its stdin,
+/// returning its output once it exits
+pub async fn pipe_through_shell(
+    command_line: &str,
+    input: String,
+    cwd: &str,
+) -> std::io::Result<std::process::Output> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        let mut command = Command::new("cmd");
+        command.arg("/C").creation_flags(CREATE_NO_WINDOW);
+        command
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+
+    let mut child = command
+        .arg(command_line)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    // Written concurrently so that commands filling their stdout before reading
+    // all of their input do not block
+    let write = tokio::spawn(async move {
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+    let output = child.wait_with_output().await?;
+    let _ = write.await;
+    Ok(output)
+}
+
+fn forward_lines(
+    output: impl AsyncRead + Unpin + Send + 'static,
+    callback: Callback,
+    sender: Sender<AsyncResult>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(output).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let result = AsyncResult {
+                result: line,
+                callback,
+            };
+            if sender.send(result).await.is_err() {
+                break;
+            }
+        }
+    })
+}
@@ -2,6 +2,7 @@ use tokio::sync::mpsc::{Receiver, Sender};
 
 use crate::{lsp::client::LSPClientHandle, state::EditorState};
 
+pub mod cli;
 pub mod web_api;
 
 pub struct AsyncHandle {
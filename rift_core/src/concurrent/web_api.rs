@@ -1,9 +1,33 @@
 use tokio::sync::mpsc::Sender;
 
-use crate::{lsp::client::LSPClientHandle, state::EditorState};
+use crate::{lsp::client::LSPClientHandle, notifications::NotificationLevel, state::EditorState};
 
 use super::AsyncResult;
 
+/// Send the response to the callback or the error to the user
+async fn send_response(
+    url: String,
+    response: reqwest::Result<String>,
+    callback: fn(String, state: &mut EditorState, lsp_handle: &mut Option<&mut LSPClientHandle>),
+    sender: Sender<AsyncResult>,
+) {
+    let result = match response {
+        Ok(content) => AsyncResult {
+            result: content,
+            callback,
+        },
+        Err(err) => AsyncResult {
+            result: format!("Request to {} failed: {}", url, err),
+            callback: |message, state, _lsp_handle| {
+                state.notify(NotificationLevel::Error, message);
+            },
+        },
+    };
+    if let Err(err) = sender.send(result).await {
+        tracing::error!("Failed to send response: {}", err);
+    }
+}
+
 pub fn get_request(
     url: String,
     callback: fn(String, state: &mut EditorState, lsp_handle: &mut Option<&mut LSPClientHandle>),
@@ -11,15 +35,11 @@ pub fn get_request(
     sender: Sender<AsyncResult>,
 ) {
     rt.spawn(async move {
-        let response = reqwest::get(url).await.unwrap();
-        let content = response.text().await.unwrap();
-        sender
-            .send(AsyncResult {
-                result: content,
-                callback,
-            })
-            .await
-            .unwrap();
+        let response = match reqwest::get(&url).await {
+            Ok(response) => response.text().await,
+            Err(err) => Err(err),
+        };
+        send_response(url, response, callback, sender).await;
     });
 }
 
@@ -32,14 +52,10 @@ pub fn post_request(
 ) {
     rt.spawn(async move {
         let client = reqwest::Client::new();
-        let response = client.post(url).body(body).send().await.unwrap();
-        let content = response.text().await.unwrap();
-        sender
-            .send(AsyncResult {
-                result: content,
-                callback,
-            })
-            .await
-            .unwrap();
+        let response = match client.post(&url).body(body).send().await {
+            Ok(response) => response.text().await,
+            Err(err) => Err(err),
+        };
+        send_response(url, response, callback, sender).await;
     });
 }
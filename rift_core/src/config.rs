@@ -0,0 +1,201 @@
+//! Layered configuration read from toml files: the global config file, overridden by the
+//! `.rift/config.toml` of the workspace. Both are reloaded when they change.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+use crate::{notifications::NotificationLevel, preferences::Preferences, state::EditorState};
+
+/// Time between checks of the modification times of the config files
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Config file of the workspace, relative to the workspace folder
+pub const PROJECT_CONFIG: &str = ".rift/config.toml";
+
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    last_check: Instant,
+    /// Workspace the configuration was loaded for
+    workspace: String,
+    /// Modification time of each config file when it was loaded
+    mtimes: Vec<Option<SystemTime>>,
+}
+
+impl Default for ConfigWatcher {
+    fn default() -> Self {
+        Self {
+            last_check: Instant::now(),
+            workspace: String::new(),
+            mtimes: vec![],
+        }
+    }
+}
+
+pub fn global_config_path() -> Option<PathBuf> {
+    crate::io::file_io::config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Config files applied on top of the defaults, later files take precedence
+pub fn config_paths(workspace: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = global_config_path().into_iter().collect();
+    if !workspace.is_empty() {
+        paths.push(Path::new(workspace).join(PROJECT_CONFIG));
+    }
+    paths
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn toml_value(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(string) => Value::String(string.value().clone()),
+        toml_edit::Value::Integer(integer) => Value::from(*integer.value()),
+        toml_edit::Value::Float(float) => Value::from(*float.value()),
+        toml_edit::Value::Boolean(boolean) => Value::Bool(*boolean.value()),
+        toml_edit::Value::Datetime(datetime) => Value::String(datetime.value().to_string()),
+        toml_edit::Value::Array(array) => Value::Array(array.iter().map(toml_value).collect()),
+        toml_edit::Value::InlineTable(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), toml_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn toml_table(table: &toml_edit::Table) -> Value {
+    let mut map = Map::new();
+    for (key, item) in table.iter() {
+        let value = match item {
+            toml_edit::Item::None => continue,
+            toml_edit::Item::Value(value) => toml_value(value),
+            toml_edit::Item::Table(table) => toml_table(table),
+            toml_edit::Item::ArrayOfTables(tables) => {
+                Value::Array(tables.iter().map(toml_table).collect())
+            }
+        };
+        map.insert(key.to_string(), value);
+    }
+    Value::Object(map)
+}
+
+/// Parse the content of a config file into the shape of the serialized preferences
+pub fn parse_layer(content: &str) -> Result<Value> {
+    let document: toml_edit::DocumentMut = content.parse()?;
+    Ok(toml_table(document.as_table()))
+}
+
+/// Tables are merged key by key, any other value replaces the one in the base
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(base) => merge(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Defaults overridden by each of the layers in order
+pub fn merge_layers(layers: Vec<Value>) -> Result<Preferences> {
+    let mut merged = serde_json::to_value(Preferences::default())?;
+    for layer in layers {
+        merge(&mut merged, layer);
+    }
+    Ok(serde_json::from_value(merged)?)
+}
+
+/// Preferences from the config files which exist, missing files are skipped
+pub fn load_preferences(paths: &[PathBuf]) -> Result<Preferences> {
+    let mut layers = vec![];
+    for path in paths {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        layers.push(
+            parse_layer(&content).with_context(|| format!("Invalid config {}", path.display()))?,
+        );
+    }
+    merge_layers(layers).context("Invalid configuration")
+}
+
+/// Load the configuration of the workspace, the current preferences are kept if it is invalid
+pub fn reload_config(state: &mut EditorState) {
+    let paths = config_paths(&state.workspace_folder);
+    state.config_watcher.workspace = state.workspace_folder.clone();
+    state.config_watcher.mtimes = paths.iter().map(|path| modified_time(path)).collect();
+    match load_preferences(&paths) {
+        Ok(preferences) => {
+            state.preferences = preferences;
+            state.update_view = true;
+        }
+        Err(err) => state.notify(NotificationLevel::Error, format!("{:#}", err)),
+    }
+}
+
+/// Reload the configuration when a config file or the workspace changed,
+/// checking at most once per `CHECK_INTERVAL`
+pub fn poll_config(state: &mut EditorState) {
+    if state.config_watcher.last_check.elapsed() < CHECK_INTERVAL {
+        return;
+    }
+    state.config_watcher.last_check = Instant::now();
+
+    let paths = config_paths(&state.workspace_folder);
+    let mtimes: Vec<Option<SystemTime>> = paths.iter().map(|path| modified_time(path)).collect();
+    if state.config_watcher.workspace == state.workspace_folder
+        && state.config_watcher.mtimes == mtimes
+    {
+        return;
+    }
+    tracing::info!("Reloading configuration");
+    reload_config(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_layers, parse_layer};
+    use crate::buffer::instance::Language;
+
+    #[test]
+    fn layered_config() {
+        let global = parse_layer(
+            "tab_width = 2\nformat_on_save = true\n\n[languages.Python]\ntab_width = 4\n",
+        )
+        .unwrap();
+        let project = parse_layer(
+            "[languages.Python]\ncomment_token = \"##\"\nlanguage_servers = [[{ program = \"pylsp\" }]]\n",
+        )
+        .unwrap();
+        let preferences = merge_layers(vec![global, project]).unwrap();
+
+        assert_eq!(preferences.tab_width, 2);
+        assert_eq!(preferences.tab_width_for(Language::Rust), 2);
+        assert_eq!(preferences.tab_width_for(Language::Python), 4);
+        assert!(preferences.format_on_save_for(Language::Python));
+        assert_eq!(preferences.comment_token_for(Language::Python), Some("##"));
+        assert_eq!(
+            preferences.language_servers_for(Language::Python).unwrap()[0][0].program,
+            "pylsp"
+        );
+        assert!(preferences.language_servers_for(Language::Rust).is_some());
+
+        assert!(merge_layers(vec![parse_layer("tab_width = \"wide\"").unwrap()]).is_err());
+        assert!(parse_layer("tab_width = ").is_err());
+    }
+}
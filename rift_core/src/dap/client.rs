@@ -0,0 +1,169 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::process::Stdio;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
+    process::Command,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use super::types::{DapMessage, OutgoingMessage};
+
+pub struct DAPClientHandle {
+    pub sender: Sender<OutgoingMessage>,
+    pub reciever: Receiver<DapMessage>,
+    seq: u64,
+}
+
+/// Starts a debug adapter communicating over stdio
+pub async fn start_dap(program: &str, args: &[String]) -> Result<DAPClientHandle> {
+    let mut command = Command::new(program);
+
+    #[cfg(target_os = "windows")]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<OutgoingMessage>(32);
+    let (incoming_tx, incoming_rx) = mpsc::channel::<DapMessage>(32);
+
+    // Send pending outgoing messages to the adapter stdin.
+    // The child is owned by this task so that it is killed once the handle is dropped.
+    tokio::spawn(async move {
+        let _child = child;
+        let mut writer = BufWriter::new(stdin);
+        while let Some(message) = outgoing_rx.recv().await {
+            let body = match serde_json::to_string(&message) {
+                Ok(body) => body,
+                Err(err) => {
+                    tracing::error!("Failed to serialize dap message: {}", err);
+                    continue;
+                }
+            };
+            let message = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+            if writer.write_all(message.as_bytes()).await.is_err() || writer.flush().await.is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    // Read incoming messages from the adapter stdout
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stdout);
+        let mut header = String::new();
+        while let Ok(bytes_read) = reader.read_line(&mut header).await {
+            // Adapter exited
+            if bytes_read == 0 {
+                break;
+            }
+
+            // Read empty line
+            if reader.read_line(&mut String::new()).await.is_err() {
+                break;
+            }
+
+            let Some(content_length) = header
+                .strip_prefix("Content-Length: ")
+                .and_then(|content_length| content_length.trim().parse::<usize>().ok())
+            else {
+                tracing::error!("Malformed dap header: {}", header);
+                header.clear();
+                continue;
+            };
+            header.clear();
+
+            let mut body = vec![0; content_length];
+            if reader.read_exact(&mut body).await.is_err() {
+                break;
+            }
+            let body = String::from_utf8_lossy(&body);
+            match serde_json::from_str::<DapMessage>(&body) {
+                Ok(message) => {
+                    if incoming_tx.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                Err(err) => tracing::error!("Malformed dap message: {}\n{}", err, body),
+            }
+        }
+    });
+
+    // Read incoming errors from the adapter stderr
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stderr);
+        let mut line = String::new();
+        while let Ok(bytes_read) = reader.read_line(&mut line).await {
+            if bytes_read == 0 {
+                break;
+            }
+            tracing::error!("{}", line);
+            line.clear();
+        }
+    });
+
+    Ok(DAPClientHandle {
+        sender: outgoing_tx,
+        reciever: incoming_rx,
+        seq: 0,
+    })
+}
+
+impl DAPClientHandle {
+    fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    /// Returns false once the adapter has exited and all its messages are handled
+    pub fn is_running(&self) -> bool {
+        !(self.reciever.is_closed() && self.reciever.is_empty())
+    }
+
+    /// Send a request returning its sequence number
+    pub fn send_request_sync(&mut self, command: &str, arguments: Value) -> Result<u64> {
+        let seq = self.next_seq();
+        self.sender.blocking_send(OutgoingMessage::Request {
+            seq,
+            command: command.to_string(),
+            arguments,
+        })?;
+        Ok(seq)
+    }
+
+    /// Reply to a reverse request
+    pub fn send_response_sync(
+        &mut self,
+        request_seq: u64,
+        command: String,
+        success: bool,
+        message: Option<String>,
+    ) -> Result<()> {
+        let seq = self.next_seq();
+        self.sender.blocking_send(OutgoingMessage::Response {
+            seq,
+            request_seq,
+            success,
+            command,
+            message,
+        })?;
+        Ok(())
+    }
+
+    pub fn recv_message_sync(&mut self) -> Option<DapMessage> {
+        self.reciever.try_recv().ok()
+    }
+}
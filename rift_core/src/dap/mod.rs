@@ -0,0 +1,541 @@
+//! Debugging through debug adapters (DAP).
+//! Configurations are read from `.rift/launch.json` in the workspace folder and
+//! default to `lldb-dap` for Rust projects and `debugpy` for Python files.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::{
+    buffer::{
+        instance::{Cursor, Language, Selection},
+        line_buffer::LineBuffer,
+    },
+    io::file_io,
+    notifications::NotificationLevel,
+    state::EditorState,
+};
+
+use self::{
+    client::{start_dap, DAPClientHandle},
+    types::{
+        DapMessage, Event, OutputEvent, Response, ScopesResponse, StackFrame, StackTraceResponse,
+        StoppedEvent, Variable, VariablesResponse,
+    },
+};
+
+pub mod client;
+pub mod types;
+
+/// Debug configurations relative to the workspace folder
+pub const LAUNCH_FILE: &str = ".rift/launch.json";
+
+/// Number of stack frames requested when stopped
+const STACK_DEPTH: usize = 20;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DebugConfig {
+    pub name: String,
+    /// Debug adapter program
+    pub adapter: String,
+    #[serde(default)]
+    pub adapter_args: Vec<String>,
+    /// Either `launch` or `attach`
+    #[serde(default = "default_request")]
+    pub request: String,
+    /// Arguments of the launch or attach request
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+fn default_request() -> String {
+    "launch".to_string()
+}
+
+/// Debug configurations of the workspace
+pub fn load_configs(
+    workspace_folder: &str,
+    language: Option<Language>,
+    path: Option<&str>,
+) -> Result<Vec<DebugConfig>> {
+    let launch_file = Path::new(workspace_folder).join(LAUNCH_FILE);
+    if launch_file.exists() {
+        let content = file_io::read_file_content(launch_file.to_str().unwrap_or_default())?;
+        return Ok(serde_json::from_str(&content)?);
+    }
+    let workspace = Path::new(workspace_folder);
+    let configs = match language {
+        Some(Language::Rust) => cargo_package_name(workspace)
+            .map(|name| DebugConfig {
+                name: format!("Debug {}", name),
+                adapter: "lldb-dap".to_string(),
+                adapter_args: vec![],
+                request: default_request(),
+                arguments: json!({
+                    "program": workspace.join("target").join("debug").join(&name),
+                    "cwd": workspace_folder,
+                    "args": [],
+                }),
+            })
+            .into_iter()
+            .collect(),
+        Some(Language::Python) => path
+            .map(|path| DebugConfig {
+                name: format!("Debug {}", path),
+                adapter: "python3".to_string(),
+                adapter_args: vec!["-m".to_string(), "debugpy.adapter".to_string()],
+                request: default_request(),
+                arguments: json!({
+                    "program": path,
+                    "cwd": workspace_folder,
+                    "console": "internalConsole",
+                }),
+            })
+            .into_iter()
+            .collect(),
+        _ => vec![],
+    };
+    Ok(configs)
+}
+
+/// Name of the package in the Cargo.toml of the folder
+fn cargo_package_name(folder: &Path) -> Option<String> {
+    let manifest = std::fs::read_to_string(folder.join("Cargo.toml")).ok()?;
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(value) = line
+                .strip_prefix("name")
+                .and_then(|rest| rest.trim_start().strip_prefix('='))
+            {
+                return Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Request awaiting a response along with what to do with it
+#[derive(Debug)]
+enum PendingRequest {
+    Initialize,
+    StackTrace,
+    Scopes,
+    Variables(String),
+    Other,
+}
+
+pub struct DebugSession {
+    pub config: DebugConfig,
+    pub handle: DAPClientHandle,
+    pending: HashMap<u64, PendingRequest>,
+    /// Thread which last stopped
+    pub thread_id: Option<i64>,
+    /// File and row the program is stopped at
+    pub stop: Option<(String, usize)>,
+    pub frames: Vec<StackFrame>,
+    /// Variables of each scope of the top stack frame
+    pub variables: Vec<(String, Vec<Variable>)>,
+    pub output: Vec<String>,
+    /// Buffer showing the stack, variables and output
+    pub panel_buffer: Option<u32>,
+}
+
+impl DebugSession {
+    fn send_request(&mut self, command: &str, arguments: Value, pending: PendingRequest) {
+        match self.handle.send_request_sync(command, arguments) {
+            Ok(seq) => {
+                self.pending.insert(seq, pending);
+            }
+            Err(err) => tracing::error!("Failed to send {} request: {}", command, err),
+        }
+    }
+
+    /// Replace the breakpoints of a file in the debug adapter
+    pub fn set_breakpoints(&mut self, path: &str, rows: &BTreeSet<usize>) {
+        let breakpoints: Vec<Value> = rows.iter().map(|row| json!({"line": row + 1})).collect();
+        self.send_request(
+            "setBreakpoints",
+            json!({
+                "source": {"path": path},
+                "breakpoints": breakpoints,
+            }),
+            PendingRequest::Other,
+        );
+    }
+
+    /// Text shown in the debug panel buffer
+    pub fn panel_text(&self) -> String {
+        let mut text = format!("{}\n\nStack\n", self.config.name);
+        for (idx, frame) in self.frames.iter().enumerate() {
+            let location = frame
+                .source
+                .as_ref()
+                .and_then(|source| source.path.clone().or(source.name.clone()))
+                .unwrap_or_default();
+            text.push_str(&format!(
+                "  #{} {} at {}:{}\n",
+                idx, frame.name, location, frame.line
+            ));
+        }
+        text.push_str("\nVariables\n");
+        for (scope, variables) in &self.variables {
+            text.push_str(&format!("  {}\n", scope));
+            for variable in variables {
+                match &variable.type_name {
+                    Some(type_name) => text.push_str(&format!(
+                        "    {}: {} = {}\n",
+                        variable.name, type_name, variable.value
+                    )),
+                    None => text.push_str(&format!("    {} = {}\n", variable.name, variable.value)),
+                }
+            }
+        }
+        text.push_str("\nOutput\n");
+        for line in &self.output {
+            text.push_str(&format!("  {}\n", line));
+        }
+        text
+    }
+}
+
+/// Start the debug adapter of the first configuration of the workspace
+pub fn start_debugging(state: &mut EditorState) {
+    if state.debug.is_some() {
+        state.notify(
+            NotificationLevel::Warning,
+            "A debug session is already running".to_string(),
+        );
+        return;
+    }
+    let (language, path) = match state.buffer_idx {
+        Some(buffer_idx) => {
+            let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+            (Some(buffer.language), buffer.file_path.clone())
+        }
+        None => (None, None),
+    };
+    let config = match load_configs(&state.workspace_folder, language, path.as_deref()) {
+        Ok(configs) => configs.into_iter().next(),
+        Err(err) => {
+            state.notify(
+                NotificationLevel::Error,
+                format!("Failed to read {}: {}", LAUNCH_FILE, err),
+            );
+            return;
+        }
+    };
+    let Some(config) = config else {
+        state.notify(
+            NotificationLevel::Error,
+            format!("No debug configuration found, add one to {}", LAUNCH_FILE),
+        );
+        return;
+    };
+
+    let handle = match state
+        .rt
+        .block_on(async { start_dap(&config.adapter, &config.adapter_args).await })
+    {
+        Ok(handle) => handle,
+        Err(err) => {
+            state.notify(
+                NotificationLevel::Error,
+                format!("Failed to start {}: {:#}", config.adapter, err),
+            );
+            return;
+        }
+    };
+    let mut session = DebugSession {
+        config,
+        handle,
+        pending: HashMap::new(),
+        thread_id: None,
+        stop: None,
+        frames: vec![],
+        variables: vec![],
+        output: vec![],
+        panel_buffer: None,
+    };
+    session.send_request(
+        "initialize",
+        json!({
+            "clientID": "rift",
+            "clientName": "rift",
+            "adapterID": session.config.adapter,
+            "linesStartAt1": true,
+            "columnsStartAt1": true,
+            "pathFormat": "path",
+        }),
+        PendingRequest::Initialize,
+    );
+    state.notify(
+        NotificationLevel::Info,
+        format!("Started {}", session.config.name),
+    );
+    state.debug = Some(session);
+}
+
+/// Terminate the debuggee and the debug adapter
+pub fn stop_debugging(state: &mut EditorState) {
+    if let Some(mut session) = state.debug.take() {
+        session.send_request(
+            "disconnect",
+            json!({"terminateDebuggee": true}),
+            PendingRequest::Other,
+        );
+        state.update_view = true;
+    }
+}
+
+/// Resume or step the stopped thread
+pub fn resume(state: &mut EditorState, command: &str) {
+    let Some(session) = &mut state.debug else {
+        return;
+    };
+    let Some(thread_id) = session.thread_id else {
+        state.notify(
+            NotificationLevel::Info,
+            "Program is not stopped".to_string(),
+        );
+        return;
+    };
+    session.send_request(
+        command,
+        json!({"threadId": thread_id}),
+        PendingRequest::Other,
+    );
+    session.stop = None;
+    state.update_view = true;
+}
+
+/// Open (or focus) the buffer showing the stack, variables and output
+pub fn show_panel(state: &mut EditorState) {
+    let Some(session) = &state.debug else {
+        state.notify(NotificationLevel::Info, "No debug session".to_string());
+        return;
+    };
+    let text = session.panel_text();
+    let buffer_id = match session.panel_buffer {
+        Some(buffer_id) if state.buffers.contains_key(&buffer_id) => buffer_id,
+        _ => {
+            let mut buffer = LineBuffer::new(text, None);
+            buffer.modified = false;
+            state.add_buffer(buffer)
+        }
+    };
+    if let Some(session) = &mut state.debug {
+        session.panel_buffer = Some(buffer_id);
+    }
+    state.buffer_idx = Some(buffer_id);
+    state.update_view = true;
+}
+
+/// Refresh the content of the debug panel buffer if it is open
+fn update_panel(state: &mut EditorState) {
+    let Some(session) = &state.debug else {
+        return;
+    };
+    let Some(buffer_id) = session.panel_buffer else {
+        return;
+    };
+    let text = session.panel_text();
+    let Some(buffer) = state.buffers.get_mut(&buffer_id) else {
+        return;
+    };
+    let last_row = buffer.get_num_lines() - 1;
+    let whole = Selection {
+        mark: Cursor { row: 0, column: 0 },
+        cursor: Cursor {
+            row: last_row,
+            column: buffer.get_line_length(last_row),
+        },
+    };
+    buffer.remove_text(&whole, &None, false);
+    buffer.insert_text(&text, &whole.mark, &None, false);
+    buffer.modified = false;
+    if let Some(instance) = state.instances.get_mut(&buffer_id) {
+        *instance = crate::buffer::instance::BufferInstance::new(buffer_id);
+    }
+    if state.buffer_idx == Some(buffer_id) {
+        state.update_view = true;
+    }
+}
+
+/// Handle messages from the debug adapter
+pub fn poll_debugger(state: &mut EditorState) {
+    loop {
+        let Some(session) = &mut state.debug else {
+            return;
+        };
+        if !session.handle.is_running() {
+            state.debug = None;
+            state.notify(NotificationLevel::Error, "Debug adapter exited".to_string());
+            state.update_view = true;
+            return;
+        }
+        let Some(message) = session.handle.recv_message_sync() else {
+            return;
+        };
+        match message {
+            DapMessage::Response(response) => handle_response(state, response),
+            DapMessage::Event(event) => handle_event(state, event),
+            DapMessage::Request(request) => {
+                if let Err(err) = session.handle.send_response_sync(
+                    request.seq,
+                    request.command.clone(),
+                    false,
+                    Some(format!("{} is not supported", request.command)),
+                ) {
+                    tracing::error!("Failed to respond to {}: {}", request.command, err);
+                }
+            }
+        }
+    }
+}
+
+fn handle_response(state: &mut EditorState, response: Response) {
+    let Some(session) = &mut state.debug else {
+        return;
+    };
+    let pending = session
+        .pending
+        .remove(&response.request_seq)
+        .unwrap_or(PendingRequest::Other);
+    if !response.success {
+        let message = format!(
+            "Debug request {} failed: {}",
+            response.command,
+            response.message.unwrap_or_default()
+        );
+        state.notify(NotificationLevel::Error, message);
+        return;
+    }
+    let body = response.body.unwrap_or_default();
+
+    match pending {
+        PendingRequest::Initialize => {
+            let request = session.config.request.clone();
+            let arguments = session.config.arguments.clone();
+            session.send_request(&request, arguments, PendingRequest::Other);
+        }
+        PendingRequest::StackTrace => {
+            let stack: StackTraceResponse = match serde_json::from_value(body) {
+                Ok(stack) => stack,
+                Err(err) => {
+                    tracing::error!("Malformed stack trace: {}", err);
+                    return;
+                }
+            };
+            session.frames = stack.stack_frames;
+            session.variables.clear();
+            let Some(frame) = session.frames.first().cloned() else {
+                return;
+            };
+            session.send_request(
+                "scopes",
+                json!({"frameId": frame.id}),
+                PendingRequest::Scopes,
+            );
+            if let Some(path) = frame.source.and_then(|source| source.path) {
+                let row = frame.line.saturating_sub(1);
+                session.stop = Some((path.clone(), row));
+                state.open_location(
+                    &path,
+                    Cursor {
+                        row,
+                        column: frame.column.saturating_sub(1),
+                    },
+                    &None,
+                );
+            }
+            update_panel(state);
+        }
+        PendingRequest::Scopes => {
+            let Ok(scopes) = serde_json::from_value::<ScopesResponse>(body) else {
+                tracing::error!("Malformed scopes response");
+                return;
+            };
+            for scope in scopes.scopes {
+                session.send_request(
+                    "variables",
+                    json!({"variablesReference": scope.variables_reference}),
+                    PendingRequest::Variables(scope.name),
+                );
+            }
+        }
+        PendingRequest::Variables(scope) => {
+            let Ok(variables) = serde_json::from_value::<VariablesResponse>(body) else {
+                tracing::error!("Malformed variables response");
+                return;
+            };
+            session.variables.push((scope, variables.variables));
+            update_panel(state);
+        }
+        PendingRequest::Other => {}
+    }
+}
+
+fn handle_event(state: &mut EditorState, event: Event) {
+    let Some(session) = &mut state.debug else {
+        return;
+    };
+    let body = event.body.unwrap_or_default();
+    match event.event.as_str() {
+        "initialized" => {
+            for (path, rows) in &state.breakpoints {
+                session.set_breakpoints(path, rows);
+            }
+            session.send_request("configurationDone", json!({}), PendingRequest::Other);
+        }
+        "stopped" => {
+            let Ok(stopped) = serde_json::from_value::<StoppedEvent>(body) else {
+                tracing::error!("Malformed stopped event");
+                return;
+            };
+            session.thread_id = stopped.thread_id.or(session.thread_id);
+            if let Some(thread_id) = session.thread_id {
+                session.send_request(
+                    "stackTrace",
+                    json!({"threadId": thread_id, "startFrame": 0, "levels": STACK_DEPTH}),
+                    PendingRequest::StackTrace,
+                );
+            }
+            state.notify(
+                NotificationLevel::Info,
+                format!("Stopped ({})", stopped.reason),
+            );
+        }
+        "continued" => {
+            session.stop = None;
+            state.update_view = true;
+        }
+        "output" => {
+            if let Ok(output) = serde_json::from_value::<OutputEvent>(body) {
+                if output.category.as_deref() != Some("telemetry") {
+                    session
+                        .output
+                        .extend(output.output.lines().map(String::from));
+                    update_panel(state);
+                }
+            }
+        }
+        "terminated" | "exited" => {
+            if let Some(mut session) = state.debug.take() {
+                session.send_request("disconnect", json!({}), PendingRequest::Other);
+                state.notify(
+                    NotificationLevel::Info,
+                    format!("{} finished", session.config.name),
+                );
+            }
+            state.update_view = true;
+        }
+        _ => {}
+    }
+}
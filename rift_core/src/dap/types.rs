@@ -0,0 +1,139 @@
+//! Wire format of the Debug Adapter Protocol messages handled by the editor.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Message received from a debug adapter
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DapMessage {
+    Response(Response),
+    Event(Event),
+    /// Reverse request sent by the adapter, such as `runInTerminal`
+    Request(Request),
+}
+
+/// Field names follow the protocol, which uses `request_seq` unlike other fields
+#[derive(Debug, Deserialize)]
+pub struct Response {
+    pub request_seq: u64,
+    pub success: bool,
+    pub command: String,
+    pub message: Option<String>,
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Event {
+    pub event: String,
+    pub body: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub seq: u64,
+    pub command: String,
+}
+
+/// Message sent to a debug adapter
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum OutgoingMessage {
+    Request {
+        seq: u64,
+        command: String,
+        arguments: Value,
+    },
+    Response {
+        seq: u64,
+        request_seq: u64,
+        success: bool,
+        command: String,
+        message: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoppedEvent {
+    pub reason: String,
+    pub thread_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutputEvent {
+    pub category: Option<String>,
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Source {
+    pub name: Option<String>,
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StackFrame {
+    pub id: i64,
+    pub name: String,
+    pub source: Option<Source>,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackTraceResponse {
+    pub stack_frames: Vec<StackFrame>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scope {
+    pub name: String,
+    pub variables_reference: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScopesResponse {
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    #[serde(rename = "type")]
+    pub type_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VariablesResponse {
+    pub variables: Vec<Variable>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DapMessage, StackTraceResponse};
+
+    #[test]
+    fn parse_dap_messages() {
+        let message: DapMessage = serde_json::from_str(
+            r#"{"seq": 4, "type": "event", "event": "stopped", "body": {"reason": "breakpoint", "threadId": 1}}"#,
+        )
+        .unwrap();
+        assert!(matches!(message, DapMessage::Event(event) if event.event == "stopped"));
+
+        let message: DapMessage = serde_json::from_str(
+            r#"{"seq": 5, "type": "response", "request_seq": 3, "success": true, "command": "stackTrace",
+                "body": {"stackFrames": [{"id": 1000, "name": "main", "line": 4, "column": 1,
+                "source": {"path": "/project/src/main.rs"}}]}}"#,
+        )
+        .unwrap();
+        let DapMessage::Response(response) = message else {
+            panic!("expected a response");
+        };
+        let stack: StackTraceResponse = serde_json::from_value(response.body.unwrap()).unwrap();
+        assert_eq!(stack.stack_frames[0].line, 4);
+    }
+}
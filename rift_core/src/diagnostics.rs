@@ -0,0 +1,231 @@
+//! Diagnostics of the current buffer shown at the end of their lines
+//! and moving the cursor between them.
+//! Diagnostics are also requested from servers which only provide them on request.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    buffer::instance::{Cursor, Language},
+    lsp::{
+        client::LSPClientHandle,
+        types::{Diagnostic, DiagnosticSeverity, PublishDiagnostics},
+    },
+    state::EditorState,
+};
+
+/// Requests for the diagnostics of buffers (`textDocument/diagnostic`)
+#[derive(Debug, Default)]
+pub struct DiagnosticPull {
+    /// Path and version of the buffer by request id
+    requests: HashMap<usize, (String, usize)>,
+    /// Version of each path the diagnostics were last requested for
+    versions: HashMap<String, usize>,
+    last_pull: Option<Instant>,
+}
+
+/// Request the diagnostics of the open buffers of the language which changed since
+/// they were last requested, once the pull interval has passed unless forced
+pub fn pull_diagnostics(
+    state: &mut EditorState,
+    language: Language,
+    lsp_handle: &mut LSPClientHandle,
+    force: bool,
+) {
+    let interval = Duration::from_millis(state.preferences.diagnostic_pull_interval);
+    let pull = &mut state.diagnostic_pull;
+    if !force && pull.last_pull.is_some_and(|last| last.elapsed() < interval) {
+        return;
+    }
+    if !lsp_handle.supports("textDocument/diagnostic") {
+        return;
+    }
+    pull.last_pull = Some(Instant::now());
+    for buffer in state.buffers.values() {
+        let Some(path) = &buffer.file_path else {
+            continue;
+        };
+        if buffer.language != language
+            || (!force && pull.versions.get(path) == Some(&buffer.version))
+        {
+            continue;
+        }
+        match lsp_handle.send_request_sync(
+            "textDocument/diagnostic".to_string(),
+            Some(LSPClientHandle::document_diagnostic_request(path.clone())),
+        ) {
+            Ok(id) => {
+                pull.requests.insert(id, (path.clone(), buffer.version));
+                pull.versions.insert(path.clone(), buffer.version);
+            }
+            Err(err) => tracing::error!("Failed to request diagnostics: {}", err),
+        }
+    }
+}
+
+/// Store the diagnostics received for a request, unchanged diagnostics are kept
+pub fn set_pulled_diagnostics(
+    state: &mut EditorState,
+    request_id: usize,
+    diagnostics: Option<Vec<Diagnostic>>,
+) {
+    let Some((path, version)) = state.diagnostic_pull.requests.remove(&request_id) else {
+        return;
+    };
+    let Some(diagnostics) = diagnostics else {
+        if let Some(published) = state.diagnostics.get_mut(&diagnostics_key(&path)) {
+            published.version = version;
+        }
+        return;
+    };
+    state.publish_diagnostics(PublishDiagnostics {
+        uri: diagnostics_key(&path),
+        version,
+        diagnostics,
+    });
+    state.update_view = true;
+}
+
+/// Key of the diagnostics of the path in `state.diagnostics`
+fn diagnostics_key(path: &str) -> String {
+    #[allow(unused_mut)]
+    let mut path = path.to_string();
+    #[cfg(target_os = "windows")]
+    {
+        path = path.to_lowercase();
+    }
+    path
+}
+
+/// Diagnostics of the buffer, empty if they are for another version of it
+pub fn buffer_diagnostics(state: &EditorState, buffer_id: u32) -> &[Diagnostic] {
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let path = diagnostics_key(buffer.file_path.as_deref().unwrap_or_default());
+    match state.diagnostics.get(&path) {
+        Some(diagnostics) if diagnostics.version != 0 && diagnostics.version == buffer.version => {
+            &diagnostics.diagnostics
+        }
+        _ => &[],
+    }
+}
+
+/// Most severe diagnostic starting on the row of each visible line of the current buffer,
+/// for the last line of wrapped rows
+pub fn inline_diagnostics(state: &EditorState) -> Vec<Option<(DiagnosticSeverity, String)>> {
+    let Some(buffer_id) = state.buffer_idx else {
+        return vec![];
+    };
+    if !state.preferences.inline_diagnostics {
+        return vec![];
+    }
+    let diagnostics = buffer_diagnostics(state, buffer_id);
+    state
+        .gutter_info
+        .iter()
+        .map(|line| {
+            if !line.wrap_end {
+                return None;
+            }
+            diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.range.in_order().0.row == line.start.row)
+                .min_by_key(|diagnostic| diagnostic.severity.clone())
+                .map(|diagnostic| (diagnostic.severity.clone(), diagnostic.message.clone()))
+        })
+        .collect()
+}
+
+/// First line of the message fit into the width, None if there is no room for it
+pub fn inline_message(message: &str, width: usize) -> Option<String> {
+    let message = message.lines().next().unwrap_or_default().trim();
+    if width < 4 || message.is_empty() {
+        return None;
+    }
+    if message.chars().count() <= width {
+        return Some(message.to_string());
+    }
+    let truncated: String = message.chars().take(width - 1).collect();
+    Some(format!("{}…", truncated.trim_end()))
+}
+
+/// Start of the next diagnostic after the cursor, or of the previous one before it.
+/// Wraps around the buffer.
+pub fn next_diagnostic(
+    diagnostics: &[Diagnostic],
+    cursor: Cursor,
+    forward: bool,
+) -> Option<Cursor> {
+    let mut starts: Vec<Cursor> = diagnostics
+        .iter()
+        .map(|diagnostic| *diagnostic.range.in_order().0)
+        .collect();
+    starts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    starts.dedup();
+    if forward {
+        starts
+            .iter()
+            .find(|start| **start > cursor)
+            .or(starts.first())
+            .copied()
+    } else {
+        starts
+            .iter()
+            .rev()
+            .find(|start| **start < cursor)
+            .or(starts.last())
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inline_message, next_diagnostic};
+    use crate::{
+        buffer::instance::{Cursor, Selection},
+        lsp::types::{Diagnostic, DiagnosticSeverity},
+    };
+
+    #[test]
+    fn inline_diagnostics() {
+        assert_eq!(
+            inline_message("unused variable\nhelp: remove it", 20),
+            Some("unused variable".to_string())
+        );
+        assert_eq!(
+            inline_message("unused variable", 8),
+            Some("unused…".to_string())
+        );
+        assert_eq!(inline_message("unused variable", 2), None);
+
+        let diagnostic = |row, column| Diagnostic {
+            range: Selection {
+                mark: Cursor { row, column },
+                cursor: Cursor {
+                    row,
+                    column: column + 1,
+                },
+            },
+            severity: DiagnosticSeverity::Warning,
+            code: String::new(),
+            source: String::new(),
+            message: String::new(),
+        };
+        let diagnostics = vec![diagnostic(4, 2), diagnostic(1, 0)];
+        let cursor = Cursor { row: 1, column: 0 };
+        assert_eq!(
+            next_diagnostic(&diagnostics, cursor, true),
+            Some(Cursor { row: 4, column: 2 })
+        );
+        assert_eq!(
+            next_diagnostic(&diagnostics, cursor, false),
+            Some(Cursor { row: 4, column: 2 })
+        );
+        assert_eq!(
+            next_diagnostic(&diagnostics, Cursor { row: 9, column: 0 }, true),
+            Some(Cursor { row: 1, column: 0 })
+        );
+        assert_eq!(next_diagnostic(&[], cursor, true), None);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::io::encryption::EncryptionKind;
+
+/// What is waiting on the user's confirmation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmKind {
+    /// Closing the buffer with the given id
+    CloseBuffer(u32),
+    Quit,
+    /// Restoring the unsaved changes journaled before a crash
+    Recover,
+}
+
+/// Answer to an unsaved changes confirmation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ConfirmChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+/// Confirmation asked before discarding unsaved changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmDialog {
+    pub kind: ConfirmKind,
+    pub message: String,
+}
+
+impl ConfirmDialog {
+    pub fn close_buffer(id: u32, name: &str) -> Self {
+        Self {
+            kind: ConfirmKind::CloseBuffer(id),
+            message: format!("{} has unsaved changes.", name),
+        }
+    }
+
+    pub fn quit(names: &[String]) -> Self {
+        Self {
+            kind: ConfirmKind::Quit,
+            message: format!("Unsaved changes in:\n{}", names.join("\n")),
+        }
+    }
+
+    pub fn recover(names: &[String]) -> Self {
+        Self {
+            kind: ConfirmKind::Recover,
+            message: format!(
+                "Unsaved changes were recovered after a crash in:\n{}",
+                names.join("\n")
+            ),
+        }
+    }
+}
+
+/// What the text entered in an input dialog is used for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputKind {
+    /// Identity file or passphrase decrypting the file at the path
+    Decrypt(String, EncryptionKind),
+    /// Name of a file created in the folder
+    NewFile(String),
+    /// Name of a folder created in the folder
+    NewFolder(String),
+    /// New name of the file or folder
+    Rename(String),
+    /// Confirmation of deleting the file or folder
+    Delete(String),
+}
+
+/// Single line of text asked from the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputDialog {
+    pub kind: InputKind,
+    pub title: String,
+    pub input: String,
+    /// Hide the input while typing
+    pub secret: bool,
+}
+
+impl InputDialog {
+    fn plain(kind: InputKind, title: String, input: String) -> Self {
+        Self {
+            kind,
+            title,
+            input,
+            secret: false,
+        }
+    }
+
+    pub fn new_file(folder: &str) -> Self {
+        Self::plain(
+            InputKind::NewFile(folder.to_string()),
+            "New file".to_string(),
+            String::new(),
+        )
+    }
+
+    pub fn new_folder(folder: &str) -> Self {
+        Self::plain(
+            InputKind::NewFolder(folder.to_string()),
+            "New folder".to_string(),
+            String::new(),
+        )
+    }
+
+    pub fn rename(path: &str, name: &str) -> Self {
+        Self::plain(
+            InputKind::Rename(path.to_string()),
+            format!("Rename {}", name),
+            name.to_string(),
+        )
+    }
+
+    pub fn delete(path: &str, name: &str) -> Self {
+        Self::plain(
+            InputKind::Delete(path.to_string()),
+            format!("Delete {}? Enter y to confirm", name),
+            String::new(),
+        )
+    }
+
+    pub fn decrypt(path: &str, kind: EncryptionKind, identity: Option<String>) -> Self {
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match kind {
+            EncryptionKind::Age => Self {
+                kind: InputKind::Decrypt(path.to_string(), kind),
+                title: format!("Identity file for {}", name),
+                input: identity.unwrap_or_default(),
+                secret: false,
+            },
+            EncryptionKind::Gpg => Self {
+                kind: InputKind::Decrypt(path.to_string(), kind),
+                title: format!("Passphrase for {}", name),
+                input: String::new(),
+                secret: true,
+            },
+        }
+    }
+}
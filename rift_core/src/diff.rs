@@ -0,0 +1,445 @@
+use std::ops::Range;
+
+use anyhow::{bail, Context, Result};
+
+use crate::io::file_io::{override_file_content, read_file_content};
+
+/// Edit turning one sequence into another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp {
+    /// Items at the given indices of the old and new sequences are equal
+    Equal(usize, usize),
+    /// Item at the index of the old sequence is removed
+    Delete(usize),
+    /// Item at the index of the new sequence is inserted
+    Insert(usize),
+}
+
+/// Edit distance above which the differing part of the sequences
+/// is reported as entirely replaced instead of searching for a shortest script
+pub const MAX_EDIT_DISTANCE: usize = 2000;
+
+/// Shortest edit script between two sequences
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffOp> {
+    // Common prefix and suffix are skipped before searching
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<DiffOp> = (0..prefix).map(|idx| DiffOp::Equal(idx, idx)).collect();
+    match myers(old_middle, new_middle) {
+        Some(middle) => ops.extend(middle.into_iter().map(|op| match op {
+            DiffOp::Equal(x, y) => DiffOp::Equal(x + prefix, y + prefix),
+            DiffOp::Delete(x) => DiffOp::Delete(x + prefix),
+            DiffOp::Insert(y) => DiffOp::Insert(y + prefix),
+        })),
+        None => {
+            ops.extend((0..old_middle.len()).map(|x| DiffOp::Delete(x + prefix)));
+            ops.extend((0..new_middle.len()).map(|y| DiffOp::Insert(y + prefix)));
+        }
+    }
+    ops.extend(
+        (0..suffix).map(|idx| DiffOp::Equal(old.len() - suffix + idx, new.len() - suffix + idx)),
+    );
+    ops
+}
+
+/// Myers' algorithm returning None if the edit distance exceeds the limit
+fn myers<T: PartialEq>(old: &[T], new: &[T]) -> Option<Vec<DiffOp>> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = ((n + m) as usize).min(MAX_EDIT_DISTANCE);
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace = vec![];
+    let mut found = false;
+
+    'search: for d in 0..=max as isize {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    // Walk back through the trace to recover the edits
+    let mut ops = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let idx = (k + offset) as usize;
+        let previous_k = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[(previous_k + offset) as usize];
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            ops.push(DiffOp::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == previous_x {
+                ops.push(DiffOp::Insert(previous_y as usize));
+            } else {
+                ops.push(DiffOp::Delete(previous_x as usize));
+            }
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    ops.reverse();
+    Some(ops)
+}
+
+/// Kind of change of a line in a side by side diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    Unchanged,
+    Modified,
+    Removed,
+    Added,
+}
+
+/// Line on one side of a side by side diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    /// Line number (starting at 0)
+    pub row: usize,
+    pub text: String,
+    pub change: LineChange,
+    /// Changed char ranges within a modified line
+    pub changed_ranges: CharRanges,
+}
+
+/// Row of a side by side diff.
+/// A side is empty when the line only exists on the other side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffRow {
+    pub left: Option<DiffLine>,
+    pub right: Option<DiffLine>,
+}
+
+/// Two texts aligned line by line for display side by side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SideBySideDiff {
+    pub left_title: String,
+    pub right_title: String,
+    pub rows: Vec<DiffRow>,
+    /// Files the sides were read from, written back by `save`
+    pub left_path: Option<String>,
+    pub right_path: Option<String>,
+    /// Sides changed by pushing hunks since they were last saved
+    pub left_modified: bool,
+    pub right_modified: bool,
+    /// Index of the selected hunk
+    pub hunk_idx: usize,
+}
+
+impl SideBySideDiff {
+    pub fn new(left_title: String, left: &str, right_title: String, right: &str) -> Self {
+        Self {
+            left_title,
+            right_title,
+            rows: side_by_side(left, right),
+            left_path: None,
+            right_path: None,
+            left_modified: false,
+            right_modified: false,
+            hunk_idx: 0,
+        }
+    }
+
+    /// Diff of two files which can be edited by pushing hunks between them
+    pub fn from_files(left_path: &str, right_path: &str) -> Result<Self> {
+        let left = read_file_content(left_path)
+            .with_context(|| format!("Failed to read {}", left_path))?;
+        let right = read_file_content(right_path)
+            .with_context(|| format!("Failed to read {}", right_path))?;
+        let mut diff = Self::new(left_path.to_string(), &left, right_path.to_string(), &right);
+        diff.left_path = Some(left_path.to_string());
+        diff.right_path = Some(right_path.to_string());
+        Ok(diff)
+    }
+
+    /// Row ranges of consecutive changed rows
+    pub fn hunks(&self) -> Vec<Range<usize>> {
+        let mut hunks: Vec<Range<usize>> = vec![];
+        for (idx, row) in self.rows.iter().enumerate() {
+            let changed = !row
+                .left
+                .as_ref()
+                .is_some_and(|line| line.change == LineChange::Unchanged);
+            match hunks.last_mut() {
+                Some(hunk) if changed && hunk.end == idx => hunk.end += 1,
+                _ if changed => hunks.push(idx..idx + 1),
+                _ => {}
+            }
+        }
+        hunks
+    }
+
+    pub fn selected_hunk(&self) -> Option<Range<usize>> {
+        self.hunks().get(self.hunk_idx).cloned()
+    }
+
+    pub fn next_hunk(&mut self) {
+        let num_hunks = self.hunks().len();
+        if num_hunks > 0 {
+            self.hunk_idx = (self.hunk_idx + 1) % num_hunks;
+        }
+    }
+
+    pub fn prev_hunk(&mut self) {
+        let num_hunks = self.hunks().len();
+        if num_hunks > 0 {
+            self.hunk_idx = (self.hunk_idx + num_hunks - 1) % num_hunks;
+        }
+    }
+
+    /// Lines of one side of the diff
+    fn side_lines(rows: &[DiffRow], left: bool) -> impl Iterator<Item = &str> {
+        rows.iter().filter_map(move |row| {
+            if left { &row.left } else { &row.right }
+                .as_ref()
+                .map(|line| line.text.as_str())
+        })
+    }
+
+    /// Replace the selected hunk on one side with the other side's version
+    pub fn push_hunk(&mut self, to_right: bool) {
+        let Some(hunk) = self.selected_hunk() else {
+            return;
+        };
+        let source = !to_right;
+        let target: Vec<&str> = Self::side_lines(&self.rows[..hunk.start], to_right)
+            .chain(Self::side_lines(&self.rows[hunk.clone()], source))
+            .chain(Self::side_lines(&self.rows[hunk.end..], to_right))
+            .collect();
+        let unchanged: Vec<&str> = Self::side_lines(&self.rows, source).collect();
+        let (left, right) = if to_right {
+            (unchanged, target)
+        } else {
+            (target, unchanged)
+        };
+        let join = |lines: Vec<&str>| lines.iter().map(|line| format!("{}\n", line)).collect();
+        let (left, right): (String, String) = (join(left), join(right));
+        self.rows = side_by_side(&left, &right);
+        if to_right {
+            self.right_modified = true;
+        } else {
+            self.left_modified = true;
+        }
+        self.hunk_idx = self.hunk_idx.min(self.hunks().len().saturating_sub(1));
+    }
+
+    /// Write the modified sides back to their files
+    pub fn save(&mut self) -> Result<()> {
+        for left in [true, false] {
+            let (path, modified) = if left {
+                (&self.left_path, self.left_modified)
+            } else {
+                (&self.right_path, self.right_modified)
+            };
+            if !modified {
+                continue;
+            }
+            let Some(path) = path else {
+                bail!("Diff side is not backed by a file");
+            };
+            let content: String = Self::side_lines(&self.rows, left)
+                .map(|line| format!("{}\n", line))
+                .collect();
+            override_file_content(path, content)?;
+            if left {
+                self.left_modified = false;
+            } else {
+                self.right_modified = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the texts are equal
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|row| {
+            row.left
+                .as_ref()
+                .is_some_and(|line| line.change == LineChange::Unchanged)
+        })
+    }
+}
+
+/// Align the lines of two texts pairing up removed and added lines as modifications
+pub fn side_by_side(left: &str, right: &str) -> Vec<DiffRow> {
+    let left_lines: Vec<&str> = left.lines().collect();
+    let right_lines: Vec<&str> = right.lines().collect();
+    let mut rows = vec![];
+    let mut removed = vec![];
+    let mut added = vec![];
+
+    let flush = |removed: &mut Vec<usize>, added: &mut Vec<usize>, rows: &mut Vec<DiffRow>| {
+        for idx in 0..removed.len().max(added.len()) {
+            let left_row = removed.get(idx).copied();
+            let right_row = added.get(idx).copied();
+            let (left_ranges, right_ranges) = match (left_row, right_row) {
+                (Some(l), Some(r)) => changed_ranges(left_lines[l], right_lines[r]),
+                _ => (vec![], vec![]),
+            };
+            let modified = left_row.is_some() && right_row.is_some();
+            rows.push(DiffRow {
+                left: left_row.map(|row| DiffLine {
+                    row,
+                    text: left_lines[row].to_string(),
+                    change: if modified {
+                        LineChange::Modified
+                    } else {
+                        LineChange::Removed
+                    },
+                    changed_ranges: left_ranges.clone(),
+                }),
+                right: right_row.map(|row| DiffLine {
+                    row,
+                    text: right_lines[row].to_string(),
+                    change: if modified {
+                        LineChange::Modified
+                    } else {
+                        LineChange::Added
+                    },
+                    changed_ranges: right_ranges.clone(),
+                }),
+            });
+        }
+        removed.clear();
+        added.clear();
+    };
+
+    for op in diff(&left_lines, &right_lines) {
+        match op {
+            DiffOp::Delete(row) => removed.push(row),
+            DiffOp::Insert(row) => added.push(row),
+            DiffOp::Equal(left_row, right_row) => {
+                flush(&mut removed, &mut added, &mut rows);
+                rows.push(DiffRow {
+                    left: Some(DiffLine {
+                        row: left_row,
+                        text: left_lines[left_row].to_string(),
+                        change: LineChange::Unchanged,
+                        changed_ranges: vec![],
+                    }),
+                    right: Some(DiffLine {
+                        row: right_row,
+                        text: right_lines[right_row].to_string(),
+                        change: LineChange::Unchanged,
+                        changed_ranges: vec![],
+                    }),
+                });
+            }
+        }
+    }
+    flush(&mut removed, &mut added, &mut rows);
+    rows
+}
+
+/// Start and end char indices of changed parts of a line
+pub type CharRanges = Vec<(usize, usize)>;
+
+/// Char ranges which differ between two versions of a line
+fn changed_ranges(left: &str, right: &str) -> (CharRanges, CharRanges) {
+    let left_chars: Vec<char> = left.chars().collect();
+    let right_chars: Vec<char> = right.chars().collect();
+    let mut left_ranges: CharRanges = vec![];
+    let mut right_ranges: CharRanges = vec![];
+
+    let extend = |ranges: &mut CharRanges, idx: usize| match ranges.last_mut() {
+        Some((_, end)) if *end == idx => *end += 1,
+        _ => ranges.push((idx, idx + 1)),
+    };
+    for op in diff(&left_chars, &right_chars) {
+        match op {
+            DiffOp::Delete(idx) => extend(&mut left_ranges, idx),
+            DiffOp::Insert(idx) => extend(&mut right_ranges, idx),
+            DiffOp::Equal(..) => {}
+        }
+    }
+    (left_ranges, right_ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, side_by_side, DiffOp, LineChange, SideBySideDiff};
+
+    #[test]
+    fn diff_ops() {
+        let ops = diff(&['a', 'b', 'c'], &['a', 'c', 'd']);
+        assert_eq!(
+            ops,
+            vec![
+                DiffOp::Equal(0, 0),
+                DiffOp::Delete(1),
+                DiffOp::Equal(2, 1),
+                DiffOp::Insert(2)
+            ]
+        );
+        assert!(diff::<char>(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn side_by_side_rows() {
+        let rows = side_by_side("one\ntwo\nthree", "one\ntoo\nthree\nfour");
+        assert_eq!(rows.len(), 4);
+        let left = rows[1].left.as_ref().unwrap();
+        let right = rows[1].right.as_ref().unwrap();
+        assert_eq!(left.change, LineChange::Modified);
+        assert_eq!(left.changed_ranges, vec![(1, 2)]);
+        assert_eq!(right.changed_ranges, vec![(1, 2)]);
+        assert!(rows[3].left.is_none());
+        assert_eq!(rows[3].right.as_ref().unwrap().change, LineChange::Added);
+    }
+
+    #[test]
+    fn push_hunks() {
+        let mut diff = SideBySideDiff::new(
+            "left".into(),
+            "one\ntwo\nthree\n",
+            "right".into(),
+            "one\ntoo\nthree\nfour\n",
+        );
+        assert_eq!(diff.hunks(), vec![1..2, 3..4]);
+
+        diff.push_hunk(true);
+        assert_eq!(diff.hunks(), vec![3..4]);
+        assert!(diff.right_modified && !diff.left_modified);
+
+        diff.push_hunk(false);
+        assert!(diff.is_empty());
+        assert_eq!(diff.rows.len(), 4);
+    }
+}
@@ -0,0 +1,288 @@
+//! EditorConfig support: `.editorconfig` files from the directory of a file up to the
+//! nearest root override the indentation, line ending and whitespace preferences.
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::buffer::line_buffer::LineEnding;
+
+pub const FILE_NAME: &str = ".editorconfig";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Tab,
+    Space,
+}
+
+/// Properties which apply to a file, None where no `.editorconfig` sets them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EditorConfig {
+    pub indent_style: Option<IndentStyle>,
+    pub indent_size: Option<usize>,
+    /// Width of a tab, used as the indent size when it is `tab`
+    pub tab_width: Option<usize>,
+    pub end_of_line: Option<LineEnding>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+}
+
+#[derive(Debug)]
+struct Section {
+    pattern: Regex,
+    properties: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default)]
+struct ConfigFile {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+/// Regex matching the paths relative to the directory of the `.editorconfig`
+/// which the glob of a section header applies to
+pub(crate) fn glob_regex(glob: &str) -> Option<Regex> {
+    // Globs without a slash match the file name in any directory
+    let (glob, prefix) = match glob.strip_prefix('/') {
+        Some(glob) => (glob, ""),
+        None if glob.contains('/') => (glob, ""),
+        None => (glob, "(?:.*/)?"),
+    };
+    let mut regex = format!("^{}", prefix);
+    let mut chars = glob.chars().peekable();
+    let mut braces = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+            }
+            '{' => {
+                braces += 1;
+                regex.push_str("(?:");
+            }
+            ',' if braces > 0 => regex.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                regex.push(')');
+            }
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    regex.push_str(&regex::escape(&c.to_string()));
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).ok()
+}
+
+fn parse(content: &str) -> ConfigFile {
+    let mut file = ConfigFile::default();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(glob) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            match glob_regex(glob) {
+                Some(pattern) => file.sections.push(Section {
+                    pattern,
+                    properties: vec![],
+                }),
+                None => tracing::warn!("Invalid editorconfig section: {}", glob),
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_lowercase();
+        match file.sections.last_mut() {
+            Some(section) => section.properties.push((key, value)),
+            None if key == "root" => file.root = value == "true",
+            None => {}
+        }
+    }
+    file
+}
+
+impl EditorConfig {
+    /// Set a property, values which are not valid such as `unset` clear it
+    fn set(&mut self, key: &str, value: &str) {
+        let flag = match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        match key {
+            "indent_style" => {
+                self.indent_style = match value {
+                    "tab" => Some(IndentStyle::Tab),
+                    "space" => Some(IndentStyle::Space),
+                    _ => None,
+                }
+            }
+            // `tab` uses the tab width, which is what None resolves to
+            "indent_size" => self.indent_size = value.parse().ok(),
+            "tab_width" => self.tab_width = value.parse().ok(),
+            "end_of_line" => {
+                self.end_of_line = match value {
+                    "lf" => Some(LineEnding::Lf),
+                    "crlf" => Some(LineEnding::Crlf),
+                    _ => None,
+                }
+            }
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = flag,
+            "insert_final_newline" => self.insert_final_newline = flag,
+            _ => {}
+        }
+    }
+
+    /// Properties of the `.editorconfig` files which apply to the file,
+    /// files closer to it and later sections take precedence
+    pub fn for_file(path: &str) -> Self {
+        let path = Path::new(path);
+        let mut files = vec![];
+        for dir in path.ancestors().skip(1) {
+            let Ok(content) = std::fs::read_to_string(dir.join(FILE_NAME)) else {
+                continue;
+            };
+            let file = parse(&content);
+            let root = file.root;
+            files.push((dir, file));
+            if root {
+                break;
+            }
+        }
+
+        let mut config = Self::default();
+        for (dir, file) in files.iter().rev() {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            for section in &file.sections {
+                if section.pattern.is_match(&relative) {
+                    for (key, value) in &section.properties {
+                        config.set(key, value);
+                    }
+                }
+            }
+        }
+        config
+    }
+
+    /// Columns of one level of indentation, None if not configured
+    pub fn indent_width(&self) -> Option<usize> {
+        self.indent_size.or(self.tab_width)
+    }
+
+    /// Apply the whitespace properties to the content of the file before it is written
+    pub fn apply_to_content(&self, content: String, eol: &str) -> String {
+        let mut content = if self.trim_trailing_whitespace == Some(true) {
+            content
+                .split(eol)
+                .map(|line| line.trim_end_matches([' ', '\t']))
+                .collect::<Vec<_>>()
+                .join(eol)
+        } else {
+            content
+        };
+        // The last line of buffers is always empty, so files end with a newline by default
+        if self.insert_final_newline == Some(false) && content.ends_with(eol) {
+            content.truncate(content.len() - eol.len());
+        }
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_regex, EditorConfig, IndentStyle};
+    use crate::buffer::line_buffer::LineEnding;
+
+    #[test]
+    fn globs() {
+        let matches = |glob: &str, path: &str| glob_regex(glob).unwrap().is_match(path);
+        assert!(matches("*", "src/main.rs"));
+        assert!(matches("*.rs", "src/main.rs"));
+        assert!(!matches("*.rs", "src/main.py"));
+        assert!(matches("*.{js,ts}", "app.ts"));
+        assert!(matches("src/*.rs", "src/main.rs"));
+        assert!(!matches("src/*.rs", "src/a/main.rs"));
+        assert!(matches("src/**.rs", "src/a/main.rs"));
+        assert!(matches("Makefile", "sub/Makefile"));
+        assert!(matches("[!a]b.txt", "cb.txt"));
+        assert!(!matches("[!a]b.txt", "ab.txt"));
+    }
+
+    #[test]
+    fn nearest_config_takes_precedence() {
+        let root = std::env::temp_dir().join(format!("rift_editorconfig_{}", std::process::id()));
+        let nested = root.join("project").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            root.join(".editorconfig"),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 4\nend_of_line = crlf\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("project").join(".editorconfig"),
+            "[*.rs]\nindent_style = tab\ntab_width = 8\nindent_size = tab\n\n[src/**]\ntrim_trailing_whitespace = true\n",
+        )
+        .unwrap();
+
+        let file = nested.join("main.rs");
+        let config = EditorConfig::for_file(file.to_str().unwrap());
+        assert_eq!(config.indent_style, Some(IndentStyle::Tab));
+        assert_eq!(config.indent_width(), Some(8));
+        assert_eq!(config.end_of_line, Some(LineEnding::Crlf));
+        assert_eq!(config.trim_trailing_whitespace, Some(true));
+        assert_eq!(config.insert_final_newline, None);
+
+        let other = EditorConfig::for_file(root.join("notes.md").to_str().unwrap());
+        assert_eq!(other.indent_style, Some(IndentStyle::Space));
+        assert_eq!(other.indent_width(), Some(4));
+        assert_eq!(other.trim_trailing_whitespace, None);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn whitespace_on_save() {
+        let config = EditorConfig {
+            trim_trailing_whitespace: Some(true),
+            insert_final_newline: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.apply_to_content("a  \nb\t\n".into(), "\n"),
+            "a\nb".to_string()
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Editor lifecycle events, delivered once per frame to native callbacks and to the hooks
+//! of the configuration so they can react without polling the state themselves.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    actions::{perform_action, Action},
+    buffer::instance::Cursor,
+    editorconfig,
+    lsp::client::LSPClientHandle,
+    state::{EditorState, Mode},
+};
+
+/// Time the cursor has to stay in place before `CursorIdle` is sent
+pub const IDLE_DELAY: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum EventKind {
+    BufferOpened,
+    BufferSaved,
+    /// Sent before the buffer is removed
+    BufferClosed,
+    ModeChanged,
+    CursorIdle,
+    FocusGained,
+}
+
+#[derive(Debug, Clone)]
+pub struct EditorEvent {
+    pub kind: EventKind,
+    /// Buffer the event is about, the current buffer for mode, cursor and focus events
+    pub buffer_id: Option<u32>,
+    pub path: Option<String>,
+}
+
+pub type EventCallback = fn(&EditorEvent, &mut EditorState, &mut Option<&mut LSPClientHandle>);
+
+/// Action performed on an event, defined in the configuration
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Hook {
+    pub event: EventKind,
+    /// Glob the path of the buffer has to match, such as `*.rs`
+    #[serde(default)]
+    pub pattern: Option<String>,
+    pub action: Action,
+}
+
+impl Hook {
+    fn matches(&self, event: &EditorEvent) -> bool {
+        if self.event != event.kind {
+            return false;
+        }
+        let Some(pattern) = &self.pattern else {
+            return true;
+        };
+        let Some(path) = &event.path else {
+            return false;
+        };
+        editorconfig::glob_regex(pattern)
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(&path.replace('\\', "/")))
+    }
+}
+
+#[derive(Debug)]
+pub struct EventRegistry {
+    callbacks: HashMap<EventKind, Vec<EventCallback>>,
+    /// Events sent since the last poll
+    queue: Vec<EditorEvent>,
+    /// Mode when the last poll happened
+    mode: Mode,
+    /// Current buffer and cursor when they last changed
+    cursor: Option<(u32, Cursor)>,
+    cursor_moved: Instant,
+    idle_sent: bool,
+}
+
+impl Default for EventRegistry {
+    fn default() -> Self {
+        Self {
+            callbacks: HashMap::new(),
+            queue: vec![],
+            mode: Mode::Normal,
+            cursor: None,
+            cursor_moved: Instant::now(),
+            idle_sent: false,
+        }
+    }
+}
+
+impl EventRegistry {
+    /// Call the callback for each event of the kind
+    pub fn subscribe(&mut self, kind: EventKind, callback: EventCallback) {
+        self.callbacks.entry(kind).or_default().push(callback);
+    }
+
+    pub fn send(&mut self, event: EditorEvent) {
+        self.queue.push(event);
+    }
+}
+
+impl EditorState {
+    /// Send an event about the buffer, delivered by the next `poll_events`
+    pub fn send_event(&mut self, kind: EventKind, buffer_id: Option<u32>) {
+        let path = buffer_id
+            .and_then(|id| self.buffers.get(&id))
+            .and_then(|buffer| buffer.file_path.clone());
+        self.events.send(EditorEvent {
+            kind,
+            buffer_id,
+            path,
+        });
+    }
+}
+
+/// Send the mode and cursor events and deliver the events sent since the last poll
+pub fn poll_events(state: &mut EditorState, lsp_handle: &mut Option<&mut LSPClientHandle>) {
+    if state.events.mode != state.mode {
+        state.events.mode = state.mode.clone();
+        state.send_event(EventKind::ModeChanged, state.buffer_idx);
+    }
+
+    let cursor = state.buffer_idx.and_then(|id| {
+        state
+            .instances
+            .get(&id)
+            .map(|instance| (id, instance.cursor))
+    });
+    if state.events.cursor != cursor {
+        state.events.cursor = cursor;
+        state.events.cursor_moved = Instant::now();
+        state.events.idle_sent = false;
+    } else if cursor.is_some()
+        && !state.events.idle_sent
+        && state.events.cursor_moved.elapsed() >= IDLE_DELAY
+    {
+        state.events.idle_sent = true;
+        state.send_event(EventKind::CursorIdle, state.buffer_idx);
+    }
+
+    // Events sent by callbacks and hooks are delivered by the next poll
+    for event in std::mem::take(&mut state.events.queue) {
+        let callbacks = state
+            .events
+            .callbacks
+            .get(&event.kind)
+            .cloned()
+            .unwrap_or_default();
+        for callback in callbacks {
+            callback(&event, state, lsp_handle);
+        }
+        let actions: Vec<Action> = state
+            .preferences
+            .hooks
+            .iter()
+            .filter(|hook| hook.matches(&event))
+            .map(|hook| hook.action.clone())
+            .collect();
+        for action in actions {
+            perform_action(action, state, lsp_handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{poll_events, EditorEvent, EventKind, Hook};
+    use crate::{
+        actions::Action,
+        buffer::line_buffer::LineBuffer,
+        state::{EditorState, Mode},
+    };
+
+    #[test]
+    fn callbacks_and_hooks() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        state.preferences.hooks = vec![Hook {
+            event: EventKind::BufferOpened,
+            pattern: Some("*.md".into()),
+            action: Action::EnterInsertMode,
+        }];
+        state
+            .events
+            .subscribe(EventKind::ModeChanged, |event, state, _lsp_handle| {
+                state
+                    .last_edit
+                    .push(Action::GoToLine(event.buffer_id.unwrap() as usize));
+            });
+
+        let id = state.add_buffer(LineBuffer::new("a\n".into(), Some("notes.md".into())));
+        state.buffer_idx = Some(id);
+        state.add_buffer(LineBuffer::new("b\n".into(), Some("main.rs".into())));
+        poll_events(&mut state, &mut None);
+        assert!(matches!(state.mode, Mode::Insert));
+        assert!(state.last_edit.is_empty());
+
+        // The mode change is noticed by the next poll
+        poll_events(&mut state, &mut None);
+        assert!(
+            matches!(state.last_edit.as_slice(), [Action::GoToLine(line)] if *line == id as usize)
+        );
+
+        let hook = &state.preferences.hooks[0];
+        assert!(!hook.matches(&EditorEvent {
+            kind: EventKind::BufferOpened,
+            buffer_id: None,
+            path: Some("/src/main.rs".into()),
+        }));
+    }
+}
@@ -0,0 +1,187 @@
+//! Export of buffers as standalone html with syntax highlighting,
+//! styled with the colors of the active theme.
+
+use crate::{
+    buffer::{
+        instance::{Cursor, HighlightType, Selection},
+        line_buffer::HighlightSpans,
+    },
+    preferences::{Color, Theme},
+};
+
+const HIGHLIGHT_TYPES: [HighlightType; 10] = [
+    HighlightType::None,
+    HighlightType::White,
+    HighlightType::Red,
+    HighlightType::Orange,
+    HighlightType::Blue,
+    HighlightType::Green,
+    HighlightType::Purple,
+    HighlightType::Yellow,
+    HighlightType::Gray,
+    HighlightType::Turquoise,
+];
+
+fn css_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn class_name(highlight_type: HighlightType) -> String {
+    format!("{:?}", highlight_type).to_lowercase()
+}
+
+fn highlight_color(theme: &Theme, highlight_type: HighlightType) -> Color {
+    match highlight_type {
+        HighlightType::None => theme.highlight_none,
+        HighlightType::White => theme.highlight_white,
+        HighlightType::Red => theme.highlight_red,
+        HighlightType::Orange => theme.highlight_orange,
+        HighlightType::Blue => theme.highlight_blue,
+        HighlightType::Green => theme.highlight_green,
+        HighlightType::Purple => theme.highlight_purple,
+        HighlightType::Yellow => theme.highlight_yellow,
+        HighlightType::Gray => theme.highlight_gray,
+        HighlightType::Turquoise => theme.highlight_turquoise,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Html document of the lines (or only the selected part of them)
+/// highlighted with spans of byte offsets into the lines joined by "\n"
+pub fn to_html(
+    title: &str,
+    lines: &[String],
+    spans: &HighlightSpans,
+    selection: Option<&Selection>,
+    line_numbers: bool,
+    theme: &Theme,
+) -> String {
+    // Highlight of each byte of the buffer, later spans take precedence
+    let len: usize = lines.iter().map(|line| line.len() + 1).sum();
+    let mut byte_types = vec![HighlightType::None; len];
+    for (start, end, highlight_type) in spans {
+        for byte_type in byte_types.iter_mut().take(end + 1).skip(*start) {
+            *byte_type = *highlight_type;
+        }
+    }
+
+    let (start, end) = match selection {
+        Some(selection) => {
+            let (start, end) = selection.in_order();
+            (*start, *end)
+        }
+        None => (
+            Cursor { row: 0, column: 0 },
+            Cursor {
+                row: lines.len().saturating_sub(1),
+                column: lines.last().map(|line| line.len()).unwrap_or(0),
+            },
+        ),
+    };
+
+    let mut code = String::new();
+    let mut line_start = 0;
+    let number_width = (end.row + 1).to_string().len();
+    for (row, line) in lines.iter().enumerate() {
+        if row >= start.row && row <= end.row {
+            let from = if row == start.row { start.column } else { 0 };
+            let to = if row == end.row {
+                end.column.min(line.len())
+            } else {
+                line.len()
+            };
+            // The trailing empty line of the buffer is not exported
+            if row == end.row && row != start.row && to == 0 {
+                break;
+            }
+            if line_numbers {
+                code.push_str(&format!(
+                    "<span class=\"ln\">{:>width$} </span>",
+                    row + 1,
+                    width = number_width
+                ));
+            }
+            let mut idx = from;
+            while idx < to {
+                let highlight_type = byte_types[line_start + idx];
+                let mut token_end = idx + 1;
+                while token_end < to && byte_types[line_start + token_end] == highlight_type {
+                    token_end += 1;
+                }
+                while !line.is_char_boundary(token_end) {
+                    token_end += 1;
+                }
+                let text = escape(line.get(idx..token_end).unwrap_or_default());
+                match highlight_type {
+                    HighlightType::None => code.push_str(&text),
+                    _ => code.push_str(&format!(
+                        "<span class=\"{}\">{}</span>",
+                        class_name(highlight_type),
+                        text
+                    )),
+                }
+                idx = token_end;
+            }
+            code.push('\n');
+        }
+        line_start += line.len() + 1;
+    }
+
+    let mut style = format!(
+        "body {{ margin: 0; background: {bg}; }}\n\
+         pre {{ margin: 0; padding: 1em; background: {bg}; color: {fg}; \
+         font-family: monospace; }}\n\
+         .ln {{ color: {gutter}; user-select: none; }}\n",
+        bg = css_color(theme.editor_bg),
+        fg = css_color(theme.highlight_none),
+        gutter = css_color(theme.gutter_text),
+    );
+    for highlight_type in HIGHLIGHT_TYPES {
+        style.push_str(&format!(
+            ".{} {{ color: {}; }}\n",
+            class_name(highlight_type),
+            css_color(highlight_color(theme, highlight_type))
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n{}</style>\n</head>\n<body>\n<pre><code>{}</code></pre>\n</body>\n</html>\n",
+        escape(title),
+        style,
+        code
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_html;
+    use crate::{
+        buffer::instance::{Cursor, HighlightType, Selection},
+        preferences::Preferences,
+    };
+
+    #[test]
+    fn export_highlighted_html() {
+        let lines = vec!["fn a<T>() {}".to_string(), "".to_string()];
+        let spans = vec![(0, 1, HighlightType::Purple)];
+        let theme = Preferences::default().theme;
+        let html = to_html("a.rs", &lines, &spans, None, true, &theme);
+        assert!(html.contains(
+            "<span class=\"ln\">1 </span><span class=\"purple\">fn</span> a&lt;T&gt;() {}\n</code>"
+        ));
+
+        let selection = Selection {
+            mark: Cursor { row: 0, column: 3 },
+            cursor: Cursor { row: 0, column: 4 },
+        };
+        let html = to_html("a.rs", &lines, &spans, Some(&selection), false, &theme);
+        assert!(html.contains("<code>a\n</code>"));
+    }
+}
@@ -0,0 +1,178 @@
+//! Detecting open files modified outside of the editor, unmodified buffers
+//! are reloaded and buffers with local changes ask what to do.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Result;
+
+use crate::{
+    actions::Action,
+    buffer::{
+        instance::{Cursor, Selection},
+        line_buffer::{LineBuffer, LineEnding},
+    },
+    io::file_io,
+    lsp::client::LSPClientHandle,
+    notifications::{NotificationAction, NotificationLevel},
+    state::EditorState,
+};
+
+/// Time between checks of the modification times of open files
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub struct FileWatcher {
+    last_check: Instant,
+    /// Modification time each buffer with local changes was last notified about
+    pub notified: HashMap<u32, SystemTime>,
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self {
+            last_check: Instant::now(),
+            notified: HashMap::new(),
+        }
+    }
+}
+
+/// Modification time of the file if it differs from the one the buffer was read or saved at
+pub fn changed_on_disk(buffer: &LineBuffer) -> Option<SystemTime> {
+    let modified = file_io::modified_time(buffer.file_path.as_ref()?)?;
+    (buffer.disk_mtime != Some(modified)).then_some(modified)
+}
+
+/// Check open files for external modification at most once per `CHECK_INTERVAL`.
+/// Returns the buffers without local changes which should be reloaded,
+/// the user is asked about buffers with local changes.
+pub fn poll_file_changes(state: &mut EditorState) -> Vec<u32> {
+    if state.file_watcher.last_check.elapsed() < CHECK_INTERVAL {
+        return vec![];
+    }
+    state.file_watcher.last_check = Instant::now();
+
+    let mut reload = vec![];
+    let mut conflicts = vec![];
+    for id in &state.buffer_order {
+        let Some(buffer) = state.buffers.get(id) else {
+            continue;
+        };
+        // Encrypted files are decrypted when opened, so they are not reloaded
+        if buffer
+            .file_path
+            .as_ref()
+            .is_some_and(|path| state.encrypted_files.contains_key(path))
+        {
+            continue;
+        }
+        let Some(modified) = changed_on_disk(buffer) else {
+            continue;
+        };
+        if !buffer.modified {
+            reload.push(*id);
+        } else if state.file_watcher.notified.get(id) != Some(&modified) {
+            conflicts.push((*id, modified, buffer.display_name()));
+        }
+    }
+
+    for (id, modified, name) in conflicts {
+        state.file_watcher.notified.insert(id, modified);
+        state.notify_with_actions(
+            NotificationLevel::Warning,
+            format!("{} changed on disk and has unsaved changes", name),
+            vec![
+                NotificationAction {
+                    label: "Reload".into(),
+                    action: Action::ReloadBuffer(id),
+                },
+                NotificationAction {
+                    label: "Keep".into(),
+                    action: Action::KeepBufferChanges(id),
+                },
+            ],
+        );
+    }
+    reload
+}
+
+/// Replace the content of the buffer with the file on disk, discarding local changes
+pub fn reload_buffer(
+    state: &mut EditorState,
+    id: u32,
+    lsp_handle: &mut Option<&mut LSPClientHandle>,
+) -> Result<()> {
+    let (buffer, _instance) = state.get_buffer_by_id(id);
+    let Some(path) = buffer.file_path.clone() else {
+        return Ok(());
+    };
+    let (content, encoding) = file_io::read_file_with_encoding(&path)?;
+    let line_ending = LineEnding::detect(&content);
+    let content = content.replace("\r\n", "\n");
+    let modified = file_io::modified_time(&path);
+
+    let (buffer, instance) = state.get_buffer_by_id_mut(id);
+    let last_row = buffer.get_num_lines() - 1;
+    let all = Selection {
+        cursor: Cursor {
+            row: last_row,
+            column: buffer.get_line_length(last_row),
+        },
+        mark: Cursor { row: 0, column: 0 },
+    };
+    buffer.remove_text(&all, lsp_handle, true);
+    buffer.insert_text(&content, &Cursor { row: 0, column: 0 }, lsp_handle, true);
+    buffer.modified = false;
+    buffer.disk_mtime = modified;
+    buffer.encoding = encoding;
+    if let Some(line_ending) = buffer.editorconfig.end_of_line.or(line_ending) {
+        buffer.line_ending = Some(line_ending);
+    }
+
+    // Keep the cursor where it was as far as the new content allows
+    let row = instance.cursor.row.min(buffer.get_num_lines() - 1);
+    let column = instance.cursor.column.min(buffer.get_line_length(row));
+    instance.cursor = Cursor { row, column };
+    instance.selection = Selection {
+        cursor: instance.cursor,
+        mark: instance.cursor,
+    };
+    instance.column_level = column;
+
+    state.file_watcher.notified.remove(&id);
+    state.update_view = true;
+    Ok(())
+}
+
+/// Mark the current file on disk as seen, the buffer overwrites it on the next save
+pub fn keep_buffer_changes(state: &mut EditorState, id: u32) {
+    let (buffer, _instance) = state.get_buffer_by_id_mut(id);
+    if let Some(path) = buffer.file_path.clone() {
+        buffer.disk_mtime = file_io::modified_time(&path);
+    }
+    state.file_watcher.notified.remove(&id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::changed_on_disk;
+    use crate::{buffer::line_buffer::LineBuffer, io::file_io};
+
+    #[test]
+    fn detect_changes_on_disk() {
+        let path = std::env::temp_dir().join(format!("rift_file_changes_{}", std::process::id()));
+        std::fs::write(&path, "one\n").unwrap();
+        let path_str = path.to_str().unwrap().to_string();
+
+        let mut buffer = LineBuffer::new("one\n".into(), Some(path_str.clone()));
+        assert!(changed_on_disk(&buffer).is_none());
+
+        buffer.disk_mtime = None;
+        assert_eq!(changed_on_disk(&buffer), file_io::modified_time(&path_str));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(changed_on_disk(&buffer).is_none());
+    }
+}
@@ -0,0 +1,178 @@
+//! Filtering the lines of the selection through a shell command, like `!` in vim.
+//! The lines are replaced by the output of the command as a single undoable edit.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    buffer::instance::{Cursor, Selection},
+    concurrent::{cli, AsyncResult},
+    lsp::client::LSPClientHandle,
+    notifications::NotificationLevel,
+    state::EditorState,
+};
+
+/// Output of a finished filter command sent back to the editor
+#[derive(Debug, Serialize, Deserialize)]
+struct FilterRun {
+    command: String,
+    buffer_id: u32,
+    /// Buffer version the input was taken from
+    version: usize,
+    start_row: usize,
+    end_row: usize,
+    success: bool,
+    stdout: String,
+    stderr: String,
+}
+
+/// Lines from the start to the end row as written to the command, each ending with a newline
+fn filter_input(lines: &[String], start_row: usize, end_row: usize) -> String {
+    lines[start_row..=end_row]
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// Text replacing the filtered lines, without the newline ending the last line
+fn filter_output(stdout: &str) -> String {
+    let output = stdout.replace("\r\n", "\n");
+    match output.strip_suffix('\n') {
+        Some(output) => output.to_string(),
+        None => output,
+    }
+}
+
+/// Run the command in the background with the selected lines as its input
+pub fn filter_selection(state: &mut EditorState, command: String) {
+    let Some(buffer_id) = state.buffer_idx else {
+        return;
+    };
+    let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+    let (start, end) = instance.selection.in_order();
+    let (start_row, end_row) = (start.row, end.row);
+    let input = filter_input(&buffer.lines, start_row, end_row);
+    let version = buffer.version;
+    let folder = state.workspace_folder.clone();
+    let sender = state.async_handle.sender.clone();
+
+    state.rt.spawn(async move {
+        let result = match cli::pipe_through_shell(&command, input, &folder).await {
+            Ok(output) => {
+                let run = FilterRun {
+                    command,
+                    buffer_id,
+                    version,
+                    start_row,
+                    end_row,
+                    success: output.status.success(),
+                    stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                    stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                };
+                AsyncResult {
+                    result: serde_json::to_string(&run).unwrap(),
+                    callback: apply_filter_run,
+                }
+            }
+            Err(err) => AsyncResult {
+                result: format!("Failed to run {}: {}", command, err),
+                callback: |message, state, _lsp_handle| {
+                    state.notify(NotificationLevel::Error, message);
+                },
+            },
+        };
+        if let Err(err) = sender.send(result).await {
+            tracing::error!("Failed to send filter result: {}", err);
+        }
+    });
+}
+
+fn apply_filter_run(
+    result: String,
+    state: &mut EditorState,
+    lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    let run: FilterRun = match serde_json::from_str(&result) {
+        Ok(run) => run,
+        Err(err) => {
+            tracing::error!("Malformed filter result: {}", err);
+            return;
+        }
+    };
+    if !run.success {
+        let reason = run
+            .stderr
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("exited with an error");
+        state.notify(
+            NotificationLevel::Error,
+            format!("{}: {}", run.command, reason.trim()),
+        );
+        return;
+    }
+    if !state.buffers.contains_key(&run.buffer_id) {
+        return;
+    }
+    // The language server handle passed in belongs to the language of the current buffer
+    let current_language = state
+        .buffer_idx
+        .map(|id| state.get_buffer_by_id(id).0.language);
+    let (buffer, instance) = state.get_buffer_by_id_mut(run.buffer_id);
+    if buffer.version != run.version || current_language != Some(buffer.language) {
+        state.notify(
+            NotificationLevel::Warning,
+            format!(
+                "Output of {} discarded, the buffer changed while it was running",
+                run.command
+            ),
+        );
+        return;
+    }
+
+    let selection = Selection {
+        mark: Cursor {
+            row: run.start_row,
+            column: 0,
+        },
+        cursor: Cursor {
+            row: run.end_row,
+            column: buffer.get_line_length(run.end_row),
+        },
+    };
+    let end = buffer.replace_text(&selection, &filter_output(&run.stdout), lsp_handle);
+    instance.selection = Selection {
+        mark: selection.mark,
+        cursor: end,
+    };
+    instance.cursor = end;
+    instance.column_level = end.column;
+    state.update_view = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_input, filter_output};
+    use crate::buffer::{
+        instance::{Cursor, Selection},
+        line_buffer::LineBuffer,
+    };
+
+    #[test]
+    fn filter_lines_as_one_edit() {
+        let mut buffer = LineBuffer::new("c\nb\na\nend\n".into(), None);
+        assert_eq!(filter_input(&buffer.lines, 0, 2), "c\nb\na\n");
+
+        let selection = Selection {
+            mark: Cursor { row: 0, column: 0 },
+            cursor: Cursor { row: 2, column: 1 },
+        };
+        let end = buffer.replace_text(&selection, &filter_output("a\r\nb\r\nc\r\n"), &None);
+        assert_eq!(end, Cursor { row: 2, column: 1 });
+        assert_eq!(buffer.get_content("\n".into()), "a\nb\nc\nend\n");
+
+        buffer.undo(&None);
+        assert_eq!(buffer.get_content("\n".into()), "c\nb\na\nend\n");
+        buffer.redo(&None);
+        assert_eq!(buffer.get_content("\n".into()), "a\nb\nc\nend\n");
+    }
+}
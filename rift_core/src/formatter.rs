@@ -0,0 +1,212 @@
+//! Formatting buffers by piping them through external tools such as rustfmt.
+//! Used when no language server is available to format the buffer.
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Result};
+
+use crate::{
+    buffer::{
+        instance::{BufferInstance, Cursor, Language, Selection},
+        line_buffer::LineBuffer,
+    },
+    diff::{diff, DiffOp},
+    lsp::client::LSPClientHandle,
+};
+
+/// Replaced with the path of the buffer in formatter arguments
+pub const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Command reading the buffer from stdin and writing the formatted buffer to stdout
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FormatterCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl FormatterCommand {
+    fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+pub fn default_formatters() -> HashMap<Language, FormatterCommand> {
+    HashMap::from([
+        (
+            Language::Rust,
+            FormatterCommand::new("rustfmt", &["--edition", "2021", "--emit", "stdout"]),
+        ),
+        (
+            Language::Python,
+            FormatterCommand::new("black", &["-q", "-"]),
+        ),
+        (
+            Language::CSS,
+            FormatterCommand::new("prettier", &["--stdin-filepath", FILE_PLACEHOLDER]),
+        ),
+        (
+            Language::HTML,
+            FormatterCommand::new("prettier", &["--stdin-filepath", FILE_PLACEHOLDER]),
+        ),
+        (
+            Language::Markdown,
+            FormatterCommand::new("prettier", &["--stdin-filepath", FILE_PLACEHOLDER]),
+        ),
+    ])
+}
+
+/// Pipe the content through the formatter returning the formatted content
+pub fn run_formatter(
+    formatter: &FormatterCommand,
+    path: Option<&str>,
+    content: &str,
+) -> Result<String> {
+    let args = formatter
+        .args
+        .iter()
+        .map(|arg| arg.replace(FILE_PLACEHOLDER, path.unwrap_or("stdin")));
+    let mut child = Command::new(&formatter.program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Written from another thread so that large outputs do not fill the pipe
+    let mut stdin = child.stdin.take().unwrap();
+    let input = content.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    if let Ok(Err(err)) = writer.join() {
+        tracing::warn!("Failed to write to {}: {}", formatter.program, err);
+    }
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "{} {}: {}",
+            formatter.program,
+            output.status,
+            stderr.lines().next().unwrap_or_default()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Replace the content of the buffer with the formatted content by only
+/// editing the lines which changed, keeping the cursor on the same line
+pub fn apply_formatted(
+    buffer: &mut LineBuffer,
+    instance: &mut BufferInstance,
+    formatted: &str,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) {
+    let new_lines = LineBuffer::new(formatted.to_string(), None).lines;
+    let ops = diff(&buffer.lines, &new_lines);
+
+    // Row of the formatted buffer each unchanged row ends up at
+    let mut row_map = HashMap::new();
+    // Changed blocks as (old start, old end, new start, new end)
+    let mut hunks: Vec<(usize, usize, usize, usize)> = vec![];
+    let (mut old_row, mut new_row) = (0, 0);
+    for op in ops {
+        match op {
+            DiffOp::Equal(x, y) => {
+                row_map.insert(x, y);
+                old_row = x + 1;
+                new_row = y + 1;
+            }
+            DiffOp::Delete(x) => {
+                match hunks.last_mut() {
+                    Some(hunk) if hunk.1 == x && hunk.3 == new_row => hunk.1 = x + 1,
+                    _ => hunks.push((x, x + 1, new_row, new_row)),
+                }
+                old_row = x + 1;
+            }
+            DiffOp::Insert(y) => {
+                match hunks.last_mut() {
+                    Some(hunk) if hunk.1 == old_row && hunk.3 == y => hunk.3 = y + 1,
+                    _ => hunks.push((old_row, old_row, y, y + 1)),
+                }
+                new_row = y + 1;
+            }
+        }
+    }
+
+    // Applied from the bottom so that earlier rows are unaffected
+    for (old_start, old_end, new_start, new_end) in hunks.into_iter().rev() {
+        let start = Cursor {
+            row: old_start,
+            column: 0,
+        };
+        if old_end > old_start {
+            buffer.remove_text(
+                &Selection {
+                    mark: start,
+                    cursor: Cursor {
+                        row: old_end,
+                        column: 0,
+                    },
+                },
+                lsp_handle,
+                true,
+            );
+        }
+        if new_end > new_start {
+            let text: String = new_lines[new_start..new_end]
+                .iter()
+                .map(|line| format!("{}\n", line))
+                .collect();
+            buffer.insert_text(&text, &start, lsp_handle, true);
+        }
+    }
+
+    let row = (0..=instance.cursor.row)
+        .rev()
+        .find_map(|row| {
+            row_map
+                .get(&row)
+                .map(|new_row| new_row + instance.cursor.row - row)
+        })
+        .unwrap_or(instance.cursor.row)
+        .min(buffer.get_num_lines() - 1);
+    let column = instance.cursor.column.min(buffer.get_line_length(row));
+    instance.cursor = Cursor { row, column };
+    instance.selection.cursor = instance.cursor;
+    instance.selection.mark = instance.cursor;
+    instance.column_level = column;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_formatted;
+    use crate::buffer::{
+        instance::{BufferInstance, Cursor},
+        line_buffer::LineBuffer,
+    };
+
+    #[test]
+    fn apply_minimal_edits() {
+        let mut buffer = LineBuffer::new("fn main(){\nlet x=1;\n}\n\nfn f() {}\n".into(), None);
+        let mut instance = BufferInstance::new(0);
+        instance.cursor = Cursor { row: 4, column: 3 };
+        apply_formatted(
+            &mut buffer,
+            &mut instance,
+            "fn main() {\n    let x = 1;\n}\n\nfn f() {}\n",
+            &None,
+        );
+        assert_eq!(
+            buffer.get_content("\n".into()),
+            "fn main() {\n    let x = 1;\n}\n\nfn f() {}\n"
+        );
+        assert_eq!((instance.cursor.row, instance.cursor.column), (4, 3));
+    }
+}
@@ -0,0 +1,293 @@
+//! Commits which last changed the lines of buffers, from `git blame`.
+
+use std::{collections::HashMap, path::Path, process::Stdio};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::{concurrent::AsyncResult, lsp::client::LSPClientHandle, state::EditorState};
+
+use super::run_git;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlameLine {
+    pub hash: String,
+    pub author: String,
+    /// Seconds since the unix epoch
+    pub time: i64,
+    pub summary: String,
+}
+
+impl BlameLine {
+    /// Lines changed in the buffer or working tree are attributed to the zero hash
+    pub fn is_committed(&self) -> bool {
+        !self.hash.chars().all(|c| c == '0')
+    }
+
+    /// Short hash, author and date shown next to the line
+    pub fn annotation(&self) -> String {
+        if !self.is_committed() {
+            return "Not committed yet".to_string();
+        }
+        format!(
+            "{} {} {}",
+            &self.hash[..8.min(self.hash.len())],
+            self.author,
+            format_date(self.time)
+        )
+    }
+
+    pub fn details(&self) -> String {
+        if !self.is_committed() {
+            return "Not committed yet".to_string();
+        }
+        format!(
+            "commit {}\nAuthor: {}\nDate: {}\n\n    {}",
+            self.hash,
+            self.author,
+            format_date(self.time),
+            self.summary
+        )
+    }
+}
+
+/// Date of the timestamp in UTC as YYYY-MM-DD
+pub fn format_date(time: i64) -> String {
+    // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = time.div_euclid(86400) + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Lines of `git blame --porcelain` output in order
+pub fn parse_porcelain(output: &str) -> Vec<BlameLine> {
+    // Details of a commit are only given for its first line
+    let mut commits: HashMap<String, BlameLine> = HashMap::new();
+    let mut lines = vec![];
+    let mut hash = String::new();
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            lines.push(commits.get(&hash).cloned().unwrap_or_default());
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        if key.len() == 40 && key.chars().all(|c| c.is_ascii_hexdigit()) {
+            hash = key.to_string();
+            commits.entry(hash.clone()).or_insert_with(|| BlameLine {
+                hash: hash.clone(),
+                ..Default::default()
+            });
+            continue;
+        }
+        let Some(commit) = commits.get_mut(&hash) else {
+            continue;
+        };
+        match key {
+            "author" => commit.author = value.to_string(),
+            "author-time" => commit.time = value.parse().unwrap_or_default(),
+            "summary" => commit.summary = value.to_string(),
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Folder of the file and the arguments blaming the buffer content given on stdin
+fn blame_command(path: &str, row: Option<usize>) -> Result<(&Path, Vec<String>)> {
+    let file = Path::new(path);
+    let folder = file.parent().context("File without a folder")?;
+    let name = file
+        .file_name()
+        .context("File without a name")?
+        .to_string_lossy();
+    let mut args = vec!["blame".to_string(), "--porcelain".to_string()];
+    if let Some(row) = row {
+        args.extend(["-L".to_string(), format!("{},{}", row + 1, row + 1)]);
+    }
+    args.extend([
+        "--contents".to_string(),
+        "-".to_string(),
+        "--".to_string(),
+        name.to_string(),
+    ]);
+    Ok((folder, args))
+}
+
+/// Blame of a row of the buffer including unsaved changes
+pub fn blame_line(state: &EditorState, buffer_id: u32, row: usize) -> Result<BlameLine> {
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let path = buffer
+        .file_path
+        .as_deref()
+        .context("Buffer is not a file")?;
+    let (folder, args) = blame_command(path, Some(row))?;
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    let output = run_git(folder, &args, Some(&buffer.get_content("\n".into())))?;
+    parse_porcelain(&output)
+        .into_iter()
+        .next()
+        .context("No blame for the line")
+}
+
+#[derive(Serialize, Deserialize)]
+struct BufferBlame {
+    buffer_id: u32,
+    version: usize,
+    lines: Vec<BlameLine>,
+}
+
+/// Blame the whole buffer in the background once the git change markers are up to date
+pub fn refresh_blame(state: &mut EditorState, buffer_id: u32) {
+    if !state.git.blame_enabled || state.git.is_pending() {
+        return;
+    }
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let Some(path) = buffer.file_path.clone() else {
+        return;
+    };
+    let version = buffer.version;
+    // Files which are not tracked have no blame
+    if state.git.base(&path).is_none()
+        || state
+            .git
+            .blame
+            .get(&buffer_id)
+            .is_some_and(|(blame_version, _)| *blame_version == version)
+        || state.git.blame_loading.contains(&buffer_id)
+    {
+        return;
+    }
+    let content = buffer.get_content("\n".into());
+    state.git.blame_loading.insert(buffer_id);
+    let Ok((folder, args)) = blame_command(&path, None) else {
+        return;
+    };
+    let folder = folder.to_path_buf();
+    let sender = state.async_handle.sender.clone();
+
+    state.rt.spawn(async move {
+        let mut command = tokio::process::Command::new("git");
+
+        #[cfg(target_os = "windows")]
+        {
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let child = command
+            .args(&args)
+            .current_dir(&folder)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let lines = match child {
+            Ok(mut child) => {
+                // Git reads all of the content before writing the blame
+                let mut stdin = child.stdin.take().unwrap();
+                if let Err(err) = stdin.write_all(content.as_bytes()).await {
+                    tracing::warn!("Failed to write to git blame: {}", err);
+                }
+                drop(stdin);
+                match child.wait_with_output().await {
+                    Ok(output) => parse_porcelain(&String::from_utf8_lossy(&output.stdout)),
+                    Err(err) => {
+                        tracing::warn!("Failed to run git blame: {}", err);
+                        vec![]
+                    }
+                }
+            }
+            Err(err) => {
+                tracing::warn!("Failed to run git blame: {}", err);
+                vec![]
+            }
+        };
+        let blame = BufferBlame {
+            buffer_id,
+            version,
+            lines,
+        };
+        let result = AsyncResult {
+            result: serde_json::to_string(&blame).unwrap(),
+            callback: apply_blame,
+        };
+        if let Err(err) = sender.send(result).await {
+            tracing::error!("Failed to send git blame: {}", err);
+        }
+    });
+}
+
+fn apply_blame(
+    result: String,
+    state: &mut EditorState,
+    _lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    let blame: BufferBlame = match serde_json::from_str(&result) {
+        Ok(blame) => blame,
+        Err(err) => {
+            tracing::error!("Malformed git blame: {}", err);
+            return;
+        }
+    };
+    state.git.blame_loading.remove(&blame.buffer_id);
+    state
+        .git
+        .blame
+        .insert(blame.buffer_id, (blame.version, blame.lines));
+    state.update_view = true;
+}
+
+/// Blame annotation of each visible line of the current buffer, on the first line of wrapped rows
+pub fn inline_blame(state: &EditorState) -> Vec<Option<String>> {
+    let Some(buffer_id) = state.buffer_idx else {
+        return vec![];
+    };
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let lines = match state.git.blame.get(&buffer_id) {
+        Some((version, lines)) if state.git.blame_enabled && *version == buffer.version => lines,
+        _ => return vec![],
+    };
+    state
+        .gutter_info
+        .iter()
+        .map(|line| {
+            if line.wrapped {
+                return None;
+            }
+            lines.get(line.start.row).map(BlameLine::annotation)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_date, parse_porcelain};
+
+    #[test]
+    fn porcelain_blame() {
+        let hash = "a".repeat(40);
+        let zero = "0".repeat(40);
+        let output = format!(
+            "{hash} 1 1 2\nauthor Ada\nauthor-time 1700000000\nsummary Add main\n\tfn main() {{\n\
+             {hash} 2 2\n\t}}\n\
+             {zero} 3 3 1\nauthor Not Committed Yet\nauthor-time 1800000000\nsummary Version of a.rs from a.rs\n\t// todo\n"
+        );
+        let lines = parse_porcelain(&output);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].author, "Ada");
+        assert_eq!(lines[1].annotation(), "aaaaaaaa Ada 2023-11-14");
+        assert!(!lines[2].is_committed());
+        assert_eq!(lines[2].annotation(), "Not committed yet");
+
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951782400), "2000-02-29");
+    }
+}
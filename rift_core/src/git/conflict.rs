@@ -0,0 +1,173 @@
+//! Merge conflicts left in files as `<<<<<<<`, `=======` and `>>>>>>>` markers.
+
+/// Side of a merge conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ConflictSide {
+    Ours,
+    Theirs,
+}
+
+/// Rows of the markers of a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    /// Row of the `<<<<<<<` marker
+    pub start: usize,
+    /// Row of the `|||||||` marker of the common ancestor in diff3 style conflicts
+    pub base: Option<usize>,
+    /// Row of the `=======` marker
+    pub separator: usize,
+    /// Row of the `>>>>>>>` marker
+    pub end: usize,
+}
+
+impl Conflict {
+    /// Rows of the lines of our side
+    pub fn ours(&self) -> std::ops::Range<usize> {
+        self.start + 1..self.base.unwrap_or(self.separator)
+    }
+
+    /// Rows of the lines of their side
+    pub fn theirs(&self) -> std::ops::Range<usize> {
+        self.separator + 1..self.end
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        (self.start..=self.end).contains(&row)
+    }
+
+    /// Side of the conflict the row is highlighted as, including the markers around it
+    pub fn side(&self, row: usize) -> Option<ConflictSide> {
+        if self.start <= row && row < self.base.unwrap_or(self.separator) {
+            Some(ConflictSide::Ours)
+        } else if self.separator < row && row <= self.end {
+            Some(ConflictSide::Theirs)
+        } else {
+            None
+        }
+    }
+}
+
+fn is_marker(line: &str, marker: char) -> bool {
+    let mut chars = line.chars();
+    (0..7).all(|_| chars.next() == Some(marker)) && matches!(chars.next(), None | Some(' '))
+}
+
+/// Complete conflicts in the lines in order, unterminated markers are ignored
+pub fn find_conflicts<S: AsRef<str>>(lines: &[S]) -> Vec<Conflict> {
+    let mut conflicts = vec![];
+    let mut start = None;
+    let mut base = None;
+    let mut separator = None;
+    for (row, line) in lines.iter().enumerate() {
+        let line = line.as_ref();
+        if is_marker(line, '<') {
+            start = Some(row);
+            base = None;
+            separator = None;
+        } else if start.is_some() && separator.is_none() && is_marker(line, '|') {
+            base = Some(row);
+        } else if start.is_some() && is_marker(line, '=') {
+            separator = Some(row);
+        } else if is_marker(line, '>') {
+            if let (Some(start), Some(separator)) = (start, separator) {
+                conflicts.push(Conflict {
+                    start,
+                    base,
+                    separator,
+                    end: row,
+                });
+            }
+            start = None;
+            separator = None;
+        }
+    }
+    conflicts
+}
+
+/// Row of the next conflict after the row or the previous one before it, wrapping around
+pub fn next_conflict(conflicts: &[Conflict], row: usize, forward: bool) -> Option<usize> {
+    if forward {
+        conflicts
+            .iter()
+            .find(|conflict| conflict.start > row)
+            .or(conflicts.first())
+            .map(|conflict| conflict.start)
+    } else {
+        conflicts
+            .iter()
+            .rev()
+            .find(|conflict| conflict.start < row)
+            .or(conflicts.last())
+            .map(|conflict| conflict.start)
+    }
+}
+
+/// Lines replacing the conflict when keeping one or both sides
+pub fn resolve<'a>(
+    lines: &'a [String],
+    conflict: &Conflict,
+    sides: &[ConflictSide],
+) -> Vec<&'a str> {
+    sides
+        .iter()
+        .flat_map(|side| match side {
+            ConflictSide::Ours => conflict.ours(),
+            ConflictSide::Theirs => conflict.theirs(),
+        })
+        .map(|row| lines[row].as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_conflicts, next_conflict, resolve, ConflictSide};
+
+    #[test]
+    fn conflicts() {
+        let lines: Vec<String> = [
+            "a",
+            "<<<<<<< HEAD",
+            "ours",
+            "||||||| base",
+            "old",
+            "=======",
+            "theirs",
+            ">>>>>>> feature",
+            "b",
+            "<<<<<<< HEAD",
+            "=======",
+            "other",
+            ">>>>>>> feature",
+            "======= not a marker",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        let conflicts = find_conflicts(&lines);
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].base, Some(3));
+        assert_eq!(conflicts[0].ours(), 2..3);
+        assert_eq!(conflicts[0].theirs(), 6..7);
+        assert_eq!(conflicts[0].side(1), Some(ConflictSide::Ours));
+        assert_eq!(conflicts[0].side(4), None);
+        assert_eq!(conflicts[0].side(7), Some(ConflictSide::Theirs));
+        assert_eq!(conflicts[1].ours(), 10..10);
+
+        assert_eq!(
+            resolve(
+                &lines,
+                &conflicts[0],
+                &[ConflictSide::Ours, ConflictSide::Theirs]
+            ),
+            vec!["ours", "theirs"]
+        );
+        assert_eq!(
+            resolve(&lines, &conflicts[1], &[ConflictSide::Ours]),
+            Vec::<&str>::new()
+        );
+
+        assert_eq!(next_conflict(&conflicts, 3, true), Some(9));
+        assert_eq!(next_conflict(&conflicts, 9, true), Some(1));
+        assert_eq!(next_conflict(&conflicts, 1, false), Some(9));
+    }
+}
@@ -0,0 +1,459 @@
+//! Changes of buffers compared to the content of their file at the git HEAD,
+//! shown as markers in the gutter.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    buffer::line_buffer::LineBuffer,
+    concurrent::AsyncResult,
+    diff::{diff, DiffOp},
+    lsp::client::LSPClientHandle,
+    state::EditorState,
+};
+
+pub mod blame;
+pub mod conflict;
+pub mod status;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// Block of changed lines, rows are exclusive at the end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub kind: HunkKind,
+    /// Rows of the buffer, empty for removed lines
+    pub start: usize,
+    pub end: usize,
+    /// Rows of the file at HEAD, empty for added lines
+    pub old_start: usize,
+    pub old_end: usize,
+}
+
+impl Hunk {
+    pub fn marker(&self) -> char {
+        match self.kind {
+            HunkKind::Added => '+',
+            HunkKind::Modified => '~',
+            HunkKind::Removed => '-',
+        }
+    }
+
+    /// Whether the marker of the hunk is shown on the row
+    pub fn contains(&self, row: usize) -> bool {
+        match self.kind {
+            // Removed lines are marked on the line following them
+            HunkKind::Removed => row == self.start,
+            _ => (self.start..self.end).contains(&row),
+        }
+    }
+}
+
+/// Changed blocks between the lines at HEAD and the lines of the buffer
+pub fn line_hunks(old: &[String], new: &[String]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = vec![];
+    let (mut old_row, mut new_row) = (0, 0);
+    for op in diff(old, new) {
+        let (old_end, new_end) = match op {
+            DiffOp::Equal(x, y) => {
+                old_row = x + 1;
+                new_row = y + 1;
+                continue;
+            }
+            DiffOp::Delete(x) => (x + 1, new_row),
+            DiffOp::Insert(y) => (old_row, y + 1),
+        };
+        match hunks.last_mut() {
+            Some(hunk) if hunk.old_end == old_row && hunk.end == new_row => {
+                hunk.old_end = old_end;
+                hunk.end = new_end;
+            }
+            _ => hunks.push(Hunk {
+                kind: HunkKind::Modified,
+                start: new_row,
+                end: new_end,
+                old_start: old_row,
+                old_end,
+            }),
+        }
+        old_row = old_end;
+        new_row = new_end;
+    }
+    for hunk in &mut hunks {
+        hunk.kind = if hunk.old_start == hunk.old_end {
+            HunkKind::Added
+        } else if hunk.start == hunk.end {
+            HunkKind::Removed
+        } else {
+            HunkKind::Modified
+        };
+    }
+    hunks
+}
+
+/// Lines of files at HEAD and the hunks of buffers compared to them
+#[derive(Debug, Default)]
+pub struct GitChanges {
+    /// Lines at HEAD keyed by path, None for files which are not tracked
+    bases: HashMap<String, Option<Vec<String>>>,
+    /// Paths whose content at HEAD is being read
+    loading: HashSet<String>,
+    /// Hunks of each buffer along with the version they were computed for
+    hunks: HashMap<u32, (usize, Vec<Hunk>)>,
+    /// Buffer version waiting for the debounce and when it was first seen
+    pending: Option<(u32, usize, Instant)>,
+    /// Show the commit of each line next to it
+    pub blame_enabled: bool,
+    /// Blame of each buffer along with the version it was computed for
+    blame: HashMap<u32, (usize, Vec<blame::BlameLine>)>,
+    /// Buffers being blamed
+    blame_loading: HashSet<u32>,
+}
+
+impl GitChanges {
+    pub fn hunks(&self, buffer_id: u32) -> &[Hunk] {
+        self.hunks
+            .get(&buffer_id)
+            .map(|(_, hunks)| hunks.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Lines of the file at HEAD if known
+    pub fn base(&self, path: &str) -> Option<&[String]> {
+        self.bases.get(path)?.as_deref()
+    }
+
+    /// Whether hunks are waiting for the debounce to be recomputed
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BaseContent {
+    path: String,
+    content: Option<String>,
+}
+
+/// Read the content of the file at HEAD in the background
+pub fn load_base(state: &mut EditorState, path: &str) {
+    if !state.git.loading.insert(path.to_string()) {
+        return;
+    }
+    let file = Path::new(path);
+    let (Some(folder), Some(name)) = (file.parent(), file.file_name()) else {
+        return;
+    };
+    let folder = folder.to_path_buf();
+    let spec = format!("HEAD:./{}", name.to_string_lossy());
+    let path = path.to_string();
+    let sender = state.async_handle.sender.clone();
+
+    state.rt.spawn(async move {
+        let mut command = tokio::process::Command::new("git");
+
+        #[cfg(target_os = "windows")]
+        {
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let output = command
+            .args(["show", &spec])
+            .current_dir(&folder)
+            .stdin(Stdio::null())
+            .output()
+            .await;
+        // Files outside of a repository or not yet committed have no base
+        let content = match output {
+            Ok(output) if output.status.success() => String::from_utf8(output.stdout).ok(),
+            Ok(_) => None,
+            Err(err) => {
+                tracing::warn!("Failed to run git: {}", err);
+                None
+            }
+        };
+        let result = AsyncResult {
+            result: serde_json::to_string(&BaseContent { path, content }).unwrap(),
+            callback: apply_base,
+        };
+        if let Err(err) = sender.send(result).await {
+            tracing::error!("Failed to send git base: {}", err);
+        }
+    });
+}
+
+fn apply_base(
+    result: String,
+    state: &mut EditorState,
+    _lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    let base: BaseContent = match serde_json::from_str(&result) {
+        Ok(base) => base,
+        Err(err) => {
+            tracing::error!("Malformed git base: {}", err);
+            return;
+        }
+    };
+    state.git.loading.remove(&base.path);
+    let lines = base
+        .content
+        .map(|content| LineBuffer::new(content, None).lines);
+    // Hunks of buffers of the file are recomputed against the new base
+    for (id, buffer) in &state.buffers {
+        if buffer.file_path.as_deref() == Some(base.path.as_str()) {
+            let hunks = lines
+                .as_ref()
+                .map(|lines| line_hunks(lines, &buffer.lines))
+                .unwrap_or_default();
+            state.git.hunks.insert(*id, (buffer.version, hunks));
+        }
+    }
+    state.git.bases.insert(base.path, lines);
+    state.update_view = true;
+}
+
+/// Read the content at HEAD of the files of all buffers again, after HEAD moved
+pub fn reload_bases(state: &mut EditorState) {
+    let paths: Vec<String> = state
+        .buffers
+        .values()
+        .filter_map(|buffer| buffer.file_path.clone())
+        .collect();
+    for path in paths {
+        load_base(state, &path);
+    }
+}
+
+/// Recompute the hunks of the buffer once it has not changed for the debounce duration
+pub fn refresh_hunks(state: &mut EditorState, buffer_id: u32) {
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let Some(path) = buffer.file_path.clone() else {
+        return;
+    };
+    if state.encrypted_files.contains_key(&path) {
+        return;
+    }
+    let version = buffer.version;
+    if !state.git.bases.contains_key(&path) {
+        load_base(state, &path);
+        return;
+    }
+    if state
+        .git
+        .hunks
+        .get(&buffer_id)
+        .is_some_and(|(hunks_version, _)| *hunks_version == version)
+    {
+        return;
+    }
+    let debounce = Duration::from_millis(state.preferences.git_diff_debounce);
+    match state.git.pending {
+        Some((id, pending_version, changed)) if id == buffer_id && pending_version == version => {
+            if changed.elapsed() < debounce {
+                return;
+            }
+        }
+        _ => {
+            state.git.pending = Some((buffer_id, version, Instant::now()));
+            return;
+        }
+    }
+    state.git.pending = None;
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let hunks = state
+        .git
+        .base(&path)
+        .map(|base| line_hunks(base, &buffer.lines))
+        .unwrap_or_default();
+    state.git.hunks.insert(buffer_id, (version, hunks));
+}
+
+/// Hunks of the buffer compared to HEAD without waiting for the debounce,
+/// None if the content at HEAD is not known
+pub fn current_hunks(state: &EditorState, buffer_id: u32) -> Option<Vec<Hunk>> {
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let base = state.git.base(buffer.file_path.as_deref()?)?;
+    Some(line_hunks(base, &buffer.lines))
+}
+
+/// Row of the next hunk after the row or the previous one before it, wrapping around
+pub fn next_hunk(hunks: &[Hunk], row: usize, forward: bool) -> Option<usize> {
+    if forward {
+        hunks
+            .iter()
+            .find(|hunk| hunk.start > row)
+            .or(hunks.first())
+            .map(|hunk| hunk.start)
+    } else {
+        hunks
+            .iter()
+            .rev()
+            .find(|hunk| hunk.start < row)
+            .or(hunks.last())
+            .map(|hunk| hunk.start)
+    }
+}
+
+/// Patch without context lines applying the hunk to the file at the path
+fn hunk_patch(path: &str, base: &[String], lines: &[String], hunk: &Hunk) -> String {
+    // Empty ranges start at the line before them
+    let range = |start: usize, end: usize| {
+        let count = end - start;
+        format!("{},{}", if count == 0 { start } else { start + 1 }, count)
+    };
+    let mut patch = format!(
+        "--- a/{}\n+++ b/{}\n@@ -{} +{} @@\n",
+        path,
+        path,
+        range(hunk.old_start, hunk.old_end),
+        range(hunk.start, hunk.end)
+    );
+    for line in &base[hunk.old_start..hunk.old_end] {
+        patch.push_str(&format!("-{}\n", line));
+    }
+    for line in &lines[hunk.start..hunk.end] {
+        patch.push_str(&format!("+{}\n", line));
+    }
+    patch
+}
+
+/// Run git in the folder returning its output, the input is written to its stdin
+pub fn run_git(folder: &Path, args: &[&str], input: Option<&str>) -> Result<String> {
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(folder)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Written from another thread so that large outputs do not fill the pipe
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.unwrap_or_default().to_string();
+        std::thread::spawn(move || stdin.write_all(input.as_bytes()))
+    });
+    let output = child.wait_with_output()?;
+    if let Some(Ok(Err(err))) = writer.map(|writer| writer.join()) {
+        tracing::warn!("Failed to write to git: {}", err);
+    }
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "git {} {}: {}",
+            args.first().unwrap_or(&""),
+            output.status,
+            stderr.lines().next().unwrap_or_default()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Root of the repository containing the file and the path of the file relative to it
+pub fn repository_path(path: &str) -> Result<(PathBuf, String)> {
+    let file = std::fs::canonicalize(path)?;
+    let folder = file.parent().context("File without a folder")?;
+    let root = run_git(folder, &["rev-parse", "--show-toplevel"], None)
+        .with_context(|| format!("{} is not in a git repository", path))?;
+    let root = std::fs::canonicalize(root.trim_end())?;
+    let relative = file
+        .strip_prefix(&root)?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Ok((root, relative))
+}
+
+/// Add the change of the hunk to the index of the repository containing the file
+pub fn stage_hunk(path: &str, base: &[String], lines: &[String], hunk: &Hunk) -> Result<()> {
+    let (root, relative) = repository_path(path)?;
+    run_git(
+        &root,
+        &["apply", "--cached", "--unidiff-zero", "-"],
+        Some(&hunk_patch(&relative, base, lines, hunk)),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hunk_patch, line_hunks, next_hunk, HunkKind};
+
+    #[test]
+    fn hunks() {
+        let lines = |text: &str| {
+            text.lines()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+        };
+        let old = lines("a\nb\nc\nd\ne");
+        let new = lines("a\nx\nc\nd\nnew\ne");
+        let hunks = line_hunks(&old, &new);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].kind, HunkKind::Modified);
+        assert_eq!((hunks[0].start, hunks[0].end), (1, 2));
+        assert_eq!((hunks[0].old_start, hunks[0].old_end), (1, 2));
+        assert_eq!(hunks[1].kind, HunkKind::Added);
+        assert_eq!((hunks[1].start, hunks[1].end), (4, 5));
+        assert!(hunks[1].contains(4));
+
+        let hunks = line_hunks(&old, &lines("a\nd\ne"));
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].kind, HunkKind::Removed);
+        assert_eq!((hunks[0].old_start, hunks[0].old_end), (1, 3));
+        assert!(hunks[0].contains(1));
+        assert!(!hunks[0].contains(2));
+
+        assert!(line_hunks(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn hunk_navigation_and_patches() {
+        let lines = |text: &str| {
+            text.lines()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+        };
+        let old = lines("a\nb\nc\nd");
+        let new = lines("a\nx\nc\nd\ne");
+        let hunks = line_hunks(&old, &new);
+        assert_eq!(next_hunk(&hunks, 0, true), Some(1));
+        assert_eq!(next_hunk(&hunks, 1, true), Some(4));
+        assert_eq!(next_hunk(&hunks, 4, true), Some(1));
+        assert_eq!(next_hunk(&hunks, 1, false), Some(4));
+        assert_eq!(next_hunk(&[], 1, false), None);
+
+        assert_eq!(
+            hunk_patch("src/a.rs", &old, &new, &hunks[0]),
+            "--- a/src/a.rs\n+++ b/src/a.rs\n@@ -2,1 +2,1 @@\n-b\n+x\n"
+        );
+        assert_eq!(
+            hunk_patch("a", &old, &new, &hunks[1]),
+            "--- a/a\n+++ b/a\n@@ -4,0 +5,1 @@\n+e\n"
+        );
+        let removed = line_hunks(&old, &lines("a\nd"));
+        assert_eq!(
+            hunk_patch("a", &old, &lines("a\nd"), &removed[0]),
+            "--- a/a\n+++ b/a\n@@ -2,2 +1,0 @@\n-b\n-c\n"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Staged, unstaged and untracked files of a repository and committing the staged ones.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::run_git;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StatusSection {
+    Staged,
+    Unstaged,
+    Untracked,
+}
+
+impl StatusSection {
+    pub fn title(&self) -> &'static str {
+        match self {
+            StatusSection::Staged => "Staged",
+            StatusSection::Unstaged => "Changes",
+            StatusSection::Untracked => "Untracked",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry {
+    pub section: StatusSection,
+    /// Status letter of `git status --porcelain`
+    pub status: char,
+    /// Path relative to the root of the repository
+    pub path: String,
+}
+
+/// Entries of `git status --porcelain` output, a file with staged and unstaged
+/// changes is listed in both sections
+pub fn parse_status(output: &str) -> Vec<StatusEntry> {
+    let mut entries = vec![];
+    for line in output.lines() {
+        let mut chars = line.chars();
+        let (Some(index), Some(worktree)) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        let Some(path) = line.get(3..) else {
+            continue;
+        };
+        // Renamed files are given as `old -> new`
+        let path = path.rsplit(" -> ").next().unwrap_or(path).to_string();
+        if index == '?' {
+            entries.push(StatusEntry {
+                section: StatusSection::Untracked,
+                status: '?',
+                path,
+            });
+            continue;
+        }
+        if index != ' ' {
+            entries.push(StatusEntry {
+                section: StatusSection::Staged,
+                status: index,
+                path: path.clone(),
+            });
+        }
+        if worktree != ' ' {
+            entries.push(StatusEntry {
+                section: StatusSection::Unstaged,
+                status: worktree,
+                path,
+            });
+        }
+    }
+    entries.sort_by_key(|entry| entry.section);
+    entries
+}
+
+/// Files of the repository listed by section
+#[derive(Debug)]
+pub struct GitStatus {
+    pub root: PathBuf,
+    pub entries: Vec<StatusEntry>,
+    /// Index of the selected entry
+    pub idx: usize,
+}
+
+impl GitStatus {
+    /// Status of the repository containing the folder
+    pub fn load(folder: &Path) -> Result<Self> {
+        let root = run_git(folder, &["rev-parse", "--show-toplevel"], None)?;
+        let root = PathBuf::from(root.trim_end());
+        let mut status = Self {
+            root,
+            entries: vec![],
+            idx: 0,
+        };
+        status.refresh()?;
+        Ok(status)
+    }
+
+    pub fn refresh(&mut self) -> Result<()> {
+        let output = run_git(
+            &self.root,
+            &["status", "--porcelain", "--untracked-files=all"],
+            None,
+        )?;
+        self.entries = parse_status(&output);
+        self.idx = self.idx.min(self.entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    pub fn selected(&self) -> Option<&StatusEntry> {
+        self.entries.get(self.idx)
+    }
+
+    pub fn label(&self, idx: usize) -> String {
+        let entry = &self.entries[idx];
+        format!(
+            "{:<9} {} {}",
+            entry.section.title(),
+            entry.status,
+            entry.path
+        )
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.entries.is_empty() {
+            self.idx = (self.idx + 1) % self.entries.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.entries.is_empty() {
+            self.idx = (self.idx + self.entries.len() - 1) % self.entries.len();
+        }
+    }
+
+    /// Unstage the selected file if it is staged and stage it otherwise
+    pub fn toggle_stage(&mut self) -> Result<()> {
+        let Some(entry) = self.selected() else {
+            return Ok(());
+        };
+        let path = entry.path.clone();
+        if entry.section == StatusSection::Staged {
+            run_git(&self.root, &["restore", "--staged", "--", &path], None)?;
+        } else {
+            run_git(&self.root, &["add", "--", &path], None)?;
+        }
+        self.refresh()
+    }
+
+    /// Message template listing the staged files, lines starting with `#` are removed
+    pub fn commit_template(&self) -> String {
+        let mut template = String::from(
+            "\n# Enter the commit message and save to commit, lines starting\n\
+             # with '#' are ignored and an empty message aborts the commit.\n#\n",
+        );
+        let staged: Vec<&StatusEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.section == StatusSection::Staged)
+            .collect();
+        if staged.is_empty() {
+            template.push_str("# No changes staged for commit\n");
+        } else {
+            template.push_str("# Changes to be committed:\n");
+            for entry in staged {
+                template.push_str(&format!("#\t{} {}\n", entry.status, entry.path));
+            }
+        }
+        template
+    }
+}
+
+/// Commit the staged changes with the message in the file
+pub fn commit(root: &Path, message_path: &str) -> Result<String> {
+    run_git(
+        root,
+        &["commit", "--cleanup=strip", "-F", message_path],
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_status, StatusSection};
+
+    #[test]
+    fn status_entries() {
+        let entries = parse_status("MM src/a.rs\n?? new.rs\n M b.rs\nR  old.rs -> c.rs\n");
+        let sections: Vec<(StatusSection, char, &str)> = entries
+            .iter()
+            .map(|entry| (entry.section, entry.status, entry.path.as_str()))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                (StatusSection::Staged, 'M', "src/a.rs"),
+                (StatusSection::Staged, 'R', "c.rs"),
+                (StatusSection::Unstaged, 'M', "src/a.rs"),
+                (StatusSection::Unstaged, 'M', "b.rs"),
+                (StatusSection::Untracked, '?', "new.rs"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Indentation of new lines from the tree-sitter indent queries of the language,
+//! or from the brackets around the cursor for languages without one.
+
+use tree_sitter::{Parser, Query, QueryCursor};
+
+use crate::buffer::instance::{Cursor, Language};
+
+/// Nodes indenting the lines between their first and last line
+const RUST_INDENTS: &str = r#"
+[
+  (block)
+  (declaration_list)
+  (field_declaration_list)
+  (enum_variant_list)
+  (match_block)
+  (use_list)
+  (field_initializer_list)
+  (arguments)
+  (parameters)
+  (array_expression)
+  (tuple_expression)
+  (token_tree)
+] @indent
+"#;
+
+/// Compound statements also indent the line after their last line, `@extend`
+const PYTHON_INDENTS: &str = r#"
+[
+  (argument_list)
+  (parameters)
+  (list)
+  (dictionary)
+  (set)
+  (tuple)
+] @indent
+[
+  (function_definition)
+  (class_definition)
+  (if_statement)
+  (elif_clause)
+  (else_clause)
+  (for_statement)
+  (while_statement)
+  (with_statement)
+  (try_statement)
+  (except_clause)
+  (finally_clause)
+] @extend
+"#;
+
+/// Indentation of a line inserted at the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewLineIndent {
+    pub indent: String,
+    /// Indentation of the closing bracket after the cursor, which gets a line of its own
+    /// when the cursor is between a pair of brackets
+    pub closing: Option<String>,
+}
+
+/// Levels of indentation from the indent query at the byte, None for languages without one
+/// or source which does not parse. Nodes starting on the same line indent once.
+fn query_levels(language: Language, source: &str, byte: usize) -> Option<usize> {
+    let (ts_language, indents) = match language {
+        Language::Rust => (tree_sitter_rust::LANGUAGE.into(), RUST_INDENTS),
+        Language::Python => (tree_sitter_python::LANGUAGE.into(), PYTHON_INDENTS),
+        _ => return None,
+    };
+    let query = Query::new(&ts_language, indents).ok()?;
+    let mut parser = Parser::new();
+    parser.set_language(&ts_language).ok()?;
+    let tree = parser.parse(source, None)?;
+    if tree.root_node().has_error() {
+        return None;
+    }
+    let extend = query.capture_index_for_name("extend");
+
+    let mut rows = vec![];
+    let mut cursor = QueryCursor::new();
+    for (query_match, idx) in cursor.captures(&query, tree.root_node(), source.as_bytes()) {
+        let capture = query_match.captures[idx];
+        let node = capture.node;
+        let inside = if Some(capture.index) == extend {
+            node.start_byte() < byte && byte <= node.end_byte()
+        } else {
+            node.start_byte() < byte && byte < node.end_byte()
+        };
+        if inside {
+            rows.push(node.start_position().row);
+        }
+    }
+    rows.sort_unstable();
+    rows.dedup();
+    Some(rows.len())
+}
+
+/// Whether the text before the cursor opens a block, which the next line is indented in
+fn opens_block(language: Language, before: &str) -> bool {
+    let before = before.trim_end();
+    before.ends_with(['(', '[', '{']) || (language == Language::Python && before.ends_with(':'))
+}
+
+/// Indentation of a line inserted at the cursor, one level deeper than the line of the cursor
+/// inside blocks and the level of the enclosing block otherwise
+pub fn new_line_indent(
+    language: Language,
+    lines: &[String],
+    cursor: &Cursor,
+    unit: &str,
+) -> NewLineIndent {
+    let line = &lines[cursor.row];
+    let base: String = line
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+    let (before, after) = line.split_at(cursor.column.min(line.len()));
+
+    let byte = lines[..cursor.row]
+        .iter()
+        .map(|line| line.len() + 1)
+        .sum::<usize>()
+        + before.len();
+    let source = lines.join("\n");
+    let mut indent = query_levels(language, &source, byte)
+        .map(|levels| unit.repeat(levels))
+        .unwrap_or_else(|| base.clone());
+
+    // Blocks which do not parse yet, such as one just opened, indent from the brackets
+    let opens = opens_block(language, before);
+    if opens && indent.len() <= base.len() {
+        indent = format!("{}{}", base, unit);
+    }
+    let closing = (opens && after.trim_start().starts_with([')', ']', '}'])).then_some(base);
+    NewLineIndent { indent, closing }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{new_line_indent, NewLineIndent};
+    use crate::buffer::instance::{Cursor, Language};
+
+    fn indent(language: Language, text: &str, row: usize, column: usize) -> NewLineIndent {
+        let lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+        new_line_indent(language, &lines, &Cursor { row, column }, "    ")
+    }
+
+    #[test]
+    fn indent_from_queries_and_brackets() {
+        let rust = "fn main() {\n    let a = [\n        1,\n    ];\n}";
+        assert_eq!(indent(Language::Rust, rust, 0, 11).indent, "    ");
+        assert_eq!(indent(Language::Rust, rust, 1, 13).indent, "        ");
+        assert_eq!(indent(Language::Rust, rust, 2, 10).indent, "        ");
+        assert_eq!(indent(Language::Rust, rust, 3, 6).indent, "    ");
+        assert_eq!(indent(Language::Rust, rust, 4, 1).indent, "");
+
+        // Splitting a pair of brackets puts the closing one on its own line
+        let split = indent(Language::Rust, "    foo(bar, {})", 0, 14);
+        assert_eq!(split.indent, "        ");
+        assert_eq!(split.closing, Some("    ".to_string()));
+        let split = indent(Language::Rust, "fn main() {}", 0, 11);
+        assert_eq!(split.indent, "    ");
+        assert_eq!(split.closing, Some(String::new()));
+
+        let python = "class A:\n    def f(self):\n        return 1\n";
+        assert_eq!(indent(Language::Python, python, 0, 8).indent, "    ");
+        assert_eq!(indent(Language::Python, python, 1, 16).indent, "        ");
+        assert_eq!(indent(Language::Python, python, 2, 16).indent, "        ");
+        assert_eq!(indent(Language::Python, "if a:", 0, 5).indent, "    ");
+
+        let plain = indent(Language::PlainText, "  text {", 0, 8);
+        assert_eq!(plain.indent, "      ");
+        assert_eq!(plain.closing, None);
+    }
+}
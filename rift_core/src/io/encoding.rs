@@ -0,0 +1,186 @@
+//! Detecting the text encoding of files and converting buffer content back to it on save.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 5] = [
+        Encoding::Utf8,
+        Encoding::Utf8Bom,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Latin1,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 BOM",
+            Encoding::Utf16Le => "UTF-16 LE",
+            Encoding::Utf16Be => "UTF-16 BE",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// Encoding with the label, ignoring case, spaces and dashes
+    pub fn parse(label: &str) -> Result<Self> {
+        let normalize = |label: &str| {
+            label
+                .chars()
+                .filter(|c| !matches!(c, ' ' | '-' | '_'))
+                .collect::<String>()
+                .to_lowercase()
+        };
+        let normalized = normalize(label);
+        match Self::ALL
+            .into_iter()
+            .find(|encoding| normalize(encoding.label()) == normalized)
+        {
+            Some(encoding) => Ok(encoding),
+            None if normalized == "iso88591" => Ok(Encoding::Latin1),
+            None => bail!("Unknown encoding: {}", label),
+        }
+    }
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Guess the encoding of UTF-16 without a byte order mark from the zero
+/// high bytes of ASCII characters
+fn guess_utf16(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let zeros = |offset: usize| {
+        bytes
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros(0), zeros(1));
+    let half = bytes.len() / 2;
+    if odd * 2 > half && even == 0 {
+        Some(Encoding::Utf16Le)
+    } else if even * 2 > half && odd == 0 {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Text of the file content and the encoding it was detected to be in,
+/// content which is not valid UTF-8 or UTF-16 is read as Latin-1
+pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return (
+            String::from_utf8_lossy(rest).into_owned(),
+            Encoding::Utf8Bom,
+        );
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        return (decode_utf16(rest, u16::from_le_bytes), Encoding::Utf16Le);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+        return (decode_utf16(rest, u16::from_be_bytes), Encoding::Utf16Be);
+    }
+    match guess_utf16(bytes) {
+        Some(Encoding::Utf16Le) => {
+            return (decode_utf16(bytes, u16::from_le_bytes), Encoding::Utf16Le)
+        }
+        Some(Encoding::Utf16Be) => {
+            return (decode_utf16(bytes, u16::from_be_bytes), Encoding::Utf16Be)
+        }
+        _ => {}
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), Encoding::Utf8),
+        Err(_) => (
+            bytes.iter().map(|byte| *byte as char).collect(),
+            Encoding::Latin1,
+        ),
+    }
+}
+
+/// Bytes of the text in the encoding, UTF-16 is written with a byte order mark
+pub fn encode(text: &str, encoding: Encoding) -> Result<Vec<u8>> {
+    Ok(match encoding {
+        Encoding::Utf8 => text.as_bytes().to_vec(),
+        Encoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+        Encoding::Utf16Le => UTF16LE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect(),
+        Encoding::Utf16Be => UTF16BE_BOM
+            .iter()
+            .copied()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect(),
+        Encoding::Latin1 => {
+            let mut bytes = Vec::with_capacity(text.len());
+            for c in text.chars() {
+                match u8::try_from(c) {
+                    Ok(byte) => bytes.push(byte),
+                    Err(_) => bail!("'{}' cannot be encoded as Latin-1", c),
+                }
+            }
+            bytes
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, Encoding};
+
+    #[test]
+    fn detect_and_round_trip() {
+        assert_eq!(decode("héllo".as_bytes()), ("héllo".into(), Encoding::Utf8));
+        assert_eq!(decode(&[0x68, 0xE9]), ("hé".into(), Encoding::Latin1));
+        assert_eq!(
+            decode(&[0xEF, 0xBB, 0xBF, 0x61]),
+            ("a".into(), Encoding::Utf8Bom)
+        );
+        assert_eq!(
+            decode(&[0x61, 0x00, 0x62, 0x00]),
+            ("ab".into(), Encoding::Utf16Le)
+        );
+        assert_eq!(
+            decode(&[0x00, 0x61, 0x00, 0x62]),
+            ("ab".into(), Encoding::Utf16Be)
+        );
+
+        for encoding in Encoding::ALL {
+            let bytes = encode("héllo\n", encoding).unwrap();
+            assert_eq!(decode(&bytes), ("héllo\n".into(), encoding));
+        }
+        assert!(encode("€", Encoding::Latin1).is_err());
+
+        assert_eq!(Encoding::parse("utf-16le").unwrap(), Encoding::Utf16Le);
+        assert_eq!(Encoding::parse("ISO-8859-1").unwrap(), Encoding::Latin1);
+        assert!(Encoding::parse("ebcdic").is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Transparent editing of `.age` and `.gpg` files through the age and gpg tools.
+//! Decrypted content is only kept in memory and re-encrypted before saving.
+
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+
+const AGE_ARMOR_HEADER: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+const PGP_ARMOR_HEADER: &[u8] = b"-----BEGIN PGP MESSAGE-----";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionKind {
+    /// Decrypted with an identity file
+    Age,
+    /// Decrypted with a passphrase (or the key cached by gpg-agent)
+    Gpg,
+}
+
+pub fn encryption_kind(path: &str) -> Option<EncryptionKind> {
+    match Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("age") => Some(EncryptionKind::Age),
+        Some("gpg") => Some(EncryptionKind::Gpg),
+        _ => None,
+    }
+}
+
+/// What is needed to encrypt an opened file again
+#[derive(Debug, Clone)]
+pub struct EncryptedFile {
+    pub kind: EncryptionKind,
+    /// Identity file for age, passphrase for gpg
+    secret: String,
+    /// Key ids a gpg file was encrypted to, symmetric encryption if empty
+    recipients: Vec<String>,
+    armor: bool,
+}
+
+/// Run the program with the input written to stdin and return its stdout and stderr
+fn run(program: &str, args: &[&str], input: Vec<u8>) -> Result<(Vec<u8>, String)> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    // Written from another thread so that large outputs do not fill the pipe
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    if let Ok(Err(err)) = writer.join() {
+        tracing::warn!("Failed to write to {}: {}", program, err);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !output.status.success() {
+        let message = stderr
+            .lines()
+            .filter(|line| !line.starts_with("[GNUPG:]"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        bail!("{} failed: {}", program, message.trim());
+    }
+    Ok((output.stdout, stderr))
+}
+
+/// Key ids from the `ENC_TO` status lines of gpg
+fn gpg_recipients(status: &str) -> Vec<String> {
+    let mut recipients: Vec<String> = status
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] ENC_TO "))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|key_id| key_id.to_string())
+        .collect();
+    recipients.dedup();
+    recipients
+}
+
+/// Decrypt the file with the identity file (age) or passphrase (gpg)
+pub fn decrypt(path: &str, kind: EncryptionKind, secret: &str) -> Result<(String, EncryptedFile)> {
+    let ciphertext = std::fs::read(path)?;
+    let (plaintext, recipients, armor) = match kind {
+        EncryptionKind::Age => {
+            let (plaintext, _) = run("age", &["--decrypt", "--identity", secret, path], vec![])?;
+            (plaintext, vec![], ciphertext.starts_with(AGE_ARMOR_HEADER))
+        }
+        EncryptionKind::Gpg => {
+            let (plaintext, status) = run(
+                "gpg",
+                &[
+                    "--batch",
+                    "--quiet",
+                    "--pinentry-mode",
+                    "loopback",
+                    "--passphrase-fd",
+                    "0",
+                    "--status-fd",
+                    "2",
+                    "--decrypt",
+                    path,
+                ],
+                format!("{}\n", secret).into_bytes(),
+            )?;
+            (
+                plaintext,
+                gpg_recipients(&status),
+                ciphertext.starts_with(PGP_ARMOR_HEADER),
+            )
+        }
+    };
+    let plaintext = String::from_utf8(plaintext).context("Decrypted content is not utf-8")?;
+    Ok((
+        plaintext,
+        EncryptedFile {
+            kind,
+            secret: secret.to_string(),
+            recipients,
+            armor,
+        },
+    ))
+}
+
+/// Encrypt the content for the same identity, recipients or passphrase it was decrypted with
+pub fn encrypt(file: &EncryptedFile, content: &str) -> Result<Vec<u8>> {
+    let mut args = vec![];
+    let mut input = vec![];
+    let program = match file.kind {
+        EncryptionKind::Age => {
+            args.extend(["--encrypt", "--identity", file.secret.as_str()]);
+            if file.armor {
+                args.push("--armor");
+            }
+            "age"
+        }
+        EncryptionKind::Gpg => {
+            args.extend(["--batch", "--yes", "--quiet"]);
+            if file.armor {
+                args.push("--armor");
+            }
+            if file.recipients.is_empty() {
+                // The passphrase is the first line of the input
+                args.extend([
+                    "--pinentry-mode",
+                    "loopback",
+                    "--passphrase-fd",
+                    "0",
+                    "--symmetric",
+                ]);
+                input.extend(format!("{}\n", file.secret).into_bytes());
+            } else {
+                args.extend(["--trust-model", "always", "--encrypt"]);
+                for recipient in &file.recipients {
+                    args.extend(["--recipient", recipient.as_str()]);
+                }
+            }
+            "gpg"
+        }
+    };
+    input.extend(content.as_bytes());
+    let (ciphertext, _) = run(program, &args, input)?;
+    Ok(ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encryption_kind, gpg_recipients, EncryptionKind};
+
+    #[test]
+    fn detect_encrypted_files() {
+        assert_eq!(encryption_kind("notes.md.age"), Some(EncryptionKind::Age));
+        assert_eq!(
+            encryption_kind("/tmp/secrets.gpg"),
+            Some(EncryptionKind::Gpg)
+        );
+        assert_eq!(encryption_kind("main.rs"), None);
+
+        let status = "[GNUPG:] ENC_TO 0123456789ABCDEF 1 0\n\
+                      [GNUPG:] KEY_CONSIDERED ABC 0\n\
+                      [GNUPG:] ENC_TO FEDCBA9876543210 18 0\n";
+        assert_eq!(
+            gpg_recipients(status),
+            vec!["0123456789ABCDEF", "FEDCBA9876543210"]
+        );
+    }
+}
@@ -4,8 +4,11 @@ use std::{
     fs::{self, File},
     io::{Read, Write},
     path,
+    time::SystemTime,
 };
 
+use super::encoding::{self, Encoding};
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct FolderEntry {
     pub path: String,
@@ -30,14 +33,37 @@ impl Ord for FolderEntry {
     }
 }
 
-/// Read file at path to string
+/// Extensions of files which are opened in an image viewer
+pub const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "svg"];
+
+/// Returns true if the file at path should be displayed as an image
+pub fn is_image_file(path: &str) -> bool {
+    path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Read file at path to string, decoding it from the detected encoding
 pub fn read_file_content(path: &str) -> Result<String> {
+    Ok(read_file_with_encoding(path)?.0)
+}
+
+/// Read file at path to string along with the encoding it was decoded from
+pub fn read_file_with_encoding(path: &str) -> Result<(String, Encoding)> {
     let mut f = File::open(path)?;
-    let mut buf = String::new();
+    let mut buf = vec![];
 
-    let _ = f.read_to_string(&mut buf)?;
+    let _ = f.read_to_end(&mut buf)?;
 
-    Ok(buf)
+    Ok(encoding::decode(&buf))
+}
+
+/// Time the file at path was last modified
+pub fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
 }
 
 /// Override file at path with new content
@@ -48,6 +74,76 @@ pub fn override_file_content(path: &str, buf: String) -> Result<()> {
     Ok(())
 }
 
+/// Override file at path with the content converted to the encoding
+pub fn override_file_content_encoded(path: &str, buf: &str, encoding: Encoding) -> Result<()> {
+    fs::write(path, encoding::encode(buf, encoding)?)?;
+    Ok(())
+}
+
+/// Directory where user configuration and data is stored,
+/// unit tests use a directory of their own under the temporary directory
+pub fn config_dir() -> Option<path::PathBuf> {
+    #[cfg(test)]
+    let base = Some(std::env::temp_dir().join(format!("rift_test_{}", std::process::id())));
+    #[cfg(all(not(test), target_os = "windows"))]
+    let base = std::env::var_os("APPDATA").map(path::PathBuf::from);
+    #[cfg(all(not(test), not(target_os = "windows")))]
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| path::Path::new(&home).join(".config")));
+    base.map(|base| base.join("rift"))
+}
+
+/// Returns true if the error was caused by missing permissions
+pub fn is_permission_denied(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<std::io::Error>()
+        .is_some_and(|err| err.kind() == std::io::ErrorKind::PermissionDenied)
+}
+
+/// Override file at path with new content using an elevated helper.
+/// pkexec is tried first (prompts graphically), falling back to sudo
+/// which only succeeds if credentials are cached.
+#[cfg(unix)]
+pub fn override_file_content_elevated(path: &str, buf: &[u8]) -> Result<()> {
+    use std::process::{Command, Stdio};
+
+    let helpers: [(&str, &[&str]); 2] =
+        [("pkexec", &["tee", "--"]), ("sudo", &["-n", "tee", "--"])];
+    let mut last_error = anyhow::anyhow!("No elevated helper available");
+    for (program, args) in helpers {
+        let mut child = match Command::new(program)
+            .args(args)
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(buf)?;
+        }
+        let output = child.wait_with_output()?;
+        if output.status.success() {
+            return Ok(());
+        }
+        last_error = anyhow::anyhow!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Err(last_error)
+}
+
+#[cfg(not(unix))]
+pub fn override_file_content_elevated(_path: &str, _buf: &[u8]) -> Result<()> {
+    anyhow::bail!("Elevated writes are not supported on this platform")
+}
+
 /// Create directory at path (recursively)
 pub fn create_directory(path: &str) -> Result<()> {
     fs::create_dir_all(path)?;
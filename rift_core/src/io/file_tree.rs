@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    file_io::{self, FolderEntry},
+    gitignore::Gitignore,
+};
+
+/// Visible row of the file tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTreeRow {
+    pub entry: FolderEntry,
+    pub depth: usize,
+    pub expanded: bool,
+}
+
+/// Tree of the workspace folder with expandable directories.
+/// Directory listings are cached until the tree is refreshed,
+/// entries ignored by git are not listed.
+#[derive(Debug, Default)]
+pub struct FileTree {
+    pub root: String,
+    pub expanded: HashSet<String>,
+    /// Index of the selected row
+    pub idx: usize,
+    entries: HashMap<String, Vec<FolderEntry>>,
+    ignore: Gitignore,
+}
+
+impl FileTree {
+    pub fn new(root: String) -> Self {
+        Self {
+            root,
+            expanded: HashSet::new(),
+            idx: 0,
+            entries: HashMap::new(),
+            ignore: Gitignore::default(),
+        }
+    }
+
+    /// Change the root of the tree collapsing all directories
+    pub fn set_root(&mut self, root: String) {
+        if root != self.root {
+            self.root = root;
+            self.expanded.clear();
+            self.idx = 0;
+            self.refresh();
+        }
+    }
+
+    /// Expand or collapse a directory
+    pub fn toggle(&mut self, path: &str) {
+        if !self.expanded.remove(path) {
+            self.expanded.insert(path.to_string());
+        }
+    }
+
+    /// Drop cached directory listings so they are read again
+    pub fn refresh(&mut self) {
+        self.entries.clear();
+        self.ignore = Gitignore::default();
+    }
+
+    fn get_entries(&mut self, path: &str) -> &Vec<FolderEntry> {
+        if !self.entries.contains_key(path) {
+            // Parents are always listed first so their patterns are already read
+            self.ignore.add_folder(path);
+            let entries = file_io::get_directory_entries(path)
+                .unwrap_or_else(|err| {
+                    tracing::error!("Failed to read directory {}: {}", path, err);
+                    vec![]
+                })
+                .into_iter()
+                .filter(|entry| {
+                    entry.name != ".git" && !self.ignore.is_ignored(&entry.path, entry.is_dir)
+                })
+                .collect();
+            self.entries.insert(path.to_string(), entries);
+        }
+        &self.entries[path]
+    }
+
+    /// The selected row, keeping the selection within the rows
+    pub fn selected(&mut self) -> Option<FileTreeRow> {
+        let rows = self.rows();
+        self.idx = self.idx.min(rows.len().saturating_sub(1));
+        rows.into_iter().nth(self.idx)
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.rows().len();
+        if len > 0 {
+            self.idx = (self.idx + 1) % len;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let len = self.rows().len();
+        if len > 0 {
+            self.idx = (self.idx + len - 1) % len;
+        }
+    }
+
+    /// Select the row of the path if it is visible
+    pub fn select_path(&mut self, path: &str) {
+        if let Some(idx) = self.rows().iter().position(|row| row.entry.path == path) {
+            self.idx = idx;
+        }
+    }
+
+    /// Flatten the expanded part of the tree into rows
+    pub fn rows(&mut self) -> Vec<FileTreeRow> {
+        let mut rows = vec![];
+        let root = self.root.clone();
+        self.collect_rows(&root, 0, &mut rows);
+        rows
+    }
+
+    fn collect_rows(&mut self, path: &str, depth: usize, rows: &mut Vec<FileTreeRow>) {
+        for entry in self.get_entries(path).clone() {
+            let expanded = entry.is_dir && self.expanded.contains(&entry.path);
+            let path = entry.path.clone();
+            rows.push(FileTreeRow {
+                entry,
+                depth,
+                expanded,
+            });
+            if expanded {
+                self.collect_rows(&path, depth + 1, rows);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileTree;
+
+    #[test]
+    fn file_tree_expand() {
+        let root = std::env::temp_dir().join(format!("rift_file_tree_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src").join("main.rs"), "").unwrap();
+        std::fs::write(root.join("Cargo.toml"), "").unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+
+        let mut tree = FileTree::new(root.to_str().unwrap().to_string());
+        let rows = tree.rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].entry.name, "src");
+        assert!(!rows[0].expanded);
+
+        tree.toggle(&rows[0].entry.path);
+        let rows = tree.rows();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[1].entry.name, "main.rs");
+        assert_eq!(rows[1].depth, 1);
+
+        tree.select_previous();
+        assert_eq!(tree.selected().unwrap().entry.name, "Cargo.toml");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! Matching paths against the patterns of `.gitignore` files.
+
+use std::path::Path;
+
+/// Pattern of a `.gitignore` file
+#[derive(Debug, Clone)]
+struct Pattern {
+    /// Folder of the `.gitignore` file the pattern applies below
+    base: String,
+    glob: String,
+    negated: bool,
+    /// Trailing `/`, matches only directories
+    dir_only: bool,
+    /// Contains a `/`, matched against the path relative to the base
+    /// instead of the file name
+    anchored: bool,
+}
+
+/// Patterns of the `.gitignore` files read so far, a later pattern overrides earlier ones
+#[derive(Debug, Clone, Default)]
+pub struct Gitignore {
+    patterns: Vec<Pattern>,
+}
+
+impl Gitignore {
+    /// Add the patterns of the `.gitignore` file in the folder if there is one
+    pub fn add_folder(&mut self, folder: &str) {
+        if let Ok(content) = std::fs::read_to_string(Path::new(folder).join(".gitignore")) {
+            self.add_patterns(folder, &content);
+        }
+    }
+
+    pub fn add_patterns(&mut self, folder: &str, content: &str) {
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(line) => (true, line),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            self.patterns.push(Pattern {
+                base: folder.to_string(),
+                glob: line.trim_start_matches('/').to_string(),
+                negated,
+                dir_only,
+                anchored,
+            });
+        }
+    }
+
+    /// Returns true if the path is ignored by the patterns of its folder or a parent folder
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for pattern in &self.patterns {
+            if pattern.dir_only && !is_dir {
+                continue;
+            }
+            let Ok(relative) = Path::new(path).strip_prefix(&pattern.base) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            let matched = if pattern.anchored {
+                glob_match(&pattern.glob, &relative)
+            } else {
+                let name = relative.rsplit('/').next().unwrap_or(&relative);
+                glob_match(&pattern.glob, name)
+            };
+            if matched {
+                ignored = !pattern.negated;
+            }
+        }
+        ignored
+    }
+}
+
+/// Match text against a glob where `*` and `?` do not match `/` and `**` matches anything
+pub fn glob_match(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_chars(&glob, &text)
+}
+
+fn glob_match_chars(glob: &[char], text: &[char]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some('*') if glob.get(1) == Some(&'*') => {
+            let rest = &glob[2..];
+            // `**/` matches any number of folders, including none
+            if let Some(after) = rest.strip_prefix(&['/']) {
+                return glob_match_chars(after, text)
+                    || (0..text.len())
+                        .filter(|idx| text[*idx] == '/')
+                        .any(|idx| glob_match_chars(after, &text[idx + 1..]));
+            }
+            (0..=text.len()).any(|skip| glob_match_chars(rest, &text[skip..]))
+        }
+        Some('*') => (0..=text.len())
+            .take_while(|skip| *skip == 0 || text[skip - 1] != '/')
+            .any(|skip| glob_match_chars(&glob[1..], &text[skip..])),
+        Some('?') => !text.is_empty() && text[0] != '/' && glob_match_chars(&glob[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match_chars(&glob[1..], &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, Gitignore};
+
+    #[test]
+    fn gitignore_patterns() {
+        assert!(glob_match("*.log", "debug.log"));
+        assert!(!glob_match("*.log", "logs/debug.log"));
+        assert!(glob_match("**/build", "a/b/build"));
+        assert!(glob_match("**/build", "build"));
+        assert!(glob_match("docs/**", "docs/a/b.md"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+
+        let mut ignore = Gitignore::default();
+        ignore.add_patterns(
+            "/repo",
+            "# comment\ntarget/\n*.log\n!keep.log\n/root.txt\nsrc/gen\n",
+        );
+        ignore.add_patterns("/repo/sub", "local\n");
+        assert!(ignore.is_ignored("/repo/target", true));
+        assert!(!ignore.is_ignored("/repo/target", false));
+        assert!(ignore.is_ignored("/repo/a/b/debug.log", false));
+        assert!(!ignore.is_ignored("/repo/keep.log", false));
+        assert!(ignore.is_ignored("/repo/root.txt", false));
+        assert!(!ignore.is_ignored("/repo/a/root.txt", false));
+        assert!(ignore.is_ignored("/repo/src/gen", true));
+        assert!(ignore.is_ignored("/repo/sub/local", false));
+        assert!(!ignore.is_ignored("/repo/local", false));
+    }
+}
@@ -0,0 +1,149 @@
+//! Paths and urls written in buffers, such as `src/main.rs:10:5` in compiler
+//! output or links in comments.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+/// Location referenced by the text under the cursor
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    Url(String),
+    /// Path with an optional one based line and column
+    File {
+        path: String,
+        line: Option<usize>,
+        column: Option<usize>,
+    },
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(
+            c,
+            '"' | '\'' | '`' | '(' | ')' | '<' | '>' | '[' | ']' | '{' | '}' | ','
+        )
+}
+
+/// Token containing the byte column of the line delimited by whitespace, quotes or brackets
+pub fn token_at(line: &str, column: usize) -> Option<&str> {
+    let column = column.min(line.len());
+    if !line.is_char_boundary(column) {
+        return None;
+    }
+    let start = line[..column]
+        .rfind(is_delimiter)
+        .map(|idx| idx + line[idx..].chars().next().unwrap().len_utf8())
+        .unwrap_or(0);
+    let end = line[column..]
+        .find(is_delimiter)
+        .map(|idx| idx + column)
+        .unwrap_or(line.len());
+    let token = line[start..end].trim_end_matches(['.', ':', ';']);
+    (!token.is_empty()).then_some(token)
+}
+
+pub fn parse_target(token: &str) -> Target {
+    if token.starts_with("http://") || token.starts_with("https://") {
+        return Target::Url(token.to_string());
+    }
+
+    // Trailing numbers separated by colons are the line and column
+    let mut parts = token.rsplitn(3, ':').collect::<Vec<_>>();
+    parts.reverse();
+    let numbers: Vec<Option<usize>> = parts.iter().map(|part| part.parse().ok()).collect();
+    match (parts.as_slice(), numbers.as_slice()) {
+        ([path, _, _], [_, Some(line), Some(column)]) => Target::File {
+            path: path.to_string(),
+            line: Some(*line),
+            column: Some(*column),
+        },
+        ([path, _, _], [_, _, Some(line)]) => Target::File {
+            path: format!("{}:{}", path, parts[1]),
+            line: Some(*line),
+            column: None,
+        },
+        ([path, _], [_, Some(line)]) => Target::File {
+            path: path.to_string(),
+            line: Some(*line),
+            column: None,
+        },
+        _ => Target::File {
+            path: token.to_string(),
+            line: None,
+            column: None,
+        },
+    }
+}
+
+/// Resolve the path against the folder of the buffer and then the workspace
+pub fn resolve_path(path: &str, buffer_folder: Option<&Path>, workspace: &str) -> Option<String> {
+    let path = Path::new(path);
+    let candidates = if path.is_absolute() {
+        vec![path.to_path_buf()]
+    } else {
+        buffer_folder
+            .map(|folder| folder.join(path))
+            .into_iter()
+            .chain([Path::new(workspace).join(path)])
+            .collect()
+    };
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        .and_then(|candidate| std::path::absolute(candidate).ok())
+        .map(|candidate| candidate.to_string_lossy().to_string())
+}
+
+/// Open the url in the default browser
+pub fn open_url(url: &str) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    let status = command.arg(url).status()?;
+    if !status.success() {
+        bail!("Failed to open {}: {}", url, status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_target, token_at, Target};
+
+    #[test]
+    fn parse_locations() {
+        let line = "  --> src/main.rs:10:5";
+        assert_eq!(token_at(line, 10), Some("src/main.rs:10:5"));
+        assert_eq!(
+            parse_target("src/main.rs:10:5"),
+            Target::File {
+                path: "src/main.rs".into(),
+                line: Some(10),
+                column: Some(5)
+            }
+        );
+        assert_eq!(
+            parse_target("README.md:3"),
+            Target::File {
+                path: "README.md".into(),
+                line: Some(3),
+                column: None
+            }
+        );
+
+        let line = "// see (https://example.com/docs).";
+        assert_eq!(
+            token_at(line, 12).map(parse_target),
+            Some(Target::Url("https://example.com/docs".into()))
+        );
+    }
+}
@@ -1 +1,6 @@
+pub mod encoding;
+pub mod encryption;
 pub mod file_io;
+pub mod file_tree;
+pub mod gitignore;
+pub mod location;
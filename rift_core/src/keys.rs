@@ -0,0 +1,321 @@
+//! Count prefixes (`5j`) and multi-key sequences (`g g`, `space f f`) typed in normal mode,
+//! and the keys of custom modes. Frontends pass plain keys by name: characters as typed,
+//! `space` and `escape`.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    actions::Action,
+    preferences::CustomMode,
+    state::{EditorState, Mode},
+};
+
+/// Time after the last key of an unfinished sequence or count until it is discarded
+pub const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Key sequences of normal mode and the actions they perform,
+/// the sequences entering custom modes take precedence
+pub fn key_sequences(modes: &[CustomMode]) -> Vec<(Vec<String>, Action)> {
+    let built_in: Vec<(&[&str], Action)> = vec![
+        (&["g", "g"], Action::GoToBufferStart),
+        (&["g", "e"], Action::GoToBufferEnd),
+        (&["g", "f"], Action::GotoFileUnderCursor),
+        (&["space", "f", "f"], Action::OpenFile),
+        (&["space", "f", "s"], Action::SaveCurrentBuffer),
+        (&["space", "b", "d"], Action::CloseCurrentBuffer),
+        (&["space", "b", "n"], Action::CycleNextBuffer),
+        (&["space", "b", "p"], Action::CyclePreviousBuffer),
+        (&["space", "w", "v"], Action::SplitVertical),
+        (&["space", "w", "s"], Action::SplitHorizontal),
+        (&["space", "w", "w"], Action::FocusNextPane),
+        (&["space", "w", "q"], Action::ClosePane),
+        (&["space", "p"], Action::OpenCommandPalette),
+    ];
+    modes
+        .iter()
+        .filter(|mode| !mode.enter.is_empty())
+        .map(|mode| (mode.enter.clone(), Action::EnterMode(mode.name.clone())))
+        .chain(built_in.into_iter().map(|(sequence, action)| {
+            (sequence.iter().map(|key| key.to_string()).collect(), action)
+        }))
+        .collect()
+}
+
+/// What a frontend does with a key passed to `PendingKeys::push`
+#[derive(Debug, Clone)]
+pub enum KeyInput {
+    /// Part of a count or an unfinished sequence, nothing else is done with the key
+    Pending,
+    /// The key completed a sequence
+    Action(Action),
+    /// Not part of a sequence, the key performs its usual action
+    Unhandled,
+}
+
+#[derive(Debug, Default)]
+pub struct PendingKeys {
+    count: Option<usize>,
+    keys: Vec<String>,
+    last_key: Option<Instant>,
+}
+
+impl PendingKeys {
+    fn is_expired(&self) -> bool {
+        self.last_key
+            .is_some_and(|last_key| last_key.elapsed() > SEQUENCE_TIMEOUT)
+    }
+
+    pub fn reset(&mut self) {
+        self.count = None;
+        self.keys.clear();
+        self.last_key = None;
+    }
+
+    /// Handle a key typed in normal mode
+    pub fn push(&mut self, key: &str, sequences: &[(Vec<String>, Action)]) -> KeyInput {
+        if self.is_expired() {
+            self.reset();
+        }
+        if key == "escape" {
+            let pending = !self.keys.is_empty() || self.count.is_some();
+            self.reset();
+            return if pending {
+                KeyInput::Pending
+            } else {
+                KeyInput::Unhandled
+            };
+        }
+        self.last_key = Some(Instant::now());
+
+        // Zero only continues a count, on its own it may be bound to something
+        if let Some(digit) = key.parse::<usize>().ok().filter(|_| self.keys.is_empty()) {
+            if digit != 0 || self.count.is_some() {
+                self.count = Some(
+                    self.count
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit),
+                );
+                return KeyInput::Pending;
+            }
+        }
+
+        self.keys.push(key.to_string());
+        if let Some((_, action)) = sequences
+            .iter()
+            .find(|(sequence, _)| *sequence == self.keys)
+        {
+            self.keys.clear();
+            return KeyInput::Action(action.clone());
+        }
+        if sequences.iter().any(|(sequence, _)| {
+            sequence.len() > self.keys.len()
+                && sequence
+                    .iter()
+                    .zip(&self.keys)
+                    .all(|(key, typed)| key == typed)
+        }) {
+            return KeyInput::Pending;
+        }
+
+        // Keys which do not continue a sequence discard it
+        let discarded = self.keys.len() > 1;
+        self.keys.clear();
+        if discarded {
+            self.reset();
+            KeyInput::Pending
+        } else {
+            KeyInput::Unhandled
+        }
+    }
+
+    /// Count typed before the current key, which is consumed by the next action
+    pub fn take_count(&mut self) -> Option<usize> {
+        if self.is_expired() {
+            self.reset();
+        }
+        self.count.take()
+    }
+
+    /// Count and keys typed so far, shown in the status bar
+    pub fn pending(&self) -> String {
+        if self.is_expired() {
+            return String::new();
+        }
+        let count = self.count.map(|count| count.to_string());
+        count
+            .into_iter()
+            .chain(self.keys.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Handle a key typed in normal mode or a custom mode
+pub fn push_key(state: &mut EditorState, key: &str) -> KeyInput {
+    let Mode::Custom(name) = &state.mode else {
+        let sequences = key_sequences(&state.preferences.modes);
+        return state.pending_keys.push(key, &sequences);
+    };
+    if key == "escape" {
+        return KeyInput::Action(Action::QuitInsertMode);
+    }
+    let Some(mode) = state
+        .preferences
+        .modes
+        .iter()
+        .find(|mode| mode.name == *name)
+    else {
+        // The mode is no longer in the configuration
+        state.mode = Mode::Normal;
+        return KeyInput::Unhandled;
+    };
+    let sticky = mode.sticky;
+    // Keys without an action are ignored
+    let Some(action) = mode.keys.get(key).cloned() else {
+        return KeyInput::Pending;
+    };
+    if !sticky {
+        state.mode = Mode::Normal;
+    }
+    KeyInput::Action(action)
+}
+
+/// Actions performed for an action typed after a count, None if the count does not apply to it
+pub fn with_count(action: &Action, count: usize) -> Option<Vec<Action>> {
+    let repeat = |first: Action, rest: Action| {
+        std::iter::once(first)
+            .chain(std::iter::repeat_n(rest, count.saturating_sub(1)))
+            .collect()
+    };
+    match action {
+        Action::GoToBufferStart | Action::GoToBufferEnd => {
+            Some(vec![Action::GoToLine(count.saturating_sub(1))])
+        }
+        Action::ScrollUp(lines) => Some(vec![Action::ScrollUp(lines.saturating_mul(count))]),
+        Action::ScrollDown(lines) => Some(vec![Action::ScrollDown(lines.saturating_mul(count))]),
+        // Following words and lines are added to the selection
+        Action::SelectTillEndOfWord => Some(repeat(
+            Action::SelectTillEndOfWord,
+            Action::ExtendSelectTillEndOfWord,
+        )),
+        Action::SelectTillStartOfWord => Some(repeat(
+            Action::SelectTillStartOfWord,
+            Action::ExtendSelectTillStartOfWord,
+        )),
+        Action::SelectCurrentLine => Some(repeat(
+            Action::SelectCurrentLine,
+            Action::SelectAndExtentCurrentLine,
+        )),
+        Action::MoveCursorDown
+        | Action::MoveCursorUp
+        | Action::MoveCursorLeft
+        | Action::MoveCursorRight
+        | Action::ExtendCursorDown
+        | Action::ExtendCursorUp
+        | Action::ExtendCursorLeft
+        | Action::ExtendCursorRight
+        | Action::ExtendSelectTillEndOfWord
+        | Action::ExtendSelectTillStartOfWord
+        | Action::SelectAndExtentCurrentLine
+        | Action::DeleteNextCharacter
+        | Action::AddIndent
+        | Action::RemoveIndent
+        | Action::Undo
+        | Action::Redo
+        | Action::PasteFromRegister
+        | Action::PasteFromClipboard
+        | Action::RepeatLastChange
+        | Action::SearchNext
+        | Action::SearchPrevious
+        | Action::NextError
+        | Action::PrevError
+        | Action::NextDiagnostic
+        | Action::PreviousDiagnostic
+        | Action::NextHunk
+        | Action::PrevHunk
+        | Action::CycleNextBuffer
+        | Action::CyclePreviousBuffer => Some(vec![action.clone(); count]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{key_sequences, with_count, KeyInput, PendingKeys};
+    use crate::actions::Action;
+    use crate::config::{merge_layers, parse_layer};
+
+    #[test]
+    fn counts_and_sequences() {
+        let sequences = key_sequences(&[]);
+        let mut keys = PendingKeys::default();
+        assert!(matches!(keys.push("1", &sequences), KeyInput::Pending));
+        assert!(matches!(keys.push("2", &sequences), KeyInput::Pending));
+        assert!(matches!(keys.push("g", &sequences), KeyInput::Pending));
+        assert_eq!(keys.pending(), "12 g");
+        assert!(matches!(
+            keys.push("g", &sequences),
+            KeyInput::Action(Action::GoToBufferStart)
+        ));
+        assert_eq!(keys.take_count(), Some(12));
+        assert_eq!(keys.take_count(), None);
+
+        assert!(matches!(keys.push("0", &sequences), KeyInput::Unhandled));
+        assert!(matches!(keys.push("j", &sequences), KeyInput::Unhandled));
+        assert!(matches!(keys.push("space", &sequences), KeyInput::Pending));
+        assert!(matches!(keys.push("f", &sequences), KeyInput::Pending));
+        assert!(matches!(
+            keys.push("f", &sequences),
+            KeyInput::Action(Action::OpenFile)
+        ));
+
+        // Keys which do not continue a sequence discard it
+        assert!(matches!(keys.push("space", &sequences), KeyInput::Pending));
+        assert!(matches!(keys.push("x", &sequences), KeyInput::Pending));
+        assert!(matches!(keys.push("x", &sequences), KeyInput::Unhandled));
+        assert!(matches!(keys.push("3", &sequences), KeyInput::Pending));
+        assert!(matches!(keys.push("escape", &sequences), KeyInput::Pending));
+        assert_eq!(keys.take_count(), None);
+        assert!(matches!(
+            keys.push("escape", &sequences),
+            KeyInput::Unhandled
+        ));
+
+        assert!(matches!(
+            with_count(&Action::GoToBufferStart, 12).as_deref(),
+            Some([Action::GoToLine(11)])
+        ));
+        assert_eq!(with_count(&Action::MoveCursorDown, 3).unwrap().len(), 3);
+        assert!(matches!(
+            with_count(&Action::SelectTillEndOfWord, 2).as_deref(),
+            Some([
+                Action::SelectTillEndOfWord,
+                Action::ExtendSelectTillEndOfWord
+            ])
+        ));
+        assert!(with_count(&Action::EnterInsertMode, 2).is_none());
+    }
+
+    #[test]
+    fn custom_mode_keys() {
+        let layer = parse_layer(
+            "[[modes]]\nname = \"window\"\nenter = [\"space\", \"w\"]\nsticky = true\n\n[modes.keys]\nv = \"SplitVertical\"\nj = { ScrollDown = 5 }\n",
+        )
+        .unwrap();
+        let preferences = merge_layers(vec![layer]).unwrap();
+        let mode = &preferences.modes[0];
+        assert!(mode.sticky);
+        assert!(matches!(mode.keys.get("j"), Some(Action::ScrollDown(5))));
+        assert_eq!(preferences.mode_indicator("window"), "WINDOW");
+
+        // The sequence entering the mode takes precedence over built in ones starting with it
+        let sequences = key_sequences(&preferences.modes);
+        let mut keys = PendingKeys::default();
+        assert!(matches!(keys.push("space", &sequences), KeyInput::Pending));
+        assert!(matches!(
+            keys.push("w", &sequences),
+            KeyInput::Action(Action::EnterMode(name)) if name == "window"
+        ));
+    }
+}
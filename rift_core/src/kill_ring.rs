@@ -0,0 +1,118 @@
+//! Kill ring of recently copied and deleted text, pasted from a picker
+//! or by cycling through the entries in place of the last paste.
+
+use std::collections::VecDeque;
+
+use crate::buffer::instance::Selection;
+
+/// Number of copies and deletions which are remembered
+pub const KILL_RING_SIZE: usize = 30;
+
+/// Characters of an entry shown in the picker
+const PREVIEW_LENGTH: usize = 60;
+
+/// Text inserted by the last paste, replaced when cycling through the kill ring
+#[derive(Debug, Clone)]
+pub struct LastPaste {
+    pub buffer_id: u32,
+    pub selection: Selection,
+    /// Buffer version right after the paste, cycling is only possible until the next edit
+    pub version: usize,
+    /// Kill ring entry which was pasted
+    pub idx: usize,
+}
+
+/// Copied and deleted text, most recent first
+#[derive(Debug, Default)]
+pub struct KillRing {
+    pub entries: VecDeque<String>,
+    pub last_paste: Option<LastPaste>,
+}
+
+impl KillRing {
+    /// Record copied or deleted text, moving it to the front if it is already present
+    pub fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        self.entries.retain(|entry| *entry != text);
+        self.entries.push_front(text);
+        self.entries.truncate(KILL_RING_SIZE);
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&String> {
+        self.entries.get(idx)
+    }
+
+    /// Single line previews of the entries numbered from 1, such as `2: fn main() {⏎…`
+    pub fn previews(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let mut preview: String = entry
+                    .chars()
+                    .take(PREVIEW_LENGTH)
+                    .map(|c| if c == '\n' { '⏎' } else { c })
+                    .collect();
+                if entry.chars().count() > PREVIEW_LENGTH {
+                    preview.push('…');
+                }
+                format!("{}: {}", idx + 1, preview)
+            })
+            .collect()
+    }
+
+    /// Index of the entry from a preview or a number typed in the picker
+    pub fn parse_preview(input: &str) -> Option<usize> {
+        let number = input.split(':').next()?.trim();
+        number.parse::<usize>().ok()?.checked_sub(1)
+    }
+
+    /// Index of the entry replacing the last paste, which must be the latest edit of the buffer
+    pub fn cycle_idx(&self, buffer_id: u32, version: usize) -> Option<usize> {
+        let last_paste = self.last_paste.as_ref()?;
+        if last_paste.buffer_id != buffer_id || last_paste.version != version {
+            return None;
+        }
+        Some((last_paste.idx + 1) % self.entries.len().max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KillRing, LastPaste, KILL_RING_SIZE};
+    use crate::buffer::instance::{Cursor, Selection};
+
+    #[test]
+    fn kill_ring_entries() {
+        let mut kill_ring = KillRing::default();
+        kill_ring.push("first".to_string());
+        kill_ring.push("second\nline".to_string());
+        kill_ring.push(String::new());
+        kill_ring.push("first".to_string());
+        assert_eq!(kill_ring.entries, vec!["first", "second\nline"]);
+        assert_eq!(kill_ring.previews()[1], "2: second⏎line");
+        assert_eq!(KillRing::parse_preview("2: second⏎line"), Some(1));
+        assert_eq!(KillRing::parse_preview("0"), None);
+
+        for idx in 0..KILL_RING_SIZE + 5 {
+            kill_ring.push(idx.to_string());
+        }
+        assert_eq!(kill_ring.entries.len(), KILL_RING_SIZE);
+
+        let cursor = Cursor { row: 0, column: 0 };
+        kill_ring.last_paste = Some(LastPaste {
+            buffer_id: 1,
+            selection: Selection {
+                mark: cursor,
+                cursor,
+            },
+            version: 4,
+            idx: KILL_RING_SIZE - 1,
+        });
+        assert_eq!(kill_ring.cycle_idx(1, 4), Some(0));
+        assert_eq!(kill_ring.cycle_idx(1, 5), None);
+        assert_eq!(kill_ring.cycle_idx(2, 4), None);
+    }
+}
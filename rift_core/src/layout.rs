@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use crate::buffer::instance::BufferInstance;
+
+/// Direction in which a pane is split
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Panes placed side by side
+    Vertical,
+    /// Panes placed one above the other
+    Horizontal,
+}
+
+/// Area of the editor as fractions of its width and height
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Region {
+    pub const FULL: Region = Region {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    /// Split the region into two at the given ratio
+    pub fn split(&self, direction: SplitDirection, ratio: f32) -> (Region, Region) {
+        match direction {
+            SplitDirection::Vertical => (
+                Region {
+                    width: self.width * ratio,
+                    ..*self
+                },
+                Region {
+                    x: self.x + self.width * ratio,
+                    width: self.width * (1.0 - ratio),
+                    ..*self
+                },
+            ),
+            SplitDirection::Horizontal => (
+                Region {
+                    height: self.height * ratio,
+                    ..*self
+                },
+                Region {
+                    y: self.y + self.height * ratio,
+                    height: self.height * (1.0 - ratio),
+                    ..*self
+                },
+            ),
+        }
+    }
+}
+
+/// Node of the window tree
+#[derive(Debug)]
+pub enum LayoutNode {
+    Pane(u32),
+    Split {
+        id: u32,
+        direction: SplitDirection,
+        ratio: f32,
+        first: Box<LayoutNode>,
+        second: Box<LayoutNode>,
+    },
+}
+
+/// Split between two panes which can be resized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Splitter {
+    pub id: u32,
+    pub direction: SplitDirection,
+    pub region: Region,
+    pub ratio: f32,
+}
+
+/// Window tree of editor panes.
+/// The focused pane shows the active buffer of the editor state
+/// while the other panes keep a copy of their buffer instance.
+#[derive(Debug)]
+pub struct Layout {
+    pub root: LayoutNode,
+    pub panes: HashMap<u32, Option<BufferInstance>>,
+    pub focused: u32,
+    next_id: u32,
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Self {
+            root: LayoutNode::Pane(0),
+            panes: HashMap::from([(0, None)]),
+            focused: 0,
+            next_id: 1,
+        }
+    }
+
+    /// Returns true if the editor is split into multiple panes
+    pub fn is_split(&self) -> bool {
+        self.panes.len() > 1
+    }
+
+    /// Split the focused pane and focus the new pane.
+    /// The instance is the current view of the focused pane.
+    pub fn split(&mut self, direction: SplitDirection, instance: Option<BufferInstance>) -> u32 {
+        let pane_id = self.next_id;
+        let split_id = self.next_id + 1;
+        self.next_id += 2;
+
+        let focused = self.focused;
+        if let Some(node) = Self::find_pane_mut(&mut self.root, focused) {
+            *node = LayoutNode::Split {
+                id: split_id,
+                direction,
+                ratio: 0.5,
+                first: Box::new(LayoutNode::Pane(focused)),
+                second: Box::new(LayoutNode::Pane(pane_id)),
+            };
+        }
+        self.panes.insert(focused, instance);
+        self.panes.insert(pane_id, None);
+        self.focused = pane_id;
+        pane_id
+    }
+
+    /// Remove a pane from the tree returning the pane which takes its place
+    pub fn remove(&mut self, id: u32) -> Option<u32> {
+        if !self.is_split() || !self.panes.contains_key(&id) {
+            return None;
+        }
+        let replacement = Self::remove_pane(&mut self.root, id);
+        self.panes.remove(&id);
+        replacement
+    }
+
+    fn remove_pane(node: &mut LayoutNode, id: u32) -> Option<u32> {
+        if let LayoutNode::Split { first, second, .. } = node {
+            let sibling = if matches!(**first, LayoutNode::Pane(pane) if pane == id) {
+                Some(std::mem::replace(&mut **second, LayoutNode::Pane(id)))
+            } else if matches!(**second, LayoutNode::Pane(pane) if pane == id) {
+                Some(std::mem::replace(&mut **first, LayoutNode::Pane(id)))
+            } else {
+                None
+            };
+            if let Some(sibling) = sibling {
+                *node = sibling;
+                return Self::first_pane(node);
+            }
+            return Self::remove_pane(first, id).or_else(|| Self::remove_pane(second, id));
+        }
+        None
+    }
+
+    fn first_pane(node: &LayoutNode) -> Option<u32> {
+        match node {
+            LayoutNode::Pane(id) => Some(*id),
+            LayoutNode::Split { first, .. } => Self::first_pane(first),
+        }
+    }
+
+    fn find_pane_mut(node: &mut LayoutNode, id: u32) -> Option<&mut LayoutNode> {
+        match node {
+            LayoutNode::Pane(pane) if *pane == id => Some(node),
+            LayoutNode::Pane(_) => None,
+            LayoutNode::Split { first, second, .. } => {
+                Self::find_pane_mut(first, id).or_else(|| Self::find_pane_mut(second, id))
+            }
+        }
+    }
+
+    /// Pane ids in display order
+    pub fn pane_ids(&self) -> Vec<u32> {
+        self.regions().into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Pane following the focused pane in display order
+    pub fn next_pane(&self) -> u32 {
+        let ids = self.pane_ids();
+        let position = ids.iter().position(|id| *id == self.focused).unwrap_or(0);
+        ids[(position + 1) % ids.len()]
+    }
+
+    /// Regions of all panes
+    pub fn regions(&self) -> Vec<(u32, Region)> {
+        let mut regions = vec![];
+        Self::collect_regions(&self.root, Region::FULL, &mut regions, &mut vec![]);
+        regions
+    }
+
+    /// Splits between panes along with the region they divide
+    pub fn splitters(&self) -> Vec<Splitter> {
+        let mut splitters = vec![];
+        Self::collect_regions(&self.root, Region::FULL, &mut vec![], &mut splitters);
+        splitters
+    }
+
+    fn collect_regions(
+        node: &LayoutNode,
+        region: Region,
+        regions: &mut Vec<(u32, Region)>,
+        splitters: &mut Vec<Splitter>,
+    ) {
+        match node {
+            LayoutNode::Pane(id) => regions.push((*id, region)),
+            LayoutNode::Split {
+                id,
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                splitters.push(Splitter {
+                    id: *id,
+                    direction: *direction,
+                    region,
+                    ratio: *ratio,
+                });
+                let (first_region, second_region) = region.split(*direction, *ratio);
+                Self::collect_regions(first, first_region, regions, splitters);
+                Self::collect_regions(second, second_region, regions, splitters);
+            }
+        }
+    }
+
+    /// Update the ratio of a split
+    pub fn set_ratio(&mut self, split_id: u32, new_ratio: f32) {
+        fn set(node: &mut LayoutNode, split_id: u32, new_ratio: f32) {
+            if let LayoutNode::Split {
+                id,
+                ratio,
+                first,
+                second,
+                ..
+            } = node
+            {
+                if *id == split_id {
+                    *ratio = new_ratio.clamp(0.1, 0.9);
+                } else {
+                    set(first, split_id, new_ratio);
+                    set(second, split_id, new_ratio);
+                }
+            }
+        }
+        set(&mut self.root, split_id, new_ratio);
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Layout, Region, SplitDirection};
+
+    #[test]
+    fn layout_split_and_remove() {
+        let mut layout = Layout::new();
+        let second = layout.split(SplitDirection::Vertical, None);
+        let third = layout.split(SplitDirection::Horizontal, None);
+        assert_eq!(layout.pane_ids(), vec![0, second, third]);
+        assert_eq!(layout.focused, third);
+
+        let regions = layout.regions();
+        assert_eq!(regions[0].1.width, 0.5);
+        assert_eq!(
+            regions[2].1,
+            Region {
+                x: 0.5,
+                y: 0.5,
+                width: 0.5,
+                height: 0.5
+            }
+        );
+
+        assert_eq!(layout.remove(second), Some(third));
+        assert_eq!(layout.pane_ids(), vec![0, third]);
+        assert_eq!(layout.remove(third), Some(0));
+        assert!(!layout.is_split());
+        assert_eq!(layout.remove(0), None);
+    }
+}
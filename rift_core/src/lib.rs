@@ -1,9 +1,48 @@
+pub mod abbreviations;
 pub mod actions;
 pub mod ai;
+pub mod block;
 pub mod buffer;
+pub mod call_hierarchy;
+pub mod code_actions;
+pub mod colors;
+pub mod command_palette;
+pub mod comments;
 pub mod concurrent;
+pub mod config;
+pub mod dap;
+pub mod diagnostics;
+pub mod dialog;
+pub mod diff;
+pub mod editorconfig;
+pub mod events;
+pub mod export;
+pub mod file_changes;
+pub mod filter;
+pub mod formatter;
+pub mod git;
+pub mod indent;
 pub mod io;
+pub mod keys;
+pub mod kill_ring;
+pub mod layout;
+pub mod linter;
+pub mod logs;
 pub mod lsp;
+pub mod markdown;
+pub mod notifications;
+pub mod pairs;
 pub mod preferences;
+pub mod recovery;
+pub mod replace;
+pub mod scratch;
+pub mod search;
+pub mod session;
+pub mod snippets;
+pub mod spellcheck;
 pub mod state;
+pub mod stats;
+pub mod tasks;
+pub mod terminal;
+pub mod text_objects;
 pub mod themes;
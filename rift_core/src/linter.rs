@@ -0,0 +1,605 @@
+//! Running external linters such as clippy or ruff in the background and
+//! parsing their output into diagnostics shown alongside language server results.
+
+use std::{collections::HashMap, path::Path, process::Stdio};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    buffer::instance::{Cursor, Language, Selection},
+    concurrent::AsyncResult,
+    formatter::FILE_PLACEHOLDER,
+    lsp::{
+        client::LSPClientHandle,
+        types::{Diagnostic, DiagnosticSeverity},
+    },
+    notifications::NotificationLevel,
+    state::EditorState,
+    tasks::match_error_format,
+};
+
+/// How the output of a linter is parsed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LintOutput {
+    /// `cargo clippy --message-format=json`
+    CargoJson,
+    /// `ruff check --output-format=json`
+    RuffJson,
+    /// `eslint -f json`
+    EslintJson,
+    /// Lines matching one of the error formats used by tasks
+    ErrorFormat(Vec<String>),
+    /// JSON with the fields of each diagnostic located by the mapping
+    Json(JsonMapping),
+    /// Lines matching the regex, with the named groups `file`, `line`, `column`,
+    /// `end_line`, `end_column`, `severity`, `code` and `message`
+    Regex(String),
+}
+
+/// JSON pointers to the diagnostics in the output of a linter and to their fields,
+/// such as `/line` for the line of `shellcheck --format=json` diagnostics.
+/// The linted file is used when there is no file field.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonMapping {
+    /// Array of diagnostics, empty for the whole output
+    pub items: String,
+    pub file: Option<String>,
+    pub line: String,
+    pub column: Option<String>,
+    pub end_line: Option<String>,
+    pub end_column: Option<String>,
+    pub severity: Option<String>,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+/// Command printing diagnostics of the file or the whole workspace to stdout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinterCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub output: LintOutput,
+}
+
+impl LinterCommand {
+    fn new(program: &str, args: &[&str], output: LintOutput) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            output,
+        }
+    }
+
+    /// Linters without the file placeholder check the whole workspace
+    pub fn is_workspace_wide(&self) -> bool {
+        !self.args.iter().any(|arg| arg.contains(FILE_PLACEHOLDER))
+    }
+}
+
+pub fn default_linters() -> HashMap<Language, LinterCommand> {
+    HashMap::from([
+        (
+            Language::Rust,
+            LinterCommand::new(
+                "cargo",
+                &["clippy", "--quiet", "--message-format=json"],
+                LintOutput::CargoJson,
+            ),
+        ),
+        (
+            Language::Python,
+            LinterCommand::new(
+                "ruff",
+                &["check", "--quiet", "--output-format=json", FILE_PLACEHOLDER],
+                LintOutput::RuffJson,
+            ),
+        ),
+    ])
+}
+
+/// Output of a finished linter sent back to the editor
+#[derive(Debug, Serialize, Deserialize)]
+struct LintRun {
+    linter: LinterCommand,
+    path: String,
+    folder: String,
+    success: bool,
+    stdout: String,
+    stderr: String,
+}
+
+/// Key of the diagnostics of a file in `state.diagnostics`
+pub fn diagnostics_key(path: &Path) -> String {
+    #[allow(unused_mut)]
+    let mut path = std::path::absolute(path)
+        .unwrap_or(path.to_path_buf())
+        .to_string_lossy()
+        .to_string();
+    #[cfg(target_os = "windows")]
+    {
+        path = path.to_lowercase();
+    }
+    path
+}
+
+/// Run the linter of the buffer's language in the background
+pub fn lint_buffer(state: &mut EditorState, buffer_id: u32) {
+    let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+    let Some(path) = buffer.file_path.clone() else {
+        return;
+    };
+    let Some(linter) = state.preferences.linters.get(&buffer.language).cloned() else {
+        return;
+    };
+    let folder = state.workspace_folder.clone();
+    let sender = state.async_handle.sender.clone();
+
+    state.rt.spawn(async move {
+        let mut command = tokio::process::Command::new(&linter.program);
+
+        #[cfg(target_os = "windows")]
+        {
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let output = command
+            .args(
+                linter
+                    .args
+                    .iter()
+                    .map(|arg| arg.replace(FILE_PLACEHOLDER, &path)),
+            )
+            .current_dir(&folder)
+            .stdin(Stdio::null())
+            .output()
+            .await;
+
+        let result = match output {
+            Ok(output) => {
+                let run = LintRun {
+                    linter,
+                    path,
+                    folder,
+                    success: output.status.success(),
+                    stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                    stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                };
+                AsyncResult {
+                    result: serde_json::to_string(&run).unwrap(),
+                    callback: apply_lint_run,
+                }
+            }
+            // Linters which are not installed are skipped silently
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                tracing::warn!("Linter {} not found", linter.program);
+                return;
+            }
+            Err(err) => AsyncResult {
+                result: format!("Failed to run {}: {}", linter.program, err),
+                callback: |message, state, _lsp_handle| {
+                    state.notify(NotificationLevel::Error, message);
+                },
+            },
+        };
+        if let Err(err) = sender.send(result).await {
+            tracing::error!("Failed to send lint result: {}", err);
+        }
+    });
+}
+
+fn apply_lint_run(
+    result: String,
+    state: &mut EditorState,
+    _lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    let run: LintRun = match serde_json::from_str(&result) {
+        Ok(run) => run,
+        Err(err) => {
+            tracing::error!("Malformed lint result: {}", err);
+            return;
+        }
+    };
+    let source = run.linter.program.clone();
+    let mut files = parse_lint_output(
+        &run.linter.output,
+        &run.stdout,
+        &run.path,
+        &run.folder,
+        &source,
+    );
+
+    if files.is_empty() && !run.success {
+        if let Some(line) = run.stderr.lines().find(|line| !line.trim().is_empty()) {
+            state.notify(
+                NotificationLevel::Warning,
+                format!("{}: {}", source, line.trim()),
+            );
+            return;
+        }
+    }
+
+    // Files linted in this run which no longer have any diagnostics
+    files
+        .entry(diagnostics_key(Path::new(&run.path)))
+        .or_default();
+    if run.linter.is_workspace_wide() {
+        for (path, diagnostics) in &state.lint_diagnostics {
+            if diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.source == source)
+            {
+                files.entry(path.clone()).or_default();
+            }
+        }
+    }
+
+    for (path, diagnostics) in files {
+        state.set_lint_diagnostics(path, diagnostics);
+    }
+    state.update_view = true;
+}
+
+/// Diagnostics in the output of a linter of the file keyed by file
+pub fn parse_lint_output(
+    output: &LintOutput,
+    stdout: &str,
+    path: &str,
+    folder: &str,
+    source: &str,
+) -> HashMap<String, Vec<Diagnostic>> {
+    let mut files: HashMap<String, Vec<Diagnostic>> = HashMap::new();
+    let mut add = |path: &str, severity, code: String, message: String, start, end| {
+        files
+            .entry(diagnostics_key(&Path::new(folder).join(path)))
+            .or_default()
+            .push(Diagnostic {
+                range: Selection {
+                    mark: start,
+                    cursor: end,
+                },
+                severity,
+                code,
+                source: source.to_string(),
+                message,
+            });
+    };
+
+    match output {
+        LintOutput::CargoJson => {
+            for line in stdout.lines() {
+                let Ok(message) = serde_json::from_str::<CargoMessage>(line) else {
+                    continue;
+                };
+                let Some(diagnostic) = message.message else {
+                    continue;
+                };
+                let severity = match diagnostic.level.as_str() {
+                    "error" | "error: internal compiler error" => DiagnosticSeverity::Error,
+                    "warning" => DiagnosticSeverity::Warning,
+                    "note" => DiagnosticSeverity::Information,
+                    _ => DiagnosticSeverity::Hint,
+                };
+                let code = diagnostic.code.map(|code| code.code).unwrap_or_default();
+                for span in diagnostic.spans.iter().filter(|span| span.is_primary) {
+                    add(
+                        &span.file_name,
+                        severity.clone(),
+                        code.clone(),
+                        diagnostic.message.clone(),
+                        location(span.line_start, span.column_start),
+                        location(span.line_end, span.column_end),
+                    );
+                }
+            }
+        }
+        LintOutput::RuffJson => {
+            let messages: Vec<RuffMessage> = serde_json::from_str(stdout).unwrap_or_default();
+            for message in messages {
+                // Syntax errors have no rule code
+                let severity = match message.code {
+                    Some(_) => DiagnosticSeverity::Warning,
+                    None => DiagnosticSeverity::Error,
+                };
+                add(
+                    &message.filename,
+                    severity,
+                    message.code.unwrap_or_default(),
+                    message.message,
+                    location(message.location.row, message.location.column),
+                    location(message.end_location.row, message.end_location.column),
+                );
+            }
+        }
+        LintOutput::EslintJson => {
+            let results: Vec<EslintResult> = serde_json::from_str(stdout).unwrap_or_default();
+            for result in results {
+                for message in result.messages {
+                    let severity = match message.severity {
+                        2 => DiagnosticSeverity::Error,
+                        _ => DiagnosticSeverity::Warning,
+                    };
+                    let start = location(message.line, message.column);
+                    let end = match (message.end_line, message.end_column) {
+                        (Some(line), Some(column)) => location(line, column),
+                        _ => start,
+                    };
+                    add(
+                        &result.file_path,
+                        severity,
+                        message.rule_id.unwrap_or_default(),
+                        message.message,
+                        start,
+                        end,
+                    );
+                }
+            }
+        }
+        LintOutput::ErrorFormat(formats) => {
+            for line in stdout.lines() {
+                let line = line.trim();
+                let Some((path, row, column, message)) = formats
+                    .iter()
+                    .find_map(|format| match_error_format(format, line))
+                else {
+                    continue;
+                };
+                let severity = if message.to_lowercase().starts_with("error") {
+                    DiagnosticSeverity::Error
+                } else {
+                    DiagnosticSeverity::Warning
+                };
+                let start = location(row, column);
+                add(&path, severity, String::new(), message, start, start);
+            }
+        }
+        LintOutput::Json(mapping) => {
+            let value: Value = serde_json::from_str(stdout).unwrap_or_default();
+            let items = value
+                .pointer(&mapping.items)
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            for item in items {
+                let field = |pointer: Option<&str>| {
+                    pointer
+                        .and_then(|pointer| item.pointer(pointer))
+                        .map(|value| match value {
+                            Value::String(string) => string.clone(),
+                            value => value.to_string(),
+                        })
+                };
+                let number = |pointer: Option<&str>| {
+                    field(pointer).and_then(|value| value.parse::<usize>().ok())
+                };
+                let Some(line) = number(Some(&mapping.line)) else {
+                    continue;
+                };
+                let column = number(mapping.column.as_deref()).unwrap_or(1);
+                let start = location(line, column);
+                let end = location(
+                    number(mapping.end_line.as_deref()).unwrap_or(line),
+                    number(mapping.end_column.as_deref()).unwrap_or(column),
+                );
+                add(
+                    &field(mapping.file.as_deref()).unwrap_or(path.to_string()),
+                    field(mapping.severity.as_deref())
+                        .map(|severity| parse_severity(&severity))
+                        .unwrap_or(DiagnosticSeverity::Warning),
+                    field(mapping.code.as_deref()).unwrap_or_default(),
+                    field(Some(&mapping.message)).unwrap_or_default(),
+                    start,
+                    end,
+                );
+            }
+        }
+        LintOutput::Regex(pattern) => {
+            let regex = match Regex::new(pattern) {
+                Ok(regex) => regex,
+                Err(err) => {
+                    tracing::warn!("Invalid lint regex {}: {}", pattern, err);
+                    return files;
+                }
+            };
+            for captures in stdout.lines().filter_map(|line| regex.captures(line)) {
+                let group = |name: &str| captures.name(name).map(|group| group.as_str());
+                let number = |name: &str| group(name).and_then(|value| value.parse().ok());
+                let Some(line) = number("line") else {
+                    continue;
+                };
+                let column = number("column").unwrap_or(1);
+                let start = location(line, column);
+                let end = location(
+                    number("end_line").unwrap_or(line),
+                    number("end_column").unwrap_or(column),
+                );
+                add(
+                    group("file").unwrap_or(path),
+                    group("severity")
+                        .map(parse_severity)
+                        .unwrap_or(DiagnosticSeverity::Warning),
+                    group("code").unwrap_or_default().to_string(),
+                    group("message").unwrap_or_default().to_string(),
+                    start,
+                    end,
+                );
+            }
+        }
+    }
+    files
+}
+
+/// Severity of a level name or number as printed by common linters
+fn parse_severity(severity: &str) -> DiagnosticSeverity {
+    match severity.to_lowercase().as_str() {
+        "error" | "fatal" | "e" | "2" => DiagnosticSeverity::Error,
+        "info" | "information" | "note" | "i" => DiagnosticSeverity::Information,
+        "hint" | "style" | "h" => DiagnosticSeverity::Hint,
+        _ => DiagnosticSeverity::Warning,
+    }
+}
+
+/// Cursor of a one based line and column
+fn location(line: usize, column: usize) -> Cursor {
+    Cursor {
+        row: line.saturating_sub(1),
+        column: column.saturating_sub(1),
+    }
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    message: Option<CargoDiagnostic>,
+}
+
+#[derive(Deserialize)]
+struct CargoDiagnostic {
+    message: String,
+    level: String,
+    code: Option<CargoCode>,
+    spans: Vec<CargoSpan>,
+}
+
+#[derive(Deserialize)]
+struct CargoCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct CargoSpan {
+    file_name: String,
+    line_start: usize,
+    line_end: usize,
+    column_start: usize,
+    column_end: usize,
+    is_primary: bool,
+}
+
+#[derive(Deserialize)]
+struct RuffMessage {
+    code: Option<String>,
+    message: String,
+    filename: String,
+    location: RuffLocation,
+    end_location: RuffLocation,
+}
+
+#[derive(Deserialize)]
+struct RuffLocation {
+    row: usize,
+    column: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EslintResult {
+    file_path: String,
+    messages: Vec<EslintMessage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EslintMessage {
+    rule_id: Option<String>,
+    severity: u8,
+    message: String,
+    line: usize,
+    column: usize,
+    end_line: Option<usize>,
+    end_column: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{diagnostics_key, parse_lint_output, JsonMapping, LintOutput};
+    use crate::lsp::types::DiagnosticSeverity;
+
+    #[test]
+    fn parse_linter_json() {
+        let stdout = r#"{"reason":"compiler-artifact","package_id":"x"}
+{"reason":"compiler-message","message":{"message":"unused variable: `x`","level":"warning","code":{"code":"unused_variables"},"spans":[{"file_name":"src/main.rs","line_start":2,"line_end":2,"column_start":9,"column_end":10,"is_primary":true}]}}"#;
+        let files = parse_lint_output(
+            &LintOutput::CargoJson,
+            stdout,
+            "/project/src/main.rs",
+            "/project",
+            "cargo",
+        );
+        let diagnostics = &files[&diagnostics_key(Path::new("/project/src/main.rs"))];
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostics[0].code, "unused_variables");
+        assert_eq!(
+            (
+                diagnostics[0].range.mark.row,
+                diagnostics[0].range.mark.column
+            ),
+            (1, 8)
+        );
+
+        let stdout = r#"[{"code":"F401","message":"`os` imported but unused","filename":"/project/main.py",
+            "location":{"row":1,"column":8},"end_location":{"row":1,"column":10}}]"#;
+        let files = parse_lint_output(
+            &LintOutput::RuffJson,
+            stdout,
+            "/project/main.py",
+            "/project",
+            "ruff",
+        );
+        let diagnostics = &files[&diagnostics_key(Path::new("/project/main.py"))];
+        assert_eq!(diagnostics[0].range.cursor.column, 9);
+    }
+
+    #[test]
+    fn parse_mapped_output() {
+        let shellcheck = JsonMapping {
+            file: Some("/file".into()),
+            line: "/line".into(),
+            column: Some("/column".into()),
+            end_line: Some("/endLine".into()),
+            end_column: Some("/endColumn".into()),
+            severity: Some("/level".into()),
+            code: Some("/code".into()),
+            message: "/message".into(),
+            ..Default::default()
+        };
+        let stdout = r#"[{"file":"run.sh","line":3,"endLine":3,"column":6,"endColumn":10,
+            "level":"error","code":2086,"message":"Double quote to prevent globbing"}]"#;
+        let files = parse_lint_output(
+            &LintOutput::Json(shellcheck),
+            stdout,
+            "/project/run.sh",
+            "/project",
+            "shellcheck",
+        );
+        let diagnostics = &files[&diagnostics_key(Path::new("/project/run.sh"))];
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Error);
+        assert_eq!(diagnostics[0].code, "2086");
+        assert_eq!(
+            (
+                diagnostics[0].range.mark.row,
+                diagnostics[0].range.cursor.column
+            ),
+            (2, 9)
+        );
+
+        let regex = r"^(?P<line>\d+):(?P<column>\d+): (?P<severity>\w+) (?P<message>.*)$";
+        let files = parse_lint_output(
+            &LintOutput::Regex(regex.into()),
+            "12:4: warning unused import\nnot a diagnostic\n",
+            "/project/main.py",
+            "/project",
+            "lint",
+        );
+        let diagnostics = &files[&diagnostics_key(Path::new("/project/main.py"))];
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert_eq!(diagnostics[0].message, "unused import");
+        assert_eq!(diagnostics[0].range.mark.row, 11);
+    }
+}
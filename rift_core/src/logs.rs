@@ -0,0 +1,359 @@
+//! Log viewer buffer tailing the tracing output of the editor,
+//! with severity coloring and filtering by level and target.
+
+use std::{
+    collections::HashSet,
+    io::{Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+
+use crate::{
+    buffer::{
+        instance::{Attribute, Cursor, HighlightType, Range, Selection},
+        line_buffer::LineBuffer,
+    },
+    state::EditorState,
+};
+
+/// Directory and name of the log file written by the frontends
+pub const LOG_DIR: &str = "logs";
+pub const LOG_FILE_NAME: &str = "rift.log";
+
+/// Entries kept in the viewer, older entries are dropped
+const MAX_ENTRIES: usize = 5000;
+
+pub fn log_file_path() -> PathBuf {
+    PathBuf::from(LOG_DIR).join(LOG_FILE_NAME)
+}
+
+/// Tracing levels from most to least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn parse(level: &str) -> Option<Self> {
+        match level.to_ascii_uppercase().as_str() {
+            "ERROR" => Some(Self::Error),
+            "WARN" => Some(Self::Warn),
+            "INFO" => Some(Self::Info),
+            "DEBUG" => Some(Self::Debug),
+            "TRACE" => Some(Self::Trace),
+            _ => None,
+        }
+    }
+
+    fn highlight_type(&self) -> HighlightType {
+        match self {
+            Self::Error => HighlightType::Red,
+            Self::Warn => HighlightType::Yellow,
+            Self::Info => HighlightType::Green,
+            Self::Debug => HighlightType::Blue,
+            Self::Trace => HighlightType::Gray,
+        }
+    }
+}
+
+/// Line starting a log entry with byte ranges of the level and target
+pub struct LogHeader<'a> {
+    pub level: LogLevel,
+    pub target: &'a str,
+    pub level_range: (usize, usize),
+    pub target_range: (usize, usize),
+}
+
+/// Header of a line starting a log entry, such as
+/// `2024-01-01T00:00:00.000000Z  INFO rift_core::lsp::client: message`
+pub fn parse_header(line: &str) -> Option<LogHeader<'_>> {
+    let mut words = line
+        .split_whitespace()
+        .map(|word| (word.as_ptr() as usize - line.as_ptr() as usize, word));
+    let (_, timestamp) = words.next()?;
+    if !timestamp.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let (level_start, level_word) = words.next()?;
+    let level = LogLevel::parse(level_word)?;
+    let (target_start, target_word) = words.next()?;
+    let target = target_word.strip_suffix(':')?;
+    Some(LogHeader {
+        level,
+        target,
+        level_range: (level_start, level_start + level_word.len()),
+        target_range: (target_start, target_start + target.len()),
+    })
+}
+
+/// Shows entries at least as severe as the level and with a target containing the text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogFilter {
+    pub level: Option<LogLevel>,
+    pub target: Option<String>,
+}
+
+impl LogFilter {
+    /// Filter from words such as `warn lsp`, levels and targets can be given in any order
+    pub fn parse(input: &str) -> Self {
+        let mut filter = Self::default();
+        for word in input.split_whitespace() {
+            match LogLevel::parse(word) {
+                Some(level) => filter.level = Some(level),
+                None => filter.target = Some(word.to_string()),
+            }
+        }
+        filter
+    }
+
+    pub fn matches(&self, level: LogLevel, target: &str) -> bool {
+        self.level.is_none_or(|max_level| level <= max_level)
+            && self
+                .target
+                .as_ref()
+                .is_none_or(|filter_target| target.contains(filter_target.as_str()))
+    }
+}
+
+/// Log entry with its continuation lines
+struct LogEntry {
+    level: LogLevel,
+    target: String,
+    lines: Vec<String>,
+}
+
+pub struct LogViewer {
+    pub buffer_id: u32,
+    pub filter: LogFilter,
+    entries: Vec<LogEntry>,
+    /// Bytes of the log file read so far
+    offset: u64,
+    /// Incomplete last line of the log file
+    partial: String,
+    /// Buffer version and spans of the severity coloring
+    segments: Option<(usize, Vec<Range>)>,
+}
+
+impl LogViewer {
+    pub fn new(buffer_id: u32) -> Self {
+        Self {
+            buffer_id,
+            filter: LogFilter::default(),
+            entries: vec![],
+            offset: 0,
+            partial: String::new(),
+            segments: None,
+        }
+    }
+
+    /// Append log text, returns true if entries were added
+    fn push_text(&mut self, text: &str) -> bool {
+        self.partial.push_str(text);
+        let Some(end) = self.partial.rfind('\n') else {
+            return false;
+        };
+        let complete: String = self.partial.drain(..=end).collect();
+        for line in complete.lines() {
+            match parse_header(line) {
+                Some(header) => self.entries.push(LogEntry {
+                    level: header.level,
+                    target: header.target.to_string(),
+                    lines: vec![line.to_string()],
+                }),
+                None => match self.entries.last_mut() {
+                    Some(entry) => entry.lines.push(line.to_string()),
+                    None => self.entries.push(LogEntry {
+                        level: LogLevel::Info,
+                        target: String::new(),
+                        lines: vec![line.to_string()],
+                    }),
+                },
+            }
+        }
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+        true
+    }
+
+    /// Lines of the entries matching the filter
+    fn visible_text(&self) -> String {
+        self.entries
+            .iter()
+            .filter(|entry| self.filter.matches(entry.level, &entry.target))
+            .flat_map(|entry| &entry.lines)
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Severity coloring of the log buffer as segments for `get_visible_lines`
+    pub fn segments(&mut self, buffer: &LineBuffer) -> Vec<Range> {
+        if let Some((version, segments)) = &self.segments {
+            if *version == buffer.version {
+                return segments.clone();
+            }
+        }
+
+        let mut segments = vec![];
+        for (row, line) in buffer.lines.iter().enumerate() {
+            let Some(header) = parse_header(line) else {
+                continue;
+            };
+            let line_start = buffer.byte_index_from_row(row, "\n");
+            let highlight_type = header.level.highlight_type();
+            segments.push(Range {
+                start: line_start + header.level_range.0,
+                end: line_start + header.level_range.1,
+                attributes: HashSet::from([Attribute::Highlight(highlight_type)]),
+            });
+            segments.push(Range {
+                start: line_start + header.target_range.0,
+                end: line_start + header.target_range.1,
+                attributes: HashSet::from([Attribute::Highlight(HighlightType::Gray)]),
+            });
+            // Messages of errors and warnings are colored as well
+            if header.level <= LogLevel::Warn {
+                segments.push(Range {
+                    start: line_start + header.target_range.1 + 1,
+                    end: line_start + line.len(),
+                    attributes: HashSet::from([Attribute::Highlight(highlight_type)]),
+                });
+            }
+        }
+        self.segments = Some((buffer.version, segments.clone()));
+        segments
+    }
+}
+
+/// Read new output from the log file, returns true if there was any
+fn read_log_file(viewer: &mut LogViewer) -> bool {
+    let Ok(mut file) = std::fs::File::open(log_file_path()) else {
+        return false;
+    };
+    let Ok(len) = file.metadata().map(|metadata| metadata.len()) else {
+        return false;
+    };
+    // The log file was truncated or replaced
+    if len < viewer.offset {
+        viewer.offset = 0;
+        viewer.partial.clear();
+        viewer.entries.clear();
+    }
+    if len == viewer.offset || file.seek(SeekFrom::Start(viewer.offset)).is_err() {
+        return false;
+    }
+    let mut bytes = vec![];
+    if file.read_to_end(&mut bytes).is_err() {
+        return false;
+    }
+    viewer.offset += bytes.len() as u64;
+    viewer.push_text(&String::from_utf8_lossy(&bytes))
+}
+
+/// Replace the content of the log buffer with the entries matching the filter.
+/// The cursor follows new output while it is on the last line.
+fn refresh_buffer(state: &mut EditorState) {
+    let Some(viewer) = &state.log_viewer else {
+        return;
+    };
+    let buffer_id = viewer.buffer_id;
+    let text = viewer.visible_text();
+    let (buffer, instance) = state.get_buffer_by_id_mut(buffer_id);
+    let following = instance.cursor.row + 2 >= buffer.get_num_lines();
+    let last_row = buffer.get_num_lines() - 1;
+    buffer.remove_text_no_log(&Selection {
+        mark: Cursor { row: 0, column: 0 },
+        cursor: Cursor {
+            row: last_row,
+            column: buffer.get_line_length(last_row),
+        },
+    });
+    let end = buffer.insert_text_no_log(&text, &Cursor { row: 0, column: 0 });
+    buffer.version += 1;
+    buffer.modified = false;
+
+    let last_row = end.row;
+    let cursor = if following {
+        Cursor {
+            row: last_row,
+            column: 0,
+        }
+    } else {
+        Cursor {
+            row: instance.cursor.row.min(last_row),
+            column: 0,
+        }
+    };
+    instance.cursor = cursor;
+    instance.selection.cursor = cursor;
+    instance.selection.mark = cursor;
+    instance.column_level = 0;
+    state.update_view = true;
+}
+
+/// Open the log viewer buffer, or switch to it if it is already open
+pub fn show_logs(state: &mut EditorState) {
+    if let Some(viewer) = &state.log_viewer {
+        if state.buffers.contains_key(&viewer.buffer_id) {
+            state.buffer_idx = Some(viewer.buffer_id);
+            return;
+        }
+    }
+    let buffer_id = state.add_buffer(LineBuffer::new(String::new(), None));
+    state.log_viewer = Some(LogViewer::new(buffer_id));
+    state.buffer_idx = Some(buffer_id);
+    poll_logs(state);
+    refresh_buffer(state);
+}
+
+pub fn set_filter(state: &mut EditorState, filter: LogFilter) {
+    let Some(viewer) = &mut state.log_viewer else {
+        return;
+    };
+    viewer.filter = filter;
+    refresh_buffer(state);
+}
+
+/// Tail the log file while the log viewer is open
+pub fn poll_logs(state: &mut EditorState) {
+    let Some(viewer) = &mut state.log_viewer else {
+        return;
+    };
+    if !state.buffers.contains_key(&viewer.buffer_id) {
+        state.log_viewer = None;
+        return;
+    }
+    if read_log_file(viewer) {
+        refresh_buffer(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_header, LogFilter, LogLevel, LogViewer};
+
+    #[test]
+    fn parse_and_filter_logs() {
+        let line = "2024-01-01T00:00:00.000000Z  WARN rift_core::lsp::client: no server";
+        let header = parse_header(line).unwrap();
+        assert_eq!(header.level, LogLevel::Warn);
+        assert_eq!(header.target, "rift_core::lsp::client");
+        assert_eq!(&line[header.level_range.0..header.level_range.1], "WARN");
+        assert!(parse_header("  continuation").is_none());
+
+        let mut viewer = LogViewer::new(0);
+        viewer.push_text(line);
+        assert!(viewer.entries.is_empty());
+        viewer.push_text("\n  detail\n2024-01-01T00:00:01.000000Z  INFO rift_core::ai: ready\n");
+        assert_eq!(viewer.entries.len(), 2);
+        assert_eq!(viewer.entries[0].lines.len(), 2);
+
+        viewer.filter = LogFilter::parse("warn lsp");
+        assert_eq!(viewer.visible_text(), format!("{}\n  detail\n", line));
+        viewer.filter = LogFilter::parse("ai");
+        assert!(viewer.visible_text().ends_with("ready\n"));
+    }
+}
@@ -1,22 +1,27 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde_json::{json, Value};
 use std::{
-    collections::HashMap,
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
     process::{self, Stdio},
     sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
 };
 use tokio::{
     io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, BufWriter},
-    process::Command,
+    process::{Child, Command},
     sync::mpsc::{self, Receiver, Sender},
 };
 
-use crate::buffer::instance::Cursor;
+use crate::buffer::instance::{Cursor, Selection};
 
 use super::types;
 
 static ID: AtomicUsize = AtomicUsize::new(0);
 
+/// Number of response times kept for each server
+const LATENCY_SAMPLES: usize = 32;
+
 fn next_id() -> usize {
     ID.fetch_add(1, Ordering::SeqCst)
 }
@@ -25,6 +30,8 @@ fn next_id() -> usize {
 pub enum IncomingMessage {
     Response(types::ResponseMessage),
     Notification(types::NotificationMessage),
+    /// Request from the server which expects a response
+    Request(types::RequestMessage),
 }
 
 pub struct Request {
@@ -48,16 +55,63 @@ pub enum OutgoingMessage {
     Response(Response),
 }
 
-pub struct LSPClientHandle {
+/// Connection to a single language server process
+pub struct LSPServer {
+    pub name: String,
     pub sender: Sender<OutgoingMessage>,
     pub reciever: Receiver<IncomingMessage>,
-    pub pending_id: usize,
-    pub pending_requests: HashMap<usize, IncomingMessage>,
+    /// Capabilities from the initialize response
+    pub capabilities: Value,
+    pub pid: Option<u32>,
+    /// Response times of the latest requests
+    pub latencies: VecDeque<Duration>,
+    /// Killed when the server is dropped
+    _child: Option<Child>,
+}
+
+/// Request sent to one or more servers whose responses are merged
+struct PendingRequest {
+    method: String,
+    sent: Instant,
+    /// Servers yet to respond
+    waiting: Vec<usize>,
+    results: Vec<Value>,
+    error: Option<types::ResponseError>,
+}
+
+/// Edits of a document not yet sent to the servers
+struct DocumentChanges {
+    version: usize,
+    /// Incremental changes in the order they were made
+    changes: Vec<Value>,
+    /// Content after the changes, for servers which only support full sync
+    content: Option<String>,
+    last_change: Instant,
+}
+
+/// Language servers attached to the buffers of a language.
+/// Notifications are sent to every server and requests to the servers supporting them,
+/// their responses are merged into a single response.
+pub struct LSPClientHandle {
+    pub servers: Vec<LSPServer>,
     pub id_method: HashMap<usize, String>,
+    /// Ids of the requests in the order they were sent, responses are returned in this order
+    order: VecDeque<usize>,
+    pending_requests: HashMap<usize, PendingRequest>,
+    /// Requests from servers by the id given to them, along with the server and its own id
+    server_requests: HashMap<usize, (usize, usize)>,
+    /// Diagnostics last published by each server per document
+    diagnostics: HashMap<String, Vec<Value>>,
+    /// Server polled first for messages, rotated so that a busy server does not starve the others
+    next_server: usize,
+    /// Changes are sent once no edits were made to the document for this long,
+    /// or before the next request
+    pub change_debounce: Duration,
+    pending_changes: RefCell<HashMap<String, DocumentChanges>>,
 }
 
 /// Starts lsp
-pub async fn start_lsp(program: &str, args: &[&str]) -> Result<LSPClientHandle> {
+pub async fn start_lsp(program: &str, args: &[&str]) -> Result<LSPServer> {
     let mut command = Command::new(program);
 
     #[cfg(target_os = "windows")]
@@ -71,8 +125,8 @@ pub async fn start_lsp(program: &str, args: &[&str]) -> Result<LSPClientHandle>
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .spawn()
-        .unwrap();
+        .kill_on_drop(true)
+        .spawn()?;
 
     let stdin = child.stdin.take().unwrap();
     let stdout = child.stdout.take().unwrap();
@@ -127,33 +181,50 @@ pub async fn start_lsp(program: &str, args: &[&str]) -> Result<LSPClientHandle>
         let mut reader = BufReader::new(stdout);
         let mut header = String::new();
         while let Ok(bytes_read) = reader.read_line(&mut header).await {
-            if bytes_read > 0 {
-                // Read empty line
-                reader.read_line(&mut String::new()).await.unwrap();
-
-                // Parse content length from header
-                let content_length = header.strip_prefix("Content-Length: ").unwrap();
-                let content_length: usize = content_length.trim().parse().unwrap();
-
-                // Parse content body
-                let mut body = vec![0; content_length];
-                reader.read_exact(&mut body).await.unwrap();
-                let body = String::from_utf8_lossy(&body);
-                let body: Value = serde_json::from_str(&body).unwrap();
-
-                // If id is present then it is a response
-                if let Some(_id) = body.get("id") {
-                    let response: types::ResponseMessage = serde_json::from_value(body).unwrap();
-                    itx.send(IncomingMessage::Response(response)).await.unwrap();
+            // Server exited
+            if bytes_read == 0 {
+                break;
+            }
+
+            // Read empty line
+            if reader.read_line(&mut String::new()).await.is_err() {
+                break;
+            }
+
+            // Parse content length from header
+            let Some(content_length) = header
+                .strip_prefix("Content-Length: ")
+                .and_then(|content_length| content_length.trim().parse::<usize>().ok())
+            else {
+                tracing::error!("Malformed lsp header: {}", header);
+                header.clear();
+                continue;
+            };
+            header.clear();
+
+            // Parse content body
+            let mut body = vec![0; content_length];
+            if reader.read_exact(&mut body).await.is_err() {
+                break;
+            }
+            let body = String::from_utf8_lossy(&body);
+            let message = serde_json::from_str::<Value>(&body).and_then(|body| {
+                // If id is present then it is a response, or a request if method is present too
+                if body.get("id").is_some() && body.get("method").is_some() {
+                    serde_json::from_value(body).map(IncomingMessage::Request)
+                } else if body.get("id").is_some() {
+                    serde_json::from_value(body).map(IncomingMessage::Response)
                 } else {
-                    let notification: types::NotificationMessage =
-                        serde_json::from_value(body).unwrap();
-                    itx.send(IncomingMessage::Notification(notification))
-                        .await
-                        .unwrap();
+                    serde_json::from_value(body).map(IncomingMessage::Notification)
                 }
-
-                header = String::new();
+            });
+            match message {
+                Ok(message) => {
+                    if itx.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                Err(err) => tracing::error!("Malformed lsp message: {}\n{}", err, body),
             }
         }
     });
@@ -163,184 +234,522 @@ pub async fn start_lsp(program: &str, args: &[&str]) -> Result<LSPClientHandle>
         let mut reader = BufReader::new(stderr);
         let mut line = String::new();
         while let Ok(bytes_read) = reader.read_line(&mut line).await {
-            if bytes_read > 0 {
-                tracing::error!("{}", line);
+            if bytes_read == 0 {
+                break;
             }
+            tracing::error!("{}", line);
+            line.clear();
         }
     });
 
-    Ok(LSPClientHandle {
+    Ok(LSPServer {
+        name: program.to_string(),
         sender: outgoing_tx,
         reciever: incoming_rx,
-        pending_id: 0,
-        pending_requests: HashMap::new(),
-        id_method: HashMap::new(),
+        capabilities: Value::Null,
+        pid: child.id(),
+        latencies: VecDeque::new(),
+        _child: Some(child),
     })
 }
 
-impl LSPClientHandle {
-    pub async fn send_request(&mut self, method: String, params: Option<Value>) -> Result<()> {
-        let id = next_id();
-        self.id_method.insert(id, method.clone());
-        self.sender
-            .send(OutgoingMessage::Request(Request { method, params, id }))
-            .await?;
-        Ok(())
+/// Server capability required by the method, None if every server handles it
+fn required_capability(method: &str) -> Option<&'static str> {
+    Some(match method {
+        "textDocument/hover" => "hoverProvider",
+        "textDocument/completion" => "completionProvider",
+        "textDocument/signatureHelp" => "signatureHelpProvider",
+        "textDocument/codeAction" => "codeActionProvider",
+        "textDocument/formatting" => "documentFormattingProvider",
+        "textDocument/foldingRange" => "foldingRangeProvider",
+        "textDocument/prepareCallHierarchy"
+        | "callHierarchy/incomingCalls"
+        | "callHierarchy/outgoingCalls" => "callHierarchyProvider",
+        "workspace/executeCommand" => "executeCommandProvider",
+        "textDocument/diagnostic" => "diagnosticProvider",
+        _ => return None,
+    })
+}
+
+/// Combine the results of the servers, lists are concatenated
+/// and otherwise the first result is used
+fn merge_results(method: &str, results: Vec<Value>) -> Value {
+    match method {
+        "textDocument/completion" => {
+            let mut incomplete = false;
+            let mut items = vec![];
+            for result in results {
+                match result {
+                    Value::Array(result_items) => items.extend(result_items),
+                    Value::Object(mut list) => {
+                        incomplete |= list
+                            .get("isIncomplete")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false);
+                        if let Some(Value::Array(result_items)) = list.remove("items") {
+                            items.extend(result_items);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if items.is_empty() {
+                return Value::Null;
+            }
+            json!({ "isIncomplete": incomplete, "items": items })
+        }
+        // Reports are always full as no previous result ids are sent
+        "textDocument/diagnostic" => {
+            let items: Vec<Value> = results
+                .iter()
+                .filter_map(|result| result.get("items").and_then(Value::as_array))
+                .flatten()
+                .cloned()
+                .collect();
+            json!({ "kind": "full", "items": items })
+        }
+        "textDocument/codeAction"
+        | "textDocument/prepareCallHierarchy"
+        | "callHierarchy/incomingCalls"
+        | "callHierarchy/outgoingCalls" => {
+            let items: Vec<Value> = results
+                .into_iter()
+                .filter_map(|result| match result {
+                    Value::Array(items) => Some(items),
+                    _ => None,
+                })
+                .flatten()
+                .collect();
+            if items.is_empty() {
+                Value::Null
+            } else {
+                Value::Array(items)
+            }
+        }
+        _ => results
+            .into_iter()
+            .find(|result| !result.is_null())
+            .unwrap_or_default(),
+    }
+}
+
+impl LSPServer {
+    /// Returns false once the server has exited and all its messages are handled
+    pub fn is_running(&self) -> bool {
+        !(self.reciever.is_closed() && self.reciever.is_empty())
+    }
+
+    /// Text document sync kind of the server: 0 for none, 1 for full and 2 for incremental
+    fn sync_kind(&self) -> u64 {
+        match self.capabilities.get("textDocumentSync") {
+            Some(Value::Number(kind)) => kind.as_u64().unwrap_or(1),
+            Some(options) => options.get("change").and_then(Value::as_u64).unwrap_or(0),
+            None if self.capabilities.is_null() => 1,
+            None => 0,
+        }
     }
 
-    pub fn send_request_sync(&mut self, method: String, params: Option<Value>) -> Result<()> {
+    /// Returns true if the server advertised the capability, or has not advertised any
+    fn supports(&self, method: &str, params: &Option<Value>) -> bool {
+        if self.capabilities.is_null() {
+            return true;
+        }
+        let Some(capability) = required_capability(method) else {
+            return true;
+        };
+        match self.capabilities.get(capability) {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+            // Commands are only sent to the server providing them
+            Some(provider) if method == "workspace/executeCommand" => {
+                let command = params.as_ref().and_then(|params| params.get("command"));
+                provider
+                    .get("commands")
+                    .and_then(Value::as_array)
+                    .is_some_and(|commands| command.is_some_and(|c| commands.contains(c)))
+            }
+            Some(_) => true,
+        }
+    }
+
+    /// Send the initialize request and wait for its response
+    pub fn initialize_sync(
+        &mut self,
+        root: String,
+        initialization_options: Option<Value>,
+    ) -> Result<()> {
         let id = next_id();
-        self.id_method.insert(id, method.clone());
         self.sender
-            .blocking_send(OutgoingMessage::Request(Request { method, params, id }))?;
+            .blocking_send(OutgoingMessage::Request(Request {
+                id,
+                method: "initialize".to_string(),
+                params: Some(LSPClientHandle::get_initialization_params(
+                    root,
+                    initialization_options,
+                )),
+            }))?;
+
+        loop {
+            match self.reciever.blocking_recv() {
+                Some(IncomingMessage::Response(response)) if response.id == id => {
+                    tracing::info!("{:#?}", response);
+                    if let Some(error) = response.error {
+                        bail!("{}", error.message);
+                    }
+                    self.capabilities = response
+                        .result
+                        .and_then(|mut result| result.get_mut("capabilities").map(Value::take))
+                        .unwrap_or_default();
+                    break;
+                }
+                Some(_) => {}
+                None => bail!("Exited during initialization"),
+            }
+        }
+        self.sender
+            .blocking_send(OutgoingMessage::Notification(Notification {
+                method: "initialized".to_string(),
+                params: None,
+            }))?;
         Ok(())
     }
+}
 
-    pub async fn send_response(
+impl LSPClientHandle {
+    pub fn new(servers: Vec<LSPServer>) -> Self {
+        Self {
+            servers,
+            id_method: HashMap::new(),
+            order: VecDeque::new(),
+            pending_requests: HashMap::new(),
+            server_requests: HashMap::new(),
+            diagnostics: HashMap::new(),
+            next_server: 0,
+            change_debounce: Duration::ZERO,
+            pending_changes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns false once all servers have exited and all their messages are handled
+    pub fn is_running(&self) -> bool {
+        self.servers.iter().any(LSPServer::is_running)
+    }
+
+    /// Number of requests waiting for responses
+    pub fn pending_count(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Queue an edit of the document replacing the range with the text, the columns of
+    /// the range are in UTF-16 code units. The content of the document is only computed
+    /// if a server needs full sync.
+    pub fn did_change(
         &self,
-        id: usize,
-        result: Option<Value>,
-        error: Option<types::ResponseError>,
-    ) -> Result<()> {
-        self.sender
-            .send(OutgoingMessage::Response(Response { id, result, error }))
-            .await?;
+        document_path: String,
+        version: usize,
+        range: Selection,
+        text: &str,
+        content: impl FnOnce() -> String,
+    ) {
+        let full_sync = self
+            .servers
+            .iter()
+            .any(|server| server.is_running() && server.sync_kind() == 1);
+        let mut pending_changes = self.pending_changes.borrow_mut();
+        let document = pending_changes
+            .entry(document_path)
+            .or_insert_with(|| DocumentChanges {
+                version,
+                changes: vec![],
+                content: None,
+                last_change: Instant::now(),
+            });
+        document.version = version;
+        document.last_change = Instant::now();
+        document
+            .changes
+            .push(LSPClientHandle::incremental_change(range, text));
+        if full_sync {
+            document.content = Some(content());
+        }
+    }
+
+    /// Returns true if edits are waiting to be sent
+    pub fn has_pending_changes(&self) -> bool {
+        !self.pending_changes.borrow().is_empty()
+    }
+
+    /// Send the queued edits, only those which are past the debounce window unless forced
+    pub fn flush_changes(&self, force: bool) -> Result<()> {
+        let mut pending_changes = self.pending_changes.borrow_mut();
+        let due: Vec<String> = pending_changes
+            .iter()
+            .filter(|(_, document)| force || document.last_change.elapsed() >= self.change_debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in due {
+            let document = pending_changes.remove(&path).unwrap();
+            for server in self.servers.iter().filter(|server| server.is_running()) {
+                // Servers prefer incremental changes which are sent in a single notification
+                let changes = match (server.sync_kind(), &document.content) {
+                    (2, _) => document.changes.clone(),
+                    (1, Some(content)) => vec![LSPClientHandle::full_change(content.clone())],
+                    _ => continue,
+                };
+                server
+                    .sender
+                    .blocking_send(OutgoingMessage::Notification(Notification {
+                        method: "textDocument/didChange".to_string(),
+                        params: Some(LSPClientHandle::did_change_text_document(
+                            path.clone(),
+                            document.version,
+                            changes,
+                        )),
+                    }))?;
+            }
+        }
         Ok(())
     }
 
+    /// Returns true if a running server supports the method
+    pub fn supports(&self, method: &str) -> bool {
+        self.servers
+            .iter()
+            .any(|server| server.is_running() && server.supports(method, &None))
+    }
+
+    /// Send the request to the servers supporting it, a null result is returned if there are none.
+    /// Returns the id of the request.
+    pub fn send_request_sync(&mut self, method: String, params: Option<Value>) -> Result<usize> {
+        // Requests are answered for the latest content
+        self.flush_changes(true)?;
+        let id = next_id();
+        self.id_method.insert(id, method.clone());
+        let mut waiting = vec![];
+        for (idx, server) in self.servers.iter().enumerate() {
+            if server.is_running() && server.supports(&method, &params) {
+                server
+                    .sender
+                    .blocking_send(OutgoingMessage::Request(Request {
+                        id,
+                        method: method.clone(),
+                        params: params.clone(),
+                    }))?;
+                waiting.push(idx);
+            }
+        }
+        self.order.push_back(id);
+        self.pending_requests.insert(
+            id,
+            PendingRequest {
+                method,
+                sent: Instant::now(),
+                waiting,
+                results: vec![],
+                error: None,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Respond to a request received from one of the servers
     pub fn send_response_sync(
-        &self,
+        &mut self,
         id: usize,
         result: Option<Value>,
         error: Option<types::ResponseError>,
     ) -> Result<()> {
-        self.sender
+        let Some((server, id)) = self.server_requests.remove(&id) else {
+            bail!("Unknown request {}", id);
+        };
+        self.servers[server]
+            .sender
             .blocking_send(OutgoingMessage::Response(Response { id, result, error }))?;
         Ok(())
     }
 
-    pub async fn send_notification(&self, method: String, params: Option<Value>) -> Result<()> {
-        self.sender
-            .send(OutgoingMessage::Notification(Notification {
-                method,
-                params,
-            }))
-            .await?;
-        Ok(())
-    }
-
     pub fn send_notification_sync(&self, method: String, params: Option<Value>) -> Result<()> {
-        self.sender
-            .blocking_send(OutgoingMessage::Notification(Notification {
-                method,
-                params,
-            }))?;
+        self.flush_changes(true)?;
+        for server in self.servers.iter().filter(|server| server.is_running()) {
+            server
+                .sender
+                .blocking_send(OutgoingMessage::Notification(Notification {
+                    method: method.clone(),
+                    params: params.clone(),
+                }))?;
+        }
         Ok(())
     }
 
-    pub async fn recv_message(&mut self) -> Option<IncomingMessage> {
-        if let Some(message) = self.reciever.recv().await {
-            match &message {
-                IncomingMessage::Response(response) => {
-                    self.pending_requests.insert(response.id, message);
-                }
-                IncomingMessage::Notification(_notification) => {
-                    return Some(message);
+    pub fn recv_message_sync(&mut self) -> Option<IncomingMessage> {
+        if let Err(err) = self.flush_changes(false) {
+            tracing::error!("Failed to send changes: {}", err);
+        }
+        let count = self.servers.len();
+        for offset in 0..count {
+            let server = (self.next_server + offset) % count;
+            while let Ok(message) = self.servers[server].reciever.try_recv() {
+                match message {
+                    IncomingMessage::Response(response) => self.add_response(server, response),
+                    IncomingMessage::Notification(notification) => {
+                        self.next_server = (server + 1) % count;
+                        return Some(IncomingMessage::Notification(
+                            self.merge_diagnostics(server, notification),
+                        ));
+                    }
+                    IncomingMessage::Request(mut request) => {
+                        self.next_server = (server + 1) % count;
+                        let id = next_id();
+                        self.server_requests.insert(id, (server, request.id));
+                        request.id = id;
+                        return Some(IncomingMessage::Request(request));
+                    }
                 }
             }
         }
 
-        if self.pending_requests.contains_key(&self.pending_id) {
-            let message = self.pending_requests.remove(&self.pending_id);
-            self.pending_id += 1;
-            return message;
+        // Servers which exited will not respond
+        for pending in self.pending_requests.values_mut() {
+            pending
+                .waiting
+                .retain(|server| self.servers[*server].is_running());
+        }
+
+        let id = *self.order.front()?;
+        if !self.pending_requests.get(&id)?.waiting.is_empty() {
+            return None;
         }
-        None
+        self.order.pop_front();
+        let pending = self.pending_requests.remove(&id)?;
+        // Errors are only returned if no server responded successfully
+        let (result, error) = match pending.error {
+            Some(error) if pending.results.is_empty() => (None, Some(error)),
+            _ => (Some(merge_results(&pending.method, pending.results)), None),
+        };
+        Some(IncomingMessage::Response(types::ResponseMessage {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }))
     }
 
-    pub fn recv_message_sync(&mut self) -> Option<IncomingMessage> {
-        if let Ok(message) = self.reciever.try_recv() {
-            match &message {
-                IncomingMessage::Response(response) => {
-                    self.pending_requests.insert(response.id, message);
-                }
-                IncomingMessage::Notification(_notification) => {
-                    return Some(message);
-                }
+    fn add_response(&mut self, server: usize, response: types::ResponseMessage) {
+        let Some(pending) = self.pending_requests.get_mut(&response.id) else {
+            return;
+        };
+        pending.waiting.retain(|waiting| *waiting != server);
+        let latencies = &mut self.servers[server].latencies;
+        if latencies.len() == LATENCY_SAMPLES {
+            latencies.pop_front();
+        }
+        latencies.push_back(pending.sent.elapsed());
+        match response.error {
+            Some(error) => {
+                tracing::error!("{}: {:#?}", self.servers[server].name, error);
+                pending.error.get_or_insert(error);
             }
+            None => pending.results.push(response.result.unwrap_or_default()),
         }
+    }
 
-        if self.pending_requests.contains_key(&self.pending_id) {
-            let message = self.pending_requests.remove(&self.pending_id);
-            self.pending_id += 1;
-            return message;
+    /// Diagnostics of the document from all servers, in place of those published by one of them
+    fn merge_diagnostics(
+        &mut self,
+        server: usize,
+        mut notification: types::NotificationMessage,
+    ) -> types::NotificationMessage {
+        if notification.method != "textDocument/publishDiagnostics" || self.servers.len() < 2 {
+            return notification;
         }
-        None
+        let Some(params) = notification.params.as_mut() else {
+            return notification;
+        };
+        let Some(uri) = params.get("uri").and_then(Value::as_str) else {
+            return notification;
+        };
+        let published = self
+            .diagnostics
+            .entry(uri.to_string())
+            .or_insert_with(|| vec![Value::Array(vec![]); self.servers.len()]);
+        published[server] = params
+            .get_mut("diagnostics")
+            .map(Value::take)
+            .unwrap_or_default();
+        params["diagnostics"] = Value::Array(
+            published
+                .iter()
+                .filter_map(Value::as_array)
+                .flatten()
+                .cloned()
+                .collect(),
+        );
+        notification
     }
 
-    pub fn get_initialization_params(&self, workspace_folder: String) -> Value {
+    pub fn get_initialization_params(root: String, initialization_options: Option<Value>) -> Value {
         json!({
             "processId": process::id(),
-            "rootUri": format!("file:///{}", workspace_folder),
+            "rootUri": format!("file:///{}", root),
+            "initializationOptions": initialization_options,
             "capabilities": {
                 "textDocument": {
                     "completion": {
                         "completionItem": {
                             "documentationFormat": ["plaintext"],
+                            "snippetSupport": true,
                             // "insertReplaceSupport": false,
                         },
                     },
                     "hover": {
                         "contentFormat": ["plaintext"],
-                    }
-                }
+                    },
+                    "signatureHelp": {
+                        "signatureInformation": {
+                            "documentationFormat": ["plaintext"],
+                            "parameterInformation": {
+                                "labelOffsetSupport": true,
+                            },
+                            "activeParameterSupport": true,
+                        },
+                    },
+                    "callHierarchy": {
+                        "dynamicRegistration": false,
+                    },
+                    "diagnostic": {
+                        "dynamicRegistration": false,
+                    },
+                    "codeAction": {
+                        "codeActionLiteralSupport": {
+                            "codeActionKind": {
+                                "valueSet": [
+                                    "",
+                                    "quickfix",
+                                    "refactor",
+                                    "refactor.extract",
+                                    "refactor.inline",
+                                    "refactor.rewrite",
+                                    "source",
+                                    "source.organizeImports",
+                                ],
+                            },
+                        },
+                    },
+                },
+                "workspace": {
+                    "diagnostics": {
+                        "refreshSupport": false,
+                    },
+                    "applyEdit": true,
+                    "workspaceEdit": {
+                        "documentChanges": true,
+                        "resourceOperations": ["create", "rename", "delete"],
+                        "failureHandling": "abort",
+                    },
+                },
             }
         })
     }
 
-    /// Send initialize request and wait for response
-    pub async fn init_lsp(&mut self, workspace_folder: String) {
-        self.send_request(
-            "initialize".to_string(),
-            Some(self.get_initialization_params(workspace_folder)),
-        )
-        .await
-        .unwrap();
-
-        loop {
-            if let Some(response) = self.recv_message().await {
-                if let IncomingMessage::Response(message) = response {
-                    tracing::info!("{:#?}", message);
-                    self.send_notification("initialized".to_string(), None)
-                        .await
-                        .unwrap();
-                    break;
-                }
-                break;
-            }
-        }
-    }
-
-    /// Send initialize request and wait for response
-    pub fn init_lsp_sync(&mut self, workspace_folder: String) {
-        self.send_request_sync(
-            "initialize".to_string(),
-            Some(self.get_initialization_params(workspace_folder)),
-        )
-        .unwrap();
-
-        loop {
-            if let Some(response) = self.recv_message_sync() {
-                if let IncomingMessage::Response(message) = response {
-                    tracing::info!("{:#?}", message);
-                    self.send_notification_sync("initialized".to_string(), None)
-                        .unwrap();
-                }
-                break;
-            }
-        }
-    }
-
     /// DidOpenTextDocument Notification
     /// method: 'textDocument/didOpen'
     pub fn did_open_text_document(document_path: String, document_content: String) -> Value {
@@ -359,28 +768,39 @@ impl LSPClientHandle {
     pub fn did_change_text_document(
         document_path: String,
         document_version: usize,
-        // range: Selection,
-        text: String,
+        content_changes: Vec<Value>,
     ) -> Value {
-        // let (start, end) = range.in_order();
         json!({
             "textDocument": {
                 "uri": format!("file:///{}", document_path),
                 "version": document_version,
             },
-            "contentChanges": [{
-                // "range": {
-                //     "start": {
-                //         "line": start.row,
-                //         "character": start.column,
-                //     },
-                //     "end": {
-                //         "line": end.row,
-                //         "character": end.column,
-                //     },
-                // },
-                "text": text,
-            }],
+            "contentChanges": content_changes,
+        })
+    }
+
+    /// Change replacing the whole document
+    pub fn full_change(text: String) -> Value {
+        json!({
+            "text": text,
+        })
+    }
+
+    /// Change replacing a range of the document
+    pub fn incremental_change(range: Selection, text: &str) -> Value {
+        let (start, end) = range.in_order();
+        json!({
+            "range": {
+                "start": {
+                    "line": start.row,
+                    "character": start.column,
+                },
+                "end": {
+                    "line": end.row,
+                    "character": end.column,
+                },
+            },
+            "text": text,
         })
     }
 
@@ -398,6 +818,20 @@ impl LSPClientHandle {
         })
     }
 
+    /// Signature Help Request
+    /// method: 'textDocument/signatureHelp'
+    pub fn signature_help_request(document_path: String, cursor: Cursor) -> Value {
+        json!({
+            "textDocument": {
+                "uri": format!("file:///{}", document_path),
+            },
+            "position": {
+                "line": cursor.row,
+                "character": cursor.column,
+            },
+        })
+    }
+
     /// Completion Request
     /// method: 'textDocument/completion'
     pub fn completion_request(document_path: String, cursor: Cursor) -> Value {
@@ -412,18 +846,285 @@ impl LSPClientHandle {
         })
     }
 
+    /// Folding Range Request
+    /// method: 'textDocument/foldingRange'
+    pub fn folding_range_request(document_path: String) -> Value {
+        json!({
+            "textDocument": {
+                "uri": format!("file:///{}", document_path),
+            },
+        })
+    }
+
+    /// Document Diagnostic Request
+    /// method: 'textDocument/diagnostic'
+    pub fn document_diagnostic_request(document_path: String) -> Value {
+        json!({
+            "textDocument": {
+                "uri": format!("file:///{}", document_path),
+            },
+        })
+    }
+
+    /// Prepare Call Hierarchy Request
+    /// method: 'textDocument/prepareCallHierarchy'
+    pub fn prepare_call_hierarchy_request(document_path: String, cursor: Cursor) -> Value {
+        json!({
+            "textDocument": {
+                "uri": format!("file:///{}", document_path),
+            },
+            "position": {
+                "line": cursor.row,
+                "character": cursor.column,
+            },
+        })
+    }
+
+    /// Incoming / Outgoing Calls Request
+    /// method: 'callHierarchy/incomingCalls' or 'callHierarchy/outgoingCalls'
+    pub fn calls_request(item: &Value) -> Value {
+        json!({
+            "item": item,
+        })
+    }
+
+    /// Code Action Request
+    /// method: 'textDocument/codeAction'
+    pub fn code_action_request(
+        document_path: String,
+        range: Selection,
+        diagnostics: Vec<Value>,
+    ) -> Value {
+        let (start, end) = range.in_order();
+        json!({
+            "textDocument": {
+                "uri": format!("file:///{}", document_path),
+            },
+            "range": {
+                "start": {
+                    "line": start.row,
+                    "character": start.column,
+                },
+                "end": {
+                    "line": end.row,
+                    "character": end.column,
+                },
+            },
+            "context": {
+                "diagnostics": diagnostics,
+            },
+        })
+    }
+
+    /// Execute Command Request
+    /// method: 'workspace/executeCommand'
+    pub fn execute_command_request(command: &types::Command) -> Value {
+        json!({
+            "command": command.command,
+            "arguments": command.arguments,
+        })
+    }
+
     /// Formatting Request
     /// method: 'textDocument/formatting'
-    pub fn formatting_request(document_path: String, tab_size: usize) -> Value {
+    pub fn formatting_request(
+        document_path: String,
+        tab_size: usize,
+        insert_spaces: bool,
+    ) -> Value {
         json!({
             "textDocument": {
                 "uri": format!("file:///{}", document_path),
             },
             "options": {
                 "tabSize": tab_size,
-                "insertSpaces": true,
+                "insertSpaces": insert_spaces,
                 "trimTrailingWhitespace": true,
             },
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, time::Duration};
+
+    use serde_json::{json, Value};
+    use tokio::sync::mpsc::{self, Receiver, Sender};
+
+    use super::{IncomingMessage, LSPClientHandle, LSPServer, OutgoingMessage};
+    use crate::{
+        buffer::instance::{Cursor, Selection},
+        lsp::types::{NotificationMessage, RequestMessage, ResponseMessage},
+    };
+
+    fn server(
+        capabilities: Value,
+    ) -> (
+        LSPServer,
+        Receiver<OutgoingMessage>,
+        Sender<IncomingMessage>,
+    ) {
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(8);
+        let (incoming_tx, incoming_rx) = mpsc::channel(8);
+        let server = LSPServer {
+            name: String::new(),
+            sender: outgoing_tx,
+            reciever: incoming_rx,
+            capabilities,
+            pid: None,
+            latencies: VecDeque::new(),
+            _child: None,
+        };
+        (server, outgoing_rx, incoming_tx)
+    }
+
+    fn respond(sender: &Sender<IncomingMessage>, id: usize, result: Value) {
+        sender
+            .blocking_send(IncomingMessage::Response(ResponseMessage {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result: Some(result),
+                error: None,
+            }))
+            .unwrap();
+    }
+
+    #[test]
+    fn multiplexed_servers() {
+        let (first, mut first_out, first_in) = server(json!({"completionProvider": {}}));
+        let (second, mut second_out, second_in) =
+            server(json!({"completionProvider": {}, "hoverProvider": true}));
+        let mut handle = LSPClientHandle::new(vec![first, second]);
+
+        // Hover is only sent to the server supporting it
+        handle
+            .send_request_sync("textDocument/hover".to_string(), None)
+            .unwrap();
+        assert!(first_out.try_recv().is_err());
+        let Ok(OutgoingMessage::Request(hover)) = second_out.try_recv() else {
+            panic!("hover not sent");
+        };
+
+        handle
+            .send_request_sync("textDocument/completion".to_string(), None)
+            .unwrap();
+        let Ok(OutgoingMessage::Request(completion)) = first_out.try_recv() else {
+            panic!("completion not sent");
+        };
+        assert!(second_out.try_recv().is_ok());
+        respond(&first_in, completion.id, json!([{"label": "a"}]));
+        respond(
+            &second_in,
+            completion.id,
+            json!({"isIncomplete": true, "items": [{"label": "b"}]}),
+        );
+        // Responses are returned in the order of the requests
+        assert!(handle.recv_message_sync().is_none());
+        respond(&second_in, hover.id, json!({"contents": "docs"}));
+        let Some(IncomingMessage::Response(response)) = handle.recv_message_sync() else {
+            panic!("no hover response");
+        };
+        assert_eq!(response.result.unwrap()["contents"], "docs");
+        let Some(IncomingMessage::Response(response)) = handle.recv_message_sync() else {
+            panic!("no completion response");
+        };
+        let result = response.result.unwrap();
+        assert_eq!(result["isIncomplete"], true);
+        assert_eq!(result["items"].as_array().unwrap().len(), 2);
+        assert_eq!(handle.servers[0].latencies.len(), 1);
+        assert_eq!(handle.servers[1].latencies.len(), 2);
+
+        let publish = |sender: &Sender<IncomingMessage>, messages: &[&str]| {
+            let diagnostics: Vec<Value> = messages
+                .iter()
+                .map(|message| json!({"message": message}))
+                .collect();
+            sender
+                .blocking_send(IncomingMessage::Notification(NotificationMessage {
+                    jsonrpc: "2.0".to_string(),
+                    method: "textDocument/publishDiagnostics".to_string(),
+                    params: Some(json!({"uri": "file:///a.rs", "diagnostics": diagnostics})),
+                }))
+                .unwrap();
+        };
+        publish(&first_in, &["x", "y"]);
+        handle.recv_message_sync().unwrap();
+        publish(&second_in, &["z"]);
+        let Some(IncomingMessage::Notification(notification)) = handle.recv_message_sync() else {
+            panic!("no diagnostics");
+        };
+        assert_eq!(
+            notification.params.unwrap()["diagnostics"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // Responses to server requests go back to the server which sent them
+        second_in
+            .blocking_send(IncomingMessage::Request(RequestMessage {
+                jsonrpc: "2.0".to_string(),
+                id: 7,
+                method: "workspace/applyEdit".to_string(),
+                params: None,
+            }))
+            .unwrap();
+        let Some(IncomingMessage::Request(request)) = handle.recv_message_sync() else {
+            panic!("no server request");
+        };
+        handle
+            .send_response_sync(request.id, Some(Value::Null), None)
+            .unwrap();
+        let Ok(OutgoingMessage::Response(response)) = second_out.try_recv() else {
+            panic!("response not sent");
+        };
+        assert_eq!(response.id, 7);
+        assert!(first_out.try_recv().is_err());
+    }
+
+    #[test]
+    fn batched_changes() {
+        let (incremental, mut incremental_out, _incremental_in) =
+            server(json!({"textDocumentSync": {"openClose": true, "change": 2}}));
+        let (full, mut full_out, _full_in) = server(json!({"textDocumentSync": 1}));
+        let mut handle = LSPClientHandle::new(vec![incremental, full]);
+        handle.change_debounce = Duration::from_secs(60);
+
+        let cursor = Cursor { row: 0, column: 1 };
+        let range = Selection {
+            cursor,
+            mark: cursor,
+        };
+        handle.did_change("a.rs".to_string(), 2, range, "b", || "ab".to_string());
+        handle.did_change("a.rs".to_string(), 3, range, "c", || "acb".to_string());
+        // Nothing is sent within the debounce window
+        assert!(handle.recv_message_sync().is_none());
+        assert!(incremental_out.try_recv().is_err());
+        assert!(handle.has_pending_changes());
+
+        // Requests send the pending changes first
+        handle
+            .send_request_sync("textDocument/hover".to_string(), None)
+            .unwrap();
+        assert!(!handle.has_pending_changes());
+        let Ok(OutgoingMessage::Notification(change)) = incremental_out.try_recv() else {
+            panic!("changes not sent");
+        };
+        let params = change.params.unwrap();
+        assert_eq!(params["textDocument"]["version"], 3);
+        assert_eq!(params["contentChanges"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            params["contentChanges"][1]["range"]["start"]["character"],
+            1
+        );
+        let Ok(OutgoingMessage::Notification(change)) = full_out.try_recv() else {
+            panic!("changes not sent");
+        };
+        assert_eq!(
+            change.params.unwrap()["contentChanges"],
+            json!([{"text": "acb"}])
+        );
+    }
+}
@@ -1,2 +1,3 @@
 pub mod client;
+pub mod servers;
 pub mod types;
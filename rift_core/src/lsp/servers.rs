@@ -0,0 +1,148 @@
+//! Language servers started for each language, configured in the preferences.
+
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use serde_json::Value;
+
+use crate::{buffer::instance::Language, lsp::client::LSPClientHandle};
+
+/// Command starting a language server which communicates over stdio
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LanguageServerConfig {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Sent as `initializationOptions` in the initialize request
+    #[serde(default)]
+    pub initialization_options: Option<Value>,
+    /// Files or folders marking the root of a project, the workspace folder is used if none is found
+    #[serde(default)]
+    pub root_markers: Vec<String>,
+}
+
+impl LanguageServerConfig {
+    fn new(program: &str, args: &[&str], root_markers: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            initialization_options: None,
+            root_markers: root_markers
+                .iter()
+                .map(|marker| marker.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Servers attached to the buffers of each language, each given as candidates
+/// of which the first one which starts is used
+pub fn default_language_servers() -> HashMap<Language, Vec<Vec<LanguageServerConfig>>> {
+    HashMap::from([(
+        Language::Rust,
+        vec![vec![LanguageServerConfig::new(
+            "rust-analyzer",
+            &[],
+            &["Cargo.toml"],
+        )]],
+    )])
+}
+
+/// Closest folder containing the file with one of the markers, or the workspace folder
+pub fn find_root(path: &str, markers: &[String], workspace_folder: &str) -> String {
+    Path::new(path)
+        .ancestors()
+        .skip(1)
+        .find(|folder| markers.iter().any(|marker| folder.join(marker).exists()))
+        .and_then(|folder| folder.to_str())
+        .map(|folder| folder.to_string())
+        .unwrap_or_else(|| workspace_folder.to_string())
+}
+
+/// Process, capabilities and response times of the servers of the language
+pub fn status_report(language: Language, lsp_handle: &LSPClientHandle) -> String {
+    let mut report = format!("Language servers for {:?}\n", language);
+    for server in &lsp_handle.servers {
+        writeln!(report, "\n{}", server.name).unwrap();
+        match server.pid {
+            Some(pid) => writeln!(report, "  PID: {}", pid).unwrap(),
+            None => report.push_str("  PID: -\n"),
+        }
+        let status = if server.is_running() {
+            "running"
+        } else {
+            "exited"
+        };
+        writeln!(report, "  Status: {}", status).unwrap();
+        match (server.latencies.back(), server.latencies.len()) {
+            (Some(last), count) => {
+                let average = server.latencies.iter().sum::<std::time::Duration>() / count as u32;
+                writeln!(
+                    report,
+                    "  Latency: {:.2?} last, {:.2?} average of {} requests",
+                    last, average, count
+                )
+                .unwrap();
+            }
+            (None, _) => report.push_str("  Latency: -\n"),
+        }
+        writeln!(
+            report,
+            "  Capabilities: {}",
+            capability_names(&server.capabilities).join(", ")
+        )
+        .unwrap();
+    }
+    writeln!(report, "\nPending requests: {}", lsp_handle.pending_count()).unwrap();
+    report
+}
+
+/// Names of the capabilities the server provides
+fn capability_names(capabilities: &Value) -> Vec<&str> {
+    let Some(capabilities) = capabilities.as_object() else {
+        return vec![];
+    };
+    let mut names: Vec<&str> = capabilities
+        .iter()
+        .filter(|(_, value)| !matches!(value, Value::Null | Value::Bool(false)))
+        .map(|(name, _)| name.strip_suffix("Provider").unwrap_or(name))
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{capability_names, find_root};
+
+    #[test]
+    fn language_server_root() {
+        let root = std::env::temp_dir().join(format!("rift_lsp_root_{}", std::process::id()));
+        let src = root.join("crate").join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(root.join("crate").join("Cargo.toml"), "").unwrap();
+        let file = src.join("main.rs");
+        let file = file.to_str().unwrap();
+
+        let markers = vec!["Cargo.toml".to_string()];
+        assert_eq!(
+            find_root(file, &markers, "/workspace"),
+            root.join("crate").to_str().unwrap()
+        );
+        assert_eq!(find_root(file, &[], "/workspace"), "/workspace");
+        assert_eq!(
+            find_root(file, &["missing.toml".to_string()], "/workspace"),
+            "/workspace"
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+
+        let capabilities = json!({"hoverProvider": true, "renameProvider": false,
+            "completionProvider": {}, "positionEncoding": "utf-16"});
+        assert_eq!(
+            capability_names(&capabilities),
+            vec!["completion", "hover", "positionEncoding"]
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod protocol;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -37,26 +39,29 @@ pub struct NotificationMessage {
     pub params: Option<Value>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CompletionItem {
     pub label: String,
     pub edit: TextEdit,
+    /// The text of the edit is in snippet syntax
+    pub snippet: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextEdit {
     pub text: String,
     pub range: Selection,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PublishDiagnostics {
     pub uri: String,
     pub version: usize,
     pub diagnostics: Vec<Diagnostic>,
 }
 
-#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+/// Diagnostic severity, ordered from the most severe
+#[derive(Debug, Clone, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum DiagnosticSeverity {
     Error,
     Warning,
@@ -64,7 +69,7 @@ pub enum DiagnosticSeverity {
     Hint,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Diagnostic {
     pub range: Selection,
     pub severity: DiagnosticSeverity,
@@ -72,3 +77,90 @@ pub struct Diagnostic {
     pub source: String,
     pub message: String,
 }
+
+/// Change to a file of a workspace edit, paths are local paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DocumentChange {
+    Edit {
+        path: String,
+        edits: Vec<TextEdit>,
+    },
+    Create {
+        path: String,
+        overwrite: bool,
+        ignore_if_exists: bool,
+    },
+    Rename {
+        old_path: String,
+        new_path: String,
+        overwrite: bool,
+        ignore_if_exists: bool,
+    },
+    Delete {
+        path: String,
+        recursive: bool,
+        ignore_if_not_exists: bool,
+    },
+}
+
+/// Changes of a workspace edit in the order they are applied
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkspaceEdit {
+    pub changes: Vec<DocumentChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Command {
+    pub command: String,
+    pub arguments: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeAction {
+    pub title: String,
+    pub edit: WorkspaceEdit,
+    /// Command executed by the language server after the edit is applied
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallHierarchyItem {
+    pub name: String,
+    pub detail: String,
+    pub path: String,
+    /// Range of the name of the symbol
+    pub range: Selection,
+    /// Item as received, sent back when requesting its calls
+    pub item: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureInformation {
+    pub label: String,
+    /// Byte ranges of the parameters in the label
+    pub parameters: Vec<(usize, usize)>,
+    /// Overrides the active parameter of the signature help
+    pub active_parameter: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureHelp {
+    pub signatures: Vec<SignatureInformation>,
+    pub active_signature: usize,
+    pub active_parameter: usize,
+}
+
+impl SignatureHelp {
+    /// Label of the active signature and the byte range of its active parameter
+    pub fn active(&self) -> Option<(&str, Option<(usize, usize)>)> {
+        let signature = self
+            .signatures
+            .get(self.active_signature)
+            .or(self.signatures.first())?;
+        let parameter = signature.active_parameter.unwrap_or(self.active_parameter);
+        Some((
+            &signature.label,
+            signature.parameters.get(parameter).copied(),
+        ))
+    }
+}
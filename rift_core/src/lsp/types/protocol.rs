@@ -0,0 +1,794 @@
+//! Wire format of the LSP payloads handled by the editor.
+//! Payloads are deserialized fallibly so that servers deviating from the spec
+//! produce an error instead of a crash.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::buffer::{
+    folding::Fold,
+    instance::{Cursor, Selection},
+};
+
+use super::{
+    CallHierarchyItem, CodeAction, Command, CompletionItem, Diagnostic, DiagnosticSeverity,
+    DocumentChange, PublishDiagnostics, SignatureHelp, SignatureInformation, TextEdit,
+    WorkspaceEdit,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl From<Position> for Cursor {
+    fn from(position: Position) -> Self {
+        Cursor {
+            row: position.line,
+            column: position.character,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl From<Range> for Selection {
+    fn from(range: Range) -> Self {
+        Selection {
+            cursor: range.end.into(),
+            mark: range.start.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspTextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+impl From<LspTextEdit> for TextEdit {
+    fn from(edit: LspTextEdit) -> Self {
+        TextEdit {
+            text: edit.new_text,
+            range: edit.range.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InsertReplaceEdit {
+    pub new_text: String,
+    pub replace: Range,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CompletionTextEdit {
+    Edit(LspTextEdit),
+    InsertReplace(InsertReplaceEdit),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspCompletionItem {
+    pub label: String,
+    #[serde(default)]
+    pub insert_text: Option<String>,
+    #[serde(default)]
+    pub text_edit: Option<CompletionTextEdit>,
+    #[serde(default)]
+    pub insert_text_format: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CompletionResponse {
+    List { items: Vec<LspCompletionItem> },
+    Items(Vec<LspCompletionItem>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum MarkedString {
+    String(String),
+    Markup { value: String },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum HoverContents {
+    Single(MarkedString),
+    Multiple(Vec<MarkedString>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Hover {
+    pub contents: HoverContents,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum DiagnosticCode {
+    Number(i64),
+    String(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LspDiagnostic {
+    pub range: Range,
+    #[serde(default)]
+    pub severity: Option<u64>,
+    #[serde(default)]
+    pub code: Option<DiagnosticCode>,
+    #[serde(default)]
+    pub source: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PublishDiagnosticsParams {
+    pub uri: String,
+    #[serde(default)]
+    pub version: Option<usize>,
+    pub diagnostics: Vec<LspDiagnostic>,
+}
+
+/// Report of a pull diagnostics response, items are missing if unchanged
+#[derive(Debug, Deserialize)]
+pub struct DocumentDiagnosticReport {
+    pub kind: String,
+    #[serde(default)]
+    pub items: Vec<LspDiagnostic>,
+}
+
+/// Text of a hover response
+pub fn parse_hover(result: Value) -> Result<String> {
+    let hover: Hover = serde_json::from_value(result).context("Malformed hover response")?;
+    let text = |marked: MarkedString| match marked {
+        MarkedString::String(value) | MarkedString::Markup { value } => value,
+    };
+    Ok(match hover.contents {
+        HoverContents::Single(marked) => text(marked),
+        HoverContents::Multiple(items) => {
+            items.into_iter().map(text).collect::<Vec<_>>().join("\n\n")
+        }
+    })
+}
+
+/// Completion items of a completion response.
+/// Items without an edit insert their text at the cursor.
+pub fn parse_completion(result: Value, cursor: Cursor) -> Result<Vec<CompletionItem>> {
+    let response: CompletionResponse =
+        serde_json::from_value(result).context("Malformed completion response")?;
+    let items = match response {
+        CompletionResponse::List { items } | CompletionResponse::Items(items) => items,
+    };
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            let edit = match item.text_edit {
+                Some(CompletionTextEdit::Edit(edit)) => edit.into(),
+                Some(CompletionTextEdit::InsertReplace(edit)) => TextEdit {
+                    text: edit.new_text,
+                    range: edit.replace.into(),
+                },
+                None => TextEdit {
+                    text: item.insert_text.unwrap_or_else(|| item.label.clone()),
+                    range: Selection {
+                        cursor,
+                        mark: cursor,
+                    },
+                },
+            };
+            CompletionItem {
+                label: item.label,
+                edit,
+                snippet: item.insert_text_format == Some(2),
+            }
+        })
+        .collect())
+}
+
+/// Text edits of a formatting response
+pub fn parse_text_edits(result: Value) -> Result<Vec<TextEdit>> {
+    let edits: Option<Vec<LspTextEdit>> =
+        serde_json::from_value(result).context("Malformed text edits")?;
+    Ok(edits
+        .unwrap_or_default()
+        .into_iter()
+        .map(TextEdit::from)
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoldingRange {
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Folds of a folding range response
+pub fn parse_folding_ranges(result: Value) -> Result<Vec<Fold>> {
+    let ranges: Option<Vec<FoldingRange>> =
+        serde_json::from_value(result).context("Malformed folding ranges")?;
+    Ok(ranges
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|range| range.end_line > range.start_line)
+        .map(|range| Fold {
+            start: range.start_line,
+            end: range.end_line,
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LspCommand {
+    pub title: String,
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Vec<Value>,
+}
+
+impl From<LspCommand> for Command {
+    fn from(command: LspCommand) -> Self {
+        Command {
+            command: command.command,
+            arguments: command.arguments,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextDocumentIdentifier {
+    pub uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDocumentEdit {
+    pub text_document: TextDocumentIdentifier,
+    pub edits: Vec<LspTextEdit>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOperationOptions {
+    #[serde(default)]
+    pub overwrite: bool,
+    #[serde(default)]
+    pub ignore_if_exists: bool,
+    #[serde(default)]
+    pub recursive: bool,
+    #[serde(default)]
+    pub ignore_if_not_exists: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ResourceOperation {
+    Create {
+        uri: String,
+        #[serde(default)]
+        options: FileOperationOptions,
+    },
+    #[serde(rename_all = "camelCase")]
+    Rename {
+        old_uri: String,
+        new_uri: String,
+        #[serde(default)]
+        options: FileOperationOptions,
+    },
+    Delete {
+        uri: String,
+        #[serde(default)]
+        options: FileOperationOptions,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum LspDocumentChange {
+    Edit(TextDocumentEdit),
+    Resource(ResourceOperation),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspWorkspaceEdit {
+    #[serde(default)]
+    pub changes: Option<HashMap<String, Vec<LspTextEdit>>>,
+    #[serde(default)]
+    pub document_changes: Option<Vec<LspDocumentChange>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyWorkspaceEditParams {
+    pub edit: LspWorkspaceEdit,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LspCodeAction {
+    pub title: String,
+    #[serde(default)]
+    pub edit: Option<LspWorkspaceEdit>,
+    #[serde(default)]
+    pub command: Option<LspCommand>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CodeActionOrCommand {
+    Command(LspCommand),
+    CodeAction(LspCodeAction),
+}
+
+/// Workspace edit with local paths, document changes are preferred over changes
+fn parse_workspace_edit(edit: LspWorkspaceEdit) -> Result<WorkspaceEdit> {
+    let edits = |edits: Vec<LspTextEdit>| edits.into_iter().map(TextEdit::from).collect();
+    let mut changes = vec![];
+    if let Some(document_changes) = edit.document_changes {
+        for change in document_changes {
+            changes.push(match change {
+                LspDocumentChange::Edit(change) => DocumentChange::Edit {
+                    path: uri_to_path(&change.text_document.uri)?,
+                    edits: edits(change.edits),
+                },
+                LspDocumentChange::Resource(ResourceOperation::Create { uri, options }) => {
+                    DocumentChange::Create {
+                        path: uri_to_path(&uri)?,
+                        overwrite: options.overwrite,
+                        ignore_if_exists: options.ignore_if_exists,
+                    }
+                }
+                LspDocumentChange::Resource(ResourceOperation::Rename {
+                    old_uri,
+                    new_uri,
+                    options,
+                }) => DocumentChange::Rename {
+                    old_path: uri_to_path(&old_uri)?,
+                    new_path: uri_to_path(&new_uri)?,
+                    overwrite: options.overwrite,
+                    ignore_if_exists: options.ignore_if_exists,
+                },
+                LspDocumentChange::Resource(ResourceOperation::Delete { uri, options }) => {
+                    DocumentChange::Delete {
+                        path: uri_to_path(&uri)?,
+                        recursive: options.recursive,
+                        ignore_if_not_exists: options.ignore_if_not_exists,
+                    }
+                }
+            });
+        }
+    } else {
+        let mut files: Vec<(String, Vec<LspTextEdit>)> =
+            edit.changes.unwrap_or_default().into_iter().collect();
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (uri, file_edits) in files {
+            changes.push(DocumentChange::Edit {
+                path: uri_to_path(&uri)?,
+                edits: edits(file_edits),
+            });
+        }
+    }
+    Ok(WorkspaceEdit { changes })
+}
+
+/// Workspace edit of a workspace/applyEdit request
+pub fn parse_apply_edit(params: Value) -> Result<WorkspaceEdit> {
+    let params: ApplyWorkspaceEditParams =
+        serde_json::from_value(params).context("Malformed workspace edit")?;
+    parse_workspace_edit(params.edit)
+}
+
+/// Code actions and commands of a code action response
+pub fn parse_code_actions(result: Value) -> Result<Vec<CodeAction>> {
+    let actions: Option<Vec<CodeActionOrCommand>> =
+        serde_json::from_value(result).context("Malformed code actions")?;
+    actions
+        .unwrap_or_default()
+        .into_iter()
+        .map(|action| {
+            Ok(match action {
+                CodeActionOrCommand::Command(command) => CodeAction {
+                    title: command.title.clone(),
+                    edit: WorkspaceEdit::default(),
+                    command: Some(command.into()),
+                },
+                CodeActionOrCommand::CodeAction(action) => CodeAction {
+                    title: action.title,
+                    edit: match action.edit {
+                        Some(edit) => parse_workspace_edit(edit)?,
+                        None => WorkspaceEdit::default(),
+                    },
+                    command: action.command.map(Command::from),
+                },
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspCallHierarchyItem {
+    pub name: String,
+    #[serde(default)]
+    pub detail: Option<String>,
+    pub uri: String,
+    pub selection_range: Range,
+}
+
+fn parse_call_hierarchy_item(item: Value) -> Result<CallHierarchyItem> {
+    let parsed: LspCallHierarchyItem =
+        serde_json::from_value(item.clone()).context("Malformed call hierarchy item")?;
+    Ok(CallHierarchyItem {
+        name: parsed.name,
+        detail: parsed.detail.unwrap_or_default(),
+        path: uri_to_path(&parsed.uri)?,
+        range: parsed.selection_range.into(),
+        item,
+    })
+}
+
+/// Items of a prepare call hierarchy response
+pub fn parse_call_hierarchy_items(result: Value) -> Result<Vec<CallHierarchyItem>> {
+    let items: Option<Vec<Value>> =
+        serde_json::from_value(result).context("Malformed call hierarchy items")?;
+    items
+        .unwrap_or_default()
+        .into_iter()
+        .map(parse_call_hierarchy_item)
+        .collect()
+}
+
+/// Callers of an incoming calls response or callees of an outgoing calls response
+pub fn parse_calls(result: Value, incoming: bool) -> Result<Vec<CallHierarchyItem>> {
+    let calls: Option<Vec<Value>> = serde_json::from_value(result).context("Malformed calls")?;
+    let field = if incoming { "from" } else { "to" };
+    calls
+        .unwrap_or_default()
+        .into_iter()
+        .map(|mut call| {
+            let item = call
+                .get_mut(field)
+                .map(Value::take)
+                .with_context(|| format!("Call without {}", field))?;
+            parse_call_hierarchy_item(item)
+        })
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ParameterLabel {
+    String(String),
+    /// UTF-16 offsets into the signature label
+    Offsets([usize; 2]),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ParameterInformation {
+    pub label: ParameterLabel,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspSignatureInformation {
+    pub label: String,
+    #[serde(default)]
+    pub parameters: Vec<ParameterInformation>,
+    #[serde(default)]
+    pub active_parameter: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LspSignatureHelp {
+    pub signatures: Vec<LspSignatureInformation>,
+    #[serde(default)]
+    pub active_signature: Option<usize>,
+    #[serde(default)]
+    pub active_parameter: Option<usize>,
+}
+
+/// Byte offset of the UTF-16 offset into the text
+fn utf16_to_byte(text: &str, offset: usize) -> usize {
+    let mut units = 0;
+    for (idx, c) in text.char_indices() {
+        if units >= offset {
+            return idx;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Byte column of a UTF-16 offset into the line, None if the offset is past
+/// the end of the line or inside a character
+pub fn utf16_to_byte_column(line: &str, offset: usize) -> Option<usize> {
+    let mut units = 0;
+    for (idx, c) in line.char_indices() {
+        if units >= offset {
+            return (units == offset).then_some(idx);
+        }
+        units += c.len_utf16();
+    }
+    (units == offset).then_some(line.len())
+}
+
+/// UTF-16 offset into the line of a byte column
+pub fn byte_to_utf16_column(line: &str, column: usize) -> usize {
+    line.get(..column).unwrap_or(line).encode_utf16().count()
+}
+
+/// Signatures of a signature help response, None if there is no signature
+pub fn parse_signature_help(result: Value) -> Result<Option<SignatureHelp>> {
+    let help: Option<LspSignatureHelp> =
+        serde_json::from_value(result).context("Malformed signature help")?;
+    let Some(help) = help.filter(|help| !help.signatures.is_empty()) else {
+        return Ok(None);
+    };
+    let signatures = help
+        .signatures
+        .into_iter()
+        .map(|signature| {
+            // Labels given as strings are searched for after the previous parameter
+            let mut search_start = 0;
+            let parameters = signature
+                .parameters
+                .into_iter()
+                .filter_map(|parameter| {
+                    let range = match parameter.label {
+                        ParameterLabel::String(label) => {
+                            let start =
+                                search_start + signature.label[search_start..].find(&label)?;
+                            (start, start + label.len())
+                        }
+                        ParameterLabel::Offsets([start, end]) => (
+                            utf16_to_byte(&signature.label, start),
+                            utf16_to_byte(&signature.label, end),
+                        ),
+                    };
+                    search_start = range.1;
+                    Some(range)
+                })
+                .collect();
+            SignatureInformation {
+                label: signature.label,
+                parameters,
+                active_parameter: signature.active_parameter,
+            }
+        })
+        .collect();
+    Ok(Some(SignatureHelp {
+        signatures,
+        active_signature: help.active_signature.unwrap_or(0),
+        active_parameter: help.active_parameter.unwrap_or(0),
+    }))
+}
+
+/// Diagnostic in the format sent to the language server
+pub fn diagnostic_to_lsp(diagnostic: &Diagnostic) -> Value {
+    let (start, end) = diagnostic.range.in_order();
+    json!({
+        "range": {
+            "start": { "line": start.row, "character": start.column },
+            "end": { "line": end.row, "character": end.column },
+        },
+        "severity": match diagnostic.severity {
+            DiagnosticSeverity::Error => 1,
+            DiagnosticSeverity::Warning => 2,
+            DiagnosticSeverity::Information => 3,
+            DiagnosticSeverity::Hint => 4,
+        },
+        "code": diagnostic.code,
+        "source": diagnostic.source,
+        "message": diagnostic.message,
+    })
+}
+
+/// Local path of a file uri
+pub fn uri_to_path(uri: &str) -> Result<String> {
+    let path = url::Url::parse(uri)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+        .with_context(|| format!("Unsupported uri: {}", uri))?;
+    #[allow(unused_mut)]
+    let mut path = path.to_str().context("Non unicode path")?.to_string();
+    #[cfg(target_os = "windows")]
+    {
+        path = path.to_lowercase();
+    }
+    Ok(path)
+}
+
+/// Diagnostics of a publishDiagnostics notification keyed by local path
+pub fn parse_publish_diagnostics(params: Value) -> Result<PublishDiagnostics> {
+    let params: PublishDiagnosticsParams =
+        serde_json::from_value(params).context("Malformed diagnostics")?;
+    Ok(PublishDiagnostics {
+        uri: uri_to_path(&params.uri)?,
+        version: params.version.unwrap_or(0),
+        diagnostics: params
+            .diagnostics
+            .into_iter()
+            .map(convert_diagnostic)
+            .collect(),
+    })
+}
+
+/// Diagnostics of a pull diagnostics response, None if they are unchanged
+pub fn parse_document_diagnostics(result: Value) -> Result<Option<Vec<Diagnostic>>> {
+    let report: DocumentDiagnosticReport =
+        serde_json::from_value(result).context("Malformed diagnostic report")?;
+    if report.kind == "unchanged" {
+        return Ok(None);
+    }
+    Ok(Some(
+        report.items.into_iter().map(convert_diagnostic).collect(),
+    ))
+}
+
+fn convert_diagnostic(diagnostic: LspDiagnostic) -> Diagnostic {
+    Diagnostic {
+        range: diagnostic.range.into(),
+        severity: match diagnostic.severity.unwrap_or(1) {
+            2 => DiagnosticSeverity::Warning,
+            3 => DiagnosticSeverity::Information,
+            4 => DiagnosticSeverity::Hint,
+            _ => DiagnosticSeverity::Error,
+        },
+        code: match diagnostic.code {
+            Some(DiagnosticCode::Number(code)) => code.to_string(),
+            Some(DiagnosticCode::String(code)) => code,
+            None => String::new(),
+        },
+        source: diagnostic.source.unwrap_or_default(),
+        message: diagnostic.message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::buffer::instance::Cursor;
+
+    use super::{
+        parse_apply_edit, parse_call_hierarchy_items, parse_calls, parse_code_actions,
+        parse_completion, parse_document_diagnostics, parse_hover, parse_signature_help,
+        uri_to_path,
+    };
+    use crate::lsp::types::DocumentChange;
+
+    #[test]
+    fn parse_lenient_responses() {
+        assert_eq!(
+            parse_hover(json!({"contents": {"kind": "plaintext", "value": "fn main()"}})).unwrap(),
+            "fn main()"
+        );
+        assert_eq!(
+            parse_hover(json!({"contents": ["a", {"language": "rust", "value": "b"}]})).unwrap(),
+            "a\n\nb"
+        );
+        assert!(parse_hover(json!({"value": 1})).is_err());
+
+        let cursor = Cursor { row: 2, column: 4 };
+        let items = parse_completion(json!([{"label": "foo"}]), cursor).unwrap();
+        assert_eq!(items[0].edit.text, "foo");
+        assert_eq!(items[0].edit.range.mark, cursor);
+        assert!(parse_completion(json!({"items": 3}), cursor).is_err());
+
+        let actions = parse_code_actions(json!([
+            {"title": "Run", "command": "run", "arguments": [1]},
+            {"title": "Fix", "edit": {"documentChanges": [
+                {"kind": "create", "uri": "file:///b.rs"},
+                {"textDocument": {"uri": "file:///a.rs", "version": 1},
+                 "edits": [{"range": {"start": {"line": 0, "character": 1},
+                                      "end": {"line": 0, "character": 2}}, "newText": "x"}]},
+            ]}},
+        ]))
+        .unwrap();
+        assert_eq!(actions[0].command.as_ref().unwrap().command, "run");
+        assert!(actions[0].edit.changes.is_empty());
+        assert_eq!(actions[1].title, "Fix");
+        assert!(matches!(
+            actions[1].edit.changes[0],
+            DocumentChange::Create {
+                overwrite: false,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &actions[1].edit.changes[1],
+            DocumentChange::Edit { edits, .. } if edits[0].text == "x"
+        ));
+        assert!(parse_code_actions(json!(null)).unwrap().is_empty());
+
+        let edit = parse_apply_edit(json!({"label": "Move", "edit": {"documentChanges": [
+            {"kind": "rename", "oldUri": "file:///a.rs", "newUri": "file:///b.rs",
+             "options": {"overwrite": true}},
+            {"kind": "delete", "uri": "file:///c", "options": {"recursive": true}},
+        ]}}))
+        .unwrap();
+        assert!(matches!(
+            edit.changes[0],
+            DocumentChange::Rename {
+                overwrite: true,
+                ignore_if_exists: false,
+                ..
+            }
+        ));
+        assert!(matches!(
+            edit.changes[1],
+            DocumentChange::Delete {
+                recursive: true,
+                ..
+            }
+        ));
+        assert!(parse_apply_edit(json!({"edit": 1})).is_err());
+
+        let item = json!({"name": "main", "kind": 12, "uri": "file:///a.rs",
+            "range": {"start": {"line": 0, "character": 0}, "end": {"line": 3, "character": 1}},
+            "selectionRange": {"start": {"line": 0, "character": 3}, "end": {"line": 0, "character": 7}}});
+        let items = parse_call_hierarchy_items(json!([item.clone()])).unwrap();
+        assert_eq!(items[0].range.mark, Cursor { row: 0, column: 3 });
+        assert_eq!(items[0].item, item);
+        assert!(parse_call_hierarchy_items(json!(null)).unwrap().is_empty());
+        let calls = parse_calls(json!([{"from": item, "fromRanges": []}]), true).unwrap();
+        assert_eq!(calls[0].name, "main");
+        assert!(parse_calls(json!([{"from": 1}]), false).is_err());
+
+        let diagnostics = parse_document_diagnostics(json!({"kind": "full", "items": [
+            {"range": {"start": {"line": 1, "character": 0}, "end": {"line": 1, "character": 3}},
+             "severity": 2, "message": "unused"}]}))
+        .unwrap()
+        .unwrap();
+        assert_eq!(diagnostics[0].message, "unused");
+        assert!(
+            parse_document_diagnostics(json!({"kind": "unchanged", "resultId": "1"}))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn decode_file_uris() {
+        assert_eq!(uri_to_path("file:///src/a%20b.rs").unwrap(), "/src/a b.rs");
+        assert_eq!(uri_to_path("file:///%C3%A9.rs").unwrap(), "/é.rs");
+        assert!(uri_to_path("https://example.com/a.rs").is_err());
+    }
+
+    #[test]
+    fn signature_help() {
+        let help = parse_signature_help(json!({"signatures": [
+            {"label": "fn add(a: i32, b: i32)", "parameters": [{"label": "a: i32"}, {"label": "b: i32"}]},
+            {"label": "fn é(x: u8)", "parameters": [{"label": [5, 10]}], "activeParameter": 0},
+        ], "activeSignature": 0, "activeParameter": 1}))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            help.active(),
+            Some(("fn add(a: i32, b: i32)", Some((15, 21))))
+        );
+        assert_eq!(help.signatures[1].parameters, vec![(6, 11)]);
+        assert_eq!(&help.signatures[1].label[6..11], "x: u8");
+        assert!(parse_signature_help(json!(null)).unwrap().is_none());
+        assert!(parse_signature_help(json!({"signatures": []}))
+            .unwrap()
+            .is_none());
+        assert!(parse_signature_help(json!({"signatures": 1})).is_err());
+    }
+}
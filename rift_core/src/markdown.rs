@@ -0,0 +1,518 @@
+use crate::buffer::{
+    folding::Folds,
+    graphemes,
+    instance::{Cursor, Selection},
+    line_buffer::{HighlightedText, LineBuffer, VisibleLinesView},
+};
+
+/// Inline span of markdown text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    Text(String),
+    Strong(String),
+    Emphasis(String),
+    Code(String),
+    Link { text: String, url: String },
+}
+
+/// Block of a markdown document along with the row it starts at in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownBlock {
+    Heading {
+        row: usize,
+        level: usize,
+        text: Vec<Inline>,
+    },
+    Paragraph {
+        row: usize,
+        text: Vec<Inline>,
+    },
+    ListItem {
+        row: usize,
+        indent: usize,
+        marker: String,
+        text: Vec<Inline>,
+    },
+    Quote {
+        row: usize,
+        text: Vec<Inline>,
+    },
+    CodeBlock {
+        row: usize,
+        language: String,
+        lines: Vec<String>,
+    },
+    Rule {
+        row: usize,
+    },
+}
+
+impl MarkdownBlock {
+    /// Row of the source where the block starts
+    pub fn row(&self) -> usize {
+        match self {
+            MarkdownBlock::Heading { row, .. }
+            | MarkdownBlock::Paragraph { row, .. }
+            | MarkdownBlock::ListItem { row, .. }
+            | MarkdownBlock::Quote { row, .. }
+            | MarkdownBlock::CodeBlock { row, .. }
+            | MarkdownBlock::Rule { row } => *row,
+        }
+    }
+}
+
+/// Parse the lines of a markdown document into blocks
+pub fn parse_markdown(lines: &[String]) -> Vec<MarkdownBlock> {
+    let mut blocks = vec![];
+    let mut paragraph: Option<(usize, String)> = None;
+    let mut code_block: Option<(usize, String, Vec<String>)> = None;
+
+    for (row, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+
+        if let Some((start, language, mut code)) = code_block.take() {
+            if trimmed.starts_with("```") {
+                blocks.push(MarkdownBlock::CodeBlock {
+                    row: start,
+                    language,
+                    lines: code,
+                });
+            } else {
+                code.push(line.clone());
+                code_block = Some((start, language, code));
+            }
+            continue;
+        }
+
+        let block = if let Some(language) = trimmed.strip_prefix("```") {
+            code_block = Some((row, language.trim().to_string(), vec![]));
+            None
+        } else if trimmed.is_empty() {
+            None
+        } else if let Some((level, text)) = parse_heading(trimmed) {
+            Some(MarkdownBlock::Heading {
+                row,
+                level,
+                text: parse_inline(text),
+            })
+        } else if is_rule(trimmed) {
+            Some(MarkdownBlock::Rule { row })
+        } else if let Some(text) = trimmed.strip_prefix('>') {
+            Some(MarkdownBlock::Quote {
+                row,
+                text: parse_inline(text.trim_start()),
+            })
+        } else if let Some((marker, text)) = parse_list_marker(trimmed) {
+            Some(MarkdownBlock::ListItem {
+                row,
+                indent: (line.len() - trimmed.len()) / 2,
+                marker,
+                text: parse_inline(text),
+            })
+        } else {
+            // Consecutive lines of text form a single paragraph
+            match &mut paragraph {
+                Some((_, text)) => {
+                    text.push(' ');
+                    text.push_str(trimmed);
+                }
+                None => paragraph = Some((row, trimmed.to_string())),
+            }
+            continue;
+        };
+
+        if let Some((start, text)) = paragraph.take() {
+            blocks.push(MarkdownBlock::Paragraph {
+                row: start,
+                text: parse_inline(&text),
+            });
+        }
+        if let Some(block) = block {
+            blocks.push(block);
+        }
+    }
+
+    if let Some((start, text)) = paragraph {
+        blocks.push(MarkdownBlock::Paragraph {
+            row: start,
+            text: parse_inline(&text),
+        });
+    }
+    if let Some((start, language, code)) = code_block {
+        blocks.push(MarkdownBlock::CodeBlock {
+            row: start,
+            language,
+            lines: code,
+        });
+    }
+    blocks
+}
+
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&level) {
+        let text = &line[level..];
+        if text.is_empty() || text.starts_with(' ') {
+            return Some((level, text.trim()));
+        }
+    }
+    None
+}
+
+fn is_rule(line: &str) -> bool {
+    let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    line.len() >= 3
+        && ["-", "*", "_"]
+            .iter()
+            .any(|c| line.chars().all(|x| x.to_string() == *c))
+}
+
+fn parse_list_marker(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(text) = line.strip_prefix(bullet) {
+            return Some(("•".to_string(), text));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        let rest = &line[digits..];
+        if let Some(text) = rest.strip_prefix(". ") {
+            return Some((format!("{}.", &line[..digits]), text));
+        }
+    }
+    None
+}
+
+/// Source of a list item line: `  - [ ] text` or `1. text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListItemSource<'a> {
+    pub indent: &'a str,
+    /// Bullet character or number of an ordered item
+    pub marker: ListMarker,
+    pub checked: Option<bool>,
+    pub text: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListMarker {
+    Bullet(char),
+    Number(usize),
+}
+
+impl ListItemSource<'_> {
+    /// Line with the same parts, checkboxes are written unchecked as `[ ]`
+    fn to_line(&self) -> String {
+        let marker = match self.marker {
+            ListMarker::Bullet(bullet) => bullet.to_string(),
+            ListMarker::Number(number) => format!("{}.", number),
+        };
+        let checkbox = match self.checked {
+            Some(true) => "[x] ",
+            Some(false) => "[ ] ",
+            None => "",
+        };
+        format!("{}{} {}{}", self.indent, marker, checkbox, self.text)
+    }
+}
+
+pub fn parse_list_item(line: &str) -> Option<ListItemSource<'_>> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let (marker, rest) = match trimmed.chars().next()? {
+        bullet @ ('-' | '*' | '+') => (ListMarker::Bullet(bullet), &trimmed[1..]),
+        _ => {
+            let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+            let number = trimmed[..digits].parse().ok()?;
+            (
+                ListMarker::Number(number),
+                trimmed[digits..].strip_prefix('.')?,
+            )
+        }
+    };
+    // A marker is followed by a space unless the item is empty
+    let rest = match rest.strip_prefix(' ') {
+        Some(rest) => rest,
+        None if rest.is_empty() => rest,
+        None => return None,
+    };
+    let (checked, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (Some(false), text.trim_start_matches(' '))
+    } else if let Some(text) = rest.strip_prefix("[x]").or(rest.strip_prefix("[X]")) {
+        (Some(true), text.trim_start_matches(' '))
+    } else {
+        (None, rest)
+    };
+    Some(ListItemSource {
+        indent,
+        marker,
+        checked,
+        text,
+    })
+}
+
+/// Check or uncheck the checkbox of a list item, adding one if missing.
+/// Other lines of text are turned into unchecked items.
+pub fn toggle_checkbox(line: &str) -> String {
+    match parse_list_item(line) {
+        Some(mut item) => {
+            item.checked = Some(!item.checked.unwrap_or(true));
+            item.to_line()
+        }
+        None if line.trim().is_empty() => line.to_string(),
+        None => {
+            let trimmed = line.trim_start();
+            format!("{}- [ ] {}", &line[..line.len() - trimmed.len()], trimmed)
+        }
+    }
+}
+
+/// Change the level of a heading by one, promoting a level 1 heading to text
+/// and demoting text to a level 1 heading
+pub fn shift_heading(line: &str, promote: bool) -> String {
+    match (parse_heading(line), promote) {
+        (Some((1, text)), true) => text.to_string(),
+        (Some((level, text)), true) => format!("{} {}", "#".repeat(level - 1), text),
+        (Some((level, text)), false) => format!("{} {}", "#".repeat((level + 1).min(6)), text),
+        (None, true) => line.to_string(),
+        (None, false) if line.trim().is_empty() => line.to_string(),
+        (None, false) => format!("# {}", line.trim_start()),
+    }
+}
+
+/// Prefix of the line inserted after a list item: the same indentation and bullet,
+/// the next number and an unchecked checkbox if the item has one
+pub fn continue_list(line: &str) -> Option<String> {
+    let item = parse_list_item(line)?;
+    let next = ListItemSource {
+        marker: match item.marker {
+            ListMarker::Number(number) => ListMarker::Number(number + 1),
+            bullet => bullet,
+        },
+        checked: item.checked.map(|_| false),
+        text: "",
+        ..item
+    };
+    Some(next.to_line())
+}
+
+/// Renumber the ordered list containing the row starting from its first number.
+/// Returns the rows which changed with their new text.
+pub fn renumber_list(lines: &[String], row: usize) -> Vec<(usize, String)> {
+    let Some(item) = lines.get(row).and_then(|line| parse_list_item(line)) else {
+        return vec![];
+    };
+    let indent = item.indent.len();
+    // Items of the same list have the same indentation, nested lines are skipped
+    let belongs = |line: &String| match parse_list_item(line) {
+        Some(other) if other.indent.len() == indent => {
+            Some(matches!(other.marker, ListMarker::Number(_)))
+        }
+        _ if line.len() - line.trim_start().len() > indent => None,
+        _ => Some(false),
+    };
+    let mut start = row;
+    for (idx, line) in lines[..row].iter().enumerate().rev() {
+        match belongs(line) {
+            Some(true) => start = idx,
+            Some(false) => break,
+            None => {}
+        }
+    }
+
+    let mut changes = vec![];
+    let mut number = None;
+    for (idx, line) in lines.iter().enumerate().skip(start) {
+        match belongs(line) {
+            Some(true) => {
+                let mut item = parse_list_item(line).unwrap();
+                let first = match item.marker {
+                    ListMarker::Number(first) => first,
+                    ListMarker::Bullet(_) => unreachable!(),
+                };
+                let expected = *number.get_or_insert(first);
+                item.marker = ListMarker::Number(expected);
+                let renumbered = item.to_line();
+                if renumbered != *line {
+                    changes.push((idx, renumbered));
+                }
+                number = Some(expected + 1);
+            }
+            Some(false) => break,
+            None => {}
+        }
+    }
+    changes
+}
+
+/// Parse emphasis, inline code and links in a line of text
+pub fn parse_inline(text: &str) -> Vec<Inline> {
+    let mut spans = vec![];
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let span = match c {
+            '`' => rest[1..]
+                .find('`')
+                .map(|end| (Inline::Code(rest[1..end + 1].to_string()), end + 2)),
+            // Underscores inside words are not emphasis
+            '_' if plain.ends_with(|c: char| c.is_alphanumeric()) => None,
+            '*' | '_' if rest[1..].starts_with(c) => {
+                let delimiter = &rest[..2];
+                rest[2..]
+                    .find(delimiter)
+                    .map(|end| (Inline::Strong(rest[2..end + 2].to_string()), end + 4))
+            }
+            '*' | '_' => rest[1..]
+                .find(c)
+                .filter(|end| *end > 0)
+                .map(|end| (Inline::Emphasis(rest[1..end + 1].to_string()), end + 2)),
+            '[' => rest.find("](").and_then(|middle| {
+                rest[middle..].find(')').map(|end| {
+                    (
+                        Inline::Link {
+                            text: rest[1..middle].to_string(),
+                            url: rest[middle + 2..middle + end].to_string(),
+                        },
+                        middle + end + 1,
+                    )
+                })
+            }),
+            _ => None,
+        };
+
+        match span {
+            Some((span, length)) => {
+                if !plain.is_empty() {
+                    spans.push(Inline::Text(std::mem::take(&mut plain)));
+                }
+                spans.push(span);
+                rest = &rest[length..];
+            }
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    if !plain.is_empty() {
+        spans.push(Inline::Text(plain));
+    }
+    spans
+}
+
+/// Syntax highlight the lines of a code block using the language of its info string
+pub fn highlight_code(language: &str, lines: &[String]) -> Option<HighlightedText> {
+    let extension = match language {
+        "rust" | "rs" => "rs",
+        "python" | "py" => "py",
+        _ => return None,
+    };
+    let mut buffer = LineBuffer::new(lines.join("\n"), Some(format!("code.{}", extension)));
+    buffer.update_highlights();
+    let max_characters = lines
+        .iter()
+        .map(|line| graphemes::visual_width(line, 0, buffer.tab_width))
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let start = Cursor { row: 0, column: 0 };
+    let (highlighted_text, _, _) = buffer.get_visible_lines(VisibleLinesView {
+        scroll: &mut start.clone(),
+        cursor: &start,
+        selection: &Selection {
+            cursor: start,
+            mark: start,
+        },
+        visible_lines: lines.len(),
+        max_characters,
+        eol_sequence: "\n".into(),
+        extra_segments: vec![],
+        folds: &mut Folds::default(),
+    });
+    Some(highlighted_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        continue_list, parse_inline, parse_markdown, renumber_list, shift_heading, toggle_checkbox,
+        Inline, MarkdownBlock,
+    };
+
+    #[test]
+    fn markdown_blocks() {
+        let lines: Vec<String> =
+            "# Title\n\nSome text\ncontinued\n- item\n```rust\nfn main() {}\n```"
+                .lines()
+                .map(String::from)
+                .collect();
+        let blocks = parse_markdown(&lines);
+        assert_eq!(blocks.len(), 4);
+        assert!(matches!(
+            &blocks[0],
+            MarkdownBlock::Heading { level: 1, .. }
+        ));
+        assert_eq!(
+            blocks[1],
+            MarkdownBlock::Paragraph {
+                row: 2,
+                text: vec![Inline::Text("Some text continued".into())]
+            }
+        );
+        assert!(matches!(&blocks[2], MarkdownBlock::ListItem { row: 4, .. }));
+        assert_eq!(
+            blocks[3],
+            MarkdownBlock::CodeBlock {
+                row: 5,
+                language: "rust".into(),
+                lines: vec!["fn main() {}".into()]
+            }
+        );
+    }
+
+    #[test]
+    fn markdown_inline() {
+        assert_eq!(
+            parse_inline("a **b** `c` [d](e)"),
+            vec![
+                Inline::Text("a ".into()),
+                Inline::Strong("b".into()),
+                Inline::Text(" ".into()),
+                Inline::Code("c".into()),
+                Inline::Text(" ".into()),
+                Inline::Link {
+                    text: "d".into(),
+                    url: "e".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_editing() {
+        assert_eq!(toggle_checkbox("  - [ ] task"), "  - [x] task");
+        assert_eq!(toggle_checkbox("- [X] task"), "- [ ] task");
+        assert_eq!(toggle_checkbox("1. task"), "1. [ ] task");
+        assert_eq!(toggle_checkbox("task"), "- [ ] task");
+
+        assert_eq!(shift_heading("## Title", true), "# Title");
+        assert_eq!(shift_heading("# Title", true), "Title");
+        assert_eq!(shift_heading("Title", false), "# Title");
+        assert_eq!(shift_heading("###### Title", false), "###### Title");
+
+        assert_eq!(continue_list("  * [x] done").unwrap(), "  * [ ] ");
+        assert_eq!(continue_list("9. item").unwrap(), "10. ");
+        assert!(continue_list("text").is_none());
+
+        let lines: Vec<String> = "1. a\n1. b\n   - nested\n5. c\n\n1. other"
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            renumber_list(&lines, 3),
+            vec![(1, "2. b".to_string()), (3, "3. c".to_string())]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::actions::Action;
+
+/// Number of notifications kept in the history
+pub const HISTORY_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl NotificationLevel {
+    /// How long notifications of the level stay visible
+    pub fn timeout(&self) -> Option<Duration> {
+        match self {
+            NotificationLevel::Info => Some(Duration::from_secs(4)),
+            NotificationLevel::Warning => Some(Duration::from_secs(8)),
+            NotificationLevel::Error => None,
+        }
+    }
+}
+
+impl std::fmt::Display for NotificationLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotificationLevel::Info => write!(f, "INFO"),
+            NotificationLevel::Warning => write!(f, "WARN"),
+            NotificationLevel::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+/// Button shown on a notification performing an action when clicked
+#[derive(Debug, Clone)]
+pub struct NotificationAction {
+    pub label: String,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: usize,
+    pub level: NotificationLevel,
+    pub message: String,
+    pub created: Instant,
+    /// Dismissed automatically after the timeout if set
+    pub timeout: Option<Duration>,
+    pub actions: Vec<NotificationAction>,
+}
+
+impl Notification {
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.timeout
+            .is_some_and(|timeout| now.duration_since(self.created) >= timeout)
+    }
+}
+
+/// Queue of user facing messages along with a history of past messages
+#[derive(Debug, Default)]
+pub struct Notifications {
+    /// Notifications currently shown
+    pub active: Vec<Notification>,
+    pub history: VecDeque<Notification>,
+    next_id: usize,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a notification returning its id
+    pub fn push(
+        &mut self,
+        level: NotificationLevel,
+        message: String,
+        actions: Vec<NotificationAction>,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let notification = Notification {
+            id,
+            level,
+            message,
+            created: Instant::now(),
+            timeout: level.timeout(),
+            actions,
+        };
+        self.history.push_back(notification.clone());
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.active.push(notification);
+        id
+    }
+
+    pub fn dismiss(&mut self, id: usize) {
+        self.active.retain(|notification| notification.id != id);
+    }
+
+    pub fn dismiss_all(&mut self) {
+        self.active.clear();
+    }
+
+    /// Remove notifications whose timeout has passed.
+    /// Returns true if any notification was removed.
+    pub fn expire(&mut self) -> bool {
+        let now = Instant::now();
+        let len = self.active.len();
+        self.active
+            .retain(|notification| !notification.is_expired(now));
+        len != self.active.len()
+    }
+
+    /// Latest notification still shown
+    pub fn latest(&self) -> Option<&Notification> {
+        self.active.last()
+    }
+
+    /// History formatted for display in a buffer, oldest first
+    pub fn history_text(&self) -> String {
+        let now = Instant::now();
+        let mut text = String::from("Notifications\n\n");
+        for notification in &self.history {
+            text.push_str(&format!(
+                "[{}] {}s ago: {}\n",
+                notification.level,
+                now.duration_since(notification.created).as_secs(),
+                notification.message
+            ));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{NotificationLevel, Notifications};
+
+    #[test]
+    fn notifications_expire() {
+        let mut notifications = Notifications::new();
+        let info = notifications.push(NotificationLevel::Info, "saved".into(), vec![]);
+        notifications.push(NotificationLevel::Error, "failed".into(), vec![]);
+
+        notifications.active[0].created = Instant::now() - Duration::from_secs(60);
+        assert!(notifications.expire());
+        assert_eq!(notifications.active.len(), 1);
+        assert_eq!(notifications.latest().unwrap().message, "failed");
+
+        notifications.dismiss_all();
+        assert!(notifications.active.is_empty());
+        assert_eq!(notifications.history.len(), 2);
+        assert_eq!(notifications.history[0].id, info);
+    }
+}
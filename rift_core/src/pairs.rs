@@ -0,0 +1,158 @@
+//! Auto-closing of brackets and quotes while typing and
+//! adding, changing and deleting the pair surrounding a selection.
+
+/// Brackets and quotes closed automatically
+pub fn default_auto_pairs() -> Vec<(char, char)> {
+    vec![
+        ('(', ')'),
+        ('[', ']'),
+        ('{', '}'),
+        ('"', '"'),
+        ('\'', '\''),
+        ('`', '`'),
+    ]
+}
+
+/// Opening and closing characters of the pair containing the character,
+/// or the character on both sides if it is not part of a pair
+pub fn pair_of(pairs: &[(char, char)], c: char) -> (char, char) {
+    pairs
+        .iter()
+        .find(|(open, close)| *open == c || *close == c)
+        .copied()
+        .unwrap_or((c, c))
+}
+
+/// Effect of typing a character in insert mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypedChar {
+    /// Move over the closing character after the cursor instead of inserting it
+    Skip,
+    /// Insert the character followed by its closing character
+    Pair(char),
+    Plain,
+}
+
+/// Effect of typing the character at the column of the line
+pub fn typed_char(pairs: &[(char, char)], line: &str, column: usize, c: char) -> TypedChar {
+    let before = line[..column].chars().next_back();
+    let after = line[column..].chars().next();
+    if after == Some(c) && pairs.iter().any(|(_, close)| *close == c) {
+        return TypedChar::Skip;
+    }
+    let Some((_, close)) = pairs.iter().find(|(open, _)| *open == c) else {
+        return TypedChar::Plain;
+    };
+    // Only pair in front of whitespace or closing characters
+    let next_free = after.is_none_or(|after| {
+        after.is_whitespace() || pairs.iter().any(|(_, close)| *close == after)
+    });
+    // Quotes after a word are apostrophes, such as in `don't`
+    let quote = c == *close;
+    let after_word = before.is_some_and(|before| before.is_alphanumeric() || before == '_');
+    if next_free && !(quote && after_word) {
+        TypedChar::Pair(*close)
+    } else {
+        TypedChar::Plain
+    }
+}
+
+/// Returns true if the cursor is between an empty pair, such as `(|)`,
+/// which is deleted together by backspace
+pub fn in_empty_pair(pairs: &[(char, char)], line: &str, column: usize) -> bool {
+    let before = line[..column].chars().next_back();
+    let after = line[column..].chars().next();
+    pairs
+        .iter()
+        .any(|(open, close)| before == Some(*open) && after == Some(*close))
+}
+
+/// Byte offsets of the opening and closing characters of the innermost pair around the byte.
+/// Quotes are only matched within the line.
+pub fn find_surround(
+    content: &str,
+    byte: usize,
+    open: char,
+    close: char,
+) -> Option<(usize, usize)> {
+    if open == close {
+        let line_start = content[..byte].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        let line_end = content[byte..]
+            .find('\n')
+            .map(|idx| byte + idx)
+            .unwrap_or(content.len());
+        let quotes: Vec<usize> = content[line_start..line_end]
+            .match_indices(open)
+            .map(|(idx, _)| line_start + idx)
+            .collect();
+        // An odd number of quotes before the cursor means it is inside a quote
+        let before = content[line_start..byte].matches(open).count();
+        return if before % 2 == 1 {
+            Some((quotes[before - 1], *quotes.get(before)?))
+        } else {
+            Some((*quotes.get(before)?, *quotes.get(before + 1)?))
+        };
+    }
+
+    // The cursor on the opening character is inside the pair
+    let (start, search_from) = if content[byte..].starts_with(open) {
+        (byte, byte + open.len_utf8())
+    } else {
+        let mut depth = 0;
+        let (start, _) = content[..byte].char_indices().rev().find(|(_, c)| {
+            if *c == close {
+                depth += 1;
+            } else if *c == open {
+                if depth == 0 {
+                    return true;
+                }
+                depth -= 1;
+            }
+            false
+        })?;
+        (start, byte)
+    };
+    let mut depth = 0;
+    let (end, _) = content[search_from..].char_indices().find(|(_, c)| {
+        if *c == open {
+            depth += 1;
+        } else if *c == close {
+            if depth == 0 {
+                return true;
+            }
+            depth -= 1;
+        }
+        false
+    })?;
+    Some((start, search_from + end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_auto_pairs, find_surround, in_empty_pair, pair_of, typed_char, TypedChar};
+
+    #[test]
+    fn auto_pairs_and_surround() {
+        let pairs = default_auto_pairs();
+        assert_eq!(typed_char(&pairs, "f", 1, '('), TypedChar::Pair(')'));
+        assert_eq!(typed_char(&pairs, "f()", 2, ')'), TypedChar::Skip);
+        assert_eq!(typed_char(&pairs, "fx", 1, '('), TypedChar::Plain);
+        assert_eq!(typed_char(&pairs, "don", 3, '\''), TypedChar::Plain);
+        assert_eq!(typed_char(&pairs, "a = ", 4, '"'), TypedChar::Pair('"'));
+        assert_eq!(typed_char(&pairs, "\"a\"", 2, '"'), TypedChar::Skip);
+        assert_eq!(typed_char(&pairs, "a", 1, 'b'), TypedChar::Plain);
+        assert!(in_empty_pair(&pairs, "f()", 2));
+        assert!(!in_empty_pair(&pairs, "f(a)", 3));
+        assert_eq!(pair_of(&pairs, ']'), ('[', ']'));
+        assert_eq!(pair_of(&pairs, '*'), ('*', '*'));
+
+        let content = "f(a, (b), c)\nx = \"s\"";
+        assert_eq!(find_surround(content, 10, '(', ')'), Some((1, 11)));
+        assert_eq!(find_surround(content, 6, '(', ')'), Some((5, 7)));
+        assert_eq!(find_surround(content, 5, '(', ')'), Some((5, 7)));
+        assert_eq!(find_surround(content, 0, '(', ')'), None);
+        assert_eq!(find_surround(content, 18, '"', '"'), Some((17, 19)));
+        assert_eq!(find_surround(content, 17, '"', '"'), Some((17, 19)));
+        assert_eq!(find_surround(content, 3, '"', '"'), None);
+    }
+}
@@ -1,4 +1,21 @@
-use crate::themes;
+use std::collections::HashMap;
+
+use crate::{
+    abbreviations::{self, Abbreviations},
+    actions::Action,
+    buffer::instance::{HighlightType, Language},
+    events::Hook,
+    formatter::{self, FormatterCommand},
+    git::{conflict::ConflictSide, HunkKind},
+    linter::{self, LinterCommand},
+    lsp::{
+        servers::{self, LanguageServerConfig},
+        types::DiagnosticSeverity,
+    },
+    pairs,
+    tasks::Task,
+    themes,
+};
 
 /// Color representation (values between 0 and 255)
 #[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
@@ -30,13 +47,164 @@ pub struct Preferences {
     pub theme: Theme,
     pub line_ending: String,
     pub tab_width: usize,
+    /// Indent with tabs instead of spaces unless an EditorConfig sets the style
+    pub use_tabs: bool,
     pub editor_font_family: String,
+    /// Font file used instead of looking up the editor font family
+    pub editor_font_path: Option<String>,
     pub ui_font_family: String,
+    /// Font file used instead of looking up the ui font family
+    pub ui_font_path: Option<String>,
     pub editor_font_size: usize,
     pub ui_font_size: usize,
     pub line_height: f32,
     pub gutter_padding: f32,
+    /// How line numbers are shown in the gutter
+    pub line_numbers: LineNumbers,
+    pub show_gutter: bool,
+    /// Minimum columns of the line numbers, wider for buffers with more lines
+    pub gutter_width: usize,
     pub editor_padding: f32,
+    /// Language servers attached together to buffers of each language,
+    /// each as a list of candidates tried in order
+    pub language_servers: HashMap<Language, Vec<Vec<LanguageServerConfig>>>,
+    /// Milliseconds without edits before changes are sent to the language servers
+    pub lsp_change_debounce: u64,
+    /// Milliseconds between requests for the diagnostics of changed buffers,
+    /// for servers which provide diagnostics on request
+    pub diagnostic_pull_interval: u64,
+    /// Milliseconds without edits before the git change markers are updated
+    pub git_diff_debounce: u64,
+    /// External formatters used when no language server is running
+    pub formatters: HashMap<Language, FormatterCommand>,
+    /// Format with the external formatter before saving
+    pub format_on_save: bool,
+    /// External linters whose diagnostics are shown alongside the language server's
+    pub linters: HashMap<Language, LinterCommand>,
+    /// Run the linter of the buffer's language after saving
+    pub lint_on_save: bool,
+    /// Words expanded when followed by a word boundary in insert mode
+    pub abbreviations: Abbreviations,
+    /// Show the message of diagnostics at the end of their lines
+    pub inline_diagnostics: bool,
+    /// Draw a line at each level of indentation
+    pub indent_guides: bool,
+    /// Color brackets by their nesting depth
+    pub bracket_colors: bool,
+    /// Draw tabs, trailing spaces and control characters as placeholders
+    pub render_whitespace: bool,
+    /// Brackets and quotes closed automatically in insert mode, empty to disable
+    pub auto_pairs: Vec<(char, char)>,
+    /// Include line numbers in html exports
+    pub export_line_numbers: bool,
+    /// Identity file suggested when opening age encrypted files
+    pub age_identity: Option<String>,
+    /// Reopen the files of the last session when a workspace folder is opened
+    pub restore_session: bool,
+    /// Journal unsaved changes so they can be restored after a crash
+    pub crash_recovery: bool,
+    /// Settings overridden for buffers of a language
+    pub languages: HashMap<Language, LanguageConfig>,
+    /// Tasks available in addition to those of the workspace tasks file
+    pub tasks: Vec<Task>,
+    /// Modes with their own keys in addition to normal and insert mode
+    pub modes: Vec<CustomMode>,
+    /// Actions performed on editor events
+    pub hooks: Vec<Hook>,
+}
+
+/// Line numbers shown in the gutter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LineNumbers {
+    Absolute,
+    /// Distance from the line of the cursor
+    Relative,
+    /// Absolute on the line of the cursor and relative on the others
+    Hybrid,
+    Off,
+}
+
+/// Mode defined in the configuration, entered with a key sequence typed in normal mode
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CustomMode {
+    pub name: String,
+    /// Shown in the status bar, the upper case name when not set
+    pub indicator: Option<String>,
+    /// Keys typed in normal mode which enter the mode, such as `["space", "w"]`
+    pub enter: Vec<String>,
+    /// Action performed by each key typed in the mode
+    pub keys: HashMap<String, Action>,
+    /// Stay in the mode after an action instead of returning to normal mode
+    pub sticky: bool,
+}
+
+/// Settings of a language which take precedence over the global ones when set
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LanguageConfig {
+    pub tab_width: Option<usize>,
+    pub use_tabs: Option<bool>,
+    pub format_on_save: Option<bool>,
+    /// Line comment token continued on new lines in addition to the built in ones
+    pub comment_token: Option<String>,
+    pub language_servers: Option<Vec<Vec<LanguageServerConfig>>>,
+}
+
+impl Preferences {
+    pub fn tab_width_for(&self, language: Language) -> usize {
+        self.languages
+            .get(&language)
+            .and_then(|config| config.tab_width)
+            .unwrap_or(self.tab_width)
+    }
+
+    pub fn use_tabs_for(&self, language: Language) -> bool {
+        self.languages
+            .get(&language)
+            .and_then(|config| config.use_tabs)
+            .unwrap_or(self.use_tabs)
+    }
+
+    pub fn format_on_save_for(&self, language: Language) -> bool {
+        self.languages
+            .get(&language)
+            .and_then(|config| config.format_on_save)
+            .unwrap_or(self.format_on_save)
+    }
+
+    pub fn comment_token_for(&self, language: Language) -> Option<&str> {
+        self.languages
+            .get(&language)
+            .and_then(|config| config.comment_token.as_deref())
+    }
+
+    /// Columns of the line numbers of a buffer with the number of lines, 0 when they are off
+    pub fn line_number_width(&self, num_lines: usize) -> usize {
+        if self.line_numbers == LineNumbers::Off {
+            return 0;
+        }
+        self.gutter_width.max(num_lines.to_string().len())
+    }
+
+    /// Status bar indicator of the custom mode with the name
+    pub fn mode_indicator(&self, name: &str) -> String {
+        self.modes
+            .iter()
+            .find(|mode| mode.name == name)
+            .and_then(|mode| mode.indicator.clone())
+            .unwrap_or_else(|| name.to_uppercase())
+    }
+
+    pub fn language_servers_for(
+        &self,
+        language: Language,
+    ) -> Option<&Vec<Vec<LanguageServerConfig>>> {
+        self.languages
+            .get(&language)
+            .and_then(|config| config.language_servers.as_ref())
+            .or_else(|| self.language_servers.get(&language))
+    }
 }
 
 impl Default for Preferences {
@@ -51,13 +219,41 @@ impl Default for Preferences {
             theme: Theme::kanagawa(),
             line_ending,
             tab_width: 4,
+            use_tabs: false,
             editor_font_family: "Monaspace Neon".into(),
+            editor_font_path: None,
             editor_font_size: 26,
             ui_font_family: "Monaspace Neon".into(),
+            ui_font_path: None,
             ui_font_size: 18,
             line_height: 1.5,
             gutter_padding: 8.0,
+            line_numbers: LineNumbers::Absolute,
+            show_gutter: true,
+            gutter_width: 3,
             editor_padding: 8.0,
+            language_servers: servers::default_language_servers(),
+            lsp_change_debounce: 100,
+            diagnostic_pull_interval: 1000,
+            git_diff_debounce: 300,
+            formatters: formatter::default_formatters(),
+            format_on_save: false,
+            linters: linter::default_linters(),
+            lint_on_save: true,
+            abbreviations: abbreviations::default_abbreviations(),
+            inline_diagnostics: true,
+            indent_guides: true,
+            bracket_colors: true,
+            render_whitespace: false,
+            auto_pairs: pairs::default_auto_pairs(),
+            export_line_numbers: true,
+            age_identity: None,
+            restore_session: true,
+            crash_recovery: true,
+            languages: HashMap::new(),
+            tasks: vec![],
+            modes: vec![],
+            hooks: vec![],
         }
     }
 }
@@ -103,6 +299,62 @@ pub struct Theme {
 }
 
 impl Theme {
+    /// Color of brackets nested at the depth
+    pub fn bracket_color(&self, depth: usize) -> Color {
+        [
+            self.highlight_yellow,
+            self.highlight_purple,
+            self.highlight_blue,
+        ][depth % 3]
+    }
+
+    /// Color of diagnostics of the severity
+    pub fn diagnostic_color(&self, severity: &DiagnosticSeverity) -> Color {
+        match severity {
+            DiagnosticSeverity::Error => self.error,
+            DiagnosticSeverity::Warning => self.warning,
+            DiagnosticSeverity::Information => self.information,
+            DiagnosticSeverity::Hint => self.hint,
+        }
+    }
+
+    /// Background of the lines of a side of a merge conflict
+    pub fn conflict_bg(&self, side: ConflictSide) -> Color {
+        let color = match side {
+            ConflictSide::Ours => self.highlight_green,
+            ConflictSide::Theirs => self.highlight_blue,
+        };
+        let mix = |a: u8, b: u8| ((a as u16 * 3 + b as u16) / 4) as u8;
+        Color::from_rgb(
+            mix(self.editor_bg.r, color.r),
+            mix(self.editor_bg.g, color.g),
+            mix(self.editor_bg.b, color.b),
+        )
+    }
+
+    pub fn highlight_color(&self, highlight: HighlightType) -> Color {
+        match highlight {
+            HighlightType::None => self.highlight_none,
+            HighlightType::White => self.highlight_white,
+            HighlightType::Red => self.highlight_red,
+            HighlightType::Orange => self.highlight_orange,
+            HighlightType::Blue => self.highlight_blue,
+            HighlightType::Green => self.highlight_green,
+            HighlightType::Purple => self.highlight_purple,
+            HighlightType::Yellow => self.highlight_yellow,
+            HighlightType::Gray => self.highlight_gray,
+            HighlightType::Turquoise => self.highlight_turquoise,
+        }
+    }
+
+    pub fn git_color(&self, kind: HunkKind) -> Color {
+        match kind {
+            HunkKind::Added => self.highlight_green,
+            HunkKind::Modified => self.highlight_blue,
+            HunkKind::Removed => self.highlight_red,
+        }
+    }
+
     fn catppuccin_mocha() -> Self {
         Self {
             editor_bg: themes::catppuccin_mocha::BASE,
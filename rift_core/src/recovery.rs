@@ -0,0 +1,340 @@
+//! Crash recovery: the content of buffers with unsaved changes is journaled to the config
+//! directory, journals left behind by a crash are offered for restoring on the next start.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    buffer::{
+        instance::{Cursor, Selection},
+        line_buffer::LineBuffer,
+    },
+    dialog::ConfirmDialog,
+    io::file_io,
+    state::EditorState,
+};
+
+/// Time between writes of the journals of buffers which keep changing
+pub const JOURNAL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Unsaved content of a buffer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Journal {
+    /// File of the buffer, None for buffers without one
+    pub path: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug)]
+pub struct Recovery {
+    last_write: Instant,
+    /// Journal file and buffer version last written for each buffer with unsaved changes
+    journals: HashMap<u32, (PathBuf, usize)>,
+}
+
+impl Default for Recovery {
+    fn default() -> Self {
+        Self {
+            last_write: Instant::now(),
+            journals: HashMap::new(),
+        }
+    }
+}
+
+pub fn recovery_dir() -> Option<PathBuf> {
+    file_io::config_dir().map(|dir| dir.join("recovery"))
+}
+
+/// File name of the journal of a buffer, after the editor owning it and the file of the buffer
+/// or its id for buffers without one
+fn journal_name(pid: u32, path: Option<&str>, id: u32) -> String {
+    match path {
+        Some(path) => {
+            let name: String = path
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            format!("{}_{}.json", pid, name)
+        }
+        None => format!("{}_untitled_{}.json", pid, id),
+    }
+}
+
+/// Process id of the editor which wrote the journal, None for journals without one
+fn journal_owner(path: &Path) -> Option<u32> {
+    let name = path.file_name()?.to_str()?;
+    name.split_once('_')?.0.parse().ok()
+}
+
+/// Whether a process with the id is running
+fn process_alive(pid: u32) -> bool {
+    #[cfg(target_os = "windows")]
+    let output = {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+    };
+    #[cfg(not(target_os = "windows"))]
+    let output = std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|status| status.success());
+
+    output.unwrap_or(false)
+}
+
+fn write_journal(path: &Path, journal: &Journal) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(journal)?)?;
+    Ok(())
+}
+
+fn remove_journal(path: &Path) {
+    if let Err(err) = std::fs::remove_file(path) {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Failed to remove recovery journal: {}", err);
+        }
+    }
+}
+
+/// Write the journals of buffers changed since they were last written and remove those
+/// of buffers which were saved or closed. Encrypted files are never journaled.
+pub fn write_journals(state: &mut EditorState) {
+    if state.recovery.last_write.elapsed() < JOURNAL_INTERVAL {
+        return;
+    }
+    state.recovery.last_write = Instant::now();
+    let Some(dir) = recovery_dir() else {
+        return;
+    };
+
+    let enabled = state.preferences.crash_recovery;
+    let mut journals = std::mem::take(&mut state.recovery.journals);
+    journals.retain(|id, (path, _)| {
+        let keep = enabled && state.buffers.get(id).is_some_and(|buffer| buffer.modified);
+        if !keep {
+            remove_journal(path);
+        }
+        keep
+    });
+    for (id, buffer) in state.buffers.iter().filter(|_| enabled) {
+        let encrypted = buffer
+            .file_path
+            .as_ref()
+            .is_some_and(|path| state.encrypted_files.contains_key(path));
+        let written = journals.get(id);
+        if !buffer.modified
+            || encrypted
+            || written.is_some_and(|(_, version)| *version == buffer.version)
+        {
+            continue;
+        }
+        let path = dir.join(journal_name(
+            std::process::id(),
+            buffer.file_path.as_deref(),
+            *id,
+        ));
+        // The buffer was saved under a new name
+        if let Some((old_path, _)) = written.filter(|(old_path, _)| *old_path != path) {
+            remove_journal(old_path);
+        }
+        let journal = Journal {
+            path: buffer.file_path.clone(),
+            content: buffer.get_content("\n".into()),
+        };
+        match write_journal(&path, &journal) {
+            Ok(()) => {
+                journals.insert(*id, (path, buffer.version));
+            }
+            Err(err) => tracing::warn!("Failed to write recovery journal: {:#}", err),
+        }
+    }
+    state.recovery.journals = journals;
+}
+
+/// Remove the journals of all buffers when the editor exits normally
+pub fn remove_journals(state: &mut EditorState) {
+    for (_id, (path, _version)) in state.recovery.journals.drain() {
+        remove_journal(&path);
+    }
+}
+
+/// Journals left behind by an editor which did not exit normally
+pub fn leftover_journals() -> Vec<(PathBuf, Journal)> {
+    recovery_dir()
+        .map(|dir| journals_in(&dir))
+        .unwrap_or_default()
+}
+
+/// Journals in the folder, skipping those of editors which are still running
+fn journals_in(dir: &Path) -> Vec<(PathBuf, Journal)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut journals: Vec<(PathBuf, Journal)> = entries
+        .flatten()
+        .filter(|entry| !journal_owner(&entry.path()).is_some_and(process_alive))
+        .filter_map(|entry| {
+            let content = std::fs::read_to_string(entry.path()).ok()?;
+            let journal = serde_json::from_str(&content).ok()?;
+            Some((entry.path(), journal))
+        })
+        .collect();
+    journals.sort_by(|(a, _), (b, _)| a.cmp(b));
+    journals
+}
+
+/// Ask whether to restore the unsaved changes of journals left behind by a crash
+pub fn offer_recovery(state: &mut EditorState) {
+    let journals = leftover_journals();
+    if journals.is_empty() || state.confirm_dialog.is_some() {
+        return;
+    }
+    let names: Vec<String> = journals
+        .iter()
+        .map(|(_, journal)| {
+            journal
+                .path
+                .clone()
+                .unwrap_or_else(|| "[No Name]".to_string())
+        })
+        .collect();
+    state.confirm_dialog = Some(ConfirmDialog::recover(&names));
+}
+
+/// Open the buffer of the journal with its unsaved changes, files which no longer exist
+/// and buffers without a file are opened as new buffers
+pub fn restore(state: &mut EditorState, journal: &Journal) {
+    if let Some(path) = journal
+        .path
+        .as_ref()
+        .filter(|path| Path::new(path).is_file())
+    {
+        state.open_location(path, Cursor { row: 0, column: 0 }, &None);
+        let Some(id) = state.buffer_idx else {
+            return;
+        };
+        let (buffer, _instance) = state.get_buffer_by_id_mut(id);
+        if buffer.file_path.as_ref() != Some(path)
+            || buffer.get_content("\n".into()) == journal.content
+        {
+            return;
+        }
+        let last = buffer.get_num_lines() - 1;
+        let selection = Selection {
+            mark: Cursor { row: 0, column: 0 },
+            cursor: Cursor {
+                row: last,
+                column: buffer.get_line_length(last),
+            },
+        };
+        buffer.replace_text(&selection, &journal.content, &None);
+        return;
+    }
+    let mut buffer = LineBuffer::new(journal.content.clone(), journal.path.clone());
+    buffer.modified = true;
+    let id = state.add_buffer(buffer);
+    state.buffer_idx = Some(id);
+}
+
+/// Restore the leftover journals, they are written again for the restored buffers
+pub fn restore_journals(state: &mut EditorState) {
+    for (path, journal) in leftover_journals() {
+        restore(state, &journal);
+        remove_journal(&path);
+    }
+    state.update_view = true;
+}
+
+pub fn discard_journals() {
+    for (path, _journal) in leftover_journals() {
+        remove_journal(&path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{journal_name, journals_in, restore, write_journal, Journal};
+    use crate::{buffer::line_buffer::LineBuffer, state::EditorState};
+
+    #[test]
+    fn restore_journaled_changes() {
+        assert_eq!(
+            journal_name(42, Some("/src/main.rs"), 1),
+            "42__src_main_rs.json"
+        );
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        let file = std::env::temp_dir().join(format!("rift_recovery_{}.txt", std::process::id()));
+        std::fs::write(&file, "saved\n").unwrap();
+        let path = file.to_str().unwrap().to_string();
+        // Already open, so the file is not added to the recent files
+        state.add_buffer(LineBuffer::new("saved\n".into(), Some(path.clone())));
+
+        restore(
+            &mut state,
+            &Journal {
+                path: Some(path.clone()),
+                content: "unsaved\n".into(),
+            },
+        );
+        let (buffer, _instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+        assert_eq!(buffer.file_path, Some(path));
+        assert_eq!(buffer.get_content("\n".into()), "unsaved\n");
+        assert!(buffer.modified);
+
+        restore(
+            &mut state,
+            &Journal {
+                path: None,
+                content: "notes\n".into(),
+            },
+        );
+        let (buffer, _instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+        assert_eq!(buffer.file_path, None);
+        assert!(buffer.modified);
+        assert_eq!(state.buffers.len(), 2);
+
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn skip_journals_of_running_editors() {
+        let dir = std::env::temp_dir().join(format!("rift_journals_{}", std::process::id()));
+        let journal = Journal {
+            path: None,
+            content: "notes\n".into(),
+        };
+        // The journal of this process, whose editor is still running
+        let live = dir.join(journal_name(std::process::id(), None, 1));
+        write_journal(&live, &journal).unwrap();
+        // The journal of a process which has exited, as after a crash
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "exit"])
+            .spawn()
+            .unwrap();
+        let exited = child.id();
+        child.wait().unwrap();
+        let crashed = dir.join(journal_name(exited, None, 1));
+        write_journal(&crashed, &journal).unwrap();
+
+        assert_eq!(journals_in(&dir), vec![(crashed, journal)]);
+        assert!(live.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
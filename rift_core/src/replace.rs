@@ -0,0 +1,331 @@
+//! Regex search and replace in the current buffer or selection,
+//! either all at once or confirming each match.
+
+use anyhow::{bail, Context, Result};
+use regex::{Regex, RegexBuilder};
+
+use crate::buffer::instance::{Cursor, Selection};
+
+/// Options of a replacement given as letters, such as `ic`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplaceFlags {
+    /// `i`: match regardless of case
+    pub case_insensitive: bool,
+    /// `c`: ask before replacing each match
+    pub confirm: bool,
+}
+
+impl ReplaceFlags {
+    pub fn parse(flags: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+        for flag in flags.trim().chars() {
+            match flag {
+                'i' => parsed.case_insensitive = true,
+                'c' => parsed.confirm = true,
+                _ => bail!("Unknown replace flag: {}", flag),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Answer to the confirmation of a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ReplaceChoice {
+    Replace,
+    Skip,
+    /// Replace this and all remaining matches
+    All,
+    Quit,
+}
+
+/// Match with its replacement, `$1` and `${name}` expanded from its capture groups
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplaceMatch {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+pub fn build_regex(pattern: &str, flags: &ReplaceFlags) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(flags.case_insensitive)
+        .multi_line(true)
+        .build()
+        .with_context(|| format!("Invalid pattern: {}", pattern))
+}
+
+/// Parse `pattern/replacement/flags`, where `\/` is a literal slash
+pub fn parse_replace_command(input: &str) -> Result<(String, String, String)> {
+    let mut parts = vec![String::new()];
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'/') => {
+                parts.last_mut().unwrap().push(chars.next().unwrap());
+            }
+            '/' if parts.len() < 3 => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+    }
+    if parts.len() < 2 || parts[0].is_empty() {
+        bail!("Expected pattern/replacement/flags");
+    }
+    parts.resize(3, String::new());
+    let flags = parts.pop().unwrap();
+    let replacement = parts.pop().unwrap();
+    let pattern = parts.pop().unwrap();
+    Ok((pattern, replacement, flags))
+}
+
+/// Next match starting at or after `from` and ending before `to`
+pub fn next_match(
+    regex: &Regex,
+    replacement: &str,
+    content: &str,
+    from: usize,
+    to: usize,
+) -> Option<ReplaceMatch> {
+    let captures = regex.captures_at(content, from)?;
+    let found = captures.get(0)?;
+    if found.end() > to {
+        return None;
+    }
+    let mut expanded = String::new();
+    captures.expand(replacement, &mut expanded);
+    Some(ReplaceMatch {
+        start: found.start(),
+        end: found.end(),
+        replacement: expanded,
+    })
+}
+
+/// Position to continue searching from after a match,
+/// skipping a character after empty matches
+pub fn next_start(content: &str, found: &ReplaceMatch) -> usize {
+    if found.start < found.end {
+        return found.end;
+    }
+    content[found.end..]
+        .chars()
+        .next()
+        .map(|c| found.end + c.len_utf8())
+        .unwrap_or(content.len() + 1)
+}
+
+/// All matches between the byte offsets
+pub fn find_matches(
+    regex: &Regex,
+    replacement: &str,
+    content: &str,
+    from: usize,
+    to: usize,
+) -> Vec<ReplaceMatch> {
+    let mut matches = vec![];
+    let mut start = from;
+    while start <= to.min(content.len()) {
+        let Some(found) = next_match(regex, replacement, content, start, to) else {
+            break;
+        };
+        start = next_start(content, &found);
+        matches.push(found);
+    }
+    matches
+}
+
+/// Text from the start of the first match to the end of the last match with the matches replaced
+pub fn apply_matches(content: &str, matches: &[ReplaceMatch]) -> String {
+    let (Some(first), Some(last)) = (matches.first(), matches.last()) else {
+        return String::new();
+    };
+    let mut text = String::new();
+    let mut position = first.start;
+    for found in matches {
+        text.push_str(&content[position..found.start]);
+        text.push_str(&found.replacement);
+        position = found.end;
+    }
+    text.push_str(&content[position..last.end]);
+    text
+}
+
+/// Cursor at a byte offset of the lines joined by "\n"
+pub fn cursor_from_byte(lines: &[String], mut byte: usize) -> Cursor {
+    for (row, line) in lines.iter().enumerate() {
+        if byte <= line.len() {
+            return Cursor { row, column: byte };
+        }
+        byte -= line.len() + 1;
+    }
+    let row = lines.len().saturating_sub(1);
+    Cursor {
+        row,
+        column: lines.last().map(|line| line.len()).unwrap_or(0),
+    }
+}
+
+pub fn selection_from_bytes(lines: &[String], start: usize, end: usize) -> Selection {
+    Selection {
+        mark: cursor_from_byte(lines, start),
+        cursor: cursor_from_byte(lines, end),
+    }
+}
+
+/// Replacement in progress which asks before replacing each match
+#[derive(Debug)]
+pub struct ReplaceSession {
+    pub buffer_id: u32,
+    pub regex: Regex,
+    pub replacement: String,
+    /// Match which is selected and waiting for confirmation
+    pub current: Option<ReplaceMatch>,
+    /// End of the replaced region, moved as matches are replaced
+    pub end: usize,
+    pub replaced: usize,
+}
+
+/// Pending replacements in a file of the workspace
+#[derive(Debug)]
+pub struct FileReplace {
+    pub path: String,
+    /// Content the matches were found in, the file must not change before applying them
+    pub content: String,
+    pub matches: Vec<ReplaceMatch>,
+}
+
+/// Replacement across the workspace waiting for confirmation in its preview buffer
+#[derive(Debug)]
+pub struct WorkspaceReplace {
+    pub preview_buffer_id: u32,
+    pub files: Vec<FileReplace>,
+}
+
+/// Changed lines of a file as row, old lines and new lines.
+/// Matches on the same lines are grouped into a single change.
+pub fn preview_changes(content: &str, matches: &[ReplaceMatch]) -> Vec<(usize, String, String)> {
+    let line_start = |byte: usize| content[..byte].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+    let line_end = |byte: usize| {
+        content[byte..]
+            .find('\n')
+            .map(|idx| byte + idx)
+            .unwrap_or(content.len())
+    };
+
+    let mut groups: Vec<(usize, usize, Vec<ReplaceMatch>)> = vec![];
+    for found in matches {
+        let (start, end) = (line_start(found.start), line_end(found.end));
+        match groups.last_mut() {
+            Some((_, group_end, group)) if start <= *group_end => {
+                *group_end = end;
+                group.push(found.clone());
+            }
+            _ => groups.push((start, end, vec![found.clone()])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(start, end, group)| {
+            let first = group.first().unwrap();
+            let last = group.last().unwrap();
+            let new = format!(
+                "{}{}{}",
+                &content[start..first.start],
+                apply_matches(content, &group),
+                &content[last.end..end]
+            );
+            let row = content[..start].matches('\n').count();
+            (row, content[start..end].to_string(), new)
+        })
+        .collect()
+}
+
+/// Preview buffer text of the changes grouped by file
+pub fn preview_text(pattern: &str, replacement: &str, files: &[FileReplace]) -> String {
+    let count: usize = files.iter().map(|file| file.matches.len()).sum();
+    let mut text = format!(
+        "Replace /{}/ with \"{}\": {} matches in {} files\n\
+         Run \"Apply Workspace Replace\" to apply the changes or close this buffer to cancel\n",
+        pattern,
+        replacement,
+        count,
+        files.len()
+    );
+    for file in files {
+        text.push_str(&format!("\n{}\n", file.path));
+        for (row, old, new) in preview_changes(&file.content, &file.matches) {
+            for (offset, line) in old.lines().enumerate() {
+                text.push_str(&format!("{:>5} - {}\n", row + offset + 1, line));
+            }
+            for (offset, line) in new.lines().enumerate() {
+                text.push_str(&format!("{:>5} + {}\n", row + offset + 1, line));
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_matches, build_regex, cursor_from_byte, find_matches, parse_replace_command,
+        preview_changes, ReplaceFlags,
+    };
+    use crate::buffer::instance::Cursor;
+
+    #[test]
+    fn regex_replace() {
+        assert_eq!(
+            parse_replace_command(r"(\w+)@b\/c/$1 at b/ic").unwrap(),
+            (
+                r"(\w+)@b/c".to_string(),
+                "$1 at b".to_string(),
+                "ic".to_string()
+            )
+        );
+        assert_eq!(
+            parse_replace_command("a/").unwrap(),
+            ("a".to_string(), String::new(), String::new())
+        );
+        assert!(parse_replace_command("abc").is_err());
+        let flags = ReplaceFlags::parse("ic").unwrap();
+        assert!(flags.case_insensitive && flags.confirm);
+        assert!(ReplaceFlags::parse("x").is_err());
+
+        let content = "Fn one() {}\nfn two() {}\n";
+        let regex = build_regex(r"^fn (\w+)", &ReplaceFlags::parse("i").unwrap()).unwrap();
+        let matches = find_matches(&regex, "pub fn ${1}_x", content, 0, content.len());
+        assert_eq!(matches.len(), 2);
+        assert_eq!(
+            apply_matches(content, &matches),
+            "pub fn one_x() {}\npub fn two_x"
+        );
+        // Matches outside of the region are not replaced
+        assert_eq!(find_matches(&regex, "", content, 1, content.len()).len(), 1);
+        assert_eq!(find_matches(&regex, "", content, 0, 10).len(), 1);
+
+        let content = "a b\na\nc\n";
+        let regex = build_regex("a", &ReplaceFlags::default()).unwrap();
+        let matches = find_matches(&regex, "x", content, 0, content.len());
+        assert_eq!(
+            preview_changes(content, &matches),
+            vec![
+                (0, "a b".to_string(), "x b".to_string()),
+                (1, "a".to_string(), "x".to_string())
+            ]
+        );
+        let regex = build_regex("b\na", &ReplaceFlags::default()).unwrap();
+        let matches = find_matches(&regex, "-", content, 0, content.len());
+        assert_eq!(
+            preview_changes(content, &matches),
+            vec![(0, "a b\na".to_string(), "a -".to_string())]
+        );
+
+        let regex = build_regex("x*", &ReplaceFlags::default()).unwrap();
+        assert_eq!(find_matches(&regex, "-", "ab", 0, 2).len(), 3);
+
+        let lines = vec!["ab".to_string(), "cd".to_string()];
+        assert_eq!(cursor_from_byte(&lines, 4), Cursor { row: 1, column: 1 });
+        assert_eq!(cursor_from_byte(&lines, 2), Cursor { row: 0, column: 2 });
+    }
+}
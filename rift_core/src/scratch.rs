@@ -0,0 +1,63 @@
+//! Named scratch buffers for notes and experiments.
+//! They are files in the config directory which are saved without asking
+//! when closed or when the editor quits.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::io::file_io;
+
+/// Extension of scratch buffers named without one
+const DEFAULT_EXTENSION: &str = "md";
+
+pub fn scratch_dir() -> Option<PathBuf> {
+    file_io::config_dir().map(|dir| dir.join("scratch"))
+}
+
+/// File name of a scratch buffer keeping only characters safe in paths.
+/// An extension can be given to pick the language, such as `experiment.py`.
+pub fn scratch_file_name(name: &str) -> Result<String> {
+    let name: String = name
+        .trim()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' '))
+        .collect();
+    let name = name.trim_matches('.');
+    if name.is_empty() {
+        bail!("Invalid scratch buffer name");
+    }
+    if Path::new(name).extension().is_some() {
+        Ok(name.to_string())
+    } else {
+        Ok(format!("{}.{}", name, DEFAULT_EXTENSION))
+    }
+}
+
+/// Path of the scratch buffer, creating the file if it does not exist
+pub fn create_scratch(name: &str) -> Result<String> {
+    let dir = scratch_dir().context("No config directory")?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(scratch_file_name(name)?);
+    if !path.exists() {
+        std::fs::write(&path, "")?;
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+pub fn is_scratch(path: &str) -> bool {
+    scratch_dir().is_some_and(|dir| Path::new(path).starts_with(dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scratch_file_name;
+
+    #[test]
+    fn sanitize_scratch_names() {
+        assert_eq!(scratch_file_name("todo").unwrap(), "todo.md");
+        assert_eq!(scratch_file_name("repl.py").unwrap(), "repl.py");
+        assert_eq!(scratch_file_name("../notes").unwrap(), "notes.md");
+        assert!(scratch_file_name("/").is_err());
+    }
+}
@@ -0,0 +1,408 @@
+//! Search within the current buffer or across the workspace,
+//! with a history of queries persisted across sessions.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use regex::Regex;
+
+use crate::{
+    actions::Action,
+    buffer::instance::{Cursor, Selection},
+    io::{file_io, gitignore::Gitignore},
+    replace::{self, ReplaceFlags},
+    tasks::QuickfixEntry,
+};
+
+/// Number of past searches which are remembered
+pub const SEARCH_HISTORY_SIZE: usize = 100;
+
+/// Workspace search stops after this many matches
+const MAX_WORKSPACE_MATCHES: usize = 1000;
+
+/// Files larger than this are skipped by workspace search
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Directories which are not searched in addition to hidden ones
+const IGNORED_DIRECTORIES: [&str; 3] = ["target", "node_modules", "__pycache__"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    Buffer,
+    Workspace,
+}
+
+impl SearchKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Buffer => "buffer",
+            Self::Workspace => "workspace",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "buffer" => Some(Self::Buffer),
+            "workspace" => Some(Self::Workspace),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PastSearch {
+    pub kind: SearchKind,
+    pub query: String,
+}
+
+impl PastSearch {
+    /// Action running the search again
+    pub fn action(&self) -> Action {
+        match self.kind {
+            SearchKind::Buffer => Action::SearchBuffer(self.query.clone()),
+            SearchKind::Workspace => Action::SearchWorkspace(self.query.clone()),
+        }
+    }
+
+    /// Parse the label shown in the search history picker
+    pub fn parse(label: &str) -> Result<Self> {
+        let Some((kind, query)) = label
+            .split_once(": ")
+            .and_then(|(kind, query)| Some((SearchKind::parse(kind)?, query)))
+        else {
+            bail!("Not a past search: {}", label);
+        };
+        Ok(Self {
+            kind,
+            query: query.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for PastSearch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.name(), self.query)
+    }
+}
+
+/// Past searches, most recent first
+#[derive(Debug, Default)]
+pub struct SearchHistory {
+    pub entries: VecDeque<PastSearch>,
+    /// File the history is persisted to across sessions
+    path: Option<PathBuf>,
+}
+
+impl SearchHistory {
+    /// Load the history saved in the config directory.
+    /// Each line is the kind of search and the query separated by a tab.
+    pub fn load() -> Self {
+        let mut history = Self {
+            path: file_io::config_dir().map(|dir| dir.join("search_history")),
+            ..Default::default()
+        };
+        let Some(content) = history
+            .path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+        else {
+            return history;
+        };
+        history.entries = content
+            .lines()
+            .filter_map(|line| {
+                let (kind, query) = line.split_once('\t')?;
+                Some(PastSearch {
+                    kind: SearchKind::parse(kind)?,
+                    query: query.to_string(),
+                })
+            })
+            .take(SEARCH_HISTORY_SIZE)
+            .collect();
+        history
+    }
+
+    /// Record a search, moving it to the front if it was run before
+    pub fn push(&mut self, kind: SearchKind, query: &str) {
+        // Queries spanning lines cannot be stored one per line
+        if query.is_empty() || query.contains('\n') {
+            return;
+        }
+        let search = PastSearch {
+            kind,
+            query: query.to_string(),
+        };
+        self.entries.retain(|entry| *entry != search);
+        self.entries.push_front(search);
+        self.entries.truncate(SEARCH_HISTORY_SIZE);
+        if let Err(err) = self.save() {
+            tracing::warn!("Failed to save search history: {}", err);
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content: String = self
+            .entries
+            .iter()
+            .map(|entry| format!("{}\t{}\n", entry.kind.name(), entry.query))
+            .collect();
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Past queries of the kind, most recent first
+    pub fn queries(&self, kind: SearchKind) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .map(|entry| entry.query.clone())
+            .collect()
+    }
+
+    /// Labels of all past searches for the search history picker
+    pub fn labels(&self) -> Vec<String> {
+        self.entries.iter().map(|entry| entry.to_string()).collect()
+    }
+}
+
+/// Start of each match of the query in the lines
+pub fn find_matches(lines: &[String], query: &str) -> Vec<Cursor> {
+    if query.is_empty() {
+        return vec![];
+    }
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.match_indices(query)
+                .map(move |(column, _)| Cursor { row, column })
+        })
+        .collect()
+}
+
+/// Selection of the next match of the query after the cursor (or before it
+/// when searching backwards), wrapping around at the end of the lines
+pub fn find_in_lines(
+    lines: &[String],
+    query: &str,
+    cursor: &Cursor,
+    backwards: bool,
+) -> Option<Selection> {
+    let matches = find_matches(lines, query);
+    let found = if backwards {
+        matches
+            .iter()
+            .rev()
+            .find(|start| *start < cursor)
+            .or(matches.last())
+    } else {
+        matches
+            .iter()
+            .find(|start| *start > cursor)
+            .or(matches.first())
+    }?;
+    Some(Selection {
+        mark: *found,
+        cursor: Cursor {
+            row: found.row,
+            column: found.column + query.len(),
+        },
+    })
+}
+
+/// Text files of the folder and its subfolders in order, skipping hidden
+/// and ignored folders, files ignored by git and large files
+pub fn workspace_files(folder: &str) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut ignore = Gitignore::default();
+    let mut folders = vec![PathBuf::from(folder)];
+    while let Some(folder) = folders.pop() {
+        let Ok(dir_entries) = std::fs::read_dir(&folder) else {
+            continue;
+        };
+        // Folders are walked depth first so the patterns of parents are read first
+        ignore.add_folder(&folder.to_string_lossy());
+        let mut dir_entries: Vec<_> = dir_entries.flatten().collect();
+        dir_entries.sort_by_key(|entry| entry.file_name());
+        let mut subfolders = vec![];
+        for entry in dir_entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if ignore.is_ignored(&entry.path().to_string_lossy(), metadata.is_dir()) {
+                continue;
+            }
+            if metadata.is_dir() {
+                if !name.starts_with('.') && !IGNORED_DIRECTORIES.contains(&name.as_str()) {
+                    subfolders.push(entry.path());
+                }
+            } else if metadata.len() <= MAX_FILE_SIZE {
+                files.push(entry.path());
+            }
+        }
+        // Pushed in reverse so that subfolders are searched in order
+        folders.extend(subfolders.into_iter().rev());
+    }
+    files
+}
+
+/// Text searched for across the workspace, `/pattern/` or `/pattern/i` is a regex
+#[derive(Debug, Clone)]
+pub enum WorkspaceQuery {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl WorkspaceQuery {
+    pub fn parse(query: &str) -> Result<Self> {
+        let Some(rest) = query.strip_prefix('/') else {
+            return Ok(Self::Literal(query.to_string()));
+        };
+        let Some((pattern, flags)) = rest.rsplit_once('/') else {
+            return Ok(Self::Literal(query.to_string()));
+        };
+        let flags = match flags {
+            "" => ReplaceFlags::default(),
+            "i" => ReplaceFlags {
+                case_insensitive: true,
+                ..Default::default()
+            },
+            // Paths such as `/usr/bin` are searched for literally
+            _ => return Ok(Self::Literal(query.to_string())),
+        };
+        Ok(Self::Regex(replace::build_regex(pattern, &flags)?))
+    }
+
+    /// Column of the first match in the line
+    pub fn find(&self, line: &str) -> Option<usize> {
+        match self {
+            Self::Literal(query) => line.find(query.as_str()),
+            Self::Regex(regex) => regex.find(line).map(|found| found.start()),
+        }
+    }
+}
+
+/// Lines of the file matching the query
+fn search_file(file: &Path, query: &WorkspaceQuery) -> Vec<QuickfixEntry> {
+    // Binary and non utf-8 files fail to read and are skipped
+    let Ok(content) = std::fs::read_to_string(file) else {
+        return vec![];
+    };
+    let path = file.to_string_lossy().to_string();
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(row, line)| {
+            query.find(line).map(|column| QuickfixEntry {
+                path: path.clone(),
+                cursor: Cursor { row, column },
+                message: line.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Lines matching the query in the files of the folder,
+/// the files are searched in parallel and the matches kept in order
+pub fn search_workspace(folder: &str, query: &str) -> Result<Vec<QuickfixEntry>> {
+    let query = WorkspaceQuery::parse(query)?;
+    let files = workspace_files(folder);
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = files.len().div_ceil(threads).max(1);
+    let mut entries: Vec<QuickfixEntry> = std::thread::scope(|scope| {
+        let handles: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let query = &query;
+                scope.spawn(move || {
+                    let mut entries = vec![];
+                    for file in chunk {
+                        entries.extend(search_file(file, query));
+                        if entries.len() >= MAX_WORKSPACE_MATCHES {
+                            break;
+                        }
+                    }
+                    entries
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+    entries.truncate(MAX_WORKSPACE_MATCHES);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_in_lines, search_workspace, PastSearch, SearchHistory, SearchKind};
+    use crate::buffer::instance::Cursor;
+
+    #[test]
+    fn search_and_history() {
+        let lines = vec!["let a = b;".to_string(), "a += b".to_string()];
+        let cursor = Cursor { row: 0, column: 4 };
+        let found = find_in_lines(&lines, "a", &cursor, false).unwrap();
+        assert_eq!(found.mark, Cursor { row: 1, column: 0 });
+        assert_eq!(found.cursor, Cursor { row: 1, column: 1 });
+        let found = find_in_lines(&lines, "a", &found.mark, false).unwrap();
+        assert_eq!(found.mark, Cursor { row: 0, column: 4 });
+        let found = find_in_lines(&lines, "b", &cursor, true).unwrap();
+        assert_eq!(found.mark, Cursor { row: 1, column: 5 });
+        assert!(find_in_lines(&lines, "c", &cursor, false).is_none());
+
+        let mut history = SearchHistory::default();
+        history.push(SearchKind::Buffer, "a");
+        history.push(SearchKind::Workspace, "fn main");
+        history.push(SearchKind::Buffer, "b");
+        history.push(SearchKind::Buffer, "a");
+        assert_eq!(history.queries(SearchKind::Buffer), vec!["a", "b"]);
+        assert_eq!(history.labels()[2], "workspace: fn main");
+        assert_eq!(
+            PastSearch::parse("workspace: fn main").unwrap(),
+            history.entries[2]
+        );
+        assert!(PastSearch::parse("fn main").is_err());
+    }
+
+    #[test]
+    fn workspace_search() {
+        let root = std::env::temp_dir().join(format!("rift_search_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("build")).unwrap();
+        std::fs::write(root.join(".gitignore"), "build/\n*.log\n").unwrap();
+        std::fs::write(
+            root.join("src").join("main.rs"),
+            "fn main() {}\nfn helper() {}\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("build").join("out.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("debug.log"), "fn main\n").unwrap();
+        let folder = root.to_str().unwrap();
+
+        let entries = search_workspace(folder, "fn main").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].path.ends_with("main.rs"));
+
+        let entries = search_workspace(folder, "/F[a-z]+ h/i").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].cursor.row, 1);
+        assert!(search_workspace(folder, "/(/").is_err());
+        assert!(search_workspace(folder, "/src/main").unwrap().is_empty());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+//! Open buffers of each workspace, saved when quitting and restored when the
+//! workspace is opened again, and recently opened files.
+
+use std::{collections::VecDeque, path::PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{buffer::instance::Cursor, io::file_io, state::EditorState};
+
+/// Number of recently opened files which are remembered
+pub const RECENT_FILES_SIZE: usize = 50;
+
+/// Recently opened files, most recent first
+#[derive(Debug, Default)]
+pub struct RecentFiles {
+    pub entries: VecDeque<String>,
+    /// File the list is persisted to across sessions
+    path: Option<PathBuf>,
+}
+
+impl RecentFiles {
+    /// Load the list saved in the config directory, one path per line
+    pub fn load() -> Self {
+        let mut recent = Self {
+            path: file_io::config_dir().map(|dir| dir.join("recent_files")),
+            ..Default::default()
+        };
+        if let Some(content) = recent
+            .path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+        {
+            recent.entries = content.lines().map(|line| line.to_string()).collect();
+            recent.entries.truncate(RECENT_FILES_SIZE);
+        }
+        recent
+    }
+
+    pub fn push(&mut self, path: &str) {
+        self.entries.retain(|entry| entry != path);
+        self.entries.push_front(path.to_string());
+        self.entries.truncate(RECENT_FILES_SIZE);
+        if let Err(err) = self.save() {
+            tracing::warn!("Failed to save recent files: {}", err);
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content: String = self
+            .entries
+            .iter()
+            .map(|entry| format!("{}\n", entry))
+            .collect();
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferSession {
+    pub path: String,
+    pub cursor: Cursor,
+    /// Line and part of the line at the top of the view
+    pub scroll: Cursor,
+}
+
+/// Open files of a workspace in tab order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub workspace: String,
+    pub buffers: Vec<BufferSession>,
+    /// Index of the buffer which was active
+    pub active: Option<usize>,
+}
+
+impl Session {
+    /// Files open in the editor, buffers without a file and encrypted files are left out
+    pub fn capture(state: &EditorState) -> Self {
+        let mut session = Self {
+            workspace: state.workspace_folder.clone(),
+            ..Default::default()
+        };
+        for id in &state.buffer_order {
+            let (Some(buffer), Some(instance)) = (state.buffers.get(id), state.instances.get(id))
+            else {
+                continue;
+            };
+            let Some(path) = &buffer.file_path else {
+                continue;
+            };
+            if state.encrypted_files.contains_key(path) {
+                continue;
+            }
+            if state.buffer_idx == Some(*id) {
+                session.active = Some(session.buffers.len());
+            }
+            session.buffers.push(BufferSession {
+                path: path.clone(),
+                cursor: instance.cursor,
+                scroll: instance.scroll,
+            });
+        }
+        session
+    }
+}
+
+/// File the session of the workspace is saved to
+pub fn session_path(workspace: &str) -> Option<PathBuf> {
+    let name: String = workspace
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    file_io::config_dir().map(|dir| dir.join("sessions").join(format!("{}.json", name)))
+}
+
+pub fn save_session(state: &EditorState) -> Result<()> {
+    let path = session_path(&state.workspace_folder).context("No config directory")?;
+    let session = Session::capture(state);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(&session)?)?;
+    Ok(())
+}
+
+pub fn load_session(workspace: &str) -> Option<Session> {
+    let content = std::fs::read_to_string(session_path(workspace)?).ok()?;
+    let session: Session = serde_json::from_str(&content).ok()?;
+    // Workspaces whose names only differ in punctuation share a file
+    (session.workspace == workspace).then_some(session)
+}
+
+/// Open the files of the session saved for the workspace.
+/// Returns false if there is no session or none of its files exist anymore.
+pub fn restore_session(state: &mut EditorState) -> bool {
+    let Some(session) = load_session(&state.workspace_folder) else {
+        return false;
+    };
+    let mut active = None;
+    for (idx, buffer) in session.buffers.iter().enumerate() {
+        if !std::path::Path::new(&buffer.path).is_file() {
+            continue;
+        }
+        state.open_location(&buffer.path, buffer.cursor, &None);
+        let Some(id) = state.buffer_idx else {
+            continue;
+        };
+        let (buffer_content, instance) = state.get_buffer_by_id_mut(id);
+        instance.scroll.row = buffer.scroll.row.min(buffer_content.get_num_lines() - 1);
+        if session.active == Some(idx) || active.is_none() {
+            active = Some(id);
+        }
+    }
+    if active.is_some() {
+        state.buffer_idx = active;
+        state.update_view = true;
+    }
+    active.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BufferSession, Session};
+    use crate::buffer::instance::Cursor;
+
+    #[test]
+    fn session_round_trip() {
+        let session = Session {
+            workspace: "/home/user/project".to_string(),
+            buffers: vec![BufferSession {
+                path: "/home/user/project/main.rs".to_string(),
+                cursor: Cursor { row: 4, column: 2 },
+                scroll: Cursor { row: 1, column: 0 },
+            }],
+            active: Some(0),
+        };
+        let json = serde_json::to_string(&session).unwrap();
+        assert_eq!(serde_json::from_str::<Session>(&json).unwrap(), session);
+    }
+}
@@ -0,0 +1,492 @@
+//! Snippets in LSP snippet syntax (`$1`, `${2:placeholder}`, `$0`) expanded with
+//! tab-stops to jump between. User snippets are loaded from `snippets/<language>.json`
+//! in the config directory, in the same format as vscode snippet files.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    abbreviations,
+    buffer::{
+        instance::{Cursor, Language, Selection},
+        marks::{shift_after_insert, shift_after_remove},
+    },
+    io::file_io,
+    lsp::client::LSPClientHandle,
+    state::{EditorState, Mode},
+};
+
+/// Text of a snippet with the byte ranges of its tab-stops in the order they are visited
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedSnippet {
+    pub text: String,
+    pub tab_stops: Vec<(usize, usize)>,
+}
+
+/// Tab-stop or placeholder found while parsing as its index and byte range
+type Stop = (usize, usize, usize);
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+/// Snippet text being parsed
+struct SnippetParser<'a> {
+    text: String,
+    stops: Vec<Stop>,
+    /// Placeholders by index, used to fill in repeated tab-stops
+    placeholders: &'a HashMap<usize, String>,
+}
+
+impl SnippetParser<'_> {
+    /// Parse text until the end or until the `}` closing a placeholder
+    fn parse_text(&mut self, chars: &mut Chars, in_placeholder: bool) {
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if matches!(chars.peek(), Some('$' | '}' | '\\')) => {
+                    self.text.push(chars.next().unwrap());
+                }
+                '}' if in_placeholder => return,
+                '$' => self.parse_dollar(chars),
+                _ => self.text.push(c),
+            }
+        }
+    }
+
+    /// Parse a tab-stop, placeholder, choice or variable following a `$`
+    fn parse_dollar(&mut self, chars: &mut Chars) {
+        let start = self.text.len();
+        let braced = chars.next_if_eq(&'{').is_some();
+        let Some(index) = parse_number(chars) else {
+            // Variables are not supported and expand to their default or nothing
+            let name = parse_name(chars);
+            if !braced {
+                if name.is_empty() {
+                    self.text.push('$');
+                }
+            } else if chars.next_if_eq(&':').is_some() {
+                self.parse_text(chars, true);
+            } else {
+                chars.next_if_eq(&'}');
+            }
+            return;
+        };
+        match braced.then(|| chars.next()).flatten() {
+            Some(':') => self.parse_text(chars, true),
+            // The first choice is inserted
+            Some('|') => {
+                let choices: String = chars.by_ref().take_while(|c| *c != '|').collect();
+                self.text
+                    .push_str(choices.split(',').next().unwrap_or_default());
+                chars.next_if_eq(&'}');
+            }
+            _ => {
+                if let Some(placeholder) = self.placeholders.get(&index) {
+                    self.text.push_str(placeholder);
+                }
+            }
+        }
+        self.stops.push((index, start, self.text.len()));
+    }
+}
+
+fn parse_number(chars: &mut Chars) -> Option<usize> {
+    let mut number = String::new();
+    while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+        number.push(digit);
+    }
+    number.parse().ok()
+}
+
+fn parse_name(chars: &mut Chars) -> String {
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+        name.push(c);
+    }
+    name
+}
+
+/// Parse snippet syntax. Tab-stops are visited in increasing order with `$0` last
+/// (or the end of the snippet if there is none), and repeated tab-stops are
+/// filled in with the placeholder of the tab-stop.
+pub fn parse_snippet(body: &str) -> ParsedSnippet {
+    let parse = |placeholders: &HashMap<usize, String>| {
+        let mut parser = SnippetParser {
+            text: String::new(),
+            stops: vec![],
+            placeholders,
+        };
+        parser.parse_text(&mut body.chars().peekable(), false);
+        (parser.text, parser.stops)
+    };
+    let (text, stops) = parse(&HashMap::new());
+    let mut placeholders = HashMap::new();
+    for (index, start, end) in stops {
+        if start < end {
+            placeholders
+                .entry(index)
+                .or_insert_with(|| text[start..end].to_string());
+        }
+    }
+    let (text, mut stops) = parse(&placeholders);
+
+    stops.sort_by_key(|(index, start, _)| (*index == 0, *index, *start));
+    stops.dedup_by_key(|(index, _, _)| *index);
+    let mut tab_stops: Vec<(usize, usize)> =
+        stops.iter().map(|(_, start, end)| (*start, *end)).collect();
+    if !stops.iter().any(|(index, _, _)| *index == 0) {
+        tab_stops.push((text.len(), text.len()));
+    }
+    ParsedSnippet { text, tab_stops }
+}
+
+/// Tab-stops of a snippet being filled in, kept in place through edits of the buffer
+#[derive(Debug, Clone)]
+pub struct SnippetSession {
+    pub tab_stops: Vec<Selection>,
+    pub current: usize,
+}
+
+impl SnippetSession {
+    pub fn adjust_insert(&mut self, start: &Cursor, end: &Cursor) {
+        for tab_stop in &mut self.tab_stops {
+            // Text typed at a tab-stop extends it
+            shift_after_insert(&mut tab_stop.mark, start, end, false);
+            shift_after_insert(&mut tab_stop.cursor, start, end, true);
+        }
+    }
+
+    pub fn adjust_remove(&mut self, start: &Cursor, end: &Cursor) {
+        for tab_stop in &mut self.tab_stops {
+            shift_after_remove(&mut tab_stop.mark, start, end);
+            shift_after_remove(&mut tab_stop.cursor, start, end);
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(untagged)]
+enum Lines {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Lines {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::Single(line) => vec![line],
+            Self::Multiple(lines) => lines,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct SnippetDefinition {
+    prefix: Lines,
+    body: Lines,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub name: String,
+    pub prefix: String,
+    pub body: String,
+}
+
+/// Snippets of each language, user snippets first
+#[derive(Debug, Default)]
+pub struct Snippets {
+    pub languages: HashMap<Language, Vec<Snippet>>,
+}
+
+impl Snippets {
+    /// Load the user snippets of each language followed by the default snippets
+    pub fn load() -> Self {
+        let mut snippets = default_snippets();
+        let Some(dir) = file_io::config_dir().map(|dir| dir.join("snippets")) else {
+            return snippets;
+        };
+        for (language, defaults) in snippets.languages.iter_mut() {
+            let path = dir.join(format!("{:?}.json", language).to_lowercase());
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            match parse_snippet_file(&content) {
+                Ok(mut user_snippets) => {
+                    user_snippets.append(defaults);
+                    *defaults = user_snippets;
+                }
+                Err(err) => tracing::warn!("Failed to load {}: {:#}", path.display(), err),
+            }
+        }
+        snippets
+    }
+
+    pub fn get(&self, language: Language, prefix: &str) -> Option<&Snippet> {
+        self.languages
+            .get(&language)?
+            .iter()
+            .find(|snippet| snippet.prefix == prefix)
+    }
+
+    /// Labels of the snippets of a language for the snippet picker, such as `fn: Function`
+    pub fn labels(&self, language: Language) -> Vec<String> {
+        self.languages
+            .get(&language)
+            .map(|snippets| {
+                snippets
+                    .iter()
+                    .map(|snippet| format!("{}: {}", snippet.prefix, snippet.name))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Prefix of a snippet from its label or a prefix typed in the picker
+    pub fn parse_label(label: &str) -> &str {
+        label.split(':').next().unwrap_or_default().trim()
+    }
+}
+
+/// Snippets of a vscode snippet file, one for each prefix
+pub fn parse_snippet_file(content: &str) -> Result<Vec<Snippet>> {
+    let definitions: HashMap<String, SnippetDefinition> =
+        serde_json::from_str(content).context("Malformed snippet file")?;
+    let mut snippets: Vec<Snippet> = definitions
+        .into_iter()
+        .flat_map(|(name, definition)| {
+            let body = definition.body.into_vec().join("\n");
+            definition
+                .prefix
+                .into_vec()
+                .into_iter()
+                .map(move |prefix| Snippet {
+                    name: name.clone(),
+                    prefix,
+                    body: body.clone(),
+                })
+        })
+        .collect();
+    snippets.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    Ok(snippets)
+}
+
+pub fn default_snippets() -> Snippets {
+    let snippets = |snippets: &[(&str, &str, &str)]| -> Vec<Snippet> {
+        snippets
+            .iter()
+            .map(|(prefix, name, body)| Snippet {
+                name: name.to_string(),
+                prefix: prefix.to_string(),
+                body: body.to_string(),
+            })
+            .collect()
+    };
+    let languages = [
+        Language::PlainText,
+        Language::Rust,
+        Language::Python,
+        Language::Markdown,
+        Language::TOML,
+        Language::CSS,
+        Language::HTML,
+    ];
+    let mut snippets_by_language: HashMap<Language, Vec<Snippet>> = languages
+        .into_iter()
+        .map(|language| (language, vec![]))
+        .collect();
+    snippets_by_language.insert(
+        Language::Rust,
+        snippets(&[
+            ("fn", "Function", "fn ${1:name}($2) {\n\t$0\n}"),
+            ("test", "Test", "#[test]\nfn ${1:name}() {\n\t$0\n}"),
+            ("impl", "Impl block", "impl ${1:Type} {\n\t$0\n}"),
+        ]),
+    );
+    snippets_by_language.insert(
+        Language::Python,
+        snippets(&[
+            ("def", "Function", "def ${1:name}($2):\n\t${0:pass}"),
+            ("class", "Class", "class ${1:Name}:\n\t${0:pass}"),
+        ]),
+    );
+    Snippets {
+        languages: snippets_by_language,
+    }
+}
+
+/// Cursor at a byte offset of text inserted at the position
+fn cursor_in_inserted(position: Cursor, text: &str, offset: usize) -> Cursor {
+    let before = &text[..offset];
+    match before.rfind('\n') {
+        Some(line_start) => Cursor {
+            row: position.row + before.matches('\n').count(),
+            column: offset - line_start - 1,
+        },
+        None => Cursor {
+            row: position.row,
+            column: position.column + offset,
+        },
+    }
+}
+
+/// Insert a snippet at the position, indented like the line, and select its first tab-stop
+pub fn insert_snippet(
+    state: &mut EditorState,
+    body: &str,
+    position: Cursor,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) {
+    let tab = state.indent_unit(state.buffer_idx.unwrap());
+    let (buffer, _instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+    let line = &buffer.lines[position.row];
+    let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+    let body = body
+        .replace('\t', &tab)
+        .replace('\n', &format!("\n{}", indent));
+
+    let parsed = parse_snippet(&body);
+    buffer.insert_text(&parsed.text, &position, lsp_handle, true);
+    let tab_stops: Vec<Selection> = parsed
+        .tab_stops
+        .iter()
+        .map(|(start, end)| Selection {
+            mark: cursor_in_inserted(position, &parsed.text, *start),
+            cursor: cursor_in_inserted(position, &parsed.text, *end),
+        })
+        .collect();
+    buffer.snippet = Some(SnippetSession {
+        tab_stops,
+        current: 0,
+    });
+    select_tab_stop(state, 0);
+}
+
+/// Select the tab-stop of the snippet being filled in, ending the snippet at the last one
+fn select_tab_stop(state: &mut EditorState, idx: usize) {
+    let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+    let Some(session) = buffer.snippet.as_mut() else {
+        return;
+    };
+    let Some(tab_stop) = session.tab_stops.get(idx).copied() else {
+        return;
+    };
+    session.current = idx;
+    if idx + 1 == session.tab_stops.len() {
+        buffer.snippet = None;
+    }
+    instance.selection = tab_stop;
+    instance.cursor = tab_stop.cursor;
+    instance.column_level = instance.cursor.column;
+}
+
+/// Jump to the next or previous tab-stop. Returns false if no snippet is being filled in.
+pub fn jump_tab_stop(state: &mut EditorState, forward: bool) -> bool {
+    let (buffer, _instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+    let Some(session) = &buffer.snippet else {
+        return false;
+    };
+    let idx = if forward {
+        session.current + 1
+    } else {
+        session.current.saturating_sub(1)
+    };
+    select_tab_stop(state, idx);
+    true
+}
+
+/// Remove the selected placeholder of the snippet being filled in so that typing replaces it
+pub fn remove_selected_placeholder(
+    state: &mut EditorState,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) {
+    let (buffer, instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+    let Some(session) = &buffer.snippet else {
+        return;
+    };
+    let selection = instance.selection;
+    let (start, end) = selection.in_order();
+    let selected = session
+        .tab_stops
+        .get(session.current)
+        .is_some_and(|tab_stop| {
+            tab_stop.mark == selection.mark && tab_stop.cursor == selection.cursor
+        });
+    if start == end || !selected {
+        return;
+    }
+    let (_text, cursor) = buffer.remove_text(&selection, lsp_handle, true);
+    instance.cursor = cursor;
+    instance.selection.cursor = cursor;
+    instance.selection.mark = cursor;
+}
+
+/// Expand the snippet whose prefix is the word before the cursor.
+/// Returns false if there is none.
+pub fn expand_before_cursor(
+    state: &mut EditorState,
+    lsp_handle: &Option<&mut LSPClientHandle>,
+) -> bool {
+    if !matches!(state.mode, Mode::Insert) {
+        return false;
+    }
+    let (buffer, instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+    let cursor = instance.cursor;
+    let line = &buffer.lines[cursor.row];
+    let Some(start) = abbreviations::word_before(line, cursor.column) else {
+        return false;
+    };
+    let Some(snippet) = state
+        .snippets
+        .get(buffer.language, &line[start..cursor.column])
+    else {
+        return false;
+    };
+    let body = snippet.body.clone();
+    let start = Cursor {
+        row: cursor.row,
+        column: start,
+    };
+    let (buffer, _instance) = state.get_buffer_by_id_mut(state.buffer_idx.unwrap());
+    buffer.remove_text(
+        &Selection {
+            mark: start,
+            cursor,
+        },
+        lsp_handle,
+        true,
+    );
+    insert_snippet(state, &body, start, lsp_handle);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_snippet, parse_snippet_file, ParsedSnippet};
+
+    #[test]
+    fn snippet_syntax() {
+        assert_eq!(
+            parse_snippet("fn ${1:name}($2) {\n    $0\n}"),
+            ParsedSnippet {
+                text: "fn name() {\n    \n}".into(),
+                tab_stops: vec![(3, 7), (8, 8), (16, 16)],
+            }
+        );
+        let parsed = parse_snippet("${1:a} = ${2|x,y|}; $1 \\$3 ${TM_FILENAME:file}");
+        assert_eq!(parsed.text, "a = x; a $3 file");
+        assert_eq!(parsed.tab_stops, vec![(0, 1), (4, 5), (16, 16)]);
+        let parsed = parse_snippet("${1:outer ${2:inner}}");
+        assert_eq!(parsed.text, "outer inner");
+        assert_eq!(parsed.tab_stops, vec![(0, 11), (6, 11), (11, 11)]);
+        assert_eq!(parse_snippet("cost: $").text, "cost: $");
+
+        let snippets = parse_snippet_file(
+            r#"{"Print": {"prefix": ["p", "print"], "body": ["print($1)", "$0"]}}"#,
+        )
+        .unwrap();
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[1].prefix, "print");
+        assert_eq!(snippets[1].body, "print($1)\n$0");
+        assert!(parse_snippet_file("[]").is_err());
+    }
+}
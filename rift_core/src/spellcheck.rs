@@ -0,0 +1,233 @@
+//! Spellchecking against hunspell style word lists.
+//! Only the word list (`.dic`) is used; affix rules are approximated by
+//! stripping a few common English suffixes.
+
+use std::{
+    collections::HashSet,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::{buffer::instance::Selection, io::file_io};
+
+/// Word lists searched for when no dictionary is configured
+pub const DICTIONARY_PATHS: [&str; 4] = [
+    "/usr/share/hunspell/en_US.dic",
+    "/usr/share/myspell/en_US.dic",
+    "/usr/share/myspell/dicts/en_US.dic",
+    "/usr/share/dict/words",
+];
+
+/// Suffixes stripped when a word is not found as is
+const SUFFIXES: [&str; 7] = ["'s", "s", "es", "ed", "ing", "ly", "er"];
+
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+    /// Words added by the user
+    personal: HashSet<String>,
+    personal_path: Option<PathBuf>,
+}
+
+impl Dictionary {
+    /// Load the first available system word list and the personal dictionary
+    pub fn load() -> Result<Self> {
+        let path = DICTIONARY_PATHS
+            .iter()
+            .find(|path| Path::new(path).exists())
+            .ok_or_else(|| anyhow::anyhow!("No dictionary found"))?;
+        let mut dictionary = Self::from_dic(&file_io::read_file_content(path)?);
+
+        dictionary.personal_path = file_io::config_dir().map(|dir| dir.join("dictionary.txt"));
+        if let Some(personal_path) = &dictionary.personal_path {
+            if let Ok(content) = std::fs::read_to_string(personal_path) {
+                dictionary.personal = content
+                    .lines()
+                    .map(|word| word.trim().to_lowercase())
+                    .filter(|word| !word.is_empty())
+                    .collect();
+            }
+        }
+        Ok(dictionary)
+    }
+
+    /// Parse a hunspell `.dic` file (or a plain list of words)
+    pub fn from_dic(content: &str) -> Self {
+        let mut lines = content.lines().peekable();
+        // Hunspell dictionaries start with the number of words
+        if lines
+            .peek()
+            .is_some_and(|line| line.trim().parse::<usize>().is_ok())
+        {
+            lines.next();
+        }
+        Self {
+            words: lines
+                .filter_map(|line| line.split('/').next())
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.words.contains(word) || self.personal.contains(word)
+    }
+
+    /// Returns true if the word is spelled correctly
+    pub fn check(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        let word = word.trim_matches('\'');
+        if word.is_empty() || self.contains(word) {
+            return true;
+        }
+        SUFFIXES.iter().any(|suffix| {
+            word.strip_suffix(suffix)
+                .is_some_and(|stem| stem.len() > 1 && self.contains(stem))
+        })
+    }
+
+    /// Known words closest to the misspelled word
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<String> {
+        let word = word.to_lowercase();
+        let length = word.chars().count();
+        let mut candidates: Vec<(usize, &String)> = self
+            .words
+            .iter()
+            .chain(self.personal.iter())
+            .filter(|candidate| candidate.chars().count().abs_diff(length) <= 2)
+            .map(|candidate| (edit_distance(&word, candidate), candidate))
+            .filter(|(distance, _)| *distance <= 2)
+            .collect();
+        candidates.sort();
+        candidates
+            .into_iter()
+            .take(limit)
+            .map(|(_, candidate)| candidate.clone())
+            .collect()
+    }
+
+    /// Add a word to the personal dictionary
+    pub fn add_word(&mut self, word: &str) -> Result<()> {
+        let word = word.to_lowercase();
+        if let Some(path) = &self.personal_path {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", word)?;
+        }
+        self.personal.insert(word);
+        Ok(())
+    }
+
+    /// Byte ranges of misspelled words within the given byte ranges of the text
+    pub fn misspellings(&self, text: &str, ranges: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        let mut misspelled = vec![];
+        for (start, end) in ranges {
+            let Some(region) = text.get(*start..*end) else {
+                continue;
+            };
+            for (offset, word) in words(region) {
+                if is_checked(word) && !self.check(word) {
+                    misspelled.push((start + offset, start + offset + word.len()));
+                }
+            }
+        }
+        misspelled
+    }
+}
+
+/// Words along with their byte offsets
+pub fn words(text: &str) -> Vec<(usize, &str)> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '\'' || c == '_';
+    let mut words = vec![];
+    let mut start = None;
+    for (idx, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, is_word_char(c)) {
+            (None, true) => start = Some(idx),
+            (Some(word_start), false) => {
+                // Quotes around a word are not part of it
+                let word = &text[word_start..idx];
+                let trimmed = word.trim_start_matches('\'');
+                let offset = word_start + word.len() - trimmed.len();
+                let trimmed = trimmed.trim_end_matches('\'');
+                if !trimmed.is_empty() {
+                    words.push((offset, trimmed));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// Skip identifiers, numbers and abbreviations
+fn is_checked(word: &str) -> bool {
+    word.chars().count() > 1
+        && word.chars().all(|c| c.is_alphabetic() || c == '\'')
+        && !word.chars().skip(1).any(|c| c.is_uppercase())
+}
+
+/// Word at the column of the line along with its start column
+pub fn word_at(line: &str, column: usize) -> Option<(usize, &str)> {
+    let byte = column.min(line.len());
+    words(line)
+        .into_iter()
+        .find(|(start, word)| *start <= byte && byte <= start + word.len())
+}
+
+/// Levenshtein distance between two words
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let cost = if a_char == *b_char { 0 } else { 1 };
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Suggestions for a misspelled word being shown to the user
+#[derive(Debug, Clone)]
+pub struct SpellMenu {
+    pub word: String,
+    /// Range of the word in the buffer
+    pub range: Selection,
+    pub suggestions: Vec<String>,
+    pub idx: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{word_at, Dictionary};
+
+    #[test]
+    fn spellcheck_words() {
+        let dictionary = Dictionary::from_dic("3\nhello/S\nworld\nspell/SG\n");
+        assert!(dictionary.check("Hello"));
+        assert!(dictionary.check("spelling"));
+        assert!(!dictionary.check("wrold"));
+        assert_eq!(dictionary.suggest("wrold", 3), vec!["world".to_string()]);
+
+        let text = "// hello wrold, parseHtml 42";
+        assert_eq!(
+            dictionary.misspellings(text, &[(2, text.len())]),
+            vec![(9, 14)]
+        );
+        assert_eq!(word_at(text, 10), Some((9, "wrold")));
+    }
+}
@@ -1,27 +1,89 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+};
 
 use copypasta::ClipboardContext;
 use tokio::sync::mpsc;
 
 use crate::{
+    actions::Action,
+    block,
     buffer::{
-        instance::{BufferInstance, Cursor, GutterInfo, Language},
-        line_buffer::{HighlightedText, LineBuffer},
+        extmarks::Sign,
+        folding::Fold,
+        instance::{
+            number_lines, Attribute, BufferInstance, Cursor, GutterInfo, Language, Range, Selection,
+        },
+        line_buffer::{HighlightedText, LineBuffer, VisibleLinesView},
     },
+    call_hierarchy::CallTree,
+    code_actions::CodeActionMenu,
+    command_palette::CommandHistory,
     concurrent::{AsyncHandle, AsyncResult},
-    io::file_io::FolderEntry,
+    config::{self, ConfigWatcher},
+    dap::DebugSession,
+    diagnostics::{self, DiagnosticPull},
+    dialog::{ConfirmDialog, InputDialog},
+    diff::SideBySideDiff,
+    editorconfig::IndentStyle,
+    events::{EventKind, EventRegistry},
+    file_changes::{self, FileWatcher},
+    formatter,
+    git::{self, conflict::find_conflicts, status::GitStatus, GitChanges, HunkKind},
+    io::{
+        encryption::{self, EncryptedFile, EncryptionKind},
+        file_io::{self, FolderEntry},
+        file_tree::FileTree,
+    },
+    keys::PendingKeys,
+    kill_ring::KillRing,
+    layout::{Layout, SplitDirection},
+    linter,
+    logs::LogViewer,
     lsp::{
         client::{start_lsp, LSPClientHandle},
-        types,
+        servers, types,
     },
+    notifications::{NotificationAction, NotificationLevel, Notifications},
     preferences::Preferences,
+    recovery::{self, Recovery},
+    replace::{ReplaceSession, WorkspaceReplace},
+    scratch,
+    search::{self, SearchHistory},
+    session::{self, RecentFiles},
+    snippets::Snippets,
+    spellcheck::{Dictionary, SpellMenu},
+    stats::{self, TextStats},
+    tasks::{Quickfix, TaskRun},
+    terminal::Terminal,
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub enum Mode {
     #[default]
     Normal,
     Insert,
+    Terminal,
+    /// Mode defined in the configuration, by name
+    Custom(String),
+}
+
+/// Everything the visible lines of the current buffer are computed from
+#[derive(PartialEq)]
+struct ViewKey {
+    buffer_id: u32,
+    version: usize,
+    highlights_version: Option<usize>,
+    extmarks_version: usize,
+    tab_width: usize,
+    cursor: Cursor,
+    selection: (Cursor, Cursor),
+    scroll: Cursor,
+    folds: Vec<Fold>,
+    visible_lines: usize,
+    max_characters: usize,
+    extra_segments: Vec<Range>,
 }
 
 pub struct EditorState {
@@ -30,14 +92,25 @@ pub struct EditorState {
     pub preferences: Preferences,
     pub buffers: HashMap<u32, LineBuffer>,
     pub instances: HashMap<u32, BufferInstance>,
+    /// Order in which open buffers are displayed and cycled through
+    pub buffer_order: Vec<u32>,
     next_id: u32,
     pub workspace_folder: String,
     pub visible_lines: usize,
     pub max_characters: usize,
     pub mode: Mode,
+    /// Set when the view may have changed, the visible lines are only
+    /// recomputed if what they are computed from changed (see `ViewKey`)
     pub update_view: bool,
+    last_view: Option<ViewKey>,
     pub highlighted_text: HighlightedText,
     pub gutter_info: Vec<GutterInfo>,
+    /// Diagnostic shown at the end of each visible line
+    pub inline_diagnostics: Vec<Option<(types::DiagnosticSeverity, String)>>,
+    /// Blame annotations of the visible lines, see `git::blame::inline_blame`
+    pub inline_blame: Vec<Option<String>>,
+    /// Virtual text of the extmarks on the visible lines
+    pub inline_virtual_text: Vec<Option<String>>,
     pub relative_cursor: Cursor,
     pub buffer_idx: Option<u32>,
     pub modal_open: bool,
@@ -45,30 +118,121 @@ pub struct EditorState {
     pub modal_options_filtered: Vec<FolderEntry>,
     pub modal_selection_idx: Option<usize>,
     pub modal_input: String,
-    pub clipboard_ctx: ClipboardContext,
+    /// Created on first use (see `clipboard`)
+    pub clipboard_ctx: Option<ClipboardContext>,
+    /// Language server and linter diagnostics keyed by path
     pub diagnostics: HashMap<String, types::PublishDiagnostics>,
+    /// Linter diagnostics, kept at the end of the diagnostics of each path
+    pub lint_diagnostics: HashMap<String, Vec<types::Diagnostic>>,
+    pub terminal: Option<Terminal>,
+    pub file_tree: FileTree,
+    pub file_tree_open: bool,
+    /// Keys move through the file tree instead of editing the buffer
+    pub file_tree_focused: bool,
+    pub markdown_preview_open: bool,
+    /// Path of the image shown in the image viewer
+    pub image_path: Option<String>,
+    /// Diff shown in the diff viewer
+    pub diff_view: Option<SideBySideDiff>,
+    pub layout: Layout,
+    pub command_palette_open: bool,
+    pub command_history: CommandHistory,
+    pub notifications: Notifications,
+    /// Unsaved changes confirmation waiting for an answer
+    pub confirm_dialog: Option<ConfirmDialog>,
+    /// Text asked from the user such as passphrases
+    pub input_dialog: Option<InputDialog>,
+    /// Set once the editor may exit
+    pub quit: bool,
+    /// Path being entered in the save as prompt
+    pub save_as_input: Option<String>,
+    pub spellcheck_enabled: bool,
+    /// Loaded on first use (see `dictionary`)
+    pub dictionary: Option<Dictionary>,
+    dictionary_loaded: bool,
+    pub git: GitChanges,
+    pub git_status: Option<GitStatus>,
+    /// Buffer with the commit message and the repository it is committed to when saved
+    pub git_commit: Option<(u32, PathBuf)>,
+    /// Misspelled byte ranges per buffer along with the version they were computed for
+    pub spell_errors: HashMap<u32, (usize, Vec<(usize, usize)>)>,
+    pub spell_menu: Option<SpellMenu>,
+    pub code_action_menu: Option<CodeActionMenu>,
+    /// Signatures of the call around the cursor, shown while typing its arguments
+    pub signature_help: Option<types::SignatureHelp>,
+    /// Content shown in the info modal of the frontend
+    pub info_modal: Option<String>,
+    /// Language whose servers are restarted by the frontend
+    pub lsp_restart: Option<Language>,
+    pub diagnostic_pull: DiagnosticPull,
+    pub call_hierarchy: Option<CallTree>,
+    /// Task being run or last run
+    pub task: Option<TaskRun>,
+    /// Locations parsed from the output of the last task
+    pub quickfix: Quickfix,
+    pub debug: Option<DebugSession>,
+    /// Breakpoint rows of each file
+    pub breakpoints: HashMap<String, BTreeSet<usize>>,
+    /// Actions replayed by `RepeatLastChange`
+    pub last_edit: Vec<Action>,
+    /// Actions of the insert mode session in progress
+    pub edit_recording: Option<Vec<Action>>,
+    /// Count and key sequence typed so far in normal mode
+    pub pending_keys: PendingKeys,
+    pub events: EventRegistry,
+    pub abbreviations_enabled: bool,
+    /// Buffer showing the log file, see `logs::poll_logs`
+    pub log_viewer: Option<LogViewer>,
+    /// Buffer version and counts of buffers, recomputed when the buffer changes
+    pub text_stats: HashMap<u32, (usize, TextStats)>,
+    /// Secrets of open encrypted files by path, used to encrypt them again on save
+    pub encrypted_files: HashMap<String, EncryptedFile>,
+    pub search_history: SearchHistory,
+    /// Query of the last search in a buffer, its matches are marked on the minimap
+    pub search_query: Option<String>,
+    pub recent_files: RecentFiles,
+    /// Checks open files for changes made outside of the editor
+    pub file_watcher: FileWatcher,
+    /// Journals of unsaved changes, see `recovery::write_journals`
+    pub recovery: Recovery,
+    pub config_watcher: ConfigWatcher,
+    /// Recently copied and deleted text
+    pub kill_ring: KillRing,
+    /// Replacement asking for confirmation of each match
+    pub replace_session: Option<ReplaceSession>,
+    /// Replacement across files waiting to be applied from its preview
+    pub workspace_replace: Option<WorkspaceReplace>,
+    /// Fold action waiting for folding ranges from the language server
+    pub pending_fold: Option<Action>,
+    pub snippets: Snippets,
 }
 
 impl EditorState {
     pub fn new(rt: tokio::runtime::Runtime) -> Self {
         let (sender, receiver) = mpsc::channel::<AsyncResult>(32);
+        let workspace_folder = std::path::absolute("/")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
         Self {
             rt,
             async_handle: AsyncHandle { sender, receiver },
             preferences: Preferences::default(),
             buffers: HashMap::new(),
+            buffer_order: vec![],
             next_id: 0,
-            workspace_folder: std::path::absolute("/")
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_owned(),
+            file_tree: FileTree::new(workspace_folder.clone()),
+            workspace_folder,
             visible_lines: 0,
             max_characters: 0,
             mode: Mode::Normal,
             instances: HashMap::new(),
             highlighted_text: vec![],
             gutter_info: vec![],
+            inline_diagnostics: vec![],
+            inline_blame: vec![],
+            inline_virtual_text: vec![],
             buffer_idx: None,
             modal_open: false,
             modal_options: vec![],
@@ -77,51 +241,858 @@ impl EditorState {
             modal_input: "".to_string(),
             relative_cursor: Cursor { row: 0, column: 0 },
             update_view: true,
-            clipboard_ctx: ClipboardContext::new().unwrap(),
+            last_view: None,
+            clipboard_ctx: None,
             diagnostics: HashMap::new(),
+            lint_diagnostics: HashMap::new(),
+            terminal: None,
+            file_tree_open: false,
+            file_tree_focused: false,
+            markdown_preview_open: false,
+            image_path: None,
+            diff_view: None,
+            layout: Layout::new(),
+            command_palette_open: false,
+            command_history: CommandHistory::load(),
+            notifications: Notifications::new(),
+            confirm_dialog: None,
+            input_dialog: None,
+            quit: false,
+            save_as_input: None,
+            spellcheck_enabled: true,
+            dictionary: None,
+            dictionary_loaded: false,
+            git: GitChanges::default(),
+            git_status: None,
+            git_commit: None,
+            spell_errors: HashMap::new(),
+            spell_menu: None,
+            code_action_menu: None,
+            signature_help: None,
+            info_modal: None,
+            lsp_restart: None,
+            diagnostic_pull: DiagnosticPull::default(),
+            call_hierarchy: None,
+            task: None,
+            quickfix: Quickfix::default(),
+            debug: None,
+            breakpoints: HashMap::new(),
+            last_edit: vec![],
+            edit_recording: None,
+            pending_keys: PendingKeys::default(),
+            events: EventRegistry::default(),
+            abbreviations_enabled: true,
+            log_viewer: None,
+            text_stats: HashMap::new(),
+            encrypted_files: HashMap::new(),
+            search_history: SearchHistory::load(),
+            search_query: None,
+            recent_files: RecentFiles::load(),
+            file_watcher: FileWatcher::default(),
+            recovery: Recovery::default(),
+            config_watcher: ConfigWatcher::default(),
+            kill_ring: KillRing::default(),
+            replace_session: None,
+            workspace_replace: None,
+            pending_fold: None,
+            snippets: Snippets::load(),
+        }
+    }
+
+    /// Get the system clipboard, connecting to it on first use
+    pub fn clipboard(&mut self) -> Option<&mut ClipboardContext> {
+        if self.clipboard_ctx.is_none() {
+            match ClipboardContext::new() {
+                Ok(clipboard_ctx) => self.clipboard_ctx = Some(clipboard_ctx),
+                Err(err) => self.notify(
+                    NotificationLevel::Warning,
+                    format!("Failed to access clipboard: {}", err),
+                ),
+            }
+        }
+        self.clipboard_ctx.as_mut()
+    }
+
+    /// Get the spellchecking dictionary, loading it on first use
+    pub fn dictionary(&mut self) -> Option<&mut Dictionary> {
+        if !self.dictionary_loaded {
+            self.dictionary_loaded = true;
+            match Dictionary::load() {
+                Ok(dictionary) => self.dictionary = Some(dictionary),
+                Err(err) => tracing::warn!("Spellcheck unavailable: {}", err),
+            }
+        }
+        self.dictionary.as_mut()
+    }
+
+    /// Misspelled words of the buffer as segments for `get_visible_lines`
+    pub fn spell_segments(&mut self, buffer_id: u32) -> Vec<Range> {
+        if !self.spellcheck_enabled {
+            return vec![];
+        }
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        let version = buffer.version;
+        let cached = self
+            .spell_errors
+            .get(&buffer_id)
+            .is_some_and(|(checked_version, _)| *checked_version == version);
+        if !cached {
+            let Some(ranges) = buffer.prose_ranges() else {
+                return vec![];
+            };
+            let content = buffer.get_content("\n".into());
+            let Some(dictionary) = self.dictionary() else {
+                return vec![];
+            };
+            let misspelled = dictionary.misspellings(&content, &ranges);
+            self.spell_errors.insert(buffer_id, (version, misspelled));
+        }
+        self.spell_errors[&buffer_id]
+            .1
+            .iter()
+            .map(|(start, end)| Range {
+                start: *start,
+                end: *end,
+                attributes: HashSet::from([Attribute::Misspelled]),
+            })
+            .collect()
+    }
+
+    /// Line the debugger is stopped at as a segment for `get_visible_lines`
+    pub fn debug_segments(&self, buffer_id: u32) -> Vec<Range> {
+        let Some((path, row)) = self.debug.as_ref().and_then(|debug| debug.stop.as_ref()) else {
+            return vec![];
+        };
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        if buffer.file_path.as_ref() != Some(path) || *row >= buffer.get_num_lines() {
+            return vec![];
+        }
+        vec![Range {
+            start: buffer.byte_index_from_row(*row, "\n"),
+            end: buffer.byte_index_from_row(*row, "\n") + buffer.get_line_length(*row),
+            attributes: HashSet::from([Attribute::DebugStop]),
+        }]
+    }
+
+    /// Sides of the merge conflicts in the buffer as segments for `get_visible_lines`
+    pub fn conflict_segments(&self, buffer_id: u32) -> Vec<Range> {
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        let mut segments = vec![];
+        for conflict in find_conflicts(&buffer.lines) {
+            for row in conflict.start..=conflict.end {
+                let Some(side) = conflict.side(row) else {
+                    continue;
+                };
+                let start = buffer.byte_index_from_row(row, "\n");
+                segments.push(Range {
+                    start,
+                    end: start + buffer.get_line_length(row),
+                    attributes: HashSet::from([Attribute::Conflict(side)]),
+                });
+            }
+        }
+        segments
+    }
+
+    /// Highlighted extmark ranges as segments for `get_visible_lines`
+    pub fn extmark_segments(&self, buffer_id: u32) -> Vec<Range> {
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        buffer
+            .extmarks
+            .iter()
+            .filter_map(|(_id, extmark)| {
+                let highlight = extmark.highlight?;
+                let end = extmark.end?;
+                Some(Range {
+                    start: buffer.byte_index_from_cursor(&extmark.start, "\n"),
+                    end: buffer.byte_index_from_cursor(&end, "\n"),
+                    attributes: HashSet::from([Attribute::Highlight(highlight)]),
+                })
+            })
+            .collect()
+    }
+
+    /// Rows around the view of the buffer which decorations are computed for
+    fn rows_around_view(&self, buffer_id: u32) -> Vec<usize> {
+        let (buffer, instance) = self.get_buffer_by_id(buffer_id);
+        // The view is scrolled to the cursor after the segments are computed
+        instance.folds.visible_rows(
+            instance.scroll.row.saturating_sub(self.visible_lines),
+            self.visible_lines * 3,
+            instance.cursor.row,
+            buffer.get_num_lines(),
+        )
+    }
+
+    /// Indent guides and brackets colored by depth of the rows around the view
+    /// as segments for `get_visible_lines`
+    pub fn guide_segments(&self, buffer_id: u32) -> Vec<Range> {
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        let rows = self.rows_around_view(buffer_id);
+        let mut segments = vec![];
+        if self.preferences.indent_guides {
+            let tab_width = self.tab_width(buffer_id);
+            for row in &rows {
+                let start = buffer.byte_index_from_row(*row, "\n");
+                segments.extend(
+                    buffer
+                        .indent_guides(*row, tab_width)
+                        .into_iter()
+                        .map(|column| Range {
+                            start: start + column,
+                            end: start + column + 1,
+                            attributes: HashSet::from([Attribute::IndentGuide]),
+                        }),
+                );
+            }
+        }
+        if let (true, Some(first), Some(last)) =
+            (self.preferences.bracket_colors, rows.first(), rows.last())
+        {
+            let start = buffer.byte_index_from_row(*first, "\n");
+            let end = buffer.byte_index_from_row(*last + 1, "\n");
+            segments.extend(
+                buffer
+                    .bracket_depths(start, end)
+                    .into_iter()
+                    .map(|(byte, depth)| Range {
+                        start: byte,
+                        end: byte + 1,
+                        attributes: HashSet::from([Attribute::Bracket(depth)]),
+                    }),
+            );
+        }
+        segments
+    }
+
+    /// Placeholders of the whitespace and control characters of the rows around the view
+    /// as segments for `get_visible_lines`
+    pub fn whitespace_segments(&self, buffer_id: u32) -> Vec<Range> {
+        if !self.preferences.render_whitespace {
+            return vec![];
+        }
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        let mut segments = vec![];
+        for row in self.rows_around_view(buffer_id) {
+            let start = buffer.byte_index_from_row(row, "\n");
+            segments.extend(
+                buffer
+                    .invisible_characters(row)
+                    .into_iter()
+                    .map(|(from, to)| Range {
+                        start: start + from,
+                        end: start + to,
+                        attributes: HashSet::from([Attribute::Invisible]),
+                    }),
+            );
+        }
+        segments
+    }
+
+    /// Lines of the block selection around the view as segments for `get_visible_lines`,
+    /// a block narrower than a column marks the column the text is inserted at
+    pub fn block_segments(&self, buffer_id: u32) -> Vec<Range> {
+        let (buffer, instance) = self.get_buffer_by_id(buffer_id);
+        let Some(selection) = instance.block() else {
+            return vec![];
+        };
+        let rows = self.rows_around_view(buffer_id);
+        block::block_columns(buffer, &selection)
+            .into_iter()
+            .filter(|(row, _, _)| rows.contains(row))
+            .map(|(row, start, end)| {
+                let line_start = buffer.byte_index_from_row(row, "\n");
+                Range {
+                    start: line_start + start,
+                    end: line_start + end.max(start + 1) - 1,
+                    attributes: HashSet::from([Attribute::Select]),
+                }
+            })
+            .collect()
+    }
+
+    /// Extmark virtual text of each visible line of the current buffer
+    fn inline_virtual_text(&self) -> Vec<Option<String>> {
+        let Some(buffer_id) = self.buffer_idx else {
+            return vec![];
+        };
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        self.gutter_info
+            .iter()
+            .map(|line| {
+                if line.wrapped {
+                    return None;
+                }
+                buffer.extmarks.virtual_text(line.start.row)
+            })
+            .collect()
+    }
+
+    /// Annotation shown after a visible line: extmark virtual text, or else its blame
+    pub fn inline_annotation(&self, idx: usize) -> Option<&Option<String>> {
+        self.inline_virtual_text
+            .get(idx)
+            .filter(|text| text.is_some())
+            .or_else(|| self.inline_blame.get(idx))
+    }
+
+    /// Line, word and character counts of the buffer
+    pub fn text_stats(&mut self, buffer_id: u32) -> TextStats {
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        match self.text_stats.get(&buffer_id) {
+            Some((version, stats)) if *version == buffer.version => *stats,
+            _ => {
+                let version = buffer.version;
+                let stats = TextStats::from_buffer(buffer);
+                self.text_stats.insert(buffer_id, (version, stats));
+                stats
+            }
+        }
+    }
+
+    /// Statusline segment with the counts of the buffer and its selection
+    pub fn text_stats_segment(&mut self, buffer_id: u32) -> String {
+        let stats = self.text_stats(buffer_id);
+        let (buffer, instance) = self.get_buffer_by_id(buffer_id);
+        let selection = TextStats::from_selection(buffer, &instance.selection);
+        stats::status_segment(&stats, selection.as_ref())
+    }
+
+    /// Severity coloring of the log viewer buffer as segments for `get_visible_lines`
+    pub fn log_segments(&mut self, buffer_id: u32) -> Vec<Range> {
+        match &mut self.log_viewer {
+            Some(viewer) if viewer.buffer_id == buffer_id => {
+                viewer.segments(&self.buffers[&buffer_id])
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Columns of the line numbers in the gutter of a view of the buffer
+    pub fn line_number_width(&self, buffer_id: Option<u32>) -> usize {
+        let num_lines = buffer_id
+            .and_then(|id| self.buffers.get(&id))
+            .map(|buffer| buffer.get_num_lines())
+            .unwrap_or(1);
+        self.preferences.line_number_width(num_lines)
+    }
+
+    /// Sign shown in the sign column for a row of the current buffer
+    pub fn sign(&self, row: usize) -> Option<Sign> {
+        let (buffer, _instance) = self.get_buffer_by_id(self.buffer_idx?);
+        buffer.extmarks.sign(row)
+    }
+
+    /// Marker shown in the gutter for a row of the current buffer:
+    /// the debugger stop location, a breakpoint or a collapsed fold
+    pub fn gutter_marker(&self, row: usize) -> Option<char> {
+        self.debug_marker(row).or_else(|| {
+            let (_buffer, instance) = self.get_buffer_by_id(self.buffer_idx?);
+            instance.folds.is_collapsed(row).then_some('▸')
+        })
+    }
+
+    fn debug_marker(&self, row: usize) -> Option<char> {
+        let path = self
+            .buffer_idx
+            .and_then(|id| self.buffers.get(&id))
+            .and_then(|buffer| buffer.file_path.as_ref())?;
+        let stop = self.debug.as_ref().and_then(|debug| debug.stop.as_ref());
+        if stop.is_some_and(|(stop_path, stop_row)| stop_path == path && *stop_row == row) {
+            Some('▶')
+        } else if self
+            .breakpoints
+            .get(path)
+            .is_some_and(|rows| rows.contains(&row))
+        {
+            Some('●')
+        } else {
+            None
+        }
+    }
+
+    /// Marker of the git change on a row of the current buffer
+    pub fn git_marker(&self, row: usize) -> Option<(char, HunkKind)> {
+        self.git
+            .hunks(self.buffer_idx?)
+            .iter()
+            .find(|hunk| hunk.contains(row))
+            .map(|hunk| (hunk.marker(), hunk.kind))
+    }
+
+    /// Rows of the current buffer with a match of the last buffer search
+    pub fn search_match_rows(&self) -> Vec<usize> {
+        let (Some(id), Some(query)) = (self.buffer_idx, &self.search_query) else {
+            return vec![];
+        };
+        let (buffer, _instance) = self.get_buffer_by_id(id);
+        let mut rows: Vec<usize> = search::find_matches(&buffer.lines, query)
+            .iter()
+            .map(|start| start.row)
+            .collect();
+        rows.dedup();
+        rows
+    }
+
+    /// Ids of buffers with unsaved changes in display order
+    pub fn modified_buffers(&self) -> Vec<u32> {
+        self.buffer_order
+            .iter()
+            .filter(|id| self.buffers.get(id).is_some_and(|buffer| buffer.modified))
+            .copied()
+            .collect()
+    }
+
+    /// Store diagnostics published by a language server keeping linter diagnostics
+    pub fn publish_diagnostics(&mut self, mut diagnostics: types::PublishDiagnostics) {
+        if let Some(lint_diagnostics) = self.lint_diagnostics.get(&diagnostics.uri) {
+            diagnostics
+                .diagnostics
+                .extend(lint_diagnostics.iter().cloned());
         }
+        self.diagnostics
+            .insert(diagnostics.uri.clone(), diagnostics);
+    }
+
+    /// Replace the linter diagnostics of a path keeping language server diagnostics.
+    /// Ranges are clamped to the lines of the buffer if the file is open.
+    pub fn set_lint_diagnostics(&mut self, path: String, mut diagnostics: Vec<types::Diagnostic>) {
+        let buffer = self.buffers.values().find(|buffer| {
+            buffer
+                .file_path
+                .as_ref()
+                .is_some_and(|file_path| linter::diagnostics_key(file_path.as_ref()) == path)
+        });
+        // Unopened files get the version of a newly opened buffer
+        let version = buffer.map(|buffer| buffer.version).unwrap_or(1);
+        if let Some(buffer) = buffer {
+            for diagnostic in &mut diagnostics {
+                let range = &mut diagnostic.range;
+                for cursor in [&mut range.mark, &mut range.cursor] {
+                    cursor.row = cursor.row.min(buffer.get_num_lines() - 1);
+                    cursor.column = cursor.column.min(buffer.get_line_length(cursor.row));
+                }
+                // Point locations underline the rest of the line
+                if range.mark == range.cursor {
+                    range.cursor.column = buffer.get_line_length(range.cursor.row);
+                }
+            }
+        }
+
+        let previous = self.lint_diagnostics.remove(&path).unwrap_or_default();
+        let entry =
+            self.diagnostics
+                .entry(path.clone())
+                .or_insert_with(|| types::PublishDiagnostics {
+                    uri: path.clone(),
+                    version,
+                    diagnostics: vec![],
+                });
+        let lsp_count = entry.diagnostics.len().saturating_sub(previous.len());
+        entry.diagnostics.truncate(lsp_count);
+        entry.diagnostics.extend(diagnostics.iter().cloned());
+        entry.version = version;
+        if !diagnostics.is_empty() {
+            self.lint_diagnostics.insert(path, diagnostics);
+        }
+    }
+
+    /// Switch to the workspace folder, reopening the files of its last session if enabled
+    pub fn open_workspace(&mut self, folder: String) {
+        if folder == self.workspace_folder {
+            return;
+        }
+        if !self.buffers.is_empty() {
+            if let Err(err) = session::save_session(self) {
+                tracing::warn!("Failed to save session: {:#}", err);
+            }
+        }
+        self.workspace_folder = folder;
+        config::reload_config(self);
+        if self.preferences.restore_session {
+            session::restore_session(self);
+        }
+    }
+
+    /// Quit the editor, saving the scratch buffers and the session and removing
+    /// the recovery journals which are no longer needed
+    pub fn quit(&mut self) {
+        self.save_scratch_buffers();
+        if let Err(err) = session::save_session(self) {
+            tracing::warn!("Failed to save session: {:#}", err);
+        }
+        recovery::remove_journals(self);
+        self.quit = true;
+    }
+
+    /// Save modified scratch buffers, which never ask before being written
+    pub fn save_scratch_buffers(&mut self) {
+        let ids: Vec<u32> = self
+            .buffers
+            .iter()
+            .filter(|(_, buffer)| {
+                buffer.modified && buffer.file_path.as_deref().is_some_and(scratch::is_scratch)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.save_buffer(id);
+        }
+    }
+
+    /// Open the file if it is not already open and move the cursor to the location.
+    /// The language server of the current buffer is told about the file if it handles it.
+    pub fn open_location(
+        &mut self,
+        path: &str,
+        cursor: Cursor,
+        lsp_handle: &Option<&mut LSPClientHandle>,
+    ) {
+        let open = self
+            .buffers
+            .iter()
+            .find(|(_, buffer)| buffer.file_path.as_deref() == Some(path))
+            .map(|(id, _)| *id);
+        let buffer_id = match open {
+            Some(id) => id,
+            None if encryption::encryption_kind(path).is_some() => {
+                self.request_decryption(path);
+                return;
+            }
+            None => {
+                let (content, encoding) = match file_io::read_file_with_encoding(path) {
+                    Ok(content) => content,
+                    Err(err) => {
+                        self.notify(
+                            NotificationLevel::Error,
+                            format!("Failed to open {}: {}", path, err),
+                        );
+                        return;
+                    }
+                };
+                let mut buffer = LineBuffer::new(content.clone(), Some(path.to_string()));
+                buffer.encoding = encoding;
+                let conflicts = find_conflicts(&buffer.lines).len();
+                if conflicts > 0 {
+                    self.notify(
+                        NotificationLevel::Warning,
+                        format!("{} has {} merge conflicts", path, conflicts),
+                    );
+                }
+                let current_language = self
+                    .buffer_idx
+                    .and_then(|id| self.buffers.get(&id))
+                    .map(|buffer| buffer.language);
+                if let Some(lsp_handle) = lsp_handle {
+                    if current_language == Some(buffer.language) {
+                        if let Err(err) = lsp_handle.send_notification_sync(
+                            "textDocument/didOpen".to_string(),
+                            Some(LSPClientHandle::did_open_text_document(
+                                path.to_string(),
+                                content,
+                            )),
+                        ) {
+                            tracing::error!("Failed to notify language server: {}", err);
+                        }
+                    }
+                }
+                self.recent_files.push(path);
+                self.add_buffer(buffer)
+            }
+        };
+
+        self.buffer_idx = Some(buffer_id);
+        let (buffer, instance) = self.get_buffer_by_id_mut(buffer_id);
+        let row = cursor.row.min(buffer.get_num_lines() - 1);
+        let column = cursor.column.min(buffer.get_line_length(row));
+        instance.cursor = Cursor { row, column };
+        instance.selection.cursor = instance.cursor;
+        instance.selection.mark = instance.cursor;
+        instance.column_level = column;
+        self.update_view = true;
+    }
+
+    /// Ask for the identity or passphrase of an encrypted file, which is opened
+    /// once it is entered. Returns false if the path is not an encrypted file.
+    pub fn request_decryption(&mut self, path: &str) -> bool {
+        let Some(kind) = encryption::encryption_kind(path) else {
+            return false;
+        };
+        if let Some((id, _)) = self
+            .buffers
+            .iter()
+            .find(|(_, buffer)| buffer.file_path.as_deref() == Some(path))
+        {
+            self.buffer_idx = Some(*id);
+            return true;
+        }
+        self.input_dialog = Some(InputDialog::decrypt(
+            path,
+            kind,
+            self.preferences.age_identity.clone(),
+        ));
+        true
+    }
+
+    /// Decrypt the file into a new buffer, the plaintext is never written to disk
+    pub fn open_encrypted(&mut self, path: &str, kind: EncryptionKind, secret: &str) {
+        match encryption::decrypt(path, kind, secret) {
+            Ok((content, file)) => {
+                self.encrypted_files.insert(path.to_string(), file);
+                let buffer = LineBuffer::new(content, Some(path.to_string()));
+                self.buffer_idx = Some(self.add_buffer(buffer));
+                self.update_view = true;
+            }
+            Err(err) => self.notify(
+                NotificationLevel::Error,
+                format!("Failed to decrypt {}: {:#}", path, err),
+            ),
+        }
+    }
+
+    /// Format the buffer with the external formatter of its language
+    pub fn format_buffer(&mut self, buffer_id: u32, lsp_handle: &Option<&mut LSPClientHandle>) {
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        let Some(formatter) = self.preferences.formatters.get(&buffer.language) else {
+            self.notify(
+                NotificationLevel::Info,
+                format!("No formatter configured for {:?}", buffer.language),
+            );
+            return;
+        };
+        let content = buffer.get_content("\n".into());
+        match formatter::run_formatter(formatter, buffer.file_path.as_deref(), &content) {
+            Ok(formatted) if formatted != content => {
+                let (buffer, instance) = self.get_buffer_by_id_mut(buffer_id);
+                formatter::apply_formatted(buffer, instance, &formatted, lsp_handle);
+                self.update_view = true;
+            }
+            Ok(_) => {}
+            Err(err) => self.notify(
+                NotificationLevel::Error,
+                format!("Failed to format: {:#}", err),
+            ),
+        }
+    }
+
+    /// Write the buffer to its file returning true on success.
+    /// Permission errors offer writing with elevated permissions or elsewhere.
+    /// Content written when saving the buffer, with its line ending
+    /// and the whitespace properties of its EditorConfig applied
+    pub fn save_content(&self, id: u32) -> String {
+        let (buffer, _instance) = self.get_buffer_by_id(id);
+        let line_ending = buffer.line_ending_or(&self.preferences.line_ending);
+        buffer.editorconfig.apply_to_content(
+            buffer.get_content(line_ending.as_str().to_string()),
+            line_ending.as_str(),
+        )
+    }
+
+    /// Mark the buffer as matching the file it was written to, so that later changes
+    /// of the file on disk are noticed again
+    pub fn mark_saved(&mut self, id: u32, path: &str) {
+        let (buffer, _instance) = self.get_buffer_by_id_mut(id);
+        buffer.modified = false;
+        buffer.disk_mtime = file_io::modified_time(path);
+        self.file_watcher.notified.remove(&id);
+        self.send_event(EventKind::BufferSaved, Some(id));
+    }
+
+    pub fn save_buffer(&mut self, id: u32) -> bool {
+        let (buffer, _instance) = self.get_buffer_by_id_mut(id);
+        let Some(path) = buffer.file_path.clone() else {
+            let message = format!("{} has no file name", buffer.display_name());
+            self.notify_with_actions(
+                NotificationLevel::Error,
+                message,
+                vec![NotificationAction {
+                    label: "Save As".into(),
+                    action: Action::OpenSaveAsPrompt,
+                }],
+            );
+            return false;
+        };
+        if file_changes::changed_on_disk(buffer).is_some() {
+            let message = format!("{} changed on disk since it was opened", path);
+            self.notify_with_actions(
+                NotificationLevel::Warning,
+                message,
+                vec![
+                    NotificationAction {
+                        label: "Overwrite".into(),
+                        action: Action::OverwriteBuffer(id),
+                    },
+                    NotificationAction {
+                        label: "Reload".into(),
+                        action: Action::ReloadBuffer(id),
+                    },
+                ],
+            );
+            return false;
+        }
+        let encoding = buffer.encoding;
+        let content = self.save_content(id);
+        let result = match self.encrypted_files.get(&path) {
+            Some(file) => encryption::encrypt(file, &content)
+                .and_then(|ciphertext| Ok(std::fs::write(&path, ciphertext)?)),
+            // Never write plaintext to a file which is expected to be encrypted
+            None if encryption::encryption_kind(&path).is_some() => Err(anyhow::anyhow!(
+                "no identity or passphrase to encrypt it with"
+            )),
+            None => file_io::override_file_content_encoded(&path, &content, encoding),
+        };
+        match result {
+            Ok(_) => {
+                self.mark_saved(id, &path);
+                true
+            }
+            Err(err) => {
+                self.save_failed(id, &path, err);
+                false
+            }
+        }
+    }
+
+    /// Report a failed save, offering to save with sudo or under another name
+    /// if the file could not be written for missing permissions
+    fn save_failed(&mut self, id: u32, path: &str, err: anyhow::Error) {
+        if file_io::is_permission_denied(&err) {
+            self.notify_with_actions(
+                NotificationLevel::Error,
+                format!("Permission denied saving {}", path),
+                vec![
+                    NotificationAction {
+                        label: "Save with sudo".into(),
+                        action: Action::SaveBufferElevated(id),
+                    },
+                    NotificationAction {
+                        label: "Save As".into(),
+                        action: Action::OpenSaveAsPrompt,
+                    },
+                ],
+            );
+        } else {
+            self.notify(
+                NotificationLevel::Error,
+                format!("Failed to save {}: {}", path, err),
+            );
+        }
+    }
+
+    /// Show a message to the user (also logged)
+    pub fn notify(&mut self, level: NotificationLevel, message: String) {
+        self.notify_with_actions(level, message, vec![]);
+    }
+
+    /// Show a message with buttons performing actions
+    pub fn notify_with_actions(
+        &mut self,
+        level: NotificationLevel,
+        message: String,
+        actions: Vec<NotificationAction>,
+    ) {
+        match level {
+            NotificationLevel::Info => tracing::info!("{}", message),
+            NotificationLevel::Warning => tracing::warn!("{}", message),
+            NotificationLevel::Error => tracing::error!("{}", message),
+        }
+        self.notifications.push(level, message, actions);
     }
 
     pub fn add_buffer(&mut self, buffer: LineBuffer) -> u32 {
         if let Some((idx, _)) = self
             .buffers
             .iter()
-            .find(|(_, buf)| buf.file_path == buffer.file_path)
+            .find(|(_, buf)| buffer.file_path.is_some() && buf.file_path == buffer.file_path)
         {
             *idx
         } else {
             self.buffers.insert(self.next_id, buffer);
             self.instances
                 .insert(self.next_id, BufferInstance::new(self.next_id));
+            self.buffer_order.push(self.next_id);
+            self.send_event(EventKind::BufferOpened, Some(self.next_id));
             self.next_id += 1;
             self.next_id - 1
         }
     }
 
     pub fn remove_buffer(&mut self, id: u32) {
-        self.buffers.remove(&id);
-        if self.buffers.is_empty() {
+        self.send_event(EventKind::BufferClosed, Some(id));
+        if let Some(path) = self.buffers.remove(&id).and_then(|buffer| buffer.file_path) {
+            self.encrypted_files.remove(&path);
+        }
+        self.instances.remove(&id);
+        self.text_stats.remove(&id);
+        let position = self.buffer_order.iter().position(|idx| *idx == id);
+        self.buffer_order.retain(|idx| *idx != id);
+        if self.buffer_order.is_empty() {
             self.buffer_idx = None;
-        } else {
-            self.buffer_idx = Some(self.buffer_idx.unwrap().saturating_sub(1));
+        } else if self.buffer_idx == Some(id) {
+            let position = position
+                .unwrap_or(0)
+                .saturating_sub(1)
+                .min(self.buffer_order.len() - 1);
+            self.buffer_idx = Some(self.buffer_order[position]);
         }
     }
 
     pub fn cycle_buffer(&mut self, reverse: bool) {
-        if self.buffer_idx.is_some() {
-            if reverse {
-                self.buffer_idx = if self.buffer_idx.unwrap() == 0 {
-                    Some((self.buffers.len() - 1).try_into().unwrap())
-                } else {
-                    Some(self.buffer_idx.unwrap() - 1)
-                };
+        if let Some(id) = self.buffer_idx {
+            let len = self.buffer_order.len();
+            let position = self
+                .buffer_order
+                .iter()
+                .position(|idx| *idx == id)
+                .unwrap_or(0);
+            let position = if reverse {
+                (position + len - 1) % len
             } else {
-                self.buffer_idx = if self.buffer_idx.unwrap() == self.buffers.len() as u32 - 1 {
-                    Some(0)
-                } else {
-                    Some(self.buffer_idx.unwrap() + 1)
-                };
-            }
+                (position + 1) % len
+            };
+            self.buffer_idx = Some(self.buffer_order[position]);
+        }
+    }
+
+    /// Move a buffer to the given position in the buffer order
+    pub fn move_buffer(&mut self, id: u32, position: usize) {
+        if let Some(current) = self.buffer_order.iter().position(|idx| *idx == id) {
+            self.buffer_order.remove(current);
+            let position = position.min(self.buffer_order.len());
+            self.buffer_order.insert(position, id);
+        }
+    }
+
+    /// Indent width of the buffer from its EditorConfig or the preferences of its language
+    pub fn tab_width(&self, id: u32) -> usize {
+        let (buffer, _instance) = self.get_buffer_by_id(id);
+        buffer
+            .editorconfig
+            .indent_width()
+            .unwrap_or_else(|| self.preferences.tab_width_for(buffer.language))
+    }
+
+    /// Columns between tab stops when the buffer is shown
+    pub fn tab_display_width(&self, id: u32) -> usize {
+        let (buffer, _instance) = self.get_buffer_by_id(id);
+        buffer
+            .editorconfig
+            .tab_width
+            .or(buffer.editorconfig.indent_size)
+            .unwrap_or_else(|| self.preferences.tab_width_for(buffer.language))
+    }
+
+    /// Text inserted for one level of indentation in the buffer
+    pub fn indent_unit(&self, id: u32) -> String {
+        let (buffer, _instance) = self.get_buffer_by_id(id);
+        let use_tabs = match buffer.editorconfig.indent_style {
+            Some(style) => style == IndentStyle::Tab,
+            None => self.preferences.use_tabs_for(buffer.language),
+        };
+        if use_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.tab_width(id))
         }
     }
 
@@ -139,17 +1110,416 @@ impl EditorState {
         )
     }
 
-    pub fn spawn_lsp(&self, language: Language) -> Option<LSPClientHandle> {
-        let command: Option<(&str, &[&str])> = match language {
-            Language::Rust => Some(("rust-analyzer", &[])),
-            _ => None,
+    /// Split the focused pane showing the active buffer in both panes
+    pub fn split_pane(&mut self, direction: SplitDirection) {
+        let instance = self
+            .buffer_idx
+            .map(|id| self.instances.get(&id).unwrap().clone());
+        self.layout.split(direction, instance);
+        self.update_view = true;
+    }
+
+    /// Focus a pane restoring the buffer it shows
+    pub fn focus_pane(&mut self, id: u32) {
+        if id == self.layout.focused || !self.layout.panes.contains_key(&id) {
+            return;
+        }
+        let instance = self
+            .buffer_idx
+            .map(|id| self.instances.get(&id).unwrap().clone());
+        self.layout.panes.insert(self.layout.focused, instance);
+        self.layout.focused = id;
+        self.restore_focused_pane();
+    }
+
+    /// Close the focused pane and focus the pane taking its place
+    pub fn close_pane(&mut self) {
+        if let Some(id) = self.layout.remove(self.layout.focused) {
+            self.layout.focused = id;
+            self.restore_focused_pane();
+        }
+    }
+
+    fn restore_focused_pane(&mut self) {
+        let focused = self.layout.focused;
+        if let Some(instance) = self.pane_instance(focused) {
+            self.buffer_idx = Some(instance.buffer_id);
+            self.instances.insert(instance.buffer_id, instance);
+        }
+        self.layout.panes.insert(focused, None);
+        self.update_view = true;
+    }
+
+    /// Buffer shown in the pane, the current buffer for the focused pane
+    pub fn pane_buffer_id(&self, id: u32) -> Option<u32> {
+        if id == self.layout.focused {
+            return self.buffer_idx;
+        }
+        self.layout
+            .panes
+            .get(&id)
+            .and_then(|instance| instance.as_ref())
+            .map(|instance| instance.buffer_id)
+    }
+
+    /// Instance of a pane which is not focused
+    /// (clamped to the current content of its buffer)
+    pub fn pane_instance(&self, id: u32) -> Option<BufferInstance> {
+        let mut instance = self.layout.panes.get(&id)?.clone()?;
+        let buffer = self.buffers.get(&instance.buffer_id)?;
+        let last_line = buffer.get_num_lines() - 1;
+        for cursor in [
+            &mut instance.cursor,
+            &mut instance.selection.cursor,
+            &mut instance.selection.mark,
+            &mut instance.scroll,
+        ] {
+            cursor.row = cursor.row.min(last_line);
+            cursor.column = cursor.column.min(buffer.get_line_length(cursor.row));
+        }
+        Some(instance)
+    }
+
+    /// Start computing the syntax highlights of a buffer in the background
+    /// (if they are not up to date)
+    pub fn request_highlights(&mut self, buffer_id: u32) {
+        if let Some(buffer) = self.buffers.get_mut(&buffer_id) {
+            buffer.request_highlights(buffer_id, &self.rt, self.async_handle.sender.clone());
+        }
+    }
+
+    /// Compute the visible lines of the current buffer along with their gutter info and
+    /// annotations. Returns the cursor relative to the view.
+    /// Skipped when nothing the lines are computed from changed since the last time.
+    pub fn update_visible_lines(&mut self, visible_lines: usize, max_characters: usize) -> Cursor {
+        let Some(buffer_id) = self.buffer_idx else {
+            return Cursor { row: 0, column: 0 };
         };
-        if let Some(command) = command {
-            return Some(
-                self.rt
-                    .block_on(async { start_lsp(command.0, command.1).await.unwrap() }),
+        let (buffer, _instance) = self.get_buffer_by_id(buffer_id);
+        let mut extra_segments = vec![];
+        for diagnostic in diagnostics::buffer_diagnostics(self, buffer_id) {
+            extra_segments.push(Range {
+                start: buffer.byte_index_from_cursor(&diagnostic.range.mark, "\n"),
+                end: buffer.byte_index_from_cursor(&diagnostic.range.cursor, "\n"),
+                attributes: HashSet::from([Attribute::DiagnosticSeverity(
+                    diagnostic.severity.clone(),
+                )]),
+            });
+        }
+        self.request_highlights(buffer_id);
+        extra_segments.extend(self.spell_segments(buffer_id));
+        git::refresh_hunks(self, buffer_id);
+        git::blame::refresh_blame(self, buffer_id);
+        extra_segments.extend(self.debug_segments(buffer_id));
+        extra_segments.extend(self.conflict_segments(buffer_id));
+        extra_segments.extend(self.extmark_segments(buffer_id));
+        extra_segments.extend(self.guide_segments(buffer_id));
+        extra_segments.extend(self.whitespace_segments(buffer_id));
+        extra_segments.extend(self.log_segments(buffer_id));
+        extra_segments.extend(self.block_segments(buffer_id));
+
+        let tab_width = self.tab_display_width(buffer_id);
+        let buffer = self.buffers.get_mut(&buffer_id).unwrap();
+        buffer.tab_width = tab_width;
+        let instance = self.instances.get_mut(&buffer_id).unwrap();
+        let cursor_row = instance.cursor.row;
+        let view_key = |buffer: &LineBuffer, instance: &BufferInstance, extra_segments| ViewKey {
+            buffer_id,
+            version: buffer.version,
+            highlights_version: buffer.highlights_version(),
+            extmarks_version: buffer.extmarks.version,
+            tab_width: buffer.tab_width,
+            cursor: instance.cursor,
+            selection: (instance.selection.cursor, instance.selection.mark),
+            scroll: instance.scroll,
+            folds: instance.folds.collapsed.clone(),
+            visible_lines,
+            max_characters,
+            extra_segments,
+        };
+        if self.last_view.as_ref() == Some(&view_key(buffer, instance, extra_segments.clone())) {
+            // Annotations can change without the lines changing
+            self.inline_diagnostics = diagnostics::inline_diagnostics(self);
+            self.inline_blame = git::blame::inline_blame(self);
+            self.inline_virtual_text = self.inline_virtual_text();
+            // The line numbers setting can change without the lines changing
+            number_lines(
+                &mut self.gutter_info,
+                cursor_row,
+                self.preferences.line_numbers,
             );
+            return self.relative_cursor;
         }
-        None
+        // Block selections are drawn from the extra segments
+        let selection = match instance.block() {
+            Some(_) => Selection {
+                mark: instance.cursor,
+                cursor: instance.cursor,
+            },
+            None => instance.selection,
+        };
+        let (mut lines, mut relative_cursor, mut gutter_info) =
+            buffer.get_visible_lines(VisibleLinesView {
+                scroll: &mut instance.scroll,
+                cursor: &instance.cursor,
+                selection: &selection,
+                visible_lines,
+                max_characters,
+                eol_sequence: "\n".into(),
+                extra_segments: extra_segments.clone(),
+                folds: &mut instance.folds,
+            });
+        buffer.decorate_lines(
+            &mut lines,
+            &mut gutter_info,
+            &mut relative_cursor,
+            visible_lines,
+        );
+        number_lines(&mut gutter_info, cursor_row, self.preferences.line_numbers);
+        // Taken after the scroll was adjusted to the cursor
+        self.last_view = Some(view_key(buffer, instance, extra_segments));
+        self.highlighted_text = lines;
+        self.gutter_info = gutter_info;
+        self.inline_diagnostics = diagnostics::inline_diagnostics(self);
+        self.inline_blame = git::blame::inline_blame(self);
+        self.inline_virtual_text = self.inline_virtual_text();
+        relative_cursor
+    }
+
+    /// Get the visible lines of a pane which is not focused
+    pub fn get_pane_visible_lines(
+        &mut self,
+        id: u32,
+        visible_lines: usize,
+        max_characters: usize,
+        eol_sequence: String,
+    ) -> Option<(HighlightedText, Vec<GutterInfo>)> {
+        let mut instance = self.pane_instance(id)?;
+        self.request_highlights(instance.buffer_id);
+        let tab_width = self.tab_display_width(instance.buffer_id);
+        let buffer = self.buffers.get_mut(&instance.buffer_id)?;
+        buffer.tab_width = tab_width;
+        let (mut lines, mut relative_cursor, mut gutter_info) =
+            buffer.get_visible_lines(VisibleLinesView {
+                scroll: &mut instance.scroll,
+                cursor: &instance.cursor,
+                selection: &instance.selection,
+                visible_lines,
+                max_characters,
+                eol_sequence,
+                extra_segments: vec![],
+                folds: &mut instance.folds,
+            });
+        buffer.decorate_lines(
+            &mut lines,
+            &mut gutter_info,
+            &mut relative_cursor,
+            visible_lines,
+        );
+        number_lines(
+            &mut gutter_info,
+            instance.cursor.row,
+            self.preferences.line_numbers,
+        );
+        self.layout.panes.insert(id, Some(instance));
+        Some((lines, gutter_info))
+    }
+
+    /// Start and initialize the language servers of the language,
+    /// rooted at the project containing the file.
+    /// For each server the first of its candidates which starts is used.
+    pub fn spawn_lsp(&mut self, language: Language, path: &str) -> Option<LSPClientHandle> {
+        let servers = self.preferences.language_servers_for(language)?.clone();
+        let mut started = vec![];
+        let mut errors = vec![];
+        for candidates in servers {
+            for server in candidates {
+                let args: Vec<&str> = server.args.iter().map(String::as_str).collect();
+                let root = servers::find_root(path, &server.root_markers, &self.workspace_folder);
+                let result = self
+                    .rt
+                    .block_on(async { start_lsp(&server.program, &args).await })
+                    .and_then(|mut lsp_server| {
+                        lsp_server.initialize_sync(root, server.initialization_options)?;
+                        Ok(lsp_server)
+                    });
+                match result {
+                    Ok(lsp_server) => {
+                        started.push(lsp_server);
+                        break;
+                    }
+                    Err(err) => errors.push(format!("{}: {:#}", server.program, err)),
+                }
+            }
+        }
+        if !errors.is_empty() {
+            self.notify(
+                NotificationLevel::Error,
+                format!("Failed to start language server ({})", errors.join(", ")),
+            );
+        }
+        if started.is_empty() {
+            return None;
+        }
+        let mut lsp_handle = LSPClientHandle::new(started);
+        lsp_handle.change_debounce =
+            std::time::Duration::from_millis(self.preferences.lsp_change_debounce);
+        Some(lsp_handle)
+    }
+
+    /// Start the language servers of the language again and open its buffers in them.
+    /// The previous servers are expected to be dropped first.
+    pub fn restart_lsp(&mut self, language: Language) -> Option<LSPClientHandle> {
+        if self.preferences.language_servers_for(language).is_none() {
+            self.notify(
+                NotificationLevel::Info,
+                format!("No language server configured for {:?}", language),
+            );
+            return None;
+        }
+        let buffers: Vec<(String, String, usize)> = self
+            .buffer_order
+            .iter()
+            .filter_map(|id| self.buffers.get(id))
+            .filter(|buffer| buffer.language == language)
+            .filter_map(|buffer| {
+                let path = buffer.file_path.clone()?;
+                Some((path, buffer.get_content("\n".to_string()), buffer.version))
+            })
+            .collect();
+        let path = buffers
+            .first()
+            .map(|(path, _, _)| path.clone())
+            .unwrap_or_else(|| self.workspace_folder.clone());
+        let lsp_handle = self.spawn_lsp(language, &path)?;
+        for (path, content, version) in buffers {
+            if let Err(err) = lsp_handle.send_notification_sync(
+                "textDocument/didOpen".to_string(),
+                Some(LSPClientHandle::did_open_text_document(
+                    path.clone(),
+                    content.clone(),
+                )),
+            ) {
+                tracing::error!("Failed to notify language server: {}", err);
+            }
+            // Opened documents start at version 1, catch up with the buffer
+            // so that diagnostics are matched with it
+            if version != 1 {
+                if let Err(err) = lsp_handle.send_notification_sync(
+                    "textDocument/didChange".to_string(),
+                    Some(LSPClientHandle::did_change_text_document(
+                        path,
+                        version,
+                        vec![LSPClientHandle::full_change(content)],
+                    )),
+                ) {
+                    tracing::error!("Failed to notify language server: {}", err);
+                }
+            }
+        }
+        self.notify(
+            NotificationLevel::Info,
+            format!("Restarted language server for {:?}", language),
+        );
+        Some(lsp_handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EditorState;
+    use crate::{
+        actions::Action,
+        buffer::{instance::Language, line_buffer::LineBuffer},
+        lsp::servers::LanguageServerConfig,
+    };
+
+    #[test]
+    fn reorder_and_close_tabs() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        let a = state.add_buffer(LineBuffer::new("a".into(), None));
+        let b = state.add_buffer(LineBuffer::new("b".into(), None));
+        let c = state.add_buffer(LineBuffer::new("c".into(), None));
+        assert_eq!(state.buffer_order, vec![a, b, c]);
+
+        state.move_buffer(c, 0);
+        assert_eq!(state.buffer_order, vec![c, a, b]);
+        state.move_buffer(c, 10);
+        assert_eq!(state.buffer_order, vec![a, b, c]);
+
+        // Cycling follows the tab order and wraps around
+        state.buffer_idx = Some(c);
+        state.cycle_buffer(false);
+        assert_eq!(state.buffer_idx, Some(a));
+        state.cycle_buffer(true);
+        assert_eq!(state.buffer_idx, Some(c));
+
+        // Closing the current tab focuses the tab before it
+        state.buffer_idx = Some(b);
+        state.remove_buffer(b);
+        assert_eq!(state.buffer_order, vec![a, c]);
+        assert_eq!(state.buffer_idx, Some(a));
+        state.remove_buffer(a);
+        assert_eq!(state.buffer_idx, Some(c));
+        // Closing another tab keeps the current one
+        let d = state.add_buffer(LineBuffer::new("d".into(), None));
+        state.remove_buffer(d);
+        assert_eq!(state.buffer_idx, Some(c));
+        state.remove_buffer(c);
+        assert_eq!(state.buffer_idx, None);
+        assert!(state.buffer_order.is_empty());
+    }
+
+    #[test]
+    fn offer_elevated_save() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        let id = 1;
+
+        // Files which cannot be written for missing permissions are offered to be saved with sudo
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        state.save_failed(id, "/etc/hosts", denied.into());
+        let notification = state.notifications.latest().unwrap();
+        assert!(matches!(
+            notification.actions[0].action,
+            Action::SaveBufferElevated(elevated) if elevated == id
+        ));
+        state.save_failed(id, "/etc/hosts", anyhow::anyhow!("disk full"));
+        assert!(state.notifications.latest().unwrap().actions.is_empty());
+    }
+
+    /// Language server answering the initialize request and exiting right after
+    const EXITING_SERVER: &str = r#"
+read -r header
+read -r _
+length=${header#Content-Length: }
+body=$(head -c "${length%?}")
+id=$(printf '%s' "$body" | sed -n 's/^{"jsonrpc":"2.0","id":\([0-9]*\).*/\1/p')
+response="{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"capabilities\":{}}}"
+printf 'Content-Length: %s\r\n\r\n%s' "${#response}" "$response"
+"#;
+
+    #[test]
+    fn restart_exiting_language_server() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mut state = EditorState::new(rt);
+        state.preferences.language_servers.insert(
+            Language::Rust,
+            vec![vec![LanguageServerConfig {
+                program: "sh".into(),
+                args: vec!["-c".into(), EXITING_SERVER.into()],
+                initialization_options: None,
+                root_markers: vec![],
+            }]],
+        );
+        let mut buffer = LineBuffer::new("fn main() {}\n".into(), Some("/src/main.rs".into()));
+        buffer.language = Language::Rust;
+        buffer.version = 3;
+        state.add_buffer(buffer);
+
+        // Opening the buffers in a server which already exited does not panic
+        let lsp_handle = state.restart_lsp(Language::Rust);
+        assert!(lsp_handle.is_some());
     }
 }
@@ -0,0 +1,209 @@
+use std::fmt::Write;
+
+use crate::{
+    buffer::{instance::Selection, line_buffer::LineBuffer},
+    lsp::client::LSPClientHandle,
+    state::EditorState,
+};
+
+/// Line, word and character counts of some text
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TextStats {
+    pub lines: usize,
+    pub non_blank_lines: usize,
+    pub words: usize,
+    pub chars: usize,
+    pub non_whitespace_chars: usize,
+    pub bytes: usize,
+}
+
+impl TextStats {
+    fn add_line(&mut self, line: &str) {
+        self.lines += 1;
+        if !line.trim().is_empty() {
+            self.non_blank_lines += 1;
+        }
+        self.words += line.split_whitespace().count();
+        self.chars += line.chars().count();
+        self.non_whitespace_chars += line.chars().filter(|c| !c.is_whitespace()).count();
+        self.bytes += line.len();
+    }
+
+    /// Counts of the lines, line endings count as one character
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut stats = Self::default();
+        for line in lines {
+            if stats.lines > 0 {
+                stats.chars += 1;
+                stats.bytes += 1;
+            }
+            stats.add_line(line);
+        }
+        stats
+    }
+
+    /// Counts of the buffer ignoring the trailing empty line
+    pub fn from_buffer(buffer: &LineBuffer) -> Self {
+        let lines = match buffer.lines.split_last() {
+            Some((last, rest)) if last.is_empty() => rest,
+            _ => &buffer.lines,
+        };
+        Self::from_lines(lines.iter().map(|line| line.as_str()))
+    }
+
+    /// Counts of the selected text, None for an empty selection
+    pub fn from_selection(buffer: &LineBuffer, selection: &Selection) -> Option<Self> {
+        if selection.mark == selection.cursor {
+            return None;
+        }
+        let text = buffer.get_selection(selection);
+        let text = text.strip_suffix('\n').unwrap_or(&text);
+        Some(Self::from_lines(text.split('\n')))
+    }
+}
+
+/// Statusline segment with the word and character counts of the buffer,
+/// prefixed by the counts of the selection if there is one
+pub fn status_segment(buffer: &TextStats, selection: Option<&TextStats>) -> String {
+    match selection {
+        Some(selection) => format!(
+            "{}/{} words, {}/{} chars",
+            selection.words, buffer.words, selection.chars, buffer.chars
+        ),
+        None => format!("{} words, {} chars", buffer.words, buffer.chars),
+    }
+}
+
+fn write_text_stats(report: &mut String, stats: &TextStats) {
+    writeln!(report, "  Lines: {}", stats.lines).unwrap();
+    writeln!(report, "  Non-blank lines: {}", stats.non_blank_lines).unwrap();
+    writeln!(report, "  Words: {}", stats.words).unwrap();
+    writeln!(report, "  Characters: {}", stats.chars).unwrap();
+    writeln!(
+        report,
+        "  Characters (no whitespace): {}",
+        stats.non_whitespace_chars
+    )
+    .unwrap();
+    writeln!(report, "  Bytes: {}", stats.bytes).unwrap();
+}
+
+/// Report of the counts of the buffer and its selection
+pub fn buffer_stats(state: &mut EditorState, buffer_id: u32) -> String {
+    let stats = state.text_stats(buffer_id);
+    let (buffer, instance) = state.get_buffer_by_id(buffer_id);
+    let mut report = format!("Statistics of {}\n\nBuffer\n", buffer.display_name());
+    write_text_stats(&mut report, &stats);
+    if let Some(selection) = TextStats::from_selection(buffer, &instance.selection) {
+        report.push_str("\nSelection\n");
+        write_text_stats(&mut report, &selection);
+    }
+    report
+}
+
+/// Human readable byte count
+fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Report of buffer memory use, highlight timing and queue depths
+pub fn editor_stats(state: &EditorState, lsp_handle: &Option<&mut LSPClientHandle>) -> String {
+    let mut report = String::from("Editor Statistics\n\n");
+
+    let total: usize = state
+        .buffers
+        .values()
+        .map(|buffer| buffer.memory_usage())
+        .sum();
+    writeln!(report, "Buffers: {}", state.buffers.len()).unwrap();
+    writeln!(report, "Estimated memory: {}", format_bytes(total)).unwrap();
+    writeln!(
+        report,
+        "Async results pending: {}",
+        state.async_handle.receiver.len()
+    )
+    .unwrap();
+
+    report.push_str("\nLanguage Server\n");
+    match lsp_handle {
+        Some(lsp_handle) => {
+            for server in &lsp_handle.servers {
+                writeln!(report, "  {}", server.name).unwrap();
+                writeln!(report, "    Incoming queue: {}", server.reciever.len()).unwrap();
+                writeln!(
+                    report,
+                    "    Outgoing queue: {}",
+                    server.sender.max_capacity() - server.sender.capacity()
+                )
+                .unwrap();
+            }
+            writeln!(report, "  Pending requests: {}", lsp_handle.pending_count()).unwrap();
+        }
+        None => report.push_str("  Not running\n"),
+    }
+
+    for id in &state.buffer_order {
+        let Some(buffer) = state.buffers.get(id) else {
+            continue;
+        };
+        writeln!(report, "\n{}", buffer.display_name()).unwrap();
+        writeln!(report, "  Lines: {}", buffer.get_num_lines()).unwrap();
+        writeln!(report, "  Memory: {}", format_bytes(buffer.memory_usage())).unwrap();
+        writeln!(report, "  Undo log: {} edits", buffer.changes.len()).unwrap();
+        writeln!(report, "  Version: {}", buffer.version).unwrap();
+        writeln!(report, "  Highlights: {} spans", buffer.highlight_count()).unwrap();
+        match buffer.highlight_duration {
+            Some(duration) => writeln!(report, "  Highlight time: {:.2?}", duration).unwrap(),
+            None => report.push_str("  Highlight time: -\n"),
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_bytes, TextStats};
+    use crate::buffer::{
+        instance::{Cursor, Selection},
+        line_buffer::LineBuffer,
+    };
+
+    #[test]
+    fn format_bytes_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2048), "2.0 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn count_text() {
+        let buffer = LineBuffer::new("héllo world\n\n  foo bar baz\n".into(), None);
+        let stats = TextStats::from_buffer(&buffer);
+        assert_eq!(stats.lines, 3);
+        assert_eq!(stats.non_blank_lines, 2);
+        assert_eq!(stats.words, 5);
+        assert_eq!(stats.chars, 26);
+        assert_eq!(stats.bytes, 27);
+
+        let selection = Selection {
+            mark: Cursor { row: 0, column: 7 },
+            cursor: Cursor { row: 2, column: 7 },
+        };
+        let stats = TextStats::from_selection(&buffer, &selection).unwrap();
+        assert_eq!(stats.lines, 3);
+        assert_eq!(stats.words, 3);
+        assert_eq!(stats.chars, 14);
+        let empty = Selection {
+            mark: selection.mark,
+            cursor: selection.mark,
+        };
+        assert!(TextStats::from_selection(&buffer, &empty).is_none());
+    }
+}
@@ -0,0 +1,398 @@
+//! Project tasks (build, test, run) and parsing of their output into a quickfix list.
+//! Tasks are read from `.rift/tasks.json` in the workspace folder and the `tasks` of the
+//! configuration, and default to the usual cargo commands in Rust projects.
+
+use std::path::Path;
+
+use anyhow::Result;
+use regex::Regex;
+use tokio::sync::oneshot;
+
+use crate::{
+    buffer::instance::Cursor, io::file_io, lsp::client::LSPClientHandle, state::EditorState,
+};
+
+/// Task definitions relative to the workspace folder
+pub const TASKS_FILE: &str = ".rift/tasks.json";
+
+/// Error formats used when a task does not specify any.
+/// `%f` matches a file path, `%l` a line, `%c` a column and `%m` the message.
+/// Locations without a message use the last `error`/`warning` line seen.
+pub const DEFAULT_ERROR_FORMATS: [&str; 5] = [
+    "--> %f:%l:%c",
+    "%f:%l:%c: %m",
+    "%f:%l: %m",
+    "File \"%f\", line %l, %m",
+    "File \"%f\", line %l",
+];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Task {
+    pub name: String,
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub error_formats: Vec<String>,
+    /// Folder the task runs in, relative to the workspace folder
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Regex matching error lines with the named groups `file`, `line`, `column`
+    /// and `message`, tried before the error formats
+    #[serde(default)]
+    pub problem_matcher: Option<String>,
+}
+
+impl Task {
+    fn new(name: &str, program: &str, args: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            error_formats: vec![],
+            cwd: None,
+            problem_matcher: None,
+        }
+    }
+
+    /// Folder the task runs in and relative paths in its output are resolved against
+    pub fn folder(&self, workspace_folder: &str) -> String {
+        match &self.cwd {
+            Some(cwd) => Path::new(workspace_folder)
+                .join(cwd)
+                .to_string_lossy()
+                .to_string(),
+            None => workspace_folder.to_string(),
+        }
+    }
+
+    /// Command line shown at the top of the output
+    pub fn command_line(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Tasks of the workspace followed by the configured tasks
+pub fn load_tasks(workspace_folder: &str, configured: &[Task]) -> Result<Vec<Task>> {
+    let path = Path::new(workspace_folder).join(TASKS_FILE);
+    if path.exists() {
+        let content = file_io::read_file_content(path.to_str().unwrap_or_default())?;
+        let mut tasks: Vec<Task> = serde_json::from_str(&content)?;
+        tasks.extend_from_slice(configured);
+        return Ok(tasks);
+    }
+    if !configured.is_empty() {
+        return Ok(configured.to_vec());
+    }
+    let workspace = Path::new(workspace_folder);
+    if workspace.join("Cargo.toml").exists() {
+        Ok(vec![
+            Task::new("build", "cargo", &["build"]),
+            Task::new("test", "cargo", &["test"]),
+            Task::new("run", "cargo", &["run"]),
+        ])
+    } else if workspace.join("pyproject.toml").exists() || workspace.join("main.py").exists() {
+        let mut tasks = vec![Task::new("test", "python3", &["-m", "pytest"])];
+        if workspace.join("main.py").exists() {
+            tasks.push(Task::new("run", "python3", &["main.py"]));
+        }
+        Ok(tasks)
+    } else {
+        Ok(vec![])
+    }
+}
+
+/// Location referenced in the output of a task
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickfixEntry {
+    pub path: String,
+    pub cursor: Cursor,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Quickfix {
+    pub entries: Vec<QuickfixEntry>,
+    pub idx: Option<usize>,
+}
+
+impl Quickfix {
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.idx = None;
+    }
+
+    /// Move to the next entry wrapping around at the end
+    pub fn next_entry(&mut self) -> Option<&QuickfixEntry> {
+        let len = self.entries.len();
+        if len == 0 {
+            return None;
+        }
+        let idx = self.idx.map(|idx| (idx + 1) % len).unwrap_or(0);
+        self.idx = Some(idx);
+        self.entries.get(idx)
+    }
+
+    /// Move to the previous entry wrapping around at the start
+    pub fn prev_entry(&mut self) -> Option<&QuickfixEntry> {
+        let len = self.entries.len();
+        if len == 0 {
+            return None;
+        }
+        let idx = self.idx.map(|idx| (idx + len - 1) % len).unwrap_or(len - 1);
+        self.idx = Some(idx);
+        self.entries.get(idx)
+    }
+}
+
+/// Turns lines of output into quickfix entries
+#[derive(Debug)]
+pub struct ErrorParser {
+    formats: Vec<String>,
+    matcher: Option<Regex>,
+    /// Folder relative paths are resolved against
+    folder: String,
+    /// Last error or warning line, used as the message for bare locations
+    header: Option<String>,
+}
+
+impl ErrorParser {
+    pub fn new(formats: &[String], folder: String) -> Self {
+        let formats = if formats.is_empty() {
+            DEFAULT_ERROR_FORMATS
+                .iter()
+                .map(|f| f.to_string())
+                .collect()
+        } else {
+            formats.to_vec()
+        };
+        Self {
+            formats,
+            matcher: None,
+            folder,
+            header: None,
+        }
+    }
+
+    /// Also match lines with the problem matcher regex of the task
+    pub fn with_matcher(mut self, pattern: &str) -> Result<Self> {
+        self.matcher = Some(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    fn match_regex(&self, line: &str) -> Option<(String, usize, usize, String)> {
+        let captures = self.matcher.as_ref()?.captures(line)?;
+        let group = |name: &str| captures.name(name).map(|group| group.as_str());
+        Some((
+            group("file")?.to_string(),
+            group("line")?.parse().ok()?,
+            group("column")
+                .and_then(|column| column.parse().ok())
+                .unwrap_or(1),
+            group("message").unwrap_or_default().to_string(),
+        ))
+    }
+
+    pub fn parse_line(&mut self, line: &str) -> Option<QuickfixEntry> {
+        let line = line.trim();
+        let Some((path, row, column, message)) = self.match_regex(line).or_else(|| {
+            self.formats
+                .iter()
+                .find_map(|format| match_error_format(format, line))
+        }) else {
+            if line.starts_with("error") || line.starts_with("warning") {
+                self.header = Some(line.to_string());
+            }
+            return None;
+        };
+        let path = Path::new(&self.folder).join(path);
+        Some(QuickfixEntry {
+            path: path.to_str()?.to_string(),
+            cursor: Cursor {
+                row: row.saturating_sub(1),
+                column: column.saturating_sub(1),
+            },
+            message: if message.is_empty() {
+                self.header.clone().unwrap_or_default()
+            } else {
+                message
+            },
+        })
+    }
+}
+
+/// Path, line, column and message of a line matching the error format
+pub fn match_error_format(format: &str, line: &str) -> Option<(String, usize, usize, String)> {
+    let mut captures = (String::new(), 0, 0, String::new());
+    match_format(format, line, &mut captures).then_some(captures)
+}
+
+fn match_format(format: &str, line: &str, captures: &mut (String, usize, usize, String)) -> bool {
+    let Some(idx) = format.find('%') else {
+        return format == line;
+    };
+    let Some(rest) = line.strip_prefix(&format[..idx]) else {
+        return false;
+    };
+    let Some(specifier) = format[idx + 1..].chars().next() else {
+        return false;
+    };
+    let format = &format[idx + 2..];
+    match specifier {
+        'l' | 'c' => {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let Ok(value) = rest[..digits].parse() else {
+                return false;
+            };
+            if specifier == 'l' {
+                captures.1 = value;
+            } else {
+                captures.2 = value;
+            }
+            match_format(format, &rest[digits..], captures)
+        }
+        'f' | 'm' => {
+            // Try every split so that paths containing the following literal still match
+            for (end, _) in rest.char_indices().skip(1).chain([(rest.len(), ' ')]) {
+                let value = &rest[..end];
+                if specifier == 'f' && value.contains(char::is_whitespace) {
+                    break;
+                }
+                if match_format(format, &rest[end..], captures) {
+                    if specifier == 'f' {
+                        captures.0 = value.to_string();
+                    } else {
+                        captures.3 = value.to_string();
+                    }
+                    return true;
+                }
+            }
+            false
+        }
+        _ => false,
+    }
+}
+
+/// Task being run or last run along with its output buffer
+#[derive(Debug)]
+pub struct TaskRun {
+    pub name: String,
+    pub buffer_id: u32,
+    pub parser: ErrorParser,
+    /// Stops the task, None once it has finished
+    pub kill: Option<oneshot::Sender<()>>,
+}
+
+impl TaskRun {
+    pub fn is_running(&self) -> bool {
+        self.kill.is_some()
+    }
+}
+
+/// Append a line to the output buffer of a task
+fn append_output(state: &mut EditorState, line: &str) {
+    let Some(task) = &state.task else {
+        return;
+    };
+    if let Some(buffer) = state.buffers.get_mut(&task.buffer_id) {
+        let cursor = Cursor {
+            row: buffer.lines.len() - 1,
+            column: 0,
+        };
+        buffer.insert_text(&format!("{}\n", line), &cursor, &None, false);
+        buffer.modified = false;
+        if state.buffer_idx == Some(task.buffer_id) {
+            state.update_view = true;
+        }
+    }
+}
+
+/// Callback for each line of output of a task
+pub fn task_output(
+    line: String,
+    state: &mut EditorState,
+    _lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    if let Some(entry) = state
+        .task
+        .as_mut()
+        .and_then(|task| task.parser.parse_line(&line))
+    {
+        state.quickfix.entries.push(entry);
+    }
+    append_output(state, &line);
+}
+
+/// Callback for when a task finishes
+pub fn task_exit(
+    status: String,
+    state: &mut EditorState,
+    _lsp_handle: &mut Option<&mut LSPClientHandle>,
+) {
+    append_output(state, &format!("[{}]", status));
+    let Some(task) = &mut state.task else {
+        return;
+    };
+    task.kill = None;
+    let message = format!(
+        "Task {} finished ({}) with {} error location(s)",
+        task.name,
+        status,
+        state.quickfix.entries.len()
+    );
+    state.notify(crate::notifications::NotificationLevel::Info, message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{match_error_format, ErrorParser, Quickfix};
+
+    #[test]
+    fn parse_task_output() {
+        assert_eq!(
+            match_error_format("%f:%l:%c: %m", "C:/src/main.c:12:5: error: expected ';'"),
+            Some(("C:/src/main.c".into(), 12, 5, "error: expected ';'".into()))
+        );
+
+        let mut parser = ErrorParser::new(&[], "/project".into());
+        assert!(parser
+            .parse_line("error[E0425]: cannot find value `x` in this scope")
+            .is_none());
+        let entry = parser.parse_line("  --> src/main.rs:3:13").unwrap();
+        assert_eq!(entry.path, "/project/src/main.rs");
+        assert_eq!((entry.cursor.row, entry.cursor.column), (2, 12));
+        assert_eq!(
+            entry.message,
+            "error[E0425]: cannot find value `x` in this scope"
+        );
+        assert!(parser
+            .parse_line("error: could not compile `app` (bin \"app\")")
+            .is_none());
+
+        let mut quickfix = Quickfix {
+            entries: vec![entry.clone(), entry],
+            idx: None,
+        };
+        quickfix.prev_entry();
+        assert_eq!(quickfix.idx, Some(1));
+        quickfix.next_entry();
+        assert_eq!(quickfix.idx, Some(0));
+
+        let mut parser = ErrorParser::new(&[], "/project/web".into())
+            .with_matcher(r"^(?P<file>[^(]+)\((?P<line>\d+),(?P<column>\d+)\): (?P<message>.*)$")
+            .unwrap();
+        let entry = parser
+            .parse_line("src/app.ts(4,7): error TS2322: Type 'string' is not assignable")
+            .unwrap();
+        assert_eq!(entry.path, "/project/web/src/app.ts");
+        assert_eq!((entry.cursor.row, entry.cursor.column), (3, 6));
+        assert_eq!(
+            entry.message,
+            "error TS2322: Type 'string' is not assignable"
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::HashSet,
+    io::{Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::Result;
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+
+use crate::buffer::{
+    instance::{Attribute, Cursor, HighlightType},
+    line_buffer::HighlightedText,
+};
+
+/// Number of lines kept in the terminal scrollback
+pub const SCROLLBACK_LINES: usize = 1000;
+
+/// Keys which are translated into escape sequences before being
+/// sent to the shell running in the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TerminalKey {
+    Enter,
+    Backspace,
+    Tab,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    Delete,
+    PageUp,
+    PageDown,
+    Ctrl(char),
+}
+
+impl TerminalKey {
+    /// Byte sequence sent to the pty for the key
+    pub fn sequence(&self, application_cursor: bool) -> Vec<u8> {
+        let cursor_prefix = if application_cursor { "\x1bO" } else { "\x1b[" };
+        match self {
+            TerminalKey::Enter => b"\r".to_vec(),
+            TerminalKey::Backspace => b"\x7f".to_vec(),
+            TerminalKey::Tab => b"\t".to_vec(),
+            TerminalKey::Escape => b"\x1b".to_vec(),
+            TerminalKey::Up => format!("{}A", cursor_prefix).into_bytes(),
+            TerminalKey::Down => format!("{}B", cursor_prefix).into_bytes(),
+            TerminalKey::Right => format!("{}C", cursor_prefix).into_bytes(),
+            TerminalKey::Left => format!("{}D", cursor_prefix).into_bytes(),
+            TerminalKey::Home => format!("{}H", cursor_prefix).into_bytes(),
+            TerminalKey::End => format!("{}F", cursor_prefix).into_bytes(),
+            TerminalKey::Delete => b"\x1b[3~".to_vec(),
+            TerminalKey::PageUp => b"\x1b[5~".to_vec(),
+            TerminalKey::PageDown => b"\x1b[6~".to_vec(),
+            TerminalKey::Ctrl(c) => {
+                if c.is_ascii_alphabetic() {
+                    vec![c.to_ascii_lowercase() as u8 & 0x1f]
+                } else {
+                    vec![]
+                }
+            }
+        }
+    }
+}
+
+/// Terminal emulator running the default shell inside a pseudo terminal.
+/// Output from the shell is parsed by a vt100 parser on a background task
+/// and the resulting screen is rendered by the frontends.
+pub struct Terminal {
+    pub rows: u16,
+    pub cols: u16,
+    parser: Arc<Mutex<vt100::Parser>>,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    updated: Arc<AtomicBool>,
+    exited: Arc<AtomicBool>,
+}
+
+impl Terminal {
+    /// Spawn the default shell of the user in the given directory
+    pub fn spawn(cwd: &str, rows: u16, cols: u16, rt: &tokio::runtime::Runtime) -> Result<Self> {
+        let pty_system = native_pty_system();
+        let pair = pty_system.openpty(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+
+        let mut command = CommandBuilder::new_default_prog();
+        command.cwd(cwd);
+        let child = pair.slave.spawn_command(command)?;
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader()?;
+        let writer = pair.master.take_writer()?;
+
+        let parser = Arc::new(Mutex::new(vt100::Parser::new(rows, cols, SCROLLBACK_LINES)));
+        let updated = Arc::new(AtomicBool::new(true));
+        let exited = Arc::new(AtomicBool::new(false));
+
+        // Read output from the pty and feed it to the parser
+        let reader_parser = parser.clone();
+        let reader_updated = updated.clone();
+        let reader_exited = exited.clone();
+        rt.spawn_blocking(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(bytes_read) => {
+                        reader_parser.lock().unwrap().process(&buf[..bytes_read]);
+                        reader_updated.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+            reader_exited.store(true, Ordering::Relaxed);
+            reader_updated.store(true, Ordering::Relaxed);
+        });
+
+        Ok(Self {
+            rows,
+            cols,
+            parser,
+            master: pair.master,
+            writer,
+            child,
+            updated,
+            exited,
+        })
+    }
+
+    /// Send raw input to the shell
+    pub fn write_input(&mut self, input: &[u8]) -> Result<()> {
+        self.parser.lock().unwrap().set_scrollback(0);
+        self.writer.write_all(input)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Send a special key to the shell
+    pub fn write_key(&mut self, key: TerminalKey) -> Result<()> {
+        let application_cursor = self.parser.lock().unwrap().screen().application_cursor();
+        self.write_input(&key.sequence(application_cursor))
+    }
+
+    /// Resize the pty and the parser screen
+    pub fn resize(&mut self, rows: u16, cols: u16) -> Result<()> {
+        if rows == 0 || cols == 0 || (rows == self.rows && cols == self.cols) {
+            return Ok(());
+        }
+        self.master.resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        self.parser.lock().unwrap().set_size(rows, cols);
+        self.rows = rows;
+        self.cols = cols;
+        self.updated.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Scroll into the scrollback by the given number of lines
+    /// (positive values scroll up towards older output)
+    pub fn scroll(&mut self, lines: isize) {
+        let mut parser = self.parser.lock().unwrap();
+        let scrollback = parser.screen().scrollback();
+        parser.set_scrollback(scrollback.saturating_add_signed(lines));
+        self.updated.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the screen changed since the last call
+    pub fn take_updated(&self) -> bool {
+        self.updated.swap(false, Ordering::Relaxed)
+    }
+
+    /// Returns true once the shell process has exited
+    pub fn has_exited(&self) -> bool {
+        self.exited.load(Ordering::Relaxed)
+    }
+
+    /// Title set by the running program
+    pub fn title(&self) -> String {
+        self.parser.lock().unwrap().screen().title().to_string()
+    }
+
+    /// Get the visible screen as highlighted text along with the cursor position
+    /// (the cursor is hidden while scrolled back or when hidden by the program)
+    pub fn get_visible_lines(&self) -> (HighlightedText, Option<Cursor>) {
+        let parser = self.parser.lock().unwrap();
+        let screen = parser.screen();
+        let (rows, cols) = screen.size();
+
+        let mut lines = vec![];
+        for row in 0..rows {
+            let mut line: Vec<(String, HashSet<Attribute>)> = vec![];
+            for col in 0..cols {
+                let Some(cell) = screen.cell(row, col) else {
+                    continue;
+                };
+                if cell.is_wide_continuation() {
+                    continue;
+                }
+
+                let mut contents = cell.contents();
+                if contents.is_empty() {
+                    contents.push(' ');
+                }
+
+                let mut attributes = HashSet::from([Attribute::Highlight(
+                    highlight_type_from_color(cell.fgcolor()),
+                )]);
+                if cell.inverse() {
+                    attributes.insert(Attribute::Select);
+                }
+                if cell.underline() {
+                    attributes.insert(Attribute::Underline);
+                }
+
+                match line.last_mut() {
+                    Some((text, last_attributes)) if *last_attributes == attributes => {
+                        text.push_str(&contents);
+                    }
+                    _ => line.push((contents, attributes)),
+                }
+            }
+            lines.push(line);
+        }
+
+        let cursor = if screen.scrollback() == 0 && !screen.hide_cursor() {
+            let (row, column) = screen.cursor_position();
+            Some(Cursor {
+                row: row as usize,
+                column: column as usize,
+            })
+        } else {
+            None
+        };
+
+        (lines, cursor)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+/// Map ansi colors to the closest highlight type of the theme
+pub fn highlight_type_from_color(color: vt100::Color) -> HighlightType {
+    match color {
+        vt100::Color::Idx(0) | vt100::Color::Idx(8) => HighlightType::Gray,
+        vt100::Color::Idx(1) | vt100::Color::Idx(9) => HighlightType::Red,
+        vt100::Color::Idx(2) | vt100::Color::Idx(10) => HighlightType::Green,
+        vt100::Color::Idx(3) | vt100::Color::Idx(11) => HighlightType::Yellow,
+        vt100::Color::Idx(4) | vt100::Color::Idx(12) => HighlightType::Blue,
+        vt100::Color::Idx(5) | vt100::Color::Idx(13) => HighlightType::Purple,
+        vt100::Color::Idx(6) | vt100::Color::Idx(14) => HighlightType::Turquoise,
+        vt100::Color::Idx(7) | vt100::Color::Idx(15) => HighlightType::White,
+        _ => HighlightType::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::instance::HighlightType;
+
+    use super::{highlight_type_from_color, TerminalKey};
+
+    #[test]
+    fn terminal_key_sequences() {
+        assert_eq!(TerminalKey::Enter.sequence(false), b"\r");
+        assert_eq!(TerminalKey::Up.sequence(false), b"\x1b[A");
+        assert_eq!(TerminalKey::Up.sequence(true), b"\x1bOA");
+        assert_eq!(TerminalKey::Ctrl('C').sequence(false), vec![3]);
+    }
+
+    #[test]
+    fn terminal_colors() {
+        assert_eq!(
+            highlight_type_from_color(vt100::Color::Idx(1)),
+            HighlightType::Red
+        );
+        assert_eq!(
+            highlight_type_from_color(vt100::Color::Default),
+            HighlightType::None
+        );
+    }
+}
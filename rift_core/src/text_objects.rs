@@ -0,0 +1,134 @@
+//! Structural selection of functions, blocks and arguments and
+//! expanding the selection to enclosing nodes of the tree-sitter syntax tree.
+
+use tree_sitter::{Node, Parser, Tree};
+
+use crate::buffer::instance::Language;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextObject {
+    Function,
+    Block,
+    Argument,
+}
+
+impl TextObject {
+    /// Node kinds of the text object, or of the parent of the text object for arguments
+    fn kinds(&self, language: Language) -> &'static [&'static str] {
+        match (self, language) {
+            (Self::Function, Language::Rust) => &["function_item", "closure_expression"],
+            (Self::Function, Language::Python) => &["function_definition", "lambda"],
+            (Self::Block, Language::Rust) => &[
+                "block",
+                "declaration_list",
+                "field_declaration_list",
+                "enum_variant_list",
+                "match_block",
+            ],
+            (Self::Block, Language::Python) => &["block"],
+            (Self::Argument, Language::Rust) => &[
+                "arguments",
+                "parameters",
+                "closure_parameters",
+                "type_arguments",
+                "type_parameters",
+            ],
+            (Self::Argument, Language::Python) => {
+                &["argument_list", "parameters", "lambda_parameters"]
+            }
+            _ => &[],
+        }
+    }
+}
+
+fn syntax_tree(language: Language, source: &str) -> Option<Tree> {
+    let ts_language = match language {
+        Language::Rust => tree_sitter_rust::LANGUAGE.into(),
+        Language::Python => tree_sitter_python::LANGUAGE.into(),
+        _ => return None,
+    };
+    let mut parser = Parser::new();
+    parser.set_language(&ts_language).ok()?;
+    parser.parse(source, None)
+}
+
+/// Innermost node of the text object containing the byte range
+fn find_text_object(node: Node, object: TextObject, language: Language) -> Option<Node> {
+    let kinds = object.kinds(language);
+    let mut node = Some(node);
+    while let Some(current) = node {
+        let parent = current.parent();
+        let found = match object {
+            TextObject::Argument => {
+                current.is_named() && parent.is_some_and(|parent| kinds.contains(&parent.kind()))
+            }
+            _ => kinds.contains(&current.kind()),
+        };
+        if found {
+            return Some(current);
+        }
+        node = parent;
+    }
+    None
+}
+
+/// Byte range of the innermost text object containing the byte range
+pub fn select_text_object(
+    language: Language,
+    source: &str,
+    start: usize,
+    end: usize,
+    object: TextObject,
+) -> Option<(usize, usize)> {
+    let tree = syntax_tree(language, source)?;
+    let node = tree.root_node().descendant_for_byte_range(start, end)?;
+    let node = find_text_object(node, object, language)?;
+    Some((node.start_byte(), node.end_byte()))
+}
+
+/// Byte range of the smallest named node containing the byte range which is larger than it
+pub fn expand_selection(
+    language: Language,
+    source: &str,
+    start: usize,
+    end: usize,
+) -> Option<(usize, usize)> {
+    let tree = syntax_tree(language, source)?;
+    let mut node = tree
+        .root_node()
+        .named_descendant_for_byte_range(start, end)?;
+    while node.start_byte() == start && node.end_byte() == end {
+        node = node.parent()?;
+    }
+    Some((node.start_byte(), node.end_byte()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_selection, select_text_object, TextObject};
+    use crate::buffer::instance::Language;
+
+    #[test]
+    fn text_objects() {
+        let source = "fn f(a: u8, b: u8) -> u8 {\n    g(a, b)\n}\n";
+        let select = |byte: usize, object| {
+            let (start, end) = select_text_object(Language::Rust, source, byte, byte, object)?;
+            Some(&source[start..end])
+        };
+        let b = source.find("a, b").unwrap() + 3;
+        assert_eq!(select(b, TextObject::Argument), Some("b"));
+        assert_eq!(select(6, TextObject::Argument), Some("a: u8"));
+        assert_eq!(select(b, TextObject::Block), Some("{\n    g(a, b)\n}"));
+        assert_eq!(select(b, TextObject::Function), Some(source.trim_end()));
+        assert_eq!(select(0, TextObject::Argument), None);
+        assert!(select_text_object(Language::Markdown, source, 0, 0, TextObject::Block).is_none());
+
+        let expand = |(start, end)| expand_selection(Language::Rust, source, start, end).unwrap();
+        let identifier = expand((b, b));
+        assert_eq!(&source[identifier.0..identifier.1], "b");
+        let arguments = expand(identifier);
+        assert_eq!(&source[arguments.0..arguments.1], "(a, b)");
+        let call = expand(arguments);
+        assert_eq!(&source[call.0..call.1], "g(a, b)");
+    }
+}
@@ -1,122 +1,98 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fs::File,
-    io::Read,
-};
+use std::collections::{HashMap, HashSet};
 
-use egui::{
-    text::LayoutJob, Color32, FontData, FontDefinitions, FontId, FontTweak, Label, Rect, RichText,
-};
+use egui::{text::LayoutJob, Color32, FontDefinitions, FontId, Label, Rect, RichText};
 use rift_core::{
     actions::{perform_action, Action},
-    buffer::instance::{Attribute, Cursor, HighlightType, Language, Range, Selection},
-    lsp::{client::LSPClientHandle, types},
+    buffer::{
+        instance::{display_text, Attribute, HighlightType, Language},
+        line_buffer::LineEnding,
+    },
+    code_actions, config, dap, diagnostics,
+    events::{self, EventKind},
+    file_changes,
+    io::{encoding::Encoding, file_io},
+    logs,
+    lsp::{
+        client::LSPClientHandle,
+        types::{self, protocol},
+    },
+    notifications::NotificationLevel,
+    preferences::Preferences,
+    recovery, session,
     state::{EditorState, Mode},
 };
 
 use crate::{
     command_dispatcher::CommandDispatcher,
     components::{
-        completion_menu::CompletionMenu, diagnostics_overlay::DiagnosticsOverlay,
-        info_modal::InfoModal,
+        call_hierarchy::CallHierarchyView, code_action_menu::CodeActionMenuView,
+        color_swatches::ColorSwatches, command_palette::CommandPalette,
+        completion_menu::CompletionMenu, confirm_dialog::ConfirmDialogView,
+        diagnostics_overlay::DiagnosticsOverlay, diff_view::DiffView, dropped_files::DroppedFiles,
+        file_tree::FileTreePanel, git_status::GitStatusView, image_viewer::ImageViewer,
+        info_modal::InfoModal, input_dialog::InputDialogView, markdown_preview::MarkdownPreview,
+        minimap::Minimap, notifications::NotificationToasts, replace_prompt::ReplacePrompt,
+        save_as_prompt::SaveAsPrompt, scrollbar::Scrollbar, signature_help::SignatureHelpView,
+        spell_menu::SpellMenuView, split_view::SplitView, tab_bar::TabBar,
+        terminal_pane::TerminalPane,
     },
+    fonts::{font_definitions, FontSelection},
 };
 
+/// Number of lines shown in the preview of the file selected in the modal
+const MODAL_PREVIEW_LINES: usize = 40;
+
 pub struct App {
     dispatcher: CommandDispatcher,
     state: EditorState,
     font_definitions: FontDefinitions,
+    font_selection: FontSelection,
     lsp_handles: HashMap<Language, LSPClientHandle>,
     info_modal: InfoModal,
     completion_menu: CompletionMenu,
     diagnostics_overlay: DiagnosticsOverlay,
+    notification_toasts: NotificationToasts,
+    terminal_pane: TerminalPane,
+    minimap: Minimap,
+    tab_bar: TabBar,
+    file_tree: FileTreePanel,
+    markdown_preview: MarkdownPreview,
+    image_viewer: ImageViewer,
+    diff_view: DiffView,
+    confirm_dialog: ConfirmDialogView,
+    save_as_prompt: SaveAsPrompt,
+    replace_prompt: ReplacePrompt,
+    input_dialog: InputDialogView,
+    spell_menu: SpellMenuView,
+    code_action_menu: CodeActionMenuView,
+    call_hierarchy: CallHierarchyView,
+    git_status: GitStatusView,
+    signature_help: SignatureHelpView,
+    color_swatches: ColorSwatches,
+    dropped_files: DroppedFiles,
+    scrollbar: Scrollbar,
+    split_view: SplitView,
+    command_palette: CommandPalette,
+    /// Path and first lines of the file selected in the modal
+    modal_preview: Option<(String, String)>,
+    /// Scroll distance in pixels not yet applied as whole lines
+    scroll_offset: f32,
     editor_focused: bool,
+    /// Started as a diff tool, quitting once the diff is closed
+    diff_mode: bool,
 }
 
 impl App {
     pub fn new(rt: tokio::runtime::Runtime) -> Self {
-        let state = EditorState::new(rt);
-        let mut fonts = FontDefinitions::default();
-        let editor_font = font_kit::source::SystemSource::new()
-            .select_best_match(
-                &[font_kit::family_name::FamilyName::Title(
-                    state.preferences.editor_font_family.to_owned(),
-                )],
-                &font_kit::properties::Properties::new(),
-            )
-            .unwrap();
-        let ui_font = font_kit::source::SystemSource::new()
-            .select_best_match(
-                &[font_kit::family_name::FamilyName::Title(
-                    state.preferences.ui_font_family.to_owned(),
-                )],
-                &font_kit::properties::Properties::new(),
-            )
-            .unwrap();
-        fonts
-            .families
-            .get_mut(&egui::FontFamily::Monospace)
-            .unwrap()
-            .insert(0, state.preferences.editor_font_family.to_owned());
-        fonts
-            .families
-            .get_mut(&egui::FontFamily::Proportional)
-            .unwrap()
-            .insert(0, state.preferences.ui_font_family.to_owned());
-        match editor_font {
-            font_kit::handle::Handle::Path { path, font_index } => {
-                let mut font_content = Vec::new();
-                File::open(path)
-                    .unwrap()
-                    .read_to_end(&mut font_content)
-                    .unwrap();
-                fonts.font_data.insert(
-                    state.preferences.editor_font_family.to_owned(),
-                    FontData {
-                        font: std::borrow::Cow::Owned(font_content),
-                        index: font_index,
-                        tweak: FontTweak::default(),
-                    },
-                );
-            }
-            font_kit::handle::Handle::Memory { bytes, font_index } => {
-                fonts.font_data.insert(
-                    state.preferences.editor_font_family.to_owned(),
-                    FontData {
-                        font: std::borrow::Cow::Owned((*bytes).clone()),
-                        index: font_index,
-                        tweak: FontTweak::default(),
-                    },
-                );
-            }
-        }
-        match ui_font {
-            font_kit::handle::Handle::Path { path, font_index } => {
-                let mut font_content = Vec::new();
-                File::open(path)
-                    .unwrap()
-                    .read_to_end(&mut font_content)
-                    .unwrap();
-                fonts.font_data.insert(
-                    state.preferences.ui_font_family.to_owned(),
-                    FontData {
-                        font: std::borrow::Cow::Owned(font_content),
-                        index: font_index,
-                        tweak: FontTweak::default(),
-                    },
-                );
-            }
-            font_kit::handle::Handle::Memory { bytes, font_index } => {
-                fonts.font_data.insert(
-                    state.preferences.ui_font_family.to_owned(),
-                    FontData {
-                        font: std::borrow::Cow::Owned((*bytes).clone()),
-                        index: font_index,
-                        tweak: FontTweak::default(),
-                    },
-                );
-            }
-        }
+        let start = std::time::Instant::now();
+        let mut state = EditorState::new(rt);
+        config::reload_config(&mut state);
+        tracing::info!("Startup: editor state created in {:?}", start.elapsed());
+
+        let start = std::time::Instant::now();
+        let font_selection = FontSelection::from_preferences(&state.preferences);
+        let fonts = font_definitions(&font_selection);
+        tracing::info!("Startup: fonts loaded in {:?}", start.elapsed());
 
         let lsp_handles = HashMap::new();
 
@@ -125,14 +101,65 @@ impl App {
             completion_menu: CompletionMenu::new(5, state.preferences.theme.selection_bg),
             state,
             font_definitions: fonts,
+            font_selection,
             lsp_handles,
             info_modal: InfoModal::default(),
             diagnostics_overlay: DiagnosticsOverlay::default(),
+            notification_toasts: NotificationToasts::default(),
+            terminal_pane: TerminalPane::default(),
+            minimap: Minimap::default(),
+            tab_bar: TabBar::default(),
+            file_tree: FileTreePanel::default(),
+            markdown_preview: MarkdownPreview::default(),
+            image_viewer: ImageViewer::default(),
+            diff_view: DiffView::default(),
+            confirm_dialog: ConfirmDialogView::default(),
+            save_as_prompt: SaveAsPrompt::default(),
+            replace_prompt: ReplacePrompt::default(),
+            input_dialog: InputDialogView::default(),
+            spell_menu: SpellMenuView::default(),
+            code_action_menu: CodeActionMenuView::default(),
+            call_hierarchy: CallHierarchyView::default(),
+            git_status: GitStatusView::default(),
+            signature_help: SignatureHelpView::default(),
+            color_swatches: ColorSwatches::default(),
+            dropped_files: DroppedFiles::default(),
+            scrollbar: Scrollbar::default(),
+            split_view: SplitView::default(),
+            command_palette: CommandPalette::default(),
+            modal_preview: None,
+            scroll_offset: 0.0,
             editor_focused: true,
+            diff_mode: false,
         }
     }
 
+    /// Reopen the files of the last session in the current folder
+    pub fn restore_session(&mut self) {
+        if let Ok(folder) = std::env::current_dir().and_then(std::path::absolute) {
+            self.state.workspace_folder = folder.to_string_lossy().to_string();
+            session::restore_session(&mut self.state);
+        }
+    }
+
+    /// Offer to restore the unsaved changes left behind by a crash
+    pub fn offer_recovery(&mut self) {
+        recovery::offer_recovery(&mut self.state);
+    }
+
+    /// Show a diff of the two files and quit once it is closed
+    pub fn open_diff(&mut self, left: String, right: String) {
+        perform_action(Action::OpenDiff(left, right), &mut self.state, &mut None);
+        self.diff_mode = self.state.diff_view.is_some();
+    }
+
     pub fn draw(&mut self, ctx: &egui::Context) {
+        // Reload fonts when they are changed in the preferences
+        let font_selection = FontSelection::from_preferences(&self.state.preferences);
+        if font_selection != self.font_selection {
+            self.font_definitions = font_definitions(&font_selection);
+            self.font_selection = font_selection;
+        }
         ctx.set_fonts(self.font_definitions.clone());
         ctx.style_mut(|style| {
             style.visuals.override_text_color = Some(self.state.preferences.theme.ui_text.into());
@@ -181,7 +208,7 @@ impl App {
         });
         let mut char_height = 0.0;
         let mut char_width = 0.0;
-        let mut gutter_width = 0.0;
+        let mut editor_origin = egui::Pos2::ZERO;
         egui::TopBottomPanel::bottom("status_line")
             .resizable(false)
             .show_separator_line(false)
@@ -199,9 +226,14 @@ impl App {
                 if self.state.buffer_idx.is_some() {
                     let (buffer, instance) =
                         self.state.get_buffer_by_id(self.state.buffer_idx.unwrap());
-                    let file_path = buffer.file_path.clone();
+                    let file_path = buffer.display_name();
                     let modified = buffer.modified;
+                    let encoding = buffer.encoding;
+                    let line_ending = buffer.line_ending_or(&self.state.preferences.line_ending);
                     let cursor = instance.cursor;
+                    let text_stats = self
+                        .state
+                        .text_stats_segment(self.state.buffer_idx.unwrap());
 
                     ui.horizontal(|ui| {
                         let mode = &self.state.mode;
@@ -214,12 +246,26 @@ impl App {
                                 RichText::new("INSERT")
                                     .color(self.state.preferences.theme.status_bar_insert_mode_fg),
                             ),
+                            Mode::Terminal => ui.label(
+                                RichText::new("TERMINAL")
+                                    .color(self.state.preferences.theme.status_bar_insert_mode_fg),
+                            ),
+                            Mode::Custom(name) => ui.label(
+                                RichText::new(self.state.preferences.mode_indicator(name))
+                                    .color(self.state.preferences.theme.status_bar_normal_mode_fg),
+                            ),
                         };
+                        let pending_keys = self.state.pending_keys.pending();
+                        if !pending_keys.is_empty() {
+                            ui.label(pending_keys);
+                        }
                         ui.separator();
-                        ui.label(file_path.as_ref().unwrap());
+                        ui.label(file_path);
                         ui.separator();
                         ui.label(format!("{}:{}", cursor.row + 1, cursor.column + 1));
                         ui.separator();
+                        ui.label(text_stats);
+                        ui.separator();
                         ui.label(if modified { "U" } else { "" });
                         ui.separator();
                         if ui.button("+").clicked() {
@@ -244,64 +290,176 @@ impl App {
                             }
                         };
                         ui.separator();
-                        if ui
-                            .button(
-                                (if self.state.preferences.line_ending == "\n" {
-                                    "lf"
-                                } else {
-                                    "crlf"
-                                })
-                                .to_string(),
-                            )
-                            .clicked()
-                        {
-                            if self.state.preferences.line_ending == "\n" {
-                                self.state.preferences.line_ending = "\r\n".to_string()
-                            } else {
-                                self.state.preferences.line_ending = "\n".to_string();
-                            }
+                        if ui.button(line_ending.label()).clicked() {
+                            let converted = match line_ending {
+                                LineEnding::Lf => LineEnding::Crlf,
+                                LineEnding::Crlf => LineEnding::Lf,
+                            };
+                            perform_action(
+                                Action::ConvertLineEndings(converted),
+                                &mut self.state,
+                                &mut None,
+                            );
+                        };
+                        ui.separator();
+                        if ui.button(encoding.label()).clicked() {
+                            let next = Encoding::ALL
+                                .iter()
+                                .position(|candidate| *candidate == encoding)
+                                .map_or(0, |idx| (idx + 1) % Encoding::ALL.len());
+                            perform_action(
+                                Action::SetBufferEncoding(Encoding::ALL[next]),
+                                &mut self.state,
+                                &mut None,
+                            );
                         };
                         ui.separator();
                     });
                 }
             });
-        egui::SidePanel::left("gutter")
-            .resizable(false)
-            .show_separator_line(false)
-            .frame(egui::Frame {
-                fill: self.state.preferences.theme.gutter_bg.into(),
-                inner_margin: egui::Margin::same(self.state.preferences.gutter_padding),
-                ..Default::default()
-            })
-            .show(ctx, |ui| {
-                ui.spacing_mut().item_spacing = egui::vec2(0.0, 0.0);
-                let rect = ui.max_rect();
-                gutter_width = rect.width() + self.state.preferences.gutter_padding * 2.0;
-                for (idx, gutter_line) in self.state.gutter_info.iter().enumerate() {
-                    let gutter_value = if gutter_line.wrapped {
-                        ".".to_string()
-                    } else {
-                        format!("{}", gutter_line.start.row + 1)
-                    };
-                    if idx == self.state.relative_cursor.row {
-                        ui.label(
-                            RichText::new(gutter_value)
-                                .font(FontId::monospace(
-                                    self.state.preferences.editor_font_size as f32,
-                                ))
-                                .color(self.state.preferences.theme.gutter_text_current_line),
+        self.tab_bar.show(ctx, &mut self.state);
+        self.terminal_pane.show(ctx, &mut self.state);
+        let dropped_files_idle =
+            self.dropped_files
+                .show(ctx, &mut self.state, &mut self.lsp_handles);
+        let command_palette_idle =
+            self.command_palette
+                .show(ctx, &mut self.state, &mut self.lsp_handles);
+        let file_tree_idle = self
+            .file_tree
+            .show(ctx, &mut self.state, &mut self.lsp_handles);
+        self.markdown_preview.show(ctx, &mut self.state);
+        // Split panes draw their own gutters
+        if !self.state.layout.is_split() && self.state.preferences.show_gutter {
+            egui::SidePanel::left("gutter")
+                .resizable(false)
+                .show_separator_line(false)
+                .frame(egui::Frame {
+                    fill: self.state.preferences.theme.gutter_bg.into(),
+                    inner_margin: egui::Margin::same(self.state.preferences.gutter_padding),
+                    ..Default::default()
+                })
+                .show(ctx, |ui| {
+                    ui.spacing_mut().item_spacing = egui::vec2(0.0, 0.0);
+                    let font_id = FontId::monospace(self.state.preferences.editor_font_size as f32);
+                    let mut clicked_row = None;
+                    let number_width = self.state.line_number_width(self.state.buffer_idx);
+                    for (idx, gutter_line) in self.state.gutter_info.iter().enumerate() {
+                        let gutter_value = gutter_line.label(number_width);
+                        let (sign, marker, git_marker) = if gutter_line.wrapped {
+                            (None, None, None)
+                        } else {
+                            (
+                                self.state.sign(gutter_line.start.row),
+                                self.state.gutter_marker(gutter_line.start.row),
+                                self.state.git_marker(gutter_line.start.row),
+                            )
+                        };
+                        let gutter_color = if idx == self.state.relative_cursor.row {
+                            self.state.preferences.theme.gutter_text_current_line
+                        } else {
+                            self.state.preferences.theme.gutter_text
+                        };
+                        // Breakpoints are toggled by clicking the gutter
+                        let mut job = LayoutJob::default();
+                        let (sign, sign_color) = match sign {
+                            Some(sign) => (
+                                sign.text,
+                                self.state.preferences.theme.highlight_color(sign.highlight),
+                            ),
+                            None => (' ', self.state.preferences.theme.gutter_text),
+                        };
+                        job.append(
+                            &format!("{} ", sign),
+                            0.0,
+                            egui::TextFormat::simple(font_id.clone(), sign_color.into()),
+                        );
+                        job.append(
+                            &gutter_value,
+                            0.0,
+                            egui::TextFormat::simple(font_id.clone(), gutter_color.into()),
+                        );
+                        job.append(
+                            &format!(" {}", marker.unwrap_or(' ')),
+                            0.0,
+                            egui::TextFormat::simple(
+                                font_id.clone(),
+                                self.state.preferences.theme.error.into(),
+                            ),
                         );
-                    } else {
-                        ui.label(
-                            RichText::new(gutter_value)
-                                .font(FontId::monospace(
-                                    self.state.preferences.editor_font_size as f32,
-                                ))
-                                .color(self.state.preferences.theme.gutter_text),
+                        let (git_marker, git_color) = match git_marker {
+                            Some((marker, kind)) => {
+                                (marker, self.state.preferences.theme.git_color(kind))
+                            }
+                            None => (' ', self.state.preferences.theme.gutter_text),
+                        };
+                        job.append(
+                            &git_marker.to_string(),
+                            0.0,
+                            egui::TextFormat::simple(font_id.clone(), git_color.into()),
                         );
+                        if ui
+                            .add(Label::new(job).sense(egui::Sense::click()))
+                            .clicked()
+                            && !gutter_line.wrapped
+                        {
+                            clicked_row = Some(gutter_line.start.row);
+                        }
                     }
-                }
-            });
+                    if let Some(row) = clicked_row {
+                        perform_action(
+                            Action::ToggleBreakpointAtRow(row),
+                            &mut self.state,
+                            &mut None,
+                        );
+                    }
+                });
+        }
+        self.minimap.show(ctx, &mut self.state);
+        self.scrollbar.show(ctx, &mut self.state);
+        let image_viewer_idle = self.image_viewer.show(ctx, &mut self.state);
+        let diff_view_idle = self.diff_view.show(ctx, &mut self.state);
+
+        // Ask before closing the window with unsaved changes
+        if ctx.input(|i| i.viewport().close_requested()) && !self.state.quit {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            perform_action(Action::Quit, &mut self.state, &mut None);
+        }
+        if ctx.input(|i| {
+            i.events
+                .iter()
+                .any(|event| matches!(event, egui::Event::WindowFocused(true)))
+        }) {
+            self.state
+                .send_event(EventKind::FocusGained, self.state.buffer_idx);
+        }
+        let confirm_dialog_idle = self.confirm_dialog.show(ctx, &mut self.state);
+        let save_as_prompt_idle = self.save_as_prompt.show(ctx, &mut self.state);
+        let input_dialog_idle = self.input_dialog.show(ctx, &mut self.state);
+        let replace_prompt_idle =
+            self.replace_prompt
+                .show(ctx, &mut self.state, &mut self.lsp_handles);
+        let spell_menu_idle = self
+            .spell_menu
+            .show(ctx, &mut self.state, &mut self.lsp_handles);
+        let code_action_menu_idle =
+            self.code_action_menu
+                .show(ctx, &mut self.state, &mut self.lsp_handles);
+        let call_hierarchy_idle =
+            self.call_hierarchy
+                .show(ctx, &mut self.state, &mut self.lsp_handles);
+        let git_status_idle = self
+            .git_status
+            .show(ctx, &mut self.state, &mut self.lsp_handles);
+        let color_picker_idle =
+            self.color_swatches
+                .show(ctx, &mut self.state, &mut self.lsp_handles);
+        if self.diff_mode && self.state.diff_view.is_none() {
+            self.state.quit = true;
+        }
+        if self.state.quit {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
         egui::CentralPanel::default()
             .frame(egui::Frame {
                 fill: self.state.preferences.theme.editor_bg.into(),
@@ -324,235 +482,227 @@ impl App {
             .show(ctx, |ui| {
                 ui.spacing_mut().item_spacing = egui::vec2(0.0, 0.0);
                 let rect = ui.max_rect();
-                let visible_lines = (rect.height() / char_height).floor() as usize;
-                let max_characters = (rect.width() / char_width).floor() as usize;
+                let pane_rects = SplitView::pane_rects(&self.state, rect, char_width);
+                let text_rect = pane_rects
+                    .iter()
+                    .find(|pane| pane.id == self.state.layout.focused)
+                    .map(|pane| pane.text_rect)
+                    .unwrap_or(rect);
+                editor_origin = text_rect.min;
+                let visible_lines = (text_rect.height() / char_height).floor() as usize;
+                let max_characters = (text_rect.width() / char_width).floor() as usize;
 
-                if let Ok(async_result) = self.state.async_handle.receiver.try_recv() {
-                    let (buffer, _instance) =
-                        self.state.get_buffer_by_id(self.state.buffer_idx.unwrap());
-                    let mut lsp_handle = self.lsp_handles.get_mut(&buffer.language);
-                    (async_result.callback)(async_result.result, &mut self.state, &mut lsp_handle);
+                // Results arrive line by line for running tasks
+                while let Ok(async_result) = self.state.async_handle.receiver.try_recv() {
+                    let language = self
+                        .state
+                        .buffer_idx
+                        .map(|id| self.state.get_buffer_by_id(id).0.language);
+                    let lsp_handle =
+                        &mut language.and_then(|language| self.lsp_handles.get_mut(&language));
+                    (async_result.callback)(async_result.result, &mut self.state, lsp_handle);
+                }
+                dap::poll_debugger(&mut self.state);
+                logs::poll_logs(&mut self.state);
+                config::poll_config(&mut self.state);
+                let language = self
+                    .state
+                    .buffer_idx
+                    .map(|id| self.state.get_buffer_by_id(id).0.language);
+                let lsp_handle =
+                    &mut language.and_then(|language| self.lsp_handles.get_mut(&language));
+                events::poll_events(&mut self.state, lsp_handle);
+                recovery::write_journals(&mut self.state);
+                for id in file_changes::poll_file_changes(&mut self.state) {
+                    let language = self.state.get_buffer_by_id(id).0.language;
+                    let lsp_handle = &mut self.lsp_handles.get_mut(&language);
+                    perform_action(Action::ReloadBuffer(id), &mut self.state, lsp_handle);
                 }
 
                 if self.state.buffer_idx.is_some() {
                     let (buffer, _instance) =
                         self.state.get_buffer_by_id(self.state.buffer_idx.unwrap());
-                    if let Some(lsp_handle) = self.lsp_handles.get_mut(&buffer.language) {
+                    let language = buffer.language;
+                    if let Some(restart) = self.state.lsp_restart.take() {
+                        // Dropping the servers kills them
+                        self.lsp_handles.remove(&restart);
+                        if let Some(lsp_handle) = self.state.restart_lsp(restart) {
+                            self.lsp_handles.insert(restart, lsp_handle);
+                        }
+                    }
+                    if self
+                        .lsp_handles
+                        .get(&language)
+                        .is_some_and(|lsp_handle| !lsp_handle.is_running())
+                    {
+                        self.lsp_handles.remove(&language);
+                        self.state.notify(
+                            NotificationLevel::Error,
+                            format!("Language server for {:?} exited", language),
+                        );
+                    }
+                    if let Some(lsp_handle) = self.lsp_handles.get_mut(&language) {
+                        diagnostics::pull_diagnostics(&mut self.state, language, lsp_handle, false);
                         if let Some(message) = lsp_handle.recv_message_sync() {
                             match message {
                                 rift_core::lsp::client::IncomingMessage::Response(response) => {
-                                    if response.error.is_some() {
+                                    let method = lsp_handle
+                                        .id_method
+                                        .get(&response.id)
+                                        .cloned()
+                                        .unwrap_or_default();
+                                    let result = response.result.unwrap_or_default();
+                                    let handled = if let Some(error) = response.error {
                                         tracing::error!(
                                             "---Error: Message Id: {}\n\n{:#?}---\n",
                                             response.id,
-                                            response.error.unwrap()
+                                            error
                                         );
-                                    } else if lsp_handle.id_method[&response.id]
-                                        == "textDocument/hover"
-                                        && response.result.is_some()
+                                        Ok(())
+                                    } else if method == "textDocument/hover" && !result.is_null() {
+                                        protocol::parse_hover(result).map(|message| {
+                                            self.info_modal.info = message;
+                                            self.info_modal.active = true;
+                                            self.editor_focused = false;
+                                        })
+                                    } else if method == "textDocument/completion"
+                                        && !result.is_null()
                                     {
-                                        let message = response.result.unwrap()["contents"]["value"]
-                                            .as_str()
-                                            .unwrap()
-                                            .to_string();
-                                        self.info_modal.info = message;
-                                        self.info_modal.active = true;
-                                        self.editor_focused = false;
-                                    } else if lsp_handle.id_method[&response.id]
-                                        == "textDocument/completion"
-                                        && response.result.is_some()
-                                    {
-                                        let items = response.result.unwrap()["items"]
-                                            .as_array()
-                                            .unwrap()
-                                            .clone();
-                                        let mut completion_items = vec![];
-                                        for item in items {
-                                            completion_items.push(types::CompletionItem {
-                                                label: item["label"].as_str().unwrap().to_owned(),
-                                                edit: types::TextEdit {
-                                                    text: item["textEdit"]["newText"]
-                                                        .as_str()
-                                                        .unwrap()
-                                                        .to_owned(),
-                                                    range: Selection {
-                                                        cursor: Cursor {
-                                                            row: item["textEdit"]["range"]["end"]
-                                                                ["line"]
-                                                                .as_u64()
-                                                                .unwrap()
-                                                                as usize,
-                                                            column: item["textEdit"]["range"]["end"]
-                                                                ["character"]
-                                                                .as_u64()
-                                                                .unwrap()
-                                                                as usize,
-                                                        },
-                                                        mark: Cursor {
-                                                            row: item["textEdit"]["range"]["start"]
-                                                                ["line"]
-                                                                .as_u64()
-                                                                .unwrap()
-                                                                as usize,
-                                                            column: item["textEdit"]["range"]
-                                                                ["start"]["character"]
-                                                                .as_u64()
-                                                                .unwrap()
-                                                                as usize,
-                                                        },
-                                                    },
-                                                },
-                                            });
-                                        }
-                                        self.completion_menu.set_items(completion_items);
-                                        self.completion_menu.active = true;
-                                        self.editor_focused = false;
-                                    } else if lsp_handle.id_method[&response.id]
-                                        == "textDocument/formatting"
-                                        && response.result.is_some()
-                                    {
-                                        let edits =
-                                            response.result.unwrap().as_array().unwrap().clone();
-                                        for edit in edits {
-                                            let text_edit = types::TextEdit {
-                                                text: edit["newText"].as_str().unwrap().to_owned(),
-                                                range: Selection {
-                                                    cursor: Cursor {
-                                                        row: edit["range"]["end"]["line"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                        column: edit["range"]["end"]["character"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                    },
-                                                    mark: Cursor {
-                                                        row: edit["range"]["start"]["line"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                        column: edit["range"]["start"]["character"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                    },
-                                                },
-                                            };
+                                        let (_buffer, instance) = self
+                                            .state
+                                            .get_buffer_by_id(self.state.buffer_idx.unwrap());
+                                        protocol::parse_completion(result, instance.cursor).map(
+                                            |completion_items| {
+                                                self.completion_menu.set_items(completion_items);
+                                                self.completion_menu.active = true;
+                                                self.editor_focused = false;
+                                            },
+                                        )
+                                    } else if method == "textDocument/foldingRange" {
+                                        protocol::parse_folding_ranges(result).map(|ranges| {
                                             perform_action(
-                                                Action::DeleteText(text_edit.range),
+                                                Action::SetFoldingRanges(ranges),
                                                 &mut self.state,
                                                 &mut Some(lsp_handle),
                                             );
+                                        })
+                                    } else if method == "textDocument/prepareCallHierarchy" {
+                                        protocol::parse_call_hierarchy_items(result).map(|items| {
                                             perform_action(
-                                                Action::InsertText(
-                                                    text_edit.text,
-                                                    text_edit.range.mark,
-                                                ),
+                                                Action::SetCallHierarchy(items),
                                                 &mut self.state,
                                                 &mut Some(lsp_handle),
                                             );
-                                        }
+                                        })
+                                    } else if method.starts_with("callHierarchy/") {
+                                        protocol::parse_calls(
+                                            result,
+                                            method.ends_with("incomingCalls"),
+                                        )
+                                        .map(|calls| {
+                                            perform_action(
+                                                Action::SetCallHierarchyCalls(calls),
+                                                &mut self.state,
+                                                &mut Some(lsp_handle),
+                                            );
+                                        })
+                                    } else if method == "textDocument/signatureHelp" {
+                                        protocol::parse_signature_help(result).map(|help| {
+                                            perform_action(
+                                                Action::SetSignatureHelp(help),
+                                                &mut self.state,
+                                                &mut Some(lsp_handle),
+                                            );
+                                        })
+                                    } else if method == "textDocument/diagnostic" {
+                                        protocol::parse_document_diagnostics(result).map(
+                                            |diagnostics| {
+                                                perform_action(
+                                                    Action::SetPulledDiagnostics(
+                                                        response.id,
+                                                        diagnostics,
+                                                    ),
+                                                    &mut self.state,
+                                                    &mut Some(lsp_handle),
+                                                );
+                                            },
+                                        )
+                                    } else if method == "textDocument/codeAction" {
+                                        protocol::parse_code_actions(result).map(|actions| {
+                                            perform_action(
+                                                Action::ShowCodeActions(actions),
+                                                &mut self.state,
+                                                &mut Some(lsp_handle),
+                                            );
+                                        })
+                                    } else if method == "textDocument/formatting" {
+                                        protocol::parse_text_edits(result).map(|edits| {
+                                            for text_edit in edits {
+                                                perform_action(
+                                                    Action::DeleteText(text_edit.range),
+                                                    &mut self.state,
+                                                    &mut Some(lsp_handle),
+                                                );
+                                                perform_action(
+                                                    Action::InsertText(
+                                                        text_edit.text,
+                                                        text_edit.range.mark,
+                                                    ),
+                                                    &mut self.state,
+                                                    &mut Some(lsp_handle),
+                                                );
+                                            }
+                                        })
                                     } else {
                                         let message = format!(
                                             "---Response to: {}({})\n\n{:#?}---\n",
-                                            lsp_handle.id_method[&response.id],
-                                            response.id,
-                                            response.result
+                                            method, response.id, result
                                         );
                                         tracing::info!("{}", message);
+                                        Ok(())
+                                    };
+                                    if let Err(err) = handled {
+                                        self.state.notify(
+                                            NotificationLevel::Warning,
+                                            format!("{:#}", err),
+                                        );
                                     }
                                 }
+                                rift_core::lsp::client::IncomingMessage::Request(request) => {
+                                    code_actions::handle_server_request(
+                                        &mut self.state,
+                                        request,
+                                        lsp_handle,
+                                    );
+                                }
                                 rift_core::lsp::client::IncomingMessage::Notification(
                                     notification,
-                                ) => {
-                                    if notification.method == "textDocument/publishDiagnostics"
-                                        && notification.params.is_some()
+                                ) => match notification.params {
+                                    Some(params)
+                                        if notification.method
+                                            == "textDocument/publishDiagnostics" =>
                                     {
-                                        let mut uri = std::path::absolute(
-                                            notification.params.as_ref().unwrap()["uri"]
-                                                .as_str()
-                                                .unwrap()
-                                                .strip_prefix("file:")
-                                                .unwrap()
-                                                .trim_start_matches("\\"),
-                                        )
-                                        .unwrap()
-                                        .to_str()
-                                        .unwrap()
-                                        .to_string();
-                                        #[cfg(target_os = "windows")]
-                                        {
-                                            uri = uri.to_lowercase();
-                                        }
-
-                                        let mut diagnostics = types::PublishDiagnostics {
-                                            uri,
-                                            version: notification.params.as_ref().unwrap()
-                                                ["version"]
-                                                .as_u64()
-                                                .unwrap_or(0)
-                                                as usize,
-                                            diagnostics: vec![],
-                                        };
-
-                                        for diagnostic in notification.params.as_ref().unwrap()
-                                            ["diagnostics"]
-                                            .as_array()
-                                            .unwrap()
-                                        {
-                                            diagnostics.diagnostics.push(types::Diagnostic {
-                                                range: Selection {
-                                                    cursor: Cursor {
-                                                        row: diagnostic["range"]["end"]["line"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                        column: diagnostic["range"]["end"]
-                                                            ["character"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                    },
-                                                    mark: Cursor {
-                                                        row: diagnostic["range"]["start"]["line"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                        column: diagnostic["range"]["start"]
-                                                            ["character"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                    },
-                                                },
-                                                severity: match diagnostic["severity"]
-                                                    .as_u64()
-                                                    .unwrap_or(1)
-                                                {
-                                                    1 => types::DiagnosticSeverity::Error,
-                                                    2 => types::DiagnosticSeverity::Warning,
-                                                    3 => types::DiagnosticSeverity::Information,
-                                                    4 => types::DiagnosticSeverity::Hint,
-                                                    _ => types::DiagnosticSeverity::Error,
-                                                },
-                                                code: diagnostic["code"].to_string(),
-                                                source: diagnostic["source"].to_string(),
-                                                message: diagnostic["message"].to_string(),
-                                            });
+                                        match protocol::parse_publish_diagnostics(params) {
+                                            Ok(diagnostics) => {
+                                                self.state.publish_diagnostics(diagnostics);
+                                            }
+                                            Err(err) => {
+                                                self.state.notify(
+                                                    NotificationLevel::Warning,
+                                                    format!("{:#}", err),
+                                                );
+                                            }
                                         }
-                                        self.state
-                                            .diagnostics
-                                            .insert(diagnostics.uri.clone(), diagnostics);
-                                    } else {
+                                    }
+                                    params => {
                                         let message = format!(
                                             "---Notification: {}\n\n{:#?}---\n",
-                                            notification.method, notification.params
+                                            notification.method, params
                                         );
                                         tracing::info!("{}", message);
                                         self.diagnostics_overlay.info = message;
                                     }
-                                }
+                                },
                             }
                         }
                     }
@@ -566,96 +716,98 @@ impl App {
                     self.state.update_view = true;
                 }
 
+                // Mouse wheel and trackpad scrolling (smoothed and with momentum by egui)
+                if self.state.buffer_idx.is_some() && ui.rect_contains_pointer(rect) {
+                    self.scroll_offset += ui.input(|i| i.smooth_scroll_delta.y);
+                    let lines = (self.scroll_offset / char_height).trunc();
+                    if lines != 0.0 {
+                        self.scroll_offset -= lines * char_height;
+                        let action = if lines > 0.0 {
+                            Action::ScrollUp(lines as usize)
+                        } else {
+                            Action::ScrollDown(-lines as usize)
+                        };
+                        // Scroll the pane under the pointer
+                        let focused = self.state.layout.focused;
+                        let hovered = ui
+                            .input(|i| i.pointer.hover_pos())
+                            .and_then(|pos| pane_rects.iter().find(|pane| pane.rect.contains(pos)))
+                            .map(|pane| pane.id)
+                            .unwrap_or(focused);
+                        perform_action(Action::FocusPane(hovered), &mut self.state, &mut None);
+                        perform_action(action, &mut self.state, &mut None);
+                        perform_action(Action::FocusPane(focused), &mut self.state, &mut None);
+                        self.state.update_view = true;
+                    }
+                }
+
+                // Update the git change markers once the debounce passes
+                if self.state.git.is_pending() {
+                    self.state.update_view = true;
+                }
                 if self.state.update_view {
-                    self.state.relative_cursor =
-                        self.update_visible_lines(visible_lines, max_characters);
+                    self.state.relative_cursor = self
+                        .state
+                        .update_visible_lines(visible_lines, max_characters);
                     self.state.update_view = false;
                 }
 
-                for line in &self.state.highlighted_text {
-                    let mut job = LayoutJob::default();
-                    for token in line {
-                        let mut format = egui::TextFormat {
-                            font_id: FontId::monospace(
-                                self.state.preferences.editor_font_size as f32,
-                            ),
-                            ..Default::default()
-                        };
-                        for attribute in &token.1 {
-                            match attribute {
-                                Attribute::None => {}
-                                Attribute::Visible => {}
-                                Attribute::Underline => {}
-                                Attribute::Highlight(highlight_type) => {
-                                    format.color = match highlight_type {
-                                        HighlightType::None => {
-                                            self.state.preferences.theme.highlight_none.into()
-                                        }
-                                        HighlightType::White => {
-                                            self.state.preferences.theme.highlight_white.into()
-                                        }
-                                        HighlightType::Red => {
-                                            self.state.preferences.theme.highlight_red.into()
-                                        }
-                                        HighlightType::Orange => {
-                                            self.state.preferences.theme.highlight_orange.into()
-                                        }
-                                        HighlightType::Blue => {
-                                            self.state.preferences.theme.highlight_blue.into()
-                                        }
-                                        HighlightType::Green => {
-                                            self.state.preferences.theme.highlight_green.into()
-                                        }
-                                        HighlightType::Purple => {
-                                            self.state.preferences.theme.highlight_purple.into()
-                                        }
-                                        HighlightType::Yellow => {
-                                            self.state.preferences.theme.highlight_yellow.into()
-                                        }
-                                        HighlightType::Gray => {
-                                            self.state.preferences.theme.highlight_gray.into()
-                                        }
-                                        HighlightType::Turquoise => {
-                                            self.state.preferences.theme.highlight_turquoise.into()
-                                        }
-                                    };
-                                }
-                                Attribute::Select => {
-                                    format.background =
-                                        self.state.preferences.theme.selection_bg.into();
-                                }
-                                Attribute::Cursor => {}
-                                Attribute::DiagnosticSeverity(severity) => {
-                                    format.underline = egui::Stroke::new(
-                                        1.0,
-                                        match severity {
-                                            types::DiagnosticSeverity::Error => {
-                                                self.state.preferences.theme.error
-                                            }
-                                            types::DiagnosticSeverity::Warning => {
-                                                self.state.preferences.theme.warning
-                                            }
-                                            types::DiagnosticSeverity::Information => {
-                                                self.state.preferences.theme.information
-                                            }
-                                            types::DiagnosticSeverity::Hint => {
-                                                self.state.preferences.theme.hint
-                                            }
-                                        },
-                                    );
-                                }
-                            }
+                if self.state.layout.is_split() {
+                    self.split_view.show(
+                        ui,
+                        &mut self.state,
+                        rect,
+                        &pane_rects,
+                        char_width,
+                        char_height,
+                    );
+                } else {
+                    for (idx, line) in self.state.highlighted_text.iter().enumerate() {
+                        let mut job = highlighted_line_job(line, &self.state.preferences);
+                        append_inline_diagnostic(
+                            &mut job,
+                            line,
+                            self.state.inline_diagnostics.get(idx),
+                            max_characters,
+                            &self.state.preferences,
+                        );
+                        if !matches!(self.state.inline_diagnostics.get(idx), Some(Some(_))) {
+                            append_inline_blame(
+                                &mut job,
+                                line,
+                                self.state.inline_annotation(idx),
+                                max_characters,
+                                &self.state.preferences,
+                            );
                         }
-                        job.append(&token.0, 0.0, format);
+                        ui.label(job);
                     }
-                    ui.label(job);
                 }
 
-                if self.editor_focused {
+                if self.editor_focused
+                    && file_tree_idle
+                    && dropped_files_idle
+                    && command_palette_idle
+                    && image_viewer_idle
+                    && diff_view_idle
+                    && confirm_dialog_idle
+                    && save_as_prompt_idle
+                    && input_dialog_idle
+                    && replace_prompt_idle
+                    && spell_menu_idle
+                    && code_action_menu_idle
+                    && call_hierarchy_idle
+                    && git_status_idle
+                    && color_picker_idle
+                {
                     self.dispatcher
                         .show(ui, &mut self.state, &mut self.lsp_handles);
                 }
             });
+        if let Some(info) = self.state.info_modal.take() {
+            self.info_modal.info = info;
+            self.info_modal.active = true;
+        }
         self.editor_focused = self.info_modal.show(ctx);
         if self.state.buffer_idx.is_some() {
             let (buffer, _instance) = self.state.get_buffer_by_id(self.state.buffer_idx.unwrap());
@@ -664,6 +816,62 @@ impl App {
                 self.editor_focused && self.completion_menu.show(ctx, &mut self.state, lsp_handle);
         }
         self.diagnostics_overlay.show(ctx);
+        self.color_swatches
+            .show_swatches(ctx, &self.state, editor_origin, char_width, char_height);
+        self.signature_help.show(
+            ctx,
+            &self.state,
+            editor_origin
+                + egui::vec2(
+                    self.state.relative_cursor.column as f32 * char_width,
+                    self.state.relative_cursor.row as f32 * char_height,
+                ),
+        );
+        self.notification_toasts.show(ctx, &mut self.state);
+        // Poll for syntax highlights being computed in the background
+        if let Some(buffer_idx) = self.state.buffer_idx {
+            let (buffer, _instance) = self.state.get_buffer_by_id(buffer_idx);
+            if buffer.highlights_pending() {
+                ctx.request_repaint_after(std::time::Duration::from_millis(10));
+            }
+        }
+        // Send edits to the language servers once the debounce window passes
+        for lsp_handle in self.lsp_handles.values() {
+            if let Err(err) = lsp_handle.flush_changes(false) {
+                tracing::error!("Failed to send changes: {}", err);
+            }
+        }
+        if self
+            .lsp_handles
+            .values()
+            .any(|lsp_handle| lsp_handle.has_pending_changes())
+        {
+            ctx.request_repaint_after(std::time::Duration::from_millis(
+                self.state.preferences.lsp_change_debounce,
+            ));
+        }
+        if self.state.git.is_pending() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(
+                self.state.preferences.git_diff_debounce,
+            ));
+        }
+        // Poll for files changed outside of the editor
+        if !self.state.buffers.is_empty() {
+            ctx.request_repaint_after(file_changes::CHECK_INTERVAL);
+        }
+        // Poll for new output of the log file
+        if self.state.log_viewer.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(500));
+        }
+        // Poll for output of a running task
+        if self
+            .state
+            .task
+            .as_ref()
+            .is_some_and(|task| task.is_running())
+        {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
         egui::CentralPanel::default()
             .frame(egui::Frame {
                 fill: Color32::TRANSPARENT,
@@ -671,37 +879,57 @@ impl App {
                 ..Default::default()
             })
             .show(ctx, |ui| {
-                ui.put(
-                    Rect::from_two_pos(
-                        egui::Pos2 {
-                            x: (self.state.relative_cursor.column as f32 * char_width)
-                                + gutter_width
-                                + self.state.preferences.editor_padding,
-                            y: (self.state.relative_cursor.row as f32 * char_height)
-                                + self.state.preferences.editor_padding,
-                        },
-                        egui::Pos2 {
-                            x: (self.state.relative_cursor.column as f32 * char_width)
-                                + gutter_width
-                                + char_width
-                                + self.state.preferences.editor_padding,
-                            y: (self.state.relative_cursor.row as f32 * char_height)
-                                + char_height
-                                + self.state.preferences.editor_padding,
-                        },
-                    ),
-                    Label::new(
-                        RichText::new(" ")
-                            .font(FontId::monospace(
-                                self.state.preferences.editor_font_size as f32,
-                            ))
-                            .background_color(if matches!(self.state.mode, Mode::Normal) {
+                let cursor_rect = Rect::from_min_size(
+                    editor_origin
+                        + egui::vec2(
+                            self.state.relative_cursor.column as f32 * char_width,
+                            self.state.relative_cursor.row as f32 * char_height,
+                        ),
+                    egui::vec2(char_width, char_height),
+                );
+                let font_id = FontId::monospace(self.state.preferences.editor_font_size as f32);
+
+                if matches!(self.state.mode, Mode::Insert) && self.editor_focused {
+                    // Allow input methods and place their candidate window at the cursor
+                    ctx.output_mut(|output| {
+                        output.ime = Some(egui::output::IMEOutput {
+                            rect: ui.max_rect(),
+                            cursor_rect,
+                        });
+                    });
+                }
+
+                if matches!(self.state.mode, Mode::Insert) && !self.dispatcher.preedit.is_empty() {
+                    // Text being composed is shown underlined at the cursor
+                    ui.put(
+                        Rect::from_min_size(
+                            cursor_rect.min,
+                            egui::vec2(
+                                char_width * self.dispatcher.preedit.chars().count() as f32,
+                                char_height,
+                            ),
+                        ),
+                        Label::new(
+                            RichText::new(&self.dispatcher.preedit)
+                                .font(font_id)
+                                .underline()
+                                .color(self.state.preferences.theme.highlight_none)
+                                .background_color(self.state.preferences.theme.editor_bg),
+                        )
+                        .extend(),
+                    );
+                } else {
+                    ui.put(
+                        cursor_rect,
+                        Label::new(RichText::new(" ").font(font_id).background_color(
+                            if matches!(self.state.mode, Mode::Normal) {
                                 self.state.preferences.theme.cursor_normal_mode_bg
                             } else {
                                 self.state.preferences.theme.cursor_insert_mode_bg
-                            }),
-                    ),
-                );
+                            },
+                        )),
+                    );
+                }
             });
         if self.state.modal_open {
             egui::Window::new("modal")
@@ -717,70 +945,187 @@ impl App {
                 })
                 .show(ctx, |ui| {
                     ui.label(&self.state.modal_input);
-                    egui::ScrollArea::vertical().show(ui, |ui| {
-                        for (idx, entry) in self.state.modal_options_filtered.iter().enumerate() {
+                    ui.horizontal_top(|ui| {
+                        ui.vertical(|ui| {
+                            egui::ScrollArea::vertical().show(ui, |ui| {
+                                for (idx, entry) in
+                                    self.state.modal_options_filtered.iter().enumerate()
+                                {
+                                    ui.label(
+                                        RichText::new(&entry.name)
+                                            .color(
+                                                if self.state.modal_selection_idx.is_some()
+                                                    && idx
+                                                        == self.state.modal_selection_idx.unwrap()
+                                                {
+                                                    self.state.preferences.theme.modal_active
+                                                } else {
+                                                    self.state.preferences.theme.modal_text
+                                                },
+                                            )
+                                            .size(self.state.preferences.ui_font_size as f32),
+                                    );
+                                }
+                            });
+                        });
+
+                        // Preview of the selected file
+                        let selected = self
+                            .state
+                            .modal_selection_idx
+                            .and_then(|idx| self.state.modal_options_filtered.get(idx))
+                            .filter(|entry| !entry.is_dir);
+                        if let Some(entry) = selected {
+                            if self.modal_preview.as_ref().map(|(path, _)| path)
+                                != Some(&entry.path)
+                            {
+                                let preview = match file_io::read_file_content(&entry.path) {
+                                    Ok(content) => content
+                                        .lines()
+                                        .take(MODAL_PREVIEW_LINES)
+                                        .collect::<Vec<_>>()
+                                        .join("\n"),
+                                    Err(_) => "Preview unavailable".to_string(),
+                                };
+                                self.modal_preview = Some((entry.path.clone(), preview));
+                            }
+                            ui.separator();
                             ui.label(
-                                RichText::new(&entry.name)
-                                    .color(
-                                        if self.state.modal_selection_idx.is_some()
-                                            && idx == self.state.modal_selection_idx.unwrap()
-                                        {
-                                            self.state.preferences.theme.modal_active
-                                        } else {
-                                            self.state.preferences.theme.modal_text
-                                        },
-                                    )
-                                    .size(self.state.preferences.ui_font_size as f32),
+                                RichText::new(&self.modal_preview.as_ref().unwrap().1)
+                                    .font(FontId::monospace(
+                                        self.state.preferences.ui_font_size as f32 * 0.8,
+                                    ))
+                                    .color(self.state.preferences.theme.modal_text),
                             );
                         }
                     });
                 });
         }
     }
+}
 
-    pub fn update_visible_lines(
-        &mut self,
-        visible_lines: usize,
-        max_characters: usize,
-    ) -> rift_core::buffer::instance::Cursor {
-        if self.state.buffer_idx.is_some() {
-            let (buffer, _instance) = self.state.get_buffer_by_id(self.state.buffer_idx.unwrap());
-            let mut extra_segments = vec![];
-            let mut path = buffer.file_path.as_ref().unwrap().clone();
-            #[cfg(target_os = "windows")]
-            {
-                path = path.to_lowercase();
-            }
+/// Append the message of the diagnostic of a line after its text if there is room for it
+pub fn append_inline_diagnostic(
+    job: &mut LayoutJob,
+    line: &[(String, HashSet<Attribute>)],
+    diagnostic: Option<&Option<(types::DiagnosticSeverity, String)>>,
+    max_characters: usize,
+    preferences: &Preferences,
+) {
+    let Some(Some((severity, message))) = diagnostic else {
+        return;
+    };
+    let line_length: usize = line.iter().map(|token| token.0.chars().count()).sum();
+    let Some(message) =
+        diagnostics::inline_message(message, max_characters.saturating_sub(line_length + 4))
+    else {
+        return;
+    };
+    job.append(
+        &format!("    {}", message),
+        0.0,
+        egui::TextFormat {
+            font_id: FontId::monospace(preferences.editor_font_size as f32),
+            color: Color32::from(preferences.theme.diagnostic_color(severity)).gamma_multiply(0.8),
+            italics: true,
+            ..Default::default()
+        },
+    );
+}
 
-            if let Some(diagnostics) = self.state.diagnostics.get(&path) {
-                if diagnostics.version != 0 && diagnostics.version == buffer.version {
-                    for diagnostic in &diagnostics.diagnostics {
-                        extra_segments.push(Range {
-                            start: buffer.byte_index_from_cursor(&diagnostic.range.mark, "\n"),
-                            end: buffer.byte_index_from_cursor(&diagnostic.range.cursor, "\n"),
-                            attributes: HashSet::from([Attribute::DiagnosticSeverity(
-                                diagnostic.severity.clone(),
-                            )]),
-                        });
-                    }
+/// Append the blame annotation of the line if there is room for it
+pub fn append_inline_blame(
+    job: &mut LayoutJob,
+    line: &[(String, HashSet<Attribute>)],
+    blame: Option<&Option<String>>,
+    max_characters: usize,
+    preferences: &Preferences,
+) {
+    let Some(Some(blame)) = blame else {
+        return;
+    };
+    let line_length: usize = line.iter().map(|token| token.0.chars().count()).sum();
+    let Some(blame) =
+        diagnostics::inline_message(blame, max_characters.saturating_sub(line_length + 4))
+    else {
+        return;
+    };
+    job.append(
+        &format!("    {}", blame),
+        0.0,
+        egui::TextFormat {
+            font_id: FontId::monospace(preferences.editor_font_size as f32),
+            color: preferences.theme.gutter_text.into(),
+            italics: true,
+            ..Default::default()
+        },
+    );
+}
+
+/// Build a layout job for a line of highlighted text
+pub fn highlighted_line_job(
+    line: &[(String, HashSet<Attribute>)],
+    preferences: &Preferences,
+) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for token in line {
+        let mut format = egui::TextFormat {
+            font_id: FontId::monospace(preferences.editor_font_size as f32),
+            ..Default::default()
+        };
+        for attribute in &token.1 {
+            match attribute {
+                Attribute::None => {}
+                Attribute::Visible => {}
+                Attribute::Underline => {}
+                Attribute::Highlight(highlight_type) => {
+                    format.color = match highlight_type {
+                        HighlightType::None => preferences.theme.highlight_none.into(),
+                        HighlightType::White => preferences.theme.highlight_white.into(),
+                        HighlightType::Red => preferences.theme.highlight_red.into(),
+                        HighlightType::Orange => preferences.theme.highlight_orange.into(),
+                        HighlightType::Blue => preferences.theme.highlight_blue.into(),
+                        HighlightType::Green => preferences.theme.highlight_green.into(),
+                        HighlightType::Purple => preferences.theme.highlight_purple.into(),
+                        HighlightType::Yellow => preferences.theme.highlight_yellow.into(),
+                        HighlightType::Gray => preferences.theme.highlight_gray.into(),
+                        HighlightType::Turquoise => preferences.theme.highlight_turquoise.into(),
+                    };
+                }
+                Attribute::Select => {
+                    format.background = preferences.theme.selection_bg.into();
+                }
+                Attribute::Cursor => {}
+                Attribute::Misspelled => {
+                    format.underline = egui::Stroke::new(1.0, preferences.theme.hint);
+                }
+                Attribute::DebugStop => {
+                    format.background =
+                        Color32::from(preferences.theme.warning).gamma_multiply(0.3);
+                }
+                Attribute::Conflict(side) => {
+                    format.background = preferences.theme.conflict_bg(*side).into();
+                }
+                // Applied after the syntax highlight below
+                Attribute::IndentGuide | Attribute::Bracket(_) | Attribute::Invisible => {}
+                Attribute::DiagnosticSeverity(severity) => {
+                    format.underline =
+                        egui::Stroke::new(1.0, preferences.theme.diagnostic_color(severity));
+                }
+            }
+        }
+        for attribute in &token.1 {
+            match attribute {
+                Attribute::IndentGuide | Attribute::Invisible => {
+                    format.color = preferences.theme.gutter_text.into()
+                }
+                Attribute::Bracket(depth) => {
+                    format.color = preferences.theme.bracket_color(*depth).into()
                 }
+                _ => {}
             }
-            let (buffer, instance) = self
-                .state
-                .get_buffer_by_id_mut(self.state.buffer_idx.unwrap());
-            let (lines, relative_cursor, gutter_info) = buffer.get_visible_lines(
-                &mut instance.scroll,
-                &instance.cursor,
-                &instance.selection,
-                visible_lines,
-                max_characters,
-                "\n".into(),
-                extra_segments,
-            );
-            self.state.highlighted_text = lines;
-            self.state.gutter_info = gutter_info;
-            return relative_cursor;
         }
-        rift_core::buffer::instance::Cursor { row: 0, column: 0 }
+        job.append(display_text(&token.0, &token.1), 0.0, format);
     }
+    job
 }
@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// CLI Arguments
+#[derive(Parser, Debug)]
+pub struct CLIArgs {
+    /// Compare two files side by side, for use as a git difftool
+    #[arg(long, num_args = 2, value_names = ["LEFT", "RIGHT"])]
+    pub diff: Option<Vec<PathBuf>>,
+    /// Reopen the files which were open when the editor last quit in the current folder
+    #[arg(long)]
+    pub restore_session: bool,
+}
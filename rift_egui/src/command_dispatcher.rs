@@ -5,19 +5,27 @@ use rift_core::{
     actions::{perform_action, Action},
     buffer::{instance::Language, line_buffer::LineBuffer},
     io::file_io,
+    keys::{self, KeyInput},
     lsp::client::LSPClientHandle,
+    notifications::NotificationLevel,
     state::{EditorState, Mode},
+    terminal::TerminalKey,
 };
 
-pub struct CommandDispatcher {}
+pub struct CommandDispatcher {
+    /// Text being composed by an input method (shown at the cursor until committed)
+    pub preedit: String,
+}
 
 impl CommandDispatcher {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            preedit: String::new(),
+        }
     }
 
     pub fn show(
-        &self,
+        &mut self,
         ui: &mut Ui,
         state: &mut EditorState,
         lsp_handles: &mut HashMap<Language, LSPClientHandle>,
@@ -29,7 +37,89 @@ impl CommandDispatcher {
             } else {
                 &mut None
             };
-            if !state.modal_open {
+            if matches!(state.mode, Mode::Terminal) {
+                for event in &i.raw.events {
+                    match event {
+                        egui::Event::Text(text) | egui::Event::Paste(text) => {
+                            perform_action(
+                                Action::TerminalInput(text.to_string()),
+                                state,
+                                lsp_handle,
+                            );
+                        }
+                        // Ctrl+C and Ctrl+X are reported as clipboard events
+                        egui::Event::Copy => {
+                            perform_action(
+                                Action::TerminalKey(TerminalKey::Ctrl('c')),
+                                state,
+                                lsp_handle,
+                            );
+                        }
+                        egui::Event::Cut => {
+                            perform_action(
+                                Action::TerminalKey(TerminalKey::Ctrl('x')),
+                                state,
+                                lsp_handle,
+                            );
+                        }
+                        egui::Event::Key {
+                            key,
+                            physical_key: _,
+                            pressed,
+                            repeat: _,
+                            modifiers,
+                        } if *pressed => {
+                            let terminal_key = match key {
+                                egui::Key::Escape => {
+                                    perform_action(Action::QuitTerminalMode, state, lsp_handle);
+                                    None
+                                }
+                                egui::Key::PageUp if modifiers.shift => {
+                                    perform_action(Action::TerminalScrollUp, state, lsp_handle);
+                                    None
+                                }
+                                egui::Key::PageDown if modifiers.shift => {
+                                    perform_action(Action::TerminalScrollDown, state, lsp_handle);
+                                    None
+                                }
+                                egui::Key::Enter => Some(TerminalKey::Enter),
+                                egui::Key::Backspace => Some(TerminalKey::Backspace),
+                                egui::Key::Tab => Some(TerminalKey::Tab),
+                                egui::Key::ArrowUp => Some(TerminalKey::Up),
+                                egui::Key::ArrowDown => Some(TerminalKey::Down),
+                                egui::Key::ArrowLeft => Some(TerminalKey::Left),
+                                egui::Key::ArrowRight => Some(TerminalKey::Right),
+                                egui::Key::Home => Some(TerminalKey::Home),
+                                egui::Key::End => Some(TerminalKey::End),
+                                egui::Key::Delete => Some(TerminalKey::Delete),
+                                egui::Key::PageUp => Some(TerminalKey::PageUp),
+                                egui::Key::PageDown => Some(TerminalKey::PageDown),
+                                _ => {
+                                    if modifiers.ctrl {
+                                        key.name()
+                                            .chars()
+                                            .next()
+                                            .filter(|c| {
+                                                key.name().len() == 1 && c.is_ascii_alphabetic()
+                                            })
+                                            .map(TerminalKey::Ctrl)
+                                    } else {
+                                        None
+                                    }
+                                }
+                            };
+                            if let Some(terminal_key) = terminal_key {
+                                perform_action(
+                                    Action::TerminalKey(terminal_key),
+                                    state,
+                                    lsp_handle,
+                                );
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            } else if !state.modal_open {
                 for event in &i.raw.events {
                     state.update_view = true;
                     match event {
@@ -42,6 +132,27 @@ impl CommandDispatcher {
                                 );
                             }
                         }
+                        // Input method composition (CJK input methods and dead keys)
+                        egui::Event::Ime(ime_event) => match ime_event {
+                            egui::ImeEvent::Preedit(text) => {
+                                self.preedit = text.clone();
+                            }
+                            egui::ImeEvent::Commit(text) => {
+                                self.preedit.clear();
+                                if matches!(state.mode, Mode::Insert) && !text.is_empty() {
+                                    perform_action(
+                                        Action::InsertTextAtCursor(text.to_string()),
+                                        state,
+                                        lsp_handle,
+                                    );
+                                }
+                            }
+                            egui::ImeEvent::Enabled | egui::ImeEvent::Disabled => {
+                                self.preedit.clear();
+                            }
+                        },
+                        // Keys are handled by the input method while composing
+                        egui::Event::Key { .. } if !self.preedit.is_empty() => {}
                         egui::Event::Key {
                             key,
                             physical_key: _,
@@ -50,12 +161,32 @@ impl CommandDispatcher {
                             modifiers,
                         } => {
                             if *pressed {
+                                if matches!(state.mode, Mode::Normal | Mode::Custom(_)) {
+                                    match sequence_key(key, modifiers)
+                                        .map(|name| keys::push_key(state, &name))
+                                    {
+                                        Some(KeyInput::Pending) => continue,
+                                        Some(KeyInput::Action(action)) => {
+                                            perform_action(action, state, lsp_handle);
+                                            return;
+                                        }
+                                        // Other keys do nothing in custom modes
+                                        _ if matches!(state.mode, Mode::Custom(_)) => continue,
+                                        Some(KeyInput::Unhandled) | None => {}
+                                    }
+                                }
                                 match key {
                                     egui::Key::Escape => {
                                         perform_action(Action::QuitInsertMode, state, lsp_handle);
                                     }
                                     egui::Key::I => {
-                                        if matches!(state.mode, Mode::Normal) {
+                                        if matches!(state.mode, Mode::Normal) && modifiers.alt {
+                                            perform_action(
+                                                Action::ShrinkSelection,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if matches!(state.mode, Mode::Normal) {
                                             perform_action(
                                                 Action::EnterInsertMode,
                                                 state,
@@ -65,7 +196,13 @@ impl CommandDispatcher {
                                         }
                                     }
                                     egui::Key::O => {
-                                        if matches!(state.mode, Mode::Normal) {
+                                        if matches!(state.mode, Mode::Normal) && modifiers.alt {
+                                            perform_action(
+                                                Action::ExpandSelectionToParentNode,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if matches!(state.mode, Mode::Normal) {
                                             perform_action(
                                                 Action::AddNewLineBelowAndEnterInsertMode,
                                                 state,
@@ -77,7 +214,7 @@ impl CommandDispatcher {
                                     egui::Key::Comma => {
                                         if modifiers.shift {
                                             perform_action(Action::RemoveIndent, state, lsp_handle);
-                                        } else {
+                                        } else if modifiers.alt {
                                             perform_action(
                                                 Action::CyclePreviousBuffer,
                                                 state,
@@ -88,12 +225,18 @@ impl CommandDispatcher {
                                     egui::Key::Period => {
                                         if modifiers.shift {
                                             perform_action(Action::AddIndent, state, lsp_handle);
-                                        } else {
+                                        } else if modifiers.alt {
                                             perform_action(
                                                 Action::CycleNextBuffer,
                                                 state,
                                                 lsp_handle,
                                             );
+                                        } else {
+                                            perform_action(
+                                                Action::RepeatLastChange,
+                                                state,
+                                                lsp_handle,
+                                            );
                                         }
                                     }
                                     egui::Key::Slash => {
@@ -146,7 +289,13 @@ impl CommandDispatcher {
                                         }
                                     }
                                     egui::Key::S => {
-                                        if modifiers.shift {
+                                        if modifiers.shift && modifiers.ctrl {
+                                            perform_action(
+                                                Action::OpenSaveAsPrompt,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if modifiers.shift {
                                             perform_action(
                                                 Action::SaveCurrentBuffer,
                                                 state,
@@ -161,7 +310,13 @@ impl CommandDispatcher {
                                         }
                                     }
                                     egui::Key::ArrowDown => {
-                                        if modifiers.shift {
+                                        if modifiers.shift && modifiers.alt {
+                                            perform_action(
+                                                Action::ExtendBlockDown,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if modifiers.shift {
                                             perform_action(
                                                 Action::ExtendCursorDown,
                                                 state,
@@ -176,7 +331,13 @@ impl CommandDispatcher {
                                         }
                                     }
                                     egui::Key::ArrowUp => {
-                                        if modifiers.shift {
+                                        if modifiers.shift && modifiers.alt {
+                                            perform_action(
+                                                Action::ExtendBlockUp,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if modifiers.shift {
                                             perform_action(
                                                 Action::ExtendCursorUp,
                                                 state,
@@ -187,7 +348,13 @@ impl CommandDispatcher {
                                         }
                                     }
                                     egui::Key::ArrowLeft => {
-                                        if modifiers.shift {
+                                        if modifiers.shift && modifiers.alt {
+                                            perform_action(
+                                                Action::ExtendBlockLeft,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if modifiers.shift {
                                             perform_action(
                                                 Action::ExtendCursorLeft,
                                                 state,
@@ -202,7 +369,13 @@ impl CommandDispatcher {
                                         }
                                     }
                                     egui::Key::ArrowRight => {
-                                        if modifiers.shift {
+                                        if modifiers.shift && modifiers.alt {
+                                            perform_action(
+                                                Action::ExtendBlockRight,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if modifiers.shift {
                                             perform_action(
                                                 Action::ExtendCursorRight,
                                                 state,
@@ -247,7 +420,13 @@ impl CommandDispatcher {
                                         }
                                     }
                                     egui::Key::G => {
-                                        if !modifiers.shift {
+                                        if modifiers.ctrl {
+                                            perform_action(
+                                                Action::GotoFileUnderCursor,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if !modifiers.shift {
                                             perform_action(
                                                 Action::GoToBufferStart,
                                                 state,
@@ -277,7 +456,16 @@ impl CommandDispatcher {
                                         }
                                     }
                                     egui::Key::P => {
-                                        if !modifiers.shift {
+                                        if modifiers.ctrl {
+                                            perform_action(
+                                                Action::OpenCommandPalette,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                            return;
+                                        } else if modifiers.alt {
+                                            perform_action(Action::CyclePaste, state, lsp_handle);
+                                        } else if !modifiers.shift {
                                             perform_action(
                                                 Action::PasteFromRegister,
                                                 state,
@@ -362,8 +550,36 @@ impl CommandDispatcher {
                                             }
                                         }
                                     }
+                                    egui::Key::A if modifiers.alt => {
+                                        perform_action(Action::LSPCodeActions, state, lsp_handle);
+                                    }
+                                    egui::Key::C if modifiers.alt => {
+                                        perform_action(Action::CallHierarchy, state, lsp_handle);
+                                    }
+                                    egui::Key::E if modifiers.alt => {
+                                        perform_action(Action::FocusFileTree, state, lsp_handle);
+                                    }
+                                    egui::Key::B if modifiers.alt => {
+                                        if modifiers.shift {
+                                            perform_action(
+                                                Action::GitShowCommit,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else {
+                                            perform_action(Action::GitBlameLine, state, lsp_handle);
+                                        }
+                                    }
                                     egui::Key::Z => {
-                                        if !modifiers.shift {
+                                        if modifiers.ctrl {
+                                            perform_action(
+                                                Action::SpellSuggestions,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if modifiers.alt {
+                                            perform_action(Action::FoldToggle, state, lsp_handle);
+                                        } else if !modifiers.shift {
                                             perform_action(Action::LSPHover, state, lsp_handle);
                                         } else {
                                             perform_action(
@@ -373,6 +589,60 @@ impl CommandDispatcher {
                                             );
                                         }
                                     }
+                                    egui::Key::F5 => {
+                                        if modifiers.shift {
+                                            perform_action(
+                                                Action::StopDebugging,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else {
+                                            perform_action(
+                                                Action::DebugContinue,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        }
+                                    }
+                                    egui::Key::F9 => {
+                                        perform_action(Action::ToggleBreakpoint, state, lsp_handle);
+                                    }
+                                    egui::Key::F10 => {
+                                        perform_action(Action::DebugStepOver, state, lsp_handle);
+                                    }
+                                    egui::Key::F11 => {
+                                        if modifiers.shift {
+                                            perform_action(Action::DebugStepOut, state, lsp_handle);
+                                        } else {
+                                            perform_action(
+                                                Action::DebugStepInto,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        }
+                                    }
+                                    egui::Key::CloseBracket => {
+                                        if matches!(state.mode, Mode::Normal) && modifiers.alt {
+                                            perform_action(
+                                                Action::NextDiagnostic,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if matches!(state.mode, Mode::Normal) {
+                                            perform_action(Action::NextError, state, lsp_handle);
+                                        }
+                                    }
+                                    egui::Key::OpenBracket => {
+                                        if matches!(state.mode, Mode::Normal) && modifiers.alt {
+                                            perform_action(
+                                                Action::PreviousDiagnostic,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else if matches!(state.mode, Mode::Normal) {
+                                            perform_action(Action::PrevError, state, lsp_handle);
+                                        }
+                                    }
                                     egui::Key::Backspace => {
                                         perform_action(
                                             Action::DeletePreviousCharacter,
@@ -398,7 +668,84 @@ impl CommandDispatcher {
                                         );
                                     }
                                     egui::Key::Tab => {
-                                        perform_action(Action::AddTab, state, lsp_handle);
+                                        if modifiers.shift {
+                                            perform_action(
+                                                Action::PreviousTabStop,
+                                                state,
+                                                lsp_handle,
+                                            );
+                                        } else {
+                                            perform_action(Action::AddTab, state, lsp_handle);
+                                        }
+                                    }
+                                    egui::Key::T => {
+                                        if matches!(state.mode, Mode::Normal) {
+                                            if modifiers.shift {
+                                                perform_action(
+                                                    Action::CloseTerminal,
+                                                    state,
+                                                    lsp_handle,
+                                                );
+                                            } else {
+                                                perform_action(
+                                                    Action::OpenTerminal,
+                                                    state,
+                                                    lsp_handle,
+                                                );
+                                                return;
+                                            }
+                                        }
+                                    }
+                                    egui::Key::E => {
+                                        if matches!(state.mode, Mode::Normal) {
+                                            if modifiers.shift {
+                                                perform_action(
+                                                    Action::ToggleMarkdownPreview,
+                                                    state,
+                                                    lsp_handle,
+                                                );
+                                            } else {
+                                                perform_action(
+                                                    Action::ToggleFileTree,
+                                                    state,
+                                                    lsp_handle,
+                                                );
+                                            }
+                                        }
+                                    }
+                                    egui::Key::V => {
+                                        if matches!(state.mode, Mode::Normal) {
+                                            if modifiers.shift {
+                                                perform_action(
+                                                    Action::SplitHorizontal,
+                                                    state,
+                                                    lsp_handle,
+                                                );
+                                            } else {
+                                                perform_action(
+                                                    Action::SplitVertical,
+                                                    state,
+                                                    lsp_handle,
+                                                );
+                                            }
+                                        }
+                                    }
+                                    egui::Key::N => {
+                                        if matches!(state.mode, Mode::Normal) {
+                                            if modifiers.shift {
+                                                perform_action(
+                                                    Action::ClosePane,
+                                                    state,
+                                                    lsp_handle,
+                                                );
+                                            } else {
+                                                perform_action(
+                                                    Action::FocusNextPane,
+                                                    state,
+                                                    lsp_handle,
+                                                );
+                                            }
+                                        }
                                     }
                                     egui::Key::U => {
                                         if !modifiers.shift {
@@ -475,54 +822,18 @@ impl CommandDispatcher {
                                                 [state.modal_selection_idx.unwrap()];
                                             if !entry.is_dir {
                                                 let path = entry.path.clone();
-                                                let initial_text =
-                                                    file_io::read_file_content(&path).unwrap();
-                                                let buffer = LineBuffer::new(
-                                                    initial_text.clone(),
-                                                    Some(path.clone()),
-                                                );
-
-                                                if let std::collections::hash_map::Entry::Vacant(
-                                                    e,
-                                                ) = lsp_handles.entry(buffer.language)
-                                                {
-                                                    if let Some(mut lsp_handle) =
-                                                        state.spawn_lsp(buffer.language)
-                                                    {
-                                                        lsp_handle.init_lsp_sync(
-                                                            state.workspace_folder.clone(),
-                                                        );
-                                                        e.insert(lsp_handle);
-                                                    }
-                                                }
-
-                                                if let Some(lsp_handle) =
-                                                    lsp_handles.get(&buffer.language)
-                                                {
-                                                    lsp_handle
-                                                    .send_notification_sync(
-                                                        "textDocument/didOpen".to_string(),
-                                                        Some(
-                                                            LSPClientHandle::did_open_text_document(
-                                                                path.clone(),
-                                                                initial_text,
-                                                            ),
-                                                        ),
-                                                    )
-                                                    .unwrap();
-                                                }
-
-                                                state.buffer_idx = Some(state.add_buffer(buffer));
+                                                open_file(&path, state, lsp_handles);
                                                 state.modal_open = false;
                                                 state.modal_options = vec![];
                                                 state.modal_options_filtered = vec![];
                                                 state.modal_selection_idx = None;
                                                 state.modal_input = "".into();
                                             } else {
-                                                state.modal_input = entry.path.clone();
+                                                let path = entry.path.clone();
+                                                state.modal_input = path.clone();
 
                                                 if modifiers.shift {
-                                                    state.workspace_folder = entry.path.clone();
+                                                    state.open_workspace(path.clone());
                                                 }
 
                                                 #[cfg(target_os = "windows")]
@@ -539,8 +850,7 @@ impl CommandDispatcher {
                                                 }
 
                                                 state.modal_options =
-                                                    file_io::get_directory_entries(&entry.path)
-                                                        .unwrap();
+                                                    file_io::get_directory_entries(&path).unwrap();
                                                 state.modal_options_filtered =
                                                     state.modal_options.clone();
                                                 state.modal_selection_idx = None;
@@ -566,6 +876,81 @@ impl CommandDispatcher {
     }
 }
 
+/// Name of a key which may be part of a count or key sequence in normal mode
+fn sequence_key(key: &egui::Key, modifiers: &egui::Modifiers) -> Option<String> {
+    if modifiers.ctrl || modifiers.alt || modifiers.command {
+        return None;
+    }
+    match key {
+        egui::Key::Space => Some("space".into()),
+        egui::Key::Escape => Some("escape".into()),
+        _ => {
+            let name = key.name();
+            let c = name.chars().next().filter(|_| name.len() == 1)?;
+            if c.is_ascii_digit() && !modifiers.shift {
+                Some(c.to_string())
+            } else if c.is_ascii_alphabetic() {
+                Some(if modifiers.shift {
+                    c.to_ascii_uppercase().to_string()
+                } else {
+                    c.to_ascii_lowercase().to_string()
+                })
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Open file as a buffer starting the language server if required
+pub fn open_file(
+    path: &str,
+    state: &mut EditorState,
+    lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+) {
+    if file_io::is_image_file(path) {
+        state.image_path = Some(path.to_string());
+        return;
+    }
+    if state.request_decryption(path) {
+        return;
+    }
+
+    let (initial_text, encoding) = match file_io::read_file_with_encoding(path) {
+        Ok(text) => text,
+        Err(err) => {
+            state.notify(
+                NotificationLevel::Error,
+                format!("Failed to open {}: {}", path, err),
+            );
+            return;
+        }
+    };
+    let mut buffer = LineBuffer::new(initial_text.clone(), Some(path.to_string()));
+    buffer.encoding = encoding;
+
+    if let std::collections::hash_map::Entry::Vacant(e) = lsp_handles.entry(buffer.language) {
+        if let Some(lsp_handle) = state.spawn_lsp(buffer.language, path) {
+            e.insert(lsp_handle);
+        }
+    }
+
+    if let Some(lsp_handle) = lsp_handles.get(&buffer.language) {
+        lsp_handle
+            .send_notification_sync(
+                "textDocument/didOpen".to_string(),
+                Some(LSPClientHandle::did_open_text_document(
+                    path.to_string(),
+                    initial_text,
+                )),
+            )
+            .unwrap();
+    }
+
+    state.buffer_idx = Some(state.add_buffer(buffer));
+    state.update_view = true;
+}
+
 impl Default for CommandDispatcher {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,113 @@
+use egui::RichText;
+use rift_core::{
+    actions::{perform_action, Action},
+    buffer::instance::Language,
+    lsp::client::LSPClientHandle,
+    state::EditorState,
+};
+use std::collections::HashMap;
+
+/// Tree of the callers or callees of the symbol under the cursor
+pub struct CallHierarchyView {}
+
+impl CallHierarchyView {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while the tree is open
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        let Some(tree) = &state.call_hierarchy else {
+            return true;
+        };
+        let preferences = &state.preferences;
+        let mut action = None;
+        let mut clicked = None;
+
+        egui::Window::new("call_hierarchy")
+            .movable(false)
+            .order(egui::Order::Tooltip)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .collapsible(false)
+            .title_bar(false)
+            .auto_sized()
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(tree.direction.title())
+                        .size(preferences.ui_font_size as f32)
+                        .color(preferences.theme.modal_active),
+                );
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(ctx.screen_rect().height() * 0.6)
+                    .show(ui, |ui| {
+                        for (row, idx) in tree.visible().into_iter().enumerate() {
+                            let mut text = RichText::new(tree.label(idx))
+                                .size(preferences.ui_font_size as f32)
+                                .family(egui::FontFamily::Monospace);
+                            if row == tree.idx {
+                                text = text.background_color(preferences.theme.selection_bg);
+                            }
+                            if ui
+                                .add(egui::Label::new(text).sense(egui::Sense::click()))
+                                .clicked()
+                            {
+                                clicked = Some(row);
+                            }
+                        }
+                    });
+                ui.separator();
+                ui.label(
+                    RichText::new("l: expand  h: collapse  tab: toggle direction  enter: open")
+                        .size(preferences.ui_font_size as f32 * 0.8),
+                );
+
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Escape) {
+                        action = Some(Action::CloseCallHierarchy);
+                    } else if i.key_pressed(egui::Key::Enter) {
+                        action = Some(Action::CallHierarchyOpen);
+                    } else if i.key_pressed(egui::Key::Tab) {
+                        action = Some(Action::CallHierarchyToggleDirection);
+                    } else if i.key_pressed(egui::Key::ArrowDown) || i.key_pressed(egui::Key::J) {
+                        action = Some(Action::CallHierarchyNext);
+                    } else if i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::K) {
+                        action = Some(Action::CallHierarchyPrevious);
+                    } else if i.key_pressed(egui::Key::ArrowRight) || i.key_pressed(egui::Key::L) {
+                        action = Some(Action::CallHierarchyExpand);
+                    } else if i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::H) {
+                        action = Some(Action::CallHierarchyCollapse);
+                    }
+                });
+            });
+
+        if let Some(row) = clicked {
+            if let Some(tree) = &mut state.call_hierarchy {
+                tree.idx = row;
+            }
+            action = Some(Action::CallHierarchyExpand);
+        }
+        if let Some(action) = action {
+            match state.buffer_idx {
+                Some(buffer_idx) => {
+                    let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+                    let lsp_handle = &mut lsp_handles.get_mut(&buffer.language);
+                    perform_action(action, state, lsp_handle);
+                }
+                None => perform_action(action, state, &mut None),
+            }
+        }
+        false
+    }
+}
+
+impl Default for CallHierarchyView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
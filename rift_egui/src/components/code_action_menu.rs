@@ -0,0 +1,94 @@
+use egui::RichText;
+use rift_core::{
+    actions::{perform_action, Action},
+    buffer::instance::Language,
+    lsp::client::LSPClientHandle,
+    state::EditorState,
+};
+use std::collections::HashMap;
+
+/// Menu of code actions offered by the language server
+pub struct CodeActionMenuView {}
+
+impl CodeActionMenuView {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while the menu is open
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        let Some(menu) = &mut state.code_action_menu else {
+            return true;
+        };
+        let num_items = menu.actions.len();
+        let preferences = &state.preferences;
+        let mut action = None;
+
+        egui::Window::new("code_action_menu")
+            .movable(false)
+            .order(egui::Order::Tooltip)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .collapsible(false)
+            .title_bar(false)
+            .auto_sized()
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new("Code Actions")
+                        .size(preferences.ui_font_size as f32)
+                        .color(preferences.theme.modal_active),
+                );
+                ui.separator();
+                for (idx, code_action) in menu.actions.iter().enumerate() {
+                    let mut text =
+                        RichText::new(&code_action.title).size(preferences.ui_font_size as f32);
+                    if idx == menu.idx {
+                        text = text.background_color(preferences.theme.selection_bg);
+                    }
+                    if ui
+                        .add(egui::Label::new(text).sense(egui::Sense::click()))
+                        .clicked()
+                    {
+                        action = Some(Action::ApplyCodeAction(idx));
+                    }
+                }
+
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Escape) {
+                        action = Some(Action::CloseCodeActionMenu);
+                    } else if i.key_pressed(egui::Key::Enter) {
+                        action = Some(Action::ApplyCodeAction(menu.idx));
+                    } else if i.key_pressed(egui::Key::ArrowDown)
+                        || i.key_pressed(egui::Key::Tab)
+                        || i.key_pressed(egui::Key::J)
+                    {
+                        menu.idx = (menu.idx + 1) % num_items;
+                    } else if i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::K) {
+                        menu.idx = (menu.idx + num_items - 1) % num_items;
+                    }
+                });
+            });
+
+        if let Some(action) = action {
+            match state.buffer_idx {
+                Some(buffer_idx) => {
+                    let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+                    let lsp_handle = &mut lsp_handles.get_mut(&buffer.language);
+                    perform_action(action, state, lsp_handle);
+                }
+                None => perform_action(action, state, &mut None),
+            }
+        }
+        false
+    }
+}
+
+impl Default for CodeActionMenuView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,199 @@
+use egui::{Color32, RichText};
+use rift_core::{
+    actions::{perform_action, Action},
+    buffer::instance::{Cursor, Language, Selection},
+    colors::{self, ColorFormat},
+    lsp::client::LSPClientHandle,
+    state::EditorState,
+};
+use std::collections::HashMap;
+
+/// Swatches next to color literals along with a picker to edit them
+pub struct ColorSwatches {
+    /// Literal being edited along with its notation and the picked color
+    editing: Option<(Selection, ColorFormat, Color32)>,
+}
+
+impl ColorSwatches {
+    pub fn new() -> Self {
+        Self { editing: None }
+    }
+
+    /// Show the color picker. Returns false while it is open
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        let Some((range, format, color)) = &mut self.editing else {
+            return true;
+        };
+        let preferences = &state.preferences;
+        let mut apply = None;
+
+        egui::Window::new("color_picker")
+            .movable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .title_bar(false)
+            .frame(egui::Frame {
+                fill: preferences.theme.modal_bg.into(),
+                inner_margin: egui::Margin::same(8.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                egui::color_picker::color_picker_color32(
+                    ui,
+                    color,
+                    egui::color_picker::Alpha::OnlyBlend,
+                );
+                let text = colors::format_color(color.to_srgba_unmultiplied(), *format);
+                ui.label(
+                    RichText::new(&text)
+                        .monospace()
+                        .size(preferences.ui_font_size as f32)
+                        .color(preferences.theme.modal_text),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Apply (Enter)").clicked() {
+                        apply = Some(true);
+                    }
+                    if ui.button("Cancel (Esc)").clicked() {
+                        apply = Some(false);
+                    }
+                });
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Enter) {
+                        apply = Some(true);
+                    } else if i.key_pressed(egui::Key::Escape) {
+                        apply = Some(false);
+                    }
+                });
+            });
+
+        match apply {
+            Some(true) => {
+                let text = colors::format_color(color.to_srgba_unmultiplied(), *format);
+                let range = *range;
+                self.editing = None;
+                Self::replace(state, lsp_handles, range, text);
+                true
+            }
+            Some(false) => {
+                self.editing = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the literal while keeping the cursor in place
+    fn replace(
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+        range: Selection,
+        text: String,
+    ) {
+        let Some(buffer_idx) = state.buffer_idx else {
+            return;
+        };
+        let (buffer, instance) = state.get_buffer_by_id(buffer_idx);
+        let (cursor, selection) = (instance.cursor, instance.selection);
+        let lsp_handle = &mut lsp_handles.get_mut(&buffer.language);
+        perform_action(Action::DeleteText(range), state, lsp_handle);
+        perform_action(Action::InsertText(text, range.mark), state, lsp_handle);
+
+        let (buffer, instance) = state.get_buffer_by_id_mut(buffer_idx);
+        let clamp = |cursor: Cursor| Cursor {
+            row: cursor.row,
+            column: cursor.column.min(buffer.get_line_length(cursor.row)),
+        };
+        instance.cursor = clamp(cursor);
+        instance.selection.cursor = clamp(selection.cursor);
+        instance.selection.mark = clamp(selection.mark);
+        instance.column_level = instance.cursor.column;
+        state.update_view = true;
+    }
+
+    /// Draw a swatch after the text of each visible row for the color literals in it
+    pub fn show_swatches(
+        &mut self,
+        ctx: &egui::Context,
+        state: &EditorState,
+        editor_origin: egui::Pos2,
+        char_width: f32,
+        char_height: f32,
+    ) {
+        let Some(buffer_idx) = state.buffer_idx else {
+            return;
+        };
+        let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+        if !colors::has_color_literals(buffer.language) {
+            return;
+        }
+        let size = char_height * 0.6;
+
+        for (row, gutter_line) in state.gutter_info.iter().enumerate() {
+            let Some(line) = buffer.lines.get(gutter_line.start.row) else {
+                continue;
+            };
+            let row_start = gutter_line.start.column;
+            let row_chars = line
+                .get(row_start..gutter_line.end)
+                .map(|text| text.chars().count())
+                .unwrap_or_default();
+            let literals = colors::find_colors(line)
+                .into_iter()
+                .filter(|literal| row_start <= literal.start && literal.start < gutter_line.end)
+                .enumerate();
+
+            for (idx, literal) in literals {
+                let [r, g, b, a] = literal.rgba;
+                let color = Color32::from_rgba_unmultiplied(r, g, b, a);
+                let position = editor_origin
+                    + egui::vec2(
+                        (row_chars + 1) as f32 * char_width + idx as f32 * (size + char_width),
+                        row as f32 * char_height + (char_height - size) / 2.0,
+                    );
+                let response = egui::Area::new(egui::Id::new(("color_swatch", row, idx)))
+                    .fixed_pos(position)
+                    .order(egui::Order::Middle)
+                    .show(ctx, |ui| {
+                        let (rect, response) =
+                            ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::click());
+                        ui.painter().rect_filled(rect, 2.0, color);
+                        ui.painter().rect_stroke(
+                            rect,
+                            2.0,
+                            egui::Stroke::new(1.0, state.preferences.theme.ui_fg_stroke),
+                        );
+                        response
+                    })
+                    .inner
+                    .on_hover_text("Edit color");
+                if response.clicked() {
+                    let range = Selection {
+                        cursor: Cursor {
+                            row: gutter_line.start.row,
+                            column: literal.end,
+                        },
+                        mark: Cursor {
+                            row: gutter_line.start.row,
+                            column: literal.start,
+                        },
+                    };
+                    self.editing = Some((range, literal.format, color));
+                }
+            }
+        }
+    }
+}
+
+impl Default for ColorSwatches {
+    fn default() -> Self {
+        Self::new()
+    }
+}
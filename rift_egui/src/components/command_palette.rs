@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use egui::{text::LayoutJob, Color32, FontId, RichText};
+use rift_core::{
+    actions::perform_action,
+    actions::Action,
+    buffer::instance::Language,
+    command_palette::{commands, filter_commands, prompt_commands, PaletteCommand, PromptCommand},
+    lsp::client::LSPClientHandle,
+    notifications::NotificationLevel,
+    state::EditorState,
+};
+
+/// Centered palette for searching and running commands
+pub struct CommandPalette {
+    commands: Vec<PaletteCommand>,
+    prompt_commands: Vec<PromptCommand>,
+    input: String,
+    selection_idx: usize,
+    /// Prompt command whose argument is being typed
+    prompt: Option<usize>,
+    /// Entry of the prompt history shown in the input
+    history_idx: Option<usize>,
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        Self {
+            commands: commands(),
+            prompt_commands: prompt_commands(),
+            input: "".to_string(),
+            selection_idx: 0,
+            prompt: None,
+            history_idx: None,
+        }
+    }
+
+    /// Name and keybinding (or argument prompt) of the command at the index
+    /// of the combined list of commands and prompt commands
+    fn entry(&self, idx: usize) -> (&'static str, String) {
+        match self.commands.get(idx) {
+            Some(command) => (command.name, command.keybinding.to_string()),
+            None => {
+                let command = &self.prompt_commands[idx - self.commands.len()];
+                (command.name, format!("<{}>", command.prompt.to_lowercase()))
+            }
+        }
+    }
+
+    /// Returns false while the palette is open
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        if !state.command_palette_open {
+            return true;
+        }
+
+        let names: Vec<&str> = self
+            .commands
+            .iter()
+            .map(|command| command.name)
+            .chain(self.prompt_commands.iter().map(|command| command.name))
+            .collect();
+        let matches = match self.prompt {
+            Some(_) => vec![],
+            None => filter_commands(&names, &self.input, &state.command_history),
+        };
+        let history = self
+            .prompt
+            .and_then(|idx| self.prompt_commands[idx].history)
+            .map(|history| history(state))
+            .unwrap_or_default();
+        self.selection_idx = self.selection_idx.min(matches.len().saturating_sub(1));
+        let font_id = FontId::proportional(state.preferences.ui_font_size as f32);
+        let mut execute = None;
+        let mut close = false;
+
+        egui::Window::new("command_palette")
+            .movable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+            .resizable(false)
+            .collapsible(false)
+            .title_bar(false)
+            .min_width(500.0)
+            .frame(egui::Frame {
+                fill: state.preferences.theme.modal_bg.into(),
+                inner_margin: egui::Margin::same(8.0),
+                stroke: egui::Stroke::new(1.0, state.preferences.theme.ui_border),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                let prompt = match self.prompt {
+                    Some(idx) => format!("{}:", self.prompt_commands[idx].prompt),
+                    None => ">".to_string(),
+                };
+                ui.label(
+                    RichText::new(format!("{} {}|", prompt, self.input))
+                        .font(font_id.clone())
+                        .color(state.preferences.theme.modal_active),
+                );
+                ui.separator();
+                // Past arguments of the prompt command, the selected one is in the input
+                for (idx, entry) in history.iter().enumerate().take(10) {
+                    let selected = self.history_idx == Some(idx);
+                    let response = egui::Frame::none()
+                        .fill(if selected {
+                            state.preferences.theme.selection_bg.into()
+                        } else {
+                            Color32::TRANSPARENT
+                        })
+                        .show(ui, |ui| {
+                            ui.label(
+                                RichText::new(entry)
+                                    .font(font_id.clone())
+                                    .color(state.preferences.theme.modal_text),
+                            );
+                        })
+                        .response
+                        .interact(egui::Sense::click());
+                    if response.clicked() {
+                        self.input = entry.clone();
+                        execute = Some(0);
+                    }
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for (row, (idx, indices)) in matches.iter().enumerate() {
+                            let (name, keybinding) = self.entry(*idx);
+                            let selected = row == self.selection_idx;
+
+                            // Highlight the matched characters of the name
+                            let mut job = LayoutJob::default();
+                            for (char_idx, c) in name.chars().enumerate() {
+                                let color: Color32 = if indices.contains(&char_idx) {
+                                    state.preferences.theme.modal_active.into()
+                                } else {
+                                    state.preferences.theme.modal_text.into()
+                                };
+                                job.append(
+                                    &c.to_string(),
+                                    0.0,
+                                    egui::TextFormat {
+                                        font_id: font_id.clone(),
+                                        color,
+                                        ..Default::default()
+                                    },
+                                );
+                            }
+
+                            let response = egui::Frame::none()
+                                .fill(if selected {
+                                    state.preferences.theme.selection_bg.into()
+                                } else {
+                                    Color32::TRANSPARENT
+                                })
+                                .show(ui, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.label(job);
+                                        ui.with_layout(
+                                            egui::Layout::right_to_left(egui::Align::Center),
+                                            |ui| {
+                                                ui.label(
+                                                    RichText::new(keybinding)
+                                                        .font(font_id.clone())
+                                                        .color(
+                                                            state.preferences.theme.highlight_gray,
+                                                        ),
+                                                );
+                                            },
+                                        );
+                                    });
+                                })
+                                .response
+                                .interact(egui::Sense::click());
+                            if selected {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                execute = Some(row);
+                            }
+                        }
+                    });
+                match self.handle_input(ui, matches.len(), &history) {
+                    Some(true) => execute = Some(self.selection_idx),
+                    Some(false) => close = true,
+                    None => {}
+                }
+            });
+
+        if close {
+            state.command_palette_open = false;
+            self.input.clear();
+            self.selection_idx = 0;
+            self.prompt = None;
+            self.history_idx = None;
+        }
+        if let Some(row) = execute {
+            self.history_idx = None;
+            let action = match (self.prompt.take(), matches.get(row)) {
+                // Run the prompt command with the typed argument
+                (Some(idx), _) => {
+                    let command = &self.prompt_commands[idx];
+                    match (command.action)(&self.input) {
+                        Ok(action) => Some((command.name, action)),
+                        Err(err) => {
+                            state.notify(NotificationLevel::Error, format!("{:#}", err));
+                            None
+                        }
+                    }
+                }
+                (None, Some((idx, _indices))) => match self.commands.get(*idx) {
+                    Some(command) => Some((command.name, (command.action)())),
+                    // Prompt for the argument before running the command
+                    None => {
+                        self.prompt = Some(idx - self.commands.len());
+                        self.input.clear();
+                        return false;
+                    }
+                },
+                (None, None) => None,
+            };
+            if let Some((name, action)) = action {
+                self.run(name, action, state, lsp_handles);
+            }
+            state.command_palette_open = false;
+            self.input.clear();
+            self.selection_idx = 0;
+        }
+        false
+    }
+
+    fn run(
+        &self,
+        name: &str,
+        action: Action,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) {
+        state.command_history.push(name);
+        let lsp_handle = match state.buffer_idx {
+            Some(buffer_idx) => {
+                let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+                &mut lsp_handles.get_mut(&buffer.language)
+            }
+            None => &mut None,
+        };
+        perform_action(action, state, lsp_handle);
+        state.update_view = true;
+    }
+
+    /// Edit the query and move the selection, or cycle through the history of a prompt.
+    /// Returns true when the selected command should be run
+    /// and false when the palette should be closed.
+    fn handle_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        num_matches: usize,
+        history: &[String],
+    ) -> Option<bool> {
+        let mut result = None;
+        ui.input(|i| {
+            for event in &i.raw.events {
+                match event {
+                    egui::Event::Text(text) => {
+                        self.input.push_str(text);
+                        self.selection_idx = 0;
+                        self.history_idx = None;
+                    }
+                    egui::Event::Key {
+                        key,
+                        physical_key: _,
+                        pressed: true,
+                        repeat: _,
+                        modifiers,
+                    } => match key {
+                        egui::Key::Backspace => {
+                            self.input.pop();
+                            self.selection_idx = 0;
+                            self.history_idx = None;
+                        }
+                        // Older entries with up and newer ones with down
+                        egui::Key::ArrowUp if self.prompt.is_some() && !history.is_empty() => {
+                            let idx = self
+                                .history_idx
+                                .map(|idx| (idx + 1).min(history.len() - 1))
+                                .unwrap_or(0);
+                            self.history_idx = Some(idx);
+                            self.input = history[idx].clone();
+                        }
+                        egui::Key::ArrowDown if self.prompt.is_some() => {
+                            self.history_idx = match self.history_idx {
+                                Some(0) | None => None,
+                                Some(idx) => Some(idx - 1),
+                            };
+                            self.input = self
+                                .history_idx
+                                .map(|idx| history[idx].clone())
+                                .unwrap_or_default();
+                        }
+                        egui::Key::ArrowDown => {
+                            self.selection_idx = (self.selection_idx + 1) % num_matches.max(1);
+                        }
+                        egui::Key::Tab if !modifiers.shift => {
+                            self.selection_idx = (self.selection_idx + 1) % num_matches.max(1);
+                        }
+                        egui::Key::ArrowUp | egui::Key::Tab => {
+                            self.selection_idx =
+                                (self.selection_idx + num_matches.max(1) - 1) % num_matches.max(1);
+                        }
+                        egui::Key::Enter => {
+                            result = Some(true);
+                        }
+                        egui::Key::Escape => {
+                            result = Some(false);
+                        }
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+        });
+        result
+    }
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -111,14 +111,14 @@ impl CompletionMenu {
                                     state,
                                     lsp_handle,
                                 );
-                                perform_action(
-                                    Action::InsertText(
-                                        completion_item.edit.text.clone(),
-                                        completion_item.edit.range.mark,
-                                    ),
-                                    state,
-                                    lsp_handle,
-                                );
+                                let text = completion_item.edit.text.clone();
+                                let position = completion_item.edit.range.mark;
+                                let action = if completion_item.snippet {
+                                    Action::InsertSnippet(text, position)
+                                } else {
+                                    Action::InsertText(text, position)
+                                };
+                                perform_action(action, state, lsp_handle);
                                 self.active = false;
                             }
                             _ => {}
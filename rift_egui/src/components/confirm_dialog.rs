@@ -0,0 +1,80 @@
+use egui::RichText;
+use rift_core::{
+    actions::{perform_action, Action},
+    dialog::{ConfirmChoice, ConfirmKind},
+    state::EditorState,
+};
+
+/// Asks whether unsaved changes should be saved before closing or restored after a crash
+pub struct ConfirmDialogView {}
+
+impl ConfirmDialogView {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while a confirmation is pending
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) -> bool {
+        let Some(dialog) = &state.confirm_dialog else {
+            return true;
+        };
+        let preferences = &state.preferences;
+        let save_label = match dialog.kind {
+            ConfirmKind::CloseBuffer(_) => "Save (s)",
+            ConfirmKind::Quit => "Save All (s)",
+            ConfirmKind::Recover => "Restore (s)",
+        };
+        let mut choice = None;
+
+        egui::Window::new("confirm_dialog")
+            .movable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .title_bar(false)
+            .frame(egui::Frame {
+                fill: preferences.theme.modal_bg.into(),
+                inner_margin: egui::Margin::same(8.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(&dialog.message)
+                        .size(preferences.ui_font_size as f32)
+                        .color(preferences.theme.modal_text),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button(save_label).clicked() {
+                        choice = Some(ConfirmChoice::Save);
+                    }
+                    if ui.button("Discard (d)").clicked() {
+                        choice = Some(ConfirmChoice::Discard);
+                    }
+                    if ui.button("Cancel (Esc)").clicked() {
+                        choice = Some(ConfirmChoice::Cancel);
+                    }
+                });
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::S) {
+                        choice = Some(ConfirmChoice::Save);
+                    } else if i.key_pressed(egui::Key::D) {
+                        choice = Some(ConfirmChoice::Discard);
+                    } else if i.key_pressed(egui::Key::Escape) || i.key_pressed(egui::Key::C) {
+                        choice = Some(ConfirmChoice::Cancel);
+                    }
+                });
+            });
+
+        if let Some(choice) = choice {
+            perform_action(Action::Confirm(choice), state, &mut None);
+        }
+        false
+    }
+}
+
+impl Default for ConfirmDialogView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
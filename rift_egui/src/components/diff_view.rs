@@ -0,0 +1,227 @@
+use egui::{text::LayoutJob, Color32, FontId, Rect, RichText};
+use rift_core::{
+    actions::{perform_action, Action},
+    diff::{DiffLine, LineChange},
+    preferences::Preferences,
+    state::EditorState,
+};
+
+/// Shows two texts side by side with changed lines and characters highlighted
+pub struct DiffView {
+    /// Hunk last scrolled to
+    scrolled_hunk: Option<usize>,
+}
+
+impl DiffView {
+    pub fn new() -> Self {
+        Self {
+            scrolled_hunk: None,
+        }
+    }
+
+    /// Returns false while a diff is open
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) -> bool {
+        let Some(diff) = &state.diff_view else {
+            self.scrolled_hunk = None;
+            return true;
+        };
+        let preferences = &state.preferences;
+        let font_id = FontId::monospace(preferences.editor_font_size as f32);
+        let mut action = None;
+        let selected_hunk = diff.selected_hunk();
+        let scroll_to_hunk = selected_hunk.is_some() && self.scrolled_hunk != Some(diff.hunk_idx);
+        self.scrolled_hunk = Some(diff.hunk_idx);
+
+        egui::Window::new("diff_view")
+            .movable(false)
+            .resizable(false)
+            .collapsible(false)
+            .title_bar(false)
+            .fixed_rect(ctx.available_rect())
+            .frame(egui::Frame {
+                fill: preferences.theme.editor_bg.into(),
+                inner_margin: egui::Margin::same(8.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                ui.columns(2, |columns| {
+                    for (column, (title, modified)) in [
+                        (&diff.left_title, diff.left_modified),
+                        (&diff.right_title, diff.right_modified),
+                    ]
+                    .into_iter()
+                    .enumerate()
+                    {
+                        let title = if modified {
+                            format!("{} *", title)
+                        } else {
+                            title.to_string()
+                        };
+                        columns[column].label(
+                            RichText::new(title)
+                                .size(preferences.ui_font_size as f32)
+                                .color(preferences.theme.ui_text),
+                        );
+                    }
+                });
+                if diff.is_empty() {
+                    ui.label(
+                        RichText::new("No changes")
+                            .size(preferences.ui_font_size as f32)
+                            .color(preferences.theme.highlight_gray),
+                    );
+                }
+                if diff.left_path.is_some() {
+                    ui.label(
+                        RichText::new(
+                            "[ ] previous/next hunk   > < push hunk right/left   s save   q close",
+                        )
+                        .size(preferences.ui_font_size as f32)
+                        .color(preferences.theme.highlight_gray),
+                    );
+                }
+                ui.separator();
+
+                // A single scroll area keeps both sides in sync
+                let row_height = ui.fonts(|fonts| fonts.row_height(&font_id));
+                let mut scroll_area = egui::ScrollArea::vertical().auto_shrink([false, false]);
+                if let Some(hunk) = selected_hunk.as_ref().filter(|_| scroll_to_hunk) {
+                    let spacing = ui.spacing().item_spacing.y;
+                    scroll_area = scroll_area
+                        .vertical_scroll_offset(hunk.start as f32 * (row_height + spacing));
+                }
+                scroll_area.show_rows(ui, row_height, diff.rows.len(), |ui, row_range| {
+                    let width = ui.available_width();
+                    for (idx, row) in diff.rows[row_range.clone()].iter().enumerate() {
+                        let (rect, _response) = ui.allocate_exact_size(
+                            egui::vec2(width, row_height),
+                            egui::Sense::hover(),
+                        );
+                        if selected_hunk
+                            .as_ref()
+                            .is_some_and(|hunk| hunk.contains(&(row_range.start + idx)))
+                        {
+                            ui.painter().rect_filled(
+                                rect,
+                                0.0,
+                                Color32::from(preferences.theme.selection_bg).gamma_multiply(0.5),
+                            );
+                        }
+                        let half = rect.width() / 2.0;
+                        for (side, line) in [&row.left, &row.right].into_iter().enumerate() {
+                            let side_rect = Rect::from_min_size(
+                                rect.min + egui::vec2(side as f32 * half, 0.0),
+                                egui::vec2(half - 4.0, row_height),
+                            );
+                            Self::paint_line(ui, side_rect, line.as_ref(), &font_id, preferences);
+                        }
+                    }
+                });
+
+                ui.input(|i| {
+                    action = if i.key_pressed(egui::Key::Escape) || i.key_pressed(egui::Key::Q) {
+                        Some(Action::CloseDiff)
+                    } else if i.key_pressed(egui::Key::CloseBracket) {
+                        Some(Action::DiffNextHunk)
+                    } else if i.key_pressed(egui::Key::OpenBracket) {
+                        Some(Action::DiffPrevHunk)
+                    } else if i.key_pressed(egui::Key::Period) && i.modifiers.shift {
+                        Some(Action::DiffPushToRight)
+                    } else if i.key_pressed(egui::Key::Comma) && i.modifiers.shift {
+                        Some(Action::DiffPushToLeft)
+                    } else if i.key_pressed(egui::Key::S) {
+                        Some(Action::DiffSave)
+                    } else {
+                        None
+                    };
+                });
+            });
+
+        if let Some(action) = action {
+            perform_action(action, state, &mut None);
+        }
+        false
+    }
+
+    fn paint_line(
+        ui: &egui::Ui,
+        rect: Rect,
+        line: Option<&DiffLine>,
+        font_id: &FontId,
+        preferences: &Preferences,
+    ) {
+        let painter = ui.painter_at(rect);
+        let Some(line) = line else {
+            // Filler for lines which only exist on the other side
+            painter.rect_filled(
+                rect,
+                0.0,
+                Color32::from(preferences.theme.gutter_bg).gamma_multiply(0.5),
+            );
+            return;
+        };
+
+        let (line_color, change_color): (Color32, Color32) = match line.change {
+            LineChange::Unchanged => (Color32::TRANSPARENT, Color32::TRANSPARENT),
+            LineChange::Removed => (
+                Color32::from(preferences.theme.error).gamma_multiply(0.15),
+                Color32::TRANSPARENT,
+            ),
+            LineChange::Added => (
+                Color32::from(preferences.theme.highlight_green).gamma_multiply(0.15),
+                Color32::TRANSPARENT,
+            ),
+            LineChange::Modified => (
+                Color32::from(preferences.theme.highlight_yellow).gamma_multiply(0.1),
+                Color32::from(preferences.theme.highlight_yellow).gamma_multiply(0.35),
+            ),
+        };
+        painter.rect_filled(rect, 0.0, line_color);
+
+        // Line number
+        let number_width = painter
+            .text(
+                rect.left_top(),
+                egui::Align2::LEFT_TOP,
+                format!("{:>5} ", line.row + 1),
+                font_id.clone(),
+                preferences.theme.gutter_text.into(),
+            )
+            .width();
+
+        // Text with the changed characters highlighted
+        let mut job = LayoutJob::default();
+        for (idx, c) in line.text.chars().enumerate() {
+            let changed = line
+                .changed_ranges
+                .iter()
+                .any(|(start, end)| (*start..*end).contains(&idx));
+            job.append(
+                &c.to_string(),
+                0.0,
+                egui::TextFormat {
+                    font_id: font_id.clone(),
+                    color: preferences.theme.highlight_none.into(),
+                    background: if changed {
+                        change_color
+                    } else {
+                        Color32::TRANSPARENT
+                    },
+                    ..Default::default()
+                },
+            );
+        }
+        let galley = ui.fonts(|fonts| fonts.layout_job(job));
+        painter.galley(
+            rect.left_top() + egui::vec2(number_width, 0.0),
+            galley,
+            preferences.theme.highlight_none.into(),
+        );
+    }
+}
+
+impl Default for DiffView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
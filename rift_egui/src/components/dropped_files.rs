@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use egui::RichText;
+use rift_core::{
+    buffer::instance::Language, lsp::client::LSPClientHandle, notifications::NotificationLevel,
+    state::EditorState,
+};
+
+use crate::command_dispatcher::open_file;
+
+/// Opens files dropped onto the window and asks whether dropped
+/// folders should become the workspace folder
+pub struct DroppedFiles {
+    pub pending_folders: Vec<String>,
+}
+
+impl DroppedFiles {
+    pub fn new() -> Self {
+        Self {
+            pending_folders: vec![],
+        }
+    }
+
+    /// Returns false while the workspace prompt is open
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+        for file in dropped_files {
+            let Some(path) = file.path else {
+                continue;
+            };
+            let path = match std::path::absolute(&path) {
+                Ok(path) => path.to_string_lossy().to_string(),
+                Err(err) => {
+                    state.notify(
+                        NotificationLevel::Error,
+                        format!("Failed to resolve dropped path: {}", err),
+                    );
+                    continue;
+                }
+            };
+            if std::path::Path::new(&path).is_dir() {
+                self.pending_folders.push(path);
+            } else {
+                open_file(&path, state, lsp_handles);
+            }
+        }
+
+        // Highlight the window while files are hovered over it
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("dropped_files"),
+            ));
+            let rect = ctx.screen_rect();
+            painter.rect_filled(
+                rect,
+                0.0,
+                egui::Color32::from(state.preferences.theme.selection_bg).gamma_multiply(0.3),
+            );
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop to open",
+                egui::FontId::proportional(state.preferences.ui_font_size as f32 * 2.0),
+                state.preferences.theme.ui_text.into(),
+            );
+        }
+
+        if let Some(folder) = self.pending_folders.first().cloned() {
+            egui::Window::new("dropped_folder")
+                .movable(false)
+                .order(egui::Order::Foreground)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .resizable(false)
+                .collapsible(false)
+                .title_bar(false)
+                .frame(egui::Frame {
+                    fill: state.preferences.theme.modal_bg.into(),
+                    inner_margin: egui::Margin::same(8.0),
+                    ..Default::default()
+                })
+                .show(ctx, |ui| {
+                    ui.label(
+                        RichText::new("Open folder as workspace?")
+                            .color(state.preferences.theme.modal_active),
+                    );
+                    ui.label(RichText::new(&folder).color(state.preferences.theme.modal_text));
+                    ui.horizontal(|ui| {
+                        if ui.button("Open (Enter)").clicked()
+                            || ui.input(|i| i.key_pressed(egui::Key::Enter))
+                        {
+                            state.open_workspace(folder.clone());
+                            state.file_tree_open = true;
+                            self.pending_folders.clear();
+                        } else if ui.button("Skip (Esc)").clicked()
+                            || ui.input(|i| i.key_pressed(egui::Key::Escape))
+                        {
+                            self.pending_folders.remove(0);
+                        }
+                    });
+                });
+            return false;
+        }
+        true
+    }
+}
+
+impl Default for DroppedFiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
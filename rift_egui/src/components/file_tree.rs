@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use egui::{RichText, Sense};
+use rift_core::{
+    actions::{perform_action, Action},
+    buffer::instance::Language,
+    dialog::InputDialog,
+    io::file_tree::FileTreeRow,
+    lsp::client::LSPClientHandle,
+    state::EditorState,
+};
+
+use crate::command_dispatcher::open_file;
+
+/// Workspace explorer drawn on the left of the editor
+pub struct FileTreePanel {}
+
+impl FileTreePanel {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while the tree has the keyboard focus
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        if !state.file_tree_open {
+            return true;
+        }
+
+        state.file_tree.set_root(state.workspace_folder.clone());
+        let rows = state.file_tree.rows();
+        let focused = state.file_tree_focused;
+        let mut selected = None;
+        let mut action = None;
+        let mut open = None;
+
+        egui::SidePanel::left("file_tree")
+            .resizable(true)
+            .default_width(200.0)
+            .frame(egui::Frame {
+                fill: state.preferences.theme.gutter_bg.into(),
+                inner_margin: egui::Margin::same(state.preferences.gutter_padding),
+                stroke: egui::Stroke::new(1.0, state.preferences.theme.ui_border),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(
+                            std::path::Path::new(&state.workspace_folder)
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or(state.workspace_folder.clone()),
+                        )
+                        .strong(),
+                    );
+                    if ui.small_button("+").on_hover_text("New File").clicked() {
+                        state.input_dialog = Some(InputDialog::new_file(&state.workspace_folder));
+                    }
+                    if ui.small_button("⟳").on_hover_text("Refresh").clicked() {
+                        state.file_tree.refresh();
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for (idx, row) in rows.iter().enumerate() {
+                            let mut text =
+                                RichText::new(format!("{} {}", icon(row), row.entry.name));
+                            if focused && idx == state.file_tree.idx {
+                                text = text.background_color(state.preferences.theme.selection_bg);
+                            }
+                            let response = ui
+                                .horizontal(|ui| {
+                                    ui.add_space(row.depth as f32 * 12.0);
+                                    ui.add(
+                                        egui::Label::new(text)
+                                            .selectable(false)
+                                            .sense(Sense::click()),
+                                    )
+                                })
+                                .inner;
+                            if focused && idx == state.file_tree.idx {
+                                response.scroll_to_me(None);
+                            }
+
+                            if response.clicked() {
+                                selected = Some(idx);
+                                if row.entry.is_dir {
+                                    action = Some(Action::FileTreeOpen);
+                                }
+                            }
+                            if response.double_clicked() && !row.entry.is_dir {
+                                open = Some(row.entry.path.clone());
+                            }
+                            response.context_menu(|ui| {
+                                if row.entry.is_dir {
+                                    if ui.button("New File").clicked() {
+                                        selected = Some(idx);
+                                        action = Some(Action::FileTreeNewFile);
+                                        ui.close_menu();
+                                    }
+                                    if ui.button("New Folder").clicked() {
+                                        selected = Some(idx);
+                                        action = Some(Action::FileTreeNewFolder);
+                                        ui.close_menu();
+                                    }
+                                }
+                                if ui.button("Rename").clicked() {
+                                    selected = Some(idx);
+                                    action = Some(Action::FileTreeRename);
+                                    ui.close_menu();
+                                }
+                                if ui.button("Delete").clicked() {
+                                    selected = Some(idx);
+                                    action = Some(Action::FileTreeDelete);
+                                    ui.close_menu();
+                                }
+                            });
+                        }
+                    });
+            });
+
+        if focused && state.input_dialog.is_none() {
+            ctx.input(|i| {
+                if i.key_pressed(egui::Key::Escape) {
+                    action = Some(Action::FocusFileTree);
+                } else if i.key_pressed(egui::Key::ArrowDown) || i.key_pressed(egui::Key::J) {
+                    action = Some(Action::FileTreeNext);
+                } else if i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::K) {
+                    action = Some(Action::FileTreePrevious);
+                } else if i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::H) {
+                    action = Some(Action::FileTreeCollapse);
+                } else if i.key_pressed(egui::Key::Enter)
+                    || i.key_pressed(egui::Key::ArrowRight)
+                    || i.key_pressed(egui::Key::L)
+                {
+                    match rows.get(state.file_tree.idx) {
+                        Some(row) if !row.entry.is_dir => open = Some(row.entry.path.clone()),
+                        _ => action = Some(Action::FileTreeOpen),
+                    }
+                } else if i.key_pressed(egui::Key::A) {
+                    action = Some(if i.modifiers.shift {
+                        Action::FileTreeNewFolder
+                    } else {
+                        Action::FileTreeNewFile
+                    });
+                } else if i.key_pressed(egui::Key::R) {
+                    action = Some(Action::FileTreeRename);
+                } else if i.key_pressed(egui::Key::D) {
+                    action = Some(Action::FileTreeDelete);
+                }
+            });
+        }
+
+        if let Some(idx) = selected {
+            state.file_tree.idx = idx;
+        }
+        if let Some(action) = action {
+            perform_action(action, state, &mut None);
+        }
+        if let Some(path) = open {
+            state.file_tree_focused = false;
+            open_file(&path, state, lsp_handles);
+        }
+
+        !state.file_tree_focused
+    }
+}
+
+impl Default for FileTreePanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Icon shown before the name of an entry
+fn icon(row: &FileTreeRow) -> &'static str {
+    if row.entry.is_dir {
+        return if row.expanded { "📂" } else { "📁" };
+    }
+    match row.entry.extension.as_str() {
+        "rs" => "🦀",
+        "py" => "🐍",
+        "md" => "📝",
+        "toml" | "json" | "yaml" | "yml" => "⚙",
+        "png" | "jpg" | "jpeg" | "gif" | "svg" => "🖼",
+        _ => "📄",
+    }
+}
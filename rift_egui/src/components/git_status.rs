@@ -0,0 +1,117 @@
+use egui::RichText;
+use rift_core::{
+    actions::{perform_action, Action},
+    buffer::instance::Language,
+    lsp::client::LSPClientHandle,
+    state::EditorState,
+};
+use std::collections::HashMap;
+
+/// Staged, unstaged and untracked files of the repository
+pub struct GitStatusView {}
+
+impl GitStatusView {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while the status is open
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        let Some(status) = &state.git_status else {
+            return true;
+        };
+        let preferences = &state.preferences;
+        let mut action = None;
+        let mut clicked = None;
+
+        egui::Window::new("git_status")
+            .movable(false)
+            .order(egui::Order::Tooltip)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .collapsible(false)
+            .title_bar(false)
+            .auto_sized()
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new("Git Status")
+                        .size(preferences.ui_font_size as f32)
+                        .color(preferences.theme.modal_active),
+                );
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(ctx.screen_rect().height() * 0.6)
+                    .show(ui, |ui| {
+                        if status.entries.is_empty() {
+                            ui.label(
+                                RichText::new("Nothing to commit, working tree clean")
+                                    .size(preferences.ui_font_size as f32),
+                            );
+                        }
+                        for idx in 0..status.entries.len() {
+                            let mut text = RichText::new(status.label(idx))
+                                .size(preferences.ui_font_size as f32)
+                                .family(egui::FontFamily::Monospace);
+                            if idx == status.idx {
+                                text = text.background_color(preferences.theme.selection_bg);
+                            }
+                            if ui
+                                .add(egui::Label::new(text).sense(egui::Sense::click()))
+                                .clicked()
+                            {
+                                clicked = Some(idx);
+                            }
+                        }
+                    });
+                ui.separator();
+                ui.label(
+                    RichText::new("s: stage/unstage  c: commit  enter: open")
+                        .size(preferences.ui_font_size as f32 * 0.8),
+                );
+
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Escape) {
+                        action = Some(Action::CloseGitStatus);
+                    } else if i.key_pressed(egui::Key::Enter) {
+                        action = Some(Action::GitStatusOpen);
+                    } else if i.key_pressed(egui::Key::S) || i.key_pressed(egui::Key::Space) {
+                        action = Some(Action::GitStatusToggleStage);
+                    } else if i.key_pressed(egui::Key::C) {
+                        action = Some(Action::GitCommit);
+                    } else if i.key_pressed(egui::Key::ArrowDown) || i.key_pressed(egui::Key::J) {
+                        action = Some(Action::GitStatusNext);
+                    } else if i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::K) {
+                        action = Some(Action::GitStatusPrevious);
+                    }
+                });
+            });
+
+        if let Some(idx) = clicked {
+            if let Some(status) = &mut state.git_status {
+                status.idx = idx;
+            }
+            action = Some(Action::GitStatusToggleStage);
+        }
+        if let Some(action) = action {
+            match state.buffer_idx {
+                Some(buffer_idx) => {
+                    let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+                    let lsp_handle = &mut lsp_handles.get_mut(&buffer.language);
+                    perform_action(action, state, lsp_handle);
+                }
+                None => perform_action(action, state, &mut None),
+            }
+        }
+        false
+    }
+}
+
+impl Default for GitStatusView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,164 @@
+use egui::{Rect, RichText, Sense, TextureHandle, TextureOptions};
+use rift_core::state::EditorState;
+
+/// Decoded image or the reason it could not be decoded
+enum LoadedImage {
+    Texture(TextureHandle),
+    Error(String),
+}
+
+/// Displays image files over the editor with zoom and pan
+pub struct ImageViewer {
+    path: Option<String>,
+    image: Option<LoadedImage>,
+    zoom: f32,
+    offset: egui::Vec2,
+}
+
+impl ImageViewer {
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            image: None,
+            zoom: 1.0,
+            offset: egui::Vec2::ZERO,
+        }
+    }
+
+    /// Returns false while an image is open
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) -> bool {
+        let Some(path) = state.image_path.clone() else {
+            self.path = None;
+            self.image = None;
+            return true;
+        };
+
+        if self.path.as_ref() != Some(&path) {
+            self.image = Some(match Self::load(ctx, &path) {
+                Ok(texture) => LoadedImage::Texture(texture),
+                Err(err) => {
+                    tracing::error!("Failed to load image {}: {}", path, err);
+                    LoadedImage::Error(err.to_string())
+                }
+            });
+            self.path = Some(path.clone());
+            self.zoom = 1.0;
+            self.offset = egui::Vec2::ZERO;
+        }
+
+        egui::Window::new("image_viewer")
+            .movable(false)
+            .resizable(false)
+            .collapsible(false)
+            .title_bar(false)
+            .fixed_rect(ctx.available_rect())
+            .frame(egui::Frame {
+                fill: state.preferences.theme.editor_bg.into(),
+                inner_margin: egui::Margin::same(8.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                let font_size = state.preferences.ui_font_size as f32;
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(&path)
+                            .size(font_size)
+                            .color(state.preferences.theme.ui_text),
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.label(
+                            RichText::new(format!(
+                                "{:.0}%  (+/- zoom, 0 reset, q close)",
+                                self.zoom * 100.0
+                            ))
+                            .size(font_size)
+                            .color(state.preferences.theme.highlight_gray),
+                        );
+                    });
+                });
+                ui.separator();
+
+                let rect = ui.available_rect_before_wrap();
+                let response = ui.allocate_rect(rect, Sense::drag());
+                match &self.image {
+                    Some(LoadedImage::Texture(texture)) => {
+                        // Pan by dragging and zoom around the pointer with the scroll wheel
+                        self.offset += response.drag_delta();
+                        if response.hovered() {
+                            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+                            if scroll != 0.0 {
+                                let zoom = (self.zoom * (1.0 + scroll * 0.002)).clamp(0.05, 20.0);
+                                if let Some(pointer) = ui.input(|i| i.pointer.hover_pos()) {
+                                    let anchor = pointer - rect.center() - self.offset;
+                                    self.offset -= anchor * (zoom / self.zoom - 1.0);
+                                }
+                                self.zoom = zoom;
+                            }
+                        }
+
+                        let size = texture.size_vec2() * self.zoom;
+                        let image_rect = Rect::from_center_size(rect.center() + self.offset, size);
+                        ui.painter_at(rect).image(
+                            texture.id(),
+                            image_rect,
+                            Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                            egui::Color32::WHITE,
+                        );
+                    }
+                    Some(LoadedImage::Error(err)) => {
+                        ui.painter_at(rect).text(
+                            rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            format!("Unable to display image: {}", err),
+                            egui::FontId::proportional(font_size),
+                            state.preferences.theme.error.into(),
+                        );
+                    }
+                    None => {}
+                }
+                self.handle_input(ui, state);
+            });
+        false
+    }
+
+    fn handle_input(&mut self, ui: &mut egui::Ui, state: &mut EditorState) {
+        ui.input(|i| {
+            for event in &i.raw.events {
+                if let egui::Event::Key {
+                    key, pressed: true, ..
+                } = event
+                {
+                    match key {
+                        egui::Key::Plus | egui::Key::Equals => {
+                            self.zoom = (self.zoom * 1.25).min(20.0);
+                        }
+                        egui::Key::Minus => {
+                            self.zoom = (self.zoom / 1.25).max(0.05);
+                        }
+                        egui::Key::Num0 => {
+                            self.zoom = 1.0;
+                            self.offset = egui::Vec2::ZERO;
+                        }
+                        egui::Key::Q | egui::Key::Escape => {
+                            state.image_path = None;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        });
+    }
+
+    fn load(ctx: &egui::Context, path: &str) -> anyhow::Result<TextureHandle> {
+        let image = image::open(path)?.to_rgba8();
+        let size = [image.width() as usize, image.height() as usize];
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+        Ok(ctx.load_texture(path, color_image, TextureOptions::LINEAR))
+    }
+}
+
+impl Default for ImageViewer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
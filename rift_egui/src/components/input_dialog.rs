@@ -0,0 +1,69 @@
+use egui::RichText;
+use rift_core::{
+    actions::{perform_action, Action},
+    state::EditorState,
+};
+
+/// Asks for a line of text such as the passphrase of an encrypted file
+pub struct InputDialogView {}
+
+impl InputDialogView {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while the dialog is open
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) -> bool {
+        let Some(dialog) = &mut state.input_dialog else {
+            return true;
+        };
+        let mut action = None;
+
+        egui::Window::new("input_dialog")
+            .movable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 48.0))
+            .resizable(false)
+            .collapsible(false)
+            .title_bar(false)
+            .frame(egui::Frame {
+                fill: state.preferences.theme.modal_bg.into(),
+                inner_margin: egui::Margin::same(8.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(&dialog.title)
+                        .size(state.preferences.ui_font_size as f32)
+                        .color(state.preferences.theme.modal_active),
+                );
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut dialog.input)
+                        .password(dialog.secret)
+                        .desired_width(480.0)
+                        .font(egui::FontId::monospace(
+                            state.preferences.ui_font_size as f32,
+                        )),
+                );
+                response.request_focus();
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Enter) {
+                        action = Some(Action::SubmitInputDialog);
+                    } else if i.key_pressed(egui::Key::Escape) {
+                        action = Some(Action::CloseInputDialog);
+                    }
+                });
+            });
+
+        if let Some(action) = action {
+            perform_action(action, state, &mut None);
+        }
+        false
+    }
+}
+
+impl Default for InputDialogView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,246 @@
+use egui::{FontId, RichText};
+use rift_core::{
+    buffer::{instance::Language, line_buffer::HighlightedText},
+    markdown::{highlight_code, parse_markdown, Inline, MarkdownBlock},
+    preferences::Preferences,
+    state::EditorState,
+};
+
+use crate::app::highlighted_line_job;
+
+/// Parsed markdown of a buffer at a particular version
+struct RenderedMarkdown {
+    buffer_id: u32,
+    version: usize,
+    blocks: Vec<MarkdownBlock>,
+    /// Highlighted lines of each code block
+    highlighted_code: Vec<Option<HighlightedText>>,
+}
+
+/// Side panel rendering a preview of the active markdown buffer
+pub struct MarkdownPreview {
+    rendered: Option<RenderedMarkdown>,
+    /// Top row of the editor when the preview was last scrolled
+    synced_row: Option<usize>,
+}
+
+impl MarkdownPreview {
+    pub fn new() -> Self {
+        Self {
+            rendered: None,
+            synced_row: None,
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) {
+        if !state.markdown_preview_open {
+            return;
+        }
+        let Some(buffer_id) = state.buffer_idx else {
+            return;
+        };
+        let (buffer, _instance) = state.get_buffer_by_id(buffer_id);
+        if buffer.language != Language::Markdown {
+            return;
+        }
+
+        // Parse the buffer again only after it is edited
+        if !self.rendered.as_ref().is_some_and(|rendered| {
+            rendered.buffer_id == buffer_id && rendered.version == buffer.version
+        }) {
+            let blocks = parse_markdown(&buffer.lines);
+            let highlighted_code = blocks
+                .iter()
+                .map(|block| match block {
+                    MarkdownBlock::CodeBlock {
+                        language, lines, ..
+                    } => highlight_code(language, lines),
+                    _ => None,
+                })
+                .collect();
+            self.rendered = Some(RenderedMarkdown {
+                buffer_id,
+                version: buffer.version,
+                blocks,
+                highlighted_code,
+            });
+        }
+        let rendered = self.rendered.as_ref().unwrap();
+
+        // Keep the preview in sync with the top of the editor
+        let top_row = state
+            .gutter_info
+            .first()
+            .map(|info| info.start.row)
+            .unwrap_or(0);
+        let scroll_target = if self.synced_row != Some(top_row) {
+            self.synced_row = Some(top_row);
+            rendered
+                .blocks
+                .iter()
+                .rposition(|block| block.row() <= top_row)
+        } else {
+            None
+        };
+
+        let preferences = &state.preferences;
+        egui::SidePanel::right("markdown_preview")
+            .resizable(true)
+            .default_width(400.0)
+            .frame(egui::Frame {
+                fill: preferences.theme.editor_bg.into(),
+                inner_margin: egui::Margin::same(12.0),
+                stroke: egui::Stroke::new(1.0, preferences.theme.ui_border),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for (idx, block) in rendered.blocks.iter().enumerate() {
+                            let response = ui
+                                .scope(|ui| {
+                                    Self::show_block(
+                                        ui,
+                                        block,
+                                        rendered.highlighted_code[idx].as_ref(),
+                                        preferences,
+                                    )
+                                })
+                                .response;
+                            if scroll_target == Some(idx) {
+                                response.scroll_to_me(Some(egui::Align::TOP));
+                            }
+                        }
+                    });
+            });
+    }
+
+    fn show_block(
+        ui: &mut egui::Ui,
+        block: &MarkdownBlock,
+        highlighted_code: Option<&HighlightedText>,
+        preferences: &Preferences,
+    ) {
+        let font_size = preferences.ui_font_size as f32;
+        match block {
+            MarkdownBlock::Heading { level, text, .. } => {
+                ui.add_space(font_size * 0.5);
+                let size = font_size * (1.8 - *level as f32 * 0.15).max(1.0);
+                Self::show_inline(ui, text, size, true, preferences);
+            }
+            MarkdownBlock::Paragraph { text, .. } => {
+                Self::show_inline(ui, text, font_size, false, preferences);
+                ui.add_space(font_size * 0.5);
+            }
+            MarkdownBlock::ListItem {
+                indent,
+                marker,
+                text,
+                ..
+            } => {
+                ui.horizontal_top(|ui| {
+                    ui.add_space(font_size * (*indent as f32 + 1.0));
+                    ui.label(
+                        RichText::new(format!("{} ", marker))
+                            .size(font_size)
+                            .color(preferences.theme.highlight_gray),
+                    );
+                    Self::show_inline(ui, text, font_size, false, preferences);
+                });
+            }
+            MarkdownBlock::Quote { text, .. } => {
+                egui::Frame::none()
+                    .inner_margin(egui::Margin {
+                        left: font_size,
+                        ..Default::default()
+                    })
+                    .stroke(egui::Stroke::new(1.0, preferences.theme.highlight_gray))
+                    .show(ui, |ui| {
+                        Self::show_inline(ui, text, font_size, false, preferences);
+                    });
+            }
+            MarkdownBlock::CodeBlock { lines, .. } => {
+                egui::Frame::none()
+                    .fill(preferences.theme.modal_bg.into())
+                    .inner_margin(egui::Margin::same(8.0))
+                    .show(ui, |ui| {
+                        ui.set_min_width(ui.available_width());
+                        ui.spacing_mut().item_spacing = egui::vec2(0.0, 0.0);
+                        match highlighted_code {
+                            Some(highlighted_lines) => {
+                                for line in highlighted_lines {
+                                    ui.label(highlighted_line_job(line, preferences));
+                                }
+                            }
+                            None => {
+                                for line in lines {
+                                    ui.label(
+                                        RichText::new(line)
+                                            .font(FontId::monospace(
+                                                preferences.editor_font_size as f32,
+                                            ))
+                                            .color(preferences.theme.highlight_none),
+                                    );
+                                }
+                            }
+                        }
+                    });
+                ui.add_space(font_size * 0.5);
+            }
+            MarkdownBlock::Rule { .. } => {
+                ui.separator();
+            }
+        }
+    }
+
+    fn show_inline(
+        ui: &mut egui::Ui,
+        text: &[Inline],
+        size: f32,
+        strong: bool,
+        preferences: &Preferences,
+    ) {
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            for span in text {
+                let rich_text = |text: &str| {
+                    let rich_text = RichText::new(text)
+                        .size(size)
+                        .color(preferences.theme.highlight_none);
+                    if strong {
+                        rich_text.strong()
+                    } else {
+                        rich_text
+                    }
+                };
+                match span {
+                    Inline::Text(text) => {
+                        ui.label(rich_text(text));
+                    }
+                    Inline::Strong(text) => {
+                        ui.label(rich_text(text).strong());
+                    }
+                    Inline::Emphasis(text) => {
+                        ui.label(rich_text(text).italics());
+                    }
+                    Inline::Code(text) => {
+                        ui.label(rich_text(text).code());
+                    }
+                    Inline::Link { text, url } => {
+                        ui.hyperlink_to(
+                            rich_text(text).color(preferences.theme.highlight_blue),
+                            url,
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl Default for MarkdownPreview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
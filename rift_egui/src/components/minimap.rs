@@ -0,0 +1,173 @@
+use egui::{Color32, Rect, Sense};
+use rift_core::{
+    actions::{perform_action, Action},
+    state::EditorState,
+};
+
+/// Condensed overview of the active buffer drawn on the right of the editor
+pub struct Minimap {
+    pub width: f32,
+    pub line_height: f32,
+    pub char_width: f32,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Self {
+            width: 100.0,
+            line_height: 2.0,
+            char_width: 1.0,
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) {
+        if state.buffer_idx.is_none() {
+            return;
+        }
+
+        egui::SidePanel::right("minimap")
+            .resizable(false)
+            .show_separator_line(false)
+            .exact_width(self.width)
+            .frame(egui::Frame {
+                fill: state.preferences.theme.editor_bg.into(),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                let rect = ui.max_rect();
+                let response = ui.allocate_rect(rect, Sense::click_and_drag());
+                let painter = ui.painter_at(rect);
+
+                let (buffer, _instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+                let num_lines = buffer.get_num_lines();
+                let capacity = ((rect.height() / self.line_height).floor() as usize).max(1);
+
+                let view_start = state
+                    .gutter_info
+                    .first()
+                    .map(|info| info.start.row)
+                    .unwrap_or(0);
+                let view_end = state
+                    .gutter_info
+                    .last()
+                    .map(|info| info.start.row)
+                    .unwrap_or(0);
+
+                // Lines shown in the minimap, scrolled proportionally
+                // with the viewport when the buffer does not fit
+                let start = if num_lines <= capacity {
+                    0
+                } else {
+                    let scrollable = num_lines.saturating_sub(view_end - view_start + 1).max(1);
+                    let fraction = (view_start as f32 / scrollable as f32).min(1.0);
+                    (fraction * (num_lines - capacity) as f32) as usize
+                };
+                let end = (start + capacity).min(num_lines);
+
+                let row_top = |row: usize| -> f32 {
+                    rect.top() + (row.saturating_sub(start)) as f32 * self.line_height
+                };
+
+                // Viewport
+                painter.rect_filled(
+                    Rect::from_x_y_ranges(
+                        rect.x_range(),
+                        row_top(view_start.max(start))..=row_top(view_end.clamp(start, end) + 1),
+                    ),
+                    0.0,
+                    Color32::from(state.preferences.theme.selection_bg).gamma_multiply(0.6),
+                );
+
+                // Condensed text
+                let text_color =
+                    Color32::from(state.preferences.theme.highlight_gray).gamma_multiply(0.8);
+                for row in start..end {
+                    let line = &buffer.lines[row];
+                    let top = row_top(row);
+                    let mut run_start: Option<usize> = None;
+                    for (column, c) in line.chars().chain(std::iter::once(' ')).enumerate() {
+                        if c.is_whitespace() {
+                            if let Some(run) = run_start.take() {
+                                let left = rect.left() + run as f32 * self.char_width;
+                                if left > rect.right() {
+                                    break;
+                                }
+                                painter.rect_filled(
+                                    Rect::from_min_max(
+                                        egui::pos2(left, top),
+                                        egui::pos2(
+                                            rect.left() + column as f32 * self.char_width,
+                                            top + self.line_height * 0.8,
+                                        ),
+                                    ),
+                                    0.0,
+                                    text_color,
+                                );
+                            }
+                        } else if run_start.is_none() {
+                            run_start = Some(column);
+                        }
+                    }
+                }
+
+                // Diagnostics
+                let path = buffer.file_path.clone().unwrap_or_default();
+                #[cfg(target_os = "windows")]
+                let path = path.to_lowercase();
+                if let Some(diagnostics) = state.diagnostics.get(&path) {
+                    for diagnostic in &diagnostics.diagnostics {
+                        let row = diagnostic.range.mark.row;
+                        if row < start || row >= end {
+                            continue;
+                        }
+                        let color = state
+                            .preferences
+                            .theme
+                            .diagnostic_color(&diagnostic.severity);
+                        let top = row_top(row);
+                        painter.rect_filled(
+                            Rect::from_min_max(
+                                egui::pos2(rect.right() - 4.0, top),
+                                egui::pos2(rect.right(), top + self.line_height.max(2.0)),
+                            ),
+                            0.0,
+                            color,
+                        );
+                    }
+                }
+
+                // Search matches
+                let match_color = Color32::from(state.preferences.theme.highlight_yellow);
+                for row in state.search_match_rows() {
+                    if row < start || row >= end {
+                        continue;
+                    }
+                    let top = row_top(row);
+                    painter.rect_filled(
+                        Rect::from_min_max(
+                            egui::pos2(rect.left(), top),
+                            egui::pos2(rect.left() + 4.0, top + self.line_height.max(2.0)),
+                        ),
+                        0.0,
+                        match_color,
+                    );
+                }
+
+                // Click or drag to scroll
+                if response.clicked() || response.dragged() {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        let row =
+                            start + ((pos.y - rect.top()) / self.line_height).max(0.0) as usize;
+                        perform_action(Action::GoToLine(row), state, &mut None);
+                        state.update_view = true;
+                    }
+                }
+            });
+    }
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
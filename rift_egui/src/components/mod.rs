@@ -1,3 +1,25 @@
+pub mod call_hierarchy;
+pub mod code_action_menu;
+pub mod color_swatches;
+pub mod command_palette;
 pub mod completion_menu;
+pub mod confirm_dialog;
 pub mod diagnostics_overlay;
+pub mod diff_view;
+pub mod dropped_files;
+pub mod file_tree;
+pub mod git_status;
+pub mod image_viewer;
 pub mod info_modal;
+pub mod input_dialog;
+pub mod markdown_preview;
+pub mod minimap;
+pub mod notifications;
+pub mod replace_prompt;
+pub mod save_as_prompt;
+pub mod scrollbar;
+pub mod signature_help;
+pub mod spell_menu;
+pub mod split_view;
+pub mod tab_bar;
+pub mod terminal_pane;
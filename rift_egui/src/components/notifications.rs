@@ -0,0 +1,94 @@
+use egui::{Color32, RichText};
+use rift_core::{
+    actions::{perform_action, Action},
+    notifications::NotificationLevel,
+    state::EditorState,
+};
+
+/// Toasts showing the active notifications in the bottom right corner
+pub struct NotificationToasts {}
+
+impl NotificationToasts {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) {
+        state.notifications.expire();
+        if state.notifications.active.is_empty() {
+            return;
+        }
+        // Repaint to remove notifications once they time out
+        ctx.request_repaint_after(std::time::Duration::from_millis(500));
+
+        let preferences = &state.preferences;
+        let mut dismissed = vec![];
+        let mut clicked: Option<Action> = None;
+
+        egui::Area::new(egui::Id::new("notification_toasts"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-16.0, -32.0))
+            .show(ctx, |ui| {
+                ui.set_max_width(360.0);
+                for notification in &state.notifications.active {
+                    let color: Color32 = match notification.level {
+                        NotificationLevel::Info => preferences.theme.information.into(),
+                        NotificationLevel::Warning => preferences.theme.warning.into(),
+                        NotificationLevel::Error => preferences.theme.error.into(),
+                    };
+                    egui::Frame {
+                        fill: preferences.theme.modal_bg.into(),
+                        stroke: egui::Stroke::new(1.0, color),
+                        rounding: egui::Rounding::same(4.0),
+                        inner_margin: egui::Margin::same(8.0),
+                        outer_margin: egui::Margin::symmetric(0.0, 4.0),
+                        ..Default::default()
+                    }
+                    .show(ui, |ui| {
+                        ui.horizontal_top(|ui| {
+                            ui.label(
+                                RichText::new(notification.level.to_string())
+                                    .size(preferences.ui_font_size as f32)
+                                    .color(color)
+                                    .strong(),
+                            );
+                            ui.add(
+                                egui::Label::new(
+                                    RichText::new(&notification.message)
+                                        .size(preferences.ui_font_size as f32)
+                                        .color(preferences.theme.ui_text),
+                                )
+                                .wrap(),
+                            );
+                            if ui.small_button("x").clicked() {
+                                dismissed.push(notification.id);
+                            }
+                        });
+                        if !notification.actions.is_empty() {
+                            ui.horizontal(|ui| {
+                                for action in &notification.actions {
+                                    if ui.button(&action.label).clicked() {
+                                        dismissed.push(notification.id);
+                                        clicked = Some(action.action.clone());
+                                    }
+                                }
+                            });
+                        }
+                    });
+                }
+            });
+
+        for id in dismissed {
+            state.notifications.dismiss(id);
+        }
+        if let Some(action) = clicked {
+            perform_action(action, state, &mut None);
+        }
+    }
+}
+
+impl Default for NotificationToasts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
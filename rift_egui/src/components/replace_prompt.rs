@@ -0,0 +1,101 @@
+use egui::RichText;
+use rift_core::{
+    actions::{perform_action, Action},
+    buffer::instance::Language,
+    lsp::client::LSPClientHandle,
+    replace::ReplaceChoice,
+    state::EditorState,
+};
+use std::collections::HashMap;
+
+/// Asks whether the selected match of a replacement should be replaced
+pub struct ReplacePrompt {}
+
+impl ReplacePrompt {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while a replacement is waiting for confirmation
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        let Some(session) = &state.replace_session else {
+            return true;
+        };
+        let replacement = session
+            .current
+            .as_ref()
+            .map(|found| found.replacement.clone())
+            .unwrap_or_default();
+        let preferences = &state.preferences;
+        let mut choice = None;
+
+        egui::Window::new("replace_prompt")
+            .movable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 48.0))
+            .resizable(false)
+            .collapsible(false)
+            .title_bar(false)
+            .frame(egui::Frame {
+                fill: preferences.theme.modal_bg.into(),
+                inner_margin: egui::Margin::same(8.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(format!("Replace with \"{}\"?", replacement))
+                        .size(preferences.ui_font_size as f32)
+                        .color(preferences.theme.modal_text),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Yes (y)").clicked() {
+                        choice = Some(ReplaceChoice::Replace);
+                    }
+                    if ui.button("No (n)").clicked() {
+                        choice = Some(ReplaceChoice::Skip);
+                    }
+                    if ui.button("All (a)").clicked() {
+                        choice = Some(ReplaceChoice::All);
+                    }
+                    if ui.button("Quit (Esc)").clicked() {
+                        choice = Some(ReplaceChoice::Quit);
+                    }
+                });
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Y) {
+                        choice = Some(ReplaceChoice::Replace);
+                    } else if i.key_pressed(egui::Key::N) {
+                        choice = Some(ReplaceChoice::Skip);
+                    } else if i.key_pressed(egui::Key::A) {
+                        choice = Some(ReplaceChoice::All);
+                    } else if i.key_pressed(egui::Key::Escape) || i.key_pressed(egui::Key::Q) {
+                        choice = Some(ReplaceChoice::Quit);
+                    }
+                });
+            });
+
+        if let Some(choice) = choice {
+            let action = Action::ReplaceStep(choice);
+            match state.buffer_idx {
+                Some(buffer_idx) => {
+                    let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+                    let lsp_handle = &mut lsp_handles.get_mut(&buffer.language);
+                    perform_action(action, state, lsp_handle);
+                }
+                None => perform_action(action, state, &mut None),
+            }
+        }
+        false
+    }
+}
+
+impl Default for ReplacePrompt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
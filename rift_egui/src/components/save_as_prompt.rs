@@ -0,0 +1,64 @@
+use egui::RichText;
+use rift_core::{
+    actions::{perform_action, Action},
+    state::EditorState,
+};
+
+/// Prompt for the path the current buffer is saved to
+pub struct SaveAsPrompt {}
+
+impl SaveAsPrompt {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while the prompt is open
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) -> bool {
+        let Some(input) = &mut state.save_as_input else {
+            return true;
+        };
+        let mut action = None;
+
+        egui::Window::new("save_as_prompt")
+            .movable(false)
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 48.0))
+            .resizable(false)
+            .collapsible(false)
+            .title_bar(false)
+            .frame(egui::Frame {
+                fill: state.preferences.theme.modal_bg.into(),
+                inner_margin: egui::Margin::same(8.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new("Save As")
+                        .size(state.preferences.ui_font_size as f32)
+                        .color(state.preferences.theme.modal_active),
+                );
+                let response = ui.add(egui::TextEdit::singleline(input).desired_width(480.0).font(
+                    egui::FontId::monospace(state.preferences.ui_font_size as f32),
+                ));
+                response.request_focus();
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Enter) {
+                        action = Some(Action::SaveBufferAs(input.clone()));
+                    } else if i.key_pressed(egui::Key::Escape) {
+                        action = Some(Action::CloseSaveAsPrompt);
+                    }
+                });
+            });
+
+        if let Some(action) = action {
+            perform_action(action, state, &mut None);
+        }
+        false
+    }
+}
+
+impl Default for SaveAsPrompt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,138 @@
+use egui::{Color32, Rect, Sense};
+use rift_core::{
+    actions::{perform_action, Action},
+    state::EditorState,
+};
+
+/// Vertical scrollbar of the editor with marks for git changes, search matches and diagnostics
+pub struct Scrollbar {
+    pub width: f32,
+}
+
+impl Scrollbar {
+    pub fn new() -> Self {
+        Self { width: 12.0 }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) {
+        if state.buffer_idx.is_none() {
+            return;
+        }
+
+        egui::SidePanel::right("scrollbar")
+            .resizable(false)
+            .show_separator_line(false)
+            .exact_width(self.width)
+            .frame(egui::Frame {
+                fill: state.preferences.theme.editor_bg.into(),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                let rect = ui.max_rect();
+                let response = ui.allocate_rect(rect, Sense::click_and_drag());
+                let painter = ui.painter_at(rect);
+
+                let (buffer, _instance) = state.get_buffer_by_id(state.buffer_idx.unwrap());
+                let num_lines = buffer.get_num_lines().max(1);
+                let view_start = state
+                    .gutter_info
+                    .first()
+                    .map(|info| info.start.row)
+                    .unwrap_or(0);
+                let view_end = state
+                    .gutter_info
+                    .last()
+                    .map(|info| info.start.row)
+                    .unwrap_or(0);
+                let row_y = |row: usize| -> f32 {
+                    rect.top() + rect.height() * (row as f32 / num_lines as f32)
+                };
+
+                // Thumb
+                let thumb_color = if response.hovered() || response.dragged() {
+                    Color32::from(state.preferences.theme.selection_bg)
+                } else {
+                    Color32::from(state.preferences.theme.selection_bg).gamma_multiply(0.6)
+                };
+                let thumb_top = row_y(view_start);
+                let thumb_bottom = row_y(view_end + 1).max(thumb_top + 8.0);
+                painter.rect_filled(
+                    Rect::from_x_y_ranges(rect.x_range(), thumb_top..=thumb_bottom),
+                    2.0,
+                    thumb_color,
+                );
+
+                // Git changes, removed lines get a thin mark
+                for hunk in state.git.hunks(state.buffer_idx.unwrap()) {
+                    let top = row_y(hunk.start);
+                    let bottom = row_y(hunk.end).max(top + 2.0);
+                    painter.rect_filled(
+                        Rect::from_min_max(
+                            egui::pos2(rect.left(), top),
+                            egui::pos2(rect.left() + 3.0, bottom),
+                        ),
+                        0.0,
+                        state.preferences.theme.git_color(hunk.kind),
+                    );
+                }
+
+                // Search matches
+                let match_color = Color32::from(state.preferences.theme.highlight_yellow);
+                for row in state.search_match_rows() {
+                    let top = row_y(row);
+                    painter.rect_filled(
+                        Rect::from_min_max(
+                            egui::pos2(rect.left() + 3.0, top),
+                            egui::pos2(rect.center().x, top + 2.0),
+                        ),
+                        0.0,
+                        match_color,
+                    );
+                }
+
+                // Diagnostics
+                let path = buffer.file_path.clone().unwrap_or_default();
+                #[cfg(target_os = "windows")]
+                let path = path.to_lowercase();
+                if let Some(diagnostics) = state.diagnostics.get(&path) {
+                    for diagnostic in &diagnostics.diagnostics {
+                        let color = state
+                            .preferences
+                            .theme
+                            .diagnostic_color(&diagnostic.severity);
+                        let top = row_y(diagnostic.range.mark.row);
+                        painter.rect_filled(
+                            Rect::from_min_max(
+                                egui::pos2(rect.center().x, top),
+                                egui::pos2(rect.right(), top + 2.0),
+                            ),
+                            0.0,
+                            color,
+                        );
+                    }
+                }
+
+                // Click or drag to center the view on the pointer
+                if response.clicked() || response.dragged() {
+                    if let Some(pos) = response.interact_pointer_pos() {
+                        let fraction = ((pos.y - rect.top()) / rect.height()).clamp(0.0, 1.0);
+                        let target = (fraction * num_lines as f32) as usize;
+                        let view_top = target.saturating_sub((view_end - view_start) / 2);
+                        let action = if view_top < view_start {
+                            Action::ScrollUp(view_start - view_top)
+                        } else {
+                            Action::ScrollDown(view_top - view_start)
+                        };
+                        perform_action(action, state, &mut None);
+                        state.update_view = true;
+                    }
+                }
+            });
+    }
+}
+
+impl Default for Scrollbar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
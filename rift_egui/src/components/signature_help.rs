@@ -0,0 +1,54 @@
+use egui::RichText;
+use rift_core::state::EditorState;
+
+/// Signature of the call around the cursor with the active parameter highlighted
+pub struct SignatureHelpView {}
+
+impl SignatureHelpView {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Shows the signature above the position of the cursor
+    pub fn show(&mut self, ctx: &egui::Context, state: &EditorState, cursor: egui::Pos2) {
+        let Some((label, parameter)) = state.signature_help.as_ref().and_then(|help| help.active())
+        else {
+            return;
+        };
+        let preferences = &state.preferences;
+        let (start, end) = parameter.unwrap_or((label.len(), label.len()));
+        let text = |text: &str| {
+            RichText::new(text)
+                .monospace()
+                .size(preferences.ui_font_size as f32)
+                .color(preferences.theme.ui_text)
+        };
+
+        egui::Area::new(egui::Id::new("signature_help"))
+            .fixed_pos(cursor)
+            .pivot(egui::Align2::LEFT_BOTTOM)
+            .order(egui::Order::Tooltip)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.spacing_mut().item_spacing.x = 0.0;
+                        ui.label(text(&label[..start]));
+                        ui.label(
+                            text(&label[start..end])
+                                .strong()
+                                .color(preferences.theme.modal_active)
+                                .background_color(preferences.theme.selection_bg),
+                        );
+                        ui.label(text(&label[end..]));
+                    });
+                });
+            });
+    }
+}
+
+impl Default for SignatureHelpView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
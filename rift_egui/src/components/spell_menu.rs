@@ -0,0 +1,107 @@
+use egui::RichText;
+use rift_core::{
+    actions::{perform_action, Action},
+    buffer::instance::Language,
+    lsp::client::LSPClientHandle,
+    state::EditorState,
+};
+use std::collections::HashMap;
+
+/// Menu of spelling suggestions for the word under the cursor
+pub struct SpellMenuView {}
+
+impl SpellMenuView {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Returns false while the menu is open
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        state: &mut EditorState,
+        lsp_handles: &mut HashMap<Language, LSPClientHandle>,
+    ) -> bool {
+        let Some(menu) = &mut state.spell_menu else {
+            return true;
+        };
+        // Suggestions followed by adding the word to the dictionary
+        let num_items = menu.suggestions.len() + 1;
+        let preferences = &state.preferences;
+        let mut action = None;
+
+        egui::Window::new("spell_menu")
+            .movable(false)
+            .order(egui::Order::Tooltip)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .collapsible(false)
+            .title_bar(false)
+            .auto_sized()
+            .show(ctx, |ui| {
+                ui.label(
+                    RichText::new(&menu.word)
+                        .size(preferences.ui_font_size as f32)
+                        .color(preferences.theme.modal_active),
+                );
+                ui.separator();
+                let labels = menu
+                    .suggestions
+                    .iter()
+                    .cloned()
+                    .chain([format!("Add \"{}\" to dictionary", menu.word)]);
+                for (idx, label) in labels.enumerate() {
+                    let mut text = RichText::new(label).size(preferences.ui_font_size as f32);
+                    if idx == menu.idx {
+                        text = text.background_color(preferences.theme.selection_bg);
+                    }
+                    if ui
+                        .add(egui::Label::new(text).sense(egui::Sense::click()))
+                        .clicked()
+                    {
+                        menu.idx = idx;
+                        action = Some(Self::choose(menu.idx, &menu.suggestions));
+                    }
+                }
+
+                ui.input(|i| {
+                    if i.key_pressed(egui::Key::Escape) {
+                        action = Some(Action::CloseSpellMenu);
+                    } else if i.key_pressed(egui::Key::Enter) {
+                        action = Some(Self::choose(menu.idx, &menu.suggestions));
+                    } else if i.key_pressed(egui::Key::ArrowDown)
+                        || i.key_pressed(egui::Key::Tab)
+                        || i.key_pressed(egui::Key::J)
+                    {
+                        menu.idx = (menu.idx + 1) % num_items;
+                    } else if i.key_pressed(egui::Key::ArrowUp) || i.key_pressed(egui::Key::K) {
+                        menu.idx = (menu.idx + num_items - 1) % num_items;
+                    }
+                });
+            });
+
+        if let Some(action) = action {
+            match state.buffer_idx {
+                Some(buffer_idx) => {
+                    let (buffer, _instance) = state.get_buffer_by_id(buffer_idx);
+                    let lsp_handle = &mut lsp_handles.get_mut(&buffer.language);
+                    perform_action(action, state, lsp_handle);
+                }
+                None => perform_action(action, state, &mut None),
+            }
+        }
+        false
+    }
+
+    fn choose(idx: usize, suggestions: &[String]) -> Action {
+        match suggestions.get(idx) {
+            Some(suggestion) => Action::ApplySpellSuggestion(suggestion.clone()),
+            None => Action::AddWordToDictionary,
+        }
+    }
+}
+
+impl Default for SpellMenuView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
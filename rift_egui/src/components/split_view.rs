@@ -0,0 +1,217 @@
+use egui::{FontId, Rect, Sense, UiBuilder};
+use rift_core::{
+    actions::{perform_action, Action},
+    layout::SplitDirection,
+    state::EditorState,
+};
+
+use crate::app::{append_inline_diagnostic, highlighted_line_job};
+
+/// Screen area of a pane and of the text inside it (excluding the gutter)
+pub struct PaneRect {
+    pub id: u32,
+    pub rect: Rect,
+    pub text_rect: Rect,
+}
+
+/// Renders the panes of a split editor with their own gutters
+/// and handles for resizing the splits
+pub struct SplitView {}
+
+impl SplitView {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Screen areas of the panes when the editor is split
+    pub fn pane_rects(state: &EditorState, rect: Rect, char_width: f32) -> Vec<PaneRect> {
+        if !state.layout.is_split() {
+            return vec![];
+        }
+        state
+            .layout
+            .regions()
+            .into_iter()
+            .map(|(id, region)| {
+                let pane_rect = Rect::from_min_size(
+                    rect.min + egui::vec2(region.x * rect.width(), region.y * rect.height()),
+                    egui::vec2(region.width * rect.width(), region.height * rect.height()),
+                )
+                .shrink(2.0);
+
+                let gutter_width = if state.preferences.show_gutter {
+                    (state.line_number_width(state.pane_buffer_id(id)) + 1) as f32 * char_width
+                        + state.preferences.gutter_padding
+                } else {
+                    0.0
+                };
+
+                let mut text_rect = pane_rect;
+                text_rect.min.x += gutter_width;
+                PaneRect {
+                    id,
+                    rect: pane_rect,
+                    text_rect,
+                }
+            })
+            .collect()
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &mut EditorState,
+        rect: Rect,
+        panes: &[PaneRect],
+        char_width: f32,
+        char_height: f32,
+    ) {
+        let font_id = FontId::monospace(state.preferences.editor_font_size as f32);
+        let focused = state.layout.focused;
+        let mut actions = vec![];
+
+        for pane in panes {
+            let (lines, gutter_info) = if pane.id == focused {
+                (state.highlighted_text.clone(), state.gutter_info.clone())
+            } else {
+                let visible_lines = (pane.text_rect.height() / char_height).floor() as usize;
+                let max_characters = (pane.text_rect.width() / char_width).floor() as usize;
+                state
+                    .get_pane_visible_lines(pane.id, visible_lines, max_characters, "\n".into())
+                    .unwrap_or_default()
+            };
+
+            // Gutter
+            if state.preferences.show_gutter {
+                let painter = ui.painter_at(pane.rect);
+                painter.rect_filled(
+                    Rect::from_x_y_ranges(
+                        pane.rect.left()
+                            ..=pane.text_rect.left() - state.preferences.gutter_padding / 2.0,
+                        pane.rect.y_range(),
+                    ),
+                    0.0,
+                    state.preferences.theme.gutter_bg,
+                );
+                let number_width = state.line_number_width(state.pane_buffer_id(pane.id));
+                for (idx, gutter_line) in gutter_info.iter().enumerate() {
+                    let gutter_value = gutter_line.label(number_width);
+                    let color = if pane.id == focused && idx == state.relative_cursor.row {
+                        state.preferences.theme.gutter_text_current_line
+                    } else {
+                        state.preferences.theme.gutter_text
+                    };
+                    painter.text(
+                        egui::pos2(
+                            pane.text_rect.left() - state.preferences.gutter_padding,
+                            pane.rect.top() + idx as f32 * char_height,
+                        ),
+                        egui::Align2::RIGHT_TOP,
+                        gutter_value,
+                        font_id.clone(),
+                        color.into(),
+                    );
+                }
+            }
+
+            // Text
+            ui.allocate_new_ui(UiBuilder::new().max_rect(pane.text_rect), |ui| {
+                ui.set_clip_rect(pane.text_rect);
+                ui.spacing_mut().item_spacing = egui::vec2(0.0, 0.0);
+                let max_characters = (pane.text_rect.width() / char_width).floor() as usize;
+                for (idx, line) in lines.iter().enumerate() {
+                    let mut job = highlighted_line_job(line, &state.preferences);
+                    // Inline diagnostics are computed for the focused pane
+                    if pane.id == focused {
+                        append_inline_diagnostic(
+                            &mut job,
+                            line,
+                            state.inline_diagnostics.get(idx),
+                            max_characters,
+                            &state.preferences,
+                        );
+                    }
+                    ui.label(job);
+                }
+            });
+
+            // Focus highlight
+            let border = if pane.id == focused {
+                state.preferences.theme.cursor_normal_mode_bg
+            } else {
+                state.preferences.theme.ui_border
+            };
+            ui.painter()
+                .rect_stroke(pane.rect.expand(1.0), 0.0, egui::Stroke::new(1.0, border));
+
+            // Click to focus
+            let clicked = ui.input(|i| {
+                i.pointer.primary_clicked()
+                    && i.pointer
+                        .interact_pos()
+                        .is_some_and(|pos| pane.rect.contains(pos))
+            });
+            if pane.id != focused && clicked {
+                actions.push(Action::FocusPane(pane.id));
+            }
+        }
+
+        // Drag handles between panes
+        for splitter in state.layout.splitters() {
+            let region = Rect::from_min_size(
+                rect.min
+                    + egui::vec2(
+                        splitter.region.x * rect.width(),
+                        splitter.region.y * rect.height(),
+                    ),
+                egui::vec2(
+                    splitter.region.width * rect.width(),
+                    splitter.region.height * rect.height(),
+                ),
+            );
+            let (handle, cursor_icon) = match splitter.direction {
+                SplitDirection::Vertical => {
+                    let x = region.left() + region.width() * splitter.ratio;
+                    (
+                        Rect::from_x_y_ranges(x - 3.0..=x + 3.0, region.y_range()),
+                        egui::CursorIcon::ResizeHorizontal,
+                    )
+                }
+                SplitDirection::Horizontal => {
+                    let y = region.top() + region.height() * splitter.ratio;
+                    (
+                        Rect::from_x_y_ranges(region.x_range(), y - 3.0..=y + 3.0),
+                        egui::CursorIcon::ResizeVertical,
+                    )
+                }
+            };
+            let response = ui.interact(
+                handle,
+                ui.id().with(("splitter", splitter.id)),
+                Sense::drag(),
+            );
+            if response.hovered() || response.dragged() {
+                ui.ctx().set_cursor_icon(cursor_icon);
+            }
+            if response.dragged() {
+                if let Some(pos) = response.interact_pointer_pos() {
+                    let ratio = match splitter.direction {
+                        SplitDirection::Vertical => (pos.x - region.left()) / region.width(),
+                        SplitDirection::Horizontal => (pos.y - region.top()) / region.height(),
+                    };
+                    actions.push(Action::ResizeSplit(splitter.id, ratio));
+                }
+            }
+        }
+
+        for action in actions {
+            perform_action(action, state, &mut None);
+        }
+    }
+}
+
+impl Default for SplitView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
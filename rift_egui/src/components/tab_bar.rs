@@ -0,0 +1,106 @@
+use egui::{RichText, Sense};
+use rift_core::{
+    actions::{perform_action, Action},
+    state::EditorState,
+};
+
+/// Strip of tabs for the open buffers drawn above the editor
+pub struct TabBar {}
+
+impl TabBar {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) {
+        if state.buffer_order.is_empty() {
+            return;
+        }
+
+        let mut actions = vec![];
+        egui::TopBottomPanel::top("tab_bar")
+            .resizable(false)
+            .show_separator_line(false)
+            .frame(egui::Frame {
+                fill: state.preferences.theme.status_bar_bg.into(),
+                inner_margin: egui::Margin::symmetric(4.0, 4.0),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                // Scroll horizontally when the tabs overflow the window
+                egui::ScrollArea::horizontal()
+                    .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            for (position, id) in state.buffer_order.iter().enumerate() {
+                                let buffer = &state.buffers[id];
+                                let active = state.buffer_idx == Some(*id);
+                                let name = buffer
+                                    .file_path
+                                    .as_ref()
+                                    .and_then(|path| {
+                                        std::path::Path::new(path)
+                                            .file_name()
+                                            .map(|name| name.to_string_lossy().to_string())
+                                    })
+                                    .unwrap_or("[No Name]".to_string());
+                                let label = if buffer.modified {
+                                    format!("● {}", name)
+                                } else {
+                                    name
+                                };
+                                let text = if active {
+                                    RichText::new(label)
+                                        .color(state.preferences.theme.gutter_text_current_line)
+                                } else {
+                                    RichText::new(label).color(state.preferences.theme.gutter_text)
+                                };
+
+                                let response = ui
+                                    .add(
+                                        egui::Button::new(text)
+                                            .selected(active)
+                                            .sense(Sense::click_and_drag()),
+                                    )
+                                    .on_hover_text(buffer.file_path.clone().unwrap_or_default());
+                                if active && state.update_view {
+                                    response.scroll_to_me(None);
+                                }
+                                if response.drag_started() {
+                                    response.dnd_set_drag_payload(*id);
+                                }
+                                if let Some(dragged) = response.dnd_release_payload::<u32>() {
+                                    actions.push(Action::MoveBuffer(*dragged, position));
+                                }
+                                if response.clicked() {
+                                    actions.push(Action::SetActiveBuffer(*id));
+                                }
+                                let close_clicked =
+                                    ui.small_button("x").on_hover_text("Close").clicked();
+                                if response.middle_clicked() || close_clicked {
+                                    actions.push(Action::SetActiveBuffer(*id));
+                                    actions.push(Action::CloseCurrentBuffer);
+                                    if let Some(current) = state.buffer_idx {
+                                        if current != *id {
+                                            actions.push(Action::SetActiveBuffer(current));
+                                        }
+                                    }
+                                }
+                                ui.separator();
+                            }
+                        });
+                    });
+            });
+
+        for action in actions {
+            perform_action(action, state, &mut None);
+            state.update_view = true;
+        }
+    }
+}
+
+impl Default for TabBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use egui::{text::LayoutJob, Color32, FontId};
+use rift_core::{
+    buffer::instance::{Attribute, HighlightType},
+    preferences::Theme,
+    state::EditorState,
+};
+
+/// Bottom panel rendering the embedded terminal
+pub struct TerminalPane {}
+
+impl TerminalPane {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut EditorState) {
+        if state.terminal.is_none() {
+            return;
+        }
+
+        egui::TopBottomPanel::bottom("terminal")
+            .resizable(true)
+            .default_height(ctx.screen_rect().height() / 3.0)
+            .frame(egui::Frame {
+                fill: state.preferences.theme.editor_bg.into(),
+                inner_margin: egui::Margin::same(state.preferences.editor_padding),
+                stroke: egui::Stroke::new(1.0, state.preferences.theme.ui_border),
+                ..Default::default()
+            })
+            .show(ctx, |ui| {
+                ui.spacing_mut().item_spacing = egui::vec2(0.0, 0.0);
+                let font_id = FontId::monospace(state.preferences.editor_font_size as f32);
+                let (char_width, char_height) = ui
+                    .fonts(|fonts| (fonts.glyph_width(&font_id, 'x'), fonts.row_height(&font_id)));
+                let rect = ui.available_rect_before_wrap();
+                let rows = (rect.height() / char_height).floor() as u16;
+                let cols = (rect.width() / char_width).floor() as u16;
+
+                let terminal = state.terminal.as_mut().unwrap();
+                if let Err(err) = terminal.resize(rows, cols) {
+                    tracing::error!("Failed to resize terminal: {}", err);
+                }
+                terminal.take_updated();
+                let (lines, cursor) = terminal.get_visible_lines();
+
+                let theme = &state.preferences.theme;
+                for line in lines {
+                    let mut job = LayoutJob::default();
+                    for (text, attributes) in line {
+                        let mut format = egui::TextFormat {
+                            font_id: font_id.clone(),
+                            ..Default::default()
+                        };
+                        for attribute in &attributes {
+                            match attribute {
+                                Attribute::Highlight(highlight_type) => {
+                                    format.color = highlight_color(theme, highlight_type);
+                                }
+                                Attribute::Select => {
+                                    format.background = theme.selection_bg.into();
+                                }
+                                Attribute::Underline => {
+                                    format.underline = egui::Stroke::new(1.0, format.color);
+                                }
+                                _ => {}
+                            }
+                        }
+                        job.append(&text, 0.0, format);
+                    }
+                    ui.label(job);
+                }
+
+                if let Some(cursor) = cursor {
+                    let min = rect.min
+                        + egui::vec2(
+                            cursor.column as f32 * char_width,
+                            cursor.row as f32 * char_height,
+                        );
+                    ui.painter().rect_filled(
+                        egui::Rect::from_min_size(min, egui::vec2(char_width, char_height)),
+                        0.0,
+                        Color32::from(theme.cursor_insert_mode_bg).gamma_multiply(0.7),
+                    );
+                }
+            });
+
+        // Output from the shell arrives in the background
+        ctx.request_repaint_after(Duration::from_millis(30));
+    }
+}
+
+impl Default for TerminalPane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn highlight_color(theme: &Theme, highlight_type: &HighlightType) -> Color32 {
+    match highlight_type {
+        HighlightType::None => theme.highlight_none.into(),
+        HighlightType::White => theme.highlight_white.into(),
+        HighlightType::Red => theme.highlight_red.into(),
+        HighlightType::Orange => theme.highlight_orange.into(),
+        HighlightType::Blue => theme.highlight_blue.into(),
+        HighlightType::Green => theme.highlight_green.into(),
+        HighlightType::Purple => theme.highlight_purple.into(),
+        HighlightType::Yellow => theme.highlight_yellow.into(),
+        HighlightType::Gray => theme.highlight_gray.into(),
+        HighlightType::Turquoise => theme.highlight_turquoise.into(),
+    }
+}
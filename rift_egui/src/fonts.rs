@@ -0,0 +1,93 @@
+use std::{fs::File, io::Read};
+
+use anyhow::Result;
+use egui::{FontData, FontDefinitions, FontTweak};
+use rift_core::preferences::Preferences;
+
+/// Fonts selected in the preferences, used to detect when they need reloading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontSelection {
+    pub editor_font_family: String,
+    pub editor_font_path: Option<String>,
+    pub ui_font_family: String,
+    pub ui_font_path: Option<String>,
+}
+
+impl FontSelection {
+    pub fn from_preferences(preferences: &Preferences) -> Self {
+        Self {
+            editor_font_family: preferences.editor_font_family.clone(),
+            editor_font_path: preferences.editor_font_path.clone(),
+            ui_font_family: preferences.ui_font_family.clone(),
+            ui_font_path: preferences.ui_font_path.clone(),
+        }
+    }
+}
+
+/// Build font definitions with the editor font used for monospace text
+/// and the ui font used for proportional text.
+/// Fonts which fail to load fall back to the bundled egui fonts.
+pub fn font_definitions(selection: &FontSelection) -> FontDefinitions {
+    let mut fonts = FontDefinitions::default();
+    let families = [
+        (
+            egui::FontFamily::Monospace,
+            &selection.editor_font_family,
+            &selection.editor_font_path,
+        ),
+        (
+            egui::FontFamily::Proportional,
+            &selection.ui_font_family,
+            &selection.ui_font_path,
+        ),
+    ];
+    for (family, name, path) in families {
+        let font_data = match path {
+            Some(path) => load_font_file(path, 0),
+            None => load_system_font(name),
+        };
+        match font_data {
+            Ok(font_data) => {
+                fonts.font_data.insert(name.to_owned(), font_data);
+                fonts
+                    .families
+                    .get_mut(&family)
+                    .unwrap()
+                    .insert(0, name.to_owned());
+            }
+            Err(err) => {
+                tracing::error!("Failed to load font {}: {}", name, err);
+            }
+        }
+    }
+    fonts
+}
+
+/// Load font from a font file
+fn load_font_file(path: &str, font_index: u32) -> Result<FontData> {
+    let mut font_content = Vec::new();
+    File::open(path)?.read_to_end(&mut font_content)?;
+    Ok(FontData {
+        font: std::borrow::Cow::Owned(font_content),
+        index: font_index,
+        tweak: FontTweak::default(),
+    })
+}
+
+/// Find the best match for the font family among the installed fonts
+fn load_system_font(family: &str) -> Result<FontData> {
+    let handle = font_kit::source::SystemSource::new().select_best_match(
+        &[font_kit::family_name::FamilyName::Title(family.to_owned())],
+        &font_kit::properties::Properties::new(),
+    )?;
+    match handle {
+        font_kit::handle::Handle::Path { path, font_index } => {
+            load_font_file(path.to_str().unwrap(), font_index)
+        }
+        font_kit::handle::Handle::Memory { bytes, font_index } => Ok(FontData {
+            font: std::borrow::Cow::Owned((*bytes).clone()),
+            index: font_index,
+            tweak: FontTweak::default(),
+        }),
+    }
+}
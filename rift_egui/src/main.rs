@@ -1,23 +1,39 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use clap::Parser;
+use rift_core::logs;
+
 pub mod app;
+pub mod cli;
 pub mod command_dispatcher;
 pub mod components;
+pub mod fonts;
 
 fn main() -> eframe::Result {
-    let file_appender = tracing_appender::rolling::never("logs", "rift.log");
+    let file_appender = tracing_appender::rolling::never(logs::LOG_DIR, logs::LOG_FILE_NAME);
     tracing_subscriber::fmt()
         .with_writer(file_appender)
         .with_ansi(false)
         .with_level(true)
         .init();
+    let cli_args = cli::CLIArgs::parse();
     let native_options = eframe::NativeOptions::default();
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap();
     let mut app = app::App::new(rt);
+    if let Some([left, right]) = cli_args.diff.as_deref() {
+        app.open_diff(
+            left.to_string_lossy().to_string(),
+            right.to_string_lossy().to_string(),
+        );
+    }
+    if cli_args.restore_session {
+        app.restore_session();
+    }
+    app.offer_recovery();
     eframe::run_simple_native("Rift", native_options, move |ctx, _frame| {
         app.draw(ctx);
     })
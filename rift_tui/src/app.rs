@@ -14,13 +14,29 @@ use ratatui::{
 use rift_core::{
     actions::{perform_action, Action},
     buffer::{
-        instance::{Attribute, Cursor, Language, Range, Selection},
+        graphemes,
+        instance::{display_text, Attribute, HighlightType, Language},
         line_buffer::LineBuffer,
     },
+    code_actions, config, dap, diagnostics,
+    dialog::{ConfirmChoice, ConfirmKind},
+    diff::LineChange,
+    events::{self, EventKind},
+    file_changes,
     io::file_io,
-    lsp::{client::LSPClientHandle, types},
-    preferences::Color,
+    keys::{self, KeyInput},
+    logs,
+    lsp::{
+        client::LSPClientHandle,
+        types::{self, protocol},
+    },
+    notifications::NotificationLevel,
+    preferences::{Color, Theme},
+    recovery,
+    replace::ReplaceChoice,
+    session,
     state::{EditorState, Mode},
+    terminal::TerminalKey,
 };
 
 use crate::cli;
@@ -29,6 +45,88 @@ pub fn color_from_rgb(c: Color) -> ratatui::style::Color {
     ratatui::style::Color::Rgb(c.r, c.g, c.b)
 }
 
+/// Style for a token with the given attributes
+pub fn style_from_attributes(theme: &Theme, attributes: &HashSet<Attribute>) -> Style {
+    let mut style = Style::new();
+    for attribute in attributes {
+        match attribute {
+            Attribute::None => {}
+            Attribute::Visible => {}
+            Attribute::Underline => {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            Attribute::Highlight(highlight_type) => {
+                style = style.fg(color_from_rgb(match highlight_type {
+                    HighlightType::None => theme.highlight_none,
+                    HighlightType::White => theme.highlight_white,
+                    HighlightType::Red => theme.highlight_red,
+                    HighlightType::Orange => theme.highlight_orange,
+                    HighlightType::Blue => theme.highlight_blue,
+                    HighlightType::Green => theme.highlight_green,
+                    HighlightType::Purple => theme.highlight_purple,
+                    HighlightType::Yellow => theme.highlight_yellow,
+                    HighlightType::Gray => theme.highlight_gray,
+                    HighlightType::Turquoise => theme.highlight_turquoise,
+                }));
+            }
+            Attribute::Select => {
+                style = style.bg(color_from_rgb(theme.selection_bg));
+            }
+            Attribute::Cursor => {}
+            Attribute::DiagnosticSeverity(severity) => {
+                style = style
+                    .add_modifier(Modifier::UNDERLINED)
+                    .underline_color(color_from_rgb(theme.diagnostic_color(severity)));
+            }
+            Attribute::Misspelled => {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            Attribute::DebugStop => {
+                style = style.bg(color_from_rgb(theme.gutter_bg));
+            }
+            Attribute::Conflict(side) => {
+                style = style.bg(color_from_rgb(theme.conflict_bg(*side)));
+            }
+            // Applied after the syntax highlight below
+            Attribute::IndentGuide | Attribute::Bracket(_) | Attribute::Invisible => {}
+        }
+    }
+    for attribute in attributes {
+        match attribute {
+            Attribute::IndentGuide | Attribute::Invisible => {
+                style = style.fg(color_from_rgb(theme.gutter_text))
+            }
+            Attribute::Bracket(depth) => {
+                style = style.fg(color_from_rgb(theme.bracket_color(*depth)))
+            }
+            _ => {}
+        }
+    }
+    style
+}
+
+/// Screen areas of the panes when the editor is split
+fn pane_areas(state: &EditorState, area: Rect) -> Vec<(u32, Rect)> {
+    if !state.layout.is_split() {
+        return vec![];
+    }
+    let scale = |start: f32, length: f32, total: u16| {
+        let from = (start * total as f32).round() as u16;
+        let to = ((start + length) * total as f32).round() as u16;
+        (from, to.min(total).saturating_sub(from))
+    };
+    state
+        .layout
+        .regions()
+        .into_iter()
+        .map(|(id, region)| {
+            let (x, width) = scale(region.x, region.width, area.width);
+            let (y, height) = scale(region.y, region.height, area.height);
+            (id, Rect::new(area.x + x, area.y + y, width, height))
+        })
+        .collect()
+}
+
 pub struct App {
     pub state: EditorState,
     pub lsp_handles: HashMap<Language, LSPClientHandle>,
@@ -40,34 +138,77 @@ pub struct App {
     pub completion_menu_items: Vec<types::CompletionItem>,
     pub completion_menu_idx: Option<usize>,
     pub completion_menu_state: widgets::ListState,
+    /// First row of the diff view which is shown
+    pub diff_scroll: usize,
+    /// Hunk last scrolled to in the diff view
+    pub diff_scrolled_hunk: Option<usize>,
+    /// Started as a diff tool, quitting once the diff is closed
+    pub diff_mode: bool,
 }
 
 impl App {
     pub fn new(rt: tokio::runtime::Runtime, cli_args: cli::CLIArgs) -> Self {
+        let start = std::time::Instant::now();
         let mut state = EditorState::new(rt);
+        config::reload_config(&mut state);
+        tracing::info!("Startup: editor state created in {:?}", start.elapsed());
         let mut lsp_handles = HashMap::new();
 
-        if let Some(path) = cli_args.path {
+        let mut diff_mode = false;
+        if let Some([left, right]) = cli_args.diff.as_deref() {
+            perform_action(
+                Action::OpenDiff(
+                    left.to_string_lossy().to_string(),
+                    right.to_string_lossy().to_string(),
+                ),
+                &mut state,
+                &mut None,
+            );
+            diff_mode = state.diff_view.is_some();
+        }
+
+        recovery::offer_recovery(&mut state);
+        if cli_args.restore_session {
+            let folder = match &cli_args.path {
+                Some(path) if path.is_dir() => path.clone(),
+                _ => std::env::current_dir().unwrap_or_default(),
+            };
+            state.workspace_folder = std::path::absolute(folder)
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            session::restore_session(&mut state);
+        } else if let Some(path) = cli_args.path {
             let mut path = path;
             if path.is_relative() {
                 path = std::path::absolute(path).unwrap();
             }
             if path.is_dir() {
-                state.workspace_folder = path.into_os_string().into_string().unwrap();
+                state.open_workspace(path.into_os_string().into_string().unwrap());
+            } else if state.request_decryption(path.to_str().unwrap()) {
+                state.workspace_folder = path.parent().unwrap().to_str().unwrap().to_string();
             } else {
                 state.workspace_folder = path.parent().unwrap().to_str().unwrap().to_string();
-                let initial_text = file_io::read_file_content(path.to_str().unwrap()).unwrap();
-                let buffer = LineBuffer::new(
+                let (initial_text, encoding) =
+                    file_io::read_file_with_encoding(path.to_str().unwrap()).unwrap();
+                let mut buffer = LineBuffer::new(
                     initial_text.clone(),
                     Some(path.to_str().unwrap().to_string()),
                 );
+                buffer.encoding = encoding;
 
                 if let std::collections::hash_map::Entry::Vacant(e) =
                     lsp_handles.entry(buffer.language)
                 {
-                    if let Some(mut lsp_handle) = state.spawn_lsp(buffer.language) {
-                        lsp_handle.init_lsp_sync(state.workspace_folder.clone());
+                    let start = std::time::Instant::now();
+                    if let Some(lsp_handle) =
+                        state.spawn_lsp(buffer.language, path.to_str().unwrap())
+                    {
                         e.insert(lsp_handle);
+                        tracing::info!(
+                            "Startup: language server initialized in {:?}",
+                            start.elapsed()
+                        );
                     }
                 }
 
@@ -98,6 +239,33 @@ impl App {
             completion_menu_items: vec![],
             completion_menu_idx: None,
             completion_menu_state: widgets::ListState::default(),
+            diff_scroll: 0,
+            diff_scrolled_hunk: None,
+            diff_mode,
+        }
+    }
+
+    /// Pass a key to the pending count and key sequence or the custom mode, true if it was consumed
+    fn push_sequence_key(&mut self, key: &event::KeyEvent) -> bool {
+        if key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return false;
+        }
+        let name = match key.code {
+            KeyCode::Char(' ') => "space".to_string(),
+            KeyCode::Esc => "escape".to_string(),
+            KeyCode::Char(c) if c.is_ascii_alphanumeric() => c.to_string(),
+            _ => return false,
+        };
+        match keys::push_key(&mut self.state, &name) {
+            KeyInput::Pending => true,
+            KeyInput::Action(action) => {
+                self.perform_action(action);
+                true
+            }
+            KeyInput::Unhandled => false,
         }
     }
 
@@ -113,16 +281,58 @@ impl App {
 
     pub fn run(&mut self, mut terminal: DefaultTerminal) -> anyhow::Result<()> {
         loop {
+            if let Some(info) = self.state.info_modal.take() {
+                self.info_modal_content = info;
+                self.info_modal_active = true;
+            }
             terminal.draw(|frame| {
                 // Layout
+                let terminal_height = if self.state.terminal.is_some() {
+                    frame.area().height / 3
+                } else {
+                    0
+                };
+                self.state.notifications.expire();
+                let message_height = if self.state.notifications.latest().is_some() {
+                    1
+                } else {
+                    0
+                };
                 let v_layout = Layout::default()
                     .direction(Direction::Vertical)
-                    .constraints([Constraint::Fill(1), Constraint::Length(1)])
+                    .constraints([
+                        Constraint::Fill(1),
+                        Constraint::Length(terminal_height),
+                        Constraint::Length(message_height),
+                        Constraint::Length(1),
+                    ])
                     .split(frame.area());
+                let (tree_area, main_area) = if self.state.file_tree_open {
+                    let tree_layout =
+                        Layout::horizontal([Constraint::Length(30), Constraint::Fill(1)])
+                            .split(v_layout[0]);
+                    (Some(tree_layout[0]), tree_layout[1])
+                } else {
+                    (None, v_layout[0])
+                };
+                // The focused pane of a split editor is rendered like an unsplit editor
+                let panes = pane_areas(&self.state, main_area);
+                let editor_area = panes
+                    .iter()
+                    .find(|(id, _)| *id == self.state.layout.focused)
+                    .map(|(_, area)| widgets::Block::bordered().inner(*area))
+                    .unwrap_or(main_area);
+                // Sign column, line numbers, marker and git marker
+                let number_width = self.state.line_number_width(self.state.buffer_idx);
+                let gutter_width = if self.state.preferences.show_gutter {
+                    number_width as u16 + 5
+                } else {
+                    0
+                };
                 let h_layout = Layout::default()
                     .direction(Direction::Horizontal)
-                    .constraints([Constraint::Length(7), Constraint::Fill(1)])
-                    .split(v_layout[0]);
+                    .constraints([Constraint::Length(gutter_width), Constraint::Fill(1)])
+                    .split(editor_area);
 
                 let visible_lines = h_layout[1].height as usize;
                 let max_characters = h_layout[1].width as usize;
@@ -136,333 +346,275 @@ impl App {
                     self.state.update_view = true;
                 }
 
-                if let Ok(async_result) = self.state.async_handle.receiver.try_recv() {
-                    let (buffer, _instance) =
-                        self.state.get_buffer_by_id(self.state.buffer_idx.unwrap());
-                    let lsp_handle = &mut self.lsp_handles.get_mut(&buffer.language);
+                // Results arrive line by line for running tasks
+                while let Ok(async_result) = self.state.async_handle.receiver.try_recv() {
+                    let language = self
+                        .state
+                        .buffer_idx
+                        .map(|id| self.state.get_buffer_by_id(id).0.language);
+                    let lsp_handle =
+                        &mut language.and_then(|language| self.lsp_handles.get_mut(&language));
                     (async_result.callback)(async_result.result, &mut self.state, lsp_handle);
                 }
+                dap::poll_debugger(&mut self.state);
+                logs::poll_logs(&mut self.state);
+                config::poll_config(&mut self.state);
+                let language = self
+                    .state
+                    .buffer_idx
+                    .map(|id| self.state.get_buffer_by_id(id).0.language);
+                let lsp_handle =
+                    &mut language.and_then(|language| self.lsp_handles.get_mut(&language));
+                events::poll_events(&mut self.state, lsp_handle);
+                recovery::write_journals(&mut self.state);
+                for id in file_changes::poll_file_changes(&mut self.state) {
+                    let language = self.state.get_buffer_by_id(id).0.language;
+                    let lsp_handle = &mut self.lsp_handles.get_mut(&language);
+                    perform_action(Action::ReloadBuffer(id), &mut self.state, lsp_handle);
+                }
 
                 if self.state.buffer_idx.is_some() {
                     let (buffer, _instance) =
                         self.state.get_buffer_by_id(self.state.buffer_idx.unwrap());
-                    if let Some(lsp_handle) = self.lsp_handles.get_mut(&buffer.language) {
+                    let language = buffer.language;
+                    if let Some(restart) = self.state.lsp_restart.take() {
+                        // Dropping the servers kills them
+                        self.lsp_handles.remove(&restart);
+                        if let Some(lsp_handle) = self.state.restart_lsp(restart) {
+                            self.lsp_handles.insert(restart, lsp_handle);
+                        }
+                    }
+                    if self
+                        .lsp_handles
+                        .get(&language)
+                        .is_some_and(|lsp_handle| !lsp_handle.is_running())
+                    {
+                        self.lsp_handles.remove(&language);
+                        self.state.notify(
+                            NotificationLevel::Error,
+                            format!("Language server for {:?} exited", language),
+                        );
+                    }
+                    if let Some(lsp_handle) = self.lsp_handles.get_mut(&language) {
+                        diagnostics::pull_diagnostics(&mut self.state, language, lsp_handle, false);
                         if let Some(message) = lsp_handle.recv_message_sync() {
                             self.state.update_view = true;
                             match message {
                                 rift_core::lsp::client::IncomingMessage::Response(response) => {
-                                    if response.error.is_some() {
+                                    let method = lsp_handle
+                                        .id_method
+                                        .get(&response.id)
+                                        .cloned()
+                                        .unwrap_or_default();
+                                    let result = response.result.unwrap_or_default();
+                                    let handled = if let Some(error) = response.error {
                                         tracing::error!(
                                             "---Error: Message Id: {}\n\n{:#?}---\n",
                                             response.id,
-                                            response.error.unwrap()
+                                            error
                                         );
-                                    } else if lsp_handle.id_method[&response.id]
-                                        == "textDocument/hover"
-                                        && response.result.is_some()
-                                    {
-                                        let message = response.result.unwrap()["contents"]["value"]
-                                            .as_str()
-                                            .unwrap()
-                                            .to_string();
-                                        self.info_modal_content = message;
-                                        self.info_modal_active = true;
-                                    } else if lsp_handle.id_method[&response.id]
-                                        == "textDocument/completion"
-                                        && response.result.is_some()
+                                        Ok(())
+                                    } else if method == "textDocument/hover" && !result.is_null() {
+                                        protocol::parse_hover(result).map(|message| {
+                                            self.info_modal_content = message;
+                                            self.info_modal_active = true;
+                                        })
+                                    } else if method == "textDocument/completion"
+                                        && !result.is_null()
                                     {
-                                        let items = response.result.unwrap()["items"]
-                                            .as_array()
-                                            .unwrap()
-                                            .clone();
-                                        let mut completion_items = vec![];
-                                        for item in items {
-                                            completion_items.push(types::CompletionItem {
-                                                label: item["label"].as_str().unwrap().to_owned(),
-                                                edit: types::TextEdit {
-                                                    text: item["textEdit"]["newText"]
-                                                        .as_str()
-                                                        .unwrap()
-                                                        .to_owned(),
-                                                    range: Selection {
-                                                        cursor: Cursor {
-                                                            row: item["textEdit"]["range"]["end"]
-                                                                ["line"]
-                                                                .as_u64()
-                                                                .unwrap()
-                                                                as usize,
-                                                            column: item["textEdit"]["range"]["end"]
-                                                                ["character"]
-                                                                .as_u64()
-                                                                .unwrap()
-                                                                as usize,
-                                                        },
-                                                        mark: Cursor {
-                                                            row: item["textEdit"]["range"]["start"]
-                                                                ["line"]
-                                                                .as_u64()
-                                                                .unwrap()
-                                                                as usize,
-                                                            column: item["textEdit"]["range"]
-                                                                ["start"]["character"]
-                                                                .as_u64()
-                                                                .unwrap()
-                                                                as usize,
-                                                        },
-                                                    },
-                                                },
-                                            });
-                                        }
-                                        self.completion_menu_active = true;
-                                        self.completion_menu_items = completion_items;
-                                        self.completion_menu_idx = None;
-                                    } else if lsp_handle.id_method[&response.id]
-                                        == "textDocument/formatting"
-                                        && response.result.is_some()
-                                    {
-                                        let edits =
-                                            response.result.unwrap().as_array().unwrap().clone();
-                                        for edit in edits {
-                                            let text_edit = types::TextEdit {
-                                                text: edit["newText"].as_str().unwrap().to_owned(),
-                                                range: Selection {
-                                                    cursor: Cursor {
-                                                        row: edit["range"]["end"]["line"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                        column: edit["range"]["end"]["character"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                    },
-                                                    mark: Cursor {
-                                                        row: edit["range"]["start"]["line"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                        column: edit["range"]["start"]["character"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                    },
-                                                },
-                                            };
+                                        let (_buffer, instance) = self
+                                            .state
+                                            .get_buffer_by_id(self.state.buffer_idx.unwrap());
+                                        protocol::parse_completion(result, instance.cursor).map(
+                                            |completion_items| {
+                                                self.completion_menu_active = true;
+                                                self.completion_menu_items = completion_items;
+                                                self.completion_menu_idx = None;
+                                            },
+                                        )
+                                    } else if method == "textDocument/foldingRange" {
+                                        protocol::parse_folding_ranges(result).map(|ranges| {
                                             perform_action(
-                                                Action::DeleteText(text_edit.range),
+                                                Action::SetFoldingRanges(ranges),
                                                 &mut self.state,
                                                 &mut Some(lsp_handle),
                                             );
+                                        })
+                                    } else if method == "textDocument/prepareCallHierarchy" {
+                                        protocol::parse_call_hierarchy_items(result).map(|items| {
                                             perform_action(
-                                                Action::InsertText(
-                                                    text_edit.text,
-                                                    text_edit.range.mark,
-                                                ),
+                                                Action::SetCallHierarchy(items),
                                                 &mut self.state,
                                                 &mut Some(lsp_handle),
                                             );
-                                        }
+                                        })
+                                    } else if method.starts_with("callHierarchy/") {
+                                        protocol::parse_calls(
+                                            result,
+                                            method.ends_with("incomingCalls"),
+                                        )
+                                        .map(|calls| {
+                                            perform_action(
+                                                Action::SetCallHierarchyCalls(calls),
+                                                &mut self.state,
+                                                &mut Some(lsp_handle),
+                                            );
+                                        })
+                                    } else if method == "textDocument/signatureHelp" {
+                                        protocol::parse_signature_help(result).map(|help| {
+                                            perform_action(
+                                                Action::SetSignatureHelp(help),
+                                                &mut self.state,
+                                                &mut Some(lsp_handle),
+                                            );
+                                        })
+                                    } else if method == "textDocument/diagnostic" {
+                                        protocol::parse_document_diagnostics(result).map(
+                                            |diagnostics| {
+                                                perform_action(
+                                                    Action::SetPulledDiagnostics(
+                                                        response.id,
+                                                        diagnostics,
+                                                    ),
+                                                    &mut self.state,
+                                                    &mut Some(lsp_handle),
+                                                );
+                                            },
+                                        )
+                                    } else if method == "textDocument/codeAction" {
+                                        protocol::parse_code_actions(result).map(|actions| {
+                                            perform_action(
+                                                Action::ShowCodeActions(actions),
+                                                &mut self.state,
+                                                &mut Some(lsp_handle),
+                                            );
+                                        })
+                                    } else if method == "textDocument/formatting" {
+                                        protocol::parse_text_edits(result).map(|edits| {
+                                            for text_edit in edits {
+                                                perform_action(
+                                                    Action::DeleteText(text_edit.range),
+                                                    &mut self.state,
+                                                    &mut Some(lsp_handle),
+                                                );
+                                                perform_action(
+                                                    Action::InsertText(
+                                                        text_edit.text,
+                                                        text_edit.range.mark,
+                                                    ),
+                                                    &mut self.state,
+                                                    &mut Some(lsp_handle),
+                                                );
+                                            }
+                                        })
                                     } else {
                                         let message = format!(
                                             "---Response to: {}({})\n\n{:#?}---\n",
-                                            lsp_handle.id_method[&response.id],
-                                            response.id,
-                                            response.result
+                                            method, response.id, result
                                         );
                                         tracing::info!("{}", message);
+                                        Ok(())
+                                    };
+                                    if let Err(err) = handled {
+                                        self.state.notify(
+                                            NotificationLevel::Warning,
+                                            format!("{:#}", err),
+                                        );
                                     }
                                 }
+                                rift_core::lsp::client::IncomingMessage::Request(request) => {
+                                    code_actions::handle_server_request(
+                                        &mut self.state,
+                                        request,
+                                        lsp_handle,
+                                    );
+                                }
                                 rift_core::lsp::client::IncomingMessage::Notification(
                                     notification,
-                                ) => {
-                                    if notification.method == "textDocument/publishDiagnostics"
-                                        && notification.params.is_some()
+                                ) => match notification.params {
+                                    Some(params)
+                                        if notification.method
+                                            == "textDocument/publishDiagnostics" =>
                                     {
-                                        let mut uri = std::path::absolute(
-                                            notification.params.as_ref().unwrap()["uri"]
-                                                .as_str()
-                                                .unwrap()
-                                                .strip_prefix("file:")
-                                                .unwrap()
-                                                .trim_start_matches("\\"),
-                                        )
-                                        .unwrap()
-                                        .to_str()
-                                        .unwrap()
-                                        .to_string();
-                                        #[cfg(target_os = "windows")]
-                                        {
-                                            uri = uri.to_lowercase();
-                                        }
-
-                                        let mut diagnostics = types::PublishDiagnostics {
-                                            uri,
-                                            version: notification.params.as_ref().unwrap()
-                                                ["version"]
-                                                .as_u64()
-                                                .unwrap_or(0)
-                                                as usize,
-                                            diagnostics: vec![],
-                                        };
-
-                                        for diagnostic in notification.params.as_ref().unwrap()
-                                            ["diagnostics"]
-                                            .as_array()
-                                            .unwrap()
-                                        {
-                                            diagnostics.diagnostics.push(types::Diagnostic {
-                                                range: Selection {
-                                                    cursor: Cursor {
-                                                        row: diagnostic["range"]["end"]["line"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                        column: diagnostic["range"]["end"]
-                                                            ["character"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                    },
-                                                    mark: Cursor {
-                                                        row: diagnostic["range"]["start"]["line"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                        column: diagnostic["range"]["start"]
-                                                            ["character"]
-                                                            .as_u64()
-                                                            .unwrap()
-                                                            as usize,
-                                                    },
-                                                },
-                                                severity: match diagnostic["severity"]
-                                                    .as_u64()
-                                                    .unwrap_or(1)
-                                                {
-                                                    1 => types::DiagnosticSeverity::Error,
-                                                    2 => types::DiagnosticSeverity::Warning,
-                                                    3 => types::DiagnosticSeverity::Information,
-                                                    4 => types::DiagnosticSeverity::Hint,
-                                                    _ => types::DiagnosticSeverity::Error,
-                                                },
-                                                code: diagnostic["code"].to_string(),
-                                                source: diagnostic["source"].to_string(),
-                                                message: diagnostic["message"].to_string(),
-                                            });
+                                        match protocol::parse_publish_diagnostics(params) {
+                                            Ok(diagnostics) => {
+                                                self.state.publish_diagnostics(diagnostics);
+                                            }
+                                            Err(err) => {
+                                                self.state.notify(
+                                                    NotificationLevel::Warning,
+                                                    format!("{:#}", err),
+                                                );
+                                            }
                                         }
-                                        self.state
-                                            .diagnostics
-                                            .insert(diagnostics.uri.clone(), diagnostics);
-                                    } else {
+                                    }
+                                    params => {
                                         let message = format!(
                                             "---Notification: {}\n\n{:#?}---\n",
-                                            notification.method, notification.params
+                                            notification.method, params
                                         );
                                         tracing::info!("{}", message);
                                     }
-                                }
+                                },
                             }
                         }
                     }
                 }
 
                 if self.state.buffer_idx.is_some() {
+                    // Update the git change markers once the debounce passes
+                    if self.state.git.is_pending() {
+                        self.state.update_view = true;
+                    }
                     // Compute view if updated
                     if self.state.update_view {
-                        self.state.relative_cursor =
-                            self.update_visible_lines(visible_lines, max_characters);
+                        self.state.relative_cursor = self
+                            .state
+                            .update_visible_lines(visible_lines, max_characters);
                         self.state.update_view = false;
                     }
 
                     // Render text
                     let mut lines = vec![];
-                    for line in &self.state.highlighted_text {
+                    for (idx, line) in self.state.highlighted_text.iter().enumerate() {
                         let mut line_widget = vec![];
                         for token in line {
-                            let mut style = Style::new();
-                            for attribute in &token.1 {
-                                match attribute {
-                                    Attribute::None => {}
-                                    Attribute::Visible => {}
-                                    Attribute::Underline => {}
-                                    Attribute::Highlight(highlight_type) => match highlight_type {
-                                        rift_core::buffer::instance::HighlightType::None => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_none,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::White => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_white,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::Red => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_red,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::Orange => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_orange,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::Blue => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_blue,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::Green => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_green,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::Purple => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_purple,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::Yellow => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_yellow,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::Gray => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_gray,
-                                            ));
-                                        }
-                                        rift_core::buffer::instance::HighlightType::Turquoise => {
-                                            style = style.fg(color_from_rgb(
-                                                self.state.preferences.theme.highlight_turquoise,
-                                            ));
-                                        }
-                                    },
-                                    Attribute::Select => {
-                                        style = style.bg(color_from_rgb(
-                                            self.state.preferences.theme.selection_bg,
-                                        ));
-                                    }
-                                    Attribute::Cursor => {}
-                                    Attribute::DiagnosticSeverity(severity) => {
-                                        style = style.add_modifier(Modifier::UNDERLINED);
-                                        // .underline_color(color_from_rgb(match severity {
-                                        //     types::DiagnosticSeverity::Error => {
-                                        //         self.preferences.theme.error
-                                        //     }
-                                        //     types::DiagnosticSeverity::Warning => {
-                                        //         self.preferences.theme.warning
-                                        //     }
-                                        //     types::DiagnosticSeverity::Information => {
-                                        //         self.preferences.theme.information
-                                        //     }
-                                        //     types::DiagnosticSeverity::Hint => {
-                                        //         self.preferences.theme.hint
-                                        //     }
-                                        // }));
-                                    }
-                                }
+                            let style =
+                                style_from_attributes(&self.state.preferences.theme, &token.1);
+                            line_widget
+                                .push(text::Span::styled(display_text(&token.0, &token.1), style));
+                        }
+                        if let Some(Some((severity, message))) =
+                            self.state.inline_diagnostics.get(idx)
+                        {
+                            let line_length: usize =
+                                line.iter().map(|token| graphemes::width(&token.0)).sum();
+                            let width =
+                                (h_layout[1].width as usize).saturating_sub(line_length + 4);
+                            if let Some(message) = diagnostics::inline_message(message, width) {
+                                let color = self.state.preferences.theme.diagnostic_color(severity);
+                                line_widget.push(text::Span::styled(
+                                    format!("    {}", message),
+                                    Style::new()
+                                        .fg(color_from_rgb(color))
+                                        .add_modifier(Modifier::ITALIC),
+                                ));
+                            }
+                        } else if let Some(Some(blame)) = self.state.inline_annotation(idx) {
+                            let line_length: usize =
+                                line.iter().map(|token| graphemes::width(&token.0)).sum();
+                            let width =
+                                (h_layout[1].width as usize).saturating_sub(line_length + 4);
+                            if let Some(blame) = diagnostics::inline_message(blame, width) {
+                                line_widget.push(text::Span::styled(
+                                    format!("    {}", blame),
+                                    Style::new()
+                                        .fg(color_from_rgb(
+                                            self.state.preferences.theme.gutter_text,
+                                        ))
+                                        .add_modifier(Modifier::ITALIC),
+                                ));
                             }
-                            line_widget.push(text::Span::styled(&token.0, style));
                         }
                         lines.push(text::Line::from(line_widget));
                     }
@@ -495,36 +647,129 @@ impl App {
                     // Render gutter
                     let mut gutter_lines = vec![];
                     for (idx, gutter_line) in self.state.gutter_info.iter().enumerate() {
-                        let gutter_value = if gutter_line.wrapped {
-                            ".  ".to_string()
+                        let (sign, marker, git_marker) = if gutter_line.wrapped {
+                            (None, None, None)
                         } else {
-                            format!("{}  ", gutter_line.start.row + 1)
+                            (
+                                self.state.sign(gutter_line.start.row),
+                                self.state.gutter_marker(gutter_line.start.row),
+                                self.state.git_marker(gutter_line.start.row),
+                            )
                         };
-                        if idx == self.state.relative_cursor.row {
-                            gutter_lines.push(
-                                text::Line::styled(
-                                    gutter_value,
-                                    Style::new().fg(color_from_rgb(
-                                        self.state.preferences.theme.gutter_text_current_line,
-                                    )),
-                                )
-                                .alignment(ratatui::layout::Alignment::Right),
-                            );
+                        let (sign, sign_color) = match sign {
+                            Some(sign) => (
+                                sign.text,
+                                self.state.preferences.theme.highlight_color(sign.highlight),
+                            ),
+                            None => (' ', self.state.preferences.theme.gutter_text),
+                        };
+                        let (git_marker, git_color) = match git_marker {
+                            Some((marker, kind)) => {
+                                (marker, self.state.preferences.theme.git_color(kind))
+                            }
+                            None => (' ', self.state.preferences.theme.gutter_text),
+                        };
+                        let gutter_color = if idx == self.state.relative_cursor.row {
+                            self.state.preferences.theme.gutter_text_current_line
                         } else {
-                            gutter_lines.push(
+                            self.state.preferences.theme.gutter_text
+                        };
+                        gutter_lines.push(text::Line::from(vec![
+                            text::Span::styled(
+                                format!("{} ", sign),
+                                Style::new().fg(color_from_rgb(sign_color)),
+                            ),
+                            text::Span::styled(
+                                gutter_line.label(number_width),
+                                Style::new().fg(color_from_rgb(gutter_color)),
+                            ),
+                            text::Span::styled(
+                                format!(" {}", marker.unwrap_or(' ')),
+                                Style::new().fg(color_from_rgb(self.state.preferences.theme.error)),
+                            ),
+                            text::Span::styled(
+                                git_marker.to_string(),
+                                Style::new().fg(color_from_rgb(git_color)),
+                            ),
+                        ]));
+                    }
+                    frame.render_widget(text::Text::from(gutter_lines), h_layout[0]);
+
+                    // Render the other panes of a split editor
+                    for (id, area) in &panes {
+                        let focused = *id == self.state.layout.focused;
+                        let border_color = if focused {
+                            self.state.preferences.theme.cursor_normal_mode_bg
+                        } else {
+                            self.state.preferences.theme.ui_border
+                        };
+                        let block = widgets::Block::bordered()
+                            .border_style(Style::new().fg(color_from_rgb(border_color)));
+                        let inner = block.inner(*area);
+                        frame.render_widget(block, *area);
+                        if focused {
+                            continue;
+                        }
+
+                        let pane_number_width =
+                            self.state.line_number_width(self.state.pane_buffer_id(*id));
+                        let pane_gutter_width = if self.state.preferences.show_gutter {
+                            pane_number_width as u16 + 3
+                        } else {
+                            0
+                        };
+                        let pane_layout = Layout::default()
+                            .direction(Direction::Horizontal)
+                            .constraints([
+                                Constraint::Length(pane_gutter_width),
+                                Constraint::Fill(1),
+                            ])
+                            .split(inner);
+                        let Some((pane_text, pane_gutter)) = self.state.get_pane_visible_lines(
+                            *id,
+                            pane_layout[1].height as usize,
+                            pane_layout[1].width as usize,
+                            "\n".into(),
+                        ) else {
+                            continue;
+                        };
+                        let lines: Vec<text::Line> = pane_text
+                            .iter()
+                            .map(|line| {
+                                text::Line::from(
+                                    line.iter()
+                                        .map(|token| {
+                                            text::Span::styled(
+                                                display_text(&token.0, &token.1),
+                                                style_from_attributes(
+                                                    &self.state.preferences.theme,
+                                                    &token.1,
+                                                ),
+                                            )
+                                        })
+                                        .collect::<Vec<_>>(),
+                                )
+                            })
+                            .collect();
+                        frame.render_widget(text::Text::from(lines), pane_layout[1]);
+                        let gutter_lines: Vec<text::Line> = pane_gutter
+                            .iter()
+                            .map(|gutter_line| {
                                 text::Line::styled(
-                                    gutter_value,
+                                    format!("{}   ", gutter_line.label(pane_number_width)),
                                     Style::new().fg(color_from_rgb(
                                         self.state.preferences.theme.gutter_text,
                                     )),
                                 )
-                                .alignment(ratatui::layout::Alignment::Right),
-                            );
-                        }
+                            })
+                            .collect();
+                        frame.render_widget(text::Text::from(gutter_lines), pane_layout[0]);
                     }
-                    frame.render_widget(text::Text::from(gutter_lines), h_layout[0]);
 
                     // Render status line
+                    let text_stats = self
+                        .state
+                        .text_stats_segment(self.state.buffer_idx.unwrap());
                     let status_mode_style = Style::default()
                         .fg(color_from_rgb(self.state.preferences.theme.status_bar_bg))
                         .bg(color_from_rgb(if matches!(self.state.mode, Mode::Normal) {
@@ -532,16 +777,19 @@ impl App {
                         } else {
                             self.state.preferences.theme.status_bar_insert_mode_fg
                         }));
+                    let mode_name = match &self.state.mode {
+                        Mode::Custom(name) => self.state.preferences.mode_indicator(name),
+                        mode => format!("{:#?}", mode),
+                    };
                     let status = text::Line::from(vec![
-                        text::Span::styled(format!(" {:#?} ", self.state.mode), status_mode_style),
+                        text::Span::styled(format!(" {} ", mode_name), status_mode_style),
+                        format!(" {} ", self.state.pending_keys.pending()).into(),
                         format!(
                             " {}({:?}) ",
                             self.state
                                 .get_buffer_by_id(self.state.buffer_idx.unwrap())
                                 .0
-                                .file_path
-                                .as_ref()
-                                .unwrap(),
+                                .display_name(),
                             self.state.buffer_idx
                         )
                         .into(),
@@ -561,8 +809,226 @@ impl App {
                                 + 1,
                         )
                         .into(),
+                        format!(" {} ", text_stats).into(),
+                        format!(
+                            " {} ",
+                            self.state
+                                .get_buffer_by_id(self.state.buffer_idx.unwrap())
+                                .0
+                                .encoding
+                                .label()
+                        )
+                        .into(),
+                        format!(
+                            " {} ",
+                            self.state
+                                .get_buffer_by_id(self.state.buffer_idx.unwrap())
+                                .0
+                                .line_ending_or(&self.state.preferences.line_ending)
+                                .label()
+                        )
+                        .into(),
+                    ]);
+                    frame.render_widget(status, v_layout[3]);
+                }
+
+                // Render file tree
+                if let Some(tree_area) = tree_area {
+                    self.state
+                        .file_tree
+                        .set_root(self.state.workspace_folder.clone());
+                    let rows = self.state.file_tree.rows();
+                    let border_color = if self.state.file_tree_focused {
+                        self.state.preferences.theme.cursor_normal_mode_bg
+                    } else {
+                        self.state.preferences.theme.ui_border
+                    };
+                    let tree_block = widgets::Block::bordered()
+                        .border_style(Style::new().fg(color_from_rgb(border_color)))
+                        .title(
+                            std::path::Path::new(&self.state.workspace_folder)
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or(self.state.workspace_folder.clone()),
+                        );
+                    let tree_list = rows
+                        .iter()
+                        .map(|row| {
+                            let marker = match (row.entry.is_dir, row.expanded) {
+                                (true, true) => "▾ ",
+                                (true, false) => "▸ ",
+                                _ => "  ",
+                            };
+                            format!("{}{}{}", "  ".repeat(row.depth), marker, row.entry.name)
+                        })
+                        .collect::<widgets::List>()
+                        .block(tree_block)
+                        .highlight_style(
+                            Style::new()
+                                .bg(color_from_rgb(self.state.preferences.theme.selection_bg)),
+                        );
+                    let selected = self
+                        .state
+                        .file_tree_focused
+                        .then_some(self.state.file_tree.idx);
+                    let mut list_state = widgets::ListState::default().with_selected(selected);
+                    frame.render_stateful_widget(tree_list, tree_area, &mut list_state);
+                }
+
+                // Render latest notification in the message area
+                if let Some(notification) = self.state.notifications.latest() {
+                    let theme = &self.state.preferences.theme;
+                    let color = match notification.level {
+                        NotificationLevel::Info => theme.information,
+                        NotificationLevel::Warning => theme.warning,
+                        NotificationLevel::Error => theme.error,
+                    };
+                    let message = text::Line::from(vec![
+                        text::Span::styled(
+                            format!(" {} ", notification.level),
+                            Style::default().fg(color_from_rgb(color)),
+                        ),
+                        notification
+                            .message
+                            .lines()
+                            .next()
+                            .unwrap_or_default()
+                            .into(),
                     ]);
-                    frame.render_widget(status, v_layout[1]);
+                    frame.render_widget(message, v_layout[2]);
+                }
+
+                // Render terminal
+                if let Some(terminal) = &mut self.state.terminal {
+                    let title = terminal.title();
+                    let terminal_block = widgets::Block::default()
+                        .borders(widgets::Borders::TOP)
+                        .title(if title.is_empty() {
+                            " Terminal ".to_string()
+                        } else {
+                            format!(" {} ", title)
+                        });
+                    let terminal_area = terminal_block.inner(v_layout[1]);
+                    if let Err(err) = terminal.resize(terminal_area.height, terminal_area.width) {
+                        tracing::error!("Failed to resize terminal: {}", err);
+                    }
+                    terminal.take_updated();
+                    let (terminal_lines, terminal_cursor) = terminal.get_visible_lines();
+
+                    let mut lines = vec![];
+                    for line in &terminal_lines {
+                        let mut line_widget = vec![];
+                        for (token, attributes) in line {
+                            line_widget.push(text::Span::styled(
+                                token,
+                                style_from_attributes(&self.state.preferences.theme, attributes),
+                            ));
+                        }
+                        lines.push(text::Line::from(line_widget));
+                    }
+                    frame.render_widget(terminal_block, v_layout[1]);
+                    frame.render_widget(text::Text::from(lines), terminal_area);
+
+                    if let Some(cursor) = terminal_cursor {
+                        if matches!(self.state.mode, Mode::Terminal) {
+                            let buf = frame.buffer_mut();
+                            if let Some(cell) = buf.cell_mut((
+                                cursor.column as u16 + terminal_area.x,
+                                cursor.row as u16 + terminal_area.y,
+                            )) {
+                                cell.set_fg(color_from_rgb(
+                                    self.state.preferences.theme.cursor_insert_mode_fg,
+                                ));
+                                cell.set_bg(color_from_rgb(
+                                    self.state.preferences.theme.cursor_insert_mode_bg,
+                                ));
+                            }
+                        }
+                    }
+                }
+
+                // Render diff
+                if let Some(diff) = &self.state.diff_view {
+                    let theme = &self.state.preferences.theme;
+                    let area = Rect {
+                        height: frame.area().height.saturating_sub(1),
+                        ..frame.area()
+                    };
+                    let diff_block = widgets::Block::default()
+                        .borders(widgets::Borders::TOP)
+                        .title(" [ ] hunk  > < push  s save  q close ");
+                    let inner = diff_block.inner(area);
+                    let height = inner.height.saturating_sub(1) as usize;
+
+                    // Scroll to the selected hunk when it changes
+                    let selected_hunk = diff.selected_hunk();
+                    if let Some(hunk) = &selected_hunk {
+                        if self.diff_scrolled_hunk != Some(diff.hunk_idx)
+                            && (hunk.start < self.diff_scroll
+                                || hunk.start >= self.diff_scroll + height)
+                        {
+                            self.diff_scroll = hunk.start.saturating_sub(height / 3);
+                        }
+                    }
+                    self.diff_scrolled_hunk = Some(diff.hunk_idx);
+                    self.diff_scroll = self.diff_scroll.min(diff.rows.len().saturating_sub(height));
+
+                    let mut sides = [vec![], vec![]];
+                    for (side, (title, modified)) in [
+                        (&diff.left_title, diff.left_modified),
+                        (&diff.right_title, diff.right_modified),
+                    ]
+                    .into_iter()
+                    .enumerate()
+                    {
+                        sides[side].push(text::Line::styled(
+                            format!("{}{}", title, if modified { " *" } else { "" }),
+                            Style::new().add_modifier(Modifier::BOLD),
+                        ));
+                    }
+                    for (idx, row) in diff
+                        .rows
+                        .iter()
+                        .enumerate()
+                        .skip(self.diff_scroll)
+                        .take(height)
+                    {
+                        let selected = selected_hunk
+                            .as_ref()
+                            .is_some_and(|hunk| hunk.contains(&idx));
+                        for (side, line) in [&row.left, &row.right].into_iter().enumerate() {
+                            let mut style = match line.as_ref().map(|line| line.change) {
+                                Some(LineChange::Unchanged) => Style::new(),
+                                Some(LineChange::Removed) => {
+                                    Style::new().fg(color_from_rgb(theme.error))
+                                }
+                                Some(LineChange::Added) => {
+                                    Style::new().fg(color_from_rgb(theme.highlight_green))
+                                }
+                                Some(LineChange::Modified) => {
+                                    Style::new().fg(color_from_rgb(theme.highlight_yellow))
+                                }
+                                None => Style::new().bg(color_from_rgb(theme.gutter_bg)),
+                            };
+                            if selected {
+                                style = style.bg(color_from_rgb(theme.selection_bg));
+                            }
+                            let content = line
+                                .as_ref()
+                                .map(|line| format!("{:>5} {}", line.row + 1, line.text))
+                                .unwrap_or_default();
+                            sides[side].push(text::Line::styled(content, style));
+                        }
+                    }
+
+                    let columns = Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)])
+                        .spacing(1)
+                        .split(inner);
+                    frame.render_widget(widgets::Clear, area);
+                    frame.render_widget(diff_block, area);
+                    for (side, lines) in sides.into_iter().enumerate() {
+                        frame.render_widget(text::Text::from(lines), columns[side]);
+                    }
                 }
 
                 // Render Modal
@@ -621,6 +1087,229 @@ impl App {
                     );
                 }
 
+                // Render unsaved changes confirmation
+                if let Some(dialog) = &self.state.confirm_dialog {
+                    let save = match dialog.kind {
+                        ConfirmKind::CloseBuffer(_) => "[s]ave",
+                        ConfirmKind::Quit => "[s]ave all",
+                        ConfirmKind::Recover => "re[s]tore",
+                    };
+                    let content = format!("{}\n\n{}  [d]iscard  [c]ancel", dialog.message, save);
+                    let height = (content.lines().count() as u16 + 2).min(frame.area().height);
+                    let width = (frame.area().width / 2).max(40).min(frame.area().width);
+                    let popup_area = Rect {
+                        x: (frame.area().width - width) / 2,
+                        y: (frame.area().height - height) / 2,
+                        width,
+                        height,
+                    };
+                    let dialog_block = widgets::Block::default().borders(widgets::Borders::ALL);
+                    let content = widgets::Paragraph::new(content)
+                        .block(dialog_block)
+                        .wrap(widgets::Wrap { trim: false });
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_widget(content, popup_area);
+                }
+
+                // Render replace confirmation below the selected match
+                if let Some(session) = &self.state.replace_session {
+                    let content = format!(
+                        "Replace with \"{}\"?  [y]es  [n]o  [a]ll  [q]uit",
+                        session
+                            .current
+                            .as_ref()
+                            .map(|found| found.replacement.as_str())
+                            .unwrap_or_default()
+                    );
+                    let width = (content.chars().count() as u16 + 2).min(frame.area().width);
+                    let y = (h_layout[1].y + self.state.relative_cursor.row as u16 + 1)
+                        .min(frame.area().height.saturating_sub(3));
+                    let popup_area = Rect {
+                        x: h_layout[1].x.min(frame.area().width - width),
+                        y,
+                        width,
+                        height: 3.min(frame.area().height),
+                    };
+                    let prompt_block = widgets::Block::default().borders(widgets::Borders::ALL);
+                    let content = widgets::Paragraph::new(content).block(prompt_block);
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_widget(content, popup_area);
+                }
+
+                // Render signature help above the cursor, or below it on the first rows
+                if let Some((label, parameter)) = self
+                    .state
+                    .signature_help
+                    .as_ref()
+                    .and_then(|help| help.active())
+                {
+                    let (start, end) = parameter.unwrap_or((label.len(), label.len()));
+                    let width = (label.chars().count() as u16 + 2).min(frame.area().width);
+                    let row = h_layout[1].y + self.state.relative_cursor.row as u16;
+                    let y = if row >= 3 { row - 3 } else { row + 1 };
+                    let popup_area = Rect {
+                        x: (h_layout[1].x + self.state.relative_cursor.column as u16)
+                            .min(frame.area().width - width),
+                        y: y.min(frame.area().height.saturating_sub(3)),
+                        width,
+                        height: 3.min(frame.area().height),
+                    };
+                    let content = text::Line::from(vec![
+                        text::Span::raw(&label[..start]),
+                        text::Span::styled(
+                            &label[start..end],
+                            Style::new()
+                                .fg(color_from_rgb(self.state.preferences.theme.modal_active))
+                                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                        ),
+                        text::Span::raw(&label[end..]),
+                    ]);
+                    let signature_block = widgets::Block::default().borders(widgets::Borders::ALL);
+                    let content = widgets::Paragraph::new(content).block(signature_block);
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_widget(content, popup_area);
+                }
+
+                // Render spelling suggestions
+                if let Some(menu) = &self.state.spell_menu {
+                    let items = menu
+                        .suggestions
+                        .iter()
+                        .cloned()
+                        .chain([format!("Add \"{}\" to dictionary", menu.word)])
+                        .collect::<Vec<_>>();
+                    let height = (items.len() as u16 + 2).min(frame.area().height);
+                    let popup_area = Rect {
+                        x: 4,
+                        y: 2,
+                        width: frame.area().width - 8,
+                        height,
+                    };
+                    let spell_block = widgets::Block::default()
+                        .borders(widgets::Borders::ALL)
+                        .title(menu.word.as_str());
+                    let spell_list = items
+                        .into_iter()
+                        .collect::<widgets::List>()
+                        .block(spell_block)
+                        .highlight_symbol(">>");
+                    let mut list_state =
+                        widgets::ListState::default().with_selected(Some(menu.idx));
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_stateful_widget(spell_list, popup_area, &mut list_state);
+                }
+
+                // Render code actions
+                if let Some(menu) = &self.state.code_action_menu {
+                    let height = (menu.actions.len() as u16 + 2).min(frame.area().height);
+                    let popup_area = Rect {
+                        x: 4,
+                        y: 2,
+                        width: frame.area().width - 8,
+                        height,
+                    };
+                    let code_action_block = widgets::Block::default()
+                        .borders(widgets::Borders::ALL)
+                        .title("Code Actions");
+                    let code_action_list = menu
+                        .actions
+                        .iter()
+                        .map(|action| action.title.as_str())
+                        .collect::<widgets::List>()
+                        .block(code_action_block)
+                        .highlight_symbol(">>");
+                    let mut list_state =
+                        widgets::ListState::default().with_selected(Some(menu.idx));
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_stateful_widget(code_action_list, popup_area, &mut list_state);
+                }
+
+                // Render call hierarchy
+                if let Some(tree) = &self.state.call_hierarchy {
+                    let visible = tree.visible();
+                    let height = (visible.len() as u16 + 2).min(frame.area().height);
+                    let popup_area = Rect {
+                        x: 4,
+                        y: 2,
+                        width: frame.area().width - 8,
+                        height,
+                    };
+                    let call_block = widgets::Block::default()
+                        .borders(widgets::Borders::ALL)
+                        .title(tree.direction.title())
+                        .title_bottom("tab: toggle direction");
+                    let call_list = visible
+                        .iter()
+                        .map(|idx| tree.label(*idx))
+                        .collect::<widgets::List>()
+                        .block(call_block)
+                        .highlight_symbol(">>");
+                    let mut list_state =
+                        widgets::ListState::default().with_selected(Some(tree.idx));
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_stateful_widget(call_list, popup_area, &mut list_state);
+                }
+
+                if let Some(status) = &self.state.git_status {
+                    let height = (status.entries.len() as u16 + 2).min(frame.area().height);
+                    let popup_area = Rect {
+                        x: 4,
+                        y: 2,
+                        width: frame.area().width - 8,
+                        height,
+                    };
+                    let status_block = widgets::Block::default()
+                        .borders(widgets::Borders::ALL)
+                        .title("Git Status")
+                        .title_bottom("s: stage/unstage  c: commit  enter: open");
+                    let status_list = (0..status.entries.len())
+                        .map(|idx| status.label(idx))
+                        .collect::<widgets::List>()
+                        .block(status_block)
+                        .highlight_symbol(">>");
+                    let mut list_state =
+                        widgets::ListState::default().with_selected(Some(status.idx));
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_stateful_widget(status_list, popup_area, &mut list_state);
+                }
+
+                // Render save as prompt
+                if let Some(input) = &self.state.save_as_input {
+                    let popup_area = Rect {
+                        x: 4,
+                        y: 2,
+                        width: frame.area().width - 8,
+                        height: 3,
+                    };
+                    let prompt_block = widgets::Block::default()
+                        .borders(widgets::Borders::ALL)
+                        .title("Save As");
+                    let content = widgets::Paragraph::new(input.as_str()).block(prompt_block);
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_widget(content, popup_area);
+                }
+
+                // Render input dialog, secret input is masked
+                if let Some(dialog) = &self.state.input_dialog {
+                    let popup_area = Rect {
+                        x: 4,
+                        y: 2,
+                        width: frame.area().width - 8,
+                        height: 3,
+                    };
+                    let dialog_block = widgets::Block::default()
+                        .borders(widgets::Borders::ALL)
+                        .title(dialog.title.as_str());
+                    let input = if dialog.secret {
+                        "*".repeat(dialog.input.chars().count())
+                    } else {
+                        dialog.input.clone()
+                    };
+                    let content = widgets::Paragraph::new(input).block(dialog_block);
+                    frame.render_widget(widgets::Clear, popup_area);
+                    frame.render_widget(content, popup_area);
+                }
+
                 // Render Info Modal
                 if self.info_modal_active {
                     let popup_area = Rect {
@@ -639,12 +1328,182 @@ impl App {
                 }
             })?;
 
+            if self.diff_mode && self.state.diff_view.is_none() {
+                self.state.quit = true;
+            }
+            if self.state.quit {
+                return Ok(());
+            }
+
             // Handle keyboard events
             if event::poll(Duration::from_millis(5))? {
-                if let event::Event::Key(key) = event::read()? {
+                let event = event::read()?;
+                if matches!(event, event::Event::FocusGained) {
+                    self.state
+                        .send_event(EventKind::FocusGained, self.state.buffer_idx);
+                }
+                if let event::Event::Key(key) = event {
                     self.state.update_view = true;
                     if key.kind == KeyEventKind::Press {
-                        if self.info_modal_active {
+                        // Messages are cleared by the next key press
+                        self.state.notifications.dismiss_all();
+                        if let Some(input) = &mut self.state.save_as_input {
+                            match key.code {
+                                KeyCode::Char(char) => input.push(char),
+                                KeyCode::Backspace => {
+                                    input.pop();
+                                }
+                                KeyCode::Enter => {
+                                    let path = input.clone();
+                                    self.perform_action(Action::SaveBufferAs(path));
+                                }
+                                KeyCode::Esc => self.perform_action(Action::CloseSaveAsPrompt),
+                                _ => {}
+                            }
+                        } else if let Some(dialog) = &mut self.state.input_dialog {
+                            match key.code {
+                                KeyCode::Char(char) => dialog.input.push(char),
+                                KeyCode::Backspace => {
+                                    dialog.input.pop();
+                                }
+                                KeyCode::Enter => self.perform_action(Action::SubmitInputDialog),
+                                KeyCode::Esc => self.perform_action(Action::CloseInputDialog),
+                                _ => {}
+                            }
+                        } else if self.state.file_tree_focused {
+                            let action = match key.code {
+                                KeyCode::Esc => Some(Action::FocusFileTree),
+                                KeyCode::Down | KeyCode::Char('j') => Some(Action::FileTreeNext),
+                                KeyCode::Up | KeyCode::Char('k') => Some(Action::FileTreePrevious),
+                                KeyCode::Left | KeyCode::Char('h') => {
+                                    Some(Action::FileTreeCollapse)
+                                }
+                                KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                                    Some(Action::FileTreeOpen)
+                                }
+                                KeyCode::Char('a') => Some(Action::FileTreeNewFile),
+                                KeyCode::Char('A') => Some(Action::FileTreeNewFolder),
+                                KeyCode::Char('r') => Some(Action::FileTreeRename),
+                                KeyCode::Char('d') => Some(Action::FileTreeDelete),
+                                _ => None,
+                            };
+                            if let Some(action) = action {
+                                self.perform_action(action);
+                            }
+                        } else if let Some(menu) = &mut self.state.spell_menu {
+                            let num_items = menu.suggestions.len() + 1;
+                            match key.code {
+                                KeyCode::Esc => self.perform_action(Action::CloseSpellMenu),
+                                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                                    menu.idx = (menu.idx + 1) % num_items;
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => {
+                                    menu.idx = (menu.idx + num_items - 1) % num_items;
+                                }
+                                KeyCode::Enter => {
+                                    let action = match menu.suggestions.get(menu.idx) {
+                                        Some(suggestion) => {
+                                            Action::ApplySpellSuggestion(suggestion.clone())
+                                        }
+                                        None => Action::AddWordToDictionary,
+                                    };
+                                    self.perform_action(action);
+                                }
+                                _ => {}
+                            }
+                        } else if self.state.call_hierarchy.is_some() {
+                            let action = match key.code {
+                                KeyCode::Esc => Some(Action::CloseCallHierarchy),
+                                KeyCode::Down | KeyCode::Char('j') => {
+                                    Some(Action::CallHierarchyNext)
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => {
+                                    Some(Action::CallHierarchyPrevious)
+                                }
+                                KeyCode::Right | KeyCode::Char('l') => {
+                                    Some(Action::CallHierarchyExpand)
+                                }
+                                KeyCode::Left | KeyCode::Char('h') => {
+                                    Some(Action::CallHierarchyCollapse)
+                                }
+                                KeyCode::Tab => Some(Action::CallHierarchyToggleDirection),
+                                KeyCode::Enter => Some(Action::CallHierarchyOpen),
+                                _ => None,
+                            };
+                            if let Some(action) = action {
+                                self.perform_action(action);
+                            }
+                        } else if self.state.git_status.is_some() {
+                            let action = match key.code {
+                                KeyCode::Esc => Some(Action::CloseGitStatus),
+                                KeyCode::Down | KeyCode::Char('j') => Some(Action::GitStatusNext),
+                                KeyCode::Up | KeyCode::Char('k') => Some(Action::GitStatusPrevious),
+                                KeyCode::Char('s') | KeyCode::Char(' ') => {
+                                    Some(Action::GitStatusToggleStage)
+                                }
+                                KeyCode::Char('c') => Some(Action::GitCommit),
+                                KeyCode::Enter => Some(Action::GitStatusOpen),
+                                _ => None,
+                            };
+                            if let Some(action) = action {
+                                self.perform_action(action);
+                            }
+                        } else if let Some(menu) = &mut self.state.code_action_menu {
+                            let num_items = menu.actions.len();
+                            match key.code {
+                                KeyCode::Esc => self.perform_action(Action::CloseCodeActionMenu),
+                                KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => {
+                                    menu.idx = (menu.idx + 1) % num_items;
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => {
+                                    menu.idx = (menu.idx + num_items - 1) % num_items;
+                                }
+                                KeyCode::Enter => {
+                                    let idx = menu.idx;
+                                    self.perform_action(Action::ApplyCodeAction(idx));
+                                }
+                                _ => {}
+                            }
+                        } else if self.state.replace_session.is_some() {
+                            let choice = match key.code {
+                                KeyCode::Char('y') => Some(ReplaceChoice::Replace),
+                                KeyCode::Char('n') => Some(ReplaceChoice::Skip),
+                                KeyCode::Char('a') => Some(ReplaceChoice::All),
+                                KeyCode::Char('q') | KeyCode::Esc => Some(ReplaceChoice::Quit),
+                                _ => None,
+                            };
+                            if let Some(choice) = choice {
+                                self.perform_action(Action::ReplaceStep(choice));
+                            }
+                        } else if self.state.confirm_dialog.is_some() {
+                            let choice = match key.code {
+                                KeyCode::Char('s') => Some(ConfirmChoice::Save),
+                                KeyCode::Char('d') => Some(ConfirmChoice::Discard),
+                                KeyCode::Char('c') | KeyCode::Esc => Some(ConfirmChoice::Cancel),
+                                _ => None,
+                            };
+                            if let Some(choice) = choice {
+                                self.perform_action(Action::Confirm(choice));
+                            }
+                        } else if self.state.diff_view.is_some() {
+                            match key.code {
+                                KeyCode::Esc | KeyCode::Char('q') => {
+                                    self.perform_action(Action::CloseDiff)
+                                }
+                                KeyCode::Char(']') => self.perform_action(Action::DiffNextHunk),
+                                KeyCode::Char('[') => self.perform_action(Action::DiffPrevHunk),
+                                KeyCode::Char('>') => self.perform_action(Action::DiffPushToRight),
+                                KeyCode::Char('<') => self.perform_action(Action::DiffPushToLeft),
+                                KeyCode::Char('s') => self.perform_action(Action::DiffSave),
+                                KeyCode::Down | KeyCode::Char('j') => {
+                                    self.diff_scroll = self.diff_scroll.saturating_add(1);
+                                }
+                                KeyCode::Up | KeyCode::Char('k') => {
+                                    self.diff_scroll = self.diff_scroll.saturating_sub(1);
+                                }
+                                _ => {}
+                            }
+                        } else if self.info_modal_active {
                             if key.code == KeyCode::Esc {
                                 self.info_modal_active = false;
                             } else if key.code == KeyCode::Up {
@@ -684,14 +1543,14 @@ impl App {
                                         &mut self.state,
                                         lsp_handle,
                                     );
-                                    perform_action(
-                                        Action::InsertText(
-                                            completion_item.edit.text.clone(),
-                                            completion_item.edit.range.mark,
-                                        ),
-                                        &mut self.state,
-                                        lsp_handle,
-                                    );
+                                    let text = completion_item.edit.text.clone();
+                                    let position = completion_item.edit.range.mark;
+                                    let action = if completion_item.snippet {
+                                        Action::InsertSnippet(text, position)
+                                    } else {
+                                        Action::InsertText(text, position)
+                                    };
+                                    perform_action(action, &mut self.state, lsp_handle);
                                 }
                                 self.completion_menu_active = false;
                                 self.completion_menu_items = vec![];
@@ -749,41 +1608,44 @@ impl App {
                                         [self.state.modal_selection_idx.unwrap()];
                                     if !entry.is_dir {
                                         let path = entry.path.clone();
-                                        let initial_text =
-                                            file_io::read_file_content(&path).unwrap();
-                                        let buffer = LineBuffer::new(
-                                            initial_text.clone(),
-                                            Some(path.clone()),
-                                        );
+                                        if !self.state.request_decryption(&path) {
+                                            let (initial_text, encoding) =
+                                                file_io::read_file_with_encoding(&path).unwrap();
+                                            let mut buffer = LineBuffer::new(
+                                                initial_text.clone(),
+                                                Some(path.clone()),
+                                            );
+                                            buffer.encoding = encoding;
 
-                                        if let std::collections::hash_map::Entry::Vacant(e) =
-                                            self.lsp_handles.entry(buffer.language)
-                                        {
-                                            if let Some(mut lsp_handle) =
-                                                self.state.spawn_lsp(buffer.language)
+                                            if let std::collections::hash_map::Entry::Vacant(e) =
+                                                self.lsp_handles.entry(buffer.language)
                                             {
-                                                lsp_handle.init_lsp_sync(
-                                                    self.state.workspace_folder.clone(),
-                                                );
-                                                e.insert(lsp_handle);
+                                                if let Some(lsp_handle) =
+                                                    self.state.spawn_lsp(buffer.language, &path)
+                                                {
+                                                    e.insert(lsp_handle);
+                                                }
                                             }
-                                        }
 
-                                        if let Some(lsp_handle) =
-                                            self.lsp_handles.get(&buffer.language)
-                                        {
-                                            lsp_handle
-                                                .send_notification_sync(
-                                                    "textDocument/didOpen".to_string(),
-                                                    Some(LSPClientHandle::did_open_text_document(
-                                                        path.clone(),
-                                                        initial_text,
-                                                    )),
-                                                )
-                                                .unwrap();
-                                        }
+                                            if let Some(lsp_handle) =
+                                                self.lsp_handles.get(&buffer.language)
+                                            {
+                                                lsp_handle
+                                                    .send_notification_sync(
+                                                        "textDocument/didOpen".to_string(),
+                                                        Some(
+                                                            LSPClientHandle::did_open_text_document(
+                                                                path.clone(),
+                                                                initial_text,
+                                                            ),
+                                                        ),
+                                                    )
+                                                    .unwrap();
+                                            }
 
-                                        self.state.buffer_idx = Some(self.state.add_buffer(buffer));
+                                            self.state.buffer_idx =
+                                                Some(self.state.add_buffer(buffer));
+                                        }
                                         self.state.modal_open = false;
                                         self.state.modal_options = vec![];
                                         self.state.modal_options_filtered = vec![];
@@ -791,10 +1653,11 @@ impl App {
                                         self.modal_list_state.select(None);
                                         self.state.modal_input = "".into();
                                     } else {
-                                        self.state.modal_input = entry.path.clone();
+                                        let path = entry.path.clone();
+                                        self.state.modal_input = path.clone();
 
                                         if key.modifiers.contains(KeyModifiers::ALT) {
-                                            self.state.workspace_folder = entry.path.clone();
+                                            self.state.open_workspace(path.clone());
                                         }
 
                                         #[cfg(target_os = "windows")]
@@ -808,7 +1671,7 @@ impl App {
                                         }
 
                                         self.state.modal_options =
-                                            file_io::get_directory_entries(&entry.path).unwrap();
+                                            file_io::get_directory_entries(&path).unwrap();
                                         self.state.modal_options_filtered =
                                             self.state.modal_options.clone();
                                         self.state.modal_selection_idx = None;
@@ -823,9 +1686,59 @@ impl App {
                                 self.modal_list_state.select(None);
                                 self.state.modal_input = "".into();
                             }
+                        } else if matches!(self.state.mode, Mode::Terminal) {
+                            if key.code == KeyCode::Esc {
+                                self.perform_action(Action::QuitTerminalMode);
+                            } else if key.code == KeyCode::PageUp
+                                && key.modifiers.contains(KeyModifiers::SHIFT)
+                            {
+                                self.perform_action(Action::TerminalScrollUp);
+                            } else if key.code == KeyCode::PageDown
+                                && key.modifiers.contains(KeyModifiers::SHIFT)
+                            {
+                                self.perform_action(Action::TerminalScrollDown);
+                            } else if let KeyCode::Char(c) = key.code {
+                                if key.modifiers.contains(KeyModifiers::CONTROL) {
+                                    self.perform_action(Action::TerminalKey(TerminalKey::Ctrl(c)));
+                                } else {
+                                    self.perform_action(Action::TerminalInput(c.into()));
+                                }
+                            } else {
+                                let terminal_key = match key.code {
+                                    KeyCode::Enter => Some(TerminalKey::Enter),
+                                    KeyCode::Backspace => Some(TerminalKey::Backspace),
+                                    KeyCode::Tab => Some(TerminalKey::Tab),
+                                    KeyCode::Up => Some(TerminalKey::Up),
+                                    KeyCode::Down => Some(TerminalKey::Down),
+                                    KeyCode::Left => Some(TerminalKey::Left),
+                                    KeyCode::Right => Some(TerminalKey::Right),
+                                    KeyCode::Home => Some(TerminalKey::Home),
+                                    KeyCode::End => Some(TerminalKey::End),
+                                    KeyCode::Delete => Some(TerminalKey::Delete),
+                                    KeyCode::PageUp => Some(TerminalKey::PageUp),
+                                    KeyCode::PageDown => Some(TerminalKey::PageDown),
+                                    _ => None,
+                                };
+                                if let Some(terminal_key) = terminal_key {
+                                    self.perform_action(Action::TerminalKey(terminal_key));
+                                }
+                            }
+                        } else if matches!(self.state.mode, Mode::Custom(_)) {
+                            // Keys without an action do nothing in custom modes
+                            self.push_sequence_key(&key);
                         } else if matches!(self.state.mode, Mode::Normal) {
-                            if key.code == KeyCode::Char('q') {
-                                return Ok(());
+                            if self.push_sequence_key(&key) {
+                                // Part of a count or key sequence
+                            } else if key.code == KeyCode::Esc {
+                                self.perform_action(Action::CloseSignatureHelp);
+                            } else if key.code == KeyCode::Char('q') {
+                                self.perform_action(Action::Quit);
+                            } else if key.code == KeyCode::Char('Q') {
+                                self.perform_action(Action::ForceQuit);
+                            } else if key.code == KeyCode::Char('i')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::ShrinkSelection);
                             } else if key.code == KeyCode::Char('i') {
                                 self.perform_action(Action::EnterInsertMode);
                             } else if key.code == KeyCode::Char('f') {
@@ -849,25 +1762,45 @@ impl App {
                             } else if key.code == KeyCode::Char('L') {
                                 self.perform_action(Action::ExtendCursorRight);
                             } else if key.code == KeyCode::Down {
-                                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                                if key
+                                    .modifiers
+                                    .contains(KeyModifiers::SHIFT | KeyModifiers::ALT)
+                                {
+                                    self.perform_action(Action::ExtendBlockDown);
+                                } else if key.modifiers.contains(KeyModifiers::SHIFT) {
                                     self.perform_action(Action::ExtendCursorDown);
                                 } else {
                                     self.perform_action(Action::MoveCursorDown);
                                 }
                             } else if key.code == KeyCode::Up {
-                                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                                if key
+                                    .modifiers
+                                    .contains(KeyModifiers::SHIFT | KeyModifiers::ALT)
+                                {
+                                    self.perform_action(Action::ExtendBlockUp);
+                                } else if key.modifiers.contains(KeyModifiers::SHIFT) {
                                     self.perform_action(Action::ExtendCursorUp);
                                 } else {
                                     self.perform_action(Action::MoveCursorUp);
                                 }
                             } else if key.code == KeyCode::Left {
-                                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                                if key
+                                    .modifiers
+                                    .contains(KeyModifiers::SHIFT | KeyModifiers::ALT)
+                                {
+                                    self.perform_action(Action::ExtendBlockLeft);
+                                } else if key.modifiers.contains(KeyModifiers::SHIFT) {
                                     self.perform_action(Action::ExtendCursorLeft);
                                 } else {
                                     self.perform_action(Action::MoveCursorLeft);
                                 }
                             } else if key.code == KeyCode::Right {
-                                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                                if key
+                                    .modifiers
+                                    .contains(KeyModifiers::SHIFT | KeyModifiers::ALT)
+                                {
+                                    self.perform_action(Action::ExtendBlockRight);
+                                } else if key.modifiers.contains(KeyModifiers::SHIFT) {
                                     self.perform_action(Action::ExtendCursorRight);
                                 } else {
                                     self.perform_action(Action::MoveCursorRight);
@@ -884,6 +1817,10 @@ impl App {
                                 } else {
                                     self.perform_action(Action::MoveCursorLineEnd);
                                 }
+                            } else if key.code == KeyCode::Char('o')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::ExpandSelectionToParentNode);
                             } else if key.code == KeyCode::Char('o') {
                                 self.perform_action(Action::AddNewLineBelowAndEnterInsertMode);
                             } else if key.code == KeyCode::Char('d') {
@@ -896,16 +1833,40 @@ impl App {
                                 self.perform_action(Action::SelectTillEndOfWord);
                             } else if key.code == KeyCode::Char('W') {
                                 self.perform_action(Action::ExtendSelectTillEndOfWord);
+                            } else if key.code == KeyCode::Char('b')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::GitBlameLine);
+                            } else if key.code == KeyCode::Char('B')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::GitShowCommit);
                             } else if key.code == KeyCode::Char('b') {
                                 self.perform_action(Action::SelectTillStartOfWord);
                             } else if key.code == KeyCode::Char('B') {
                                 self.perform_action(Action::ExtendSelectTillStartOfWord);
+                            } else if key.code == KeyCode::Char('a')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::LSPCodeActions);
+                            } else if key.code == KeyCode::Char('c')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::CallHierarchy);
+                            } else if key.code == KeyCode::Char('e')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::FocusFileTree);
                             } else if key.code == KeyCode::Char('a') {
                                 self.perform_action(Action::InsertAfterSelection);
                             } else if key.code == KeyCode::Backspace {
                                 self.perform_action(Action::DeletePreviousCharacter);
                             } else if key.code == KeyCode::Delete {
                                 self.perform_action(Action::DeleteNextCharacter);
+                            } else if key.code == KeyCode::Char('g')
+                                && key.modifiers.contains(KeyModifiers::CONTROL)
+                            {
+                                self.perform_action(Action::GotoFileUnderCursor);
                             } else if key.code == KeyCode::Char('g') {
                                 self.perform_action(Action::GoToBufferStart);
                             } else if key.code == KeyCode::Char('G') {
@@ -914,6 +1875,10 @@ impl App {
                                 self.perform_action(Action::FormatCurrentBuffer);
                             } else if key.code == KeyCode::Char('S') {
                                 self.perform_action(Action::SaveCurrentBuffer);
+                            } else if key.code == KeyCode::Char('A') {
+                                self.perform_action(Action::OpenSaveAsPrompt);
+                            } else if key.code == KeyCode::Char('M') {
+                                self.perform_action(Action::SpellSuggestions);
                             } else if key.code == KeyCode::Char('u') {
                                 self.perform_action(Action::Undo);
                             } else if key.code == KeyCode::Char('U') {
@@ -922,10 +1887,28 @@ impl App {
                                 self.perform_action(Action::AddIndent);
                             } else if key.code == KeyCode::Char('<') {
                                 self.perform_action(Action::RemoveIndent);
-                            } else if key.code == KeyCode::Char(',') {
+                            } else if key.code == KeyCode::Char('v') {
+                                self.perform_action(Action::SplitVertical);
+                            } else if key.code == KeyCode::Char('V') {
+                                self.perform_action(Action::SplitHorizontal);
+                            } else if key.code == KeyCode::Char('n') {
+                                self.perform_action(Action::FocusNextPane);
+                            } else if key.code == KeyCode::Char('N') {
+                                self.perform_action(Action::ClosePane);
+                            } else if key.code == KeyCode::Char(',')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
                                 self.perform_action(Action::CyclePreviousBuffer);
-                            } else if key.code == KeyCode::Char('.') {
+                            } else if key.code == KeyCode::Char('.')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
                                 self.perform_action(Action::CycleNextBuffer);
+                            } else if key.code == KeyCode::Char('.') {
+                                self.perform_action(Action::RepeatLastChange);
+                            } else if key.code == KeyCode::Char('z')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::FoldToggle);
                             } else if key.code == KeyCode::Char('z') {
                                 self.perform_action(Action::LSPHover);
                             } else if key.code == KeyCode::Char('Z') {
@@ -934,10 +1917,46 @@ impl App {
                                 self.perform_action(Action::CopyToRegister);
                             } else if key.code == KeyCode::Char('Y') {
                                 self.perform_action(Action::CopyToClipboard);
+                            } else if key.code == KeyCode::Char('p')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::CyclePaste);
                             } else if key.code == KeyCode::Char('p') {
                                 self.perform_action(Action::PasteFromRegister);
                             } else if key.code == KeyCode::Char('P') {
                                 self.perform_action(Action::PasteFromClipboard);
+                            } else if key.code == KeyCode::Char(']')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::NextDiagnostic);
+                            } else if key.code == KeyCode::Char('[')
+                                && key.modifiers.contains(KeyModifiers::ALT)
+                            {
+                                self.perform_action(Action::PreviousDiagnostic);
+                            } else if key.code == KeyCode::Char(']') {
+                                self.perform_action(Action::NextError);
+                            } else if key.code == KeyCode::Char('[') {
+                                self.perform_action(Action::PrevError);
+                            } else if key.code == KeyCode::F(5) {
+                                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                                    self.perform_action(Action::StopDebugging);
+                                } else {
+                                    self.perform_action(Action::DebugContinue);
+                                }
+                            } else if key.code == KeyCode::F(9) {
+                                self.perform_action(Action::ToggleBreakpoint);
+                            } else if key.code == KeyCode::F(10) {
+                                self.perform_action(Action::DebugStepOver);
+                            } else if key.code == KeyCode::F(11) {
+                                if key.modifiers.contains(KeyModifiers::SHIFT) {
+                                    self.perform_action(Action::DebugStepOut);
+                                } else {
+                                    self.perform_action(Action::DebugStepInto);
+                                }
+                            } else if key.code == KeyCode::Char('t') {
+                                self.perform_action(Action::OpenTerminal);
+                            } else if key.code == KeyCode::Char('T') {
+                                self.perform_action(Action::CloseTerminal);
                             }
                         } else if matches!(self.state.mode, Mode::Insert) {
                             if key.code == KeyCode::Esc {
@@ -988,6 +2007,8 @@ impl App {
                                 self.perform_action(Action::DeleteNextCharacter);
                             } else if key.code == KeyCode::Tab {
                                 self.perform_action(Action::AddTab);
+                            } else if key.code == KeyCode::BackTab {
+                                self.perform_action(Action::PreviousTabStop);
                             } else {
                                 // println!("{:#?}", key.code);
                             }
@@ -997,50 +2018,4 @@ impl App {
             }
         }
     }
-
-    pub fn update_visible_lines(
-        &mut self,
-        visible_lines: usize,
-        max_characters: usize,
-    ) -> rift_core::buffer::instance::Cursor {
-        if self.state.buffer_idx.is_some() {
-            let (buffer, _instance) = self.state.get_buffer_by_id(self.state.buffer_idx.unwrap());
-            let mut extra_segments = vec![];
-            let mut path = buffer.file_path.as_ref().unwrap().clone();
-            #[cfg(target_os = "windows")]
-            {
-                path = path.to_lowercase();
-            }
-
-            if let Some(diagnostics) = self.state.diagnostics.get(&path) {
-                if diagnostics.version != 0 && diagnostics.version == buffer.version {
-                    for diagnostic in &diagnostics.diagnostics {
-                        extra_segments.push(Range {
-                            start: buffer.byte_index_from_cursor(&diagnostic.range.mark, "\n"),
-                            end: buffer.byte_index_from_cursor(&diagnostic.range.cursor, "\n"),
-                            attributes: HashSet::from([Attribute::DiagnosticSeverity(
-                                diagnostic.severity.clone(),
-                            )]),
-                        });
-                    }
-                }
-            }
-            let (buffer, instance) = self
-                .state
-                .get_buffer_by_id_mut(self.state.buffer_idx.unwrap());
-            let (lines, relative_cursor, gutter_info) = buffer.get_visible_lines(
-                &mut instance.scroll,
-                &instance.cursor,
-                &instance.selection,
-                visible_lines,
-                max_characters,
-                "\n".into(),
-                extra_segments,
-            );
-            self.state.highlighted_text = lines;
-            self.state.gutter_info = gutter_info;
-            return relative_cursor;
-        }
-        rift_core::buffer::instance::Cursor { row: 0, column: 0 }
-    }
 }
@@ -6,4 +6,10 @@ use clap::Parser;
 #[derive(Parser, Debug)]
 pub struct CLIArgs {
     pub path: Option<PathBuf>,
+    /// Compare two files side by side, for use as a git difftool
+    #[arg(long, num_args = 2, value_names = ["LEFT", "RIGHT"])]
+    pub diff: Option<Vec<PathBuf>>,
+    /// Reopen the files which were open when the editor last quit in the folder
+    #[arg(long)]
+    pub restore_session: bool,
 }
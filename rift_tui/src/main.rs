@@ -1,10 +1,15 @@
 use clap::Parser;
+use ratatui::crossterm::{
+    event::{DisableFocusChange, EnableFocusChange},
+    execute,
+};
+use rift_core::logs;
 
 pub mod app;
 pub mod cli;
 
 fn main() -> anyhow::Result<()> {
-    let file_appender = tracing_appender::rolling::never("logs", "rift.log");
+    let file_appender = tracing_appender::rolling::never(logs::LOG_DIR, logs::LOG_FILE_NAME);
     tracing_subscriber::fmt()
         .with_writer(file_appender)
         .with_ansi(false)
@@ -13,12 +18,14 @@ fn main() -> anyhow::Result<()> {
     let cli_args = cli::CLIArgs::parse();
     let mut terminal = ratatui::init();
     terminal.clear()?;
+    execute!(std::io::stdout(), EnableFocusChange)?;
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap();
     let mut app = app::App::new(rt, cli_args);
     app.run(terminal)?;
+    execute!(std::io::stdout(), DisableFocusChange)?;
     ratatui::restore();
     Ok(())
 }